@@ -14,6 +14,7 @@ import 'package:appflowy_backend/protobuf/flowy-database2/protobuf.dart';
 import 'package:appflowy_backend/protobuf/flowy-document/protobuf.dart';
 import 'package:appflowy_backend/protobuf/flowy-error/errors.pb.dart';
 import 'package:appflowy_backend/protobuf/flowy-folder/protobuf.dart';
+import 'package:appflowy_backend/protobuf/flowy-notification/protobuf.dart';
 import 'package:appflowy_backend/protobuf/flowy-search/protobuf.dart';
 import 'package:appflowy_backend/protobuf/flowy-user/protobuf.dart';
 import 'package:appflowy_backend/protobuf/flowy-ai/protobuf.dart';
@@ -28,6 +29,10 @@ import '../protobuf/flowy-config/entities.pb.dart';
 import '../protobuf/flowy-config/event_map.pb.dart';
 import '../protobuf/flowy-date/entities.pb.dart';
 import '../protobuf/flowy-date/event_map.pb.dart';
+import '../protobuf/flowy-kv/entities.pb.dart';
+import '../protobuf/flowy-kv/event_map.pb.dart';
+import '../protobuf/flowy-sync/entities.pb.dart';
+import '../protobuf/flowy-sync/event_map.pb.dart';
 
 import 'error.dart';
 
@@ -37,9 +42,12 @@ part 'dart_event/flowy-database2/dart_event.dart';
 part 'dart_event/flowy-document/dart_event.dart';
 part 'dart_event/flowy-config/dart_event.dart';
 part 'dart_event/flowy-date/dart_event.dart';
+part 'dart_event/flowy-kv/dart_event.dart';
+part 'dart_event/flowy-sync/dart_event.dart';
 part 'dart_event/flowy-search/dart_event.dart';
 part 'dart_event/flowy-ai/dart_event.dart';
 part 'dart_event/flowy-storage/dart_event.dart';
+part 'dart_event/flowy-notification/dart_event.dart';
 
 enum FFIException {
   RequestIsEmpty,
@@ -76,6 +84,14 @@ Future<FlowyResult<Uint8List, Uint8List>> _extractPayload(
             return FlowySuccess(Uint8List.fromList(response.payload));
           case FFIStatusCode.Err:
             final errorBytes = Uint8List.fromList(response.payload);
+            if (response.hasError() &&
+                response.error.category != FFIErrorCategory.Handler) {
+              // The payload is not a FlowyError when the request did not reach its handler.
+              Log.error(
+                "Dispatch ${response.error.category} error: ${response.error.message}",
+              );
+              return FlowyFailure(emptyBytes());
+            }
             GlobalErrorCodeNotifier.receiveErrorBytes(errorBytes);
             return FlowyFailure(errorBytes);
           case FFIStatusCode.Internal:
@@ -56,6 +56,32 @@ typedef _invoke_async_Dart = void Function(
   int len,
 );
 
+/// C function `async_event_batch`.
+void async_event_batch(
+  Pointer<Int64> ports,
+  int ports_len,
+  Pointer<Uint8> input,
+  int len,
+) {
+  _invoke_async_batch(ports, ports_len, input, len);
+}
+
+final _invoke_async_batch_Dart _invoke_async_batch = _dart_ffi_lib
+    .lookupFunction<_invoke_async_batch_C, _invoke_async_batch_Dart>(
+        'async_event_batch');
+typedef _invoke_async_batch_C = Void Function(
+  Pointer<Int64> ports,
+  Uint64 ports_len,
+  Pointer<Uint8> input,
+  Uint64 len,
+);
+typedef _invoke_async_batch_Dart = void Function(
+  Pointer<Int64> ports,
+  int ports_len,
+  Pointer<Uint8> input,
+  int len,
+);
+
 /// C function `sync_event`.
 Pointer<Uint8> sync_event(
   Pointer<Uint8> input,
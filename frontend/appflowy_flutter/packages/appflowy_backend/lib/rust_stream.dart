@@ -1,10 +1,14 @@
 import 'dart:async';
+import 'dart:collection';
 import 'dart:ffi';
 import 'dart:isolate';
 import 'dart:typed_data';
+
+import 'package:appflowy_backend/dispatch/dispatch.dart';
 import 'package:appflowy_backend/log.dart';
+import 'package:fixnum/fixnum.dart';
 
-import 'protobuf/flowy-notification/subject.pb.dart';
+import 'protobuf/flowy-notification/protobuf.dart';
 
 typedef ObserverCallback = void Function(SubscribeObject observable);
 
@@ -15,6 +19,11 @@ class RustStreamReceiver {
   late StreamController<SubscribeObject> _observableController;
   late StreamSubscription<Uint8List> _ffiSubscription;
 
+  /// The ack ids of the last acknowledged notifications. The notifications that must be
+  /// acknowledged are resent until Rust receives the ack, so they may arrive more than once.
+  final Queue<Int64> _ackedIds = Queue();
+  static const int _maxAckedIds = 256;
+
   int get port => _ffiPort.sendPort.nativePort;
   StreamController<SubscribeObject> get observable => _observableController;
 
@@ -39,6 +48,13 @@ class RustStreamReceiver {
   void _streamCallback(Uint8List bytes) {
     try {
       final observable = SubscribeObject.fromBuffer(bytes);
+      if (observable.hasAckId()) {
+        final isDuplicate = _ackedIds.contains(observable.ackId);
+        _ack(observable.ackId);
+        if (isDuplicate) {
+          return;
+        }
+      }
       _observableController.add(observable);
     } catch (e, s) {
       Log.error(
@@ -48,6 +64,16 @@ class RustStreamReceiver {
     }
   }
 
+  void _ack(Int64 ackId) {
+    if (!_ackedIds.contains(ackId)) {
+      _ackedIds.addLast(ackId);
+      if (_ackedIds.length > _maxAckedIds) {
+        _ackedIds.removeFirst();
+      }
+    }
+    NotificationEventAckNotification(NotificationAckPB(ackId: ackId)).send();
+  }
+
   Future<void> dispose() async {
     await _ffiSubscription.cancel();
     await _streamController.close();
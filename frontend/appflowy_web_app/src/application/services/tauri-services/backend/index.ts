@@ -5,4 +5,6 @@ export * from "./models/flowy-document";
 export * from "./models/flowy-error";
 export * from "./models/flowy-config";
 export * from "./models/flowy-date";
+export * from "./models/flowy-kv";
+export * from "./models/flowy-sync";
 export * from "./models/flowy-storage";
@@ -0,0 +1,69 @@
+use std::path::Path;
+
+use walkdir::WalkDir;
+
+/// The folder of a crate that contains its Cap'n Proto schemas.
+pub const CAPNP_SCHEMA_PATH: &str = "resources/capnp";
+
+/// The folder of a crate that the Rust code of its Cap'n Proto schemas is generated into.
+pub const CAPNP_OUTPUT_PATH: &str = "src/capnp";
+
+/// Compiles the `.capnp` schemas of the crate into `src/capnp`, one `<schema>_capnp.rs` file per
+/// schema, and declares them in `src/capnp/mod.rs`. The generated `Owned` types are the ones the
+/// `lib_dispatch::prelude::Capnp` payload takes. Does nothing if the crate has no schema.
+///
+/// The `capnp` compiler must be installed, see https://capnproto.org/install.html.
+pub fn gen(crate_name: &str) {
+  if !Path::new(CAPNP_SCHEMA_PATH).exists() {
+    return;
+  }
+
+  let mut file_names = vec![];
+  let mut command = capnpc::CompilerCommand::new();
+  command
+    .src_prefix(CAPNP_SCHEMA_PATH)
+    .output_path(CAPNP_OUTPUT_PATH)
+    .default_parent_module(vec!["capnp".to_owned()]);
+  for entry in WalkDir::new(CAPNP_SCHEMA_PATH)
+    .into_iter()
+    .filter_map(|e| e.ok())
+    .filter(|e| {
+      e.path()
+        .extension()
+        .map(|ext| ext == "capnp")
+        .unwrap_or(false)
+    })
+  {
+    // https://stackoverflow.com/questions/49077147/how-can-i-force-build-rs-to-run-again-without-cleaning-my-whole-project
+    println!("cargo:rerun-if-changed={}", entry.path().display());
+    let file_name = entry
+      .path()
+      .file_stem()
+      .unwrap()
+      .to_str()
+      .unwrap()
+      .to_string();
+    file_names.push(file_name);
+    command.file(entry.path());
+  }
+
+  if file_names.is_empty() {
+    return;
+  }
+  file_names.sort();
+
+  std::fs::create_dir_all(CAPNP_OUTPUT_PATH).unwrap();
+  if let Err(err) = command.run() {
+    panic!("Generate {} capnp files failed with: {}", crate_name, err);
+  }
+
+  let mut mod_file = String::new();
+  mod_file.push_str("#![cfg_attr(rustfmt, rustfmt::skip)]\n");
+  mod_file.push_str("// Auto-generated, do not edit\n");
+  for file_name in file_names {
+    mod_file.push_str(&format!("pub mod {}_capnp;\n", file_name));
+  }
+  let mod_path = Path::new(CAPNP_OUTPUT_PATH).join("mod.rs");
+  std::fs::write(&mod_path, mod_file)
+    .unwrap_or_else(|err| panic!("Failed to write {}: {}", mod_path.display(), err));
+}
@@ -1,12 +1,18 @@
 #[cfg(feature = "proto_gen")]
 pub mod protobuf_file;
 
+#[cfg(feature = "capnp_gen")]
+pub mod capnp_file;
+
 #[cfg(feature = "dart_event")]
 pub mod dart_event;
 
 #[cfg(feature = "ts_event")]
 pub mod ts_event;
 
+#[cfg(feature = "native_event")]
+pub mod native_event;
+
 #[cfg(any(feature = "proto_gen", feature = "dart_event", feature = "ts_event"))]
 mod flowy_toml;
 
@@ -23,6 +29,11 @@ pub struct ProtoCache {
 pub enum Project {
   Tauri,
   TauriApp,
+  /// The web client, which sends the events through the transport registered with
+  /// `setEventTransport`, see `ts_event::TS_TRANSPORT`.
+  Web {
+    relative_path: String,
+  },
   Native,
 }
 
@@ -33,6 +44,9 @@ impl Project {
       Project::TauriApp => {
         "appflowy_web_app/src/application/services/tauri-services/backend".to_string()
       },
+      Project::Web { .. } => {
+        "appflowy_web_app/src/application/services/js-services/backend".to_string()
+      },
       Project::Native => panic!("Native project is not supported yet."),
     }
   }
@@ -40,6 +54,7 @@ impl Project {
   pub fn event_root(&self) -> String {
     match self {
       Project::Tauri | Project::TauriApp => "../../".to_string(),
+      Project::Web { relative_path } => relative_path.to_string(),
       Project::Native => panic!("Native project is not supported yet."),
     }
   }
@@ -47,6 +62,7 @@ impl Project {
   pub fn model_root(&self) -> String {
     match self {
       Project::Tauri | Project::TauriApp => "../../".to_string(),
+      Project::Web { relative_path } => relative_path.to_string(),
       Project::Native => panic!("Native project is not supported yet."),
     }
   }
@@ -58,6 +74,13 @@ impl Project {
 import { Ok, Err, Result } from "ts-results";
 import { invoke } from "@tauri-apps/api/tauri";
 import * as pb from "../..";
+"#
+      .to_string(),
+      Project::Web { .. } => r#"
+/// Auto generate. Do not edit
+import { Ok, Err, Result } from "ts-results";
+import { invoke } from "../transport";
+import * as pb from "../..";
 "#
       .to_string(),
       Project::Native => panic!("Native project is not supported yet."),
@@ -0,0 +1,47 @@
+use crate::util::get_tera;
+use tera::Context;
+
+pub struct EventTemplate {
+  tera_context: Context,
+}
+
+pub struct EventRenderContext {
+  pub input_deserializer: Option<String>,
+  pub output_deserializer: Option<String>,
+  pub event: String,
+  pub event_ty: String,
+}
+
+impl EventTemplate {
+  pub fn new(ctx: EventRenderContext) -> Self {
+    let mut tera_context = Context::new();
+    // UserEvent and GetUserProfile give userEventGetUserProfile
+    let mut func_name = format!("{}{}", ctx.event_ty, ctx.event);
+    if let Some(first) = func_name.get_mut(0..1) {
+      first.make_ascii_lowercase();
+    }
+    tera_context.insert("func_name", &func_name);
+    tera_context.insert("event", &ctx.event);
+    tera_context.insert("has_input", &ctx.input_deserializer.is_some());
+    if let Some(input) = ctx.input_deserializer.as_ref() {
+      tera_context.insert("input_deserializer", input);
+    }
+    tera_context.insert("has_output", &ctx.output_deserializer.is_some());
+    if let Some(output) = ctx.output_deserializer.as_ref() {
+      tera_context.insert("output_deserializer", output);
+    }
+    EventTemplate { tera_context }
+  }
+
+  /// Renders `swift_event.tera` or `kotlin_event.tera`.
+  pub fn render(&self, template: &str) -> Option<String> {
+    let tera = get_tera("native_event");
+    match tera.render(template, &self.tera_context) {
+      Ok(r) => Some(r),
+      Err(e) => {
+        log::error!("{:?}", e);
+        None
+      },
+    }
+  }
+}
@@ -0,0 +1,6 @@
+#![allow(clippy::module_inception)]
+
+mod event_template;
+mod native_event;
+
+pub use native_event::*;
@@ -0,0 +1,215 @@
+use std::path::PathBuf;
+
+use crate::dart_event::{parse_dart_event_files, parse_event_crate};
+use crate::util::path_string_with_component;
+
+use super::event_template::*;
+
+/// Generates the Swift and Kotlin functions of the events of `crate_name`. They send the events
+/// through `async_event_with_callback` of the C ABI, so the native extensions can use the
+/// dispatcher without Flutter.
+///
+/// The files are written to `NATIVE_BINDING_PATH`, nothing is generated when it is not set.
+pub fn gen(crate_name: &str) {
+  let binding_path = match std::env::var("NATIVE_BINDING_PATH") {
+    Ok(path) => path,
+    Err(_) => {
+      println!("NATIVE_BINDING_PATH was not set, skip generate native bindings");
+      return;
+    },
+  };
+
+  let crate_path = std::fs::canonicalize(".")
+    .unwrap()
+    .as_path()
+    .display()
+    .to_string();
+  let event_ast = parse_dart_event_files(vec![crate_path])
+    .iter()
+    .flat_map(parse_event_crate)
+    .collect::<Vec<_>>();
+
+  let mut swift = SWIFT_IMPORTED.to_owned();
+  let mut kotlin = KOTLIN_IMPORTED.to_owned();
+  for event_ast in event_ast.iter() {
+    let template = EventTemplate::new(EventRenderContext {
+      input_deserializer: event_ast
+        .event_input
+        .as_ref()
+        .map(|input| input.get_ident().unwrap().to_string()),
+      output_deserializer: event_ast
+        .event_output
+        .as_ref()
+        .map(|output| output.get_ident().unwrap().to_string()),
+      event: event_ast.event.to_string(),
+      event_ty: event_ast.event_ty.to_string(),
+    });
+    if let Some(content) = template.render("swift_event.tera") {
+      swift.push_str(&content);
+    }
+    if let Some(content) = template.render("kotlin_event.tera") {
+      kotlin.push_str(&content);
+    }
+  }
+
+  write_binding(&binding_path, "swift", crate_name, "Events.swift", &swift);
+  write_binding(&binding_path, "swift", "", "Dispatch.swift", SWIFT_DISPATCH);
+  write_binding(&binding_path, "kotlin", crate_name, "Events.kt", &kotlin);
+  write_binding(&binding_path, "kotlin", "", "Dispatch.kt", KOTLIN_DISPATCH);
+}
+
+fn write_binding(binding_path: &str, language: &str, crate_name: &str, file: &str, content: &str) {
+  let folder: PathBuf = [binding_path, language, crate_name].iter().collect();
+  if !folder.as_path().exists() {
+    std::fs::create_dir_all(folder.as_path()).unwrap();
+  }
+  let file_path = path_string_with_component(&folder, vec![file]);
+  println!("cargo:rerun-if-changed={}", file_path);
+  if let Err(err) = std::fs::write(&file_path, content) {
+    panic!("Failed to write file: {}, {:?}", file_path, err);
+  }
+}
+
+const SWIFT_IMPORTED: &str = r#"/// Auto generate. Do not edit
+import Foundation
+import SwiftProtobuf
+"#;
+
+const KOTLIN_IMPORTED: &str = r#"/// Auto generate. Do not edit
+package io.appflowy.dispatch
+
+import io.appflowy.models.*
+"#;
+
+/// Sends the `FFIRequest`s through the C ABI. Each pending request is identified by the context
+/// passed to the callback, because a C function pointer can't capture it.
+const SWIFT_DISPATCH: &str = r#"/// Auto generate. Do not edit
+import Foundation
+import SwiftProtobuf
+import DartFFI
+
+public enum DispatchError: Error {
+    /// The handler of the event failed. The payload is the encoded error.
+    case handler(Data)
+    case `internal`
+}
+
+private final class PendingRequests {
+    static let shared = PendingRequests()
+
+    private let lock = NSLock()
+    private var nextContext: Int64 = 0
+    private var continuations: [Int64: CheckedContinuation<Data, Error>] = [:]
+
+    func insert(_ continuation: CheckedContinuation<Data, Error>) -> Int64 {
+        lock.lock()
+        defer { lock.unlock() }
+        nextContext += 1
+        continuations[nextContext] = continuation
+        return nextContext
+    }
+
+    func complete(_ context: Int64, _ bytes: Data) {
+        lock.lock()
+        let continuation = continuations.removeValue(forKey: context)
+        lock.unlock()
+        guard let continuation = continuation else { return }
+        do {
+            let response = try FFIResponse(serializedData: bytes)
+            switch response.code {
+            case .ok: continuation.resume(returning: response.payload)
+            case .err: continuation.resume(throwing: DispatchError.handler(response.payload))
+            default: continuation.resume(throwing: DispatchError.internal)
+            }
+        } catch {
+            continuation.resume(throwing: error)
+        }
+    }
+}
+
+public enum Dispatch {
+    public static func asyncRequest(event: String, payload: Data) async throws -> Data {
+        var request = FFIRequest()
+        request.event = event
+        request.payload = payload
+        let input = try request.serializedData()
+        return try await withCheckedThrowingContinuation { continuation in
+            let context = PendingRequests.shared.insert(continuation)
+            input.withUnsafeBytes { buffer in
+                async_event_with_callback(
+                    buffer.bindMemory(to: UInt8.self).baseAddress,
+                    UInt(buffer.count),
+                    { context, data, len in
+                        let bytes = data.map { Data(bytes: $0, count: Int(len)) } ?? Data()
+                        PendingRequests.shared.complete(context, bytes)
+                    },
+                    context
+                )
+            }
+        }
+    }
+}
+"#;
+
+/// Loads `dart_ffi` with JNA. The callback is kept in a field so it isn't collected while the
+/// library can still call it.
+const KOTLIN_DISPATCH: &str = r#"/// Auto generate. Do not edit
+package io.appflowy.dispatch
+
+import com.google.protobuf.ByteString
+import com.sun.jna.Callback
+import com.sun.jna.Library
+import com.sun.jna.Native
+import com.sun.jna.Pointer
+import io.appflowy.models.FFIRequest
+import io.appflowy.models.FFIResponse
+import io.appflowy.models.FFIStatusCode
+import java.util.concurrent.ConcurrentHashMap
+import java.util.concurrent.atomic.AtomicLong
+import kotlin.coroutines.resume
+import kotlin.coroutines.resumeWithException
+import kotlinx.coroutines.suspendCancellableCoroutine
+
+/// The handler of the event failed. The payload is the encoded error.
+class DispatchException(val code: FFIStatusCode, val payload: ByteArray) :
+    Exception("Event failed with $code")
+
+private interface DartFFI : Library {
+    fun interface CompletionCallback : Callback {
+        fun invoke(context: Long, data: Pointer?, len: Long)
+    }
+
+    fun async_event_with_callback(input: ByteArray, len: Long, callback: CompletionCallback, context: Long)
+}
+
+object Dispatch {
+    private val ffi: DartFFI = Native.load("dart_ffi", DartFFI::class.java)
+    private val nextContext = AtomicLong()
+    private val pending = ConcurrentHashMap<Long, (FFIResponse) -> Unit>()
+    private val callback = DartFFI.CompletionCallback { context, data, len ->
+        val bytes = data?.getByteArray(0, len.toInt()) ?: ByteArray(0)
+        pending.remove(context)?.invoke(FFIResponse.parseFrom(bytes))
+    }
+
+    suspend fun asyncRequest(event: String, payload: ByteArray): ByteArray {
+        val input = FFIRequest.newBuilder()
+            .setEvent(event)
+            .setPayload(ByteString.copyFrom(payload))
+            .build()
+            .toByteArray()
+        return suspendCancellableCoroutine { continuation ->
+            val context = nextContext.incrementAndGet()
+            pending[context] = { response ->
+                val bytes = response.payload.toByteArray()
+                if (response.code == FFIStatusCode.Ok) {
+                    continuation.resume(bytes)
+                } else {
+                    continuation.resumeWithException(DispatchException(response.code, bytes))
+                }
+            }
+            continuation.invokeOnCancellation { pending.remove(context) }
+            ffi.async_event_with_callback(input, input.size.toLong(), callback, context)
+        }
+    }
+}
+"#;
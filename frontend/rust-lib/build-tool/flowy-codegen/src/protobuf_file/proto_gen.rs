@@ -136,14 +136,24 @@ fn write_rust_crate_mod_file(crate_contexts: &[ProtobufCrateContext]) {
         mod_file_content.push_str(" #![allow(ambiguous_glob_reexports)]\n");
 
         mod_file_content.push_str("// Auto-generated, do not edit\n");
+        let mut names = vec![];
         walk_dir(
           context.protobuf_crate.proto_output_path(),
           |e| !e.file_type().is_dir() && !e.file_name().to_string_lossy().starts_with('.'),
           |_, name| {
             let c = format!("\nmod {};\npub use {}::*;\n", &name, &name);
             mod_file_content.push_str(c.as_ref());
+            names.push(name);
           },
         );
+        // The descriptors the plugins convert the JSON payloads with.
+        mod_file_content.push_str("\npub fn file_descriptors() -> ");
+        mod_file_content
+          .push_str("Vec<&'static ::protobuf::descriptor::FileDescriptorProto> {\n  vec![\n");
+        for name in names {
+          mod_file_content.push_str(&format!("    {}::file_descriptor_proto(),\n", name));
+        }
+        mod_file_content.push_str("  ]\n}\n");
         file.write_all(mod_file_content.as_bytes()).unwrap();
       },
       Err(err) => {
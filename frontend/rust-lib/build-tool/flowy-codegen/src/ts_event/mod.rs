@@ -47,6 +47,13 @@ pub fn gen(dest_folder_name: &str, project: Project) {
     std::fs::create_dir_all(ts_event_folder.as_path()).unwrap();
   }
 
+  if let Project::Web { .. } = project {
+    let transport_path: PathBuf = [&root, &backend_service_path, "events", "transport.ts"]
+      .iter()
+      .collect();
+    std::fs::write(transport_path, TS_TRANSPORT).unwrap();
+  }
+
   let event_file = "event";
   let event_file_ext = "ts";
   let ts_event_file_path = path_string_with_component(
@@ -202,3 +209,97 @@ pub fn ast_to_event_render_ctx(ast: &[EventASTContext]) -> Vec<EventRenderContex
 
 const TS_FOOTER: &str = r#"
 "#;
+
+/// The `invoke` used by the events of [Project::Web]. The events are sent through the transport
+/// registered with `setEventTransport`: the WASM module or the dispatcher's WebSocket bridge, whose
+/// frames are encoded by `WebSocketTransport`.
+pub const TS_TRANSPORT: &str = r#"/// Auto generate. Do not edit
+export interface EventRequest {
+  ty: string;
+  payload: number[];
+}
+
+export interface EventResponse {
+  code: number;
+  payload: Uint8Array;
+}
+
+export interface EventTransport {
+  send(request: EventRequest): Promise<EventResponse>;
+}
+
+let transport: EventTransport | undefined;
+
+export function setEventTransport(value: EventTransport) {
+  transport = value;
+}
+
+export async function invoke(_command: string, args: { request: EventRequest }): Promise<EventResponse> {
+  if (!transport) {
+    throw new Error("No event transport, call setEventTransport first");
+  }
+  return transport.send(args.request);
+}
+
+/// Sends the events to a WASM build of the dispatcher.
+export class WasmTransport implements EventTransport {
+  constructor(private readonly asyncEvent: (name: string, payload: Uint8Array) => Promise<EventResponse>) {}
+
+  send(request: EventRequest): Promise<EventResponse> {
+    return this.asyncEvent(request.ty, Uint8Array.from(request.payload));
+  }
+}
+
+/// Sends the events to the dispatcher's WebSocket bridge.
+export class WebSocketTransport implements EventTransport {
+  private nextId = 0;
+  private pending = new Map<number, (response: EventResponse) => void>();
+  private readonly ready: Promise<void>;
+
+  constructor(
+    private readonly socket: WebSocket,
+    token: string,
+    private readonly onNotification?: (payload: Uint8Array) => void,
+  ) {
+    socket.binaryType = "arraybuffer";
+    this.ready = new Promise((resolve, reject) => {
+      socket.addEventListener("open", () => {
+        socket.send(token);
+        resolve();
+      });
+      socket.addEventListener("error", reject);
+    });
+    socket.addEventListener("message", (event) => this.receive(new Uint8Array(event.data)));
+  }
+
+  async send(request: EventRequest): Promise<EventResponse> {
+    await this.ready;
+    const id = this.nextId++;
+    const name = new TextEncoder().encode(request.ty);
+    const frame = new Uint8Array(6 + name.length + request.payload.length);
+    const view = new DataView(frame.buffer);
+    view.setUint32(0, id);
+    view.setUint16(4, name.length);
+    frame.set(name, 6);
+    frame.set(request.payload, 6 + name.length);
+    return new Promise((resolve) => {
+      this.pending.set(id, resolve);
+      this.socket.send(frame);
+    });
+  }
+
+  private receive(frame: Uint8Array) {
+    const view = new DataView(frame.buffer, frame.byteOffset, frame.byteLength);
+    if (frame[0] === 1) {
+      this.onNotification?.(frame.subarray(1));
+      return;
+    }
+    const id = view.getUint32(1);
+    const resolve = this.pending.get(id);
+    if (resolve) {
+      this.pending.delete(id);
+      resolve({ code: frame[5], payload: frame.subarray(6) });
+    }
+  }
+}
+"#;
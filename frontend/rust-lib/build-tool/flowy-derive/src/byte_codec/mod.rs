@@ -0,0 +1,72 @@
+use proc_macro2::TokenStream;
+use syn::{Meta, NestedMeta};
+
+const CODEC_ATTR: &str = "codec";
+
+/// The codecs of lib-dispatch a type can be encoded with, named by the `#[codec(..)]` attribute.
+/// MessagePack is used when the attribute is omitted.
+fn codec_ident(input: &syn::DeriveInput) -> Result<syn::Ident, Vec<syn::Error>> {
+  let mut codec = None;
+  for attr in input
+    .attrs
+    .iter()
+    .filter(|attr| attr.path.is_ident(CODEC_ATTR))
+  {
+    let name = match attr.parse_meta() {
+      Ok(Meta::List(list)) if list.nested.len() == 1 => match list.nested.first() {
+        Some(NestedMeta::Meta(Meta::Path(path))) => path.get_ident().cloned(),
+        _ => None,
+      },
+      _ => None,
+    };
+    let ident = match name.as_ref().map(|name| name.to_string()).as_deref() {
+      Some("json") => format_ident!("JsonCodec"),
+      Some("msgpack") => format_ident!("MessagePackCodec"),
+      Some("cbor") => format_ident!("CborCodec"),
+      Some("bincode") => format_ident!("BincodeCodec"),
+      _ => {
+        return Err(vec![syn::Error::new_spanned(
+          attr,
+          "expected #[codec(json)], #[codec(msgpack)], #[codec(cbor)] or #[codec(bincode)]",
+        )])
+      },
+    };
+    if codec.replace(ident).is_some() {
+      return Err(vec![syn::Error::new_spanned(
+        attr,
+        "duplicate #[codec(..)] attribute",
+      )]);
+    }
+  }
+  Ok(codec.unwrap_or_else(|| format_ident!("MessagePackCodec")))
+}
+
+pub fn expand_to_bytes_derive(input: &syn::DeriveInput) -> Result<TokenStream, Vec<syn::Error>> {
+  let codec = codec_ident(input)?;
+  let ident = &input.ident;
+  let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+  Ok(quote! {
+    impl #impl_generics lib_dispatch::prelude::ToBytes for #ident #ty_generics #where_clause {
+      fn into_bytes(self) -> Result<bytes::Bytes, lib_dispatch::prelude::DispatchError> {
+        lib_dispatch::prelude::encode_bytes::<lib_dispatch::prelude::#codec, _>(&self)
+      }
+    }
+  })
+}
+
+pub fn expand_from_bytes_derive(input: &syn::DeriveInput) -> Result<TokenStream, Vec<syn::Error>> {
+  let codec = codec_ident(input)?;
+  let ident = &input.ident;
+  let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+  Ok(quote! {
+    impl #impl_generics lib_dispatch::prelude::AFPluginFromBytes
+    for #ident #ty_generics #where_clause
+    {
+      fn parse_from_bytes(
+        bytes: bytes::Bytes,
+      ) -> Result<Self, lib_dispatch::prelude::DispatchError> {
+        lib_dispatch::prelude::decode_bytes::<lib_dispatch::prelude::#codec, _>(&bytes)
+      }
+    }
+  })
+}
@@ -8,6 +8,7 @@ use syn::{parse_macro_input, DeriveInput};
 #[macro_use]
 extern crate quote;
 
+mod byte_codec;
 mod dart_event;
 mod node;
 mod proto_buf;
@@ -29,6 +30,27 @@ pub fn derive_proto_buf_enum(input: TokenStream) -> TokenStream {
     .into()
 }
 
+/// Implements `ToBytes` of lib-dispatch with a serde codec, chosen with `#[codec(json)]`,
+/// `#[codec(msgpack)]`, `#[codec(cbor)]` or `#[codec(bincode)]`. MessagePack by default. The
+/// type must implement `Serialize`.
+#[proc_macro_derive(ToBytes, attributes(codec))]
+pub fn derive_to_bytes(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  byte_codec::expand_to_bytes_derive(&input)
+    .unwrap_or_else(to_compile_errors)
+    .into()
+}
+
+/// Implements `AFPluginFromBytes` of lib-dispatch, the counterpart of [ToBytes](derive@ToBytes).
+/// The type must implement `Deserialize`.
+#[proc_macro_derive(FromBytes, attributes(codec))]
+pub fn derive_from_bytes(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  byte_codec::expand_from_bytes_derive(&input)
+    .unwrap_or_else(to_compile_errors)
+    .into()
+}
+
 #[proc_macro_derive(Flowy_Event, attributes(event, event_err))]
 pub fn derive_dart_event(input: TokenStream) -> TokenStream {
   let input = parse_macro_input!(input as DeriveInput);
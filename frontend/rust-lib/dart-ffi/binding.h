@@ -3,14 +3,36 @@
 #include <stdint.h>
 #include <stdlib.h>
 
+typedef void (*CompletionCallback)(int64_t context, const uint8_t *data, uintptr_t len);
+
 int64_t init_sdk(int64_t port, char *data);
 
 void async_event(int64_t port, const uint8_t *input, uintptr_t len);
 
+void async_event_with_callback(const uint8_t *input,
+                               uintptr_t len,
+                               CompletionCallback callback,
+                               int64_t context);
+
+void async_event_batch(const int64_t *ports,
+                       uintptr_t ports_len,
+                       const uint8_t *input,
+                       uintptr_t len);
+
 const uint8_t *sync_event(const uint8_t *input, uintptr_t len);
 
 int32_t set_stream_port(int64_t port);
 
+int32_t register_notification_port(int64_t port, uint32_t capacity);
+
+int32_t unregister_notification_port(int64_t port);
+
+int32_t subscribe_notification_port(int64_t port, const char *source);
+
+int32_t unsubscribe_notification_port(int64_t port, const char *source);
+
+int32_t ack_notification_port(int64_t port, uint32_t count);
+
 int32_t set_log_stream_port(int64_t port);
 
 void link_me_please(void);
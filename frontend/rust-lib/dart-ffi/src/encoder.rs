@@ -0,0 +1,72 @@
+use std::sync::Arc;
+
+use lib_dispatch::prelude::{AFPluginEventResponse, ToBytes};
+use tracing::error;
+
+use crate::handoff::PayloadHandoff;
+use crate::model::FFIResponse;
+
+/// Encodes the responses into the [FFIResponse] frames handed back to the callers.
+///
+/// Encoding a large response, like a whole document, copies its payload a couple of times. The
+/// responses whose payload reaches the offload threshold are encoded on the blocking pool of the
+/// runtime instead, so the dispatch worker keeps handling the other requests in the meantime.
+/// So are the ones whose payload is handed off in a file, see [PayloadHandoff].
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ResponseEncoder {
+  /// `None` encodes all the responses on the dispatch worker.
+  offload_threshold: Option<usize>,
+  /// `None` copies all the payloads in the responses.
+  handoff: Option<Arc<PayloadHandoff>>,
+}
+
+impl ResponseEncoder {
+  pub(crate) fn new(
+    offload_threshold: Option<usize>,
+    handoff: Option<Arc<PayloadHandoff>>,
+  ) -> Self {
+    Self {
+      offload_threshold,
+      handoff,
+    }
+  }
+
+  fn should_offload(&self, response: &AFPluginEventResponse) -> bool {
+    let payload = response.payload.as_ref();
+    self
+      .offload_threshold
+      .is_some_and(|threshold| payload.len() >= threshold)
+      || self
+        .handoff
+        .as_ref()
+        .is_some_and(|handoff| handoff.should_hand_off(payload))
+  }
+
+  /// The bytes of the [FFIResponse] built from `response`.
+  pub(crate) async fn encode(self, response: AFPluginEventResponse) -> Vec<u8> {
+    if !self.should_offload(&response) {
+      return encode_response(FFIResponse::from(response));
+    }
+    let handoff = self.handoff;
+    let encode = move || {
+      let mut response = FFIResponse::from(response);
+      if let Some(handoff) = handoff {
+        response.hand_off(&handoff);
+      }
+      encode_response(response)
+    };
+    match tokio::task::spawn_blocking(encode).await {
+      Ok(bytes) => bytes,
+      Err(err) => {
+        error!("[FFI]: Failed to encode the response: {}", err);
+        encode_response(FFIResponse::internal_error(
+          "The response could not be encoded",
+        ))
+      },
+    }
+  }
+}
+
+pub(crate) fn encode_response(response: FFIResponse) -> Vec<u8> {
+  response.into_bytes().unwrap_or_default().to_vec()
+}
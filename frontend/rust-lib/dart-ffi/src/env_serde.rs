@@ -19,6 +19,28 @@ pub struct AppFlowyDartConfiguration {
   pub(crate) appflowy_cloud_config: AFCloudConfiguration,
   #[serde(default)]
   pub(crate) envs: HashMap<String, String>,
+  /// The responses whose payload is at least this many bytes are encoded on a blocking thread
+  /// instead of the dispatch worker. See [ResponseEncoder](crate::encoder::ResponseEncoder).
+  #[serde(default)]
+  pub(crate) response_offload_threshold: Option<usize>,
+  /// The payloads of at least this many bytes are handed over in the `handoff` directory of the
+  /// root instead of being copied through the FFI calls. See
+  /// [PayloadHandoff](crate::handoff::PayloadHandoff).
+  #[serde(default)]
+  pub(crate) payload_handoff_threshold: Option<usize>,
+  /// The WebSocket server the mutations are synced with, if any. See
+  /// [SyncEngine](flowy_sync::SyncEngine).
+  #[serde(default)]
+  pub(crate) sync_server_url: Option<String>,
+  #[serde(default)]
+  pub(crate) sync_server_token: String,
+  /// The plugins whose requests are logged by the dispatcher, like `flowy-folder`. See
+  /// `AppFlowyCoreConfig::dispatch_log_filter`.
+  #[serde(default)]
+  pub(crate) dispatch_log_plugins: Vec<String>,
+  /// The level of the logs of `dispatch_log_plugins`, `trace` by default.
+  #[serde(default)]
+  pub(crate) dispatch_log_level: Option<String>,
 }
 
 impl AppFlowyDartConfiguration {
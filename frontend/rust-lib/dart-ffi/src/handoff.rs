@@ -0,0 +1,72 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tracing::warn;
+
+/// The directory, under the root of the app, the payloads are handed over in.
+pub(crate) const HANDOFF_DIR: &str = "handoff";
+
+/// Hands the large payloads over through files instead of copying them through the FFI call.
+///
+/// A client sends a payload of at least `threshold` bytes by writing it to a file of the handoff
+/// directory and setting `payload_file` on the [FFIRequest](crate::model::FFIRequest) instead of
+/// `payload`. The core reads the file and removes it. The responses whose payload reaches the
+/// threshold come back the same way, with `payload_file` set on the
+/// [FFIResponse](crate::model::FFIResponse), and the client removes the file once read.
+///
+/// The directory is best put on a memory backed file system, like `/dev/shm` or the cache
+/// directory of the app, so the payloads don't hit the disk.
+#[derive(Debug)]
+pub(crate) struct PayloadHandoff {
+  dir: PathBuf,
+  threshold: usize,
+  next_file: AtomicU64,
+}
+
+impl PayloadHandoff {
+  /// Creates the directory, and removes the payloads left over by the previous run.
+  pub(crate) fn new(dir: PathBuf, threshold: usize) -> io::Result<Self> {
+    if dir.exists() {
+      std::fs::remove_dir_all(&dir)?;
+    }
+    std::fs::create_dir_all(&dir)?;
+    Ok(Self {
+      dir: dir.canonicalize()?,
+      threshold,
+      next_file: AtomicU64::new(0),
+    })
+  }
+
+  pub(crate) fn should_hand_off(&self, payload: &[u8]) -> bool {
+    payload.len() >= self.threshold
+  }
+
+  /// Writes `payload` to a new file of the directory and returns its path.
+  pub(crate) fn write(&self, payload: &[u8]) -> io::Result<String> {
+    let n = self.next_file.fetch_add(1, Ordering::Relaxed);
+    let path = self.dir.join(format!("response-{}.bin", n));
+    std::fs::write(&path, payload)?;
+    Ok(path.to_string_lossy().into_owned())
+  }
+
+  /// Reads the payload of the file written by the client and removes it. Only the files of the
+  /// directory are read.
+  pub(crate) fn take(&self, file: &str) -> Result<Vec<u8>, String> {
+    let path = Path::new(file)
+      .canonicalize()
+      .map_err(|e| format!("Invalid payload file {}: {}", file, e))?;
+    if path.parent() != Some(self.dir.as_path()) {
+      return Err(format!(
+        "The payload file {} is not in {:?}",
+        file, self.dir
+      ));
+    }
+    let payload = std::fs::read(&path)
+      .map_err(|e| format!("Failed to read the payload file {}: {}", file, e))?;
+    if let Err(err) = std::fs::remove_file(&path) {
+      warn!("[FFI]: Failed to remove the payload file {}: {}", file, err);
+    }
+    Ok(payload)
+  }
+}
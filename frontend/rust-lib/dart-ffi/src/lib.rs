@@ -1,52 +1,103 @@
 #![allow(clippy::not_unsafe_ptr_arg_deref)]
 
 use allo_isolate::Isolate;
-use futures::ready;
 use lazy_static::lazy_static;
 use semver::Version;
-use std::future::Future;
-use std::pin::Pin;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
-use std::task::{Context, Poll};
 use std::{ffi::CStr, os::raw::c_char};
 use tokio::sync::mpsc;
-use tokio::task::LocalSet;
 use tracing::{debug, error, info, trace, warn};
 
 use flowy_core::config::AppFlowyCoreConfig;
 use flowy_core::*;
-use flowy_notification::{register_notification_sender, unregister_all_notification_sender};
+use flowy_notification::{
+  register_buffered_subscriber, register_notification_sender, set_notification_buffer_dir,
+  unregister_all_notification_sender,
+};
 use flowy_server_pub::AuthenticatorType;
+use lib_dispatch::config::{SystemConfig, SYSTEM_CONFIG_FILE};
 use lib_dispatch::prelude::ToBytes;
 use lib_dispatch::prelude::*;
 use lib_dispatch::runtime::AFPluginRuntime;
 use lib_log::stream_log::StreamLogSender;
 
 use crate::appflowy_yaml::save_appflowy_cloud_config;
+use crate::encoder::{encode_response, ResponseEncoder};
 use crate::env_serde::AppFlowyDartConfiguration;
-use crate::notification::DartNotificationSender;
+use crate::handoff::{PayloadHandoff, HANDOFF_DIR};
+use crate::notification::{DartNotificationSender, PortNotificationSender, NOTIFICATION_PORTS};
+use crate::runner::TaskRouter;
 use crate::{
   c::{extend_front_four_bytes_into_bytes, forget_rust},
-  model::{FFIRequest, FFIResponse},
+  model::{FFIRequest, FFIRequestBatch, FFIResponse},
 };
 
 mod appflowy_yaml;
 mod c;
+mod encoder;
 mod env_serde;
+mod handoff;
 mod model;
 mod notification;
 mod protobuf;
+mod runner;
 
 lazy_static! {
   static ref DART_APPFLOWY_CORE: DartAppFlowyCore = DartAppFlowyCore::new();
   static ref LOG_STREAM_ISOLATE: RwLock<Option<Isolate>> = RwLock::new(None);
 }
 
+static PORT_SENDER_REGISTERED: AtomicBool = AtomicBool::new(false);
+
+/// The name the notifications of the Flutter isolate are buffered under while it restarts.
+const DART_NOTIFICATION_SUBSCRIBER: &str = "dart";
+
+/// Called with the response of [async_event_with_callback], encoded as a [FFIResponse]. The
+/// bytes are only valid until the callback returns.
+pub type CompletionCallback = extern "C" fn(context: i64, data: *const u8, len: usize);
+
+/// How the response of a [Task] is handed back to the caller.
+enum Completion {
+  /// Posted to the Dart isolate listening on the port.
+  Port(i64),
+  /// Passed to the callback along with the context given by the caller.
+  Callback {
+    callback: CompletionCallback,
+    context: i64,
+  },
+  /// Sent to the caller waiting in [sync_event].
+  Return(mpsc::Sender<FFIResponse>),
+  /// Dropped, for the requests sent by the core itself, like the remote changes of the sync
+  /// engine.
+  Discard,
+}
+
+/// Hands `response` back to the caller of a request that was not dispatched.
+fn complete(response: FFIResponse, completion: Completion) {
+  match completion {
+    Completion::Port(port) => {
+      let bytes = encode_response(response);
+      if !Isolate::new(port).post(bytes) {
+        error!("[FFI]: Failed to post the response to the {} port", port);
+      }
+    },
+    Completion::Callback { callback, context } => {
+      let bytes = encode_response(response);
+      callback(context, bytes.as_ptr(), bytes.len());
+    },
+    Completion::Return(ret) => {
+      let _ = ret.try_send(response);
+    },
+    Completion::Discard => {},
+  }
+}
+
 pub struct Task {
   dispatcher: Arc<AFPluginDispatcher>,
   request: AFPluginRequest,
-  port: i64,
-  ret: Option<mpsc::Sender<AFPluginEventResponse>>,
+  completion: Completion,
 }
 
 unsafe impl Send for Task {}
@@ -54,8 +105,9 @@ unsafe impl Sync for DartAppFlowyCore {}
 
 struct DartAppFlowyCore {
   core: Arc<RwLock<Option<AppFlowyCore>>>,
-  handle: RwLock<Option<std::thread::JoinHandle<()>>>,
-  sender: RwLock<Option<mpsc::UnboundedSender<Task>>>,
+  handles: RwLock<Vec<std::thread::JoinHandle<()>>>,
+  router: RwLock<Option<TaskRouter>>,
+  handoff: RwLock<Option<Arc<PayloadHandoff>>>,
 }
 
 impl DartAppFlowyCore {
@@ -63,11 +115,16 @@ impl DartAppFlowyCore {
     Self {
       #[allow(clippy::arc_with_non_send_sync)]
       core: Arc::new(RwLock::new(None)),
-      handle: RwLock::new(None),
-      sender: RwLock::new(None),
+      handles: RwLock::new(vec![]),
+      router: RwLock::new(None),
+      handoff: RwLock::new(None),
     }
   }
 
+  fn handoff(&self) -> Option<Arc<PayloadHandoff>> {
+    self.handoff.read().ok().and_then(|handoff| handoff.clone())
+  }
+
   fn dispatcher(&self) -> Option<Arc<AFPluginDispatcher>> {
     let binding = self
       .core
@@ -77,23 +134,76 @@ impl DartAppFlowyCore {
     core.map(|core| core.event_dispatcher.clone())
   }
 
-  fn dispatch(
-    &self,
-    request: AFPluginRequest,
-    port: i64,
-    ret: Option<mpsc::Sender<AFPluginEventResponse>>,
-  ) {
-    if let Ok(sender_guard) = self.sender.read() {
-      if let Err(e) = sender_guard.as_ref().unwrap().send(Task {
-        dispatcher: self.dispatcher().unwrap(),
+  fn dispatch(&self, request: AFPluginRequest, completion: Completion) {
+    let dispatcher = match self.dispatcher() {
+      Some(dispatcher) => dispatcher,
+      None => {
+        error!("[FFI]: {:?} dispatched before init_sdk", request.event);
+        let response = FFIResponse::internal_error("The core is not initialized");
+        return complete(response, completion);
+      },
+    };
+    // The trivial synchronous handlers answer right away, without crossing the queue.
+    let request = match dispatcher.try_call_inline(request) {
+      Ok(response) => return complete(FFIResponse::from(response), completion),
+      Err(request) => request,
+    };
+    if let Ok(router_guard) = self.router.read() {
+      if let Err(task) = router_guard.as_ref().unwrap().send(Task {
+        dispatcher,
         request,
-        port,
-        ret,
+        completion,
       }) {
-        error!("Failed to send task: {}", e);
+        error!("Failed to send task: {:?}", task.request.event);
+        complete(
+          FFIResponse::internal_error("The dispatcher is stopped"),
+          task.completion,
+        );
+      }
+    } else {
+      warn!("Failed to acquire read lock for router");
+    }
+  }
+
+  /// Same as [DartAppFlowyCore::dispatch] for several requests, handed to the workers with a
+  /// single message per worker.
+  fn dispatch_batch(&self, requests: Vec<(AFPluginRequest, Completion)>) {
+    let dispatcher = match self.dispatcher() {
+      Some(dispatcher) => dispatcher,
+      None => {
+        error!(
+          "[FFI]: {} requests dispatched before init_sdk",
+          requests.len()
+        );
+        for (_, completion) in requests {
+          complete(
+            FFIResponse::internal_error("The core is not initialized"),
+            completion,
+          );
+        }
+        return;
+      },
+    };
+    let tasks = requests
+      .into_iter()
+      .map(|(request, completion)| Task {
+        dispatcher: dispatcher.clone(),
+        request,
+        completion,
+      })
+      .collect();
+    if let Ok(router_guard) = self.router.read() {
+      if let Err(tasks) = router_guard.as_ref().unwrap().send_batch(tasks) {
+        error!("Failed to send {} tasks", tasks.len());
+        for task in tasks {
+          complete(
+            FFIResponse::internal_error("The dispatcher is stopped"),
+            task.completion,
+          );
+        }
       }
     } else {
-      warn!("Failed to acquire read lock for sender");
+      warn!("Failed to acquire read lock for router");
     }
   }
 }
@@ -111,6 +221,19 @@ pub extern "C" fn init_sdk(_port: i64, data: *mut c_char) -> i64 {
     .expect("Failed to convert C string to Rust string");
   let configuration = AppFlowyDartConfiguration::from_str(serde_str);
   configuration.write_env();
+  // Read before anything starts, so a broken config doesn't leave the app half started. The
+  // logs are not set up yet.
+  let system_config = match SystemConfig::load(
+    &Path::new(&configuration.root).join(SYSTEM_CONFIG_FILE),
+    std::env::vars(),
+  ) {
+    Ok(system_config) => system_config,
+    Err(err) => {
+      eprintln!("[FFI]: {}", err);
+      return -1;
+    },
+  };
+  set_notification_buffer_dir(Path::new(&configuration.root).join("notifications"));
 
   if configuration.authenticator_type == AuthenticatorType::AppFlowyCloud {
     let _ = save_appflowy_cloud_config(&configuration.root, &configuration.appflowy_cloud_config);
@@ -124,7 +247,7 @@ pub extern "C" fn init_sdk(_port: i64, data: *mut c_char) -> i64 {
     app_version = min_version;
   }
 
-  let config = AppFlowyCoreConfig::new(
+  let mut config = AppFlowyCoreConfig::new(
     app_version,
     configuration.custom_app_path,
     configuration.origin_app_path,
@@ -132,6 +255,20 @@ pub extern "C" fn init_sdk(_port: i64, data: *mut c_char) -> i64 {
     configuration.platform,
     DEFAULT_NAME.to_string(),
   );
+  if let Some(url) = &configuration.sync_server_url {
+    config = config.sync_server(url, &configuration.sync_server_token);
+  }
+  config = config.system_config(system_config.clone());
+  // After the system config, whose log level replaces the log filter.
+  let dispatch_log_level = configuration
+    .dispatch_log_level
+    .as_deref()
+    .unwrap_or("trace");
+  config = config.dispatch_log_filter(dispatch_log_level, configuration.dispatch_log_plugins);
+  #[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+  {
+    config = config.keyring(Arc::new(flowy_encrypt::OsKeyring::new(DEFAULT_NAME)));
+  }
 
   if let Some(core) = &*DART_APPFLOWY_CORE.core.write().unwrap() {
     core.close_db();
@@ -142,26 +279,64 @@ pub extern "C" fn init_sdk(_port: i64, data: *mut c_char) -> i64 {
     .unwrap()
     .take()
     .map(|isolate| Arc::new(LogStreamSenderImpl { isolate }) as Arc<dyn StreamLogSender>);
-  let (sender, task_rx) = mpsc::unbounded_channel::<Task>();
-  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
-  let cloned_runtime = runtime.clone();
-  let handle = std::thread::spawn(move || {
-    let local_set = LocalSet::new();
-    cloned_runtime.block_on(local_set.run_until(Runner { rx: task_rx }));
-  });
-
-  *DART_APPFLOWY_CORE.sender.write().unwrap() = Some(sender);
-  *DART_APPFLOWY_CORE.handle.write().unwrap() = Some(handle);
+  let runtime = Arc::new(AFPluginRuntime::with_config(&system_config).unwrap());
+  // Outside of the closure, which would borrow the whole configuration, some of it moved.
+  let handoff_dir = Path::new(&configuration.root).join(HANDOFF_DIR);
+  let handoff = configuration
+    .payload_handoff_threshold
+    .and_then(
+      |threshold| match PayloadHandoff::new(handoff_dir, threshold) {
+        Ok(handoff) => Some(Arc::new(handoff)),
+        Err(err) => {
+          eprintln!("[FFI]: The payloads won't be handed off: {}", err);
+          None
+        },
+      },
+    );
+  *DART_APPFLOWY_CORE.handoff.write().unwrap() = handoff.clone();
+  let encoder = ResponseEncoder::new(configuration.response_offload_threshold, handoff);
+  let (router, handles) = TaskRouter::start(runtime.clone(), encoder);
+
+  *DART_APPFLOWY_CORE.router.write().unwrap() = Some(router);
+  *DART_APPFLOWY_CORE.handles.write().unwrap() = handles;
   let cloned_runtime = runtime.clone();
-  *DART_APPFLOWY_CORE.core.write().unwrap() = runtime
-    .block_on(async move { Some(AppFlowyCore::new(config, cloned_runtime, log_stream).await) });
+  let core =
+    runtime.block_on(async move { AppFlowyCore::new(config, cloned_runtime, log_stream).await });
+  let remote_changes = core
+    .sync_engine
+    .as_ref()
+    .and_then(|engine| engine.take_remote_changes());
+  *DART_APPFLOWY_CORE.core.write().unwrap() = Some(core);
+
+  // The remote changes go through the workers like the requests of the client.
+  if let Some(mut remote_changes) = remote_changes {
+    runtime.spawn(async move {
+      while let Some(message) = remote_changes.recv().await {
+        DART_APPFLOWY_CORE.dispatch(message.into_request(), Completion::Discard);
+      }
+    });
+  }
   0
 }
 
+/// Decodes the [FFIRequest] the caller passed, reading its payload from the handoff directory if
+/// it was handed off.
+fn decode_request(input: *const u8, len: usize) -> Result<AFPluginRequest, String> {
+  let mut request = FFIRequest::from_u8_pointer(input, len)?;
+  request.take_payload_file(DART_APPFLOWY_CORE.handoff().as_deref())?;
+  Ok(request.into())
+}
+
 #[no_mangle]
 #[allow(clippy::let_underscore_future)]
 pub extern "C" fn async_event(port: i64, input: *const u8, len: usize) {
-  let request: AFPluginRequest = FFIRequest::from_u8_pointer(input, len).into();
+  let request = match decode_request(input, len) {
+    Ok(request) => request,
+    Err(err) => {
+      error!("[FFI]: {}", err);
+      return complete(FFIResponse::protocol_error(&err), Completion::Port(port));
+    },
+  };
   #[cfg(feature = "sync_verbose_log")]
   trace!(
     "[FFI]: {} Async Event: {:?} with {} port",
@@ -170,56 +345,113 @@ pub extern "C" fn async_event(port: i64, input: *const u8, len: usize) {
     port
   );
 
-  DART_APPFLOWY_CORE.dispatch(request, port, None);
+  DART_APPFLOWY_CORE.dispatch(request, Completion::Port(port));
 }
 
-/// A persistent future that processes [Arbiter] commands.
-struct Runner {
-  rx: mpsc::UnboundedReceiver<Task>,
+/// Same as [async_event], but the response is passed to `callback` instead of being posted to a
+/// Dart port. It lets the callers that are not Dart isolates, like native tests or other
+/// bindings, use the dispatcher.
+#[no_mangle]
+pub extern "C" fn async_event_with_callback(
+  input: *const u8,
+  len: usize,
+  callback: CompletionCallback,
+  context: i64,
+) {
+  let request = match decode_request(input, len) {
+    Ok(request) => request,
+    Err(err) => {
+      error!("[FFI]: {}", err);
+      let completion = Completion::Callback { callback, context };
+      return complete(FFIResponse::protocol_error(&err), completion);
+    },
+  };
+  #[cfg(feature = "sync_verbose_log")]
+  trace!(
+    "[FFI]: {} Async Event: {:?} with callback",
+    &request.id,
+    &request.event,
+  );
+
+  DART_APPFLOWY_CORE.dispatch(request, Completion::Callback { callback, context });
 }
 
-impl Future for Runner {
-  type Output = ();
-
-  fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-    loop {
-      match ready!(self.rx.poll_recv(cx)) {
-        None => return Poll::Ready(()),
-        Some(task) => {
-          let Task {
-            dispatcher,
-            request,
-            port,
-            ret,
-          } = task;
-
-          tokio::task::spawn_local(async move {
-            let resp = AFPluginDispatcher::boxed_async_send_with_callback(
-              dispatcher.as_ref(),
-              request,
-              move |resp: AFPluginEventResponse| {
-                #[cfg(feature = "sync_verbose_log")]
-                trace!("[FFI]: Post data to dart through {} port", port);
-                Box::pin(post_to_flutter(resp, port))
-              },
-            )
-            .await;
-
-            if let Some(ret) = ret {
-              let _ = ret.send(resp).await;
-            }
-          });
-        },
-      }
+/// Same as [async_event] for several requests encoded as a [FFIRequestBatch]. `ports` points to
+/// `ports_len` ports, one per request in the same order, that receive their responses. The
+/// requests are queued at once, which saves the wakeups of the dispatcher during a burst of
+/// events. All the ports receive an error if the batch is rejected.
+#[no_mangle]
+pub extern "C" fn async_event_batch(
+  ports: *const i64,
+  ports_len: usize,
+  input: *const u8,
+  len: usize,
+) {
+  if ports.is_null() {
+    error!("[FFI]: The ports of the request batch are a null pointer");
+    return;
+  }
+  let ports = unsafe { std::slice::from_raw_parts(ports, ports_len) };
+  let batch = match FFIRequestBatch::from_u8_pointer(input, len) {
+    Ok(batch) if batch.requests.len() == ports.len() => batch,
+    Ok(batch) => {
+      let err = format!(
+        "The request batch has {} requests for {} ports",
+        batch.requests.len(),
+        ports.len()
+      );
+      return reject_batch(&err, ports);
+    },
+    Err(err) => return reject_batch(&err, ports),
+  };
+  let handoff = DART_APPFLOWY_CORE.handoff();
+  let mut requests = Vec::with_capacity(batch.requests.len());
+  for (mut request, port) in batch.requests.into_iter().zip(ports) {
+    match request.take_payload_file(handoff.as_deref()) {
+      Ok(()) => requests.push((request.into(), Completion::Port(*port))),
+      Err(err) => {
+        error!("[FFI]: {}", err);
+        complete(FFIResponse::protocol_error(&err), Completion::Port(*port));
+      },
     }
   }
+  DART_APPFLOWY_CORE.dispatch_batch(requests);
+}
+
+fn reject_batch(err: &str, ports: &[i64]) {
+  error!("[FFI]: {}", err);
+  for port in ports {
+    complete(FFIResponse::protocol_error(err), Completion::Port(*port));
+  }
 }
 
+/// Dispatches the request and blocks until its response is ready. The response is encoded as a
+/// [FFIResponse] prefixed with its length on four big endian bytes. It must not be called from
+/// the dispatcher's runtime.
 #[no_mangle]
-pub extern "C" fn sync_event(_input: *const u8, _len: usize) -> *const u8 {
-  error!("unimplemented sync_event");
+pub extern "C" fn sync_event(input: *const u8, len: usize) -> *const u8 {
+  let mut response = match decode_request(input, len) {
+    Ok(request) => {
+      #[cfg(feature = "sync_verbose_log")]
+      trace!("[FFI]: {} Sync Event: {:?}", &request.id, &request.event);
+
+      let (tx, mut rx) = mpsc::channel(1);
+      DART_APPFLOWY_CORE.dispatch(request, Completion::Return(tx));
+      rx.blocking_recv().unwrap_or_else(|| {
+        error!("[FFI]: sync_event dropped before completion");
+        FFIResponse::internal_error("The request was dropped before completion")
+      })
+    },
+    Err(err) => {
+      error!("[FFI]: {}", err);
+      FFIResponse::protocol_error(&err)
+    },
+  };
+  if let Some(handoff) = DART_APPFLOWY_CORE.handoff() {
+    response.hand_off(&handoff);
+  }
 
-  let response_bytes = vec![];
+  let response_bytes = response.into_bytes().unwrap_or_default().to_vec();
   let result = extend_front_four_bytes_into_bytes(&response_bytes);
   forget_rust(result)
 }
@@ -227,10 +459,75 @@ pub extern "C" fn sync_event(_input: *const u8, _len: usize) -> *const u8 {
 #[no_mangle]
 pub extern "C" fn set_stream_port(notification_port: i64) -> i32 {
   unregister_all_notification_sender();
-  register_notification_sender(DartNotificationSender::new(notification_port));
+  register_buffered_subscriber(
+    DART_NOTIFICATION_SUBSCRIBER,
+    DartNotificationSender::new(notification_port),
+  );
+  register_notification_sender(PortNotificationSender);
+  PORT_SENDER_REGISTERED.store(true, Ordering::SeqCst);
+  0
+}
+
+/// Registers a port that receives the notifications as framed payloads. See
+/// [NotificationPorts](crate::notification::NotificationPorts).
+/// `capacity` is the number of frames the port can receive before acknowledging them.
+#[no_mangle]
+pub extern "C" fn register_notification_port(port: i64, capacity: u32) -> i32 {
+  if !PORT_SENDER_REGISTERED.swap(true, Ordering::SeqCst) {
+    register_notification_sender(PortNotificationSender);
+  }
+  NOTIFICATION_PORTS.register(port, capacity as usize);
   0
 }
 
+#[no_mangle]
+pub extern "C" fn unregister_notification_port(port: i64) -> i32 {
+  port_result(NOTIFICATION_PORTS.unregister(port))
+}
+
+/// Restricts the port to the notifications of `source`. It can be called multiple times to
+/// subscribe to several sources.
+#[no_mangle]
+pub extern "C" fn subscribe_notification_port(port: i64, source: *const c_char) -> i32 {
+  match c_str_to_string(source) {
+    Some(source) => port_result(NOTIFICATION_PORTS.subscribe(port, &source)),
+    None => -1,
+  }
+}
+
+#[no_mangle]
+pub extern "C" fn unsubscribe_notification_port(port: i64, source: *const c_char) -> i32 {
+  match c_str_to_string(source) {
+    Some(source) => port_result(NOTIFICATION_PORTS.unsubscribe(port, &source)),
+    None => -1,
+  }
+}
+
+/// Called by Dart once it processed `count` frames, allowing the port to receive more.
+#[no_mangle]
+pub extern "C" fn ack_notification_port(port: i64, count: u32) -> i32 {
+  port_result(NOTIFICATION_PORTS.ack(port, count as usize))
+}
+
+fn port_result(found: bool) -> i32 {
+  if found {
+    0
+  } else {
+    warn!("[Notification]: unknown notification port");
+    -1
+  }
+}
+
+fn c_str_to_string(data: *const c_char) -> Option<String> {
+  if data.is_null() {
+    return None;
+  }
+  unsafe { CStr::from_ptr(data) }
+    .to_str()
+    .ok()
+    .map(|s| s.to_owned())
+}
+
 #[no_mangle]
 pub extern "C" fn set_log_stream_port(port: i64) -> i32 {
   *LOG_STREAM_ISOLATE.write().unwrap() = Some(Isolate::new(port));
@@ -242,16 +539,9 @@ pub extern "C" fn set_log_stream_port(port: i64) -> i32 {
 pub extern "C" fn link_me_please() {}
 
 #[inline(always)]
-#[allow(clippy::blocks_in_conditions)]
-async fn post_to_flutter(response: AFPluginEventResponse, port: i64) {
+async fn post_to_flutter(response: AFPluginEventResponse, port: i64, encoder: ResponseEncoder) {
   let isolate = allo_isolate::Isolate::new(port);
-  match isolate
-    .catch_unwind(async {
-      let ffi_resp = FFIResponse::from(response);
-      ffi_resp.into_bytes().unwrap().to_vec()
-    })
-    .await
-  {
+  match isolate.catch_unwind(encoder.encode(response)).await {
     Ok(_) => {
       #[cfg(feature = "sync_verbose_log")]
       trace!("[FFI]: Post data to dart success");
@@ -3,6 +3,8 @@ use flowy_derive::ProtoBuf;
 use lib_dispatch::prelude::AFPluginRequest;
 use std::convert::TryFrom;
 
+use crate::handoff::PayloadHandoff;
+
 #[derive(Default, ProtoBuf)]
 pub struct FFIRequest {
   #[pb(index = 1)]
@@ -10,19 +12,73 @@ pub struct FFIRequest {
 
   #[pb(index = 2)]
   pub(crate) payload: Vec<u8>,
+
+  /// The contract version of the payload, sent by the clients that declare it.
+  #[pb(index = 3, one_of)]
+  pub(crate) version: Option<u32>,
+
+  /// The id of the request, chosen by the client to match the progress notifications of the
+  /// request. A random id is used otherwise.
+  #[pb(index = 4, one_of)]
+  pub(crate) request_id: Option<String>,
+
+  /// The file the payload was written to instead of `payload`, when it's too large to be copied
+  /// through the FFI call. See [PayloadHandoff].
+  #[pb(index = 5, one_of)]
+  pub(crate) payload_file: Option<String>,
 }
 
 impl FFIRequest {
-  pub fn from_u8_pointer(pointer: *const u8, len: usize) -> Self {
+  /// Decodes the request, or returns why the bytes are not a valid request.
+  pub fn from_u8_pointer(pointer: *const u8, len: usize) -> Result<Self, String> {
+    if pointer.is_null() {
+      return Err("The request is a null pointer".to_owned());
+    }
+    let buffer = unsafe { std::slice::from_raw_parts(pointer, len) }.to_vec();
+    let bytes = Bytes::from(buffer);
+    FFIRequest::try_from(bytes).map_err(|e| format!("Invalid request: {:?}", e))
+  }
+
+  /// Reads the payload handed over in a file, if any.
+  pub(crate) fn take_payload_file(
+    &mut self,
+    handoff: Option<&PayloadHandoff>,
+  ) -> Result<(), String> {
+    if let Some(file) = self.payload_file.take() {
+      let handoff = handoff.ok_or_else(|| "The payload handoff is not enabled".to_owned())?;
+      self.payload = handoff.take(&file)?;
+    }
+    Ok(())
+  }
+}
+
+/// The requests sent at once with `async_event_batch`, e.g. during a burst of keystrokes.
+#[derive(Default, ProtoBuf)]
+pub struct FFIRequestBatch {
+  #[pb(index = 1)]
+  pub(crate) requests: Vec<FFIRequest>,
+}
+
+impl FFIRequestBatch {
+  pub fn from_u8_pointer(pointer: *const u8, len: usize) -> Result<Self, String> {
+    if pointer.is_null() {
+      return Err("The request batch is a null pointer".to_owned());
+    }
     let buffer = unsafe { std::slice::from_raw_parts(pointer, len) }.to_vec();
     let bytes = Bytes::from(buffer);
-    let request: FFIRequest = FFIRequest::try_from(bytes).unwrap();
-    request
+    FFIRequestBatch::try_from(bytes).map_err(|e| format!("Invalid request batch: {:?}", e))
   }
 }
 
 impl std::convert::From<FFIRequest> for AFPluginRequest {
   fn from(ffi_request: FFIRequest) -> Self {
-    AFPluginRequest::new(ffi_request.event).payload(ffi_request.payload)
+    let mut request = AFPluginRequest::new(ffi_request.event).payload(ffi_request.payload);
+    if let Some(request_id) = ffi_request.request_id {
+      request.id = request_id;
+    }
+    match ffi_request.version {
+      Some(version) => request.version(version),
+      None => request,
+    }
   }
 }
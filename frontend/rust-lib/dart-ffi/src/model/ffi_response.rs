@@ -1,5 +1,8 @@
 use flowy_derive::{ProtoBuf, ProtoBuf_Enum};
-use lib_dispatch::prelude::{AFPluginEventResponse, Payload, StatusCode};
+use lib_dispatch::prelude::{AFPluginEventResponse, ErrorOrigin, Payload, StatusCode};
+use tracing::warn;
+
+use crate::handoff::PayloadHandoff;
 
 #[derive(ProtoBuf_Enum, Clone, Copy, Default)]
 pub enum FFIStatusCode {
@@ -9,6 +12,35 @@ pub enum FFIStatusCode {
   Internal = 2,
 }
 
+/// Where a failed request failed, so the clients can handle each kind of failure differently.
+#[derive(ProtoBuf_Enum, Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum FFIErrorCategory {
+  /// The handler returned an error. The payload of the response is the `FlowyError`.
+  #[default]
+  Handler = 0,
+  /// The request could not be decoded from the bytes passed to the FFI.
+  Protocol = 1,
+  /// The dispatcher could not deliver the request to a handler: the event is unknown, its version
+  /// is not supported or its payload could not be decoded.
+  Dispatch = 2,
+  /// The core failed while handling the request: it was not initialized, the handler panicked or
+  /// the response was lost.
+  Internal = 3,
+  /// The request was forwarded to a remote core and the transport failed. It may be retried.
+  Transport = 4,
+}
+
+#[derive(ProtoBuf, Default, Debug)]
+pub struct FFIError {
+  #[pb(index = 1)]
+  pub category: FFIErrorCategory,
+
+  /// Describes the failure. Empty for the [FFIErrorCategory::Handler] errors, which are described
+  /// by the payload.
+  #[pb(index = 2)]
+  pub message: String,
+}
+
 #[derive(ProtoBuf, Default)]
 pub struct FFIResponse {
   #[pb(index = 1)]
@@ -16,6 +48,52 @@ pub struct FFIResponse {
 
   #[pb(index = 2)]
   code: FFIStatusCode,
+
+  /// Set when the request failed.
+  #[pb(index = 3, one_of)]
+  error: Option<FFIError>,
+
+  /// The file the payload was written to instead of `payload`, see [PayloadHandoff]. The client
+  /// removes it once read.
+  #[pb(index = 4, one_of)]
+  payload_file: Option<String>,
+}
+
+impl FFIResponse {
+  pub fn internal_error(message: &str) -> Self {
+    Self::error(FFIStatusCode::Internal, FFIErrorCategory::Internal, message)
+  }
+
+  pub fn protocol_error(message: &str) -> Self {
+    Self::error(FFIStatusCode::Err, FFIErrorCategory::Protocol, message)
+  }
+
+  /// Moves the payload to a file if it reaches the threshold of `handoff`. The payload stays
+  /// inline if the file can't be written.
+  pub(crate) fn hand_off(&mut self, handoff: &PayloadHandoff) {
+    if !handoff.should_hand_off(&self.payload) {
+      return;
+    }
+    match handoff.write(&self.payload) {
+      Ok(file) => {
+        self.payload = vec![];
+        self.payload_file = Some(file);
+      },
+      Err(err) => warn!("[FFI]: Failed to hand the payload off: {}", err),
+    }
+  }
+
+  fn error(code: FFIStatusCode, category: FFIErrorCategory, message: &str) -> Self {
+    FFIResponse {
+      payload: message.as_bytes().to_vec(),
+      code,
+      error: Some(FFIError {
+        category,
+        message: message.to_owned(),
+      }),
+      payload_file: None,
+    }
+  }
 }
 
 impl std::convert::From<AFPluginEventResponse> for FFIResponse {
@@ -30,11 +108,27 @@ impl std::convert::From<AFPluginEventResponse> for FFIResponse {
       StatusCode::Err => FFIStatusCode::Err,
     };
 
-    // let msg = match resp.error {
-    //     None => "".to_owned(),
-    //     Some(e) => format!("{:?}", e),
-    // };
+    let error = match (&resp.status_code, resp.error_origin) {
+      (StatusCode::Ok, _) => None,
+      (StatusCode::Err, None) => Some(FFIError {
+        category: FFIErrorCategory::Handler,
+        message: String::new(),
+      }),
+      (StatusCode::Err, Some(origin)) => Some(FFIError {
+        category: match origin {
+          ErrorOrigin::Dispatcher => FFIErrorCategory::Dispatch,
+          ErrorOrigin::Internal => FFIErrorCategory::Internal,
+          ErrorOrigin::Transport => FFIErrorCategory::Transport,
+        },
+        message: String::from_utf8_lossy(&payload).into_owned(),
+      }),
+    };
 
-    FFIResponse { payload, code }
+    FFIResponse {
+      payload,
+      code,
+      error,
+      payload_file: None,
+    }
   }
 }
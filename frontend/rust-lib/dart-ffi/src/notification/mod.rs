@@ -1,3 +1,5 @@
+mod port;
 mod sender;
 
+pub use port::*;
 pub use sender::*;
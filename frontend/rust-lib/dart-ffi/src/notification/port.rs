@@ -0,0 +1,167 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::TryInto;
+use std::sync::{Mutex, RwLock};
+
+use allo_isolate::Isolate;
+use bytes::Bytes;
+use flowy_notification::entities::SubscribeObject;
+use flowy_notification::NotificationSender;
+use lazy_static::lazy_static;
+use tracing::{error, warn};
+
+use crate::c::extend_front_four_bytes_into_bytes;
+
+lazy_static! {
+  pub(crate) static ref NOTIFICATION_PORTS: NotificationPorts = NotificationPorts::default();
+}
+
+/// The notification ports handed over by Dart.
+///
+/// Every post contains one or more frames, each frame being a [SubscribeObject] prefixed with its
+/// length on four big endian bytes. A port receives at most `capacity` frames that it hasn't
+/// acknowledged yet. The frames sent while the port has no credit left are kept until the port
+/// acknowledges the previous ones, and the oldest ones are dropped when more than `capacity`
+/// frames are waiting.
+#[derive(Default)]
+pub struct NotificationPorts {
+  ports: RwLock<HashMap<i64, Mutex<NotificationPort>>>,
+}
+
+struct NotificationPort {
+  isolate: Isolate,
+  /// The sources the port subscribed to. The port receives every notification when it's empty.
+  sources: HashSet<String>,
+  capacity: usize,
+  credits: usize,
+  pending: VecDeque<Vec<u8>>,
+  dropped: u64,
+}
+
+impl NotificationPort {
+  fn accept(&self, source: &str) -> bool {
+    self.sources.is_empty() || self.sources.contains(source)
+  }
+
+  fn push(&mut self, port: i64, frame: Vec<u8>) {
+    if self.credits > 0 && self.pending.is_empty() {
+      self.credits -= 1;
+      self.isolate.post(frame);
+      return;
+    }
+
+    self.pending.push_back(frame);
+    if self.pending.len() > self.capacity {
+      self.pending.pop_front();
+      self.dropped += 1;
+      warn!(
+        "[Notification]: port {} is too slow, {} notifications dropped",
+        port, self.dropped
+      );
+    }
+  }
+
+  /// Posts as many pending frames as the credits allow, in a single post.
+  fn flush(&mut self) {
+    let mut batch = vec![];
+    while self.credits > 0 {
+      match self.pending.pop_front() {
+        None => break,
+        Some(frame) => {
+          self.credits -= 1;
+          batch.extend(frame);
+        },
+      }
+    }
+    if !batch.is_empty() {
+      self.isolate.post(batch);
+    }
+  }
+}
+
+impl NotificationPorts {
+  pub fn register(&self, port: i64, capacity: usize) {
+    let capacity = capacity.max(1);
+    let notification_port = NotificationPort {
+      isolate: Isolate::new(port),
+      sources: HashSet::new(),
+      capacity,
+      credits: capacity,
+      pending: VecDeque::new(),
+      dropped: 0,
+    };
+    if let Ok(mut ports) = self.ports.write() {
+      ports.insert(port, Mutex::new(notification_port));
+    }
+  }
+
+  pub fn unregister(&self, port: i64) -> bool {
+    match self.ports.write() {
+      Ok(mut ports) => ports.remove(&port).is_some(),
+      Err(_) => false,
+    }
+  }
+
+  pub fn subscribe(&self, port: i64, source: &str) -> bool {
+    self.with_port(port, |p| {
+      p.sources.insert(source.to_owned());
+    })
+  }
+
+  pub fn unsubscribe(&self, port: i64, source: &str) -> bool {
+    self.with_port(port, |p| {
+      p.sources.remove(source);
+    })
+  }
+
+  /// Gives `count` credits back to the port, once Dart processed that many frames.
+  pub fn ack(&self, port: i64, count: usize) -> bool {
+    self.with_port(port, |p| {
+      p.credits = (p.credits + count).min(p.capacity);
+      p.flush();
+    })
+  }
+
+  fn send(&self, source: &str, frame: &[u8]) {
+    let ports = match self.ports.read() {
+      Ok(ports) => ports,
+      Err(err) => {
+        error!("[Notification]: failed to read the ports: {:?}", err);
+        return;
+      },
+    };
+    for (port, notification_port) in ports.iter() {
+      if let Ok(mut notification_port) = notification_port.lock() {
+        if notification_port.accept(source) {
+          notification_port.push(*port, frame.to_vec());
+        }
+      }
+    }
+  }
+
+  fn with_port(&self, port: i64, f: impl FnOnce(&mut NotificationPort)) -> bool {
+    let ports = match self.ports.read() {
+      Ok(ports) => ports,
+      Err(_) => return false,
+    };
+    match ports.get(&port).and_then(|p| p.lock().ok()) {
+      None => false,
+      Some(mut notification_port) => {
+        f(&mut notification_port);
+        true
+      },
+    }
+  }
+}
+
+/// Forwards the notifications to the [NOTIFICATION_PORTS].
+pub struct PortNotificationSender;
+
+impl NotificationSender for PortNotificationSender {
+  fn send_subject(&self, subject: SubscribeObject) -> Result<(), String> {
+    let source = subject.source.clone();
+    let bytes: Bytes = subject.try_into().map_err(|e| format!("{:?}", e))?;
+    let frame = extend_front_four_bytes_into_bytes(&bytes);
+    NOTIFICATION_PORTS.send(&source, &frame);
+    Ok(())
+  }
+}
@@ -19,7 +19,12 @@ impl DartNotificationSender {
 impl NotificationSender for DartNotificationSender {
   fn send_subject(&self, subject: SubscribeObject) -> Result<(), String> {
     let bytes: Bytes = subject.try_into().unwrap();
-    self.isolate.post(bytes.to_vec());
-    Ok(())
+    // The port is closed while the isolate restarts, the notification is buffered until the
+    // isolate registers again.
+    if self.isolate.post(bytes.to_vec()) {
+      Ok(())
+    } else {
+      Err("The Dart port is closed".to_string())
+    }
   }
 }
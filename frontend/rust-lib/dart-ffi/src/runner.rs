@@ -0,0 +1,200 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::thread::JoinHandle;
+
+use futures::ready;
+use tokio::sync::{mpsc, Semaphore};
+use tokio::task::LocalSet;
+use tracing::error;
+
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+
+use crate::encoder::ResponseEncoder;
+use crate::model::FFIResponse;
+use crate::{post_to_flutter, Completion, Task};
+
+/// The number of batches a worker takes from its channel each time it wakes up. The batches
+/// sent while it was handling the previous ones are drained at once instead of waking it up
+/// for each of them.
+const MAX_BATCHES_PER_WAKE: usize = 64;
+
+/// The number of requests of a plugin that are handled at the same time. The next ones wait
+/// for one of them to finish.
+pub(crate) const MAX_IN_FLIGHT_PER_PLUGIN: usize = 32;
+
+/// Spreads the tasks over several worker threads, each driving its own `LocalSet` on the shared
+/// runtime, so the requests of independent plugins are handled in parallel.
+///
+/// All the requests of a plugin go to the same worker, so they start in the order they were
+/// dispatched, like they did when a single thread handled all of them.
+pub(crate) struct TaskRouter {
+  workers: Vec<mpsc::UnboundedSender<Vec<Task>>>,
+}
+
+impl TaskRouter {
+  /// Starts one worker per worker thread of `runtime`, encoding the responses with `encoder`.
+  /// The returned handles join once the router is dropped.
+  pub(crate) fn start(
+    runtime: Arc<AFPluginRuntime>,
+    encoder: ResponseEncoder,
+  ) -> (Self, Vec<JoinHandle<()>>) {
+    let num_workers = runtime.num_workers().max(1);
+    let mut workers = Vec::with_capacity(num_workers);
+    let mut handles = Vec::with_capacity(num_workers);
+    for index in 0..num_workers {
+      let (sender, rx) = mpsc::unbounded_channel::<Vec<Task>>();
+      let runtime = runtime.clone();
+      let encoder = encoder.clone();
+      let handle = std::thread::Builder::new()
+        .name(format!("dispatch-worker-{}", index))
+        .spawn(move || {
+          let local_set = LocalSet::new();
+          runtime.block_on(local_set.run_until(Runner::new(rx, encoder)));
+        })
+        .expect("Failed to spawn the dispatch worker");
+      workers.push(sender);
+      handles.push(handle);
+    }
+    (Self { workers }, handles)
+  }
+
+  /// Hands `task` to the worker of its plugin. Returns the task if the worker stopped.
+  pub(crate) fn send(&self, task: Task) -> Result<(), Task> {
+    let index = self.worker_index(&task);
+    self.workers[index]
+      .send(vec![task])
+      .map_err(|e| e.0.into_iter().next().unwrap())
+  }
+
+  /// Hands the tasks to the workers of their plugins, with a single message per worker. Returns
+  /// the tasks whose worker stopped.
+  pub(crate) fn send_batch(&self, tasks: Vec<Task>) -> Result<(), Vec<Task>> {
+    let mut batches: Vec<Vec<Task>> = self.workers.iter().map(|_| vec![]).collect();
+    for task in tasks {
+      let index = self.worker_index(&task);
+      batches[index].push(task);
+    }
+    let mut failed = vec![];
+    for (worker, batch) in self.workers.iter().zip(batches) {
+      if batch.is_empty() {
+        continue;
+      }
+      if let Err(e) = worker.send(batch) {
+        failed.extend(e.0);
+      }
+    }
+    if failed.is_empty() {
+      Ok(())
+    } else {
+      Err(failed)
+    }
+  }
+
+  fn worker_index(&self, task: &Task) -> usize {
+    // The events without a plugin fail right away, any worker does.
+    let key = task
+      .dispatcher
+      .plugin_name(&task.request.event)
+      .unwrap_or_default();
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    (hasher.finish() % self.workers.len() as u64) as usize
+  }
+}
+
+/// A persistent future that processes the tasks routed to a worker.
+struct Runner {
+  rx: mpsc::UnboundedReceiver<Vec<Task>>,
+  batches: Vec<Vec<Task>>,
+  limits: HashMap<String, Arc<Semaphore>>,
+  encoder: ResponseEncoder,
+}
+
+impl Runner {
+  fn new(rx: mpsc::UnboundedReceiver<Vec<Task>>, encoder: ResponseEncoder) -> Self {
+    Self {
+      rx,
+      batches: Vec::with_capacity(MAX_BATCHES_PER_WAKE),
+      limits: HashMap::new(),
+      encoder,
+    }
+  }
+}
+
+impl Future for Runner {
+  type Output = ();
+
+  fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    loop {
+      let this = &mut *self;
+      if ready!(this
+        .rx
+        .poll_recv_many(cx, &mut this.batches, MAX_BATCHES_PER_WAKE))
+        == 0
+      {
+        return Poll::Ready(());
+      }
+      for task in this.batches.drain(..).flatten() {
+        spawn_task(&mut this.limits, this.encoder.clone(), task);
+      }
+    }
+  }
+}
+
+fn spawn_task(limits: &mut HashMap<String, Arc<Semaphore>>, encoder: ResponseEncoder, task: Task) {
+  let plugin = task
+    .dispatcher
+    .plugin_name(&task.request.event)
+    .unwrap_or_default();
+  let limit = limits
+    .entry(plugin)
+    .or_insert_with(|| Arc::new(Semaphore::new(MAX_IN_FLIGHT_PER_PLUGIN)))
+    .clone();
+  let Task {
+    dispatcher,
+    request,
+    completion,
+  } = task;
+
+  tokio::task::spawn_local(async move {
+    let _permit = match limit.acquire_owned().await {
+      Ok(permit) => permit,
+      Err(err) => {
+        error!("[FFI]: {}", err);
+        return;
+      },
+    };
+    match completion {
+      Completion::Port(port) => {
+        AFPluginDispatcher::boxed_async_send_with_callback(
+          dispatcher.as_ref(),
+          request,
+          move |resp: AFPluginEventResponse| {
+            #[cfg(feature = "sync_verbose_log")]
+            tracing::trace!("[FFI]: Post data to dart through {} port", port);
+            Box::pin(post_to_flutter(resp, port, encoder))
+          },
+        )
+        .await;
+      },
+      Completion::Callback { callback, context } => {
+        let resp = AFPluginDispatcher::async_send(dispatcher.as_ref(), request).await;
+        let bytes = encoder.encode(resp).await;
+        callback(context, bytes.as_ptr(), bytes.len());
+      },
+      Completion::Return(ret) => {
+        let resp = AFPluginDispatcher::async_send(dispatcher.as_ref(), request).await;
+        let _ = ret.send(FFIResponse::from(resp)).await;
+      },
+      Completion::Discard => {
+        AFPluginDispatcher::async_send(dispatcher.as_ref(), request).await;
+      },
+    }
+  });
+}
@@ -0,0 +1,246 @@
+use std::convert::TryFrom;
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use std::time::Duration;
+
+use flowy_notification::entities::SubscribeObject;
+use flowy_user::errors::{ErrorCode, FlowyError};
+use lib_dispatch::prelude::*;
+use tokio::sync::broadcast::error::TryRecvError;
+use tokio::sync::broadcast::Receiver;
+use tokio::task::LocalSet;
+
+use crate::EventIntegrationTest;
+
+/// How long [EventTester::wait_for_notification] waits before failing.
+pub const NOTIFICATION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Sends an event to the core and asserts on its response and on the notifications the core
+/// posted since the tester was created.
+///
+/// ```ignore
+/// let view = EventTester::new(test.clone())
+///   .event(FolderEvent::CreateView)
+///   .payload(payload)
+///   .async_send()
+///   .await
+///   .assert_ok()
+///   .wait_for_notification(&workspace_id, FolderNotification::DidUpdateWorkspaceViews)
+///   .await
+///   .parse::<ViewPB>();
+/// ```
+pub struct EventTester {
+  sdk: EventIntegrationTest,
+  request: Option<AFPluginRequest>,
+  response: Option<AFPluginEventResponse>,
+  receiver: Receiver<SubscribeObject>,
+  notifications: Vec<SubscribeObject>,
+  local_set: LocalSet,
+}
+
+impl EventTester {
+  pub fn new(sdk: EventIntegrationTest) -> Self {
+    let receiver = sdk.notification_sender.subscribe_all();
+    Self {
+      sdk,
+      request: None,
+      response: None,
+      receiver,
+      notifications: vec![],
+      local_set: Default::default(),
+    }
+  }
+
+  pub fn event<Event>(mut self, event: Event) -> Self
+  where
+    Event: Eq + Hash + Debug + Clone + Display,
+  {
+    self.request = Some(AFPluginRequest::new(event));
+    self
+  }
+
+  pub fn payload<P>(mut self, payload: P) -> Self
+  where
+    P: ToBytes,
+  {
+    let bytes = payload
+      .into_bytes()
+      .unwrap_or_else(|e| panic!("Encode {} failed: {:?}", std::any::type_name::<P>(), e));
+    self.request = Some(self.take_request().payload(bytes));
+    self
+  }
+
+  pub async fn async_send(mut self) -> Self {
+    let request = self.take_request();
+    let dispatcher = self.sdk.dispatcher();
+    let response = self
+      .local_set
+      .run_until(AFPluginDispatcher::async_send(dispatcher.as_ref(), request))
+      .await;
+    self.response = Some(response);
+    self
+  }
+
+  #[track_caller]
+  pub fn assert_ok(self) -> Self {
+    let response = self.response();
+    if response.status_code != StatusCode::Ok {
+      panic!("Expected an Ok response, got: {:?}", self.error());
+    }
+    self
+  }
+
+  #[track_caller]
+  pub fn assert_err(self) -> Self {
+    assert_eq!(
+      self.response().status_code,
+      StatusCode::Err,
+      "Expected an Err response"
+    );
+    self
+  }
+
+  /// Asserts that the handler returned a [FlowyError] with `code`.
+  #[track_caller]
+  pub fn assert_error_code(self, code: ErrorCode) -> Self {
+    let tester = self.assert_err();
+    match tester.error() {
+      Some(error) => {
+        assert_eq!(error.code, code, "Unexpected error: {:?}", error);
+        tester
+      },
+      None => panic!("Expected a FlowyError with code {:?}", code),
+    }
+  }
+
+  /// Asserts that the core posted a notification of type `ty` for `id`.
+  #[track_caller]
+  pub fn assert_notification(mut self, id: &str, ty: impl Into<i32>) -> Self {
+    let ty = ty.into();
+    if self.notifications(id, ty).is_empty() {
+      panic!("Expected a notification {} for {}", ty, id);
+    }
+    self
+  }
+
+  /// Asserts that the core did not post any notification of type `ty` for `id`.
+  #[track_caller]
+  pub fn assert_no_notification(mut self, id: &str, ty: impl Into<i32>) -> Self {
+    let ty = ty.into();
+    let notifications = self.notifications(id, ty);
+    assert!(
+      notifications.is_empty(),
+      "Unexpected notifications {} for {}: {:?}",
+      ty,
+      id,
+      notifications
+    );
+    self
+  }
+
+  /// Waits up to [NOTIFICATION_TIMEOUT] for the core to post a notification of type `ty` for
+  /// `id`, for the notifications that are posted after the response.
+  pub async fn wait_for_notification(mut self, id: &str, ty: impl Into<i32>) -> Self {
+    let ty = ty.into();
+    if !self.notifications(id, ty).is_empty() {
+      return self;
+    }
+    let received = tokio::time::timeout(NOTIFICATION_TIMEOUT, async {
+      while let Ok(notification) = self.receiver.recv().await {
+        let is_expected = notification.id == id && notification.ty == ty;
+        self.notifications.push(notification);
+        if is_expected {
+          return true;
+        }
+      }
+      false
+    })
+    .await;
+    if !matches!(received, Ok(true)) {
+      panic!("Timeout waiting for the notification {} for {}", ty, id);
+    }
+    self
+  }
+
+  /// The notifications of type `ty` for `id` posted so far.
+  pub fn notifications(&mut self, id: &str, ty: impl Into<i32>) -> Vec<SubscribeObject> {
+    self.drain_notifications();
+    let ty = ty.into();
+    self
+      .notifications
+      .iter()
+      .filter(|notification| notification.id == id && notification.ty == ty)
+      .cloned()
+      .collect()
+  }
+
+  /// Parses the payload of the notification of type `ty` for `id` posted last.
+  #[track_caller]
+  pub fn parse_notification<T>(&mut self, id: &str, ty: impl Into<i32>) -> T
+  where
+    T: AFPluginFromBytes,
+  {
+    let ty = ty.into();
+    let payload = self
+      .notifications(id, ty)
+      .pop()
+      .and_then(|notification| notification.payload)
+      .unwrap_or_else(|| panic!("No notification {} with payload for {}", ty, id));
+    T::parse_from_bytes(payload.into())
+      .unwrap_or_else(|e| panic!("Parse {} failed: {:?}", std::any::type_name::<T>(), e))
+  }
+
+  #[track_caller]
+  pub fn parse<R>(self) -> R
+  where
+    R: AFPluginFromBytes,
+  {
+    self.parse_ref()
+  }
+
+  /// Like [EventTester::parse], without consuming the tester.
+  #[track_caller]
+  pub fn parse_ref<R>(&self) -> R
+  where
+    R: AFPluginFromBytes,
+  {
+    match self.response().clone().parse::<R, FlowyError>() {
+      Ok(Ok(data)) => data,
+      Ok(Err(e)) => panic!("Parser {:?} failed: {:?}", std::any::type_name::<R>(), e),
+      Err(e) => panic!("Parser {:?} failed: {:?}", std::any::type_name::<R>(), e),
+    }
+  }
+
+  /// The error returned by the handler, if the response is an error.
+  pub fn error(&self) -> Option<FlowyError> {
+    let response = self.response();
+    if response.status_code == StatusCode::Ok {
+      return None;
+    }
+    <AFPluginData<FlowyError>>::try_from(response.payload.clone())
+      .ok()
+      .map(|data| data.into_inner())
+  }
+
+  fn drain_notifications(&mut self) {
+    loop {
+      match self.receiver.try_recv() {
+        Ok(notification) => self.notifications.push(notification),
+        Err(TryRecvError::Lagged(count)) => {
+          tracing::warn!("EventTester missed {} notifications", count);
+        },
+        Err(TryRecvError::Empty) | Err(TryRecvError::Closed) => break,
+      }
+    }
+  }
+
+  #[track_caller]
+  pub fn response(&self) -> &AFPluginEventResponse {
+    self.response.as_ref().expect("must call async_send first")
+  }
+
+  #[track_caller]
+  fn take_request(&mut self) -> AFPluginRequest {
+    self.request.take().expect("must call event first")
+  }
+}
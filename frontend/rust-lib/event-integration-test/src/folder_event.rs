@@ -328,6 +328,22 @@ impl EventIntegrationTest {
       .items
   }
 
+  pub async fn export_workspace(
+    &self,
+    export_type: ExportTypePB,
+    target_path: &str,
+  ) -> Result<ExportWorkspaceResultPB, FlowyError> {
+    EventBuilder::new(self.clone())
+      .event(FolderEvent::ExportWorkspace)
+      .payload(ExportWorkspacePayloadPB {
+        export_type,
+        target_path: target_path.to_string(),
+      })
+      .async_send()
+      .await
+      .try_parse::<ExportWorkspaceResultPB>()
+  }
+
   pub async fn get_view_ancestors(&self, view_id: &str) -> Vec<ViewPB> {
     EventBuilder::new(self.clone())
       .event(FolderEvent::GetViewAncestors)
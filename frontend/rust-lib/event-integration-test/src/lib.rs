@@ -30,7 +30,9 @@ pub mod database_event;
 pub mod document;
 pub mod document_event;
 pub mod event_builder;
+pub mod event_tester;
 pub mod folder_event;
+pub mod scenario;
 pub mod user_event;
 
 #[derive(Clone)]
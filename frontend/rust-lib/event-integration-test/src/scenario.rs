@@ -0,0 +1,238 @@
+use std::any::{type_name, Any};
+use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+
+use flowy_user::errors::ErrorCode;
+use lib_dispatch::prelude::*;
+
+use crate::event_tester::EventTester;
+use crate::EventIntegrationTest;
+
+/// The values saved by the steps of a [Scenario], read by the steps that come after them.
+#[derive(Default)]
+pub struct ScenarioValues {
+  values: HashMap<String, Box<dyn Any>>,
+}
+
+impl ScenarioValues {
+  pub fn insert<T: 'static>(&mut self, key: &str, value: T) {
+    self.values.insert(key.to_owned(), Box::new(value));
+  }
+
+  /// The value saved under `key`. Panics if there is none or if it's not a `T`.
+  #[track_caller]
+  pub fn get<T: Clone + 'static>(&self, key: &str) -> T {
+    match self.values.get(key) {
+      Some(value) => value
+        .downcast_ref::<T>()
+        .unwrap_or_else(|| panic!("The scenario value {} is not a {}", key, type_name::<T>()))
+        .clone(),
+      None => panic!("No scenario value saved as {}", key),
+    }
+  }
+
+  pub fn contains(&self, key: &str) -> bool {
+    self.values.contains_key(key)
+  }
+}
+
+enum Expectation {
+  Ok,
+  Err,
+  ErrorCode(ErrorCode),
+}
+
+type BuildRequest = Box<dyn FnOnce(&ScenarioValues, EventTester) -> EventTester>;
+type NotificationKey = Box<dyn FnOnce(&ScenarioValues) -> (String, i32)>;
+type SaveValue = Box<dyn FnOnce(&EventTester, &mut ScenarioValues)>;
+
+struct ScenarioStep {
+  name: String,
+  request: BuildRequest,
+  expectation: Option<Expectation>,
+  notifications: Vec<NotificationKey>,
+  saves: Vec<SaveValue>,
+}
+
+/// Chains events that make up a feature, like sign in → create workspace → create document. Each
+/// step can save values parsed from its response, and the payloads of the next steps are built
+/// from them.
+///
+/// ```ignore
+/// let values = Scenario::new(test.clone())
+///   .value("workspace_id", workspace.id.clone())
+///   .step("create view", FolderEvent::CreateView, |values| CreateViewPayloadPB {
+///     parent_view_id: values.get("workspace_id"),
+///     ..
+///   })
+///   .expect_notification(
+///     |values| values.get("workspace_id"),
+///     FolderNotification::DidUpdateWorkspaceViews,
+///   )
+///   .save("view_id", |view: ViewPB| view.id)
+///   .step("read view", FolderEvent::GetView, |values| ViewIdPB {
+///     value: values.get("view_id"),
+///   })
+///   .run()
+///   .await;
+/// ```
+///
+/// The steps are expected to succeed unless one of the `expect_err` is called.
+pub struct Scenario {
+  sdk: EventIntegrationTest,
+  values: ScenarioValues,
+  steps: Vec<ScenarioStep>,
+}
+
+impl Scenario {
+  pub fn new(sdk: EventIntegrationTest) -> Self {
+    Self {
+      sdk,
+      values: ScenarioValues::default(),
+      steps: vec![],
+    }
+  }
+
+  /// Saves `value` under `key` before the first step runs.
+  pub fn value<T: 'static>(mut self, key: &str, value: T) -> Self {
+    self.values.insert(key, value);
+    self
+  }
+
+  /// Adds a step that sends `event` with the payload built by `payload`.
+  pub fn step<Event, P, F>(mut self, name: &str, event: Event, payload: F) -> Self
+  where
+    Event: Eq + Hash + Debug + Clone + Display + 'static,
+    P: ToBytes,
+    F: FnOnce(&ScenarioValues) -> P + 'static,
+  {
+    self.steps.push(ScenarioStep {
+      name: name.to_owned(),
+      request: Box::new(move |values, tester| tester.event(event).payload(payload(values))),
+      expectation: None,
+      notifications: vec![],
+      saves: vec![],
+    });
+    self
+  }
+
+  /// Adds a step that sends `event` without a payload.
+  pub fn step_without_payload<Event>(mut self, name: &str, event: Event) -> Self
+  where
+    Event: Eq + Hash + Debug + Clone + Display + 'static,
+  {
+    self.steps.push(ScenarioStep {
+      name: name.to_owned(),
+      request: Box::new(move |_, tester| tester.event(event)),
+      expectation: None,
+      notifications: vec![],
+      saves: vec![],
+    });
+    self
+  }
+
+  /// The last step is expected to fail.
+  #[track_caller]
+  pub fn expect_err(mut self) -> Self {
+    self.last_step().expectation = Some(Expectation::Err);
+    self
+  }
+
+  /// The last step is expected to fail with `code`.
+  #[track_caller]
+  pub fn expect_error_code(mut self, code: ErrorCode) -> Self {
+    self.last_step().expectation = Some(Expectation::ErrorCode(code));
+    self
+  }
+
+  /// The last step is expected to post a notification of type `ty` for the id returned by `id`.
+  /// The notification is awaited for up to
+  /// [NOTIFICATION_TIMEOUT](crate::event_tester::NOTIFICATION_TIMEOUT).
+  #[track_caller]
+  pub fn expect_notification<F>(mut self, id: F, ty: impl Into<i32>) -> Self
+  where
+    F: FnOnce(&ScenarioValues) -> String + 'static,
+  {
+    let ty = ty.into();
+    self
+      .last_step()
+      .notifications
+      .push(Box::new(move |values| (id(values), ty)));
+    self
+  }
+
+  /// Parses the response of the last step as `R` and saves the value returned by `f` under `key`.
+  #[track_caller]
+  pub fn save<R, T, F>(mut self, key: &str, f: F) -> Self
+  where
+    R: AFPluginFromBytes,
+    T: 'static,
+    F: FnOnce(R) -> T + 'static,
+  {
+    let key = key.to_owned();
+    self.last_step().saves.push(Box::new(move |tester, values| {
+      values.insert(&key, f(tester.parse_ref::<R>()))
+    }));
+    self
+  }
+
+  /// Runs the steps in order and returns the values they saved. Panics with the name of the step
+  /// whose expectations are not met.
+  pub async fn run(self) -> ScenarioValues {
+    let Scenario {
+      sdk,
+      mut values,
+      steps,
+    } = self;
+    for (index, step) in steps.into_iter().enumerate() {
+      tracing::debug!("[scenario]: step {} {}", index, step.name);
+      let tester = EventTester::new(sdk.clone());
+      let mut tester = (step.request)(&values, tester).async_send().await;
+      check_expectation(&step.name, &tester, step.expectation);
+      for notification in step.notifications {
+        let (id, ty) = notification(&values);
+        tester = tester.wait_for_notification(&id, ty).await;
+      }
+      for save in step.saves {
+        save(&tester, &mut values);
+      }
+    }
+    values
+  }
+
+  #[track_caller]
+  fn last_step(&mut self) -> &mut ScenarioStep {
+    self
+      .steps
+      .last_mut()
+      .expect("must add a step to the scenario first")
+  }
+}
+
+fn check_expectation(name: &str, tester: &EventTester, expectation: Option<Expectation>) {
+  let status_code = tester.response().status_code.clone();
+  match expectation.unwrap_or(Expectation::Ok) {
+    Expectation::Ok => {
+      if status_code != StatusCode::Ok {
+        panic!(
+          "Step `{}` expected an Ok response, got: {:?}",
+          name,
+          tester.error()
+        );
+      }
+    },
+    Expectation::Err => {
+      if status_code != StatusCode::Err {
+        panic!("Step `{}` expected an Err response", name);
+      }
+    },
+    Expectation::ErrorCode(code) => match tester.error() {
+      Some(error) if error.code == code => {},
+      error => panic!(
+        "Step `{}` expected an error with code {:?}, got: {:?}",
+        name, code, error
+      ),
+    },
+  }
+}
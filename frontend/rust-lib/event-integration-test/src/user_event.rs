@@ -18,9 +18,9 @@ use flowy_server_pub::af_cloud_config::AFCloudConfiguration;
 use flowy_server_pub::AuthenticatorType;
 use flowy_user::entities::{
   AuthenticatorPB, ChangeWorkspaceIconPB, CloudSettingPB, CreateWorkspacePB, ImportAppFlowyDataPB,
-  OauthSignInPB, RenameWorkspacePB, RepeatedUserWorkspacePB, SignInUrlPB, SignInUrlPayloadPB,
-  SignUpPayloadPB, UpdateCloudConfigPB, UpdateUserProfilePayloadPB, UserProfilePB,
-  UserWorkspaceIdPB, UserWorkspacePB,
+  OauthSignInPB, RenameWorkspacePB, ReorderWorkspacesPB, RepeatedUserWorkspacePB, SignInUrlPB,
+  SignInUrlPayloadPB, SignUpPayloadPB, UpdateCloudConfigPB, UpdateUserProfilePayloadPB,
+  UserProfilePB, UserWorkspaceIdPB, UserWorkspacePB,
 };
 use flowy_user::errors::{FlowyError, FlowyResult};
 use flowy_user::event_map::UserEvent;
@@ -267,6 +267,15 @@ impl EventIntegrationTest {
       .parse::<RepeatedUserWorkspacePB>()
   }
 
+  pub async fn reorder_workspaces(&self, workspace_ids: Vec<String>) {
+    let payload = ReorderWorkspacesPB { workspace_ids };
+    EventBuilder::new(self.clone())
+      .event(UserEvent::ReorderWorkspaces)
+      .payload(payload)
+      .async_send()
+      .await;
+  }
+
   pub async fn delete_workspace(&self, workspace_id: &str) {
     let payload = UserWorkspaceIdPB {
       workspace_id: workspace_id.to_string(),
@@ -320,6 +329,11 @@ impl TestNotificationSender {
     Self::default()
   }
 
+  /// Receives all the notifications posted from now on.
+  pub fn subscribe_all(&self) -> tokio::sync::broadcast::Receiver<SubscribeObject> {
+    self.sender.subscribe()
+  }
+
   pub fn subscribe<T>(&self, id: &str, ty: impl Into<i32> + Send) -> tokio::sync::mpsc::Receiver<T>
   where
     T: TryFrom<Bytes, Error = ProtobufError> + Send + 'static,
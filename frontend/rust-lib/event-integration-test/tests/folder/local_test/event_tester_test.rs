@@ -0,0 +1,56 @@
+use event_integration_test::event_tester::EventTester;
+use event_integration_test::EventIntegrationTest;
+use flowy_folder::entities::{CreateViewPayloadPB, RepeatedViewPB, ViewIdPB, ViewPB};
+use flowy_folder::event_map::FolderEvent;
+use flowy_folder::notification::FolderNotification;
+use flowy_user::errors::ErrorCode;
+
+#[tokio::test]
+async fn create_view_with_event_tester_test() {
+  let test = EventIntegrationTest::new_anon().await;
+  let workspace = test.get_current_workspace().await;
+  let payload = CreateViewPayloadPB {
+    parent_view_id: workspace.id.clone(),
+    name: "event tester view".to_string(),
+    desc: "".to_string(),
+    thumbnail: None,
+    layout: Default::default(),
+    initial_data: vec![],
+    meta: Default::default(),
+    set_as_current: false,
+    index: None,
+    section: None,
+    view_id: None,
+    extra: None,
+  };
+
+  let mut tester = EventTester::new(test.clone())
+    .event(FolderEvent::CreateView)
+    .payload(payload)
+    .async_send()
+    .await
+    .assert_ok()
+    .wait_for_notification(&workspace.id, FolderNotification::DidUpdateWorkspaceViews)
+    .await;
+  let views = tester.parse_notification::<RepeatedViewPB>(
+    &workspace.id,
+    FolderNotification::DidUpdateWorkspaceViews,
+  );
+  let view = tester.parse::<ViewPB>();
+  assert_eq!(view.name, "event tester view");
+  assert!(views.items.iter().any(|item| item.id == view.id));
+}
+
+#[tokio::test]
+async fn get_unknown_view_with_event_tester_test() {
+  let test = EventIntegrationTest::new_anon().await;
+  EventTester::new(test)
+    .event(FolderEvent::GetView)
+    .payload(ViewIdPB {
+      value: "unknown view".to_string(),
+    })
+    .async_send()
+    .await
+    .assert_error_code(ErrorCode::RecordNotFound)
+    .assert_no_notification("unknown view", FolderNotification::DidUpdateView);
+}
@@ -0,0 +1,57 @@
+use event_integration_test::EventIntegrationTest;
+use flowy_folder::entities::ExportTypePB;
+use tempdir::TempDir;
+
+#[tokio::test]
+async fn export_workspace_to_markdown_test() {
+  let test = EventIntegrationTest::new_anon().await;
+  test.create_document("Export: notes").await;
+  let tempdir = TempDir::new("export").unwrap();
+  let target_path = tempdir.path().join("workspace");
+  let target_path = target_path.to_str().unwrap();
+
+  let result = test
+    .export_workspace(ExportTypePB::Markdown, target_path)
+    .await
+    .unwrap();
+  assert_eq!(result.path, target_path);
+  assert!(result.exported > 0);
+  // The reserved characters are replaced in the file names
+  let file_path = walkdir::WalkDir::new(target_path)
+    .into_iter()
+    .filter_map(|entry| entry.ok())
+    .find(|entry| entry.file_name() == "Export_ notes.md");
+  assert!(file_path.is_some());
+  // Nothing is left next to the export
+  assert_eq!(std::fs::read_dir(tempdir.path()).unwrap().count(), 1);
+
+  // The target must not exist
+  let error = test
+    .export_workspace(ExportTypePB::Markdown, target_path)
+    .await
+    .unwrap_err();
+  assert!(error.msg.contains("already exists"));
+}
+
+#[tokio::test]
+async fn export_workspace_backup_test() {
+  let test = EventIntegrationTest::new_anon().await;
+  let view = test.create_document("Backup").await;
+  let tempdir = TempDir::new("export").unwrap();
+  let target_path = tempdir.path().join("backup.zip");
+
+  let result = test
+    .export_workspace(ExportTypePB::Backup, target_path.to_str().unwrap())
+    .await
+    .unwrap();
+  let file = std::fs::File::open(&target_path).unwrap();
+  let mut archive = zip::ZipArchive::new(file).unwrap();
+  let manifest: serde_json::Value =
+    serde_json::from_reader(archive.by_name("manifest.json").unwrap()).unwrap();
+  let views = manifest["views"].as_array().unwrap();
+  assert_eq!(views.len() as i64, result.exported + result.skipped);
+  let exported_view = views.iter().find(|v| v["id"] == view.id.as_str()).unwrap();
+  let file_name = exported_view["file"].as_str().unwrap();
+  assert!(file_name.ends_with("Backup.json"));
+  assert!(archive.by_name(file_name).is_ok());
+}
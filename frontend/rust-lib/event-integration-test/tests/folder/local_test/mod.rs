@@ -1,8 +1,12 @@
+mod event_tester_test;
+mod export_test;
+mod scenario_test;
 mod folder_test;
 mod import_test;
 mod script;
 mod subscription_test;
 mod test;
+mod trash_test;
 
 mod publish_database_test;
 mod publish_document_test;
@@ -0,0 +1,64 @@
+use event_integration_test::scenario::Scenario;
+use event_integration_test::EventIntegrationTest;
+use flowy_folder::entities::{CreateViewPayloadPB, ViewIdPB, ViewPB};
+use flowy_folder::event_map::FolderEvent;
+use flowy_folder::notification::FolderNotification;
+use flowy_user::errors::ErrorCode;
+
+fn create_view_payload(parent_view_id: String, name: &str) -> CreateViewPayloadPB {
+  CreateViewPayloadPB {
+    parent_view_id,
+    name: name.to_string(),
+    desc: "".to_string(),
+    thumbnail: None,
+    layout: Default::default(),
+    initial_data: vec![],
+    meta: Default::default(),
+    set_as_current: false,
+    index: None,
+    section: None,
+    view_id: None,
+    extra: None,
+  }
+}
+
+#[tokio::test]
+async fn create_nested_views_scenario_test() {
+  let test = EventIntegrationTest::new_anon().await;
+  let workspace = test.get_current_workspace().await;
+  let values = Scenario::new(test.clone())
+    .value("workspace_id", workspace.id.clone())
+    .step("create parent view", FolderEvent::CreateView, |values| {
+      create_view_payload(values.get("workspace_id"), "parent")
+    })
+    .expect_notification(
+      |values| values.get("workspace_id"),
+      FolderNotification::DidUpdateWorkspaceViews,
+    )
+    .save("parent_id", |view: ViewPB| view.id)
+    .step("create child view", FolderEvent::CreateView, |values| {
+      create_view_payload(values.get("parent_id"), "child")
+    })
+    .expect_notification(
+      |values| values.get("parent_id"),
+      FolderNotification::DidUpdateChildViews,
+    )
+    .save("child_id", |view: ViewPB| view.id)
+    .step("read parent view", FolderEvent::GetView, |values| {
+      ViewIdPB {
+        value: values.get("parent_id"),
+      }
+    })
+    .save("parent", |view: ViewPB| view)
+    .step("read unknown view", FolderEvent::GetView, |_| ViewIdPB {
+      value: "unknown view".to_string(),
+    })
+    .expect_error_code(ErrorCode::RecordNotFound)
+    .run()
+    .await;
+
+  let parent = values.get::<ViewPB>("parent");
+  let child_id = values.get::<String>("child_id");
+  assert_eq!(parent.name, "parent");
+  assert!(parent.child_views.iter().any(|view| view.id == child_id));
+}
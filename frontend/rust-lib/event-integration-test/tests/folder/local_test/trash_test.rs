@@ -0,0 +1,87 @@
+use event_integration_test::event_builder::EventBuilder;
+use event_integration_test::EventIntegrationTest;
+use flowy_folder::entities::{RepeatedTrashIdPB, TrashIdPB, TrashRetentionPB};
+use flowy_folder::event_map::FolderEvent;
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+async fn get_trash_retention(test: &EventIntegrationTest) -> u32 {
+  EventBuilder::new(test.clone())
+    .event(FolderEvent::GetTrashRetention)
+    .async_send()
+    .await
+    .parse::<TrashRetentionPB>()
+    .days
+}
+
+async fn set_trash_retention(test: &EventIntegrationTest, days: u32) {
+  EventBuilder::new(test.clone())
+    .event(FolderEvent::SetTrashRetention)
+    .payload(TrashRetentionPB { days })
+    .async_send()
+    .await;
+}
+
+async fn purge_expired_trash(test: &EventIntegrationTest) -> Vec<TrashIdPB> {
+  EventBuilder::new(test.clone())
+    .event(FolderEvent::PurgeExpiredTrash)
+    .async_send()
+    .await
+    .parse::<RepeatedTrashIdPB>()
+    .items
+}
+
+#[tokio::test]
+async fn trash_retention_test() {
+  let test = EventIntegrationTest::new_anon().await;
+  let current_workspace = test.get_current_workspace().await;
+  let view = test
+    .create_view(&current_workspace.id, "My view".to_string())
+    .await;
+  test.delete_view(&view.id).await;
+
+  assert_eq!(get_trash_retention(&test).await, 30);
+  let trash = test.get_trash().await.items;
+  assert_eq!(trash.len(), 1);
+  assert_eq!(
+    trash[0].expires_at,
+    trash[0].create_time + 30 * SECONDS_PER_DAY
+  );
+
+  set_trash_retention(&test, 1).await;
+  assert_eq!(get_trash_retention(&test).await, 1);
+  let trash = test.get_trash().await.items;
+  assert_eq!(trash[0].expires_at, trash[0].create_time + SECONDS_PER_DAY);
+
+  // The views are kept in the trash until they're deleted by the user
+  set_trash_retention(&test, 0).await;
+  let trash = test.get_trash().await.items;
+  assert_eq!(trash[0].expires_at, 0);
+}
+
+#[tokio::test]
+async fn purge_expired_trash_keeps_recent_views_test() {
+  let test = EventIntegrationTest::new_anon().await;
+  let current_workspace = test.get_current_workspace().await;
+  let view = test
+    .create_view(&current_workspace.id, "My view".to_string())
+    .await;
+  test.delete_view(&view.id).await;
+
+  set_trash_retention(&test, 1).await;
+  assert!(purge_expired_trash(&test).await.is_empty());
+  let trash = test.get_trash().await.items;
+  assert_eq!(trash.len(), 1);
+  assert_eq!(trash[0].id, view.id);
+
+  // The view can still be restored
+  EventBuilder::new(test.clone())
+    .event(FolderEvent::RestoreTrashItem)
+    .payload(TrashIdPB {
+      id: view.id.clone(),
+    })
+    .async_send()
+    .await;
+  assert!(test.get_trash().await.items.is_empty());
+  assert_eq!(test.get_view(&view.id).await.id, view.id);
+}
@@ -56,6 +56,31 @@ async fn af_cloud_workspace_change_name_and_icon() {
   assert_eq!(local_workspaces.items[0].icon, new_icon);
 }
 
+#[tokio::test]
+async fn af_cloud_workspace_reorder() {
+  use_localhost_af_cloud().await;
+  let test = EventIntegrationTest::new().await;
+  let user_profile_pb = test.af_cloud_sign_up().await;
+  test.create_workspace("my second workspace").await;
+  let workspaces = get_synced_workspaces(&test, user_profile_pb.id).await;
+  assert_eq!(workspaces.len(), 2);
+
+  let reversed_ids = workspaces
+    .iter()
+    .rev()
+    .map(|workspace| workspace.workspace_id.clone())
+    .collect::<Vec<_>>();
+  test.reorder_workspaces(reversed_ids.clone()).await;
+  let local_ids = test
+    .get_all_workspaces()
+    .await
+    .items
+    .into_iter()
+    .map(|workspace| workspace.workspace_id)
+    .collect::<Vec<_>>();
+  assert_eq!(local_ids, reversed_ids);
+}
+
 #[tokio::test]
 async fn af_cloud_create_workspace_test() {
   use_localhost_af_cloud().await;
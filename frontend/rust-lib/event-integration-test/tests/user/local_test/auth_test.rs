@@ -1,8 +1,15 @@
 use event_integration_test::user_event::{login_password, unique_email};
 use event_integration_test::{event_builder::EventBuilder, EventIntegrationTest};
-use flowy_user::entities::{AuthenticatorPB, SignInPayloadPB, SignUpPayloadPB};
+use flowy_user::entities::{
+  AuthenticatorPB, IssueSessionTokenPB, SessionTokenPB, SignInPayloadPB, SignUpPayloadPB,
+};
 use flowy_user::errors::ErrorCode;
+use flowy_user::event_map::UserEvent;
 use flowy_user::event_map::UserEvent::*;
+use flowy_user_pub::session::SESSION_TOKEN_CAPABILITY;
+use lib_dispatch::capability::PermissionDenied;
+use lib_dispatch::prelude::*;
+use tokio::task::LocalSet;
 
 use crate::user::local_test::helper::*;
 
@@ -103,3 +110,54 @@ async fn sign_in_with_invalid_password() {
       .is_some())
   }
 }
+
+#[tokio::test]
+async fn session_token_capabilities_test() {
+  let sdk = EventIntegrationTest::new().await;
+  sdk.init_anon_user().await;
+  let token = EventBuilder::new(sdk.clone())
+    .event(IssueSessionToken)
+    .payload(IssueSessionTokenPB::default())
+    .async_send()
+    .await
+    .parse::<SessionTokenPB>()
+    .token;
+
+  let send_remote = |event: UserEvent, payload: Vec<u8>| {
+    let request = AFPluginRequest::new(event)
+      .payload(payload)
+      .metadata(TRANSPORT_METADATA, "http")
+      .metadata(AUTHORIZATION_METADATA, &token);
+    let dispatcher = sdk.dispatcher();
+    async move {
+      LocalSet::new()
+        .run_until(AFPluginDispatcher::async_send(dispatcher.as_ref(), request))
+        .await
+    }
+  };
+
+  // Issued without capabilities, the token can't be used to issue broader ones
+  let payload = IssueSessionTokenPB {
+    capabilities: vec![SESSION_TOKEN_CAPABILITY.to_string()],
+  };
+  let resp = send_remote(IssueSessionToken, payload.into_bytes().unwrap().to_vec()).await;
+  assert_eq!(
+    PermissionDenied::from_response(&resp).unwrap().missing,
+    vec![SESSION_TOKEN_CAPABILITY]
+  );
+  let resp = send_remote(GetUserProfile, vec![]).await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+
+  EventBuilder::new(sdk.clone())
+    .event(RevokeSessionToken)
+    .payload(SessionTokenPB {
+      token: token.clone(),
+    })
+    .async_send()
+    .await;
+  let resp = send_remote(GetUserProfile, vec![]).await;
+  assert_eq!(
+    Unauthorized::from_response(&resp).unwrap().reason,
+    "invalid token"
+  );
+}
@@ -15,6 +15,7 @@ pub fn init(ai_manager: Weak<AIManager>) -> AFPlugin {
   let ai_tools = Arc::new(AICompletion::new(cloud_service, user_service));
   AFPlugin::new()
     .name("flowy-ai")
+    .proto_descriptors(crate::protobuf::file_descriptors())
     .state(ai_manager)
     .state(ai_tools)
     .event(AIEvent::StreamMessage, stream_chat_message_handler)
@@ -57,6 +58,14 @@ pub fn init(ai_manager: Weak<AIManager>) -> AFPlugin {
     .event(AIEvent::GetOfflineAIAppLink, get_offline_app_handler)
     .event(AIEvent::CreateChatContext, create_chat_context_handler)
     .event(AIEvent::GetChatInfo, create_chat_context_handler)
+    // Recorded in the audit log
+    .mutating(AIEvent::CreateChatContext)
+    .mutating(AIEvent::StreamMessage)
+    .mutating(AIEvent::ChatWithFile)
+    .mutating(AIEvent::ToggleChatWithFile)
+    .mutating(AIEvent::ToggleLocalAI)
+    .mutating(AIEvent::ToggleLocalAIChat)
+    .mutating(AIEvent::UpdateLocalLLM)
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Display, Hash, ProtoBuf_Enum, Flowy_Event)]
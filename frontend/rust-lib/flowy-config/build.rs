@@ -3,6 +3,7 @@ fn main() {
   {
     flowy_codegen::protobuf_file::dart_gen(env!("CARGO_PKG_NAME"));
     flowy_codegen::dart_event::gen(env!("CARGO_PKG_NAME"));
+    flowy_codegen::native_event::gen(env!("CARGO_PKG_NAME"));
   }
 
   #[cfg(feature = "tauri_ts")]
@@ -11,10 +11,14 @@ use crate::event_handler::*;
 pub fn init(store_preferences: Weak<KVStorePreferences>) -> AFPlugin {
   AFPlugin::new()
     .name(env!("CARGO_PKG_NAME"))
+    .proto_descriptors(crate::protobuf::file_descriptors())
     .state(store_preferences)
     .event(ConfigEvent::SetKeyValue, set_key_value_handler)
     .event(ConfigEvent::GetKeyValue, get_key_value_handler)
     .event(ConfigEvent::RemoveKeyValue, remove_key_value_handler)
+    // Recorded in the audit log
+    .mutating(ConfigEvent::SetKeyValue)
+    .mutating(ConfigEvent::RemoveKeyValue)
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Display, ProtoBuf_Enum, Flowy_Event)]
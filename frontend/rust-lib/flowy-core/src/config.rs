@@ -1,16 +1,24 @@
+use std::collections::BTreeMap;
 use std::fmt;
 use std::path::Path;
+use std::sync::Arc;
 
 use base64::Engine;
+use lib_dispatch::config::{ConfigSection, ConfigStore, SystemConfig};
+use lib_dispatch::feature_flag::FeatureFlags;
+use lib_dispatch::prelude::DispatchError;
 use semver::Version;
+use serde::{Deserialize, Serialize};
 use tracing::{error, info};
 
+use flowy_encrypt::Keyring;
 use flowy_server_pub::af_cloud_config::AFCloudConfiguration;
+use flowy_sync::SyncConfig;
 use flowy_user::services::entities::URL_SAFE_ENGINE;
 use lib_infra::file_util::copy_dir_recursive;
 use lib_infra::util::OperatingSystem;
 
-use crate::integrate::log::create_log_filter;
+use crate::integrate::log::{create_dispatch_log_filter, create_log_filter};
 
 #[derive(Clone)]
 pub struct AppFlowyCoreConfig {
@@ -27,7 +35,17 @@ pub struct AppFlowyCoreConfig {
   /// the origin_application_path.
   pub application_path: String,
   pub(crate) log_filter: String,
+  /// The collector endpoint the dispatcher spans are exported to. Requires the `otlp` feature.
+  pub(crate) otlp_endpoint: Option<String>,
+  /// The server the mutations are synced with, see [SyncEngine](flowy_sync::SyncEngine).
+  pub(crate) sync_config: Option<SyncConfig>,
   cloud_config: Option<AFCloudConfiguration>,
+  /// The features turned on or off by the app, exposed to the handlers as [FeatureToggles].
+  features: BTreeMap<String, bool>,
+  /// The keyring of the OS, keeping the key encrypting the local data.
+  pub(crate) keyring: Option<Arc<dyn Keyring>>,
+  /// The tuning of the dispatcher, see [AppFlowyCoreConfig::system_config].
+  pub(crate) system_config: SystemConfig,
 }
 
 impl fmt::Debug for AppFlowyCoreConfig {
@@ -97,7 +115,12 @@ impl AppFlowyCoreConfig {
       device_id,
       platform,
       log_filter,
+      otlp_endpoint: None,
+      sync_config: None,
       cloud_config,
+      features: BTreeMap::new(),
+      keyring: None,
+      system_config: SystemConfig::default(),
     }
   }
 
@@ -109,4 +132,155 @@ impl AppFlowyCoreConfig {
     );
     self
   }
+
+  /// Export the dispatcher spans to the OpenTelemetry collector listening on `endpoint`.
+  pub fn otlp_endpoint(mut self, endpoint: &str) -> Self {
+    self.otlp_endpoint = Some(endpoint.to_owned());
+    self
+  }
+
+  /// Sync the mutations with the other devices through the WebSocket server at `url`, which
+  /// authenticates the connections with `token`.
+  pub fn sync_server(mut self, url: &str, token: &str) -> Self {
+    self.sync_config = Some(SyncConfig::new(url, token));
+    self
+  }
+
+  /// Keep the key encrypting the local data in `keyring`. Without a keyring, the local data is
+  /// stored in plaintext.
+  pub fn keyring(mut self, keyring: Arc<dyn Keyring>) -> Self {
+    self.keyring = Some(keyring);
+    self
+  }
+
+  /// Tune the dispatcher with `config`, which must be the one the runtime was created with. Its
+  /// log level replaces the one of [AppFlowyCoreConfig::log_filter].
+  pub fn system_config(mut self, config: SystemConfig) -> Self {
+    if let Some(level) = &config.log_level {
+      self = self.log_filter(level, vec![]);
+    }
+    self.system_config = config;
+    self
+  }
+
+  /// Turn the feature `name` on or off. The handlers read it with `Config<FeatureToggles>`, and
+  /// the events gated behind the flag of the same name are enabled with it.
+  pub fn feature(mut self, name: &str, enabled: bool) -> Self {
+    self.features.insert(name.to_owned(), enabled);
+    self
+  }
+
+  /// Declares the features as the [FeatureFlags] of the dispatcher. Their overrides, made with
+  /// `SysSetFeatureFlag`, take precedence.
+  pub(crate) fn define_feature_flags(&self, flags: &FeatureFlags) {
+    for (name, enabled) in &self.features {
+      flags.define(name, *enabled);
+    }
+  }
+
+  /// The configuration registered in the dispatcher, read by the handlers with the `Config`
+  /// extractor instead of the environment variables.
+  pub(crate) fn config_store(&self) -> ConfigStore {
+    let mut store = ConfigStore::new();
+    if let Err(err) = self.fill_config_store(&mut store) {
+      error!("Build the config store failed: {}", err);
+    }
+    store
+  }
+
+  fn fill_config_store(&self, store: &mut ConfigStore) -> Result<(), DispatchError> {
+    store.set_section(
+      PathsConfig::SECTION,
+      PathsConfig {
+        storage_path: self.storage_path.clone(),
+        application_path: self.application_path.clone(),
+      },
+    )?;
+    store.set_section(
+      AppConfig::SECTION,
+      AppConfig {
+        name: self.name.clone(),
+        version: self.app_version.to_string(),
+        platform: self.platform.clone(),
+        device_id: self.device_id.clone(),
+      },
+    )?;
+    store.set_section(
+      FeatureToggles::SECTION,
+      FeatureToggles(self.features.clone()),
+    )?;
+    if let Some(cloud_config) = &self.cloud_config {
+      store.set_section(
+        ServerConfig::SECTION,
+        ServerConfig {
+          base_url: cloud_config.base_url.clone(),
+          ws_url: cloud_config.ws_base_url.clone(),
+          gotrue_url: cloud_config.gotrue_url.clone(),
+        },
+      )?;
+    }
+    Ok(())
+  }
+
+  /// Enable the dispatcher logs for the requests handled by the given plugins.
+  pub fn dispatch_log_filter(mut self, level: &str, plugins: Vec<String>) -> Self {
+    if !plugins.is_empty() {
+      self.log_filter = format!(
+        "{},{}",
+        self.log_filter,
+        create_dispatch_log_filter(level, plugins)
+      );
+    }
+    self
+  }
+}
+
+/// Where the app keeps its files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathsConfig {
+  pub storage_path: String,
+  pub application_path: String,
+}
+
+impl ConfigSection for PathsConfig {
+  const SECTION: &'static str = "paths";
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppConfig {
+  pub name: String,
+  pub version: String,
+  pub platform: String,
+  pub device_id: String,
+}
+
+impl ConfigSection for AppConfig {
+  const SECTION: &'static str = "app";
+}
+
+/// The AppFlowy Cloud endpoints. Only set when the app runs against a cloud server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerConfig {
+  pub base_url: String,
+  pub ws_url: String,
+  pub gotrue_url: String,
+}
+
+impl ConfigSection for ServerConfig {
+  const SECTION: &'static str = "server";
+}
+
+/// The features set with [AppFlowyCoreConfig::feature]. The unknown features are off.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FeatureToggles(pub BTreeMap<String, bool>);
+
+impl FeatureToggles {
+  pub fn is_enabled(&self, name: &str) -> bool {
+    self.0.get(name).copied().unwrap_or(false)
+  }
+}
+
+impl ConfigSection for FeatureToggles {
+  const SECTION: &'static str = "features";
 }
@@ -1,13 +1,24 @@
+use std::ops::Range;
 use std::sync::{Arc, Weak};
 
 use crate::deps_resolve::CollabSnapshotSql;
 use collab_integrate::collab_builder::AppFlowyCollabBuilder;
 use collab_integrate::CollabKVDB;
+use diesel::dsl::sql;
+use diesel::sql_types::BigInt;
 use flowy_database2::DatabaseManager;
 use flowy_document::entities::{DocumentSnapshotData, DocumentSnapshotMeta};
+use flowy_document::history::{
+  DocumentEdit, DocumentEditEvent, DocumentEditKind, DocumentEventStore, DocumentHistorySnapshot,
+};
 use flowy_document::manager::{DocumentManager, DocumentSnapshotService, DocumentUserService};
 use flowy_document_pub::cloud::DocumentCloudService;
 use flowy_error::{FlowyError, FlowyResult};
+use flowy_sqlite::{
+  prelude::*,
+  schema::{document_edit_table, document_history_snapshot_table},
+  DBConnection,
+};
 use flowy_storage_pub::storage::StorageService;
 use flowy_user::services::authenticate_user::AuthenticateUser;
 
@@ -22,13 +33,15 @@ impl DocumentDepsResolver {
   ) -> Arc<DocumentManager> {
     let user_service: Arc<dyn DocumentUserService> =
       Arc::new(DocumentUserImpl(authenticate_user.clone()));
-    let snapshot_service = Arc::new(DocumentSnapshotImpl(authenticate_user));
+    let snapshot_service = Arc::new(DocumentSnapshotImpl(authenticate_user.clone()));
+    let history_store = Arc::new(DocumentEventStoreImpl(authenticate_user));
     Arc::new(DocumentManager::new(
       user_service.clone(),
       collab_builder,
       cloud_service,
       storage_service,
       snapshot_service,
+      history_store,
     ))
   }
 }
@@ -79,6 +92,145 @@ impl DocumentSnapshotService for DocumentSnapshotImpl {
   }
 }
 
+/// Stores the history of the documents in the sqlite database of the current user.
+struct DocumentEventStoreImpl(Weak<AuthenticateUser>);
+
+impl DocumentEventStoreImpl {
+  fn get_sqlite_connection(&self) -> FlowyResult<DBConnection> {
+    let authenticate_user = self
+      .0
+      .upgrade()
+      .ok_or(FlowyError::internal().with_context("Unexpected error: UserSession is None"))?;
+    let uid = authenticate_user.user_id()?;
+    authenticate_user.get_sqlite_connection(uid)
+  }
+}
+
+#[derive(Queryable)]
+struct DocumentEditRow {
+  seq: i64,
+  document_id: String,
+  kind: i32,
+  payload: Vec<u8>,
+  created_at: i64,
+}
+
+#[derive(Queryable, Insertable)]
+#[diesel(table_name = document_history_snapshot_table)]
+struct DocumentHistorySnapshotRow {
+  document_id: String,
+  seq: i64,
+  state_vector: Vec<u8>,
+  doc_state: Vec<u8>,
+  created_at: i64,
+}
+
+impl DocumentEventStore for DocumentEventStoreImpl {
+  fn append(&self, document_id: &str, edit: DocumentEdit, created_at: i64) -> FlowyResult<i64> {
+    let mut conn = self.get_sqlite_connection()?;
+    let seq = conn.immediate_transaction::<_, diesel::result::Error, _>(|conn| {
+      insert_into(document_edit_table::table)
+        .values((
+          document_edit_table::document_id.eq(document_id),
+          document_edit_table::kind.eq(edit.kind as i32),
+          document_edit_table::payload.eq(edit.payload),
+          document_edit_table::created_at.eq(created_at),
+        ))
+        .execute(conn)?;
+      select(sql::<BigInt>("last_insert_rowid()")).get_result::<i64>(conn)
+    })?;
+    Ok(seq)
+  }
+
+  fn read_range(
+    &self,
+    document_id: &str,
+    seqs: Range<i64>,
+    limit: usize,
+  ) -> FlowyResult<Vec<DocumentEditEvent>> {
+    let mut conn = self.get_sqlite_connection()?;
+    let rows = document_edit_table::table
+      .filter(document_edit_table::document_id.eq(document_id))
+      .filter(document_edit_table::seq.ge(seqs.start))
+      .filter(document_edit_table::seq.lt(seqs.end))
+      .order(document_edit_table::seq.asc())
+      .limit(limit as i64)
+      .load::<DocumentEditRow>(&mut *conn)?;
+    Ok(
+      rows
+        .into_iter()
+        .filter_map(|row| {
+          Some(DocumentEditEvent {
+            seq: row.seq,
+            document_id: row.document_id,
+            kind: DocumentEditKind::from_i32(row.kind)?,
+            payload: row.payload,
+            created_at: row.created_at,
+          })
+        })
+        .collect(),
+    )
+  }
+
+  fn last_seq(&self, document_id: &str) -> FlowyResult<Option<i64>> {
+    let mut conn = self.get_sqlite_connection()?;
+    let seq = document_edit_table::table
+      .filter(document_edit_table::document_id.eq(document_id))
+      .select(diesel::dsl::max(document_edit_table::seq))
+      .first::<Option<i64>>(&mut *conn)?;
+    Ok(seq)
+  }
+
+  fn save_snapshot(&self, snapshot: DocumentHistorySnapshot) -> FlowyResult<()> {
+    let mut conn = self.get_sqlite_connection()?;
+    replace_into(document_history_snapshot_table::table)
+      .values(DocumentHistorySnapshotRow {
+        document_id: snapshot.document_id,
+        seq: snapshot.seq,
+        state_vector: snapshot.state_vector,
+        doc_state: snapshot.doc_state,
+        created_at: snapshot.created_at,
+      })
+      .execute(&mut *conn)?;
+    Ok(())
+  }
+
+  fn latest_snapshot(&self, document_id: &str) -> FlowyResult<Option<DocumentHistorySnapshot>> {
+    let mut conn = self.get_sqlite_connection()?;
+    let row = document_history_snapshot_table::table
+      .filter(document_history_snapshot_table::document_id.eq(document_id))
+      .order(document_history_snapshot_table::seq.desc())
+      .first::<DocumentHistorySnapshotRow>(&mut *conn)
+      .optional()?;
+    Ok(row.map(|row| DocumentHistorySnapshot {
+      document_id: row.document_id,
+      seq: row.seq,
+      state_vector: row.state_vector,
+      doc_state: row.doc_state,
+      created_at: row.created_at,
+    }))
+  }
+
+  fn compact(&self, document_id: &str, seq: i64) -> FlowyResult<usize> {
+    let mut conn = self.get_sqlite_connection()?;
+    let deleted = conn.immediate_transaction::<_, diesel::result::Error, _>(|conn| {
+      delete(
+        document_history_snapshot_table::table
+          .filter(document_history_snapshot_table::document_id.eq(document_id))
+          .filter(document_history_snapshot_table::seq.lt(seq)),
+      )
+      .execute(conn)?;
+      delete(
+        document_edit_table::table
+          .filter(document_edit_table::document_id.eq(document_id))
+          .filter(document_edit_table::seq.le(seq)),
+      )
+      .execute(conn)
+    })?;
+    Ok(deleted)
+  }
+}
+
 struct DocumentUserImpl(Weak<AuthenticateUser>);
 impl DocumentUserService for DocumentUserImpl {
   fn user_id(&self) -> Result<i64, FlowyError> {
@@ -11,11 +11,12 @@ use flowy_database2::template::{make_default_board, make_default_calendar, make_
 use flowy_database2::DatabaseManager;
 use flowy_document::entities::DocumentDataPB;
 use flowy_document::manager::DocumentManager;
+use flowy_document::parser::document_data_parser::DocumentDataParser;
 use flowy_document::parser::json::parser::JsonToDocumentParser;
 use flowy_error::{FlowyError, FlowyResult};
 use flowy_folder::entities::{CreateViewParams, ViewLayoutPB};
 use flowy_folder::manager::{FolderManager, FolderUser};
-use flowy_folder::share::ImportType;
+use flowy_folder::share::{ExportFormat, ExportedView, ImportType};
 use flowy_folder::view_operation::{
   DatabaseEncodedCollab, DocumentEncodedCollab, EncodedCollabWrapper, FolderOperationHandler,
   FolderOperationHandlers, ImportedData, View, ViewData,
@@ -172,6 +173,27 @@ impl FolderOperationHandler for DocumentFolderOperation {
     Ok(data_bytes)
   }
 
+  async fn export_view(
+    &self,
+    view_id: &str,
+    format: ExportFormat,
+  ) -> Result<ExportedView, FlowyError> {
+    let data = self.0.get_document_data(view_id).await?;
+    let parser = DocumentDataParser::new(Arc::new(data), None);
+    // The JSON is the one the documents are imported from
+    let exported = match format {
+      ExportFormat::Markdown => ExportedView {
+        extension: "md",
+        data: parser.to_markdown().into_bytes(),
+      },
+      ExportFormat::Json => ExportedView {
+        extension: "json",
+        data: serde_json::to_vec_pretty(&parser.to_json())?,
+      },
+    };
+    Ok(exported)
+  }
+
   async fn create_view_with_view_data(
     &self,
     user_id: i64,
@@ -416,6 +438,28 @@ impl FolderOperationHandler for DatabaseFolderOperation {
     Ok(Bytes::from(view_id.to_string()))
   }
 
+  async fn export_view(
+    &self,
+    view_id: &str,
+    format: ExportFormat,
+  ) -> Result<ExportedView, FlowyError> {
+    let exported = match format {
+      ExportFormat::Markdown => ExportedView {
+        extension: "csv",
+        data: self
+          .0
+          .export_csv(view_id, CSVFormat::Original)
+          .await?
+          .into_bytes(),
+      },
+      ExportFormat::Json => ExportedView {
+        extension: "json",
+        data: self.0.get_database_json_string(view_id).await?.into_bytes(),
+      },
+    };
+    Ok(exported)
+  }
+
   /// Create a database view with duplicated data.
   /// If the ext contains the {"database_id": "xx"}, then it will link
   /// to the existing database.
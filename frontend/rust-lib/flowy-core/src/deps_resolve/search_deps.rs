@@ -1,19 +1,72 @@
+use flowy_document::manager::DocumentManager;
+use flowy_error::{ErrorCode, FlowyError, FlowyResult};
 use flowy_folder::manager::FolderManager;
+use flowy_search::document::entities::DocumentIndexData;
+use flowy_search::document::indexer::{DocumentContentProvider, DocumentIndexManagerImpl};
 use flowy_search::folder::handler::FolderSearchHandler;
 use flowy_search::folder::indexer::FolderIndexManagerImpl;
 use flowy_search::services::manager::SearchManager;
 use flowy_search_pub::cloud::SearchCloudService;
-use std::sync::Arc;
+use flowy_user::services::authenticate_user::AuthenticateUser;
+use lib_infra::async_trait::async_trait;
+use std::sync::{Arc, Weak};
+use tracing::error;
 
 pub struct SearchDepsResolver();
 impl SearchDepsResolver {
   pub async fn resolve(
     folder_indexer: Arc<FolderIndexManagerImpl>,
     _cloud_service: Arc<dyn SearchCloudService>,
-    _folder_manager: Arc<FolderManager>,
+    folder_manager: Arc<FolderManager>,
+    document_manager: Arc<DocumentManager>,
+    authenticate_user: Weak<AuthenticateUser>,
   ) -> Arc<SearchManager> {
     let folder_handler = Arc::new(FolderSearchHandler::new(folder_indexer));
     // let document_handler = Arc::new(DocumentSearchHandler::new(cloud_service, folder_manager));
-    Arc::new(SearchManager::new(vec![folder_handler]))
+    let search_manager = SearchManager::new(vec![folder_handler]);
+    match DocumentIndexManagerImpl::new(authenticate_user) {
+      Ok(document_indexer) => {
+        let document_indexer = Arc::new(document_indexer);
+        document_indexer.watch_document_changes(Arc::new(DocumentContentProviderImpl {
+          folder_manager: Arc::downgrade(&folder_manager),
+          document_manager: Arc::downgrade(&document_manager),
+        }));
+        Arc::new(search_manager.with_document_index(document_indexer))
+      },
+      Err(err) => {
+        error!("Failed to open the document index: {}", err);
+        Arc::new(search_manager)
+      },
+    }
+  }
+}
+
+struct DocumentContentProviderImpl {
+  folder_manager: Weak<FolderManager>,
+  document_manager: Weak<DocumentManager>,
+}
+
+#[async_trait]
+impl DocumentContentProvider for DocumentContentProviderImpl {
+  async fn document_content(&self, document_id: &str) -> FlowyResult<Option<DocumentIndexData>> {
+    let (folder_manager, document_manager) = self
+      .folder_manager
+      .upgrade()
+      .zip(self.document_manager.upgrade())
+      .ok_or_else(|| FlowyError::internal().with_context("The managers are already dropped"))?;
+    // The views in the trash are not searchable
+    let view = match folder_manager.get_view_pb(document_id).await {
+      Ok(view) => view,
+      Err(err) if err.code == ErrorCode::RecordNotFound => return Ok(None),
+      Err(err) => return Err(err),
+    };
+    let content = document_manager.get_document_text(document_id).await?;
+    let workspace_id = document_manager.user_service.workspace_id()?;
+    Ok(Some(DocumentIndexData {
+      id: document_id.to_string(),
+      title: view.name,
+      content,
+      workspace_id,
+    }))
   }
 }
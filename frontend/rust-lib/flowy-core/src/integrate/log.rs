@@ -1,5 +1,8 @@
+use lib_dispatch::system::{ConfigChange, LiveConfig};
 use lib_infra::util::OperatingSystem;
 use lib_log::stream_log::StreamLogSender;
+use lib_log::OtlpConfig;
+use std::collections::BTreeMap;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
@@ -19,9 +22,18 @@ pub(crate) fn init_log(
   if !INIT_LOG.load(Ordering::SeqCst) {
     INIT_LOG.store(true, Ordering::SeqCst);
 
-    let _ = lib_log::Builder::new("log", &config.storage_path, platform, stream_log_sender)
-      .env_filter(&config.log_filter)
-      .build();
+    let log_filter = with_log_levels(config.log_filter.clone(), &config.system_config.log_levels);
+    let mut builder =
+      lib_log::Builder::new("log", &config.storage_path, platform, stream_log_sender)
+        .env_filter(&log_filter);
+    if let Some(endpoint) = &config.otlp_endpoint {
+      let otlp = OtlpConfig::new(endpoint, &config.name)
+        .resource_attribute("service.version", &config.app_version.to_string())
+        .resource_attribute("os.type", &config.platform)
+        .target("lib_dispatch");
+      builder = builder.otlp(otlp);
+    }
+    let _ = builder.build();
   }
 }
 
@@ -67,7 +79,10 @@ pub fn create_log_filter(
 
   // Most of the time, we don't need to see the logs from the following crates
   // filters.push(format!("flowy_sqlite={}", "info"));
-  // filters.push(format!("lib_dispatch={}", level));
+
+  // Only the info logs of the dispatcher are shown by default, not the spans of each request. Use
+  // [create_dispatch_log_filter] to trace the requests of specific plugins.
+  filters.push("lib_dispatch=info".to_owned());
 
   filters.push(format!("client_api={}", level));
   #[cfg(feature = "profiling")]
@@ -78,6 +93,55 @@ pub fn create_log_filter(
   filters.join(",")
 }
 
+/// Reloads the filter of the logs when the log levels of the dispatcher change, e.g. with
+/// `SysSetLogLevel`. A new `log_level` replaces the level of the [create_log_filter], and the
+/// `log_levels` of the modules are added on top of it.
+pub(crate) fn observe_log_levels(config: &AppFlowyCoreConfig, live_config: &LiveConfig) {
+  let log_filter = config.log_filter.clone();
+  let initial_level = config.system_config.log_level.clone();
+  let platform = config.platform.clone();
+  live_config.observe(move |change: &ConfigChange| {
+    if !change
+      .changed
+      .iter()
+      .any(|setting| setting == "log_level" || setting == "log_levels")
+    {
+      return;
+    }
+    let current = &change.current;
+    let filter = match &current.log_level {
+      Some(level) if current.log_level != initial_level => {
+        create_log_filter(level.clone(), vec![], OperatingSystem::from(&platform))
+      },
+      _ => log_filter.clone(),
+    };
+    let filter = with_log_levels(filter, &current.log_levels);
+    match lib_log::reload_env_filter(&filter) {
+      Ok(_) => tracing::info!("Log filter reloaded: {}", filter),
+      Err(err) => tracing::warn!("Reload the log filter failed: {}", err),
+    }
+  });
+}
+
+/// Appends the levels of the modules to `filter`, they take precedence over its directives for
+/// the same modules.
+fn with_log_levels(filter: String, log_levels: &BTreeMap<String, String>) -> String {
+  log_levels.iter().fold(filter, |filter, (target, level)| {
+    format!("{},{}={}", filter, target, level)
+  })
+}
+
+/// Each request handled by the dispatcher runs inside a `dispatch` span that records the event,
+/// the request id and the name of the plugin. The returned directives enable the dispatcher logs
+/// only for the requests handled by the given plugins, e.g. `flowy-folder`.
+pub fn create_dispatch_log_filter(level: &str, plugins: Vec<String>) -> String {
+  plugins
+    .into_iter()
+    .map(|plugin| format!("lib_dispatch[dispatch{{module={}}}]={}", plugin, level))
+    .collect::<Vec<String>>()
+    .join(",")
+}
+
 #[cfg(debug_assertions)]
 fn get_bool_from_env_var(env_var_name: &str) -> bool {
   match std::env::var(env_var_name) {
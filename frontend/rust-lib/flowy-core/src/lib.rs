@@ -2,6 +2,8 @@
 
 use flowy_search::folder::indexer::FolderIndexManagerImpl;
 use flowy_search::services::manager::SearchManager;
+use std::convert::TryFrom;
+use std::path::Path;
 use std::sync::{Arc, Weak};
 use std::time::Duration;
 use sysinfo::System;
@@ -10,18 +12,32 @@ use tracing::{debug, error, event, info, instrument};
 
 use collab_integrate::collab_builder::{AppFlowyCollabBuilder, CollabPluginProviderType};
 use flowy_ai::ai_manager::AIManager;
+use flowy_database2::event_map::DatabaseEvent;
 use flowy_database2::DatabaseManager;
+use flowy_document::event_map::DocumentEvent;
 use flowy_document::manager::DocumentManager;
-use flowy_error::{FlowyError, FlowyResult};
+use flowy_encrypt::KeyManager;
+use flowy_error::{internal_error, FlowyError, FlowyResult, LocalizeErrors};
+use flowy_folder::event_map::FolderEvent;
 use flowy_folder::manager::FolderManager;
+use flowy_folder::sync_conflict::ViewConflictResolver;
+use flowy_folder::trash::run_trash_purge;
 use flowy_server::af_cloud::define::ServerUser;
 
+use flowy_kv::KVStore;
+use flowy_sqlite::dispatch::PoolManager;
 use flowy_sqlite::kv::KVStorePreferences;
+use flowy_storage::attachment::AttachmentStore;
 use flowy_storage::manager::StorageManager;
+use flowy_sync::{SqliteOfflineStore, SyncConflicts, SyncEngine, SyncState, Telemetry};
 use flowy_user::services::authenticate_user::AuthenticateUser;
 use flowy_user::services::entities::UserConfig;
 use flowy_user::user_manager::UserManager;
 
+use lib_dispatch::audit::{
+  audit_plugin, AuditMiddleware, AuditStore, BufferedAuditStore, FileAuditStore,
+};
+use lib_dispatch::feature_flag::FileFlagStore;
 use lib_dispatch::prelude::*;
 use lib_dispatch::runtime::AFPluginRuntime;
 use lib_infra::priority_task::{TaskDispatcher, TaskRunner};
@@ -33,7 +49,7 @@ use crate::config::AppFlowyCoreConfig;
 use crate::deps_resolve::file_storage_deps::FileStorageResolver;
 use crate::deps_resolve::*;
 use crate::integrate::collab_interact::CollabInteractImpl;
-use crate::integrate::log::init_log;
+use crate::integrate::log::{init_log, observe_log_levels};
 use crate::integrate::server::{current_server_type, Server, ServerProvider};
 use crate::integrate::user::UserStatusCallbackImpl;
 
@@ -61,6 +77,16 @@ pub struct AppFlowyCore {
   pub search_manager: Arc<SearchManager>,
   pub ai_manager: Arc<AIManager>,
   pub storage_manager: Arc<StorageManager>,
+  /// Set if a sync server is configured. The host applies the remote changes, see
+  /// [SyncEngine::take_remote_changes].
+  pub sync_engine: Option<Arc<SyncEngine>>,
+  /// Counts the usage once the user opted in, uploaded by the [SyncEngine] if there is one.
+  pub telemetry: Telemetry,
+  /// Switches the dispatch logs between plain text and JSON lines.
+  pub dispatch_log_format: LogFormatHandle,
+  /// Set if the host provides a keyring. The passphrase set up with it encrypts the local data
+  /// from the next launch on.
+  pub key_manager: Option<Arc<KeyManager>>,
 }
 
 impl AppFlowyCore {
@@ -119,9 +145,14 @@ impl AppFlowyCore {
       config.app_version.clone(),
     );
 
+    let key_manager = config
+      .keyring
+      .clone()
+      .map(|keyring| Arc::new(KeyManager::new(&config.storage_path, keyring)));
     let authenticate_user = Arc::new(AuthenticateUser::new(
       user_config.clone(),
       store_preference.clone(),
+      key_manager.clone(),
     ));
 
     let server_type = current_server_type();
@@ -220,6 +251,8 @@ impl AppFlowyCore {
         folder_indexer,
         server_provider.clone(),
         folder_manager.clone(),
+        document_manager.clone(),
+        Arc::downgrade(&authenticate_user),
       )
       .await;
 
@@ -261,19 +294,78 @@ impl AppFlowyCore {
         error!("Init user failed: {}", err)
       }
     }
-    #[allow(clippy::arc_with_non_send_sync)]
-    let event_dispatcher = Arc::new(AFPluginDispatcher::new(
-      runtime,
-      make_plugins(
-        Arc::downgrade(&folder_manager),
-        Arc::downgrade(&database_manager),
-        Arc::downgrade(&user_manager),
-        Arc::downgrade(&document_manager),
-        Arc::downgrade(&search_manager),
-        Arc::downgrade(&ai_manager),
-        Arc::downgrade(&storage_manager),
-      ),
+    let mut plugins = make_plugins(
+      Arc::downgrade(&folder_manager),
+      Arc::downgrade(&database_manager),
+      Arc::downgrade(&user_manager),
+      Arc::downgrade(&document_manager),
+      Arc::downgrade(&search_manager),
+      Arc::downgrade(&ai_manager),
+      Arc::downgrade(&storage_manager),
+    );
+    let audit = make_audit_middleware(&config, Arc::downgrade(&authenticate_user));
+    if let Some((_, store)) = &audit {
+      plugins.push(audit_plugin(store.clone()));
+    }
+    let log_middleware = make_log_middleware();
+    let dispatch_log_format = log_middleware.format_handle();
+    #[cfg(all(unix, debug_assertions))]
+    let cli_runtime = runtime.clone();
+    let mut event_dispatcher = AFPluginDispatcher::new(runtime, plugins)
+      .with_system_config(&config.system_config)
+      .config(config.config_store())
+      .with_crash_file(Path::new(&config.storage_path).join("flowy-crash.json"))
+      .with_flag_store(FileFlagStore::new(
+        Path::new(&config.storage_path).join("feature_flags.json"),
+      ))
+      .with_middleware(log_middleware)
+      // The requests arriving over a bridge only get the capabilities of their session token.
+      .with_middleware(AuthMiddleware::new(Arc::new(
+        authenticate_user.session_tokens(),
+      )))
+      .with_response_mapper(LocalizeErrors);
+    if let Some((middleware, _)) = audit {
+      event_dispatcher = event_dispatcher.with_middleware(middleware);
+    }
+    observe_log_levels(&config, &event_dispatcher.live_config());
+    config.define_feature_flags(&event_dispatcher.feature_flags());
+    match make_kv_store(&config, key_manager.as_deref()) {
+      Ok(kv_store) => event_dispatcher = kv_store.register(event_dispatcher),
+      Err(err) => error!("Failed to open the key value store: {}", err),
+    }
+    match AttachmentStore::new(&config.storage_path, event_dispatcher.metrics()) {
+      Ok(store) => event_dispatcher = store.register(event_dispatcher),
+      Err(err) => error!("Failed to open the attachment store: {}", err),
+    }
+    let telemetry = Telemetry::new().consent_store(store_preference.clone());
+    event_dispatcher = telemetry.register(event_dispatcher);
+    let sync_engine = make_sync_engine(&config, &telemetry);
+    match &sync_engine {
+      Some(engine) => {
+        event_dispatcher = engine.register(event_dispatcher);
+        let engine = engine.clone();
+        af_spawn(async move { engine.run().await });
+      },
+      None => {
+        event_dispatcher = event_dispatcher
+          .data(SyncState::default())
+          .data(SyncConflicts::default());
+      },
+    }
+    let pool_manager = make_pool_manager(&event_dispatcher, Arc::downgrade(&user_manager));
+    event_dispatcher = pool_manager.register(event_dispatcher);
+    af_spawn(pool_manager.run_health_checks(POOL_HEALTH_CHECK_INTERVAL));
+    af_spawn(run_trash_purge(
+      Arc::downgrade(&folder_manager),
+      TRASH_PURGE_INTERVAL,
     ));
+    #[allow(clippy::arc_with_non_send_sync)]
+    let event_dispatcher = Arc::new(event_dispatcher);
+
+    #[cfg(debug_assertions)]
+    start_metrics_exporter(&event_dispatcher);
+    #[cfg(all(unix, debug_assertions))]
+    start_cli_socket(cli_runtime, &event_dispatcher);
 
     Self {
       config,
@@ -288,6 +380,10 @@ impl AppFlowyCore {
       search_manager,
       ai_manager,
       storage_manager,
+      sync_engine,
+      telemetry,
+      dispatch_log_format,
+      key_manager,
     }
   }
 
@@ -297,6 +393,166 @@ impl AppFlowyCore {
   }
 }
 
+/// Syncs the edits of the documents and the changes of the views, if a sync server is
+/// configured. The mutations made offline are kept in the storage path until they are sent.
+fn make_sync_engine(config: &AppFlowyCoreConfig, telemetry: &Telemetry) -> Option<Arc<SyncEngine>> {
+  let sync_config = config.sync_config.clone()?;
+  let mut engine = SyncEngine::new(sync_config, &config.device_id)
+    .sync(DocumentEvent::ApplyAction)
+    .sync(DocumentEvent::CreateText)
+    .sync(DocumentEvent::ApplyTextDeltaEvent)
+    .sync(DocumentEvent::ApplyDocumentUpdate)
+    .sync(FolderEvent::CreateView)
+    .sync(FolderEvent::UpdateView)
+    .sync(FolderEvent::DeleteView)
+    .sync(FolderEvent::MoveNestedView)
+    .sync(FolderEvent::UpdateViewIcon)
+    .resolve(FolderEvent::UpdateView, ViewConflictResolver)
+    .resolve(FolderEvent::MoveNestedView, ViewConflictResolver)
+    .resolve(FolderEvent::UpdateViewIcon, ViewConflictResolver)
+    .telemetry(telemetry.clone());
+  match SqliteOfflineStore::new(&config.storage_path) {
+    Ok(store) => engine = engine.offline_store(Arc::new(store)),
+    Err(err) => error!("Failed to open the sync offline store: {}", err),
+  }
+  Some(Arc::new(engine))
+}
+
+/// Opens the key value store, encrypting its values if the user set up a passphrase. The values
+/// written before are encrypted on the first launch with the key.
+fn make_kv_store(
+  config: &AppFlowyCoreConfig,
+  key_manager: Option<&KeyManager>,
+) -> FlowyResult<KVStore> {
+  let store = KVStore::new(&config.storage_path)?;
+  let key = match key_manager {
+    None => None,
+    Some(key_manager) => key_manager.key().map_err(internal_error)?,
+  };
+  match key {
+    None => Ok(store),
+    Some(key) => {
+      let store = store.with_encryption(key);
+      let encrypted = store.encrypt_existing_values()?;
+      if encrypted > 0 {
+        info!("Encrypted {} values of the key value store", encrypted);
+      }
+      Ok(store)
+    },
+  }
+}
+
+/// Log every request except the ones sent on each keystroke, which are sampled.
+fn make_log_middleware() -> LogMiddleware {
+  LogMiddleware::new()
+    .with_sampling(DocumentEvent::ApplyAction, LogSampling::OneIn(50))
+    .with_sampling(DocumentEvent::ApplyTextDeltaEvent, LogSampling::OneIn(50))
+    .with_sampling(DatabaseEvent::UpdateCell, LogSampling::OneIn(50))
+    .with_error_code(|response| match &response.payload {
+      Payload::Bytes(bytes) => FlowyError::try_from(bytes.clone())
+        .ok()
+        .map(|err| err.code.value() as i64),
+      Payload::None => None,
+    })
+}
+
+/// Record the mutating events in an audit log stored next to the user data.
+fn make_audit_middleware(
+  config: &AppFlowyCoreConfig,
+  authenticate_user: Weak<AuthenticateUser>,
+) -> Option<(AuditMiddleware, Arc<dyn AuditStore>)> {
+  let dir = Path::new(&config.storage_path).join("audit");
+  // Written on a background thread, the requests are recorded without waiting for the disk.
+  match FileAuditStore::new(dir).and_then(BufferedAuditStore::new) {
+    Ok(store) => {
+      let store: Arc<dyn AuditStore> = Arc::new(store);
+      let middleware = AuditMiddleware::new(store.clone()).with_identity(move || {
+        let user = authenticate_user.upgrade()?;
+        user.user_id().ok().map(|uid| uid.to_string())
+      });
+      Some((middleware, store))
+    },
+    Err(err) => {
+      error!("Failed to open the audit log: {}", err);
+      None
+    },
+  }
+}
+
+/// How often the health of the database of the current user is recorded in the metrics.
+const POOL_HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often the views that stayed in the trash longer than the retention are deleted.
+const TRASH_PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+fn make_pool_manager(
+  dispatcher: &AFPluginDispatcher,
+  user_manager: Weak<UserManager>,
+) -> PoolManager {
+  PoolManager::new(dispatcher.metrics(), move || {
+    let user_manager = user_manager
+      .upgrade()
+      .ok_or_else(|| "The user manager is dropped".to_string())?;
+    let uid = user_manager.user_id().map_err(|err| err.to_string())?;
+    user_manager.db_pool(uid).map_err(|err| err.to_string())
+  })
+}
+
+/// Serve the dispatcher metrics in the Prometheus text format when the `APPFLOWY_METRICS_ADDR`
+/// environment variable is set, e.g. `APPFLOWY_METRICS_ADDR=127.0.0.1:9464`.
+#[cfg(debug_assertions)]
+fn start_metrics_exporter(dispatcher: &AFPluginDispatcher) {
+  if let Ok(addr) = std::env::var("APPFLOWY_METRICS_ADDR") {
+    match addr.parse() {
+      Ok(addr) => {
+        let registry = dispatcher.metrics();
+        af_spawn(async move {
+          if let Err(err) = lib_dispatch::metrics::serve_prometheus_metrics(registry, addr).await {
+            error!("Metrics exporter stopped: {}", err);
+          }
+        });
+      },
+      Err(err) => error!("Invalid metrics exporter address {}: {}", addr, err),
+    }
+  }
+}
+
+/// Serve the dispatcher to the `flowy-cli` tool on the Unix domain socket at the path of the
+/// `APPFLOWY_CLI_SOCKET` environment variable, e.g. `APPFLOWY_CLI_SOCKET=/tmp/appflowy.sock`.
+/// The requests are authenticated with the session tokens, like the ones of the other bridges.
+#[cfg(all(unix, debug_assertions))]
+fn start_cli_socket(runtime: Arc<AFPluginRuntime>, dispatcher: &Arc<AFPluginDispatcher>) {
+  let path = match std::env::var("APPFLOWY_CLI_SOCKET") {
+    Ok(path) => path,
+    Err(_) => return,
+  };
+  // The socket left by the previous run would fail the bind.
+  let _ = std::fs::remove_file(&path);
+  let dispatcher = CliDispatcher(dispatcher.clone());
+  let spawned = std::thread::Builder::new()
+    .name("cli-socket".to_owned())
+    .spawn(move || {
+      let CliDispatcher(dispatcher) = dispatcher;
+      // Like the dart-ffi workers, the socket is served on a LocalSet of its own thread.
+      let local_set = tokio::task::LocalSet::new();
+      let serving = local_set.run_until(lib_dispatch::bridge::serve_unix_socket(&path, dispatcher));
+      if let Err(err) = runtime.block_on(serving) {
+        error!("CLI socket {} stopped: {}", path, err);
+      }
+    });
+  if let Err(err) = spawned {
+    error!("Failed to start the CLI socket: {}", err);
+  }
+}
+
+/// The dispatcher handed to the thread serving the CLI socket, shared like the one handed to
+/// the dart-ffi workers.
+#[cfg(all(unix, debug_assertions))]
+struct CliDispatcher(Arc<AFPluginDispatcher>);
+
+#[cfg(all(unix, debug_assertions))]
+unsafe impl Send for CliDispatcher {}
+
 impl From<Server> for CollabPluginProviderType {
   fn from(server_type: Server) -> Self {
     match server_type {
@@ -28,9 +28,12 @@ pub fn make_plugins(
   let document_plugin2 = flowy_document::event_map::init(document_manager2);
   let config_plugin = flowy_config::event_map::init(store_preferences);
   let date_plugin = flowy_date::event_map::init();
+  let kv_plugin = flowy_kv::event_map::init();
+  let sync_plugin = flowy_sync::event_map::init();
   let search_plugin = flowy_search::event_map::init(search_manager);
   let ai_plugin = flowy_ai::event_map::init(ai_manager);
   let file_storage_plugin = flowy_storage::event_map::init(file_storage_manager);
+  let notification_plugin = flowy_notification::event_map::init();
   vec![
     user_plugin,
     folder_plugin,
@@ -38,8 +41,11 @@ pub fn make_plugins(
     document_plugin2,
     config_plugin,
     date_plugin,
+    kv_plugin,
+    sync_plugin,
     search_plugin,
     ai_plugin,
     file_storage_plugin,
+    notification_plugin,
   ]
 }
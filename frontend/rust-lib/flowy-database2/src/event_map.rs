@@ -11,6 +11,7 @@ use crate::manager::DatabaseManager;
 pub fn init(database_manager: Weak<DatabaseManager>) -> AFPlugin {
   let plugin = AFPlugin::new()
     .name(env!("CARGO_PKG_NAME"))
+    .proto_descriptors(crate::protobuf::file_descriptors())
     .state(database_manager);
   plugin
          .event(DatabaseEvent::GetDatabase, get_database_data_handler)
@@ -98,6 +99,47 @@ pub fn init(database_manager: Weak<DatabaseManager>) -> AFPlugin {
          // Media
          .event(DatabaseEvent::UpdateMediaCell, update_media_cell_handler)
          .event(DatabaseEvent::RenameMediaFile, rename_media_cell_file_handler)
+         // Recorded in the audit log
+         .mutating(DatabaseEvent::UpdateDatabaseSetting)
+         .mutating(DatabaseEvent::DeleteAllSorts)
+         .mutating(DatabaseEvent::UpdateField)
+         .mutating(DatabaseEvent::UpdateFieldTypeOption)
+         .mutating(DatabaseEvent::DeleteField)
+         .mutating(DatabaseEvent::ClearField)
+         .mutating(DatabaseEvent::UpdateFieldType)
+         .mutating(DatabaseEvent::DuplicateField)
+         .mutating(DatabaseEvent::MoveField)
+         .mutating(DatabaseEvent::CreateField)
+         .mutating(DatabaseEvent::CreateRow)
+         .mutating(DatabaseEvent::UpdateRowMeta)
+         .mutating(DatabaseEvent::DeleteRows)
+         .mutating(DatabaseEvent::DuplicateRow)
+         .mutating(DatabaseEvent::MoveRow)
+         .mutating(DatabaseEvent::RemoveCover)
+         .mutating(DatabaseEvent::UpdateCell)
+         .mutating(DatabaseEvent::CreateSelectOption)
+         .mutating(DatabaseEvent::InsertOrUpdateSelectOption)
+         .mutating(DatabaseEvent::DeleteSelectOption)
+         .mutating(DatabaseEvent::UpdateSelectOptionCell)
+         .mutating(DatabaseEvent::UpdateChecklistCell)
+         .mutating(DatabaseEvent::UpdateDateCell)
+         .mutating(DatabaseEvent::SetGroupByField)
+         .mutating(DatabaseEvent::MoveGroup)
+         .mutating(DatabaseEvent::MoveGroupRow)
+         .mutating(DatabaseEvent::UpdateGroup)
+         .mutating(DatabaseEvent::CreateGroup)
+         .mutating(DatabaseEvent::DeleteGroup)
+         .mutating(DatabaseEvent::MoveCalendarEvent)
+         .mutating(DatabaseEvent::SetLayoutSetting)
+         .mutating(DatabaseEvent::CreateDatabaseView)
+         .mutating(DatabaseEvent::UpdateFieldSettings)
+         .mutating(DatabaseEvent::UpdateCalculation)
+         .mutating(DatabaseEvent::RemoveCalculation)
+         .mutating(DatabaseEvent::UpdateRelationCell)
+         .mutating(DatabaseEvent::SummarizeRow)
+         .mutating(DatabaseEvent::TranslateRow)
+         .mutating(DatabaseEvent::UpdateMediaCell)
+         .mutating(DatabaseEvent::RenameMediaFile)
 }
 
 /// [DatabaseEvent] defines events that are used to interact with the Grid. You could check [this](https://appflowy.gitbook.io/docs/essential-documentation/contribute-to-appflowy/architecture/backend/protobuf)
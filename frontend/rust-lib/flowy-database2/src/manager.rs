@@ -35,6 +35,7 @@ use lib_infra::box_any::BoxAny;
 use lib_infra::priority_task::TaskDispatcher;
 
 use crate::entities::{DatabaseLayoutPB, DatabaseSnapshotPB, FieldType, RowMetaPB};
+use crate::notification::register_notification_filters;
 use crate::services::cell::stringify_cell;
 use crate::services::database::DatabaseEditor;
 use crate::services::database_view::DatabaseLayoutDepsResolver;
@@ -70,6 +71,7 @@ impl DatabaseManager {
     cloud_service: Arc<dyn DatabaseCloudService>,
     ai_service: Arc<dyn DatabaseAIService>,
   ) -> Self {
+    register_notification_filters();
     Self {
       user: database_user,
       workspace_database_manager: Default::default(),
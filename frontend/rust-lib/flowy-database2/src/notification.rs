@@ -1,5 +1,8 @@
 use flowy_derive::ProtoBuf_Enum;
-use flowy_notification::NotificationBuilder;
+use flowy_notification::{register_typed_payload_view, NotificationBuilder, TypedNotification};
+use serde_json::json;
+
+use crate::entities::RowsChangePB;
 
 pub(crate) const DATABASE_OBSERVABLE_SOURCE: &str = "Database";
 
@@ -89,6 +92,42 @@ impl std::convert::From<i32> for DatabaseNotification {
   }
 }
 
+/// The row changes of a database view, sent with [DatabaseNotification::DidUpdateRow].
+impl TypedNotification for RowsChangePB {
+  const SOURCE: &'static str = DATABASE_OBSERVABLE_SOURCE;
+  const TY: i32 = DatabaseNotification::DidUpdateRow as i32;
+}
+
+/// Lets the client subscribe to the row changes of the rows it displays only, e.g. with the
+/// filter `row_ids in ["r1", "r2"]`.
+pub(crate) fn register_notification_filters() {
+  register_typed_payload_view(|changes: &RowsChangePB| {
+    let inserted_row_ids: Vec<&str> = changes
+      .inserted_rows
+      .iter()
+      .map(|row| row.row_meta.id.as_str())
+      .collect();
+    let updated_row_ids: Vec<&str> = changes
+      .updated_rows
+      .iter()
+      .map(|row| row.row_id.as_str())
+      .collect();
+    let row_ids: Vec<&str> = inserted_row_ids
+      .iter()
+      .copied()
+      .chain(changes.deleted_rows.iter().map(|id| id.as_str()))
+      .chain(updated_row_ids.iter().copied())
+      .collect();
+    json!({
+      "row_ids": row_ids,
+      "inserted_row_ids": inserted_row_ids,
+      "deleted_row_ids": changes.deleted_rows,
+      "updated_row_ids": updated_row_ids,
+      "is_move_row": changes.is_move_row,
+    })
+  });
+}
+
 #[tracing::instrument(level = "trace")]
 pub fn send_notification(id: &str, ty: DatabaseNotification) -> NotificationBuilder {
   #[cfg(feature = "verbose_log")]
@@ -8,6 +8,7 @@ use crate::event_handler::query_date_handler;
 pub fn init() -> AFPlugin {
   AFPlugin::new()
     .name(env!("CARGO_PKG_NAME"))
+    .proto_descriptors(crate::protobuf::file_descriptors())
     .event(DateEvent::QueryDate, query_date_handler)
 }
 
@@ -0,0 +1,188 @@
+//! The CRDT layer of the documents. Instead of the text deltas, the peers exchange the updates
+//! of the [yrs] document, which converge whatever the order they are applied in.
+//!
+//! The state vector of a peer summarizes the updates it applied. The update encoded since the
+//! state vector of another peer holds what that peer misses, so two peers are in sync once each
+//! applied the update encoded since its own state vector by the other one.
+
+use flowy_error::{FlowyError, FlowyResult};
+use yrs::types::ToJson;
+use yrs::updates::decoder::Decode;
+use yrs::updates::encoder::Encode;
+use yrs::{
+  Any, Array, ArrayRef, Doc, GetString, Map, MapRef, Out, ReadTxn, StateVector, Text, TextRef,
+  Transact, TransactionMut, Update,
+};
+
+/// The state vector of the updates applied by `txn`, encoded as a payload.
+pub fn encode_state_vector<T: ReadTxn>(txn: &T) -> Vec<u8> {
+  txn.state_vector().encode_v1()
+}
+
+/// Encodes what the peer whose state vector is `state_vector` misses. An empty state vector
+/// encodes the whole document.
+pub fn encode_update_since<T: ReadTxn>(txn: &T, state_vector: &[u8]) -> FlowyResult<Vec<u8>> {
+  let state_vector = if state_vector.is_empty() {
+    StateVector::default()
+  } else {
+    StateVector::decode_v1(state_vector).map_err(|err| {
+      FlowyError::invalid_data().with_context(format!("Invalid state vector: {}", err))
+    })?
+  };
+  Ok(txn.encode_state_as_update_v1(&state_vector))
+}
+
+/// Applies an update encoded by [encode_update_since]. Applying the same update twice, or the
+/// updates in any order, leads to the same state.
+pub fn apply_update(txn: &mut TransactionMut, update: &[u8]) -> FlowyResult<()> {
+  let update = Update::decode_v1(update)
+    .map_err(|err| FlowyError::invalid_data().with_context(format!("Invalid update: {}", err)))?;
+  txn
+    .apply_update(update)
+    .map_err(|err| FlowyError::internal().with_context(format!("Failed to apply update: {}", err)))
+}
+
+/// A standalone CRDT document holding named text, map and list types.
+pub struct CrdtDoc {
+  doc: Doc,
+}
+
+impl CrdtDoc {
+  /// `client_id` identifies the peer, it must be unique among the peers editing the document.
+  pub fn new(client_id: u64) -> Self {
+    Self {
+      doc: Doc::with_client_id(client_id),
+    }
+  }
+
+  pub fn text(&self, name: &str) -> CrdtText {
+    CrdtText {
+      doc: self.doc.clone(),
+      text: self.doc.get_or_insert_text(name),
+    }
+  }
+
+  pub fn map(&self, name: &str) -> CrdtMap {
+    CrdtMap {
+      doc: self.doc.clone(),
+      map: self.doc.get_or_insert_map(name),
+    }
+  }
+
+  pub fn list(&self, name: &str) -> CrdtList {
+    CrdtList {
+      doc: self.doc.clone(),
+      list: self.doc.get_or_insert_array(name),
+    }
+  }
+
+  pub fn state_vector(&self) -> Vec<u8> {
+    encode_state_vector(&self.doc.transact())
+  }
+
+  /// See [encode_update_since].
+  pub fn encode_update(&self, state_vector: &[u8]) -> FlowyResult<Vec<u8>> {
+    encode_update_since(&self.doc.transact(), state_vector)
+  }
+
+  /// See [apply_update].
+  pub fn apply_update(&self, update: &[u8]) -> FlowyResult<()> {
+    apply_update(&mut self.doc.transact_mut(), update)
+  }
+}
+
+pub struct CrdtText {
+  doc: Doc,
+  text: TextRef,
+}
+
+impl CrdtText {
+  /// `index` counts the characters, like the indexes of the text deltas.
+  pub fn insert(&self, index: u32, chunk: &str) {
+    self.text.insert(&mut self.doc.transact_mut(), index, chunk);
+  }
+
+  pub fn remove(&self, index: u32, len: u32) {
+    self
+      .text
+      .remove_range(&mut self.doc.transact_mut(), index, len);
+  }
+
+  pub fn len(&self) -> u32 {
+    self.text.len(&self.doc.transact())
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  pub fn get_string(&self) -> String {
+    self.text.get_string(&self.doc.transact())
+  }
+}
+
+pub struct CrdtMap {
+  doc: Doc,
+  map: MapRef,
+}
+
+impl CrdtMap {
+  pub fn insert<V: Into<Any>>(&self, key: &str, value: V) {
+    self
+      .map
+      .insert(&mut self.doc.transact_mut(), key, value.into());
+  }
+
+  pub fn get(&self, key: &str) -> Option<Any> {
+    match self.map.get(&self.doc.transact(), key)? {
+      Out::Any(value) => Some(value),
+      _ => None,
+    }
+  }
+
+  /// Returns whether the key had a value.
+  pub fn remove(&self, key: &str) -> bool {
+    self.map.remove(&mut self.doc.transact_mut(), key).is_some()
+  }
+
+  pub fn to_json(&self) -> Any {
+    self.map.to_json(&self.doc.transact())
+  }
+}
+
+pub struct CrdtList {
+  doc: Doc,
+  list: ArrayRef,
+}
+
+impl CrdtList {
+  pub fn push<V: Into<Any>>(&self, value: V) {
+    self
+      .list
+      .push_back(&mut self.doc.transact_mut(), value.into());
+  }
+
+  pub fn insert<V: Into<Any>>(&self, index: u32, value: V) {
+    self
+      .list
+      .insert(&mut self.doc.transact_mut(), index, value.into());
+  }
+
+  pub fn remove(&self, index: u32, len: u32) {
+    self
+      .list
+      .remove_range(&mut self.doc.transact_mut(), index, len);
+  }
+
+  pub fn len(&self) -> u32 {
+    self.list.len(&self.doc.transact())
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  pub fn to_json(&self) -> Any {
+    self.list.to_json(&self.doc.transact())
+  }
+}
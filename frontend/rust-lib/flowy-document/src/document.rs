@@ -4,8 +4,20 @@ use crate::entities::{
 use crate::notification::{send_notification, DocumentNotification};
 use collab::preclude::Collab;
 use collab_document::document::Document;
+use flowy_notification::{CoalesceStrategy, NotificationCoalescer};
 use futures::StreamExt;
 use lib_dispatch::prelude::af_spawn;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// The awareness states carry the full state of the collaborators, so only the last one of each
+/// window is delivered.
+fn awareness_coalescer() -> &'static NotificationCoalescer {
+  static COALESCER: OnceLock<NotificationCoalescer> = OnceLock::new();
+  COALESCER.get_or_init(|| {
+    NotificationCoalescer::new(Duration::from_millis(100), CoalesceStrategy::KeepLatest)
+  })
+}
 
 pub fn subscribe_document_changed(doc_id: &str, document: &mut Document) {
   let doc_id_clone_for_block_changed = doc_id.to_owned();
@@ -26,12 +38,13 @@ pub fn subscribe_document_changed(doc_id: &str, document: &mut Document) {
   document.subscribe_awareness_state("key", move |events| {
     #[cfg(feature = "verbose_log")]
     tracing::trace!("subscribe_awareness_state: {:?}", events);
-    send_notification(
+    let subject = send_notification(
       &doc_id_clone_for_awareness_state,
       DocumentNotification::DidUpdateDocumentAwarenessState,
     )
     .payload::<DocumentAwarenessStatesPB>(events.into())
-    .send();
+    .build();
+    awareness_coalescer().send_subject(subject);
   });
 }
 
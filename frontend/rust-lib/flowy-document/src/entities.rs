@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::ops::Range;
 
 use collab::core::collab_state::SyncState;
 use collab_document::{
@@ -14,6 +15,7 @@ use flowy_error::ErrorCode;
 use lib_infra::validator_fn::{required_not_empty_str, required_valid_path};
 use validator::Validate;
 
+use crate::history::{DocumentEditEvent, DocumentEditKind, DocumentHistorySnapshot};
 use crate::parse::{NotEmptyStr, NotEmptyVec};
 
 #[derive(Default, ProtoBuf)]
@@ -163,7 +165,7 @@ impl TryInto<CloseDocumentParams> for CloseDocumentPayloadPB {
   }
 }
 
-#[derive(Default, ProtoBuf, Debug)]
+#[derive(Default, ProtoBuf, Debug, Clone)]
 pub struct ApplyActionPayloadPB {
   #[pb(index = 1)]
   pub document_id: String,
@@ -264,7 +266,7 @@ pub struct ChildrenPB {
   pub children: Vec<String>,
 }
 // Actions
-#[derive(Default, ProtoBuf, Debug)]
+#[derive(Default, ProtoBuf, Debug, Clone)]
 pub struct BlockActionPB {
   #[pb(index = 1)]
   pub action: BlockActionTypePB,
@@ -273,7 +275,7 @@ pub struct BlockActionPB {
   pub payload: BlockActionPayloadPB,
 }
 
-#[derive(Default, ProtoBuf, Debug)]
+#[derive(Default, ProtoBuf, Debug, Clone)]
 pub struct BlockActionPayloadPB {
   // When action = Insert, Update, Delete or Move, block needs to be passed.
   #[pb(index = 1, one_of)]
@@ -297,7 +299,7 @@ pub struct BlockActionPayloadPB {
   pub delta: Option<String>,
 }
 
-#[derive(ProtoBuf_Enum, Debug)]
+#[derive(ProtoBuf_Enum, Debug, Clone)]
 pub enum BlockActionTypePB {
   Insert = 0,
   Update = 1,
@@ -478,6 +480,149 @@ pub struct DocumentSnapshotStatePB {
   pub new_snapshot_id: i64,
 }
 
+#[derive(Default, ProtoBuf)]
+pub struct DocumentHistoryQueryPB {
+  #[pb(index = 1)]
+  pub document_id: String,
+
+  /// The sequence number of the first event, included.
+  #[pb(index = 2)]
+  pub from_seq: i64,
+
+  /// The sequence number of the last event, excluded. Up to the last event if unset.
+  #[pb(index = 3, one_of)]
+  pub to_seq: Option<i64>,
+
+  #[pb(index = 4)]
+  pub limit: i64,
+}
+
+pub struct DocumentHistoryQueryParams {
+  pub document_id: String,
+  pub seqs: Range<i64>,
+  pub limit: usize,
+}
+
+impl TryInto<DocumentHistoryQueryParams> for DocumentHistoryQueryPB {
+  type Error = ErrorCode;
+  fn try_into(self) -> Result<DocumentHistoryQueryParams, Self::Error> {
+    let document_id =
+      NotEmptyStr::parse(self.document_id).map_err(|_| ErrorCode::DocumentIdIsEmpty)?;
+    let limit = if self.limit > 0 {
+      self.limit as usize
+    } else {
+      DEFAULT_HISTORY_LIMIT
+    };
+    Ok(DocumentHistoryQueryParams {
+      document_id: document_id.0,
+      seqs: self.from_seq..self.to_seq.unwrap_or(i64::MAX),
+      limit,
+    })
+  }
+}
+
+/// The number of events returned by a history query without a limit.
+pub const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+#[derive(Debug, Default, ProtoBuf_Enum, PartialEq, Eq, Clone, Copy)]
+pub enum DocumentEditKindPB {
+  #[default]
+  ApplyAction = 0,
+  CreateText = 1,
+  ApplyTextDelta = 2,
+  ApplyUpdate = 3,
+}
+
+impl From<DocumentEditKind> for DocumentEditKindPB {
+  fn from(kind: DocumentEditKind) -> Self {
+    match kind {
+      DocumentEditKind::ApplyAction => DocumentEditKindPB::ApplyAction,
+      DocumentEditKind::CreateText => DocumentEditKindPB::CreateText,
+      DocumentEditKind::ApplyTextDelta => DocumentEditKindPB::ApplyTextDelta,
+      DocumentEditKind::ApplyUpdate => DocumentEditKindPB::ApplyUpdate,
+    }
+  }
+}
+
+#[derive(Debug, Default, ProtoBuf)]
+pub struct DocumentEditEventPB {
+  #[pb(index = 1)]
+  pub seq: i64,
+
+  #[pb(index = 2)]
+  pub kind: DocumentEditKindPB,
+
+  /// The payload of the event that applied the edit, e.g. an [ApplyActionPayloadPB].
+  #[pb(index = 3)]
+  pub payload: Vec<u8>,
+
+  #[pb(index = 4)]
+  pub created_at: i64,
+}
+
+impl From<DocumentEditEvent> for DocumentEditEventPB {
+  fn from(event: DocumentEditEvent) -> Self {
+    Self {
+      seq: event.seq,
+      kind: event.kind.into(),
+      payload: event.payload,
+      created_at: event.created_at,
+    }
+  }
+}
+
+/// Sent once an edit of the document is appended to its history.
+#[derive(Debug, Default, ProtoBuf)]
+pub struct DocumentChangedPB {
+  #[pb(index = 1)]
+  pub document_id: String,
+
+  /// The sequence number of the edit in the history.
+  #[pb(index = 2)]
+  pub seq: i64,
+
+  #[pb(index = 3)]
+  pub kind: DocumentEditKindPB,
+}
+
+#[derive(Debug, Default, ProtoBuf)]
+pub struct RepeatedDocumentEditEventPB {
+  #[pb(index = 1)]
+  pub items: Vec<DocumentEditEventPB>,
+}
+
+/// The state of the document once the events up to `seq` were applied. The history starts after
+/// it.
+#[derive(Debug, Default, ProtoBuf)]
+pub struct DocumentHistorySnapshotPB {
+  #[pb(index = 1)]
+  pub document_id: String,
+
+  #[pb(index = 2)]
+  pub seq: i64,
+
+  #[pb(index = 3)]
+  pub state_vector: Vec<u8>,
+
+  #[pb(index = 4)]
+  pub doc_state: Vec<u8>,
+
+  #[pb(index = 5)]
+  pub created_at: i64,
+}
+
+impl From<DocumentHistorySnapshot> for DocumentHistorySnapshotPB {
+  fn from(snapshot: DocumentHistorySnapshot) -> Self {
+    Self {
+      document_id: snapshot.document_id,
+      seq: snapshot.seq,
+      state_vector: snapshot.state_vector,
+      doc_state: snapshot.doc_state,
+      created_at: snapshot.created_at,
+    }
+  }
+}
+
 #[derive(Debug, Default, ProtoBuf)]
 pub struct DocumentSyncStatePB {
   #[pb(index = 1)]
@@ -505,7 +650,7 @@ impl From<SyncState> for DocumentSyncStatePB {
   }
 }
 
-#[derive(Default, ProtoBuf, Debug)]
+#[derive(Default, ProtoBuf, Debug, Clone)]
 pub struct TextDeltaPayloadPB {
   #[pb(index = 1)]
   pub document_id: String,
@@ -538,6 +683,60 @@ impl TryInto<TextDeltaParams> for TextDeltaPayloadPB {
   }
 }
 
+/// A CRDT update of the document, see [crate::crdt].
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct DocumentUpdatePB {
+  #[pb(index = 1)]
+  pub document_id: String,
+
+  #[pb(index = 2)]
+  pub update: Vec<u8>,
+}
+
+pub struct DocumentUpdateParams {
+  pub document_id: String,
+  pub update: Vec<u8>,
+}
+
+impl TryInto<DocumentUpdateParams> for DocumentUpdatePB {
+  type Error = ErrorCode;
+  fn try_into(self) -> Result<DocumentUpdateParams, Self::Error> {
+    let document_id =
+      NotEmptyStr::parse(self.document_id).map_err(|_| ErrorCode::DocumentIdIsEmpty)?;
+    Ok(DocumentUpdateParams {
+      document_id: document_id.0,
+      update: self.update,
+    })
+  }
+}
+
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct DocumentStateVectorPB {
+  #[pb(index = 1)]
+  pub document_id: String,
+
+  /// The state vector of the peer asking for the update. Empty to get the whole document.
+  #[pb(index = 2)]
+  pub state_vector: Vec<u8>,
+}
+
+pub struct DocumentStateVectorParams {
+  pub document_id: String,
+  pub state_vector: Vec<u8>,
+}
+
+impl TryInto<DocumentStateVectorParams> for DocumentStateVectorPB {
+  type Error = ErrorCode;
+  fn try_into(self) -> Result<DocumentStateVectorParams, Self::Error> {
+    let document_id =
+      NotEmptyStr::parse(self.document_id).map_err(|_| ErrorCode::DocumentIdIsEmpty)?;
+    Ok(DocumentStateVectorParams {
+      document_id: document_id.0,
+      state_vector: self.state_vector,
+    })
+  }
+}
+
 pub struct DocumentSnapshotMeta {
   pub snapshot_id: String,
   pub object_id: String,
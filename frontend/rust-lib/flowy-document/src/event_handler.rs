@@ -16,6 +16,7 @@ use lib_dispatch::prelude::{data_result_ok, AFPluginData, AFPluginState, DataRes
 use tracing::instrument;
 
 use crate::entities::*;
+use crate::history::DocumentEditKind;
 use crate::parser::document_data_parser::DocumentDataParser;
 use crate::parser::external::parser::ExternalDataToNestedJSONParser;
 use crate::parser::parser_entities::{
@@ -120,7 +121,8 @@ pub(crate) async fn apply_action_handler(
   manager: AFPluginState<Weak<DocumentManager>>,
 ) -> FlowyResult<()> {
   let manager = upgrade_document(manager)?;
-  let params: ApplyActionParams = data.into_inner().try_into()?;
+  let payload = data.into_inner();
+  let params: ApplyActionParams = payload.clone().try_into()?;
   let doc_id = params.document_id;
   let document = manager.editable_document(&doc_id).await?;
   let actions = params.actions;
@@ -128,6 +130,7 @@ pub(crate) async fn apply_action_handler(
     tracing::trace!("{} applying actions: {:?}", doc_id, actions);
   }
   document.write().await.apply_action(actions)?;
+  manager.record_edit(&doc_id, DocumentEditKind::ApplyAction, payload);
   Ok(())
 }
 
@@ -137,11 +140,13 @@ pub(crate) async fn create_text_handler(
   manager: AFPluginState<Weak<DocumentManager>>,
 ) -> FlowyResult<()> {
   let manager = upgrade_document(manager)?;
-  let params: TextDeltaParams = data.into_inner().try_into()?;
+  let payload = data.into_inner();
+  let params: TextDeltaParams = payload.clone().try_into()?;
   let doc_id = params.document_id;
   let document = manager.editable_document(&doc_id).await?;
   let mut document = document.write().await;
   document.apply_text_delta(&params.text_id, params.delta);
+  manager.record_edit(&doc_id, DocumentEditKind::CreateText, payload);
   Ok(())
 }
 
@@ -151,7 +156,8 @@ pub(crate) async fn apply_text_delta_handler(
   manager: AFPluginState<Weak<DocumentManager>>,
 ) -> FlowyResult<()> {
   let manager = upgrade_document(manager)?;
-  let params: TextDeltaParams = data.into_inner().try_into()?;
+  let payload = data.into_inner();
+  let params: TextDeltaParams = payload.clone().try_into()?;
   let doc_id = params.document_id;
   let document = manager.editable_document(&doc_id).await?;
   let text_id = params.text_id;
@@ -161,6 +167,7 @@ pub(crate) async fn apply_text_delta_handler(
     tracing::trace!("{} applying delta: {:?}", doc_id, delta);
   }
   document.apply_text_delta(&text_id, delta);
+  manager.record_edit(&doc_id, DocumentEditKind::ApplyTextDelta, payload);
   Ok(())
 }
 
@@ -264,6 +271,69 @@ pub(crate) async fn get_snapshot_data_handler(
   data_result_ok(snapshot)
 }
 
+pub(crate) async fn get_document_history_handler(
+  data: AFPluginData<DocumentHistoryQueryPB>,
+  manager: AFPluginState<Weak<DocumentManager>>,
+) -> DataResult<RepeatedDocumentEditEventPB, FlowyError> {
+  let manager = upgrade_document(manager)?;
+  let params: DocumentHistoryQueryParams = data.into_inner().try_into()?;
+  let events = manager.get_document_history(params).await?;
+  data_result_ok(RepeatedDocumentEditEventPB { items: events })
+}
+
+pub(crate) async fn get_document_history_snapshot_handler(
+  data: AFPluginData<OpenDocumentPayloadPB>,
+  manager: AFPluginState<Weak<DocumentManager>>,
+) -> DataResult<DocumentHistorySnapshotPB, FlowyError> {
+  let manager = upgrade_document(manager)?;
+  let params: OpenDocumentParams = data.into_inner().try_into()?;
+  let snapshot = manager
+    .get_document_history_snapshot(&params.document_id)
+    .await?;
+  data_result_ok(snapshot)
+}
+
+pub(crate) async fn compact_document_history_handler(
+  data: AFPluginData<OpenDocumentPayloadPB>,
+  manager: AFPluginState<Weak<DocumentManager>>,
+) -> DataResult<DocumentHistorySnapshotPB, FlowyError> {
+  let manager = upgrade_document(manager)?;
+  let params: OpenDocumentParams = data.into_inner().try_into()?;
+  let snapshot = manager
+    .compact_document_history(&params.document_id)
+    .await?;
+  data_result_ok(snapshot)
+}
+
+pub(crate) async fn apply_document_update_handler(
+  data: AFPluginData<DocumentUpdatePB>,
+  manager: AFPluginState<Weak<DocumentManager>>,
+) -> FlowyResult<()> {
+  let manager = upgrade_document(manager)?;
+  let payload = data.into_inner();
+  let params: DocumentUpdateParams = payload.clone().try_into()?;
+  manager
+    .apply_document_update(&params.document_id, &params.update)
+    .await?;
+  manager.record_edit(&params.document_id, DocumentEditKind::ApplyUpdate, payload);
+  Ok(())
+}
+
+pub(crate) async fn get_document_update_handler(
+  data: AFPluginData<DocumentStateVectorPB>,
+  manager: AFPluginState<Weak<DocumentManager>>,
+) -> DataResult<DocumentUpdatePB, FlowyError> {
+  let manager = upgrade_document(manager)?;
+  let params: DocumentStateVectorParams = data.into_inner().try_into()?;
+  let update = manager
+    .encode_document_update(&params.document_id, &params.state_vector)
+    .await?;
+  data_result_ok(DocumentUpdatePB {
+    document_id: params.document_id,
+    update,
+  })
+}
+
 impl From<BlockActionPB> for BlockAction {
   fn from(pb: BlockActionPB) -> Self {
     Self {
@@ -12,6 +12,7 @@ use crate::{event_handler::*, manager::DocumentManager};
 pub fn init(document_manager: Weak<DocumentManager>) -> AFPlugin {
   AFPlugin::new()
     .name(env!("CARGO_PKG_NAME"))
+    .proto_descriptors(crate::protobuf::file_descriptors())
     .state(document_manager)
     .event(DocumentEvent::CreateDocument, create_document_handler)
     .event(DocumentEvent::OpenDocument, open_document_handler)
@@ -52,6 +53,37 @@ pub fn init(document_manager: Weak<DocumentManager>) -> AFPlugin {
       DocumentEvent::SetAwarenessState,
       set_awareness_local_state_handler,
     )
+    .event(
+      DocumentEvent::GetDocumentHistory,
+      get_document_history_handler,
+    )
+    .event(
+      DocumentEvent::GetDocumentHistorySnapshot,
+      get_document_history_snapshot_handler,
+    )
+    .event(
+      DocumentEvent::CompactDocumentHistory,
+      compact_document_history_handler,
+    )
+    .event(
+      DocumentEvent::ApplyDocumentUpdate,
+      apply_document_update_handler,
+    )
+    .event(
+      DocumentEvent::GetDocumentUpdate,
+      get_document_update_handler,
+    )
+    // Recorded in the audit log
+    .mutating(DocumentEvent::CreateDocument)
+    .mutating(DocumentEvent::ApplyAction)
+    .mutating(DocumentEvent::Redo)
+    .mutating(DocumentEvent::Undo)
+    .mutating(DocumentEvent::CreateText)
+    .mutating(DocumentEvent::ApplyTextDeltaEvent)
+    .mutating(DocumentEvent::UploadFile)
+    .mutating(DocumentEvent::DeleteFile)
+    .mutating(DocumentEvent::CompactDocumentHistory)
+    .mutating(DocumentEvent::ApplyDocumentUpdate)
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Display, ProtoBuf_Enum, Flowy_Event)]
@@ -137,4 +169,25 @@ pub enum DocumentEvent {
 
   #[event(input = "OpenDocumentPayloadPB", output = "DocumentTextPB")]
   GetDocumentText = 20,
+
+  /// The edits recorded since the latest history snapshot, see [crate::history].
+  #[event(
+    input = "DocumentHistoryQueryPB",
+    output = "RepeatedDocumentEditEventPB"
+  )]
+  GetDocumentHistory = 21,
+
+  #[event(input = "OpenDocumentPayloadPB", output = "DocumentHistorySnapshotPB")]
+  GetDocumentHistorySnapshot = 22,
+
+  #[event(input = "OpenDocumentPayloadPB", output = "DocumentHistorySnapshotPB")]
+  CompactDocumentHistory = 23,
+
+  /// Applies a CRDT update of another peer, see [crate::crdt].
+  #[event(input = "DocumentUpdatePB")]
+  ApplyDocumentUpdate = 24,
+
+  /// Encodes what the peer whose state vector is in the payload misses.
+  #[event(input = "DocumentStateVectorPB", output = "DocumentUpdatePB")]
+  GetDocumentUpdate = 25,
 }
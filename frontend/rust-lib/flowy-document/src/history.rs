@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+use collab::entity::EncodedCollab;
+use flowy_error::FlowyResult;
+use lib_infra::util::timestamp;
+use tracing::warn;
+
+/// The event a [DocumentEdit] was applied by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentEditKind {
+  ApplyAction = 0,
+  CreateText = 1,
+  ApplyTextDelta = 2,
+  ApplyUpdate = 3,
+}
+
+impl DocumentEditKind {
+  pub fn from_i32(value: i32) -> Option<Self> {
+    match value {
+      0 => Some(DocumentEditKind::ApplyAction),
+      1 => Some(DocumentEditKind::CreateText),
+      2 => Some(DocumentEditKind::ApplyTextDelta),
+      3 => Some(DocumentEditKind::ApplyUpdate),
+      _ => None,
+    }
+  }
+}
+
+/// An edit applied to a document. The payload is the protobuf payload of the event that applied
+/// it, e.g. the `ApplyActionPayloadPB` for [DocumentEditKind::ApplyAction].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentEdit {
+  pub kind: DocumentEditKind,
+  pub payload: Vec<u8>,
+}
+
+/// A [DocumentEdit] appended to the history of a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentEditEvent {
+  /// Increases with each event appended to the store, across all the documents.
+  pub seq: i64,
+  pub document_id: String,
+  pub kind: DocumentEditKind,
+  pub payload: Vec<u8>,
+  pub created_at: i64,
+}
+
+/// The state of a document once the events up to `seq` included were applied. The history only
+/// keeps the events that come after its latest snapshot, see [DocumentHistory::compact].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentHistorySnapshot {
+  pub document_id: String,
+  pub seq: i64,
+  pub state_vector: Vec<u8>,
+  pub doc_state: Vec<u8>,
+  pub created_at: i64,
+}
+
+/// Persists the history of the documents. The events are never updated, only appended and
+/// deleted once a snapshot covers them.
+pub trait DocumentEventStore: Send + Sync {
+  /// Appends `edit` to the history of the document and returns its sequence number.
+  fn append(&self, document_id: &str, edit: DocumentEdit, created_at: i64) -> FlowyResult<i64>;
+
+  /// The events of the document whose sequence number is in `seqs`, in order, at most `limit`.
+  fn read_range(
+    &self,
+    document_id: &str,
+    seqs: Range<i64>,
+    limit: usize,
+  ) -> FlowyResult<Vec<DocumentEditEvent>>;
+
+  /// The sequence number of the last event of the document, if any.
+  fn last_seq(&self, document_id: &str) -> FlowyResult<Option<i64>>;
+
+  fn save_snapshot(&self, snapshot: DocumentHistorySnapshot) -> FlowyResult<()>;
+
+  fn latest_snapshot(&self, document_id: &str) -> FlowyResult<Option<DocumentHistorySnapshot>>;
+
+  /// Deletes the events of the document up to `seq` included, and the snapshots before the one
+  /// at `seq`. Returns the number of deleted events.
+  fn compact(&self, document_id: &str, seq: i64) -> FlowyResult<usize>;
+}
+
+/// Records the edits applied to the documents and answers the history queries of the client.
+#[derive(Clone)]
+pub struct DocumentHistory {
+  store: Arc<dyn DocumentEventStore>,
+}
+
+impl DocumentHistory {
+  pub fn new(store: Arc<dyn DocumentEventStore>) -> Self {
+    Self { store }
+  }
+
+  /// Appends `edit` to the history of the document and returns its sequence number. A failure
+  /// is only logged, the edit itself was already applied.
+  pub fn record(&self, document_id: &str, edit: DocumentEdit) -> Option<i64> {
+    match self.store.append(document_id, edit, timestamp()) {
+      Ok(seq) => Some(seq),
+      Err(err) => {
+        warn!("Failed to record an edit of {}: {}", document_id, err);
+        None
+      },
+    }
+  }
+
+  pub fn events(
+    &self,
+    document_id: &str,
+    seqs: Range<i64>,
+    limit: usize,
+  ) -> FlowyResult<Vec<DocumentEditEvent>> {
+    self.store.read_range(document_id, seqs, limit)
+  }
+
+  pub fn latest_snapshot(&self, document_id: &str) -> FlowyResult<Option<DocumentHistorySnapshot>> {
+    self.store.latest_snapshot(document_id)
+  }
+
+  /// Saves `state`, the current state of the document, as a snapshot at its last event and
+  /// deletes the events it covers. Returns `None` if nothing was recorded since the latest
+  /// snapshot.
+  pub fn compact(
+    &self,
+    document_id: &str,
+    state: EncodedCollab,
+  ) -> FlowyResult<Option<DocumentHistorySnapshot>> {
+    let seq = match self.store.last_seq(document_id)? {
+      Some(seq) => seq,
+      None => return Ok(None),
+    };
+    let snapshot = DocumentHistorySnapshot {
+      document_id: document_id.to_string(),
+      seq,
+      state_vector: state.state_vector.to_vec(),
+      doc_state: state.doc_state.to_vec(),
+      created_at: timestamp(),
+    };
+    self.store.save_snapshot(snapshot.clone())?;
+    self.store.compact(document_id, seq)?;
+    Ok(Some(snapshot))
+  }
+}
+
+/// Keeps the history in memory, for the tests and the platforms without sqlite.
+#[derive(Default)]
+pub struct MemoryDocumentEventStore {
+  inner: Mutex<MemoryHistory>,
+}
+
+#[derive(Default)]
+struct MemoryHistory {
+  last_seq: i64,
+  events: HashMap<String, Vec<DocumentEditEvent>>,
+  snapshots: HashMap<String, DocumentHistorySnapshot>,
+}
+
+impl DocumentEventStore for MemoryDocumentEventStore {
+  fn append(&self, document_id: &str, edit: DocumentEdit, created_at: i64) -> FlowyResult<i64> {
+    let mut inner = self.inner.lock().unwrap();
+    inner.last_seq += 1;
+    let seq = inner.last_seq;
+    inner
+      .events
+      .entry(document_id.to_string())
+      .or_default()
+      .push(DocumentEditEvent {
+        seq,
+        document_id: document_id.to_string(),
+        kind: edit.kind,
+        payload: edit.payload,
+        created_at,
+      });
+    Ok(seq)
+  }
+
+  fn read_range(
+    &self,
+    document_id: &str,
+    seqs: Range<i64>,
+    limit: usize,
+  ) -> FlowyResult<Vec<DocumentEditEvent>> {
+    let inner = self.inner.lock().unwrap();
+    Ok(
+      inner
+        .events
+        .get(document_id)
+        .into_iter()
+        .flatten()
+        .filter(|event| seqs.contains(&event.seq))
+        .take(limit)
+        .cloned()
+        .collect(),
+    )
+  }
+
+  fn last_seq(&self, document_id: &str) -> FlowyResult<Option<i64>> {
+    let inner = self.inner.lock().unwrap();
+    Ok(
+      inner
+        .events
+        .get(document_id)
+        .and_then(|events| events.last())
+        .map(|event| event.seq),
+    )
+  }
+
+  fn save_snapshot(&self, snapshot: DocumentHistorySnapshot) -> FlowyResult<()> {
+    let mut inner = self.inner.lock().unwrap();
+    inner
+      .snapshots
+      .insert(snapshot.document_id.clone(), snapshot);
+    Ok(())
+  }
+
+  fn latest_snapshot(&self, document_id: &str) -> FlowyResult<Option<DocumentHistorySnapshot>> {
+    let inner = self.inner.lock().unwrap();
+    Ok(inner.snapshots.get(document_id).cloned())
+  }
+
+  fn compact(&self, document_id: &str, seq: i64) -> FlowyResult<usize> {
+    let mut inner = self.inner.lock().unwrap();
+    let events = match inner.events.get_mut(document_id) {
+      Some(events) => events,
+      None => return Ok(0),
+    };
+    let len = events.len();
+    events.retain(|event| event.seq > seq);
+    Ok(len - events.len())
+  }
+}
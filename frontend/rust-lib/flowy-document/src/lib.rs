@@ -1,8 +1,10 @@
+pub mod crdt;
 pub mod document;
 pub mod document_data;
 pub mod entities;
 pub mod event_handler;
 pub mod event_map;
+pub mod history;
 pub mod manager;
 pub mod parser;
 pub mod protobuf;
@@ -17,9 +17,10 @@ use collab_entity::CollabType;
 use collab_plugins::CollabKVDB;
 use dashmap::DashMap;
 use lib_infra::util::timestamp;
+use tracing::{error, info, trace};
 use tracing::{event, instrument};
-use tracing::{info, trace};
 
+use crate::crdt;
 use crate::document::{
   subscribe_document_changed, subscribe_document_snapshot_state, subscribe_document_sync_state,
 };
@@ -29,12 +30,15 @@ use collab_integrate::collab_builder::{
 use flowy_document_pub::cloud::DocumentCloudService;
 use flowy_error::{internal_error, ErrorCode, FlowyError, FlowyResult};
 use flowy_storage_pub::storage::{CreatedUpload, StorageService};
-use lib_dispatch::prelude::af_spawn;
+use lib_dispatch::prelude::{af_spawn, ToBytes};
 
 use crate::entities::UpdateDocumentAwarenessStatePB;
 use crate::entities::{
+  DocumentChangedPB, DocumentEditEventPB, DocumentHistoryQueryParams, DocumentHistorySnapshotPB,
   DocumentSnapshotData, DocumentSnapshotMeta, DocumentSnapshotMetaPB, DocumentSnapshotPB,
 };
+use crate::history::{DocumentEdit, DocumentEditKind, DocumentEventStore, DocumentHistory};
+use crate::notification::{send_notification, DocumentNotification};
 use crate::reminder::DocumentReminderAction;
 
 pub trait DocumentUserService: Send + Sync {
@@ -60,6 +64,7 @@ pub struct DocumentManager {
   cloud_service: Arc<dyn DocumentCloudService>,
   storage_service: Weak<dyn StorageService>,
   snapshot_service: Arc<dyn DocumentSnapshotService>,
+  history: DocumentHistory,
 }
 
 impl DocumentManager {
@@ -69,6 +74,7 @@ impl DocumentManager {
     cloud_service: Arc<dyn DocumentCloudService>,
     storage_service: Weak<dyn StorageService>,
     snapshot_service: Arc<dyn DocumentSnapshotService>,
+    history_store: Arc<dyn DocumentEventStore>,
   ) -> Self {
     Self {
       user_service,
@@ -78,6 +84,7 @@ impl DocumentManager {
       cloud_service,
       storage_service,
       snapshot_service,
+      history: DocumentHistory::new(history_store),
     }
   }
 
@@ -397,7 +404,101 @@ impl DocumentManager {
     Ok(snapshot)
   }
 
+  /// Appends the payload of the event that edited the document to its history, and notifies the
+  /// client with a [DocumentChangedPB].
+  pub fn record_edit<T: ToBytes>(&self, doc_id: &str, kind: DocumentEditKind, payload: T) {
+    let payload = match payload.into_bytes() {
+      Ok(payload) => payload,
+      Err(err) => {
+        error!("Failed to encode an edit of {}: {:?}", doc_id, err);
+        return;
+      },
+    };
+    let edit = DocumentEdit {
+      kind,
+      payload: payload.to_vec(),
+    };
+    if let Some(seq) = self.history.record(doc_id, edit) {
+      send_notification(doc_id, DocumentNotification::DidChangeDocument)
+        .payload(DocumentChangedPB {
+          document_id: doc_id.to_string(),
+          seq,
+          kind: kind.into(),
+        })
+        .send();
+    }
+  }
+
+  /// Return the edits recorded since the latest history snapshot of the document.
+  pub async fn get_document_history(
+    &self,
+    params: DocumentHistoryQueryParams,
+  ) -> FlowyResult<Vec<DocumentEditEventPB>> {
+    let events = self
+      .history
+      .events(&params.document_id, params.seqs, params.limit)?
+      .into_iter()
+      .map(DocumentEditEventPB::from)
+      .collect();
+    Ok(events)
+  }
+
+  pub async fn get_document_history_snapshot(
+    &self,
+    doc_id: &str,
+  ) -> FlowyResult<DocumentHistorySnapshotPB> {
+    self
+      .history
+      .latest_snapshot(doc_id)?
+      .map(DocumentHistorySnapshotPB::from)
+      .ok_or_else(|| {
+        FlowyError::record_not_found()
+          .with_context(format!("The history of {} has no snapshot", doc_id))
+      })
+  }
+
+  /// Snapshots the current state of the document and drops the edits it covers from the history.
+  pub async fn compact_document_history(
+    &self,
+    doc_id: &str,
+  ) -> FlowyResult<DocumentHistorySnapshotPB> {
+    let document = self.editable_document(doc_id).await?;
+    let state = document.read().await.encode_collab()?;
+    match self.history.compact(doc_id, state)? {
+      Some(snapshot) => Ok(snapshot.into()),
+      None => self.get_document_history_snapshot(doc_id).await,
+    }
+  }
+
   #[instrument(level = "debug", skip_all, err)]
+  /// Applies the CRDT update of another peer to the opened document, see [crate::crdt].
+  pub async fn apply_document_update(&self, doc_id: &str, update: &[u8]) -> FlowyResult<()> {
+    let document = self.editable_document(doc_id).await?;
+    let mut document = document.write().await;
+    let mut txn = document.transact_mut();
+    crdt::apply_update(&mut txn, update)
+  }
+
+  /// Encodes what the peer whose state vector is `state_vector` misses of the opened document.
+  pub async fn encode_document_update(
+    &self,
+    doc_id: &str,
+    state_vector: &[u8],
+  ) -> FlowyResult<Vec<u8>> {
+    let document = self.editable_document(doc_id).await?;
+    let document = document.read().await;
+    let txn = document.transact();
+    crdt::encode_update_since(&txn, state_vector)
+  }
+
+  /// The state vector of the opened document.
+  pub async fn document_state_vector(&self, doc_id: &str) -> FlowyResult<Vec<u8>> {
+    let document = self.editable_document(doc_id).await?;
+    let document = document.read().await;
+    let txn = document.transact();
+    Ok(crdt::encode_state_vector(&txn))
+  }
+
   pub async fn upload_file(
     &self,
     workspace_id: String,
@@ -1,5 +1,7 @@
 use flowy_derive::ProtoBuf_Enum;
-use flowy_notification::NotificationBuilder;
+use flowy_notification::{NotificationBuilder, NotificationType, TypedNotification};
+
+use crate::entities::{DocEventPB, DocumentChangedPB};
 
 const DOCUMENT_OBSERVABLE_SOURCE: &str = "Document";
 
@@ -12,6 +14,7 @@ pub enum DocumentNotification {
   DidUpdateDocumentSnapshotState = 2,
   DidUpdateDocumentSyncState = 3,
   DidUpdateDocumentAwarenessState = 4,
+  DidChangeDocument = 5,
 }
 
 impl std::convert::From<DocumentNotification> for i32 {
@@ -26,12 +29,30 @@ impl std::convert::From<i32> for DocumentNotification {
       2 => DocumentNotification::DidUpdateDocumentSnapshotState,
       3 => DocumentNotification::DidUpdateDocumentSyncState,
       4 => DocumentNotification::DidUpdateDocumentAwarenessState,
+      5 => DocumentNotification::DidChangeDocument,
       _ => DocumentNotification::Unknown,
     }
   }
 }
 
+impl NotificationType for DocumentNotification {
+  const SOURCE: &'static str = DOCUMENT_OBSERVABLE_SOURCE;
+}
+
+/// The block changes of a document, observed with `observe::<DocEventPB>(doc_id)`.
+impl TypedNotification for DocEventPB {
+  const SOURCE: &'static str = DOCUMENT_OBSERVABLE_SOURCE;
+  const TY: i32 = DocumentNotification::DidReceiveUpdate as i32;
+}
+
+/// The edits appended to the history of a document, observed with
+/// `observe::<DocumentChangedPB>(doc_id)`.
+impl TypedNotification for DocumentChangedPB {
+  const SOURCE: &'static str = DOCUMENT_OBSERVABLE_SOURCE;
+  const TY: i32 = DocumentNotification::DidChangeDocument as i32;
+}
+
 #[tracing::instrument(level = "trace")]
 pub(crate) fn send_notification(id: &str, ty: DocumentNotification) -> NotificationBuilder {
-  NotificationBuilder::new(id, ty, DOCUMENT_OBSERVABLE_SOURCE)
+  NotificationBuilder::typed(id, ty)
 }
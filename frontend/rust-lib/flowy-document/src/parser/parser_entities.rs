@@ -2,7 +2,7 @@ use crate::parse::NotEmptyStr;
 use crate::parser::constant::*;
 use crate::parser::utils::{
   convert_insert_delta_from_json, convert_nested_block_children_to_html, delta_to_html,
-  delta_to_text, required_not_empty_str, serialize_color_attribute,
+  delta_to_markdown, delta_to_text, required_not_empty_str, serialize_color_attribute,
 };
 use flowy_derive::{ProtoBuf, ProtoBuf_Enum};
 use flowy_error::ErrorCode;
@@ -163,6 +163,33 @@ impl InsertDelta {
     self.insert.clone()
   }
 
+  /// The colors, the underline and the formulas have no markdown syntax, their text is kept as
+  /// is.
+  pub fn to_markdown(&self) -> String {
+    let attrs = match &self.attributes {
+      Some(attrs) => attrs,
+      None => return self.insert.clone(),
+    };
+    let enabled = |key: &str| attrs.get(key).and_then(Value::as_bool).unwrap_or(false);
+    let mut markdown = self.insert.clone();
+    if enabled(CODE) {
+      markdown = format!("`{}`", markdown);
+    }
+    if enabled(STRIKETHROUGH) {
+      markdown = format!("~~{}~~", markdown);
+    }
+    if enabled(ITALIC) {
+      markdown = format!("_{}_", markdown);
+    }
+    if enabled(BOLD) {
+      markdown = format!("**{}**", markdown);
+    }
+    if let Some(href) = attrs.get(HREF).and_then(Value::as_str) {
+      markdown = format!("[{}]({})", markdown, href);
+    }
+    markdown
+  }
+
   pub fn to_html(&self) -> String {
     let mut html = String::new();
     let mut style = String::new();
@@ -491,6 +518,138 @@ impl NestedBlock {
     };
     text
   }
+
+  /// Converts the block and its children to markdown. The children of the list items are
+  /// indented under their item, the children of the other blocks follow them.
+  pub fn convert_to_markdown(&self) -> String {
+    let mut markdown = String::new();
+    self.push_markdown(&mut markdown, "", 0);
+    let markdown = markdown.trim_end();
+    if markdown.is_empty() {
+      String::new()
+    } else {
+      format!("{}\n", markdown)
+    }
+  }
+
+  /// `number` is the position of the block among the numbered list items it follows.
+  fn push_markdown(&self, markdown: &mut String, indent: &str, number: usize) {
+    let delta = self
+      .data
+      .get(DELTA)
+      .and_then(convert_insert_delta_from_json)
+      .unwrap_or_default();
+    let text = delta_to_markdown(&delta);
+    let data_str = |key: &str| {
+      self
+        .data
+        .get(key)
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_owned()
+    };
+
+    let mut child_indent = indent.to_owned();
+    match self.ty.as_str() {
+      PAGE => {
+        if !text.is_empty() {
+          push_markdown_lines(markdown, indent, indent, &text);
+          markdown.push('\n');
+        }
+      },
+      HEADING => {
+        let level = self
+          .data
+          .get(LEVEL)
+          .and_then(Value::as_u64)
+          .unwrap_or(1)
+          .clamp(1, 6) as usize;
+        let prefix = format!("{}{} ", indent, "#".repeat(level));
+        push_markdown_lines(markdown, &prefix, indent, &text);
+        markdown.push('\n');
+      },
+      BULLETED_LIST | TOGGLE_LIST | TODO_LIST | NUMBERED_LIST => {
+        let marker = match self.ty.as_str() {
+          NUMBERED_LIST => format!("{}. ", number.max(1)),
+          TODO_LIST => {
+            let checked = self
+              .data
+              .get(CHECKED)
+              .and_then(Value::as_bool)
+              .unwrap_or_default();
+            format!("- [{}] ", if checked { "x" } else { " " })
+          },
+          _ => "- ".to_owned(),
+        };
+        // The lines of the item and its children are aligned with the text after the marker.
+        let marker_width = if self.ty == TODO_LIST {
+          2
+        } else {
+          marker.len()
+        };
+        child_indent.push_str(&" ".repeat(marker_width));
+        let prefix = format!("{}{}", indent, marker);
+        push_markdown_lines(markdown, &prefix, &child_indent, &text);
+      },
+      QUOTE => {
+        let prefix = format!("{}> ", indent);
+        push_markdown_lines(markdown, &prefix, &prefix, &text);
+        markdown.push('\n');
+      },
+      CALLOUT => {
+        let prefix = format!("{}> ", indent);
+        let text = format!("{} {}", data_str(ICON), text);
+        push_markdown_lines(markdown, &prefix, &prefix, text.trim_start());
+        markdown.push('\n');
+      },
+      CODE => {
+        let fence = format!("{}```", indent);
+        markdown.push_str(&format!("{}{}\n", fence, data_str(LANGUAGE)));
+        push_markdown_lines(markdown, indent, indent, &delta_to_text(&delta));
+        markdown.push_str(&format!("{}\n\n", fence));
+      },
+      DIVIDER => markdown.push_str(&format!("{}---\n\n", indent)),
+      IMAGE => markdown.push_str(&format!("{}![]({})\n\n", indent, data_str(URL))),
+      MATH_EQUATION => {
+        markdown.push_str(&format!("{}$${}$$\n\n", indent, data_str(FORMULA)));
+      },
+      _ => {
+        push_markdown_lines(markdown, indent, indent, &text);
+        markdown.push('\n');
+      },
+    }
+
+    // A list item ends with a single line break, the blocks following a list are separated from
+    // it by a blank line so they don't continue its text.
+    let mut after_list_item = is_list_block(&self.ty);
+    let mut number = 0;
+    for child in &self.children {
+      let is_list_item = is_list_block(&child.ty);
+      if after_list_item && !is_list_item {
+        markdown.push('\n');
+      }
+      number = if child.ty == NUMBERED_LIST {
+        number + 1
+      } else {
+        0
+      };
+      child.push_markdown(markdown, &child_indent, number);
+      after_list_item = is_list_item;
+    }
+  }
+}
+
+fn is_list_block(ty: &str) -> bool {
+  matches!(ty, BULLETED_LIST | NUMBERED_LIST | TODO_LIST | TOGGLE_LIST)
+}
+
+/// Pushes the lines of `text`, the first one prefixed with `first`, the others with `rest`.
+fn push_markdown_lines(markdown: &mut String, first: &str, rest: &str, text: &str) {
+  for (i, line) in text.split('\n').enumerate() {
+    markdown.push_str(if i == 0 { first } else { rest });
+    markdown.push_str(line);
+    markdown.push('\n');
+  }
 }
 
 pub struct ConvertBlockToHtmlParams {
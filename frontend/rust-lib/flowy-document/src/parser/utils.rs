@@ -63,6 +63,14 @@ pub fn delta_to_text(delta: &Vec<InsertDelta>) -> String {
   result
 }
 
+pub fn delta_to_markdown(delta: &[InsertDelta]) -> String {
+  let mut result = String::new();
+  for d in delta {
+    result.push_str(d.to_markdown().as_str());
+  }
+  result
+}
+
 pub fn delta_to_html(delta: &Vec<InsertDelta>) -> String {
   let mut result = String::new();
   for d in delta {
@@ -0,0 +1,194 @@
+use collab_document::document_data::default_document_data;
+use flowy_document::crdt::CrdtDoc;
+use yrs::updates::decoder::Decode;
+use yrs::{Any, StateVector};
+
+use crate::document::util::{gen_document_id, DocumentTest};
+
+/// The encoding of a state vector depends on the order its clients were added in.
+fn decode(state_vector: Vec<u8>) -> StateVector {
+  StateVector::decode_v1(&state_vector).unwrap()
+}
+
+/// Applies the updates to each peer in a different order.
+fn deliver(peers: &[CrdtDoc], updates: &[Vec<u8>]) {
+  for (i, peer) in peers.iter().enumerate() {
+    for j in 0..updates.len() {
+      let update = &updates[(i + j) % updates.len()];
+      peer.apply_update(update).unwrap();
+    }
+  }
+}
+
+/// The update of each peer since the state they started from.
+fn local_updates(peers: &[CrdtDoc], base: &[u8]) -> Vec<Vec<u8>> {
+  peers
+    .iter()
+    .map(|peer| peer.encode_update(base).unwrap())
+    .collect()
+}
+
+fn peers_from(origin: &CrdtDoc, count: u64) -> Vec<CrdtDoc> {
+  let state = origin.encode_update(&[]).unwrap();
+  (1..=count)
+    .map(|client_id| {
+      let peer = CrdtDoc::new(client_id + 1);
+      peer.apply_update(&state).unwrap();
+      peer
+    })
+    .collect()
+}
+
+#[test]
+fn crdt_text_convergence_test() {
+  let origin = CrdtDoc::new(1);
+  origin.text("text").insert(0, "hello world");
+  let base = origin.state_vector();
+  let peers = peers_from(&origin, 3);
+
+  peers[0].text("text").insert(5, ",");
+  peers[1].text("text").remove(0, 1);
+  peers[1].text("text").insert(0, "H");
+  peers[2].text("text").insert(11, "!");
+
+  deliver(&peers, &local_updates(&peers, &base));
+  let text = peers[0].text("text").get_string();
+  assert_eq!(text, "Hello, world!");
+  for peer in &peers {
+    assert_eq!(peer.text("text").get_string(), text);
+    assert_eq!(decode(peer.state_vector()), decode(peers[0].state_vector()));
+  }
+}
+
+#[test]
+fn crdt_concurrent_insert_at_same_index_test() {
+  let origin = CrdtDoc::new(1);
+  let base = origin.state_vector();
+  let peers = peers_from(&origin, 3);
+  for (peer, chunk) in peers.iter().zip(["a", "b", "c"]) {
+    peer.text("text").insert(0, chunk);
+  }
+
+  deliver(&peers, &local_updates(&peers, &base));
+  let text = peers[0].text("text").get_string();
+  assert_eq!(text.len(), 3);
+  for peer in &peers {
+    assert_eq!(peer.text("text").get_string(), text);
+  }
+}
+
+#[test]
+fn crdt_map_convergence_test() {
+  let origin = CrdtDoc::new(1);
+  origin.map("meta").insert("title", "untitled");
+  origin.map("meta").insert("icon", "📄");
+  let base = origin.state_vector();
+  let peers = peers_from(&origin, 3);
+
+  peers[0].map("meta").insert("title", "Notes");
+  peers[1].map("meta").insert("title", "Todos");
+  peers[1].map("meta").insert("archived", true);
+  assert!(peers[2].map("meta").remove("icon"));
+
+  deliver(&peers, &local_updates(&peers, &base));
+  let meta = peers[0].map("meta");
+  assert_eq!(meta.get("icon"), None);
+  assert_eq!(meta.get("archived"), Some(Any::Bool(true)));
+  let title = meta.get("title").unwrap();
+  assert!(title == Any::from("Notes") || title == Any::from("Todos"));
+  for peer in &peers {
+    assert_eq!(peer.map("meta").to_json(), meta.to_json());
+  }
+}
+
+#[test]
+fn crdt_list_convergence_test() {
+  let origin = CrdtDoc::new(1);
+  for item in ["a", "b", "c"] {
+    origin.list("items").push(item);
+  }
+  let base = origin.state_vector();
+  let peers = peers_from(&origin, 3);
+
+  peers[0].list("items").remove(1, 1);
+  peers[1].list("items").insert(0, "first");
+  peers[2].list("items").push("last");
+
+  deliver(&peers, &local_updates(&peers, &base));
+  let items = peers[0].list("items");
+  assert_eq!(
+    items.to_json(),
+    Any::from(vec![
+      Any::from("first"),
+      Any::from("a"),
+      Any::from("c"),
+      Any::from("last")
+    ])
+  );
+  for peer in &peers {
+    assert_eq!(peer.list("items").to_json(), items.to_json());
+    assert_eq!(peer.list("items").len(), 4);
+  }
+}
+
+#[test]
+fn crdt_update_idempotent_test() {
+  let peer_1 = CrdtDoc::new(1);
+  let peer_2 = CrdtDoc::new(2);
+  peer_1.text("text").insert(0, "abc");
+  let update = peer_1.encode_update(&peer_2.state_vector()).unwrap();
+  peer_2.apply_update(&update).unwrap();
+  peer_2.apply_update(&update).unwrap();
+  assert_eq!(peer_2.text("text").get_string(), "abc");
+
+  assert!(peer_2.apply_update(&[0xff, 0xff]).is_err());
+}
+
+#[tokio::test]
+async fn document_crdt_update_sync_test() {
+  let test = DocumentTest::new();
+  let uid = test.user_service.user_id().unwrap();
+  let doc_id = gen_document_id();
+  let data = default_document_data(&doc_id);
+  test
+    .create_document(uid, &doc_id, Some(data))
+    .await
+    .unwrap();
+  test.open_document(&doc_id).await.unwrap();
+  let document = test.editable_document(&doc_id).await.unwrap();
+
+  // The peer catches up with the whole document
+  let peer = CrdtDoc::new(42);
+  let update = test.encode_document_update(&doc_id, &[]).await.unwrap();
+  peer.apply_update(&update).unwrap();
+  assert_eq!(
+    decode(peer.state_vector()),
+    decode(test.document_state_vector(&doc_id).await.unwrap())
+  );
+
+  // Then receives the edits it misses
+  document
+    .write()
+    .await
+    .apply_text_delta("text", r#"[{"insert":"hello"}]"#.to_string());
+  let update = test
+    .encode_document_update(&doc_id, &peer.state_vector())
+    .await
+    .unwrap();
+  peer.apply_update(&update).unwrap();
+  assert_eq!(
+    decode(peer.state_vector()),
+    decode(test.document_state_vector(&doc_id).await.unwrap())
+  );
+
+  // And the document applies the edits of the peer
+  peer.map("peer").insert("name", "peer");
+  let update = peer
+    .encode_update(&test.document_state_vector(&doc_id).await.unwrap())
+    .unwrap();
+  test.apply_document_update(&doc_id, &update).await.unwrap();
+  assert_eq!(
+    decode(peer.state_vector()),
+    decode(test.document_state_vector(&doc_id).await.unwrap())
+  );
+}
@@ -0,0 +1,106 @@
+use collab_document::document_data::default_document_data;
+use flowy_document::entities::{
+  DocumentChangedPB, DocumentEditKindPB, DocumentHistoryQueryParams, TextDeltaPayloadPB,
+};
+use flowy_document::history::DocumentEditKind;
+use flowy_notification::observe;
+
+use crate::document::util::{gen_document_id, DocumentTest};
+
+fn text_delta(doc_id: &str, delta: &str) -> TextDeltaPayloadPB {
+  TextDeltaPayloadPB {
+    document_id: doc_id.to_string(),
+    text_id: "text".to_string(),
+    delta: Some(delta.to_string()),
+  }
+}
+
+fn query(doc_id: &str, from_seq: i64) -> DocumentHistoryQueryParams {
+  DocumentHistoryQueryParams {
+    document_id: doc_id.to_string(),
+    seqs: from_seq..i64::MAX,
+    limit: 100,
+  }
+}
+
+#[tokio::test]
+async fn document_history_test() {
+  let test = DocumentTest::new();
+  let uid = test.user_service.user_id().unwrap();
+  let doc_id = gen_document_id();
+  let data = default_document_data(&doc_id);
+  test
+    .create_document(uid, &doc_id, Some(data))
+    .await
+    .unwrap();
+
+  for delta in [r#"[{"insert":"a"}]"#, r#"[{"insert":"b"}]"#] {
+    test.record_edit(
+      &doc_id,
+      DocumentEditKind::ApplyTextDelta,
+      text_delta(&doc_id, delta),
+    );
+  }
+  let events = test.get_document_history(query(&doc_id, 0)).await.unwrap();
+  assert_eq!(events.len(), 2);
+  assert!(events[0].seq < events[1].seq);
+
+  // Read the range after the first event only.
+  let events = test
+    .get_document_history(query(&doc_id, events[1].seq))
+    .await
+    .unwrap();
+  assert_eq!(events.len(), 1);
+  let last_seq = events[0].seq;
+
+  // The snapshot covers the recorded events, which are dropped from the history.
+  let snapshot = test.compact_document_history(&doc_id).await.unwrap();
+  assert_eq!(snapshot.seq, last_seq);
+  assert!(!snapshot.doc_state.is_empty());
+  assert!(test
+    .get_document_history(query(&doc_id, 0))
+    .await
+    .unwrap()
+    .is_empty());
+
+  test.record_edit(
+    &doc_id,
+    DocumentEditKind::ApplyTextDelta,
+    text_delta(&doc_id, r#"[{"insert":"c"}]"#),
+  );
+  let events = test.get_document_history(query(&doc_id, 0)).await.unwrap();
+  assert_eq!(events.len(), 1);
+  assert!(events[0].seq > snapshot.seq);
+  assert_eq!(
+    test
+      .get_document_history_snapshot(&doc_id)
+      .await
+      .unwrap()
+      .seq,
+    last_seq
+  );
+}
+
+#[tokio::test]
+async fn document_changed_notification_test() {
+  let test = DocumentTest::new();
+  let uid = test.user_service.user_id().unwrap();
+  let doc_id = gen_document_id();
+  let data = default_document_data(&doc_id);
+  test
+    .create_document(uid, &doc_id, Some(data))
+    .await
+    .unwrap();
+
+  let mut changes = observe::<DocumentChangedPB>(&doc_id);
+  test.record_edit(
+    &doc_id,
+    DocumentEditKind::ApplyTextDelta,
+    text_delta(&doc_id, r#"[{"insert":"a"}]"#),
+  );
+  let change = changes.recv().await.unwrap();
+  let events = test.get_document_history(query(&doc_id, 0)).await.unwrap();
+  assert_eq!(change.document_id, doc_id);
+  assert_eq!(change.seq, events[0].seq);
+  assert_eq!(change.kind, DocumentEditKindPB::ApplyTextDelta);
+}
@@ -1,3 +1,5 @@
+mod document_crdt_test;
+mod document_history_test;
 mod document_insert_test;
 mod document_redo_undo_test;
 mod document_test;
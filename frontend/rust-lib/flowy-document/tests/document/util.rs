@@ -18,6 +18,7 @@ use collab_integrate::collab_builder::{
 };
 use collab_integrate::CollabKVDB;
 use flowy_document::entities::{DocumentSnapshotData, DocumentSnapshotMeta};
+use flowy_document::history::MemoryDocumentEventStore;
 use flowy_document::manager::{DocumentManager, DocumentSnapshotService, DocumentUserService};
 use flowy_document_pub::cloud::*;
 use flowy_error::{ErrorCode, FlowyError, FlowyResult};
@@ -50,6 +51,7 @@ impl DocumentTest {
       cloud_service,
       Arc::downgrade(&file_storage),
       document_snapshot,
+      Arc::new(MemoryDocumentEventStore::default()),
     );
     Self { inner: manager }
   }
@@ -1,4 +1,6 @@
-use crate::parser::parse_to_html_text::utils::{assert_document_html_eq, assert_document_text_eq};
+use crate::parser::parse_to_html_text::utils::{
+  assert_document_html_eq, assert_document_markdown_eq, assert_document_text_eq,
+};
 
 macro_rules! generate_test_cases {
     ($($block_ty:ident),*) => {
@@ -35,3 +37,32 @@ async fn block_tests() {
     assert_document_text_eq(json_data, expect_text);
   }
 }
+
+macro_rules! generate_markdown_test_cases {
+    ($($block_ty:ident),*) => {
+        [
+            $(
+                (
+                    include_str!(concat!("../../assets/json/", stringify!($block_ty), ".json")),
+                    include_str!(concat!("../../assets/markdown/", stringify!($block_ty), ".md")),
+                )
+            ),*
+        ]
+    };
+}
+
+#[tokio::test]
+async fn block_markdown_tests() {
+  let test_cases = generate_markdown_test_cases!(
+    heading,
+    divider,
+    code,
+    bulleted_list,
+    numbered_list,
+    todo_list,
+    quote
+  );
+  for (json_data, expect_markdown) in test_cases.iter() {
+    assert_document_markdown_eq(json_data, expect_markdown);
+  }
+}
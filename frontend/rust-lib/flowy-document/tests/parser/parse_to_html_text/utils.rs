@@ -19,3 +19,12 @@ pub fn assert_document_text_eq(source: &str, expect: &str) {
   let text = parser.to_text();
   assert_eq!(expect, text);
 }
+
+pub fn assert_document_markdown_eq(source: &str, expect: &str) {
+  let document_data = JsonToDocumentParser::json_str_to_document(source)
+    .unwrap()
+    .into();
+  let parser = DocumentDataParser::new(Arc::new(document_data), None);
+  let markdown = parser.to_markdown();
+  assert_eq!(expect, markdown);
+}
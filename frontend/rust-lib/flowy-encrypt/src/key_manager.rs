@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::aead::Aead;
+use aes_gcm::{Aes256Gcm, KeyInit};
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use pbkdf2::hmac::Hmac;
+use pbkdf2::pbkdf2;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+/// The length of the local encryption key in bytes.
+pub const ENCRYPTION_KEY_LENGTH: usize = 32;
+
+/// The number of PBKDF2 iterations for the new keys. The keys derived before keep the count they
+/// were derived with.
+const KEY_DERIVATION_ITERATIONS: u32 = 100_000;
+
+const KEY_SALT_LENGTH: usize = 16;
+
+const KEY_NONCE_LENGTH: usize = 12;
+
+/// The file, next to the encrypted data, holding the salt the key is derived with.
+const KEY_PARAMS_FILE: &str = "encryption_key.json";
+
+/// The name of the key in the keyring.
+const KEYRING_KEY_NAME: &str = "appflowy.local_encryption_key";
+
+/// Encrypted with the key to tell whether a passphrase derives the right key.
+const KEY_VERIFIER: &[u8] = b"appflowy";
+
+/// The key encrypting the local data at rest with AES-256-GCM.
+#[derive(Clone, PartialEq, Eq)]
+pub struct EncryptionKey([u8; ENCRYPTION_KEY_LENGTH]);
+
+impl EncryptionKey {
+  pub fn derive(passphrase: &str, salt: &[u8], iterations: u32) -> Result<Self> {
+    let mut key = [0u8; ENCRYPTION_KEY_LENGTH];
+    pbkdf2::<Hmac<Sha256>>(passphrase.as_bytes(), salt, iterations, &mut key)?;
+    Ok(Self(key))
+  }
+
+  pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+    let key = bytes
+      .try_into()
+      .map_err(|_| anyhow!("Incorrect encryption key length"))?;
+    Ok(Self(key))
+  }
+
+  pub fn as_bytes(&self) -> &[u8] {
+    &self.0
+  }
+
+  /// Returns the nonce followed by the ciphertext.
+  pub fn encrypt(&self, data: &[u8]) -> Result<Vec<u8>> {
+    let cipher = Aes256Gcm::new(GenericArray::from_slice(&self.0));
+    let nonce: [u8; KEY_NONCE_LENGTH] = rand::thread_rng().gen();
+    let ciphertext = cipher
+      .encrypt(GenericArray::from_slice(&nonce), data)
+      .map_err(|e| anyhow!("Encryption error: {:?}", e))?;
+    Ok(nonce.into_iter().chain(ciphertext).collect())
+  }
+
+  pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>> {
+    if data.len() <= KEY_NONCE_LENGTH {
+      return Err(anyhow!("Ciphertext too short to include nonce."));
+    }
+    let cipher = Aes256Gcm::new(GenericArray::from_slice(&self.0));
+    let (nonce, ciphertext) = data.split_at(KEY_NONCE_LENGTH);
+    cipher
+      .decrypt(GenericArray::from_slice(nonce), ciphertext)
+      .map_err(|e| anyhow!("Decryption error: {:?}", e))
+  }
+}
+
+impl fmt::Debug for EncryptionKey {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str("EncryptionKey(**)")
+  }
+}
+
+/// The hooks to the keyring of the OS, e.g. the Keychain on macOS or the Credential Manager on
+/// Windows, implemented by the host of the app.
+pub trait Keyring: Send + Sync {
+  fn get_secret(&self, name: &str) -> Result<Option<String>>;
+  fn set_secret(&self, name: &str, secret: &str) -> Result<()>;
+  fn delete_secret(&self, name: &str) -> Result<()>;
+}
+
+/// A [Keyring] forgetting the secrets when the app exits, for the platforms without a keyring.
+#[derive(Default)]
+pub struct MemoryKeyring {
+  secrets: Mutex<HashMap<String, String>>,
+}
+
+impl Keyring for MemoryKeyring {
+  fn get_secret(&self, name: &str) -> Result<Option<String>> {
+    Ok(self.secrets.lock().unwrap().get(name).cloned())
+  }
+
+  fn set_secret(&self, name: &str, secret: &str) -> Result<()> {
+    self
+      .secrets
+      .lock()
+      .unwrap()
+      .insert(name.to_owned(), secret.to_owned());
+    Ok(())
+  }
+
+  fn delete_secret(&self, name: &str) -> Result<()> {
+    self.secrets.lock().unwrap().remove(name);
+    Ok(())
+  }
+}
+
+#[derive(Serialize, Deserialize)]
+struct KeyParams {
+  salt: String,
+  iterations: u32,
+  verifier: String,
+}
+
+/// Manages the key encrypting the local data. The key is derived from a passphrase chosen by the
+/// user, then kept in the [Keyring] so the app can open the data without asking for the
+/// passphrase again. Only the salt and a verifier of the key are written to disk.
+pub struct KeyManager {
+  params_path: PathBuf,
+  keyring: Arc<dyn Keyring>,
+  key: RwLock<Option<EncryptionKey>>,
+}
+
+impl KeyManager {
+  pub fn new<P: AsRef<Path>>(dir: P, keyring: Arc<dyn Keyring>) -> Self {
+    Self {
+      params_path: dir.as_ref().join(KEY_PARAMS_FILE),
+      keyring,
+      key: RwLock::new(None),
+    }
+  }
+
+  /// Whether a passphrase was set up, i.e. the data may be encrypted.
+  pub fn is_configured(&self) -> bool {
+    self.params_path.exists()
+  }
+
+  /// Derives the key from a new passphrase. Fails if a passphrase was already set up, as the
+  /// data encrypted with the previous key couldn't be read anymore.
+  pub fn setup(&self, passphrase: &str) -> Result<EncryptionKey> {
+    if self.is_configured() {
+      return Err(anyhow!("The encryption passphrase is already set up"));
+    }
+    let salt: [u8; KEY_SALT_LENGTH] = rand::thread_rng().gen();
+    let key = EncryptionKey::derive(passphrase, &salt, KEY_DERIVATION_ITERATIONS)?;
+    let params = KeyParams {
+      salt: STANDARD.encode(salt),
+      iterations: KEY_DERIVATION_ITERATIONS,
+      verifier: STANDARD.encode(key.encrypt(KEY_VERIFIER)?),
+    };
+    fs::write(&self.params_path, serde_json::to_vec(&params)?)?;
+    self.remember(&key)?;
+    Ok(key)
+  }
+
+  /// Derives the key from the passphrase set up before, e.g. when the keyring lost it.
+  pub fn unlock(&self, passphrase: &str) -> Result<EncryptionKey> {
+    let params = self.read_params()?;
+    let key = EncryptionKey::derive(
+      passphrase,
+      &STANDARD.decode(&params.salt)?,
+      params.iterations,
+    )?;
+    if !verify(&key, &params) {
+      return Err(anyhow!("Invalid encryption passphrase"));
+    }
+    self.remember(&key)?;
+    Ok(key)
+  }
+
+  /// Returns the key if it's unlocked or kept in the keyring, `None` otherwise.
+  pub fn key(&self) -> Result<Option<EncryptionKey>> {
+    if let Some(key) = self.key.read().unwrap().clone() {
+      return Ok(Some(key));
+    }
+    if !self.is_configured() {
+      return Ok(None);
+    }
+    let secret = match self.keyring.get_secret(KEYRING_KEY_NAME)? {
+      None => return Ok(None),
+      Some(secret) => secret,
+    };
+    let key = EncryptionKey::from_bytes(&STANDARD.decode(secret)?)?;
+    if !verify(&key, &self.read_params()?) {
+      return Err(anyhow!("The keyring holds the key of another passphrase"));
+    }
+    *self.key.write().unwrap() = Some(key.clone());
+    Ok(Some(key))
+  }
+
+  /// Forgets the key, the passphrase is asked again to [KeyManager::unlock] the data.
+  pub fn lock(&self) -> Result<()> {
+    *self.key.write().unwrap() = None;
+    self.keyring.delete_secret(KEYRING_KEY_NAME)
+  }
+
+  fn remember(&self, key: &EncryptionKey) -> Result<()> {
+    self
+      .keyring
+      .set_secret(KEYRING_KEY_NAME, &STANDARD.encode(key.as_bytes()))?;
+    *self.key.write().unwrap() = Some(key.clone());
+    Ok(())
+  }
+
+  fn read_params(&self) -> Result<KeyParams> {
+    if !self.is_configured() {
+      return Err(anyhow!("The encryption passphrase is not set up"));
+    }
+    Ok(serde_json::from_slice(&fs::read(&self.params_path)?)?)
+  }
+}
+
+fn verify(key: &EncryptionKey, params: &KeyParams) -> bool {
+  STANDARD
+    .decode(&params.verifier)
+    .ok()
+    .and_then(|verifier| key.decrypt(&verifier).ok())
+    .map(|verifier| verifier == KEY_VERIFIER)
+    .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+  use tempfile::TempDir;
+
+  use super::*;
+
+  #[test]
+  fn key_manager_test() {
+    let tempdir = TempDir::new().unwrap();
+    let keyring = Arc::new(MemoryKeyring::default());
+    let manager = KeyManager::new(tempdir.path(), keyring.clone());
+    assert!(manager.key().unwrap().is_none());
+    assert!(manager.unlock("passphrase").is_err());
+
+    let key = manager.setup("passphrase").unwrap();
+    assert!(manager.setup("another passphrase").is_err());
+    let encrypted = key.encrypt(b"hello world").unwrap();
+    assert_eq!(key.decrypt(&encrypted).unwrap(), b"hello world");
+
+    // The key is read back from the keyring after a restart
+    let manager = KeyManager::new(tempdir.path(), keyring.clone());
+    assert_eq!(manager.key().unwrap(), Some(key.clone()));
+
+    manager.lock().unwrap();
+    assert!(manager.key().unwrap().is_none());
+    assert!(manager.unlock("wrong passphrase").is_err());
+    assert_eq!(manager.unlock("passphrase").unwrap(), key);
+    assert_eq!(manager.key().unwrap(), Some(key));
+  }
+
+  #[test]
+  fn decrypt_with_another_key_test() {
+    let key = EncryptionKey::derive("passphrase", b"salt", 1000).unwrap();
+    let other = EncryptionKey::derive("passphrase", b"other salt", 1000).unwrap();
+    let encrypted = key.encrypt(b"hello world").unwrap();
+    assert!(other.decrypt(&encrypted).is_err());
+    assert!(key.decrypt(&encrypted[..8]).is_err());
+  }
+}
@@ -1,3 +1,9 @@
 pub use encrypt::*;
+pub use key_manager::*;
+#[cfg(feature = "os_keyring")]
+pub use os_keyring::*;
 
 mod encrypt;
+mod key_manager;
+#[cfg(feature = "os_keyring")]
+mod os_keyring;
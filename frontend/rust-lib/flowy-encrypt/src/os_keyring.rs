@@ -0,0 +1,42 @@
+use anyhow::Result;
+
+use crate::Keyring;
+
+/// The [Keyring] of the OS: the Keychain on macOS, the Credential Manager on Windows and the
+/// Secret Service on Linux. The secrets are stored under `service`, e.g. the name of the app.
+pub struct OsKeyring {
+  service: String,
+}
+
+impl OsKeyring {
+  pub fn new(service: &str) -> Self {
+    Self {
+      service: service.to_owned(),
+    }
+  }
+
+  fn entry(&self, name: &str) -> Result<keyring::Entry> {
+    Ok(keyring::Entry::new(&self.service, name)?)
+  }
+}
+
+impl Keyring for OsKeyring {
+  fn get_secret(&self, name: &str) -> Result<Option<String>> {
+    match self.entry(name)?.get_password() {
+      Ok(secret) => Ok(Some(secret)),
+      Err(keyring::Error::NoEntry) => Ok(None),
+      Err(err) => Err(err.into()),
+    }
+  }
+
+  fn set_secret(&self, name: &str, secret: &str) -> Result<()> {
+    Ok(self.entry(name)?.set_password(secret)?)
+  }
+
+  fn delete_secret(&self, name: &str) -> Result<()> {
+    match self.entry(name)?.delete_password() {
+      Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+      Err(err) => Err(err.into()),
+    }
+  }
+}
@@ -317,6 +317,15 @@ pub enum ErrorCode {
 
   #[error("Group name is empty")]
   GroupNameIsEmpty = 109,
+
+  #[error("DocumentIndexManager or its dependencies are unavailable")]
+  DocumentIndexManagerUnavailable = 110,
+
+  #[error("The operation was cancelled")]
+  Cancelled = 111,
+
+  #[error("The local data is encrypted, unlock it with the passphrase")]
+  EncryptionLocked = 112,
 }
 
 impl ErrorCode {
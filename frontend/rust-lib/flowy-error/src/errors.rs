@@ -9,6 +9,7 @@ use validator::{ValidationError, ValidationErrors};
 use flowy_derive::ProtoBuf;
 
 use crate::code::ErrorCode;
+use crate::i18n::error_catalog;
 
 pub type FlowyResult<T> = anyhow::Result<T, FlowyError>;
 
@@ -23,6 +24,11 @@ pub struct FlowyError {
 
   #[pb(index = 3)]
   pub payload: Vec<u8>,
+
+  /// The message to show to the user, in their language, see
+  /// [ErrorCatalog](crate::i18n::ErrorCatalog). Filled when the error is sent to the app.
+  #[pb(index = 4)]
+  pub localized_msg: String,
 }
 
 macro_rules! static_flowy_error {
@@ -40,6 +46,7 @@ impl FlowyError {
       code,
       msg: msg.to_string(),
       payload: vec![],
+      localized_msg: String::new(),
     }
   }
   pub fn with_context<T: Display>(mut self, error: T) -> Self {
@@ -52,6 +59,12 @@ impl FlowyError {
     self
   }
 
+  /// Fills the message shown to the user with the one of the code in `locale`.
+  pub fn localized(mut self, locale: &str) -> Self {
+    self.localized_msg = error_catalog().message(&self.code, locale);
+    self
+  }
+
   pub fn is_record_not_found(&self) -> bool {
     self.code == ErrorCode::RecordNotFound
   }
@@ -126,11 +139,17 @@ impl FlowyError {
     folder_index_manager_unavailable,
     ErrorCode::FolderIndexManagerUnavailable
   );
+  static_flowy_error!(
+    document_index_manager_unavailable,
+    ErrorCode::DocumentIndexManagerUnavailable
+  );
   static_flowy_error!(workspace_data_not_match, ErrorCode::WorkspaceDataNotMatch);
+  static_flowy_error!(cancelled, ErrorCode::Cancelled);
   static_flowy_error!(local_ai, ErrorCode::LocalAIError);
   static_flowy_error!(local_ai_unavailable, ErrorCode::LocalAIUnavailable);
   static_flowy_error!(response_timeout, ErrorCode::ResponseTimeout);
   static_flowy_error!(file_storage_limit, ErrorCode::FileStorageLimitExceeded);
+  static_flowy_error!(encryption_locked, ErrorCode::EncryptionLocked);
 }
 
 impl std::convert::From<ErrorCode> for FlowyError {
@@ -140,6 +159,7 @@ impl std::convert::From<ErrorCode> for FlowyError {
       code,
       msg,
       payload: vec![],
+      localized_msg: String::new(),
     }
   }
 }
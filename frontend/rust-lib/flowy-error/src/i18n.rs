@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+
+use crate::code::ErrorCode;
+
+/// The locale whose messages are the ones of [ErrorCode] itself. Every fallback chain ends
+/// with it.
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// The translations bundled with the core, by locale. Each maps the name of the [ErrorCode]
+/// variants to their message, the missing ones fall back to the next locale of the chain.
+const BUNDLED_TRANSLATIONS: [(&str, &str); 6] = [
+  ("de", include_str!("../resources/i18n/de.json")),
+  ("es", include_str!("../resources/i18n/es.json")),
+  ("fr", include_str!("../resources/i18n/fr.json")),
+  ("pt", include_str!("../resources/i18n/pt.json")),
+  ("zh-CN", include_str!("../resources/i18n/zh-CN.json")),
+  ("zh-TW", include_str!("../resources/i18n/zh-TW.json")),
+];
+
+/// The locales that fall back to another one instead of their language only.
+const BUNDLED_FALLBACKS: [(&str, &str); 3] =
+  [("zh", "zh-CN"), ("zh-HK", "zh-TW"), ("zh-MO", "zh-TW")];
+
+static CATALOG: OnceLock<ErrorCatalog> = OnceLock::new();
+
+/// The catalog of the bundled translations, used to fill [FlowyError::localized_msg] when the
+/// errors are sent to the app.
+///
+/// [FlowyError::localized_msg]: crate::FlowyError::localized_msg
+pub fn error_catalog() -> &'static ErrorCatalog {
+  CATALOG.get_or_init(ErrorCatalog::bundled)
+}
+
+/// The user-facing messages of the [ErrorCode]s, by locale, so that every platform shows the
+/// same message for the same error.
+///
+/// A message is looked up along the fallback chain of the locale: the locale itself, the locale
+/// it's configured to fall back to or else its parent, and so on until the default locale. For
+/// example `zh-HK` goes through `zh-TW`, `zh` and `zh-CN`, and `fr-CA` through `fr`. The locales
+/// are matched case-insensitively, `_` and `-` alike.
+pub struct ErrorCatalog {
+  translations: HashMap<String, HashMap<String, String>>,
+  fallbacks: HashMap<String, String>,
+  default_locale: RwLock<String>,
+}
+
+impl ErrorCatalog {
+  pub fn new() -> Self {
+    Self {
+      translations: HashMap::new(),
+      fallbacks: HashMap::new(),
+      default_locale: RwLock::new(DEFAULT_LOCALE.to_owned()),
+    }
+  }
+
+  /// The catalog holding the translations bundled with the core.
+  pub fn bundled() -> Self {
+    let mut catalog = Self::new();
+    for (locale, json) in BUNDLED_TRANSLATIONS {
+      let messages = serde_json::from_str(json)
+        .unwrap_or_else(|err| panic!("Invalid translations of {}: {}", locale, err));
+      catalog.add_translations(locale, messages);
+    }
+    for (locale, fallback) in BUNDLED_FALLBACKS {
+      catalog.add_fallback(locale, fallback);
+    }
+    catalog
+  }
+
+  /// Adds the `messages` of `locale`, by name of [ErrorCode] variant, replacing the ones it
+  /// already has.
+  pub fn add_translations(&mut self, locale: &str, messages: HashMap<String, String>) {
+    self
+      .translations
+      .entry(normalize(locale))
+      .or_default()
+      .extend(messages);
+  }
+
+  /// Makes `locale` fall back to `fallback` instead of its parent.
+  pub fn add_fallback(&mut self, locale: &str, fallback: &str) {
+    self
+      .fallbacks
+      .insert(normalize(locale), normalize(fallback));
+  }
+
+  /// The locale of the errors that are not sent with one, e.g. the language chosen in the
+  /// settings of the app.
+  pub fn set_default_locale(&self, locale: &str) {
+    *self.default_locale.write().unwrap() = normalize(locale);
+  }
+
+  pub fn default_locale(&self) -> String {
+    self.default_locale.read().unwrap().clone()
+  }
+
+  /// The locales the messages of `locale` are looked up in, in order.
+  pub fn fallback_chain(&self, locale: &str) -> Vec<String> {
+    let mut chain: Vec<String> = vec![];
+    let mut next = Some(normalize(locale));
+    while let Some(locale) = next.take() {
+      if chain.contains(&locale) {
+        break;
+      }
+      next = self
+        .fallbacks
+        .get(&locale)
+        .cloned()
+        .or_else(|| locale.rsplit_once('-').map(|(parent, _)| parent.to_owned()));
+      chain.push(locale);
+    }
+    for locale in [self.default_locale(), DEFAULT_LOCALE.to_owned()] {
+      if !chain.contains(&locale) {
+        chain.push(locale);
+      }
+    }
+    chain
+  }
+
+  /// The message of `code` in `locale`, or in the first locale of its fallback chain that
+  /// translates it. The message of the [ErrorCode] itself otherwise.
+  pub fn message(&self, code: &ErrorCode, locale: &str) -> String {
+    let key = format!("{:?}", code);
+    self
+      .fallback_chain(locale)
+      .iter()
+      .find_map(|locale| self.translations.get(locale)?.get(&key))
+      .cloned()
+      .unwrap_or_else(|| code.to_string())
+  }
+}
+
+impl Default for ErrorCatalog {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+fn normalize(locale: &str) -> String {
+  locale.trim().replace('_', "-").to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::code::ErrorCode;
+  use crate::i18n::ErrorCatalog;
+
+  #[test]
+  fn error_catalog_fallback_test() {
+    let catalog = ErrorCatalog::bundled();
+    assert_eq!(
+      catalog.fallback_chain("zh_HK"),
+      vec!["zh-hk", "zh-tw", "zh", "zh-cn", "en"]
+    );
+    assert_eq!(catalog.fallback_chain("fr-CA"), vec!["fr-ca", "fr", "en"]);
+
+    assert_eq!(
+      catalog.message(&ErrorCode::RecordNotFound, "fr-CA"),
+      "Enregistrement introuvable"
+    );
+    assert_eq!(
+      catalog.message(&ErrorCode::RecordNotFound, "zh"),
+      catalog.message(&ErrorCode::RecordNotFound, "zh-CN")
+    );
+    // The missing translations fall back to the message of the code.
+    assert_eq!(
+      catalog.message(&ErrorCode::GroupIdIsEmpty, "de-DE"),
+      "Group id is empty"
+    );
+    assert_eq!(
+      catalog.message(&ErrorCode::RecordNotFound, "ja-JP"),
+      "Record not found"
+    );
+
+    catalog.set_default_locale("de");
+    assert_eq!(
+      catalog.message(&ErrorCode::RecordNotFound, "ja-JP"),
+      "Eintrag nicht gefunden"
+    );
+  }
+}
@@ -11,3 +11,9 @@ impl std::convert::From<::r2d2::Error> for FlowyError {
     FlowyError::internal().with_context(error)
   }
 }
+
+impl std::convert::From<flowy_sqlite::migration::ModuleMigrationError> for FlowyError {
+  fn from(error: flowy_sqlite::migration::ModuleMigrationError) -> Self {
+    FlowyError::internal().with_context(error)
+  }
+}
@@ -1,14 +1,56 @@
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
 
 use bytes::Bytes;
 
-use lib_dispatch::prelude::{AFPluginEventResponse, ResponseBuilder};
+use lib_dispatch::prelude::{
+  AFPluginEventResponse, AFPluginRequest, ContentType, Payload, ResponseBuilder, ResponseMapper,
+  StatusCode, LOCALE_METADATA,
+};
 
+use crate::i18n::error_catalog;
 use crate::FlowyError;
 
 impl lib_dispatch::Error for FlowyError {
   fn as_response(&self) -> AFPluginEventResponse {
-    let bytes: Bytes = self.clone().try_into().unwrap();
+    let locale = error_catalog().default_locale();
+    let bytes: Bytes = self.clone().localized(&locale).try_into().unwrap();
     ResponseBuilder::Err().data(bytes).build()
   }
 }
+
+/// Translates the message shown to the user of the errors returned by the handlers into the
+/// locale of the request, in its [LOCALE_METADATA]. The errors of the requests sent without a
+/// locale keep the message of the default locale of the [ErrorCatalog].
+///
+/// [ErrorCatalog]: crate::i18n::ErrorCatalog
+#[derive(Default)]
+pub struct LocalizeErrors;
+
+impl ResponseMapper for LocalizeErrors {
+  fn map(
+    &self,
+    request: &AFPluginRequest,
+    mut response: AFPluginEventResponse,
+  ) -> AFPluginEventResponse {
+    let locale = match request.metadata.get(LOCALE_METADATA) {
+      Some(locale) if response.status_code == StatusCode::Err => locale,
+      _ => return response,
+    };
+    if response.error_origin.is_some()
+      || !matches!(response.content_type, None | Some(ContentType::Protobuf))
+    {
+      return response;
+    }
+    let error = match &response.payload {
+      Payload::Bytes(bytes) => match FlowyError::try_from(bytes.clone()) {
+        Ok(error) => error.localized(locale),
+        Err(_) => return response,
+      },
+      Payload::None => return response,
+    };
+    if let Ok(bytes) = TryInto::<Bytes>::try_into(error) {
+      response.payload = Payload::Bytes(bytes);
+    }
+    response
+  }
+}
@@ -1,7 +1,10 @@
 pub mod code;
 mod errors;
+pub mod i18n;
 mod impl_from;
 pub mod protobuf;
 
 pub use code::*;
 pub use errors::*;
+#[cfg(feature = "impl_from_dispatch_error")]
+pub use impl_from::dispatch::LocalizeErrors;
@@ -0,0 +1,73 @@
+use std::path::PathBuf;
+
+use flowy_derive::{ProtoBuf, ProtoBuf_Enum};
+use lib_infra::validator_fn::required_not_empty_str;
+use validator::Validate;
+
+use crate::share::{ExportParams, ExportSummary, ExportType};
+
+#[derive(Clone, Debug, ProtoBuf_Enum)]
+pub enum ExportTypePB {
+  Markdown = 0,
+  Json = 1,
+  Backup = 2,
+}
+
+impl Default for ExportTypePB {
+  fn default() -> Self {
+    Self::Markdown
+  }
+}
+
+impl From<ExportTypePB> for ExportType {
+  fn from(pb: ExportTypePB) -> Self {
+    match pb {
+      ExportTypePB::Markdown => ExportType::Markdown,
+      ExportTypePB::Json => ExportType::Json,
+      ExportTypePB::Backup => ExportType::Backup,
+    }
+  }
+}
+
+#[derive(Clone, Debug, Validate, ProtoBuf, Default)]
+pub struct ExportWorkspacePayloadPB {
+  #[pb(index = 1)]
+  pub export_type: ExportTypePB,
+
+  // The directory to create, or the zip archive of a backup. It must not exist.
+  #[pb(index = 2)]
+  #[validate(custom(function = "required_not_empty_str"))]
+  pub target_path: String,
+}
+
+impl From<ExportWorkspacePayloadPB> for ExportParams {
+  fn from(pb: ExportWorkspacePayloadPB) -> Self {
+    Self {
+      export_type: pb.export_type.into(),
+      target_path: PathBuf::from(pb.target_path),
+    }
+  }
+}
+
+#[derive(Clone, Debug, ProtoBuf, Default)]
+pub struct ExportWorkspaceResultPB {
+  #[pb(index = 1)]
+  pub path: String,
+
+  #[pb(index = 2)]
+  pub exported: i64,
+
+  // The views whose layout can't be exported
+  #[pb(index = 3)]
+  pub skipped: i64,
+}
+
+impl From<ExportSummary> for ExportWorkspaceResultPB {
+  fn from(summary: ExportSummary) -> Self {
+    Self {
+      path: summary.path,
+      exported: summary.exported as i64,
+      skipped: summary.skipped as i64,
+    }
+  }
+}
@@ -1,6 +1,8 @@
 use collab_folder::TrashInfo;
 use flowy_derive::ProtoBuf;
 
+use crate::trash::TrashRetention;
+
 #[derive(Eq, PartialEq, ProtoBuf, Default, Debug, Clone)]
 pub struct TrashPB {
   #[pb(index = 1)]
@@ -14,6 +16,10 @@ pub struct TrashPB {
 
   #[pb(index = 4)]
   pub create_time: i64,
+
+  /// The time the view is deleted permanently at, 0 if it's kept in the trash.
+  #[pb(index = 5)]
+  pub expires_at: i64,
 }
 
 impl std::convert::From<TrashInfo> for TrashPB {
@@ -23,6 +29,7 @@ impl std::convert::From<TrashInfo> for TrashPB {
       name: trash_info.name,
       modified_time: trash_info.created_at,
       create_time: trash_info.created_at,
+      expires_at: 0,
     }
   }
 }
@@ -42,6 +49,22 @@ pub struct RepeatedTrashPB {
   pub items: Vec<TrashPB>,
 }
 
+impl RepeatedTrashPB {
+  pub(crate) fn new(trash: Vec<TrashInfo>, retention: &TrashRetention) -> Self {
+    let items = trash
+      .into_iter()
+      .map(|trash_info| {
+        let expires_at = retention.expires_at(trash_info.created_at).unwrap_or(0);
+        TrashPB {
+          expires_at,
+          ..trash_info.into()
+        }
+      })
+      .collect();
+    RepeatedTrashPB { items }
+  }
+}
+
 impl std::convert::From<Vec<TrashInfo>> for RepeatedTrashPB {
   fn from(trash_revs: Vec<TrashInfo>) -> Self {
     let items: Vec<TrashPB> = trash_revs
@@ -71,3 +94,26 @@ pub struct RepeatedTrashIdPB {
   #[pb(index = 1)]
   pub items: Vec<TrashIdPB>,
 }
+
+#[derive(PartialEq, Eq, ProtoBuf, Default, Debug, Clone)]
+pub struct TrashRetentionPB {
+  /// The number of days the views stay in the trash, 0 keeps them until they're deleted.
+  #[pb(index = 1)]
+  pub days: u32,
+}
+
+impl From<TrashRetention> for TrashRetentionPB {
+  fn from(retention: TrashRetention) -> Self {
+    Self {
+      days: retention.days,
+    }
+  }
+}
+
+impl From<TrashRetentionPB> for TrashRetention {
+  fn from(retention: TrashRetentionPB) -> Self {
+    Self {
+      days: retention.days,
+    }
+  }
+}
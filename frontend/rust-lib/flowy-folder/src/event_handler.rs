@@ -2,11 +2,14 @@ use std::sync::{Arc, Weak};
 use tracing::instrument;
 
 use flowy_error::{FlowyError, FlowyResult};
-use lib_dispatch::prelude::{data_result_ok, AFPluginData, AFPluginState, DataResult};
+use flowy_notification::ProgressReporter;
+use lib_dispatch::prelude::{
+  data_result_ok, AFPluginData, AFPluginState, CancellationToken, DataResult,
+};
 
 use crate::entities::*;
 use crate::manager::FolderManager;
-use crate::share::ImportParams;
+use crate::share::{ExportParams, ImportParams};
 
 fn upgrade_folder(
   folder_manager: AFPluginState<Weak<FolderManager>>,
@@ -327,8 +330,8 @@ pub(crate) async fn read_trash_handler(
   folder: AFPluginState<Weak<FolderManager>>,
 ) -> DataResult<RepeatedTrashPB, FlowyError> {
   let folder = upgrade_folder(folder)?;
-  let trash = folder.get_my_trash_info().await;
-  data_result_ok(trash.into())
+  let trash = folder.get_my_trash().await;
+  data_result_ok(trash)
 }
 
 #[tracing::instrument(level = "debug", skip(identifier, folder), err)]
@@ -372,6 +375,38 @@ pub(crate) async fn delete_my_trash_handler(
   Ok(())
 }
 
+#[tracing::instrument(level = "debug", skip(folder), err)]
+pub(crate) async fn get_trash_retention_handler(
+  folder: AFPluginState<Weak<FolderManager>>,
+) -> DataResult<TrashRetentionPB, FlowyError> {
+  let folder = upgrade_folder(folder)?;
+  data_result_ok(folder.get_trash_retention().into())
+}
+
+#[tracing::instrument(level = "debug", skip(data, folder), err)]
+pub(crate) async fn set_trash_retention_handler(
+  data: AFPluginData<TrashRetentionPB>,
+  folder: AFPluginState<Weak<FolderManager>>,
+) -> Result<(), FlowyError> {
+  let folder = upgrade_folder(folder)?;
+  folder.set_trash_retention(data.into_inner().into())?;
+  Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip(folder), err)]
+pub(crate) async fn purge_expired_trash_handler(
+  folder: AFPluginState<Weak<FolderManager>>,
+) -> DataResult<RepeatedTrashIdPB, FlowyError> {
+  let folder = upgrade_folder(folder)?;
+  let items = folder
+    .purge_expired_trash()
+    .await?
+    .into_iter()
+    .map(|id| TrashIdPB { id })
+    .collect();
+  data_result_ok(RepeatedTrashIdPB { items })
+}
+
 #[tracing::instrument(level = "debug", skip(data, folder), err)]
 pub(crate) async fn import_data_handler(
   data: AFPluginData<ImportPayloadPB>,
@@ -394,6 +429,21 @@ pub(crate) async fn import_zip_file_handler(
   Ok(())
 }
 
+#[tracing::instrument(level = "debug", skip(data, folder, progress, cancellation), err)]
+pub(crate) async fn export_workspace_handler(
+  data: AFPluginData<ExportWorkspacePayloadPB>,
+  folder: AFPluginState<Weak<FolderManager>>,
+  progress: ProgressReporter,
+  cancellation: CancellationToken,
+) -> DataResult<ExportWorkspaceResultPB, FlowyError> {
+  let folder = upgrade_folder(folder)?;
+  let params: ExportParams = data.try_into_inner()?.into();
+  let summary = folder
+    .export_workspace(params, progress, cancellation)
+    .await?;
+  data_result_ok(summary.into())
+}
+
 #[tracing::instrument(level = "debug", skip(folder), err)]
 pub(crate) async fn get_folder_snapshots_handler(
   data: AFPluginData<WorkspaceIdPB>,
@@ -10,6 +10,7 @@ use crate::manager::FolderManager;
 
 pub fn init(folder: Weak<FolderManager>) -> AFPlugin {
   AFPlugin::new().name("Flowy-Folder").state(folder)
+    .proto_descriptors(crate::protobuf::file_descriptors())
     // Workspace
     .event(FolderEvent::CreateFolderWorkspace, create_workspace_handler)
     .event(FolderEvent::GetCurrentWorkspaceSetting, read_current_workspace_setting_handler)
@@ -31,8 +32,12 @@ pub fn init(folder: Weak<FolderManager>) -> AFPlugin {
     .event(FolderEvent::PermanentlyDeleteTrashItem, delete_trash_handler)
     .event(FolderEvent::RecoverAllTrashItems, restore_all_trash_handler)
     .event(FolderEvent::PermanentlyDeleteAllTrashItem, delete_my_trash_handler)
+    .event(FolderEvent::GetTrashRetention, get_trash_retention_handler)
+    .event(FolderEvent::SetTrashRetention, set_trash_retention_handler)
+    .event(FolderEvent::PurgeExpiredTrash, purge_expired_trash_handler)
     .event(FolderEvent::ImportData, import_data_handler)
     .event(FolderEvent::ImportZipFile, import_zip_file_handler)
+    .event(FolderEvent::ExportWorkspace, export_workspace_handler)
     .event(FolderEvent::GetFolderSnapshots, get_folder_snapshots_handler)
     .event(FolderEvent::UpdateViewIcon, update_view_icon_handler)
     .event(FolderEvent::ReadFavorites, read_favorites_handler)
@@ -48,6 +53,29 @@ pub fn init(folder: Weak<FolderManager>) -> AFPlugin {
     .event(FolderEvent::UnpublishViews, unpublish_views_handler)
     .event(FolderEvent::SetPublishNamespace, set_publish_namespace_handler)
     .event(FolderEvent::GetPublishNamespace, get_publish_namespace_handler)
+    // Recorded in the audit log
+    .mutating(FolderEvent::CreateFolderWorkspace)
+    .mutating(FolderEvent::CreateView)
+    .mutating(FolderEvent::CreateOrphanView)
+    .mutating(FolderEvent::UpdateView)
+    .mutating(FolderEvent::DeleteView)
+    .mutating(FolderEvent::DuplicateView)
+    .mutating(FolderEvent::MoveView)
+    .mutating(FolderEvent::MoveNestedView)
+    .mutating(FolderEvent::UpdateViewIcon)
+    .mutating(FolderEvent::UpdateViewVisibilityStatus)
+    .mutating(FolderEvent::ToggleFavorite)
+    .mutating(FolderEvent::RestoreTrashItem)
+    .mutating(FolderEvent::RecoverAllTrashItems)
+    .mutating(FolderEvent::PermanentlyDeleteTrashItem)
+    .mutating(FolderEvent::PermanentlyDeleteAllTrashItem)
+    .mutating(FolderEvent::SetTrashRetention)
+    .mutating(FolderEvent::PurgeExpiredTrash)
+    .mutating(FolderEvent::ImportData)
+    .mutating(FolderEvent::ImportZipFile)
+    .mutating(FolderEvent::PublishView)
+    .mutating(FolderEvent::UnpublishViews)
+    .mutating(FolderEvent::SetPublishNamespace)
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Display, Hash, ProtoBuf_Enum, Flowy_Event)]
@@ -200,4 +228,22 @@ pub enum FolderEvent {
 
   #[event(input = "ImportZipPB")]
   ImportZipFile = 48,
+
+  /// Exports the views of the current workspace. The progress is sent with the
+  /// DidUpdateProgress notifications of the request, and the export stops when the request is
+  /// cancelled.
+  #[event(input = "ExportWorkspacePayloadPB", output = "ExportWorkspaceResultPB")]
+  ExportWorkspace = 49,
+
+  /// Return how long the views stay in the trash before they're deleted permanently
+  #[event(output = "TrashRetentionPB")]
+  GetTrashRetention = 50,
+
+  #[event(input = "TrashRetentionPB")]
+  SetTrashRetention = 51,
+
+  /// Delete permanently the views that stayed in the trash longer than the retention. It runs
+  /// periodically in the background as well.
+  #[event(output = "RepeatedTrashIdPB")]
+  PurgeExpiredTrash = 52,
 }
@@ -16,6 +16,8 @@ pub mod manager_test_util;
 
 pub mod publish_util;
 pub mod share;
+pub mod sync_conflict;
 #[cfg(feature = "test_helper")]
 mod test_helper;
+pub mod trash;
 mod util;
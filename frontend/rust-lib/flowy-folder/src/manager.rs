@@ -13,7 +13,7 @@ use crate::notification::{
   send_current_workspace_notification, send_notification, FolderNotification,
 };
 use crate::publish_util::{generate_publish_name, view_pb_to_publish_view};
-use crate::share::{ImportParams, ImportValue};
+use crate::share::{collect_export_entries, ExportEntry, ImportParams, ImportValue};
 use crate::util::{folder_not_init_error, workspace_data_not_sync_error};
 use crate::view_operation::{
   create_view, EncodedCollabWrapper, FolderOperationHandler, FolderOperationHandlers, ViewData,
@@ -676,6 +676,27 @@ impl FolderManager {
     Ok(views)
   }
 
+  /// The views of the workspace to export, the parents before their children. The views in the
+  /// trash and the private views of the other members are left out.
+  pub(crate) async fn get_views_to_export(
+    &self,
+    workspace_id: &str,
+  ) -> FlowyResult<Vec<ExportEntry>> {
+    let lock = self
+      .mutex_folder
+      .load_full()
+      .ok_or_else(folder_not_init_error)?;
+    let folder = lock.read().await;
+    let view_ids_should_be_filtered = Self::get_view_ids_should_be_filtered(&folder);
+    Ok(collect_export_entries(workspace_id, |parent_view_id| {
+      folder
+        .get_views_belong_to(parent_view_id)
+        .into_iter()
+        .filter(|view| !view_ids_should_be_filtered.contains(&view.id))
+        .collect()
+    }))
+  }
+
   /// Retrieves the ancestors of the view corresponding to the specified view ID, including the view itself.
   ///
   /// For example, if the view hierarchy is as follows:
@@ -1704,7 +1725,7 @@ impl FolderManager {
   }
 
   /// Returns a handler that implements the [FolderOperationHandler] trait
-  fn get_handler(
+  pub(crate) fn get_handler(
     &self,
     view_layout: &ViewLayout,
   ) -> FlowyResult<Arc<dyn FolderOperationHandler + Send + Sync>> {
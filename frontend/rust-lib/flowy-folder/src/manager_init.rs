@@ -138,6 +138,7 @@ impl FolderManager {
       section_change_rx,
       weak_mutex_folder.clone(),
       Arc::downgrade(&self.user),
+      Arc::downgrade(&self.store_preferences),
     );
     subscribe_folder_view_changed(
       workspace_id.clone(),
@@ -4,12 +4,14 @@ use crate::entities::{
 };
 use crate::manager::{get_workspace_private_view_pbs, get_workspace_public_view_pbs, FolderUser};
 use crate::notification::{send_notification, FolderNotification};
+use crate::trash::TrashRetention;
 use collab::core::collab_state::SyncState;
 use collab::lock::RwLock;
 use collab_folder::{
   Folder, SectionChange, SectionChangeReceiver, TrashSectionChange, View, ViewChange,
   ViewChangeReceiver,
 };
+use flowy_sqlite::kv::KVStorePreferences;
 use lib_dispatch::prelude::af_spawn;
 use std::collections::HashSet;
 use std::sync::Weak;
@@ -23,6 +25,7 @@ pub(crate) fn subscribe_folder_view_changed(
   mut rx: ViewChangeReceiver,
   weak_mutex_folder: Weak<RwLock<Folder>>,
   user: Weak<dyn FolderUser>,
+  store_preferences: Weak<KVStorePreferences>,
 ) {
   af_spawn(async move {
     while let Ok(value) = rx.recv().await {
@@ -159,7 +162,11 @@ pub(crate) fn subscribe_folder_trash_changed(
               unique_ids.insert(view.parent_view_id.clone());
             }
 
-            let repeated_trash: RepeatedTrashPB = folder.get_my_trash_info().into();
+            let retention = store_preferences
+              .upgrade()
+              .map(|store_preferences| TrashRetention::load(&store_preferences))
+              .unwrap_or_default();
+            let repeated_trash = RepeatedTrashPB::new(folder.get_my_trash_info(), &retention);
             send_notification("trash", FolderNotification::DidUpdateTrash)
               .payload(repeated_trash)
               .send();
@@ -0,0 +1,310 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use collab_folder::{View, ViewLayout};
+use flowy_error::{ErrorCode, FlowyError, FlowyResult};
+use flowy_notification::ProgressReporter;
+use lib_dispatch::prelude::CancellationToken;
+use lib_infra::file_util::zip_folder;
+use lib_infra::util::timestamp;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::manager::FolderManager;
+
+/// The name of the file describing the exported views, at the root of the JSON exports.
+const MANIFEST_NAME: &str = "manifest";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportType {
+  /// A directory of markdown documents and CSV databases.
+  Markdown,
+  /// A directory holding the JSON of each view, along with a manifest of the views.
+  Json,
+  /// The JSON export in a zip archive.
+  Backup,
+}
+
+impl ExportType {
+  fn view_format(&self) -> ExportFormat {
+    match self {
+      ExportType::Markdown => ExportFormat::Markdown,
+      ExportType::Json | ExportType::Backup => ExportFormat::Json,
+    }
+  }
+}
+
+/// The format a view is exported in, see
+/// [FolderOperationHandler::export_view](crate::view_operation::FolderOperationHandler::export_view).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+  /// Readable by the other apps: markdown for the documents, CSV for the databases.
+  Markdown,
+  /// The whole content of the view.
+  Json,
+}
+
+/// The content of an exported view, written in a file with the `extension`.
+#[derive(Clone, Debug)]
+pub struct ExportedView {
+  pub extension: &'static str,
+  pub data: Vec<u8>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ExportParams {
+  pub export_type: ExportType,
+  /// The directory, or the zip archive of a backup, to create. It must not exist.
+  pub target_path: PathBuf,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ExportSummary {
+  pub path: String,
+  pub exported: usize,
+  /// The views whose layout can't be exported, e.g. the chats.
+  pub skipped: usize,
+}
+
+/// A view to export, along with the path of its file relative to the root of the export.
+pub(crate) struct ExportEntry {
+  pub view: Arc<View>,
+  /// The path without extension. The children of the view are exported in the directory of
+  /// the same path.
+  pub path: PathBuf,
+}
+
+/// Lists the views below `workspace_id`, the parents before their children. `children` returns
+/// the child views of a view, without the ones that must not be exported.
+pub(crate) fn collect_export_entries<F>(workspace_id: &str, children: F) -> Vec<ExportEntry>
+where
+  F: Fn(&str) -> Vec<Arc<View>>,
+{
+  let mut entries = vec![];
+  let mut names = HashSet::from([MANIFEST_NAME.to_owned()]);
+  collect_children(
+    workspace_id,
+    Path::new(""),
+    &children,
+    &mut names,
+    &mut entries,
+  );
+  entries
+}
+
+fn collect_children<F>(
+  parent_view_id: &str,
+  dir: &Path,
+  children: &F,
+  names: &mut HashSet<String>,
+  entries: &mut Vec<ExportEntry>,
+) where
+  F: Fn(&str) -> Vec<Arc<View>>,
+{
+  for view in children(parent_view_id) {
+    let path = dir.join(unique_file_name(&view.name, names));
+    let view_id = view.id.clone();
+    entries.push(ExportEntry {
+      view,
+      path: path.clone(),
+    });
+    collect_children(&view_id, &path, children, &mut HashSet::new(), entries);
+  }
+}
+
+/// The name is unique among its siblings, whatever the case, so the files don't overwrite each
+/// other on the case-insensitive file systems.
+fn unique_file_name(name: &str, names: &mut HashSet<String>) -> String {
+  let name = sanitize_file_name(name);
+  let mut unique_name = name.clone();
+  let mut count = 1;
+  while !names.insert(unique_name.to_lowercase()) {
+    count += 1;
+    unique_name = format!("{} ({})", name, count);
+  }
+  unique_name
+}
+
+fn sanitize_file_name(name: &str) -> String {
+  let name = name
+    .chars()
+    .map(|c| match c {
+      '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+      c if c.is_control() => '_',
+      c => c,
+    })
+    .collect::<String>();
+  // Windows drops the trailing dots of the file names
+  let name = name.trim().trim_end_matches('.');
+  if name.is_empty() {
+    "Untitled".to_owned()
+  } else {
+    name.to_owned()
+  }
+}
+
+#[derive(Serialize)]
+struct ExportManifest {
+  workspace_id: String,
+  exported_at: i64,
+  views: Vec<ManifestView>,
+}
+
+#[derive(Serialize)]
+struct ManifestView {
+  id: String,
+  parent_view_id: String,
+  name: String,
+  layout: ViewLayout,
+  /// The path of the file of the view. `None` if its layout can't be exported.
+  file: Option<String>,
+}
+
+/// The directory the export is written in, next to the target. It's removed when dropped,
+/// unless the export completed, so a cancelled or failed export leaves nothing behind.
+struct Staging {
+  path: PathBuf,
+  completed: bool,
+}
+
+impl Staging {
+  fn new(target_path: &Path) -> FlowyResult<Self> {
+    let mut path = target_path.as_os_str().to_owned();
+    path.push(".part");
+    let path = PathBuf::from(path);
+    if path.exists() {
+      fs::remove_dir_all(&path)?;
+    }
+    fs::create_dir_all(&path)?;
+    Ok(Self {
+      path,
+      completed: false,
+    })
+  }
+
+  fn write(&self, relative_path: &Path, data: &[u8]) -> FlowyResult<()> {
+    let path = self.path.join(relative_path);
+    if let Some(parent) = path.parent() {
+      fs::create_dir_all(parent)?;
+    }
+    fs::write(path, data)?;
+    Ok(())
+  }
+
+  /// Moves the export to `target_path`, zipped if `zip` is true.
+  fn complete(mut self, target_path: &Path, zip: bool) -> FlowyResult<()> {
+    if zip {
+      let mut archive_path = self.path.as_os_str().to_owned();
+      archive_path.push(".zip");
+      let archive_path = PathBuf::from(archive_path);
+      let result =
+        zip_folder(&self.path, &archive_path).and_then(|_| fs::rename(&archive_path, target_path));
+      if result.is_err() {
+        let _ = fs::remove_file(&archive_path);
+      }
+      result?;
+    } else {
+      fs::rename(&self.path, target_path)?;
+      self.completed = true;
+    }
+    Ok(())
+  }
+}
+
+impl Drop for Staging {
+  fn drop(&mut self) {
+    if !self.completed {
+      if let Err(err) = fs::remove_dir_all(&self.path) {
+        warn!(
+          "[Export]: failed to remove {}: {}",
+          self.path.display(),
+          err
+        );
+      }
+    }
+  }
+}
+
+impl FolderManager {
+  /// Exports the views of the current workspace to `params.target_path`. The progress is
+  /// reported once per view, and the export stops with a [ErrorCode::Cancelled] error as soon as
+  /// `cancellation` is cancelled.
+  pub(crate) async fn export_workspace(
+    &self,
+    params: ExportParams,
+    progress: ProgressReporter,
+    cancellation: CancellationToken,
+  ) -> FlowyResult<ExportSummary> {
+    let target_path = params.target_path;
+    if target_path.exists() {
+      return Err(
+        FlowyError::invalid_data()
+          .with_context(format!("{} already exists", target_path.display())),
+      );
+    }
+    let workspace_id = self.user.workspace_id()?;
+    let entries = self.get_views_to_export(&workspace_id).await?;
+    let staging = Staging::new(&target_path)?;
+    let format = params.export_type.view_format();
+    let total = entries.len() as u64;
+
+    let mut summary = ExportSummary {
+      path: target_path.to_string_lossy().to_string(),
+      ..Default::default()
+    };
+    let mut manifest_views = Vec::with_capacity(entries.len());
+    for (i, entry) in entries.into_iter().enumerate() {
+      if cancellation.is_cancelled() {
+        return Err(FlowyError::cancelled());
+      }
+      progress.report_with_message(i as u64, total, &entry.view.name);
+
+      let exported = match self.get_handler(&entry.view.layout) {
+        Ok(handler) => handler.export_view(&entry.view.id, format).await,
+        Err(err) => Err(err),
+      };
+      let file = match exported {
+        Ok(exported) => {
+          let path = entry.path.with_extension(exported.extension);
+          staging.write(&path, &exported.data)?;
+          summary.exported += 1;
+          Some(path.to_string_lossy().replace('\\', "/"))
+        },
+        Err(err) if err.code == ErrorCode::NotSupportYet => {
+          summary.skipped += 1;
+          None
+        },
+        Err(err) => return Err(err),
+      };
+      manifest_views.push(ManifestView {
+        id: entry.view.id.clone(),
+        parent_view_id: entry.view.parent_view_id.clone(),
+        name: entry.view.name.clone(),
+        layout: entry.view.layout.clone(),
+        file,
+      });
+    }
+
+    if format == ExportFormat::Json {
+      let manifest = ExportManifest {
+        workspace_id,
+        exported_at: timestamp(),
+        views: manifest_views,
+      };
+      let data = serde_json::to_vec_pretty(&manifest)?;
+      staging.write(Path::new(&format!("{}.json", MANIFEST_NAME)), &data)?;
+    }
+    if cancellation.is_cancelled() {
+      return Err(FlowyError::cancelled());
+    }
+    staging.complete(&target_path, params.export_type == ExportType::Backup)?;
+    progress.finish();
+    info!(
+      "[Export]: exported {} views to {}, skipped {}",
+      summary.exported, summary.path, summary.skipped
+    );
+    Ok(summary)
+  }
+}
@@ -0,0 +1,24 @@
+use flowy_sync::{ConflictResolver, SyncMessage};
+
+use crate::entities::{MoveNestedViewPayloadPB, UpdateViewIconPayloadPB, UpdateViewPayloadPB};
+use crate::event_map::FolderEvent;
+
+/// Settles the collisions of the view changes made on several devices: the last change of each
+/// kind wins, e.g. the last rename of a view, without discarding a move of the same view.
+pub struct ViewConflictResolver;
+
+impl ConflictResolver for ViewConflictResolver {
+  fn object_id(&self, message: &SyncMessage) -> Option<String> {
+    let payload = message.payload.as_slice();
+    let view_id = if message.event == FolderEvent::UpdateView.to_string() {
+      UpdateViewPayloadPB::try_from(payload).ok()?.view_id
+    } else if message.event == FolderEvent::UpdateViewIcon.to_string() {
+      UpdateViewIconPayloadPB::try_from(payload).ok()?.view_id
+    } else if message.event == FolderEvent::MoveNestedView.to_string() {
+      MoveNestedViewPayloadPB::try_from(payload).ok()?.view_id
+    } else {
+      return None;
+    };
+    Some(format!("{}/{}", view_id, message.event))
+  }
+}
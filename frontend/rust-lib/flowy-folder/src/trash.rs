@@ -0,0 +1,112 @@
+use std::sync::Weak;
+use std::time::Duration;
+
+use collab_folder::TrashInfo;
+use flowy_error::FlowyResult;
+use flowy_sqlite::kv::KVStorePreferences;
+use lib_infra::util::timestamp;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use crate::entities::RepeatedTrashPB;
+use crate::manager::FolderManager;
+use crate::notification::{send_notification, FolderNotification};
+
+const TRASH_RETENTION_KEY: &str = "trash_retention";
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// How long the views stay in the trash before they're deleted permanently.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrashRetention {
+  /// 0 keeps the views in the trash until they're deleted by the user.
+  pub days: u32,
+}
+
+impl Default for TrashRetention {
+  fn default() -> Self {
+    Self { days: 30 }
+  }
+}
+
+impl TrashRetention {
+  pub(crate) fn load(store_preferences: &KVStorePreferences) -> Self {
+    store_preferences
+      .get_object(TRASH_RETENTION_KEY)
+      .unwrap_or_default()
+  }
+
+  /// The time, in seconds, the view trashed at `trashed_at` is deleted at. `None` if it's kept.
+  pub fn expires_at(&self, trashed_at: i64) -> Option<i64> {
+    if self.days == 0 {
+      return None;
+    }
+    Some(trashed_at + self.days as i64 * SECONDS_PER_DAY)
+  }
+
+  pub fn is_expired(&self, trash: &TrashInfo, now: i64) -> bool {
+    self
+      .expires_at(trash.created_at)
+      .map(|expires_at| expires_at <= now)
+      .unwrap_or(false)
+  }
+}
+
+impl FolderManager {
+  pub(crate) fn get_trash_retention(&self) -> TrashRetention {
+    TrashRetention::load(&self.store_preferences)
+  }
+
+  pub(crate) fn set_trash_retention(&self, retention: TrashRetention) -> FlowyResult<()> {
+    self
+      .store_preferences
+      .set_object(TRASH_RETENTION_KEY, &retention)?;
+    Ok(())
+  }
+
+  /// The trash of the current user along with the time each view is deleted at.
+  pub(crate) async fn get_my_trash(&self) -> RepeatedTrashPB {
+    let trash = self.get_my_trash_info().await;
+    RepeatedTrashPB::new(trash, &self.get_trash_retention())
+  }
+
+  /// Deletes permanently the views that stayed in the trash longer than the retention, and
+  /// returns their ids.
+  pub async fn purge_expired_trash(&self) -> FlowyResult<Vec<String>> {
+    let retention = self.get_trash_retention();
+    let now = timestamp();
+    let expired_ids = self
+      .get_my_trash_info()
+      .await
+      .into_iter()
+      .filter(|trash| retention.is_expired(trash, now))
+      .map(|trash| trash.id)
+      .collect::<Vec<_>>();
+    if expired_ids.is_empty() {
+      return Ok(expired_ids);
+    }
+
+    for view_id in &expired_ids {
+      self.delete_trash(view_id).await?;
+    }
+    info!("[Trash]: purged {} expired views", expired_ids.len());
+    send_notification("trash", FolderNotification::DidUpdateTrash)
+      .payload(self.get_my_trash().await)
+      .send();
+    Ok(expired_ids)
+  }
+}
+
+/// Purges the expired trash every `interval`, until the [FolderManager] is dropped.
+pub async fn run_trash_purge(folder_manager: Weak<FolderManager>, interval: Duration) {
+  loop {
+    tokio::time::sleep(interval).await;
+    let folder_manager = match folder_manager.upgrade() {
+      None => break,
+      Some(folder_manager) => folder_manager,
+    };
+    if let Err(err) = folder_manager.purge_expired_trash().await {
+      error!("[Trash]: purge the expired trash failed: {}", err);
+    }
+  }
+}
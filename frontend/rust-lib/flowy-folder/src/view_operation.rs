@@ -15,7 +15,7 @@ use lib_infra::util::timestamp;
 
 use crate::entities::{CreateViewParams, ViewLayoutPB};
 use crate::manager::FolderUser;
-use crate::share::ImportType;
+use crate::share::{ExportFormat, ExportedView, ImportType};
 
 #[derive(Debug, Clone)]
 pub enum EncodedCollabWrapper {
@@ -67,6 +67,16 @@ pub trait FolderOperationHandler {
   /// Returns the [ViewData] that can be used to create the same view.
   async fn duplicate_view(&self, view_id: &str) -> Result<Bytes, FlowyError>;
 
+  /// Returns the content of the view in the `format`, used to export the workspace. The views
+  /// whose handler doesn't support it are left out of the export.
+  async fn export_view(
+    &self,
+    _view_id: &str,
+    _format: ExportFormat,
+  ) -> Result<ExportedView, FlowyError> {
+    Err(FlowyError::not_support())
+  }
+
   /// get the encoded collab data from the disk.
   async fn get_encoded_collab_v1_from_disk(
     &self,
@@ -0,0 +1,24 @@
+use flowy_derive::ProtoBuf;
+
+#[derive(Default, ProtoBuf)]
+pub struct KVKeyPB {
+  /// Groups the keys of a feature, e.g. `window`.
+  #[pb(index = 1)]
+  pub namespace: String,
+
+  #[pb(index = 2)]
+  pub key: String,
+}
+
+#[derive(Default, ProtoBuf)]
+pub struct KVValuePB {
+  #[pb(index = 1)]
+  pub namespace: String,
+
+  #[pb(index = 2)]
+  pub key: String,
+
+  /// Empty if the key has no value.
+  #[pb(index = 3, one_of)]
+  pub value: Option<String>,
+}
@@ -0,0 +1,41 @@
+use flowy_error::{FlowyError, FlowyResult};
+use lib_dispatch::prelude::{data_result_ok, AFPluginData, AppData, DataResult};
+
+use crate::entities::{KVKeyPB, KVValuePB};
+use crate::store::KVStore;
+
+pub(crate) async fn get_value_handler(
+  store: AppData<KVStore>,
+  data: AFPluginData<KVKeyPB>,
+) -> DataResult<KVValuePB, FlowyError> {
+  let data = data.into_inner();
+  let value = store.get(&data.namespace, &data.key)?;
+  data_result_ok(KVValuePB {
+    namespace: data.namespace,
+    key: data.key,
+    value,
+  })
+}
+
+pub(crate) async fn set_value_handler(
+  store: AppData<KVStore>,
+  data: AFPluginData<KVValuePB>,
+) -> FlowyResult<()> {
+  let data = data.into_inner();
+  match data.value {
+    None => {
+      store.remove(&data.namespace, &data.key)?;
+    },
+    Some(value) => store.set(&data.namespace, &data.key, &value)?,
+  }
+  Ok(())
+}
+
+pub(crate) async fn remove_value_handler(
+  store: AppData<KVStore>,
+  data: AFPluginData<KVKeyPB>,
+) -> FlowyResult<()> {
+  let data = data.into_inner();
+  store.remove(&data.namespace, &data.key)?;
+  Ok(())
+}
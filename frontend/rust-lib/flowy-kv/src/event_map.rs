@@ -0,0 +1,34 @@
+use strum_macros::Display;
+
+use flowy_derive::{Flowy_Event, ProtoBuf_Enum};
+use lib_dispatch::prelude::AFPlugin;
+
+use crate::event_handler::*;
+
+/// The handlers read the [KVStore](crate::KVStore) registered in the dispatcher with
+/// [KVStore::register](crate::KVStore::register).
+pub fn init() -> AFPlugin {
+  AFPlugin::new()
+    .name(env!("CARGO_PKG_NAME"))
+    .proto_descriptors(crate::protobuf::file_descriptors())
+    .event(KVEvent::GetValue, get_value_handler)
+    .event(KVEvent::SetValue, set_value_handler)
+    .event(KVEvent::RemoveValue, remove_value_handler)
+    // Recorded in the audit log
+    .mutating(KVEvent::SetValue)
+    .mutating(KVEvent::RemoveValue)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Display, ProtoBuf_Enum, Flowy_Event)]
+#[event_err = "FlowyError"]
+pub enum KVEvent {
+  #[event(input = "KVKeyPB", output = "KVValuePB")]
+  GetValue = 0,
+
+  /// Removes the key if the value is empty.
+  #[event(input = "KVValuePB")]
+  SetValue = 1,
+
+  #[event(input = "KVKeyPB")]
+  RemoveValue = 2,
+}
@@ -0,0 +1,7 @@
+pub mod entities;
+mod event_handler;
+pub mod event_map;
+mod protobuf;
+pub mod store;
+
+pub use store::KVStore;
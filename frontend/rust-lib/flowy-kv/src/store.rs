@@ -0,0 +1,320 @@
+use std::path::Path;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use diesel::{
+  Connection, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl, TextExpressionMethods,
+};
+use flowy_encrypt::EncryptionKey;
+use flowy_error::{internal_error, ErrorCode, FlowyError, FlowyResult};
+use flowy_sqlite::migration::ModuleMigrations;
+use flowy_sqlite::{DBConnection, Database, PoolConfig};
+use lib_dispatch::prelude::AFPluginDispatcher;
+use lib_infra::util::timestamp;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+const DB_NAME: &str = "kv_store.db";
+
+/// Prefixes the encrypted values, the values stored before the encryption was turned on don't
+/// have it.
+const ENCRYPTED_VALUE_PREFIX: &str = "enc1:";
+
+const CREATE_KV_STORE_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS kv_store_table (
+  namespace TEXT NOT NULL,
+  key TEXT NOT NULL,
+  value TEXT NOT NULL,
+  updated_at BIGINT NOT NULL,
+  PRIMARY KEY (namespace, key)
+);
+"#;
+
+diesel::table! {
+    kv_store_table (namespace, key) {
+        namespace -> Text,
+        key -> Text,
+        value -> Text,
+        updated_at -> BigInt,
+    }
+}
+
+/// A persisted key value store for the small states of the app, like the last opened view or the
+/// window layout. The keys are grouped by namespace, usually the name of the feature, so the
+/// features can't overwrite each other's values.
+///
+/// The store is registered in the dispatcher as app data with [KVStore::register]. The Rust
+/// handlers read it with the `AppData<KVStore>` extractor and the client with the
+/// [KVEvent](crate::event_map::KVEvent)s.
+///
+/// The values are encrypted at rest once the store has a key, see [KVStore::with_encryption].
+/// The namespaces and the keys are stored in plaintext.
+#[derive(Clone)]
+pub struct KVStore {
+  database: Database,
+  key: Option<EncryptionKey>,
+}
+
+impl KVStore {
+  pub fn new(root: &str) -> FlowyResult<Self> {
+    if !Path::new(root).exists() {
+      return Err(FlowyError::internal().with_context(format!("{} not exists", root)));
+    }
+    let database = Database::new(root, DB_NAME, PoolConfig::default()).map_err(internal_error)?;
+    let mut conn = database.get_connection().map_err(internal_error)?;
+    migrations().run(&mut *conn)?;
+    Ok(Self {
+      database,
+      key: None,
+    })
+  }
+
+  /// Encrypts the values written from now on with `key`. The values written before stay in
+  /// plaintext until [KVStore::encrypt_existing_values] runs.
+  pub fn with_encryption(mut self, key: EncryptionKey) -> Self {
+    self.key = Some(key);
+    self
+  }
+
+  /// Encrypts the plaintext values, the migration path of the stores created before the
+  /// encryption was turned on. Returns the number of encrypted values.
+  pub fn encrypt_existing_values(&self) -> FlowyResult<usize> {
+    let key = self
+      .key
+      .as_ref()
+      .ok_or_else(|| FlowyError::internal().with_context("The key value store has no key"))?;
+    let mut conn = self.connection()?;
+    conn.transaction::<_, FlowyError, _>(|conn| {
+      let rows = kv_store_table::table
+        .filter(kv_store_table::value.not_like(format!("{}%", ENCRYPTED_VALUE_PREFIX)))
+        .select((
+          kv_store_table::namespace,
+          kv_store_table::key,
+          kv_store_table::value,
+        ))
+        .load::<(String, String, String)>(conn)?;
+      for (namespace, row_key, value) in &rows {
+        diesel::update(
+          kv_store_table::table
+            .filter(kv_store_table::namespace.eq(namespace))
+            .filter(kv_store_table::key.eq(row_key)),
+        )
+        .set(kv_store_table::value.eq(encrypt_value(key, value)?))
+        .execute(conn)?;
+      }
+      Ok(rows.len())
+    })
+  }
+
+  /// Registers the store in `dispatcher` as app data.
+  pub fn register(&self, dispatcher: AFPluginDispatcher) -> AFPluginDispatcher {
+    dispatcher.data(self.clone())
+  }
+
+  pub fn get(&self, namespace: &str, key: &str) -> FlowyResult<Option<String>> {
+    let mut conn = self.connection()?;
+    let value = kv_store_table::table
+      .filter(kv_store_table::namespace.eq(namespace))
+      .filter(kv_store_table::key.eq(key))
+      .select(kv_store_table::value)
+      .first::<String>(&mut *conn)
+      .optional()?;
+    value.map(|value| self.decrypt_value(value)).transpose()
+  }
+
+  pub fn set(&self, namespace: &str, key: &str, value: &str) -> FlowyResult<()> {
+    let value = match &self.key {
+      Some(encryption_key) => encrypt_value(encryption_key, value)?,
+      None => value.to_owned(),
+    };
+    let mut conn = self.connection()?;
+    diesel::replace_into(kv_store_table::table)
+      .values((
+        kv_store_table::namespace.eq(namespace),
+        kv_store_table::key.eq(key),
+        kv_store_table::value.eq(value),
+        kv_store_table::updated_at.eq(timestamp()),
+      ))
+      .execute(&mut *conn)?;
+    Ok(())
+  }
+
+  /// Returns whether the key had a value.
+  pub fn remove(&self, namespace: &str, key: &str) -> FlowyResult<bool> {
+    let mut conn = self.connection()?;
+    let removed = diesel::delete(
+      kv_store_table::table
+        .filter(kv_store_table::namespace.eq(namespace))
+        .filter(kv_store_table::key.eq(key)),
+    )
+    .execute(&mut *conn)?;
+    Ok(removed > 0)
+  }
+
+  /// The keys of the namespace, in order.
+  pub fn keys(&self, namespace: &str) -> FlowyResult<Vec<String>> {
+    let mut conn = self.connection()?;
+    let keys = kv_store_table::table
+      .filter(kv_store_table::namespace.eq(namespace))
+      .select(kv_store_table::key)
+      .order(kv_store_table::key.asc())
+      .load::<String>(&mut *conn)?;
+    Ok(keys)
+  }
+
+  /// Reads the value of the key, stored by [KVStore::set_object], as a `T`.
+  pub fn get_object<T: DeserializeOwned>(
+    &self,
+    namespace: &str,
+    key: &str,
+  ) -> FlowyResult<Option<T>> {
+    match self.get(namespace, key)? {
+      Some(value) => Ok(Some(serde_json::from_str(&value)?)),
+      None => Ok(None),
+    }
+  }
+
+  /// Stores `value` as JSON.
+  pub fn set_object<T: Serialize>(&self, namespace: &str, key: &str, value: &T) -> FlowyResult<()> {
+    let value = serde_json::to_string(value)?;
+    self.set(namespace, key, &value)
+  }
+
+  fn connection(&self) -> FlowyResult<DBConnection> {
+    self.database.get_connection().map_err(internal_error)
+  }
+
+  fn decrypt_value(&self, value: String) -> FlowyResult<String> {
+    let encrypted = match value.strip_prefix(ENCRYPTED_VALUE_PREFIX) {
+      None => return Ok(value),
+      Some(encrypted) => encrypted,
+    };
+    let key = self.key.as_ref().ok_or_else(|| {
+      FlowyError::new(
+        ErrorCode::InvalidEncryptSecret,
+        "The value is encrypted, but the key value store has no key",
+      )
+    })?;
+    let data = STANDARD
+      .decode(encrypted)
+      .map_err(|err| FlowyError::invalid_data().with_context(err))?;
+    let data = key
+      .decrypt(&data)
+      .map_err(|err| FlowyError::new(ErrorCode::InvalidEncryptSecret, err))?;
+    String::from_utf8(data).map_err(|err| FlowyError::invalid_data().with_context(err))
+  }
+}
+
+fn encrypt_value(key: &EncryptionKey, value: &str) -> FlowyResult<String> {
+  let encrypted = key.encrypt(value.as_bytes()).map_err(internal_error)?;
+  Ok(format!(
+    "{}{}",
+    ENCRYPTED_VALUE_PREFIX,
+    STANDARD.encode(encrypted)
+  ))
+}
+
+/// The schema of the store. Append the new steps, never change the applied ones.
+fn migrations() -> ModuleMigrations {
+  ModuleMigrations::new("kv").sql(1, "create_kv_store_table", CREATE_KV_STORE_SQL)
+}
+
+#[cfg(test)]
+mod tests {
+  use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+  use flowy_encrypt::EncryptionKey;
+  use flowy_error::ErrorCode;
+  use serde::{Deserialize, Serialize};
+  use tempfile::TempDir;
+
+  use crate::store::{kv_store_table, KVStore};
+
+  #[derive(Serialize, Deserialize, Debug, PartialEq)]
+  struct WindowLayout {
+    width: u32,
+    height: u32,
+  }
+
+  #[test]
+  fn kv_store_test() {
+    let tempdir = TempDir::new().unwrap();
+    let store = KVStore::new(tempdir.path().to_str().unwrap()).unwrap();
+
+    store.set("view", "last_opened", "view_1").unwrap();
+    store.set("view", "last_opened", "view_2").unwrap();
+    store.set("sidebar", "last_opened", "section").unwrap();
+    assert_eq!(
+      store.get("view", "last_opened").unwrap(),
+      Some("view_2".to_string())
+    );
+    assert_eq!(store.keys("view").unwrap(), vec!["last_opened"]);
+
+    assert!(store.remove("view", "last_opened").unwrap());
+    assert!(!store.remove("view", "last_opened").unwrap());
+    assert_eq!(store.get("view", "last_opened").unwrap(), None);
+    assert_eq!(
+      store.get("sidebar", "last_opened").unwrap(),
+      Some("section".to_string())
+    );
+
+    let layout = WindowLayout {
+      width: 1280,
+      height: 720,
+    };
+    store.set_object("window", "layout", &layout).unwrap();
+    assert_eq!(
+      store
+        .get_object::<WindowLayout>("window", "layout")
+        .unwrap(),
+      Some(layout)
+    );
+  }
+
+  fn raw_value(store: &KVStore, namespace: &str, key: &str) -> String {
+    let mut conn = store.connection().unwrap();
+    kv_store_table::table
+      .filter(kv_store_table::namespace.eq(namespace))
+      .filter(kv_store_table::key.eq(key))
+      .select(kv_store_table::value)
+      .first::<String>(&mut *conn)
+      .unwrap()
+  }
+
+  #[test]
+  fn kv_store_encryption_test() {
+    let tempdir = TempDir::new().unwrap();
+    let root = tempdir.path().to_str().unwrap();
+    let key = EncryptionKey::derive("passphrase", b"salt", 1000).unwrap();
+    let store = KVStore::new(root).unwrap();
+    store.set("view", "last_opened", "view_1").unwrap();
+    store.set("view", "last_closed", "view_2").unwrap();
+
+    // The plaintext values stay readable until they're migrated
+    let store = KVStore::new(root).unwrap().with_encryption(key.clone());
+    assert_eq!(
+      store.get("view", "last_opened").unwrap(),
+      Some("view_1".to_string())
+    );
+    store.set("view", "last_closed", "view_3").unwrap();
+    assert!(!raw_value(&store, "view", "last_closed").contains("view_3"));
+    assert_eq!(store.encrypt_existing_values().unwrap(), 1);
+    assert_eq!(store.encrypt_existing_values().unwrap(), 0);
+    assert!(!raw_value(&store, "view", "last_opened").contains("view_1"));
+    assert_eq!(
+      store.get("view", "last_opened").unwrap(),
+      Some("view_1".to_string())
+    );
+    assert_eq!(
+      store.get("view", "last_closed").unwrap(),
+      Some("view_3".to_string())
+    );
+
+    let store = KVStore::new(root).unwrap();
+    let error = store.get("view", "last_opened").unwrap_err();
+    assert_eq!(error.code, ErrorCode::InvalidEncryptSecret);
+    let other_key = EncryptionKey::derive("passphrase", b"other salt", 1000).unwrap();
+    let store = store.with_encryption(other_key);
+    let error = store.get("view", "last_opened").unwrap_err();
+    assert_eq!(error.code, ErrorCode::InvalidEncryptSecret);
+  }
+}
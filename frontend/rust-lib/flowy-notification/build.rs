@@ -1,6 +1,9 @@
 fn main() {
   #[cfg(feature = "dart")]
-  flowy_codegen::protobuf_file::dart_gen(env!("CARGO_PKG_NAME"));
+  {
+    flowy_codegen::protobuf_file::dart_gen(env!("CARGO_PKG_NAME"));
+    flowy_codegen::dart_event::gen(env!("CARGO_PKG_NAME"));
+  }
 
   #[cfg(feature = "tauri_ts")]
   {
@@ -0,0 +1,91 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+
+use crate::entities::SubscribeObject;
+use crate::send_subject;
+
+/// The delay before the first retry, doubled after each retry.
+const ACK_INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const ACK_MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// The notification is dropped after this number of retries.
+const ACK_MAX_RETRIES: usize = 10;
+
+lazy_static! {
+  static ref UNACKED: Mutex<HashSet<i64>> = Mutex::new(HashSet::new());
+}
+
+static NEXT_ACK_ID: AtomicI64 = AtomicI64::new(1);
+
+/// Sends `subject` with a new ack id and sends it again, with an exponential backoff, until the
+/// client acknowledges it with the `AckNotification` event. The client may receive the same
+/// notification more than once and is expected to ignore the ack ids it already handled.
+pub fn send_subject_with_ack(mut subject: SubscribeObject) -> i64 {
+  let ack_id = NEXT_ACK_ID.fetch_add(1, Ordering::Relaxed);
+  subject.ack_id = Some(ack_id);
+  UNACKED.lock().unwrap().insert(ack_id);
+  send_subject(subject.clone());
+
+  match tokio::runtime::Handle::try_current() {
+    Ok(runtime) => {
+      runtime.spawn(async move {
+        let mut backoff = ACK_INITIAL_BACKOFF;
+        for _ in 0..ACK_MAX_RETRIES {
+          tokio::time::sleep(backoff).await;
+          if !is_unacked(ack_id) {
+            return;
+          }
+          tracing::debug!("Resend the unacked notification {}: {}", ack_id, subject);
+          send_subject(subject.clone());
+          backoff = (backoff * 2).min(ACK_MAX_BACKOFF);
+        }
+        if UNACKED.lock().unwrap().remove(&ack_id) {
+          tracing::error!("The notification {} was never acked: {}", ack_id, subject);
+        }
+      });
+    },
+    Err(_) => tracing::warn!("No runtime to resend the notification {}", ack_id),
+  }
+  ack_id
+}
+
+/// Confirms the receipt of the notification `ack_id`. Returns false if the notification is
+/// unknown or was already acknowledged.
+pub fn ack_notification(ack_id: i64) -> bool {
+  UNACKED.lock().unwrap().remove(&ack_id)
+}
+
+pub fn is_unacked(ack_id: i64) -> bool {
+  UNACKED.lock().unwrap().contains(&ack_id)
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::entities::SubscribeObject;
+  use crate::{ack_notification, is_unacked, send_subject_with_ack, NotificationCollector};
+
+  #[test]
+  fn ack_notification_test() {
+    let collector = NotificationCollector::new();
+    crate::register_notification_sender(collector.clone());
+    let ack_id = send_subject_with_ack(SubscribeObject {
+      source: "Ack".to_string(),
+      id: "session".to_string(),
+      ..Default::default()
+    });
+
+    let sent = collector
+      .notifications()
+      .into_iter()
+      .find(|subject| subject.source == "Ack")
+      .unwrap();
+    assert_eq!(sent.ack_id, Some(ack_id));
+    assert!(is_unacked(ack_id));
+    assert!(ack_notification(ack_id));
+    assert!(!is_unacked(ack_id));
+    assert!(!ack_notification(ack_id));
+  }
+}
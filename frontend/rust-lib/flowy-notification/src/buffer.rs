@@ -0,0 +1,288 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use lazy_static::lazy_static;
+use lib_dispatch::memory::MemoryUsage;
+
+use crate::entities::SubscribeObject;
+use crate::NotificationSender;
+
+/// The number of undelivered notifications kept per subscriber, the oldest are dropped first.
+pub const NOTIFICATION_BUFFER_CAPACITY: usize = 1000;
+
+lazy_static! {
+  static ref SUBSCRIBERS: Mutex<BufferedSubscribers> = Mutex::new(BufferedSubscribers::default());
+}
+
+/// Registers the sender of the subscriber `name`, e.g. the Flutter isolate, and delivers the
+/// notifications it missed since it was disconnected before any new one. Registering the
+/// same subscriber again replaces its sender.
+///
+/// The notifications that can't be delivered, because the subscriber is disconnected or its
+/// sender fails, are buffered until the subscriber registers again. See
+/// [set_notification_buffer_dir] to keep them across restarts.
+pub fn register_buffered_subscriber<T: NotificationSender>(name: &str, sender: T) {
+  let mut subscribers = SUBSCRIBERS.lock().unwrap();
+  let subscriber = subscribers.entry(name);
+  subscriber.sender = Some(Box::new(sender));
+  subscriber.replay();
+}
+
+/// Keeps buffering the notifications of `name` until it registers again.
+pub fn disconnect_buffered_subscriber(name: &str) {
+  if let Some(subscriber) = SUBSCRIBERS.lock().unwrap().entries.get_mut(name) {
+    subscriber.sender = None;
+  }
+}
+
+/// Persists the buffered notifications in `dir`, one file per subscriber. The notifications
+/// persisted by a previous run are loaded and delivered before the buffered ones.
+pub fn set_notification_buffer_dir<P: AsRef<Path>>(dir: P) {
+  let dir = dir.as_ref().to_path_buf();
+  if let Err(err) = std::fs::create_dir_all(&dir) {
+    tracing::error!("Failed to create the notification buffer dir: {}", err);
+    return;
+  }
+  let mut subscribers = SUBSCRIBERS.lock().unwrap();
+  subscribers.dir = Some(dir.clone());
+  for entry in std::fs::read_dir(&dir).into_iter().flatten().flatten() {
+    if let Some(name) = subscriber_name(&entry.path()) {
+      subscribers.entry(&name);
+    }
+  }
+  for (name, subscriber) in subscribers.entries.iter_mut() {
+    subscriber.attach_file(buffer_file(&dir, name));
+    subscriber.replay();
+  }
+}
+
+pub(crate) fn deliver_to_buffered_subscribers(subject: &SubscribeObject) {
+  let mut subscribers = SUBSCRIBERS.lock().unwrap();
+  for subscriber in subscribers.entries.values_mut() {
+    subscriber.send(subject.clone());
+  }
+}
+
+/// The memory held by the notifications waiting for their subscriber.
+pub(crate) fn buffered_memory_usage() -> MemoryUsage {
+  let subscribers = SUBSCRIBERS.lock().unwrap();
+  subscribers
+    .entries
+    .values()
+    .flat_map(|subscriber| subscriber.queue.iter())
+    .fold(MemoryUsage::default(), |usage, subject| {
+      let bytes = std::mem::size_of::<SubscribeObject>()
+        + subject.source.len()
+        + subject.id.len()
+        + subject.payload.as_ref().map_or(0, Vec::len)
+        + subject.error.as_ref().map_or(0, Vec::len);
+      MemoryUsage::new(usage.bytes + bytes, usage.items + 1)
+    })
+}
+
+pub(crate) fn disconnect_all_buffered_subscribers() {
+  for subscriber in SUBSCRIBERS.lock().unwrap().entries.values_mut() {
+    subscriber.sender = None;
+  }
+}
+
+#[derive(Default)]
+struct BufferedSubscribers {
+  dir: Option<PathBuf>,
+  entries: HashMap<String, BufferedSubscriber>,
+}
+
+impl BufferedSubscribers {
+  fn entry(&mut self, name: &str) -> &mut BufferedSubscriber {
+    let dir = self.dir.clone();
+    self.entries.entry(name.to_owned()).or_insert_with(|| {
+      let mut subscriber = BufferedSubscriber::default();
+      if let Some(dir) = dir {
+        subscriber.attach_file(buffer_file(&dir, name));
+      }
+      subscriber
+    })
+  }
+}
+
+#[derive(Default)]
+struct BufferedSubscriber {
+  sender: Option<Box<dyn NotificationSender>>,
+  queue: VecDeque<SubscribeObject>,
+  file: Option<PathBuf>,
+}
+
+impl BufferedSubscriber {
+  fn send(&mut self, subject: SubscribeObject) {
+    // The notifications are delivered in order, the new ones wait for the buffered ones.
+    if self.queue.is_empty() {
+      if let Some(sender) = &self.sender {
+        match sender.send_subject(subject.clone()) {
+          Ok(_) => return,
+          Err(err) => tracing::warn!("Buffer the undelivered notification: {}", err),
+        }
+      }
+    }
+    self.queue.push_back(subject);
+    while self.queue.len() > NOTIFICATION_BUFFER_CAPACITY {
+      self.queue.pop_front();
+    }
+    self.persist();
+  }
+
+  /// Delivers the buffered notifications, stopping at the first one that fails.
+  fn replay(&mut self) {
+    let sender = match &self.sender {
+      Some(sender) => sender,
+      None => return,
+    };
+    let buffered = self.queue.len();
+    while let Some(subject) = self.queue.front() {
+      if let Err(err) = sender.send_subject(subject.clone()) {
+        tracing::warn!("Replay the buffered notifications failed: {}", err);
+        break;
+      }
+      self.queue.pop_front();
+    }
+    if buffered > 0 {
+      tracing::debug!(
+        "Replayed {} buffered notifications",
+        buffered - self.queue.len()
+      );
+      self.persist();
+    }
+  }
+
+  fn attach_file(&mut self, file: PathBuf) {
+    if self.file.as_ref() == Some(&file) {
+      return;
+    }
+    // The persisted notifications are older than the ones buffered in memory.
+    let mut persisted = read_buffer_file(&file);
+    persisted.extend(self.queue.drain(..));
+    self.queue = persisted;
+    while self.queue.len() > NOTIFICATION_BUFFER_CAPACITY {
+      self.queue.pop_front();
+    }
+    self.file = Some(file);
+    self.persist();
+  }
+
+  fn persist(&self) {
+    let file = match &self.file {
+      Some(file) => file,
+      None => return,
+    };
+    let result = if self.queue.is_empty() {
+      match std::fs::remove_file(file) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.to_string()),
+        _ => Ok(()),
+      }
+    } else {
+      serde_json::to_vec(&self.queue)
+        .map_err(|err| err.to_string())
+        .and_then(|bytes| std::fs::write(file, bytes).map_err(|err| err.to_string()))
+    };
+    if let Err(err) = result {
+      tracing::error!("Failed to persist the buffered notifications: {}", err);
+    }
+  }
+}
+
+const BUFFER_FILE_SUFFIX: &str = ".notifications.json";
+
+fn buffer_file(dir: &Path, name: &str) -> PathBuf {
+  dir.join(format!("{}{}", name, BUFFER_FILE_SUFFIX))
+}
+
+fn subscriber_name(path: &Path) -> Option<String> {
+  let file_name = path.file_name()?.to_str()?;
+  file_name
+    .strip_suffix(BUFFER_FILE_SUFFIX)
+    .map(|name| name.to_owned())
+}
+
+fn read_buffer_file(file: &Path) -> VecDeque<SubscribeObject> {
+  match std::fs::read(file) {
+    Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|err| {
+      tracing::error!("Failed to read the buffered notifications: {}", err);
+      VecDeque::new()
+    }),
+    Err(_) => VecDeque::new(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::atomic::{AtomicBool, Ordering};
+  use std::sync::Arc;
+
+  use crate::entities::SubscribeObject;
+  use crate::{
+    disconnect_buffered_subscriber, register_buffered_subscriber, send_subject,
+    set_notification_buffer_dir, NotificationCollector, NotificationSender,
+  };
+
+  /// A sender whose port can be closed, like the port of a restarting isolate.
+  #[derive(Clone)]
+  struct PortSender {
+    open: Arc<AtomicBool>,
+    collector: NotificationCollector,
+  }
+
+  impl NotificationSender for PortSender {
+    fn send_subject(&self, subject: SubscribeObject) -> Result<(), String> {
+      if !self.open.load(Ordering::SeqCst) {
+        return Err("closed".to_string());
+      }
+      self.collector.send_subject(subject)
+    }
+  }
+
+  fn send(id: &str) {
+    send_subject(SubscribeObject {
+      source: "Buffer".to_string(),
+      id: id.to_string(),
+      ..Default::default()
+    });
+  }
+
+  fn received(collector: &NotificationCollector) -> Vec<String> {
+    collector
+      .notifications()
+      .into_iter()
+      .filter(|subject| subject.source == "Buffer")
+      .map(|subject| subject.id)
+      .collect()
+  }
+
+  #[test]
+  fn replay_buffered_notifications_test() {
+    let dir = std::env::temp_dir().join(format!("notification_buffer_{}", std::process::id()));
+    let sender = PortSender {
+      open: Arc::new(AtomicBool::new(true)),
+      collector: NotificationCollector::new(),
+    };
+    register_buffered_subscriber("buffer_test", sender.clone());
+    send("1");
+
+    // The port closes, then the isolate goes away.
+    sender.open.store(false, Ordering::SeqCst);
+    send("2");
+    disconnect_buffered_subscriber("buffer_test");
+    send("3");
+    set_notification_buffer_dir(&dir);
+    assert!(dir.join("buffer_test.notifications.json").exists());
+    assert_eq!(received(&sender.collector), vec!["1"]);
+
+    // The missed notifications are delivered first, in order.
+    sender.open.store(true, Ordering::SeqCst);
+    register_buffered_subscriber("buffer_test", sender.clone());
+    send("4");
+    assert_eq!(received(&sender.collector), vec!["1", "2", "3", "4"]);
+    assert!(!dir.join("buffer_test.notifications.json").exists());
+
+    let _ = std::fs::remove_dir_all(dir);
+  }
+}
@@ -1,5 +1,7 @@
+use crate::buffer::deliver_to_buffered_subscribers;
 use crate::entities::SubscribeObject;
-use crate::NOTIFICATION_SENDER;
+use crate::send_subject_with_ack;
+use crate::{notify_observers, subscriptions, NotificationType, NOTIFICATION_SENDER};
 use bytes::Bytes;
 use lib_dispatch::prelude::ToBytes;
 
@@ -25,6 +27,11 @@ impl NotificationBuilder {
     }
   }
 
+  /// Same as [NotificationBuilder::new] with the source of the notification type.
+  pub fn typed<T: NotificationType>(id: &str, ty: T) -> Self {
+    Self::new(id, ty, T::SOURCE)
+  }
+
   pub fn payload<T>(mut self, payload: T) -> Self
   where
     T: ToBytes,
@@ -61,26 +68,33 @@ impl NotificationBuilder {
       id: self.id,
       payload,
       error,
+      ack_id: None,
     }
   }
 
   pub fn send(self) {
-    let payload = self.payload.map(|bytes| bytes.to_vec());
-    let error = self.error.map(|bytes| bytes.to_vec());
-    let subject = SubscribeObject {
-      source: self.source,
-      ty: self.ty,
-      id: self.id,
-      payload,
-      error,
-    };
+    send_subject(self.build());
+  }
 
-    send_subject(subject);
+  /// Sends the notification until the client acknowledges it, for the notifications that must
+  /// not be dropped. Returns the ack id the client confirms the receipt with.
+  pub fn send_with_ack(self) -> i64 {
+    send_subject_with_ack(self.build())
   }
 }
 
 #[inline]
 pub fn send_subject(subject: SubscribeObject) {
+  notify_observers(&subject);
+  if !subscriptions().should_deliver_subject(&subject) {
+    tracing::trace!(
+      "Skip the notification of the unsubscribed or filtered out topic: {}",
+      subject.id
+    );
+    return;
+  }
+
+  deliver_to_buffered_subscribers(&subject);
   match NOTIFICATION_SENDER.read() {
     Ok(read_guard) => read_guard.iter().for_each(|sender| {
       if let Err(e) = sender.send_subject(subject.clone()) {
@@ -0,0 +1,306 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use flowy_error::FlowyError;
+use lazy_static::lazy_static;
+use lib_dispatch::prelude::{AFPluginEventRequest, DispatchError, FromAFPluginRequest, Payload};
+use tokio::sync::{mpsc, Semaphore};
+
+use crate::entities::{ChannelFramePB, ChannelNotification};
+use crate::NotificationBuilder;
+
+/// The number of frames buffered in each direction of a channel. The client waits for the
+/// response to a frame before sending the next one, and the handler waits for the acks of its
+/// frames once this many are pending.
+pub const DEFAULT_CHANNEL_WINDOW: usize = 16;
+
+lazy_static! {
+  static ref CHANNELS: ChannelTable = ChannelTable::default();
+}
+
+pub fn channels() -> &'static ChannelTable {
+  &CHANNELS
+}
+
+/// The channels that are open, by id.
+#[derive(Default)]
+pub struct ChannelTable {
+  channels: Mutex<HashMap<String, ChannelState>>,
+}
+
+struct ChannelState {
+  /// Locked while a frame of the client is handed to the handler, so the frames are queued in
+  /// the order of their sequence numbers.
+  inbound: Arc<tokio::sync::Mutex<Inbound>>,
+  /// Taken by the [Channel] of the handler.
+  receiver: Option<mpsc::Receiver<Vec<u8>>>,
+  /// One permit per frame the handler can send before the client acks the previous ones.
+  credits: Arc<Semaphore>,
+  /// The frames sent by the handler that the client hasn't acked yet.
+  unacked: BTreeSet<i64>,
+}
+
+struct Inbound {
+  next_seq: i64,
+  /// Dropped when the client closes the channel, which ends the frames the handler receives.
+  sender: Option<mpsc::Sender<Vec<u8>>>,
+}
+
+impl ChannelTable {
+  /// Opens the channel served by the handler of the request `channel_id`.
+  pub fn open(&self, channel_id: &str) -> Result<(), FlowyError> {
+    let mut channels = self.channels.lock().unwrap();
+    if channels.contains_key(channel_id) {
+      return Err(
+        FlowyError::invalid_data().with_context(format!("The channel {} is open", channel_id)),
+      );
+    }
+    let (sender, receiver) = mpsc::channel(DEFAULT_CHANNEL_WINDOW);
+    channels.insert(
+      channel_id.to_owned(),
+      ChannelState {
+        inbound: Arc::new(tokio::sync::Mutex::new(Inbound {
+          next_seq: 0,
+          sender: Some(sender),
+        })),
+        receiver: Some(receiver),
+        credits: Arc::new(Semaphore::new(DEFAULT_CHANNEL_WINDOW)),
+        unacked: BTreeSet::new(),
+      },
+    );
+    Ok(())
+  }
+
+  /// Hands the frame of the client to the handler. Waits while the handler has a full window of
+  /// frames to read. The frames must be sent in order, the others are rejected.
+  pub async fn receive(&self, frame: ChannelFramePB) -> Result<(), FlowyError> {
+    let inbound = self
+      .channels
+      .lock()
+      .unwrap()
+      .get(&frame.channel_id)
+      .map(|channel| channel.inbound.clone())
+      .ok_or_else(|| channel_closed(&frame.channel_id))?;
+    let mut inbound = inbound.lock().await;
+    if frame.seq != inbound.next_seq {
+      return Err(FlowyError::invalid_data().with_context(format!(
+        "Expected the frame {} of the channel {}, got {}",
+        inbound.next_seq, frame.channel_id, frame.seq
+      )));
+    }
+    let sender = inbound
+      .sender
+      .as_ref()
+      .ok_or_else(|| channel_closed(&frame.channel_id))?;
+    sender
+      .send(frame.payload)
+      .await
+      .map_err(|_| channel_closed(&frame.channel_id))?;
+    inbound.next_seq += 1;
+    Ok(())
+  }
+
+  /// Confirms that the client handled the frame `seq` of the handler. Returns false if the frame
+  /// is unknown or was already acked.
+  pub fn ack(&self, channel_id: &str, seq: i64) -> bool {
+    let mut channels = self.channels.lock().unwrap();
+    match channels.get_mut(channel_id) {
+      Some(channel) if channel.unacked.remove(&seq) => {
+        channel.credits.add_permits(1);
+        true
+      },
+      _ => false,
+    }
+  }
+
+  /// Closes the channel on the side of the client: the handler receives the frames already sent
+  /// and then the end of the channel, and can't send frames anymore. Returns false if the
+  /// channel is not open.
+  pub async fn close(&self, channel_id: &str) -> bool {
+    let inbound = match self.channels.lock().unwrap().get(channel_id) {
+      Some(channel) => {
+        channel.credits.close();
+        channel.inbound.clone()
+      },
+      None => return false,
+    };
+    inbound.lock().await.sender = None;
+    true
+  }
+
+  pub fn is_open(&self, channel_id: &str) -> bool {
+    self.channels.lock().unwrap().contains_key(channel_id)
+  }
+
+  /// The handler side of the channel, once only.
+  fn attach(&self, channel_id: &str) -> Option<Channel> {
+    let mut channels = self.channels.lock().unwrap();
+    let channel = channels.get_mut(channel_id)?;
+    Some(Channel {
+      id: channel_id.to_owned(),
+      receiver: channel.receiver.take()?,
+      credits: channel.credits.clone(),
+      next_seq: AtomicI64::new(0),
+    })
+  }
+
+  fn track(&self, channel_id: &str, seq: i64) {
+    if let Some(channel) = self.channels.lock().unwrap().get_mut(channel_id) {
+      channel.unacked.insert(seq);
+    }
+  }
+
+  fn remove(&self, channel_id: &str) {
+    self.channels.lock().unwrap().remove(channel_id);
+  }
+}
+
+fn channel_closed(channel_id: &str) -> FlowyError {
+  FlowyError::record_not_found().with_context(format!("The channel {} is closed", channel_id))
+}
+
+/// A two-way channel between the client and the handler of a request, e.g. to exchange the
+/// cursor positions of a document while it's open. The frames go over the existing transports:
+/// the client sends its frames with the `SendChannelFrame` event, the handler sends its frames as
+/// [ChannelNotification::DidReceiveFrame] notifications whose id is the channel id.
+///
+/// The client opens the channel with the `OpenChannel` event, whose id is the id of the request
+/// it sends next. The handler of that request extracts the channel and serves it until the
+/// client closes it with the `CloseChannel` event:
+///
+/// ```ignore
+/// pub(crate) async fn share_cursor_handler(
+///   data: AFPluginData<DocumentIdPB>,
+///   mut channel: Channel,
+///   manager: AFPluginState<Weak<DocumentManager>>,
+/// ) -> Result<(), FlowyError> {
+///   let document = manager.upgrade()?.get_document(&data.document_id)?;
+///   let mut remote_cursors = document.subscribe_cursors();
+///   loop {
+///     tokio::select! {
+///       Some(cursor) = channel.recv() => document.set_cursor(cursor)?,
+///       Some(cursor) = remote_cursors.recv() => channel.send(cursor).await?,
+///       else => return Ok(()),
+///     }
+///   }
+/// }
+/// ```
+///
+/// The client is notified with [ChannelNotification::DidClose] once the channel is dropped.
+pub struct Channel {
+  id: String,
+  receiver: mpsc::Receiver<Vec<u8>>,
+  credits: Arc<Semaphore>,
+  next_seq: AtomicI64,
+}
+
+impl Channel {
+  pub fn id(&self) -> &str {
+    &self.id
+  }
+
+  /// The next frame of the client, or `None` once the client closed the channel.
+  pub async fn recv(&mut self) -> Option<Vec<u8>> {
+    self.receiver.recv().await
+  }
+
+  /// Sends a frame to the client. Waits while a full window of frames is not acked yet. Fails
+  /// once the client closed the channel.
+  pub async fn send<T: Into<Vec<u8>>>(&self, payload: T) -> Result<(), FlowyError> {
+    let credit = self
+      .credits
+      .acquire()
+      .await
+      .map_err(|_| channel_closed(&self.id))?;
+    credit.forget();
+    let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+    channels().track(&self.id, seq);
+    NotificationBuilder::typed(&self.id, ChannelNotification::DidReceiveFrame)
+      .payload(ChannelFramePB {
+        channel_id: self.id.clone(),
+        seq,
+        payload: payload.into(),
+      })
+      .send();
+    Ok(())
+  }
+}
+
+impl Drop for Channel {
+  fn drop(&mut self) {
+    channels().remove(&self.id);
+    NotificationBuilder::typed(&self.id, ChannelNotification::DidClose).send();
+  }
+}
+
+impl FromAFPluginRequest for Channel {
+  type Error = DispatchError;
+  type Future = std::future::Ready<Result<Self, DispatchError>>;
+
+  fn from_request(req: &AFPluginEventRequest, _: &mut Payload) -> Self::Future {
+    let channel = channels().attach(req.id()).ok_or_else(|| {
+      DispatchError::from(format!("No channel is open for the request {}", req.id()))
+    });
+    std::future::ready(channel)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::entities::ChannelFramePB;
+  use crate::{channels, DEFAULT_CHANNEL_WINDOW};
+
+  fn frame(channel_id: &str, seq: i64, payload: &[u8]) -> ChannelFramePB {
+    ChannelFramePB {
+      channel_id: channel_id.to_owned(),
+      seq,
+      payload: payload.to_vec(),
+    }
+  }
+
+  #[tokio::test]
+  async fn channel_frames_test() {
+    channels().open("channel_1").unwrap();
+    assert!(channels().open("channel_1").is_err());
+    let mut channel = channels().attach("channel_1").unwrap();
+    assert!(channels().attach("channel_1").is_none());
+
+    // The frames of the client are delivered in order, the others are rejected.
+    channels()
+      .receive(frame("channel_1", 0, b"a"))
+      .await
+      .unwrap();
+    assert!(channels()
+      .receive(frame("channel_1", 2, b"c"))
+      .await
+      .is_err());
+    channels()
+      .receive(frame("channel_1", 1, b"b"))
+      .await
+      .unwrap();
+    assert_eq!(channel.recv().await.unwrap(), b"a");
+    assert_eq!(channel.recv().await.unwrap(), b"b");
+
+    // The handler sends a window of frames before it waits for the acks.
+    for _ in 0..DEFAULT_CHANNEL_WINDOW {
+      channel.send(b"x".to_vec()).await.unwrap();
+    }
+    assert_eq!(channel.credits.available_permits(), 0);
+    assert!(channels().ack("channel_1", 0));
+    assert!(!channels().ack("channel_1", 0));
+    assert_eq!(channel.credits.available_permits(), 1);
+
+    // Closing ends the frames of the client and the sends of the handler.
+    assert!(channels().close("channel_1").await);
+    assert!(channel.recv().await.is_none());
+    assert!(channel.send(b"y".to_vec()).await.is_err());
+    assert!(channels()
+      .receive(frame("channel_1", 2, b"c"))
+      .await
+      .is_err());
+
+    drop(channel);
+    assert!(!channels().is_open("channel_1"));
+  }
+}
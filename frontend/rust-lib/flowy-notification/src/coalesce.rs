@@ -0,0 +1,141 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+
+use crate::entities::SubscribeObject;
+use crate::send_subject;
+
+type FoldFn = Arc<dyn Fn(SubscribeObject, SubscribeObject) -> SubscribeObject + Send + Sync>;
+
+/// How the notifications of the same topic sent within a window become one.
+#[derive(Clone)]
+pub enum CoalesceStrategy {
+  /// Only the last notification is delivered, for the notifications carrying a full state.
+  KeepLatest,
+  /// The pending notification and the new one are merged by the fold fn, for the notifications
+  /// carrying a delta.
+  Merge(FoldFn),
+}
+
+impl CoalesceStrategy {
+  pub fn merge<F>(fold: F) -> Self
+  where
+    F: Fn(SubscribeObject, SubscribeObject) -> SubscribeObject + Send + Sync + 'static,
+  {
+    CoalesceStrategy::Merge(Arc::new(fold))
+  }
+}
+
+/// Coalesces the notifications of the same topic, identified by its source, id and type, that
+/// are sent within `window` into a single one, to cut the FFI chatter caused by rapid edits.
+///
+/// Unlike the [DebounceNotificationSender](crate::DebounceNotificationSender), the window starts
+/// with the first notification of the topic and is not extended by the next ones, so a steady
+/// stream of edits is still delivered every `window`.
+#[derive(Clone)]
+pub struct NotificationCoalescer {
+  window: Duration,
+  strategy: CoalesceStrategy,
+  pending: Arc<DashMap<String, SubscribeObject>>,
+}
+
+impl NotificationCoalescer {
+  pub fn new(window: Duration, strategy: CoalesceStrategy) -> Self {
+    Self {
+      window,
+      strategy,
+      pending: Arc::new(DashMap::new()),
+    }
+  }
+
+  pub fn send_subject(&self, subject: SubscribeObject) {
+    let runtime = match tokio::runtime::Handle::try_current() {
+      Ok(runtime) if !self.window.is_zero() => runtime,
+      _ => {
+        send_subject(subject);
+        return;
+      },
+    };
+
+    let key = format!("{}-{}-{}", subject.source, subject.id, subject.ty);
+    match self.pending.entry(key.clone()) {
+      Entry::Occupied(mut entry) => {
+        let merged = match &self.strategy {
+          CoalesceStrategy::KeepLatest => subject,
+          CoalesceStrategy::Merge(fold) => fold(entry.get().clone(), subject),
+        };
+        entry.insert(merged);
+      },
+      Entry::Vacant(entry) => {
+        entry.insert(subject);
+        let pending = self.pending.clone();
+        let window = self.window;
+        runtime.spawn(async move {
+          tokio::time::sleep(window).await;
+          if let Some((_, subject)) = pending.remove(&key) {
+            send_subject(subject);
+          }
+        });
+      },
+    }
+  }
+
+  /// The number of topics waiting for their window to end.
+  pub fn pending(&self) -> usize {
+    self.pending.len()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use crate::entities::SubscribeObject;
+  use crate::{
+    register_notification_sender, CoalesceStrategy, NotificationCoalescer, NotificationCollector,
+  };
+
+  fn subject(id: &str, payload: u8) -> SubscribeObject {
+    SubscribeObject {
+      source: "Document".to_string(),
+      ty: 1,
+      id: id.to_string(),
+      payload: Some(vec![payload]),
+      ..Default::default()
+    }
+  }
+
+  #[tokio::test]
+  async fn coalesce_notification_test() {
+    let collector = NotificationCollector::new();
+    register_notification_sender(collector.clone());
+    let window = Duration::from_millis(20);
+    let latest = NotificationCoalescer::new(window, CoalesceStrategy::KeepLatest);
+    let merged = NotificationCoalescer::new(
+      window,
+      CoalesceStrategy::merge(|mut pending, next| {
+        pending
+          .payload
+          .get_or_insert_with(Vec::new)
+          .extend(next.payload.unwrap_or_default());
+        pending
+      }),
+    );
+
+    for i in 1..=3 {
+      latest.send_subject(subject("latest", i));
+      merged.send_subject(subject("merged", i));
+    }
+    assert_eq!(latest.pending(), 1);
+    tokio::time::sleep(window * 3).await;
+
+    let latest = collector.find("latest", 1);
+    assert_eq!(latest.len(), 1);
+    assert_eq!(latest[0].payload, Some(vec![3]));
+    let merged = collector.find("merged", 1);
+    assert_eq!(merged.len(), 1);
+    assert_eq!(merged[0].payload, Some(vec![1, 2, 3]));
+  }
+}
@@ -0,0 +1,69 @@
+use flowy_derive::{ProtoBuf, ProtoBuf_Enum};
+
+use crate::NotificationType;
+
+pub(crate) const CHANNEL_OBSERVABLE_SOURCE: &str = "Channel";
+
+/// Opens or closes a [Channel](crate::Channel). The id of the channel is the id of the request
+/// whose handler serves it.
+#[derive(Default, ProtoBuf, Clone, Debug)]
+pub struct ChannelIdPB {
+  #[pb(index = 1)]
+  pub channel_id: String,
+}
+
+/// A frame of a channel, sent by the client with the `SendChannelFrame` event or by the handler
+/// as a [ChannelNotification::DidReceiveFrame] notification. The frames of each direction are
+/// numbered from 0.
+#[derive(Default, ProtoBuf, Clone, Debug, PartialEq)]
+pub struct ChannelFramePB {
+  #[pb(index = 1)]
+  pub channel_id: String,
+
+  #[pb(index = 2)]
+  pub seq: i64,
+
+  #[pb(index = 3)]
+  pub payload: Vec<u8>,
+}
+
+/// Confirms that the client handled the frame `seq` sent by the handler, which lets the handler
+/// send one more frame.
+#[derive(Default, ProtoBuf, Clone, Debug)]
+pub struct ChannelAckPB {
+  #[pb(index = 1)]
+  pub channel_id: String,
+
+  #[pb(index = 2)]
+  pub seq: i64,
+}
+
+#[derive(ProtoBuf_Enum, Debug, Default)]
+pub enum ChannelNotification {
+  #[default]
+  Unknown = 0,
+  /// The payload is a [ChannelFramePB].
+  DidReceiveFrame = 1,
+  /// The handler is done with the channel, no more frames are sent or received.
+  DidClose = 2,
+}
+
+impl std::convert::From<ChannelNotification> for i32 {
+  fn from(notification: ChannelNotification) -> Self {
+    notification as i32
+  }
+}
+
+impl std::convert::From<i32> for ChannelNotification {
+  fn from(notification: i32) -> Self {
+    match notification {
+      1 => ChannelNotification::DidReceiveFrame,
+      2 => ChannelNotification::DidClose,
+      _ => ChannelNotification::Unknown,
+    }
+  }
+}
+
+impl NotificationType for ChannelNotification {
+  const SOURCE: &'static str = CHANNEL_OBSERVABLE_SOURCE;
+}
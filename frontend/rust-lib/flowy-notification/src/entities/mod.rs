@@ -1,3 +1,9 @@
+mod channel;
+mod progress;
 mod subject;
+mod subscription;
 
+pub use channel::*;
+pub use progress::*;
 pub use subject::*;
+pub use subscription::*;
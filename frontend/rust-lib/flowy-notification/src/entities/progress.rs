@@ -0,0 +1,62 @@
+use flowy_derive::{ProtoBuf, ProtoBuf_Enum};
+
+use crate::NotificationType;
+
+pub(crate) const PROGRESS_OBSERVABLE_SOURCE: &str = "Progress";
+
+/// The progress of a long operation, e.g. an import or a full sync. It's sent with the id of
+/// the request that started the operation as the notification id.
+#[derive(Default, ProtoBuf, Clone, Debug, PartialEq)]
+pub struct ProgressPB {
+  #[pb(index = 1)]
+  pub request_id: String,
+
+  #[pb(index = 2)]
+  pub completed: u64,
+
+  /// 0 if the total is not known yet.
+  #[pb(index = 3)]
+  pub total: u64,
+
+  #[pb(index = 4)]
+  pub message: String,
+
+  #[pb(index = 5)]
+  pub is_finish: bool,
+}
+
+impl ProgressPB {
+  /// The completion between 0 and 100, if the total is known.
+  pub fn percent(&self) -> Option<u8> {
+    if self.total == 0 {
+      return None;
+    }
+    Some((self.completed.min(self.total) * 100 / self.total) as u8)
+  }
+}
+
+#[derive(ProtoBuf_Enum, Debug, Default)]
+pub enum ProgressNotification {
+  #[default]
+  Unknown = 0,
+  DidUpdateProgress = 1,
+}
+
+impl std::convert::From<ProgressNotification> for i32 {
+  fn from(notification: ProgressNotification) -> Self {
+    notification as i32
+  }
+}
+
+impl std::convert::From<i32> for ProgressNotification {
+  fn from(notification: i32) -> Self {
+    match notification {
+      1 => ProgressNotification::DidUpdateProgress,
+      _ => ProgressNotification::Unknown,
+    }
+  }
+}
+
+impl NotificationType for ProgressNotification {
+  const SOURCE: &'static str = PROGRESS_OBSERVABLE_SOURCE;
+}
@@ -1,8 +1,8 @@
 use flowy_derive::ProtoBuf;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::{fmt, fmt::Formatter};
 
-#[derive(Debug, Clone, ProtoBuf, Serialize)]
+#[derive(Debug, Clone, ProtoBuf, Serialize, Deserialize)]
 pub struct SubscribeObject {
   #[pb(index = 1)]
   pub source: String,
@@ -18,6 +18,12 @@ pub struct SubscribeObject {
 
   #[pb(index = 5, one_of)]
   pub error: Option<Vec<u8>>,
+
+  /// Set on the notifications that must be acknowledged by the client, see
+  /// [NotificationBuilder::send_with_ack](crate::NotificationBuilder::send_with_ack).
+  #[pb(index = 6, one_of)]
+  #[serde(default)]
+  pub ack_id: Option<i64>,
 }
 
 impl std::fmt::Display for SubscribeObject {
@@ -43,6 +49,7 @@ impl std::default::Default for SubscribeObject {
       id: "".to_string(),
       payload: None,
       error: None,
+      ack_id: None,
     }
   }
 }
@@ -0,0 +1,21 @@
+use flowy_derive::ProtoBuf;
+
+/// A topic the client subscribes to, usually the id of the observed object, e.g. a document id
+/// or a workspace id.
+#[derive(Default, ProtoBuf, Clone, Debug)]
+pub struct NotificationTopicPB {
+  #[pb(index = 1)]
+  pub id: String,
+
+  /// Only the notifications of the topic whose payload matches the
+  /// [NotificationFilter](crate::NotificationFilter) expression are delivered, e.g.
+  /// `row.id in ["r1", "r2"]`.
+  #[pb(index = 2, one_of)]
+  pub filter: Option<String>,
+}
+
+#[derive(Default, ProtoBuf, Clone, Debug)]
+pub struct NotificationAckPB {
+  #[pb(index = 1)]
+  pub ack_id: i64,
+}
@@ -0,0 +1,94 @@
+use flowy_error::FlowyError;
+use lib_dispatch::prelude::AFPluginData;
+
+use crate::entities::{
+  ChannelAckPB, ChannelFramePB, ChannelIdPB, NotificationAckPB, NotificationTopicPB,
+};
+use crate::{ack_notification, channels, subscriptions, NotificationFilter};
+
+#[tracing::instrument(level = "debug", skip(data), err)]
+pub(crate) async fn subscribe_notification_handler(
+  data: AFPluginData<NotificationTopicPB>,
+) -> Result<(), FlowyError> {
+  let topic = data.into_inner();
+  if topic.id.is_empty() {
+    return Err(FlowyError::invalid_data().with_context("The topic id is empty"));
+  }
+  let filter = topic
+    .filter
+    .as_deref()
+    .map(NotificationFilter::parse)
+    .transpose()
+    .map_err(|err| FlowyError::invalid_data().with_context(err))?;
+  subscriptions().subscribe_with_filter(&topic.id, filter);
+  Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip(data), err)]
+pub(crate) async fn unsubscribe_notification_handler(
+  data: AFPluginData<NotificationTopicPB>,
+) -> Result<(), FlowyError> {
+  let topic = data.into_inner();
+  if !subscriptions().unsubscribe_with_filter(&topic.id, topic.filter.as_deref()) {
+    tracing::debug!(
+      "Unsubscribe from a topic that is not subscribed: {}",
+      topic.id
+    );
+  }
+  Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip(data), err)]
+pub(crate) async fn ack_notification_handler(
+  data: AFPluginData<NotificationAckPB>,
+) -> Result<(), FlowyError> {
+  let ack_id = data.into_inner().ack_id;
+  if !ack_notification(ack_id) {
+    tracing::debug!("Ack an unknown or already acked notification: {}", ack_id);
+  }
+  Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip(data), err)]
+pub(crate) async fn open_channel_handler(
+  data: AFPluginData<ChannelIdPB>,
+) -> Result<(), FlowyError> {
+  let channel_id = data.into_inner().channel_id;
+  if channel_id.is_empty() {
+    return Err(FlowyError::invalid_data().with_context("The channel id is empty"));
+  }
+  channels().open(&channel_id)
+}
+
+#[tracing::instrument(level = "debug", skip(data), err)]
+pub(crate) async fn send_channel_frame_handler(
+  data: AFPluginData<ChannelFramePB>,
+) -> Result<(), FlowyError> {
+  channels().receive(data.into_inner()).await
+}
+
+#[tracing::instrument(level = "debug", skip(data), err)]
+pub(crate) async fn ack_channel_frame_handler(
+  data: AFPluginData<ChannelAckPB>,
+) -> Result<(), FlowyError> {
+  let ack = data.into_inner();
+  if !channels().ack(&ack.channel_id, ack.seq) {
+    tracing::debug!(
+      "Ack an unknown or already acked frame {} of the channel {}",
+      ack.seq,
+      ack.channel_id
+    );
+  }
+  Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip(data), err)]
+pub(crate) async fn close_channel_handler(
+  data: AFPluginData<ChannelIdPB>,
+) -> Result<(), FlowyError> {
+  let channel_id = data.into_inner().channel_id;
+  if !channels().close(&channel_id).await {
+    tracing::debug!("Close a channel that is not open: {}", channel_id);
+  }
+  Ok(())
+}
@@ -0,0 +1,68 @@
+use strum_macros::Display;
+
+use flowy_derive::{Flowy_Event, ProtoBuf_Enum};
+use lib_dispatch::prelude::AFPlugin;
+
+use crate::buffer::buffered_memory_usage;
+use crate::event_handler::*;
+
+pub fn init() -> AFPlugin {
+  AFPlugin::new()
+    .name(env!("CARGO_PKG_NAME"))
+    .proto_descriptors(crate::protobuf::file_descriptors())
+    .event(
+      NotificationEvent::SubscribeNotification,
+      subscribe_notification_handler,
+    )
+    .event(
+      NotificationEvent::UnsubscribeNotification,
+      unsubscribe_notification_handler,
+    )
+    .event(NotificationEvent::AckNotification, ack_notification_handler)
+    .event(NotificationEvent::OpenChannel, open_channel_handler)
+    .event(
+      NotificationEvent::SendChannelFrame,
+      send_channel_frame_handler,
+    )
+    .event(
+      NotificationEvent::AckChannelFrame,
+      ack_channel_frame_handler,
+    )
+    .event(NotificationEvent::CloseChannel, close_channel_handler)
+    .memory_reporter("notification.buffers", buffered_memory_usage)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Display, ProtoBuf_Enum, Flowy_Event)]
+#[event_err = "FlowyError"]
+pub enum NotificationEvent {
+  /// Only deliver the notifications of the subscribed topics, see [SubscriptionTable].
+  ///
+  /// [SubscriptionTable]: crate::SubscriptionTable
+  #[event(input = "NotificationTopicPB")]
+  SubscribeNotification = 0,
+
+  #[event(input = "NotificationTopicPB")]
+  UnsubscribeNotification = 1,
+
+  /// Confirms the receipt of a notification sent with an ack id.
+  #[event(input = "NotificationAckPB")]
+  AckNotification = 2,
+
+  /// Opens a channel with the handler of the request whose id is the channel id, see [Channel].
+  ///
+  /// [Channel]: crate::Channel
+  #[event(input = "ChannelIdPB")]
+  OpenChannel = 3,
+
+  /// Sends a frame to the handler. The response is delayed while the handler has a full window
+  /// of frames to read.
+  #[event(input = "ChannelFramePB")]
+  SendChannelFrame = 4,
+
+  /// Confirms the receipt of a frame sent by the handler.
+  #[event(input = "ChannelAckPB")]
+  AckChannelFrame = 5,
+
+  #[event(input = "ChannelIdPB")]
+  CloseChannel = 6,
+}
@@ -0,0 +1,448 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, RwLock};
+
+use bytes::Bytes;
+use lazy_static::lazy_static;
+use serde_json::Value;
+
+use crate::TypedNotification;
+
+type PayloadView = Arc<dyn Fn(&[u8]) -> Option<Value> + Send + Sync>;
+
+lazy_static! {
+  static ref PAYLOAD_VIEWS: RwLock<HashMap<(String, i32), PayloadView>> =
+    RwLock::new(HashMap::new());
+}
+
+/// Registers how the payload of the notifications of `source` and `ty` is seen by the
+/// subscription filters. The notifications without a view are always delivered, their payload
+/// can't be filtered.
+pub fn register_payload_view<F>(source: &str, ty: i32, view: F)
+where
+  F: Fn(&[u8]) -> Option<Value> + Send + Sync + 'static,
+{
+  PAYLOAD_VIEWS
+    .write()
+    .unwrap()
+    .insert((source.to_owned(), ty), Arc::new(view));
+}
+
+/// Same as [register_payload_view] for a [TypedNotification], `view` gets the deserialized
+/// payload.
+pub fn register_typed_payload_view<T, F>(view: F)
+where
+  T: TypedNotification,
+  F: Fn(&T) -> Value + Send + Sync + 'static,
+{
+  register_payload_view(T::SOURCE, T::TY, move |bytes| {
+    T::try_from(Bytes::copy_from_slice(bytes))
+      .ok()
+      .map(|payload| view(&payload))
+  });
+}
+
+pub(crate) fn payload_view(source: &str, ty: i32) -> Option<PayloadView> {
+  PAYLOAD_VIEWS
+    .read()
+    .unwrap()
+    .get(&(source.to_owned(), ty))
+    .cloned()
+}
+
+/// A filter expression a subscription carries, evaluated against the payload view of the
+/// notifications before they are delivered, e.g.
+/// `row.id in ["r1", "r2"] && !is_hidden` or `progress >= 50`.
+///
+/// The paths read the fields of the view, `a.b[0]`, and resolve to `null` when the field is
+/// missing. The values are compared with `==`, `!=`, `<`, `<=`, `>`, `>=` and `in`, the
+/// conditions are combined with `&&`, `||`, `!` and parentheses. A path alone is true unless
+/// it's `false` or `null`. When the path resolves to an array, `in` is true if any of its
+/// elements is in the list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotificationFilter {
+  source: String,
+  expr: Expr,
+}
+
+impl NotificationFilter {
+  pub fn parse(source: &str) -> Result<Self, String> {
+    let tokens = tokenize(source)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let expr = parser.parse_or()?;
+    if parser.pos != parser.tokens.len() {
+      return Err(format!(
+        "Unexpected {:?} in the filter: {}",
+        parser.tokens[parser.pos], source
+      ));
+    }
+    Ok(Self {
+      source: source.to_owned(),
+      expr,
+    })
+  }
+
+  /// The expression the filter was parsed from.
+  pub fn as_str(&self) -> &str {
+    &self.source
+  }
+
+  pub fn matches(&self, value: &Value) -> bool {
+    self.expr.eval(value)
+  }
+}
+
+impl Display for NotificationFilter {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.write_str(&self.source)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+  Or(Box<Expr>, Box<Expr>),
+  And(Box<Expr>, Box<Expr>),
+  Not(Box<Expr>),
+  Compare(Path, CompareOp, Value),
+  In(Path, Vec<Value>),
+  Truthy(Path),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CompareOp {
+  Eq,
+  Ne,
+  Lt,
+  Le,
+  Gt,
+  Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+  Field(String),
+  Index(usize),
+}
+
+type Path = Vec<Segment>;
+
+impl Expr {
+  fn eval(&self, value: &Value) -> bool {
+    match self {
+      Expr::Or(left, right) => left.eval(value) || right.eval(value),
+      Expr::And(left, right) => left.eval(value) && right.eval(value),
+      Expr::Not(expr) => !expr.eval(value),
+      Expr::Compare(path, op, literal) => compare(resolve(value, path), *op, literal),
+      Expr::In(path, literals) => {
+        let contains = |field: &Value| {
+          literals
+            .iter()
+            .any(|literal| compare(field, CompareOp::Eq, literal))
+        };
+        match resolve(value, path) {
+          Value::Array(fields) => fields.iter().any(contains),
+          field => contains(field),
+        }
+      },
+      Expr::Truthy(path) => !matches!(resolve(value, path), Value::Null | Value::Bool(false)),
+    }
+  }
+}
+
+fn resolve<'a>(value: &'a Value, path: &Path) -> &'a Value {
+  let mut current = value;
+  for segment in path {
+    let next = match segment {
+      Segment::Field(name) => current.get(name),
+      Segment::Index(index) => current.get(index),
+    };
+    current = match next {
+      Some(next) => next,
+      None => return &Value::Null,
+    };
+  }
+  current
+}
+
+fn compare(field: &Value, op: CompareOp, literal: &Value) -> bool {
+  let ordering = match (field, literal) {
+    (Value::Number(a), Value::Number(b)) => match (a.as_f64(), b.as_f64()) {
+      (Some(a), Some(b)) => a.partial_cmp(&b),
+      _ => None,
+    },
+    (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+    _ => None,
+  };
+  match op {
+    CompareOp::Eq => ordering.map(|o| o.is_eq()).unwrap_or(field == literal),
+    CompareOp::Ne => !ordering.map(|o| o.is_eq()).unwrap_or(field == literal),
+    CompareOp::Lt => ordering.map(|o| o.is_lt()).unwrap_or(false),
+    CompareOp::Le => ordering.map(|o| o.is_le()).unwrap_or(false),
+    CompareOp::Gt => ordering.map(|o| o.is_gt()).unwrap_or(false),
+    CompareOp::Ge => ordering.map(|o| o.is_ge()).unwrap_or(false),
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+  Ident(String),
+  Literal(Value),
+  Op(CompareOp),
+  In,
+  And,
+  Or,
+  Not,
+  LParen,
+  RParen,
+  LBracket,
+  RBracket,
+  Comma,
+  Dot,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+  let chars: Vec<char> = source.chars().collect();
+  let mut tokens = vec![];
+  let mut i = 0;
+  while i < chars.len() {
+    let c = chars[i];
+    let next = chars.get(i + 1).copied();
+    let (token, len) = match c {
+      c if c.is_whitespace() => {
+        i += 1;
+        continue;
+      },
+      '(' => (Token::LParen, 1),
+      ')' => (Token::RParen, 1),
+      '[' => (Token::LBracket, 1),
+      ']' => (Token::RBracket, 1),
+      ',' => (Token::Comma, 1),
+      '.' => (Token::Dot, 1),
+      '&' if next == Some('&') => (Token::And, 2),
+      '|' if next == Some('|') => (Token::Or, 2),
+      '=' if next == Some('=') => (Token::Op(CompareOp::Eq), 2),
+      '!' if next == Some('=') => (Token::Op(CompareOp::Ne), 2),
+      '!' => (Token::Not, 1),
+      '<' if next == Some('=') => (Token::Op(CompareOp::Le), 2),
+      '<' => (Token::Op(CompareOp::Lt), 1),
+      '>' if next == Some('=') => (Token::Op(CompareOp::Ge), 2),
+      '>' => (Token::Op(CompareOp::Gt), 1),
+      '"' => {
+        let mut value = String::new();
+        let mut end = i + 1;
+        loop {
+          match chars.get(end) {
+            Some('"') => break,
+            Some('\\') => {
+              let escaped = chars
+                .get(end + 1)
+                .ok_or_else(|| format!("Unterminated string in the filter: {}", source))?;
+              value.push(*escaped);
+              end += 2;
+            },
+            Some(c) => {
+              value.push(*c);
+              end += 1;
+            },
+            None => return Err(format!("Unterminated string in the filter: {}", source)),
+          }
+        }
+        (Token::Literal(Value::String(value)), end + 1 - i)
+      },
+      c if c.is_ascii_digit() || (c == '-' && next.map_or(false, |n| n.is_ascii_digit())) => {
+        let mut end = i + 1;
+        while end < chars.len() && (chars[end].is_ascii_digit() || chars[end] == '.') {
+          end += 1;
+        }
+        let text: String = chars[i..end].iter().collect();
+        let number = serde_json::from_str::<Value>(&text)
+          .map_err(|_| format!("Invalid number {} in the filter: {}", text, source))?;
+        (Token::Literal(number), end - i)
+      },
+      c if c.is_alphabetic() || c == '_' => {
+        let mut end = i + 1;
+        while end < chars.len() && (chars[end].is_alphanumeric() || chars[end] == '_') {
+          end += 1;
+        }
+        let word: String = chars[i..end].iter().collect();
+        let token = match word.as_str() {
+          "true" => Token::Literal(Value::Bool(true)),
+          "false" => Token::Literal(Value::Bool(false)),
+          "null" => Token::Literal(Value::Null),
+          "in" => Token::In,
+          _ => Token::Ident(word),
+        };
+        (token, end - i)
+      },
+      c => return Err(format!("Unexpected '{}' in the filter: {}", c, source)),
+    };
+    tokens.push(token);
+    i += len;
+  }
+  Ok(tokens)
+}
+
+struct Parser {
+  tokens: Vec<Token>,
+  pos: usize,
+}
+
+impl Parser {
+  fn peek(&self) -> Option<&Token> {
+    self.tokens.get(self.pos)
+  }
+
+  fn next(&mut self) -> Option<Token> {
+    let token = self.tokens.get(self.pos).cloned();
+    self.pos += 1;
+    token
+  }
+
+  fn expect(&mut self, expected: Token) -> Result<(), String> {
+    match self.next() {
+      Some(token) if token == expected => Ok(()),
+      token => Err(format!("Expected {:?}, found {:?}", expected, token)),
+    }
+  }
+
+  fn parse_or(&mut self) -> Result<Expr, String> {
+    let mut expr = self.parse_and()?;
+    while self.peek() == Some(&Token::Or) {
+      self.pos += 1;
+      expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+    }
+    Ok(expr)
+  }
+
+  fn parse_and(&mut self) -> Result<Expr, String> {
+    let mut expr = self.parse_unary()?;
+    while self.peek() == Some(&Token::And) {
+      self.pos += 1;
+      expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+    }
+    Ok(expr)
+  }
+
+  fn parse_unary(&mut self) -> Result<Expr, String> {
+    match self.peek() {
+      Some(Token::Not) => {
+        self.pos += 1;
+        Ok(Expr::Not(Box::new(self.parse_unary()?)))
+      },
+      Some(Token::LParen) => {
+        self.pos += 1;
+        let expr = self.parse_or()?;
+        self.expect(Token::RParen)?;
+        Ok(expr)
+      },
+      _ => self.parse_condition(),
+    }
+  }
+
+  fn parse_condition(&mut self) -> Result<Expr, String> {
+    let path = self.parse_path()?;
+    match self.peek() {
+      Some(Token::Op(op)) => {
+        let op = *op;
+        self.pos += 1;
+        Ok(Expr::Compare(path, op, self.parse_literal()?))
+      },
+      Some(Token::In) => {
+        self.pos += 1;
+        self.expect(Token::LBracket)?;
+        let mut literals = vec![];
+        if self.peek() != Some(&Token::RBracket) {
+          loop {
+            literals.push(self.parse_literal()?);
+            if self.peek() != Some(&Token::Comma) {
+              break;
+            }
+            self.pos += 1;
+          }
+        }
+        self.expect(Token::RBracket)?;
+        Ok(Expr::In(path, literals))
+      },
+      _ => Ok(Expr::Truthy(path)),
+    }
+  }
+
+  fn parse_path(&mut self) -> Result<Path, String> {
+    let mut path = match self.next() {
+      Some(Token::Ident(name)) => vec![Segment::Field(name)],
+      token => return Err(format!("Expected a field, found {:?}", token)),
+    };
+    loop {
+      match self.peek() {
+        Some(Token::Dot) => {
+          self.pos += 1;
+          match self.next() {
+            Some(Token::Ident(name)) => path.push(Segment::Field(name)),
+            token => return Err(format!("Expected a field after '.', found {:?}", token)),
+          }
+        },
+        Some(Token::LBracket) => {
+          self.pos += 1;
+          match self.next() {
+            Some(Token::Literal(Value::Number(n))) if n.as_u64().is_some() => {
+              path.push(Segment::Index(n.as_u64().unwrap() as usize))
+            },
+            token => return Err(format!("Expected an index, found {:?}", token)),
+          }
+          self.expect(Token::RBracket)?;
+        },
+        _ => return Ok(path),
+      }
+    }
+  }
+
+  fn parse_literal(&mut self) -> Result<Value, String> {
+    match self.next() {
+      Some(Token::Literal(value)) => Ok(value),
+      token => Err(format!("Expected a value, found {:?}", token)),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use crate::NotificationFilter;
+
+  #[test]
+  fn notification_filter_test() {
+    let row = json!({
+      "row": { "id": "r1", "height": 40 },
+      "cells": [{ "field_id": "f1" }],
+      "tags": ["a", "b"],
+      "is_hidden": false,
+    });
+    let cases = [
+      (r#"row.id == "r1""#, true),
+      (r#"row.id in ["r2", "r3"]"#, false),
+      (r#"row.id in ["r2", "r1"] && !is_hidden"#, true),
+      (r#"tags in ["b", "c"]"#, true),
+      (r#"tags in ["c"]"#, false),
+      ("row.height >= 40 && row.height < 41", true),
+      (r#"cells[0].field_id != "f1" || is_hidden"#, false),
+      ("!(row.missing == null)", false),
+      ("row.id", true),
+      ("is_hidden", false),
+    ];
+    for (source, expected) in cases {
+      let filter = NotificationFilter::parse(source).unwrap();
+      assert_eq!(filter.matches(&row), expected, "{}", source);
+    }
+
+    for invalid in [
+      "row.id ==",
+      r#"row.id == "r1"#,
+      "row.id in r1",
+      "&& row.id",
+      "row.id == 1)",
+    ] {
+      assert!(NotificationFilter::parse(invalid).is_err(), "{}", invalid);
+    }
+  }
+}
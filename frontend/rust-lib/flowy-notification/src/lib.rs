@@ -5,10 +5,42 @@ use lazy_static::lazy_static;
 mod builder;
 pub use builder::*;
 
+mod ack;
+pub use ack::*;
+
+mod channel;
+pub use channel::*;
+
+mod buffer;
+pub use buffer::{
+  disconnect_buffered_subscriber, register_buffered_subscriber, set_notification_buffer_dir,
+  NOTIFICATION_BUFFER_CAPACITY,
+};
+
+mod coalesce;
+pub use coalesce::*;
+
 mod debounce;
 pub use debounce::*;
 
+mod filter;
+pub use filter::{register_payload_view, register_typed_payload_view, NotificationFilter};
+
+mod observer;
+pub use observer::*;
+
+mod progress;
+pub use progress::*;
+
+mod transport;
+pub use transport::*;
+
+mod subscription;
+pub use subscription::*;
+
 pub mod entities;
+mod event_handler;
+pub mod event_map;
 mod protobuf;
 
 lazy_static! {
@@ -32,8 +64,19 @@ pub fn unregister_all_notification_sender() {
     Ok(mut write_guard) => write_guard.clear(),
     Err(err) => tracing::error!("Failed to remove all notification senders: {:?}", err),
   }
+  // The subscriptions belong to the client that registered the senders.
+  subscriptions().reset();
+  buffer::disconnect_all_buffered_subscribers();
 }
 
+/// The transport that delivers the notifications to a client: a Dart port, the websocket
+/// bridge or the [NotificationCollector] of the tests.
 pub trait NotificationSender: Send + Sync + 'static {
   fn send_subject(&self, subject: SubscribeObject) -> Result<(), String>;
 }
+
+/// The notification enum of a module, e.g. `FolderNotification`, tied to the source the
+/// frontend observes it from.
+pub trait NotificationType: Into<i32> {
+  const SOURCE: &'static str;
+}
@@ -0,0 +1,198 @@
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::RwLock;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures_core::Stream;
+use lazy_static::lazy_static;
+use protobuf::ProtobufError;
+use tokio::sync::mpsc;
+
+use crate::entities::SubscribeObject;
+
+lazy_static! {
+  static ref OBSERVERS: RwLock<Vec<Observer>> = RwLock::new(vec![]);
+}
+
+/// The payload of a notification, tied to the source and the type of the notification it's
+/// sent with, so the Rust modules can observe it without parsing the raw bytes.
+pub trait TypedNotification: TryFrom<Bytes, Error = ProtobufError> + Send + 'static {
+  const SOURCE: &'static str;
+  const TY: i32;
+}
+
+struct Observer {
+  source: &'static str,
+  ty: i32,
+  /// `None` to observe the notifications sent for any id.
+  id: Option<String>,
+  sender: mpsc::UnboundedSender<SubscribeObject>,
+}
+
+/// Returns the stream of the `T` notifications sent for `id`. The notifications go through the
+/// same pipeline as the ones delivered to the client, they are received once the debouncing or
+/// coalescing is done. The observers are not affected by the client subscriptions.
+///
+/// The observer is removed once the stream is dropped.
+pub fn observe<T: TypedNotification>(id: &str) -> NotificationStream<T> {
+  add_observer(Some(id.to_owned()))
+}
+
+/// Like [observe], for the `T` notifications sent for any id, e.g. the changes of all the
+/// documents.
+pub fn observe_all<T: TypedNotification>() -> NotificationStream<T> {
+  add_observer(None)
+}
+
+fn add_observer<T: TypedNotification>(id: Option<String>) -> NotificationStream<T> {
+  let (sender, receiver) = mpsc::unbounded_channel();
+  let observer = Observer {
+    source: T::SOURCE,
+    ty: T::TY,
+    id,
+    sender,
+  };
+  match OBSERVERS.write() {
+    Ok(mut observers) => observers.push(observer),
+    Err(err) => tracing::error!("Failed to add the notification observer: {:?}", err),
+  }
+  NotificationStream {
+    receiver,
+    phantom: PhantomData,
+  }
+}
+
+/// Forwards `subject` to its observers and forgets the observers whose stream was dropped.
+pub(crate) fn notify_observers(subject: &SubscribeObject) {
+  let mut has_closed = false;
+  match OBSERVERS.read() {
+    Ok(observers) => {
+      for observer in observers.iter() {
+        if observer.sender.is_closed() {
+          has_closed = true;
+        } else if observer.ty == subject.ty
+          && observer.id.as_ref().map_or(true, |id| *id == subject.id)
+          && observer.source == subject.source
+        {
+          let _ = observer.sender.send(subject.clone());
+        }
+      }
+    },
+    Err(err) => tracing::error!("Read notification observers failed: {}", err),
+  }
+
+  if has_closed {
+    if let Ok(mut observers) = OBSERVERS.write() {
+      observers.retain(|observer| !observer.sender.is_closed());
+    }
+  }
+}
+
+/// The notifications returned by [observe], deserialized as `T`. The notifications without a
+/// payload or whose payload is not a `T` are skipped.
+pub struct NotificationStream<T> {
+  receiver: mpsc::UnboundedReceiver<SubscribeObject>,
+  phantom: PhantomData<T>,
+}
+
+impl<T: TypedNotification> NotificationStream<T> {
+  pub async fn recv(&mut self) -> Option<T> {
+    std::future::poll_fn(|cx| self.poll_next_notification(cx)).await
+  }
+
+  fn poll_next_notification(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+    loop {
+      let subject = match self.receiver.poll_recv(cx) {
+        Poll::Ready(Some(subject)) => subject,
+        Poll::Ready(None) => return Poll::Ready(None),
+        Poll::Pending => return Poll::Pending,
+      };
+      let payload = match subject.payload {
+        Some(payload) => payload,
+        None => continue,
+      };
+      match T::try_from(Bytes::from(payload)) {
+        Ok(notification) => return Poll::Ready(Some(notification)),
+        Err(err) => tracing::error!(
+          "Failed to parse the {} notification of {}: {:?}",
+          std::any::type_name::<T>(),
+          subject.id,
+          err
+        ),
+      }
+    }
+  }
+}
+
+impl<T: TypedNotification> Stream for NotificationStream<T> {
+  type Item = T;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+    self.get_mut().poll_next_notification(cx)
+  }
+}
+
+impl<T> Unpin for NotificationStream<T> {}
+
+#[cfg(test)]
+mod tests {
+  use std::convert::TryInto;
+
+  use bytes::Bytes;
+
+  use crate::entities::SubscribeObject;
+  use crate::{observe, observe_all, send_subject, TypedNotification};
+
+  /// A notification whose payload is itself a [SubscribeObject], to avoid declaring a protobuf
+  /// message for the test.
+  impl TypedNotification for SubscribeObject {
+    const SOURCE: &'static str = "Test";
+    const TY: i32 = 7;
+  }
+
+  fn send(id: &str, ty: i32, inner_id: &str) {
+    let inner = SubscribeObject {
+      id: inner_id.to_string(),
+      ..Default::default()
+    };
+    let payload: Bytes = inner.try_into().unwrap();
+    send_subject(SubscribeObject {
+      source: "Test".to_string(),
+      ty,
+      id: id.to_string(),
+      payload: Some(payload.to_vec()),
+      ..Default::default()
+    });
+  }
+
+  #[tokio::test]
+  async fn observe_typed_notification_test() {
+    let mut stream = observe::<SubscribeObject>("observed");
+    send("other", 7, "skipped");
+    send("observed", 1, "skipped");
+    send("observed", 7, "first");
+
+    let notification = stream.recv().await.unwrap();
+    assert_eq!(notification.id, "first");
+  }
+
+  #[tokio::test]
+  async fn observe_all_typed_notification_test() {
+    let mut stream = observe_all::<SubscribeObject>();
+    send("any_1", 1, "all_skipped");
+    send("any_1", 7, "all_first");
+    send("any_2", 7, "all_second");
+
+    // The notifications of the other tests are received too
+    let mut ids = vec![];
+    while ids.len() < 2 {
+      let notification = stream.recv().await.unwrap();
+      if notification.id.starts_with("all_") {
+        ids.push(notification.id);
+      }
+    }
+    assert_eq!(ids, vec!["all_first", "all_second"]);
+  }
+}
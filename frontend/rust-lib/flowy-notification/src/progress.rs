@@ -0,0 +1,133 @@
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use lib_dispatch::prelude::{AFPluginEventRequest, DispatchError, FromAFPluginRequest, Payload};
+
+use crate::entities::{ProgressNotification, ProgressPB};
+use crate::{CoalesceStrategy, NotificationBuilder, NotificationCoalescer};
+
+/// The progress updates of a request sent within this window are delivered as one.
+const PROGRESS_WINDOW: Duration = Duration::from_millis(100);
+
+fn progress_coalescer() -> &'static NotificationCoalescer {
+  static COALESCER: OnceLock<NotificationCoalescer> = OnceLock::new();
+  COALESCER
+    .get_or_init(|| NotificationCoalescer::new(PROGRESS_WINDOW, CoalesceStrategy::KeepLatest))
+}
+
+/// Sends the [ProgressPB] of the request being handled, as a
+/// [ProgressNotification::DidUpdateProgress] notification whose id is the request id. Add it to
+/// the parameters of a handler to get the reporter of its request:
+///
+/// ```ignore
+/// pub(crate) async fn import_handler(
+///   data: AFPluginData<ImportPB>,
+///   progress: ProgressReporter,
+/// ) -> Result<(), FlowyError> {
+///   for (i, file) in files.iter().enumerate() {
+///     progress.report(i as u64, files.len() as u64);
+///     ..
+///   }
+///   progress.finish();
+/// }
+/// ```
+///
+/// The updates are coalesced, only the last update of each window is delivered. The clones
+/// report the progress of the same request.
+#[derive(Clone)]
+pub struct ProgressReporter {
+  request_id: String,
+  last: Arc<Mutex<ProgressPB>>,
+}
+
+impl ProgressReporter {
+  pub fn new(request_id: &str) -> Self {
+    Self {
+      request_id: request_id.to_owned(),
+      last: Arc::new(Mutex::new(ProgressPB {
+        request_id: request_id.to_owned(),
+        ..Default::default()
+      })),
+    }
+  }
+
+  pub fn request_id(&self) -> &str {
+    &self.request_id
+  }
+
+  pub fn report(&self, completed: u64, total: u64) {
+    self.update(|progress| {
+      progress.completed = completed;
+      progress.total = total;
+    });
+  }
+
+  pub fn report_with_message(&self, completed: u64, total: u64, message: &str) {
+    self.update(|progress| {
+      progress.completed = completed;
+      progress.total = total;
+      progress.message = message.to_owned();
+    });
+  }
+
+  /// Reports the operation as done. The updates reported afterwards are ignored.
+  pub fn finish(&self) {
+    self.update(|progress| {
+      progress.completed = progress.total.max(progress.completed);
+      progress.is_finish = true;
+    });
+  }
+
+  /// The last reported progress.
+  pub fn progress(&self) -> ProgressPB {
+    self.last.lock().unwrap().clone()
+  }
+
+  fn update<F: FnOnce(&mut ProgressPB)>(&self, f: F) {
+    let progress = {
+      let mut last = self.last.lock().unwrap();
+      if last.is_finish {
+        return;
+      }
+      f(&mut last);
+      last.clone()
+    };
+    let subject =
+      NotificationBuilder::typed(&self.request_id, ProgressNotification::DidUpdateProgress)
+        .payload(progress)
+        .build();
+    progress_coalescer().send_subject(subject);
+  }
+}
+
+impl FromAFPluginRequest for ProgressReporter {
+  type Error = DispatchError;
+  type Future = std::future::Ready<Result<Self, DispatchError>>;
+
+  fn from_request(req: &AFPluginEventRequest, _: &mut Payload) -> Self::Future {
+    std::future::ready(Ok(ProgressReporter::new(req.id())))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::ProgressReporter;
+
+  #[test]
+  fn progress_reporter_test() {
+    let reporter = ProgressReporter::new("request_1");
+    reporter.report(1, 4);
+    assert_eq!(reporter.progress().percent(), Some(25));
+
+    reporter.report_with_message(3, 4, "importing");
+    reporter.finish();
+    let progress = reporter.progress();
+    assert_eq!(progress.percent(), Some(100));
+    assert_eq!(progress.message, "importing");
+    assert!(progress.is_finish);
+
+    // The updates after the end are ignored.
+    reporter.report(1, 4);
+    assert_eq!(reporter.progress(), progress);
+  }
+}
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+use serde_json::Value;
+
+use crate::entities::SubscribeObject;
+use crate::filter::payload_view;
+use crate::NotificationFilter;
+
+lazy_static! {
+  static ref SUBSCRIPTIONS: SubscriptionTable = SubscriptionTable::default();
+}
+
+/// The subscription table of the process, consulted before delivering a notification.
+pub fn subscriptions() -> &'static SubscriptionTable {
+  &SUBSCRIPTIONS
+}
+
+/// The topics the client subscribed to with the `SubscribeNotification` event.
+///
+/// The clients that never subscribe keep receiving all the notifications. Once a client
+/// subscribed to a topic, only the notifications of the subscribed topics are delivered.
+/// Several observers of the same topic can subscribe, the topic is removed once all of them
+/// unsubscribed. An observer can narrow its subscription with a [NotificationFilter], the
+/// notifications of the topic are then delivered if at least one of its observers accepts them.
+#[derive(Default)]
+pub struct SubscriptionTable {
+  enabled: AtomicBool,
+  /// The filter of each observer of a topic, `None` for the observers that receive all the
+  /// notifications of the topic.
+  topics: RwLock<HashMap<String, Vec<Option<NotificationFilter>>>>,
+}
+
+impl SubscriptionTable {
+  pub fn subscribe(&self, topic: &str) {
+    self.subscribe_with_filter(topic, None);
+  }
+
+  pub fn subscribe_with_filter(&self, topic: &str, filter: Option<NotificationFilter>) {
+    self.enabled.store(true, Ordering::Release);
+    let mut topics = self.topics.write().unwrap();
+    topics.entry(topic.to_owned()).or_default().push(filter);
+  }
+
+  /// Returns whether the topic had been subscribed to.
+  pub fn unsubscribe(&self, topic: &str) -> bool {
+    self.unsubscribe_with_filter(topic, None)
+  }
+
+  /// Removes the observer of `topic` that subscribed with `filter`. Returns whether there was
+  /// one.
+  pub fn unsubscribe_with_filter(&self, topic: &str, filter: Option<&str>) -> bool {
+    let mut topics = self.topics.write().unwrap();
+    let observers = match topics.get_mut(topic) {
+      Some(observers) => observers,
+      None => return false,
+    };
+    let position = observers
+      .iter()
+      .position(|observer| observer.as_ref().map(|filter| filter.as_str()) == filter);
+    match position {
+      Some(position) => {
+        observers.remove(position);
+        if observers.is_empty() {
+          topics.remove(topic);
+        }
+        true
+      },
+      None => false,
+    }
+  }
+
+  pub fn is_subscribed(&self, topic: &str) -> bool {
+    self.topics.read().unwrap().contains_key(topic)
+  }
+
+  /// Whether the notifications of `topic` may be delivered to the client, regardless of the
+  /// filters of its observers.
+  pub fn should_deliver(&self, topic: &str) -> bool {
+    !self.enabled.load(Ordering::Acquire) || self.is_subscribed(topic)
+  }
+
+  /// Whether `subject` is delivered to the client: its topic is subscribed to and one of the
+  /// observers of the topic accepts its payload. The payloads without a view registered with
+  /// [register_payload_view](crate::register_payload_view) can't be filtered, they are
+  /// delivered.
+  pub fn should_deliver_subject(&self, subject: &SubscribeObject) -> bool {
+    if !self.enabled.load(Ordering::Acquire) {
+      return true;
+    }
+
+    let topics = self.topics.read().unwrap();
+    let observers = match topics.get(&subject.id) {
+      Some(observers) => observers,
+      None => return false,
+    };
+    if observers.iter().any(Option::is_none) {
+      return true;
+    }
+
+    let view = match payload_view(&subject.source, subject.ty) {
+      Some(view) => view,
+      None => return true,
+    };
+    let value = match &subject.payload {
+      Some(payload) => match view(payload) {
+        Some(value) => value,
+        None => return true,
+      },
+      None => Value::Null,
+    };
+    observers
+      .iter()
+      .flatten()
+      .any(|filter| filter.matches(&value))
+  }
+
+  /// Forgets all the subscriptions, e.g. when the client restarts, so it receives all the
+  /// notifications until it subscribes again.
+  pub fn reset(&self) {
+    self.topics.write().unwrap().clear();
+    self.enabled.store(false, Ordering::Release);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use crate::entities::SubscribeObject;
+  use crate::{register_payload_view, NotificationFilter, SubscriptionTable};
+
+  #[test]
+  fn subscription_table_test() {
+    let table = SubscriptionTable::default();
+    assert!(table.should_deliver("doc_1"));
+
+    table.subscribe("doc_1");
+    table.subscribe("doc_1");
+    assert!(table.should_deliver("doc_1"));
+    assert!(!table.should_deliver("doc_2"));
+
+    assert!(table.unsubscribe("doc_1"));
+    assert!(table.should_deliver("doc_1"));
+    assert!(table.unsubscribe("doc_1"));
+    assert!(!table.should_deliver("doc_1"));
+    assert!(!table.unsubscribe("doc_1"));
+
+    table.reset();
+    assert!(table.should_deliver("doc_2"));
+  }
+
+  #[test]
+  fn subscription_filter_test() {
+    register_payload_view("SubscriptionFilterTest", 1, |payload| {
+      Some(json!({ "row_id": String::from_utf8_lossy(payload) }))
+    });
+    let subject = |row_id: &str| SubscribeObject {
+      source: "SubscriptionFilterTest".to_owned(),
+      ty: 1,
+      id: "grid_1".to_owned(),
+      payload: Some(row_id.as_bytes().to_vec()),
+      ..Default::default()
+    };
+
+    let table = SubscriptionTable::default();
+    let filter = NotificationFilter::parse(r#"row_id in ["r1", "r2"]"#).unwrap();
+    table.subscribe_with_filter("grid_1", Some(filter.clone()));
+    assert!(table.should_deliver_subject(&subject("r1")));
+    assert!(!table.should_deliver_subject(&subject("r3")));
+
+    // An observer without a filter receives all the notifications of the topic.
+    table.subscribe("grid_1");
+    assert!(table.should_deliver_subject(&subject("r3")));
+    assert!(table.unsubscribe("grid_1"));
+    assert!(!table.should_deliver_subject(&subject("r3")));
+
+    // Without a payload view the notification can't be filtered.
+    let mut unknown = subject("r3");
+    unknown.ty = 2;
+    assert!(table.should_deliver_subject(&unknown));
+
+    assert!(!table.unsubscribe_with_filter("grid_1", Some("row_id == \"r1\"")));
+    assert!(table.unsubscribe_with_filter("grid_1", Some(filter.as_str())));
+    assert!(!table.is_subscribed("grid_1"));
+  }
+}
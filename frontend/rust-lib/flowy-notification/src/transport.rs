@@ -0,0 +1,104 @@
+use std::convert::TryInto;
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use tokio::sync::broadcast;
+
+use crate::entities::SubscribeObject;
+use crate::NotificationSender;
+
+/// Forwards the notifications, encoded with protobuf, to the clients connected to the websocket
+/// bridge of the dispatcher, see `WebSocketBridge::with_notifications` in lib-dispatch.
+#[derive(Clone)]
+pub struct BroadcastNotificationSender {
+  sender: broadcast::Sender<Bytes>,
+}
+
+impl BroadcastNotificationSender {
+  pub fn new(sender: broadcast::Sender<Bytes>) -> Self {
+    Self { sender }
+  }
+}
+
+impl NotificationSender for BroadcastNotificationSender {
+  fn send_subject(&self, subject: SubscribeObject) -> Result<(), String> {
+    let bytes: Bytes = subject.try_into().map_err(|err| format!("{:?}", err))?;
+    // No client connected is not an error, the notification is just dropped.
+    let _ = self.sender.send(bytes);
+    Ok(())
+  }
+}
+
+/// Keeps the notifications in memory, for the tests of the modules that don't run the whole
+/// core. The clones share the same notifications.
+#[derive(Clone, Default)]
+pub struct NotificationCollector {
+  notifications: Arc<Mutex<Vec<SubscribeObject>>>,
+}
+
+impl NotificationCollector {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn notifications(&self) -> Vec<SubscribeObject> {
+    self.notifications.lock().unwrap().clone()
+  }
+
+  /// The notifications of type `ty` sent for `id`.
+  pub fn find(&self, id: &str, ty: impl Into<i32>) -> Vec<SubscribeObject> {
+    let ty = ty.into();
+    self
+      .notifications
+      .lock()
+      .unwrap()
+      .iter()
+      .filter(|subject| subject.id == id && subject.ty == ty)
+      .cloned()
+      .collect()
+  }
+
+  /// Returns the collected notifications and forgets them.
+  pub fn take(&self) -> Vec<SubscribeObject> {
+    std::mem::take(&mut *self.notifications.lock().unwrap())
+  }
+}
+
+impl NotificationSender for NotificationCollector {
+  fn send_subject(&self, subject: SubscribeObject) -> Result<(), String> {
+    self.notifications.lock().unwrap().push(subject);
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{NotificationBuilder, NotificationCollector, NotificationSender, NotificationType};
+
+  struct DocChanged;
+
+  impl From<DocChanged> for i32 {
+    fn from(_: DocChanged) -> Self {
+      1
+    }
+  }
+
+  impl NotificationType for DocChanged {
+    const SOURCE: &'static str = "Document";
+  }
+
+  #[test]
+  fn collect_typed_notification_test() {
+    let collector = NotificationCollector::new();
+    let subject = NotificationBuilder::typed("doc_1", DocChanged).build();
+    collector.send_subject(subject).unwrap();
+
+    let found = collector.find("doc_1", DocChanged);
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].source, "Document");
+    assert_eq!(found[0].ty, 1);
+    assert!(collector.find("doc_2", DocChanged).is_empty());
+    assert_eq!(collector.take().len(), 1);
+    assert!(collector.notifications().is_empty());
+  }
+}
@@ -9,6 +9,7 @@ fn main() {
   {
     flowy_codegen::protobuf_file::dart_gen(crate_name);
     flowy_codegen::dart_event::gen(crate_name);
+    flowy_codegen::native_event::gen(crate_name);
   }
 
   #[cfg(feature = "tauri_ts")]
@@ -0,0 +1,59 @@
+use tantivy::schema::{Field, Value};
+use tantivy::TantivyDocument;
+
+use crate::document::schema::DocumentSchema;
+use crate::entities::{IndexTypePB, SearchResultPB};
+
+/// The content of a document as it's indexed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentIndexData {
+  pub id: String,
+  pub title: String,
+  pub content: String,
+  pub workspace_id: String,
+}
+
+impl DocumentIndexData {
+  pub(crate) fn from_doc(doc: &TantivyDocument, schema: &DocumentSchema) -> Self {
+    let text = |field: Field| {
+      doc
+        .get_first(field)
+        .and_then(|value| value.as_str())
+        .unwrap_or_default()
+        .to_string()
+    };
+    Self {
+      id: text(schema.id),
+      title: text(schema.title),
+      content: text(schema.content),
+      workspace_id: text(schema.workspace_id),
+    }
+  }
+
+  /// The result of a search, `preview` being the excerpt of the content matching the query.
+  pub(crate) fn into_result(self, score: f32, preview: String) -> SearchResultPB {
+    SearchResultPB {
+      index_type: IndexTypePB::Document,
+      view_id: self.id.clone(),
+      id: self.id,
+      data: self.title,
+      icon: None,
+      score: score as f64,
+      workspace_id: self.workspace_id,
+      preview: if preview.is_empty() {
+        None
+      } else {
+        Some(preview)
+      },
+    }
+  }
+}
+
+/// A page of the results of a search, ranked by relevance.
+#[derive(Debug, Clone)]
+pub struct DocumentSearchPage {
+  pub items: Vec<SearchResultPB>,
+  /// The number of documents matching the query, across all the pages.
+  pub total: u64,
+  pub offset: u64,
+}
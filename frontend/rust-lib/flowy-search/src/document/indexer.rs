@@ -0,0 +1,225 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::time::Duration;
+
+use flowy_document::entities::DocumentChangedPB;
+use flowy_error::{ErrorCode, FlowyError, FlowyResult};
+use flowy_notification::observe_all;
+use flowy_user::services::authenticate_user::AuthenticateUser;
+use futures::FutureExt;
+use lib_dispatch::prelude::af_spawn;
+use lib_infra::async_trait::async_trait;
+use tantivy::collector::{Count, TopDocs};
+use tantivy::directory::MmapDirectory;
+use tantivy::query::{BooleanQuery, Occur, Query, QueryParser, TermQuery};
+use tantivy::schema::IndexRecordOption;
+use tantivy::snippet::SnippetGenerator;
+use tantivy::{doc, Index, IndexReader, IndexWriter, TantivyDocument, Term};
+use tracing::{error, trace, warn};
+
+use super::entities::{DocumentIndexData, DocumentSearchPage};
+use super::schema::DocumentSchema;
+
+const DOCUMENT_INDEX_DIR: &str = "document_index";
+
+/// The number of results of a page when the query has no limit.
+pub const DEFAULT_DOCUMENT_SEARCH_LIMIT: usize = 20;
+
+/// The maximum number of characters of the preview of a result.
+const PREVIEW_MAX_CHARS: usize = 150;
+
+/// The changes of a document are indexed once it wasn't edited for this long, so a document
+/// being typed in is not indexed on every keystroke.
+const INDEX_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Gives the content to index for a document. Implemented by the app, which owns both the
+/// documents and the views.
+#[async_trait]
+pub trait DocumentContentProvider: Send + Sync + 'static {
+  /// `None` if the document should not be searchable anymore, e.g. it was moved to the trash.
+  async fn document_content(&self, document_id: &str) -> FlowyResult<Option<DocumentIndexData>>;
+}
+
+/// The full-text index of the documents of the user, kept up to date with the
+/// `DidChangeDocument` notifications of the documents.
+#[derive(Clone)]
+pub struct DocumentIndexManagerImpl {
+  schema: DocumentSchema,
+  index: Index,
+  index_reader: IndexReader,
+  index_writer: Arc<Mutex<IndexWriter>>,
+}
+
+impl DocumentIndexManagerImpl {
+  /// Opens the index stored in the index directory of the user.
+  pub fn new(auth_user: Weak<AuthenticateUser>) -> FlowyResult<Self> {
+    let auth_user = auth_user
+      .upgrade()
+      .ok_or_else(FlowyError::document_index_manager_unavailable)?;
+    let index_path = auth_user.get_index_path().join(DOCUMENT_INDEX_DIR);
+    Self::open(&index_path)
+  }
+
+  /// Opens the index stored in `index_path`, created if needed.
+  pub fn open(index_path: &Path) -> FlowyResult<Self> {
+    if !index_path.exists() {
+      fs::create_dir_all(index_path)
+        .map_err(|err| FlowyError::new(ErrorCode::FailedToOpenIndexDir, err.to_string()))?;
+    }
+
+    let schema = DocumentSchema::new();
+    let dir = MmapDirectory::open(index_path)
+      .map_err(|err| FlowyError::new(ErrorCode::FailedToOpenIndexDir, err.to_string()))?;
+    let index = Index::open_or_create(dir, schema.schema.clone())?;
+    let index_reader = index.reader()?;
+    let index_writer = index.writer_with_num_threads(1, 15_000_000)?;
+
+    Ok(Self {
+      schema,
+      index,
+      index_reader,
+      index_writer: Arc::new(Mutex::new(index_writer)),
+    })
+  }
+
+  /// Indexes the documents, replacing their previous content.
+  pub fn index_documents(&self, documents: Vec<DocumentIndexData>) -> FlowyResult<()> {
+    let ids = documents.iter().map(|data| data.id.clone()).collect();
+    self.write(ids, documents)
+  }
+
+  pub fn remove_documents(&self, ids: Vec<String>) -> FlowyResult<()> {
+    self.write(ids, vec![])
+  }
+
+  /// Removes the documents with the `ids`, then adds the `documents`, in a single commit.
+  fn write(&self, ids: Vec<String>, documents: Vec<DocumentIndexData>) -> FlowyResult<()> {
+    if ids.is_empty() && documents.is_empty() {
+      return Ok(());
+    }
+
+    let mut index_writer = self.get_index_writer()?;
+    for id in ids {
+      index_writer.delete_term(Term::from_field_text(self.schema.id, &id));
+    }
+    for data in documents {
+      index_writer.add_document(doc![
+        self.schema.id => data.id,
+        self.schema.title => data.title,
+        self.schema.content => data.content,
+        self.schema.workspace_id => data.workspace_id,
+      ])?;
+    }
+    index_writer.commit()?;
+
+    // Makes the changes visible to the next search
+    self.index_reader.reload()?;
+    Ok(())
+  }
+
+  /// Searches the titles and the contents of the documents, the most relevant first. Only the
+  /// documents of `workspace_id` are returned if set.
+  pub fn search(
+    &self,
+    query: &str,
+    workspace_id: Option<&str>,
+    offset: usize,
+    limit: usize,
+  ) -> FlowyResult<DocumentSearchPage> {
+    let mut query_parser =
+      QueryParser::for_index(&self.index, vec![self.schema.title, self.schema.content]);
+    // A match in the title ranks higher than the same match in the content
+    query_parser.set_field_boost(self.schema.title, 2.0);
+    // The user input is not expected to follow the query syntax
+    let (text_query, _) = query_parser.parse_query_lenient(query);
+    let query: Box<dyn Query> = match workspace_id {
+      Some(workspace_id) => {
+        let workspace_query = TermQuery::new(
+          Term::from_field_text(self.schema.workspace_id, workspace_id),
+          IndexRecordOption::Basic,
+        );
+        Box::new(BooleanQuery::new(vec![
+          (Occur::Must, text_query),
+          (Occur::Must, Box::new(workspace_query)),
+        ]))
+      },
+      None => text_query,
+    };
+
+    let searcher = self.index_reader.searcher();
+    let collector = (TopDocs::with_limit(limit.max(1)).and_offset(offset), Count);
+    let (top_docs, total) = searcher.search(&*query, &collector)?;
+    let mut snippet_generator = SnippetGenerator::create(&searcher, &*query, self.schema.content)?;
+    snippet_generator.set_max_num_chars(PREVIEW_MAX_CHARS);
+
+    let mut items = Vec::with_capacity(top_docs.len());
+    for (score, doc_address) in top_docs {
+      let doc: TantivyDocument = searcher.doc(doc_address)?;
+      let preview = snippet_generator
+        .snippet_from_doc(&doc)
+        .fragment()
+        .to_string();
+      items.push(DocumentIndexData::from_doc(&doc, &self.schema).into_result(score, preview));
+    }
+
+    Ok(DocumentSearchPage {
+      items,
+      total: total as u64,
+      offset: offset as u64,
+    })
+  }
+
+  pub fn num_docs(&self) -> u64 {
+    self.index_reader.searcher().num_docs()
+  }
+
+  /// Re-indexes the documents once they change. The changes received while waiting for
+  /// [INDEX_DEBOUNCE] are indexed together.
+  pub fn watch_document_changes(&self, provider: Arc<dyn DocumentContentProvider>) {
+    let indexer = self.clone();
+    let mut changes = observe_all::<DocumentChangedPB>();
+    af_spawn(async move {
+      while let Some(change) = changes.recv().await {
+        let mut document_ids = HashSet::from([change.document_id]);
+        tokio::time::sleep(INDEX_DEBOUNCE).await;
+        while let Some(Some(change)) = changes.recv().now_or_never() {
+          document_ids.insert(change.document_id);
+        }
+        indexer.reindex(provider.as_ref(), document_ids).await;
+      }
+    });
+  }
+
+  async fn reindex(&self, provider: &dyn DocumentContentProvider, document_ids: HashSet<String>) {
+    let mut documents = vec![];
+    let mut ids = vec![];
+    for document_id in document_ids {
+      match provider.document_content(&document_id).await {
+        Ok(content) => {
+          documents.extend(content);
+          ids.push(document_id);
+        },
+        Err(err) => warn!(
+          "[Search]: failed to read the document {}: {}",
+          document_id, err
+        ),
+      }
+    }
+    trace!("[Search]: indexing {} documents", documents.len());
+    if let Err(err) = self.write(ids, documents) {
+      error!("[Search]: failed to index the documents: {}", err);
+    }
+  }
+
+  fn get_index_writer(&self) -> FlowyResult<MutexGuard<IndexWriter>> {
+    self.index_writer.lock().map_err(|err| {
+      error!(
+        "DocumentIndexManager failed to lock index writer: {:?}",
+        err
+      );
+      FlowyError::document_index_manager_unavailable()
+    })
+  }
+}
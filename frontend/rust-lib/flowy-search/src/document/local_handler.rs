@@ -0,0 +1,49 @@
+use std::sync::Arc;
+
+use flowy_error::FlowyResult;
+use lib_infra::async_trait::async_trait;
+
+use super::indexer::DocumentIndexManagerImpl;
+use crate::entities::{SearchFilterPB, SearchResultPB};
+use crate::services::manager::{SearchHandler, SearchType};
+
+/// The number of documents returned to the [SearchManager](crate::services::manager::SearchManager)
+/// queries, the client pages through the others with the `SearchDocuments` event.
+const LOCAL_DOCUMENT_SEARCH_LIMIT: usize = 10;
+
+/// Searches the contents of the documents in the local index.
+pub struct LocalDocumentSearchHandler {
+  pub index_manager: Arc<DocumentIndexManagerImpl>,
+}
+
+impl LocalDocumentSearchHandler {
+  pub fn new(index_manager: Arc<DocumentIndexManagerImpl>) -> Self {
+    Self { index_manager }
+  }
+}
+
+#[async_trait]
+impl SearchHandler for LocalDocumentSearchHandler {
+  fn search_type(&self) -> SearchType {
+    SearchType::LocalDocument
+  }
+
+  async fn perform_search(
+    &self,
+    query: String,
+    filter: Option<SearchFilterPB>,
+  ) -> FlowyResult<Vec<SearchResultPB>> {
+    let workspace_id = filter.and_then(|filter| filter.workspace_id);
+    let page = self.index_manager.search(
+      &query,
+      workspace_id.as_deref(),
+      0,
+      LOCAL_DOCUMENT_SEARCH_LIMIT,
+    )?;
+    Ok(page.items)
+  }
+
+  fn index_count(&self) -> u64 {
+    self.index_manager.num_docs()
+  }
+}
@@ -1 +1,5 @@
+pub mod entities;
 pub mod handler;
+pub mod indexer;
+pub mod local_handler;
+pub mod schema;
@@ -0,0 +1,45 @@
+use tantivy::schema::{Field, Schema, STORED, STRING, TEXT};
+
+pub const DOCUMENT_ID_FIELD_NAME: &str = "id";
+pub const DOCUMENT_TITLE_FIELD_NAME: &str = "title";
+pub const DOCUMENT_CONTENT_FIELD_NAME: &str = "content";
+pub const DOCUMENT_WORKSPACE_ID_FIELD_NAME: &str = "workspace_id";
+
+/// The schema of the index of the document contents.
+///
+/// Do not change the schema after the index has been created, see
+/// [FolderSchema](crate::folder::schema::FolderSchema).
+#[derive(Clone)]
+pub struct DocumentSchema {
+  pub schema: Schema,
+  pub id: Field,
+  pub title: Field,
+  pub content: Field,
+  pub workspace_id: Field,
+}
+
+impl DocumentSchema {
+  pub fn new() -> Self {
+    let mut schema_builder = Schema::builder();
+    let id = schema_builder.add_text_field(DOCUMENT_ID_FIELD_NAME, STRING | STORED);
+    let title = schema_builder.add_text_field(DOCUMENT_TITLE_FIELD_NAME, TEXT | STORED);
+    // The content is stored to generate the previews of the results
+    let content = schema_builder.add_text_field(DOCUMENT_CONTENT_FIELD_NAME, TEXT | STORED);
+    let workspace_id =
+      schema_builder.add_text_field(DOCUMENT_WORKSPACE_ID_FIELD_NAME, STRING | STORED);
+
+    Self {
+      schema: schema_builder.build(),
+      id,
+      title,
+      content,
+      workspace_id,
+    }
+  }
+}
+
+impl Default for DocumentSchema {
+  fn default() -> Self {
+    Self::new()
+  }
+}
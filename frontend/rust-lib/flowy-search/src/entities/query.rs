@@ -23,3 +23,22 @@ pub struct SearchQueryPB {
   #[pb(index = 4, one_of)]
   pub channel: Option<String>,
 }
+
+/// A query of the contents of the documents, answered with a page of the results instead of
+/// the `DidUpdateResults` notifications.
+#[derive(Eq, PartialEq, ProtoBuf, Default, Debug, Clone)]
+pub struct DocumentSearchQueryPB {
+  #[pb(index = 1)]
+  pub search: String,
+
+  #[pb(index = 2, one_of)]
+  pub workspace_id: Option<String>,
+
+  /// The number of results to skip, the results of the previous pages.
+  #[pb(index = 3)]
+  pub offset: u64,
+
+  /// The number of results of the page, `DEFAULT_DOCUMENT_SEARCH_LIMIT` if 0.
+  #[pb(index = 4)]
+  pub limit: u64,
+}
@@ -3,6 +3,7 @@ use flowy_derive::{ProtoBuf, ProtoBuf_Enum};
 use flowy_folder::entities::ViewIconPB;
 
 use super::IndexTypePB;
+use crate::document::entities::DocumentSearchPage;
 
 #[derive(Debug, Default, ProtoBuf, Clone)]
 pub struct RepeatedSearchResultPB {
@@ -10,6 +11,31 @@ pub struct RepeatedSearchResultPB {
   pub items: Vec<SearchResultPB>,
 }
 
+/// A page of the results of a [DocumentSearchQueryPB](super::DocumentSearchQueryPB), the most
+/// relevant first.
+#[derive(Debug, Default, ProtoBuf, Clone)]
+pub struct DocumentSearchPagePB {
+  #[pb(index = 1)]
+  pub items: Vec<SearchResultPB>,
+
+  /// The number of results across all the pages.
+  #[pb(index = 2)]
+  pub total: u64,
+
+  #[pb(index = 3)]
+  pub offset: u64,
+}
+
+impl From<DocumentSearchPage> for DocumentSearchPagePB {
+  fn from(page: DocumentSearchPage) -> Self {
+    Self {
+      items: page.items,
+      total: page.total,
+      offset: page.offset,
+    }
+  }
+}
+
 #[derive(ProtoBuf, Default, Debug, Clone)]
 pub struct SearchResultPB {
   #[pb(index = 1)]
@@ -1,9 +1,12 @@
 use std::sync::{Arc, Weak};
 
 use flowy_error::{FlowyError, FlowyResult};
-use lib_dispatch::prelude::{AFPluginData, AFPluginState};
+use lib_dispatch::prelude::{data_result_ok, AFPluginData, AFPluginState, DataResult};
 
-use crate::{entities::SearchQueryPB, services::manager::SearchManager};
+use crate::{
+  entities::{DocumentSearchPagePB, DocumentSearchQueryPB, SearchQueryPB},
+  services::manager::SearchManager,
+};
 
 fn upgrade_manager(
   search_manager: AFPluginState<Weak<SearchManager>>,
@@ -25,3 +28,14 @@ pub(crate) async fn search_handler(
 
   Ok(())
 }
+
+#[tracing::instrument(level = "debug", skip(manager), err)]
+pub(crate) async fn search_documents_handler(
+  data: AFPluginData<DocumentSearchQueryPB>,
+  manager: AFPluginState<Weak<SearchManager>>,
+) -> DataResult<DocumentSearchPagePB, FlowyError> {
+  let query = data.into_inner();
+  let manager = upgrade_manager(manager)?;
+  let page = manager.search_documents(query)?;
+  data_result_ok(page)
+}
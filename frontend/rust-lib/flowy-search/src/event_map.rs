@@ -4,13 +4,18 @@ use strum_macros::Display;
 use flowy_derive::{Flowy_Event, ProtoBuf_Enum};
 use lib_dispatch::prelude::*;
 
-use crate::{event_handler::search_handler, services::manager::SearchManager};
+use crate::{
+  event_handler::{search_documents_handler, search_handler},
+  services::manager::SearchManager,
+};
 
 pub fn init(search_manager: Weak<SearchManager>) -> AFPlugin {
   AFPlugin::new()
     .state(search_manager)
     .name(env!("CARGO_PKG_NAME"))
+    .proto_descriptors(crate::protobuf::file_descriptors())
     .event(SearchEvent::Search, search_handler)
+    .event(SearchEvent::SearchDocuments, search_documents_handler)
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Display, Hash, ProtoBuf_Enum, Flowy_Event)]
@@ -18,4 +23,8 @@ pub fn init(search_manager: Weak<SearchManager>) -> AFPlugin {
 pub enum SearchEvent {
   #[event(input = "SearchQueryPB")]
   Search = 0,
+
+  /// Searches the contents of the documents in the local index, one page at a time.
+  #[event(input = "DocumentSearchQueryPB", output = "DocumentSearchPagePB")]
+  SearchDocuments = 1,
 }
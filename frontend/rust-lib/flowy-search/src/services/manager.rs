@@ -2,8 +2,13 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 use super::notifier::{SearchNotifier, SearchResultChanged, SearchResultReceiverRunner};
-use crate::entities::{SearchFilterPB, SearchResultNotificationPB, SearchResultPB};
-use flowy_error::FlowyResult;
+use crate::document::indexer::{DocumentIndexManagerImpl, DEFAULT_DOCUMENT_SEARCH_LIMIT};
+use crate::document::local_handler::LocalDocumentSearchHandler;
+use crate::entities::{
+  DocumentSearchPagePB, DocumentSearchQueryPB, SearchFilterPB, SearchResultNotificationPB,
+  SearchResultPB,
+};
+use flowy_error::{FlowyError, FlowyResult};
 use lib_dispatch::prelude::af_spawn;
 use lib_infra::async_trait::async_trait;
 use tokio::sync::broadcast;
@@ -12,6 +17,7 @@ use tokio::sync::broadcast;
 pub enum SearchType {
   Folder,
   Document,
+  LocalDocument,
 }
 
 #[async_trait]
@@ -37,6 +43,7 @@ pub trait SearchHandler: Send + Sync + 'static {
 pub struct SearchManager {
   pub handlers: HashMap<SearchType, Arc<dyn SearchHandler>>,
   notifier: SearchNotifier,
+  document_index: Option<Arc<DocumentIndexManagerImpl>>,
 }
 
 impl SearchManager {
@@ -50,7 +57,21 @@ impl SearchManager {
     let (notifier, _) = broadcast::channel(100);
     af_spawn(SearchResultReceiverRunner(Some(notifier.subscribe())).run());
 
-    Self { handlers, notifier }
+    Self {
+      handlers,
+      notifier,
+      document_index: None,
+    }
+  }
+
+  /// Searches the contents of the documents in `document_index` too.
+  pub fn with_document_index(mut self, document_index: Arc<DocumentIndexManagerImpl>) -> Self {
+    let handler = LocalDocumentSearchHandler::new(document_index.clone());
+    self
+      .handlers
+      .insert(handler.search_type(), Arc::new(handler));
+    self.document_index = Some(document_index);
+    self
   }
 
   pub fn get_handler(&self, search_type: SearchType) -> Option<&Arc<dyn SearchHandler>> {
@@ -87,4 +108,26 @@ impl SearchManager {
       });
     }
   }
+
+  /// Returns a page of the documents whose content matches the query.
+  pub fn search_documents(
+    &self,
+    query: DocumentSearchQueryPB,
+  ) -> FlowyResult<DocumentSearchPagePB> {
+    let document_index = self
+      .document_index
+      .as_ref()
+      .ok_or_else(FlowyError::document_index_manager_unavailable)?;
+    let limit = match query.limit {
+      0 => DEFAULT_DOCUMENT_SEARCH_LIMIT,
+      limit => limit as usize,
+    };
+    let page = document_index.search(
+      &query.search,
+      query.workspace_id.as_deref(),
+      query.offset as usize,
+      limit,
+    )?;
+    Ok(page.into())
+  }
 }
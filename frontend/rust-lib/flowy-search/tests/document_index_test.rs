@@ -0,0 +1,93 @@
+use flowy_search::document::entities::DocumentIndexData;
+use flowy_search::document::indexer::DocumentIndexManagerImpl;
+use tempfile::TempDir;
+
+fn document(id: &str, title: &str, content: &str, workspace_id: &str) -> DocumentIndexData {
+  DocumentIndexData {
+    id: id.to_string(),
+    title: title.to_string(),
+    content: content.to_string(),
+    workspace_id: workspace_id.to_string(),
+  }
+}
+
+fn ids(indexer: &DocumentIndexManagerImpl, query: &str, workspace_id: Option<&str>) -> Vec<String> {
+  indexer
+    .search(query, workspace_id, 0, 10)
+    .unwrap()
+    .items
+    .into_iter()
+    .map(|item| item.id)
+    .collect()
+}
+
+#[test]
+fn search_document_content_test() {
+  let tempdir = TempDir::new().unwrap();
+  let indexer = DocumentIndexManagerImpl::open(tempdir.path()).unwrap();
+  indexer
+    .index_documents(vec![
+      document("1", "Groceries", "apples, bread and milk", "w1"),
+      document("2", "Recipes", "bake the bread for an hour", "w1"),
+      document("3", "Bread", "notes about sourdough", "w2"),
+    ])
+    .unwrap();
+  assert_eq!(indexer.num_docs(), 3);
+
+  // The match in the title ranks first
+  let page = indexer.search("bread", None, 0, 10).unwrap();
+  assert_eq!(page.total, 3);
+  assert_eq!(page.items[0].id, "3");
+  assert_eq!(page.items[0].data, "Bread");
+  let preview = page.items[1].preview.clone().unwrap();
+  assert!(preview.contains("bread"));
+
+  assert_eq!(ids(&indexer, "bread", Some("w2")), vec!["3"]);
+  assert_eq!(ids(&indexer, "sourdough", Some("w1")), Vec::<String>::new());
+  assert_eq!(ids(&indexer, "", None), Vec::<String>::new());
+}
+
+#[test]
+fn search_document_pagination_test() {
+  let tempdir = TempDir::new().unwrap();
+  let indexer = DocumentIndexManagerImpl::open(tempdir.path()).unwrap();
+  let documents = (0..5)
+    .map(|i| document(&i.to_string(), "", "meeting notes", "w1"))
+    .collect();
+  indexer.index_documents(documents).unwrap();
+
+  let mut seen = vec![];
+  for offset in [0, 2, 4] {
+    let page = indexer.search("meeting", None, offset, 2).unwrap();
+    assert_eq!(page.total, 5);
+    assert_eq!(page.offset, offset as u64);
+    seen.extend(page.items.into_iter().map(|item| item.id));
+  }
+  seen.sort();
+  assert_eq!(seen, vec!["0", "1", "2", "3", "4"]);
+}
+
+#[test]
+fn update_document_index_test() {
+  let tempdir = TempDir::new().unwrap();
+  let indexer = DocumentIndexManagerImpl::open(tempdir.path()).unwrap();
+  indexer
+    .index_documents(vec![document("1", "Plan", "first draft", "w1")])
+    .unwrap();
+  indexer
+    .index_documents(vec![document("1", "Plan", "final version", "w1")])
+    .unwrap();
+  assert_eq!(ids(&indexer, "draft", None), Vec::<String>::new());
+  assert_eq!(ids(&indexer, "final", None), vec!["1"]);
+
+  indexer.remove_documents(vec!["1".to_string()]).unwrap();
+  assert_eq!(ids(&indexer, "final", None), Vec::<String>::new());
+
+  // The index is kept on disk
+  indexer
+    .index_documents(vec![document("2", "Trip", "pack the bags", "w1")])
+    .unwrap();
+  drop(indexer);
+  let indexer = DocumentIndexManagerImpl::open(tempdir.path()).unwrap();
+  assert_eq!(ids(&indexer, "bags", None), vec!["2"]);
+}
@@ -1,3 +1,4 @@
 // mod search;
 
+mod document_index_test;
 mod tantivy_test;
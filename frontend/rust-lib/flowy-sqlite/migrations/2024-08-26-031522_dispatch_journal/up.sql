@@ -0,0 +1,10 @@
+-- Your SQL goes here
+CREATE TABLE dispatch_journal_table (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    event TEXT NOT NULL,
+    payload BLOB NOT NULL,
+    version INTEGER,
+    status INTEGER NOT NULL DEFAULT 0,
+    attempts INTEGER NOT NULL DEFAULT 1,
+    created_at BIGINT NOT NULL
+);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE document_history_snapshot_table;
+DROP INDEX document_edit_document_id_idx;
+DROP TABLE document_edit_table;
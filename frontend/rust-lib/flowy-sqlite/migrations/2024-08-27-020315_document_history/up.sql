@@ -0,0 +1,18 @@
+-- Your SQL goes here
+CREATE TABLE document_edit_table (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    document_id TEXT NOT NULL,
+    kind INTEGER NOT NULL,
+    payload BLOB NOT NULL,
+    created_at BIGINT NOT NULL
+);
+CREATE INDEX document_edit_document_id_idx ON document_edit_table (document_id, seq);
+
+CREATE TABLE document_history_snapshot_table (
+    document_id TEXT NOT NULL,
+    seq BIGINT NOT NULL,
+    state_vector BLOB NOT NULL,
+    doc_state BLOB NOT NULL,
+    created_at BIGINT NOT NULL,
+    PRIMARY KEY (document_id, seq)
+);
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+CREATE TABLE user_workspace_order_table (
+    uid BIGINT NOT NULL,
+    workspace_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    PRIMARY KEY (uid, workspace_id)
+);
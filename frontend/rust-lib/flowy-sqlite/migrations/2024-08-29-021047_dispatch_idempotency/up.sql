@@ -0,0 +1,10 @@
+-- Your SQL goes here
+CREATE TABLE dispatch_idempotency_table (
+    idempotency_key TEXT NOT NULL,
+    event TEXT NOT NULL,
+    status_code INTEGER NOT NULL,
+    content_type TEXT,
+    payload BLOB NOT NULL,
+    created_at BIGINT NOT NULL,
+    PRIMARY KEY (idempotency_key, event)
+);
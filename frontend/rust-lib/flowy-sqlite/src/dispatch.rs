@@ -0,0 +1,189 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use diesel::r2d2::R2D2Connection;
+use lib_dispatch::metrics::MetricsRegistry;
+use lib_dispatch::prelude::*;
+
+use crate::{ConnectionPool, DBConnection};
+
+/// The number of connections opened by the pool.
+pub const SQLITE_POOL_CONNECTIONS: &str = "sqlite_pool_connections";
+/// The number of idle connections of the pool.
+pub const SQLITE_POOL_IDLE_CONNECTIONS: &str = "sqlite_pool_idle_connections";
+/// 1 if the last health check could ping the database, 0 otherwise.
+pub const SQLITE_POOL_HEALTHY: &str = "sqlite_pool_healthy";
+/// The number of times a handler could not get a connection.
+pub const SQLITE_POOL_CHECKOUT_ERRORS_TOTAL: &str = "sqlite_pool_checkout_errors_total";
+/// How long the handlers waited for a connection.
+pub const SQLITE_POOL_CHECKOUT_SECONDS: &str = "sqlite_pool_checkout_seconds";
+
+type PoolProvider = Arc<dyn Fn() -> Result<Arc<ConnectionPool>, String> + Send + Sync>;
+
+/// Hands out the pooled connections of the current database to the handlers. It's registered in
+/// the dispatcher as app data with [PoolManager::register], the handlers get a connection
+/// with the [PooledConn] extractor instead of opening the database themselves.
+///
+/// The pool is resolved by the provider on each checkout, so it follows the database of the
+/// signed in user.
+#[derive(Clone)]
+pub struct PoolManager {
+  provider: PoolProvider,
+  metrics: Arc<MetricsRegistry>,
+}
+
+impl PoolManager {
+  pub fn new<F>(metrics: Arc<MetricsRegistry>, provider: F) -> Self
+  where
+    F: Fn() -> Result<Arc<ConnectionPool>, String> + Send + Sync + 'static,
+  {
+    Self {
+      provider: Arc::new(provider),
+      metrics,
+    }
+  }
+
+  /// Registers the manager in `dispatcher`, the handlers get their connections with the
+  /// [PooledConn] extractor.
+  pub fn register(&self, dispatcher: AFPluginDispatcher) -> AFPluginDispatcher {
+    dispatcher.data(self.clone())
+  }
+
+  pub fn connection(&self) -> Result<DBConnection, String> {
+    let started_at = Instant::now();
+    let result = (self.provider)().and_then(|pool| pool.get().map_err(|err| err.to_string()));
+    self
+      .metrics
+      .histogram(SQLITE_POOL_CHECKOUT_SECONDS, &[])
+      .observe(started_at.elapsed());
+    if let Err(err) = &result {
+      tracing::error!("Get a sqlite connection failed: {}", err);
+      self
+        .metrics
+        .counter(SQLITE_POOL_CHECKOUT_ERRORS_TOTAL, &[])
+        .inc();
+    }
+    result
+  }
+
+  /// Pings the database and records the state of the pool in the metrics. Returns whether the
+  /// database answered.
+  pub fn check_health(&self) -> bool {
+    let healthy = match (self.provider)() {
+      Ok(pool) => {
+        let state = pool.state();
+        self
+          .metrics
+          .gauge(SQLITE_POOL_CONNECTIONS, &[])
+          .set(state.connections as i64);
+        self
+          .metrics
+          .gauge(SQLITE_POOL_IDLE_CONNECTIONS, &[])
+          .set(state.idle_connections as i64);
+        pool
+          .get()
+          .map_err(|err| err.to_string())
+          .and_then(|mut conn| conn.ping().map_err(|err| err.to_string()))
+          .map_err(|err| tracing::warn!("The sqlite health check failed: {}", err))
+          .is_ok()
+      },
+      Err(err) => {
+        tracing::debug!("Skip the sqlite health check: {}", err);
+        false
+      },
+    };
+    self
+      .metrics
+      .gauge(SQLITE_POOL_HEALTHY, &[])
+      .set(healthy as i64);
+    healthy
+  }
+
+  /// Runs [PoolManager::check_health] every `interval`, forever.
+  pub async fn run_health_checks(self, interval: Duration) {
+    loop {
+      tokio::time::sleep(interval).await;
+      self.check_health();
+    }
+  }
+}
+
+/// A connection of the pool of the [PoolManager], returned to the pool once dropped.
+pub struct PooledConn(DBConnection);
+
+impl PooledConn {
+  pub fn into_inner(self) -> DBConnection {
+    self.0
+  }
+}
+
+impl Deref for PooledConn {
+  type Target = DBConnection;
+
+  fn deref(&self) -> &Self::Target {
+    &self.0
+  }
+}
+
+impl DerefMut for PooledConn {
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    &mut self.0
+  }
+}
+
+impl FromAFPluginRequest for PooledConn {
+  type Error = DispatchError;
+  type Future = std::future::Ready<Result<Self, DispatchError>>;
+
+  fn from_request(req: &AFPluginEventRequest, _payload: &mut Payload) -> Self::Future {
+    let result = match req.get_app_data::<AppData<PoolManager>>() {
+      Some(manager) => manager
+        .connection()
+        .map(PooledConn)
+        .map_err(DispatchError::from),
+      None => Err(DispatchError::from(
+        "The pool manager is not registered".to_string(),
+      )),
+    };
+    std::future::ready(result)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+
+  use lib_dispatch::metrics::MetricsRegistry;
+  use tempfile::TempDir;
+
+  use crate::dispatch::{PoolManager, SQLITE_POOL_CHECKOUT_ERRORS_TOTAL, SQLITE_POOL_HEALTHY};
+
+  #[test]
+  fn pool_manager_health_check_test() {
+    let tempdir = TempDir::new().unwrap();
+    let pool = crate::init(tempdir.path()).unwrap().get_pool();
+    let metrics = Arc::new(MetricsRegistry::new());
+    let manager = PoolManager::new(metrics.clone(), move || Ok(pool.clone()));
+
+    assert!(manager.connection().is_ok());
+    assert!(manager.check_health());
+    assert_eq!(metrics.gauge(SQLITE_POOL_HEALTHY, &[]).get(), 1);
+  }
+
+  #[test]
+  fn pool_manager_without_database_test() {
+    let metrics = Arc::new(MetricsRegistry::new());
+    let manager = PoolManager::new(metrics.clone(), || Err("No user signed in".to_string()));
+
+    assert!(manager.connection().is_err());
+    assert!(!manager.check_health());
+    assert_eq!(metrics.gauge(SQLITE_POOL_HEALTHY, &[]).get(), 0);
+    assert_eq!(
+      metrics
+        .counter(SQLITE_POOL_CHECKOUT_ERRORS_TOTAL, &[])
+        .get(),
+      1
+    );
+  }
+}
@@ -0,0 +1,402 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use diesel::dsl::sql;
+use diesel::sql_types::Integer;
+use diesel::{ExpressionMethods, Insertable, QueryDsl, Queryable, RunQueryDsl};
+use lib_dispatch::prelude::*;
+
+use crate::dispatch::PoolManager;
+use crate::schema::dispatch_journal_table;
+
+/// Whether the handler of a journaled event can be called again with a request it may already
+/// have handled, see [DurableQueue::journal].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Idempotency {
+  /// Handling the request twice has the same effect as handling it once, so the requests that
+  /// were interrupted by a crash are dispatched again on startup. The handlers that are only
+  /// idempotent per request read the [JournalKey] of the request to skip the work already done.
+  Idempotent,
+  /// The requests that were interrupted are not dispatched again, their entries are marked
+  /// [JournalStatus::Abandoned] instead.
+  NotIdempotent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JournalStatus {
+  /// Journaled, but the handler didn't respond yet. Found on startup if the app stopped meanwhile.
+  Pending = 0,
+  Done = 1,
+  /// The handler responded with an error. Failed requests are not dispatched again.
+  Failed = 2,
+  /// An interrupted request that couldn't be dispatched again, see [Idempotency::NotIdempotent]
+  /// and [DurableQueue::max_attempts].
+  Abandoned = 3,
+}
+
+impl JournalStatus {
+  fn from_i32(value: i32) -> Self {
+    match value {
+      0 => JournalStatus::Pending,
+      1 => JournalStatus::Done,
+      2 => JournalStatus::Failed,
+      _ => JournalStatus::Abandoned,
+    }
+  }
+}
+
+/// Attached to the extensions of a journaled request. The handlers read it with the
+/// `Extension<JournalKey>` extractor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JournalKey {
+  pub id: i32,
+  /// 1 the first time the request is dispatched, incremented each time it's dispatched again.
+  pub attempt: i32,
+}
+
+impl JournalKey {
+  pub fn is_replay(&self) -> bool {
+    self.attempt > 1
+  }
+}
+
+#[derive(Debug, Clone, Queryable)]
+struct JournalRow {
+  id: i32,
+  event: String,
+  payload: Vec<u8>,
+  version: Option<i32>,
+  status: i32,
+  attempts: i32,
+  created_at: i64,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = dispatch_journal_table)]
+struct NewJournalRow<'a> {
+  event: &'a str,
+  payload: &'a [u8],
+  version: Option<i32>,
+  status: i32,
+  attempts: i32,
+  created_at: i64,
+}
+
+/// A request recorded by the [DurableQueue].
+#[derive(Debug, Clone)]
+pub struct JournalEntry {
+  pub id: i32,
+  pub event: String,
+  pub payload: Vec<u8>,
+  pub version: Option<u32>,
+  pub status: JournalStatus,
+  pub attempts: i32,
+  /// In seconds since the epoch.
+  pub created_at: i64,
+}
+
+impl From<JournalRow> for JournalEntry {
+  fn from(row: JournalRow) -> Self {
+    Self {
+      id: row.id,
+      event: row.event,
+      payload: row.payload,
+      version: row.version.map(|version| version as u32),
+      status: JournalStatus::from_i32(row.status),
+      attempts: row.attempts,
+      created_at: row.created_at,
+    }
+  }
+}
+
+/// What [DurableQueue::replay] did with the pending entries.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReplayReport {
+  pub succeeded: usize,
+  pub failed: usize,
+  pub abandoned: usize,
+}
+
+/// Makes the requests of the write events survive a crash. The requests of the events registered
+/// with [DurableQueue::journal] are written to the `dispatch_journal_table` of the user database
+/// before their plugin is called, and marked done or failed once it responds. The entries still
+/// pending on the next startup are dispatched again by [DurableQueue::replay], in the order they
+/// were journaled.
+///
+/// The queue is a middleware, registered with [DurableQueue::register]. A request is rejected if
+/// it can't be journaled, e.g. when no user is signed in.
+#[derive(Clone)]
+pub struct DurableQueue {
+  pool: PoolManager,
+  events: Arc<HashMap<String, Idempotency>>,
+  max_attempts: i32,
+}
+
+impl DurableQueue {
+  pub fn new(pool: PoolManager) -> Self {
+    Self {
+      pool,
+      events: Arc::new(HashMap::new()),
+      max_attempts: 3,
+    }
+  }
+
+  /// Journals the requests of `event`. `idempotency` is declared by the handler of the event and
+  /// decides whether its interrupted requests are dispatched again.
+  pub fn journal<E: ToString>(mut self, event: E, idempotency: Idempotency) -> Self {
+    Arc::make_mut(&mut self.events).insert(event.to_string(), idempotency);
+    self
+  }
+
+  /// How many times a request is dispatched at most, 3 by default. A request that crashed the
+  /// app that many times is abandoned instead of crashing it again.
+  pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+    self.max_attempts = max_attempts.max(1) as i32;
+    self
+  }
+
+  /// Registers the queue in `dispatcher` as a middleware.
+  pub fn register(&self, dispatcher: AFPluginDispatcher) -> AFPluginDispatcher {
+    dispatcher.with_middleware(self.clone())
+  }
+
+  pub fn entries(&self, status: JournalStatus) -> Result<Vec<JournalEntry>, String> {
+    let mut conn = self.pool.connection()?;
+    let rows = dispatch_journal_table::table
+      .filter(dispatch_journal_table::status.eq(status as i32))
+      .order(dispatch_journal_table::id.asc())
+      .load::<JournalRow>(&mut *conn)
+      .map_err(|err| err.to_string())?;
+    Ok(rows.into_iter().map(JournalEntry::from).collect())
+  }
+
+  /// Deletes the entries that are done. Returns how many were deleted.
+  pub fn prune(&self) -> Result<usize, String> {
+    let mut conn = self.pool.connection()?;
+    diesel::delete(
+      dispatch_journal_table::table
+        .filter(dispatch_journal_table::status.eq(JournalStatus::Done as i32)),
+    )
+    .execute(&mut *conn)
+    .map_err(|err| err.to_string())
+  }
+
+  /// Dispatches the pending entries again, one after the other, and prunes the done entries. It's
+  /// called once on startup, after the user database is opened and before the client sends new
+  /// requests. With the `local_set` feature, it must run in a `LocalSet`.
+  pub async fn replay(&self, dispatch: &AFPluginDispatcher) -> Result<ReplayReport, String> {
+    self.prune()?;
+    let mut report = ReplayReport::default();
+    for entry in self.entries(JournalStatus::Pending)? {
+      let idempotency = self.events.get(&entry.event).copied();
+      if idempotency != Some(Idempotency::Idempotent) || entry.attempts >= self.max_attempts {
+        tracing::warn!(
+          "Abandon the interrupted request of {} journaled as {}",
+          entry.event,
+          entry.id
+        );
+        self.set_status(entry.id, JournalStatus::Abandoned)?;
+        report.abandoned += 1;
+        continue;
+      }
+
+      let key = JournalKey {
+        id: entry.id,
+        attempt: entry.attempts + 1,
+      };
+      self.set_attempts(key)?;
+      let mut request = AFPluginRequest::new(entry.event.as_str()).payload(entry.payload);
+      if let Some(version) = entry.version {
+        request = request.version(version);
+      }
+      request.extensions().insert(key);
+      let response =
+        AFPluginDispatcher::async_send_with_callback(dispatch, request, |_| Box::pin(async {}))
+          .await;
+      if response.status_code == StatusCode::Ok {
+        report.succeeded += 1;
+      } else {
+        report.failed += 1;
+      }
+    }
+    Ok(report)
+  }
+
+  fn append(&self, request: &AFPluginRequest) -> Result<JournalKey, String> {
+    let mut conn = self.pool.connection()?;
+    let created_at = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map(|duration| duration.as_secs() as i64)
+      .unwrap_or_default();
+    let row = NewJournalRow {
+      event: request.event.as_str(),
+      payload: request.payload_bytes(),
+      version: request.version.map(|version| version as i32),
+      status: JournalStatus::Pending as i32,
+      attempts: 1,
+      created_at,
+    };
+    let id = conn
+      .immediate_transaction(|conn| {
+        diesel::insert_into(dispatch_journal_table::table)
+          .values(&row)
+          .execute(conn)?;
+        diesel::select(sql::<Integer>("last_insert_rowid()")).get_result::<i32>(conn)
+      })
+      .map_err(|err: diesel::result::Error| err.to_string())?;
+    Ok(JournalKey { id, attempt: 1 })
+  }
+
+  fn set_status(&self, id: i32, status: JournalStatus) -> Result<(), String> {
+    let mut conn = self.pool.connection()?;
+    diesel::update(dispatch_journal_table::table.find(id))
+      .set(dispatch_journal_table::status.eq(status as i32))
+      .execute(&mut *conn)
+      .map(|_| ())
+      .map_err(|err| err.to_string())
+  }
+
+  fn set_attempts(&self, key: JournalKey) -> Result<(), String> {
+    let mut conn = self.pool.connection()?;
+    diesel::update(dispatch_journal_table::table.find(key.id))
+      .set(dispatch_journal_table::attempts.eq(key.attempt))
+      .execute(&mut *conn)
+      .map(|_| ())
+      .map_err(|err| err.to_string())
+  }
+}
+
+impl AFPluginMiddleware for DurableQueue {
+  fn on_request(&self, request: &mut AFPluginRequest) -> Result<(), DispatchError> {
+    // The replayed requests carry the key of their entry already.
+    if !self.events.contains_key(request.event.as_str())
+      || request.extensions().contains::<JournalKey>()
+    {
+      return Ok(());
+    }
+    let key = self.append(request).map_err(|err| {
+      DispatchError::from(format!(
+        "Failed to journal the request of {}: {}",
+        request.event.as_str(),
+        err
+      ))
+    })?;
+    request.extensions().insert(key);
+    Ok(())
+  }
+
+  fn on_response(&self, request: &AFPluginRequest, response: &mut AFPluginEventResponse) {
+    if let Some(key) = request.extensions().get::<JournalKey>() {
+      let status = if response.status_code == StatusCode::Ok {
+        JournalStatus::Done
+      } else {
+        JournalStatus::Failed
+      };
+      if let Err(err) = self.set_status(key.id, status) {
+        tracing::error!(
+          "Failed to mark the journal entry {} {:?}: {}",
+          key.id,
+          status,
+          err
+        );
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::{Arc, Mutex};
+
+  use lib_dispatch::metrics::MetricsRegistry;
+  use lib_dispatch::prelude::*;
+  use lib_dispatch::runtime::AFPluginRuntime;
+  use tempfile::TempDir;
+  use tokio::task::LocalSet;
+
+  use crate::dispatch::PoolManager;
+  use crate::durable_queue::{DurableQueue, Idempotency, JournalKey, JournalStatus};
+
+  #[derive(Clone, Default)]
+  struct Calls(Arc<Mutex<Vec<(String, i32)>>>);
+
+  async fn add_row(
+    name: String,
+    key: Extension<JournalKey>,
+    calls: AppData<Calls>,
+  ) -> Result<(), DispatchError> {
+    calls.0.lock().unwrap().push((name.clone(), key.attempt));
+    if name == "invalid" {
+      return Err(DispatchError::from("invalid row".to_string()));
+    }
+    Ok(())
+  }
+
+  fn make_queue(tempdir: &TempDir) -> DurableQueue {
+    let pool = crate::init(tempdir.path()).unwrap().get_pool();
+    let manager = PoolManager::new(Arc::new(MetricsRegistry::new()), move || Ok(pool.clone()));
+    DurableQueue::new(manager)
+      .journal("add_row", Idempotency::Idempotent)
+      .journal("send_mail", Idempotency::NotIdempotent)
+  }
+
+  fn make_dispatcher(queue: &DurableQueue, calls: &Calls) -> AFPluginDispatcher {
+    let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+    let plugin = AFPlugin::new()
+      .event("add_row", add_row)
+      .event("send_mail", add_row);
+    queue.register(AFPluginDispatcher::new(runtime, vec![plugin]).data(calls.clone()))
+  }
+
+  #[tokio::test]
+  async fn durable_queue_journal_test() {
+    let tempdir = TempDir::new().unwrap();
+    let queue = make_queue(&tempdir);
+    let calls = Calls::default();
+    let dispatcher = make_dispatcher(&queue, &calls);
+    let local_set = LocalSet::new();
+
+    for name in ["a", "invalid"] {
+      local_set
+        .run_until(AFPluginDispatcher::async_send(
+          &dispatcher,
+          AFPluginRequest::new("add_row").payload(name),
+        ))
+        .await;
+    }
+
+    assert_eq!(queue.entries(JournalStatus::Done).unwrap().len(), 1);
+    let failed = queue.entries(JournalStatus::Failed).unwrap();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].payload, b"invalid".to_vec());
+    assert!(queue.entries(JournalStatus::Pending).unwrap().is_empty());
+  }
+
+  #[tokio::test]
+  async fn durable_queue_replay_test() {
+    let tempdir = TempDir::new().unwrap();
+    let queue = make_queue(&tempdir);
+
+    // Journaled, but the app stopped before the plugins were called.
+    for event in ["add_row", "send_mail"] {
+      let mut request = AFPluginRequest::new(event).payload("b");
+      queue.on_request(&mut request).unwrap();
+    }
+
+    let calls = Calls::default();
+    let dispatcher = make_dispatcher(&queue, &calls);
+    let report = LocalSet::new()
+      .run_until(queue.replay(&dispatcher))
+      .await
+      .unwrap();
+
+    assert_eq!(report.succeeded, 1);
+    assert_eq!(report.abandoned, 1);
+    assert_eq!(*calls.0.lock().unwrap(), vec![("b".to_string(), 2)]);
+    assert_eq!(queue.entries(JournalStatus::Done).unwrap().len(), 1);
+    let abandoned = queue.entries(JournalStatus::Abandoned).unwrap();
+    assert_eq!(abandoned[0].event, "send_mail");
+    assert!(queue.entries(JournalStatus::Pending).unwrap().is_empty());
+  }
+}
@@ -0,0 +1,290 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use diesel::{ExpressionMethods, Insertable, OptionalExtension, QueryDsl, Queryable, RunQueryDsl};
+use lib_dispatch::prelude::*;
+
+use crate::dispatch::PoolManager;
+use crate::schema::dispatch_idempotency_table;
+
+#[derive(Debug, Clone, Queryable)]
+struct IdempotencyRow {
+  idempotency_key: String,
+  event: String,
+  status_code: i32,
+  content_type: Option<String>,
+  payload: Vec<u8>,
+  created_at: i64,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = dispatch_idempotency_table)]
+struct NewIdempotencyRow<'a> {
+  idempotency_key: &'a str,
+  event: &'a str,
+  status_code: i32,
+  content_type: Option<&'a str>,
+  payload: &'a [u8],
+  created_at: i64,
+}
+
+impl From<IdempotencyRow> for AFPluginEventResponse {
+  fn from(row: IdempotencyRow) -> Self {
+    let status_code = if row.status_code == StatusCode::Ok as i32 {
+      StatusCode::Ok
+    } else {
+      StatusCode::Err
+    };
+    let mut builder = ResponseBuilder::new(status_code).data(row.payload);
+    if let Some(content_type) = row.content_type.as_deref().and_then(ContentType::from_mime) {
+      builder = builder.content_type(content_type);
+    }
+    builder.build()
+  }
+}
+
+/// Makes the mutations safe to retry. The response of a request carrying an idempotency key, see
+/// [AFPluginRequest::idempotency_key], is written to the `dispatch_idempotency_table` of the user
+/// database along with the key. The requests sent again with the same key and event within the
+/// retention window are answered with the recorded response, their plugin isn't called again,
+/// even if the app restarted meanwhile.
+///
+/// Only the responses of the handlers are recorded: a request rejected by the dispatcher, or
+/// interrupted before its handler responded, runs again when it's retried.
+///
+/// The store is a middleware, registered with [IdempotencyStore::register].
+#[derive(Clone)]
+pub struct IdempotencyStore {
+  pool: PoolManager,
+  retention: Duration,
+}
+
+impl IdempotencyStore {
+  pub fn new(pool: PoolManager) -> Self {
+    Self {
+      pool,
+      retention: Duration::from_secs(24 * 60 * 60),
+    }
+  }
+
+  /// How long a response is replayed after it was recorded, a day by default.
+  pub fn retention(mut self, retention: Duration) -> Self {
+    self.retention = retention;
+    self
+  }
+
+  /// Registers the store in `dispatcher` as a middleware.
+  pub fn register(&self, dispatcher: AFPluginDispatcher) -> AFPluginDispatcher {
+    dispatcher.with_middleware(self.clone())
+  }
+
+  /// Deletes the responses older than the retention window. Returns how many were deleted.
+  pub fn prune(&self) -> Result<usize, String> {
+    let mut conn = self.pool.connection()?;
+    diesel::delete(
+      dispatch_idempotency_table::table
+        .filter(dispatch_idempotency_table::created_at.lt(self.expired_before(now()))),
+    )
+    .execute(&mut *conn)
+    .map_err(|err| err.to_string())
+  }
+
+  fn expired_before(&self, now: i64) -> i64 {
+    now - self.retention.as_secs() as i64
+  }
+
+  fn lookup(&self, key: &str, event: &str, now: i64) -> Result<Option<IdempotencyRow>, String> {
+    let mut conn = self.pool.connection()?;
+    dispatch_idempotency_table::table
+      .find((key, event))
+      .filter(dispatch_idempotency_table::created_at.ge(self.expired_before(now)))
+      .first::<IdempotencyRow>(&mut *conn)
+      .optional()
+      .map_err(|err| err.to_string())
+  }
+
+  /// Records the response unless the key already has one, e.g. when `response` was replayed.
+  fn record(
+    &self,
+    key: &str,
+    request: &AFPluginRequest,
+    response: &AFPluginEventResponse,
+    now: i64,
+  ) -> Result<(), String> {
+    let mut conn = self.pool.connection()?;
+    let row = NewIdempotencyRow {
+      idempotency_key: key,
+      event: request.event.as_str(),
+      status_code: response.status_code.clone() as i32,
+      content_type: response
+        .content_type
+        .map(|content_type| content_type.as_str()),
+      payload: response.payload.as_ref(),
+      created_at: now,
+    };
+    conn
+      .immediate_transaction(|conn| {
+        // The expired response of the key is replaced.
+        diesel::delete(
+          dispatch_idempotency_table::table
+            .find((key, request.event.as_str()))
+            .filter(dispatch_idempotency_table::created_at.lt(self.expired_before(now))),
+        )
+        .execute(conn)?;
+        diesel::insert_or_ignore_into(dispatch_idempotency_table::table)
+          .values(&row)
+          .execute(conn)
+      })
+      .map(|_| ())
+      .map_err(|err: diesel::result::Error| err.to_string())
+  }
+}
+
+impl AFPluginMiddleware for IdempotencyStore {
+  fn respond(&self, request: &AFPluginRequest) -> Option<AFPluginEventResponse> {
+    let key = request.metadata.get(IDEMPOTENCY_KEY_METADATA)?;
+    match self.lookup(key, request.event.as_str(), now()) {
+      Ok(row) => row.map(|row| {
+        tracing::debug!(
+          "Replay the response of {} recorded for the key {} at {}",
+          row.event,
+          row.idempotency_key,
+          row.created_at
+        );
+        row.into()
+      }),
+      Err(err) => {
+        tracing::error!("Failed to read the response of the key {}: {}", key, err);
+        None
+      },
+    }
+  }
+
+  fn on_response(&self, request: &AFPluginRequest, response: &mut AFPluginEventResponse) {
+    let key = match request.metadata.get(IDEMPOTENCY_KEY_METADATA) {
+      Some(key) => key,
+      None => return,
+    };
+    if response.error_origin.is_some() {
+      return;
+    }
+    if let Err(err) = self.record(key, request, response, now()) {
+      tracing::error!("Failed to record the response of the key {}: {}", key, err);
+    }
+  }
+}
+
+/// In seconds since the epoch.
+fn now() -> i64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|duration| duration.as_secs() as i64)
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::atomic::{AtomicUsize, Ordering};
+  use std::sync::Arc;
+
+  use lib_dispatch::metrics::MetricsRegistry;
+  use lib_dispatch::prelude::*;
+  use lib_dispatch::runtime::AFPluginRuntime;
+  use tempfile::TempDir;
+  use tokio::task::LocalSet;
+
+  use crate::dispatch::PoolManager;
+  use crate::idempotency::{now, IdempotencyStore};
+
+  #[derive(Clone, Default)]
+  struct Calls(Arc<AtomicUsize>);
+
+  async fn create_row(name: String, calls: AppData<Calls>) -> Result<String, DispatchError> {
+    let id = calls.0.fetch_add(1, Ordering::SeqCst) + 1;
+    if name == "invalid" {
+      return Err(DispatchError::from("invalid row".to_string()));
+    }
+    Ok(format!("{}-{}", name, id))
+  }
+
+  fn make_store(tempdir: &TempDir) -> IdempotencyStore {
+    let pool = crate::init(tempdir.path()).unwrap().get_pool();
+    let manager = PoolManager::new(Arc::new(MetricsRegistry::new()), move || Ok(pool.clone()));
+    IdempotencyStore::new(manager)
+  }
+
+  fn make_dispatcher(store: &IdempotencyStore, calls: &Calls) -> AFPluginDispatcher {
+    let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+    let plugin = AFPlugin::new().event("create_row", create_row);
+    store.register(AFPluginDispatcher::new(runtime, vec![plugin]).data(calls.clone()))
+  }
+
+  async fn send(
+    dispatcher: &AFPluginDispatcher,
+    name: &str,
+    key: Option<&str>,
+  ) -> AFPluginEventResponse {
+    let mut request = AFPluginRequest::new("create_row").payload(name);
+    if let Some(key) = key {
+      request = request.idempotency_key(key);
+    }
+    LocalSet::new()
+      .run_until(AFPluginDispatcher::async_send(dispatcher, request))
+      .await
+  }
+
+  #[tokio::test]
+  async fn idempotency_replay_test() {
+    let tempdir = TempDir::new().unwrap();
+    let store = make_store(&tempdir);
+    let calls = Calls::default();
+    let dispatcher = make_dispatcher(&store, &calls);
+
+    let first = send(&dispatcher, "a", Some("k1")).await;
+    assert_eq!(first.payload.as_ref(), b"a-1");
+    let replayed = send(&dispatcher, "a", Some("k1")).await;
+    assert_eq!(replayed.status_code, StatusCode::Ok);
+    assert_eq!(replayed.payload.as_ref(), b"a-1");
+    assert_eq!(send(&dispatcher, "a", None).await.payload.as_ref(), b"a-2");
+
+    // The errors of the handler are replayed as well.
+    send(&dispatcher, "invalid", Some("k2")).await;
+    let replayed = send(&dispatcher, "invalid", Some("k2")).await;
+    assert_eq!(replayed.status_code, StatusCode::Err);
+    assert_eq!(calls.0.load(Ordering::SeqCst), 3);
+
+    // After a restart.
+    let dispatcher = make_dispatcher(&make_store(&tempdir), &calls);
+    let replayed = send(&dispatcher, "a", Some("k1")).await;
+    assert_eq!(replayed.payload.as_ref(), b"a-1");
+    assert_eq!(calls.0.load(Ordering::SeqCst), 3);
+  }
+
+  #[tokio::test]
+  async fn idempotency_retention_test() {
+    let tempdir = TempDir::new().unwrap();
+    let store = make_store(&tempdir);
+    let calls = Calls::default();
+    let dispatcher = make_dispatcher(&store, &calls);
+
+    // Recorded two days ago.
+    let request = AFPluginRequest::new("create_row");
+    let response = ResponseBuilder::Ok().data("old").build();
+    store
+      .record("k1", &request, &response, now() - 2 * 24 * 60 * 60)
+      .unwrap();
+    assert_eq!(
+      send(&dispatcher, "a", Some("k1")).await.payload.as_ref(),
+      b"a-1"
+    );
+    assert_eq!(
+      send(&dispatcher, "a", Some("k1")).await.payload.as_ref(),
+      b"a-1"
+    );
+
+    store
+      .record("k2", &request, &response, now() - 2 * 24 * 60 * 60)
+      .unwrap();
+    assert_eq!(store.prune().unwrap(), 1);
+    assert_eq!(calls.0.load(Ordering::SeqCst), 1);
+  }
+}
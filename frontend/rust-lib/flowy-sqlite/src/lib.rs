@@ -11,9 +11,18 @@ pub use diesel::*;
 pub use diesel_derives::*;
 use diesel_migrations::{EmbeddedMigrations, MigrationHarness};
 
-pub use crate::sqlite_impl::{ConnectionPool, DBConnection, Database, PoolConfig};
+pub use crate::sqlite_impl::{
+  encrypt_database, is_plaintext_database, ConnectionPool, DBConnection, Database, PoolConfig,
+};
 
+#[cfg(feature = "dispatch")]
+pub mod dispatch;
+#[cfg(feature = "dispatch")]
+pub mod durable_queue;
+#[cfg(feature = "dispatch")]
+pub mod idempotency;
 pub mod kv;
+pub mod migration;
 mod sqlite_impl;
 
 pub mod schema;
@@ -33,11 +42,22 @@ pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("../flowy-sqlite/mi
 pub const DB_NAME: &str = "flowy-database.db";
 
 pub fn init<P: AsRef<Path>>(storage_path: P) -> Result<Database, io::Error> {
+  open(storage_path, PoolConfig::default())
+}
+
+/// Like [init], with the database encrypted by SQLCipher with the raw `key`. The plaintext
+/// database of the previous launches is encrypted first. Requires the `sqlcipher` feature.
+pub fn init_encrypted<P: AsRef<Path>>(storage_path: P, key: &[u8]) -> Result<Database, io::Error> {
+  let storage_path = storage_path.as_ref().to_str().unwrap();
+  encrypt_database(storage_path, DB_NAME, key).map_err(as_io_error)?;
+  open(storage_path, PoolConfig::default().encryption_key(key))
+}
+
+fn open<P: AsRef<Path>>(storage_path: P, pool_config: PoolConfig) -> Result<Database, io::Error> {
   let storage_path = storage_path.as_ref().to_str().unwrap();
   if !Path::new(storage_path).exists() {
     std::fs::create_dir_all(storage_path)?;
   }
-  let pool_config = PoolConfig::default();
   let database = Database::new(storage_path, DB_NAME, pool_config).map_err(as_io_error)?;
   let mut conn = database.get_connection().map_err(as_io_error)?;
   (*conn)
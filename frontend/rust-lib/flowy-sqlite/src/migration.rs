@@ -0,0 +1,317 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use diesel::connection::{AnsiTransactionManager, SimpleConnection, TransactionManager};
+use diesel::dsl::max;
+use diesel::{sql_query, ExpressionMethods, QueryDsl, QueryResult, RunQueryDsl, SqliteConnection};
+
+const MODULE_MIGRATION_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS module_migration_table (
+  module TEXT NOT NULL,
+  version INTEGER NOT NULL,
+  name TEXT NOT NULL,
+  applied_at BIGINT NOT NULL,
+  PRIMARY KEY (module, version)
+);
+"#;
+
+diesel::table! {
+    module_migration_table (module, version) {
+        module -> Text,
+        version -> Integer,
+        name -> Text,
+        applied_at -> BigInt,
+    }
+}
+
+type MigrationFn = Box<dyn Fn(&mut SqliteConnection) -> QueryResult<()> + Send + Sync>;
+
+struct MigrationStep {
+  version: i32,
+  name: String,
+  up: MigrationFn,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ModuleMigrationError {
+  #[error("Invalid migrations of {module}: {reason}")]
+  InvalidSteps { module: String, reason: String },
+  #[error("Migration {version} ({name}) of {module} failed, rolled back: {source}")]
+  Step {
+    module: String,
+    version: i32,
+    name: String,
+    source: diesel::result::Error,
+  },
+  #[error("diesel error: {0}")]
+  Diesel(#[from] diesel::result::Error),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationRecord {
+  pub version: i32,
+  pub name: String,
+}
+
+/// The outcome of [ModuleMigrations::run] or [ModuleMigrations::dry_run].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationReport {
+  pub module: String,
+  pub from_version: i32,
+  /// The version of the module once the steps are applied. With a dry run, the version the
+  /// module would have.
+  pub to_version: i32,
+  pub applied: Vec<MigrationRecord>,
+  pub dry_run: bool,
+}
+
+/// The versioned schema of the storage of a module, for the modules that keep their data in
+/// their own tables or database instead of the diesel migrations of the main database.
+///
+/// The steps are applied in the order of their versions, when the module starts:
+///
+/// ```ignore
+/// ModuleMigrations::new("kv")
+///   .sql(1, "create_kv_store_table", KV_STORE_SQL)
+///   .sql(2, "add_kv_store_index", KV_STORE_INDEX_SQL)
+///   .run(&mut conn)?;
+/// ```
+///
+/// The version of each module is recorded in the `module_migration_table` of the database the
+/// steps run against. Only the steps above it are applied, all of them in one transaction, so a
+/// failing step rolls back the steps applied before it and the module stays at its version.
+pub struct ModuleMigrations {
+  module: String,
+  steps: Vec<MigrationStep>,
+}
+
+impl ModuleMigrations {
+  pub fn new(module: &str) -> Self {
+    Self {
+      module: module.to_string(),
+      steps: vec![],
+    }
+  }
+
+  /// Adds a step that runs `up` against the connection. The versions start at 1 and must be
+  /// added in increasing order.
+  pub fn step<F>(mut self, version: i32, name: &str, up: F) -> Self
+  where
+    F: Fn(&mut SqliteConnection) -> QueryResult<()> + Send + Sync + 'static,
+  {
+    self.steps.push(MigrationStep {
+      version,
+      name: name.to_string(),
+      up: Box::new(up),
+    });
+    self
+  }
+
+  /// Adds a step that executes the `sql` statements, like [ModuleMigrations::step].
+  pub fn sql(self, version: i32, name: &str, sql: &'static str) -> Self {
+    self.step(version, name, move |conn| conn.batch_execute(sql))
+  }
+
+  /// The version the module is at, zero if none of its steps were applied.
+  pub fn current_version(&self, conn: &mut SqliteConnection) -> QueryResult<i32> {
+    sql_query(MODULE_MIGRATION_SQL).execute(conn)?;
+    let version = module_migration_table::table
+      .filter(module_migration_table::module.eq(&self.module))
+      .select(max(module_migration_table::version))
+      .first::<Option<i32>>(conn)?;
+    Ok(version.unwrap_or(0))
+  }
+
+  /// Applies the pending steps.
+  pub fn run(&self, conn: &mut SqliteConnection) -> Result<MigrationReport, ModuleMigrationError> {
+    self.migrate(conn, false)
+  }
+
+  /// Applies the pending steps and rolls them back, to check that they would succeed without
+  /// changing the database.
+  pub fn dry_run(
+    &self,
+    conn: &mut SqliteConnection,
+  ) -> Result<MigrationReport, ModuleMigrationError> {
+    self.migrate(conn, true)
+  }
+
+  fn migrate(
+    &self,
+    conn: &mut SqliteConnection,
+    dry_run: bool,
+  ) -> Result<MigrationReport, ModuleMigrationError> {
+    self.validate()?;
+    let from_version = self.current_version(conn)?;
+    let pending = self
+      .steps
+      .iter()
+      .filter(|step| step.version > from_version)
+      .collect::<Vec<_>>();
+    let mut report = MigrationReport {
+      module: self.module.clone(),
+      from_version,
+      to_version: from_version,
+      applied: vec![],
+      dry_run,
+    };
+    if pending.is_empty() {
+      return Ok(report);
+    }
+
+    AnsiTransactionManager::begin_transaction(conn)?;
+    let result = pending.iter().try_for_each(|step| self.apply(conn, step));
+    match result {
+      Ok(()) if !dry_run => AnsiTransactionManager::commit_transaction(conn)?,
+      _ => AnsiTransactionManager::rollback_transaction(conn)?,
+    }
+    result?;
+
+    for step in pending {
+      report.to_version = step.version;
+      report.applied.push(MigrationRecord {
+        version: step.version,
+        name: step.name.clone(),
+      });
+    }
+    if !dry_run {
+      tracing::info!(
+        "Migrated {} from version {} to {}",
+        self.module,
+        report.from_version,
+        report.to_version
+      );
+    }
+    Ok(report)
+  }
+
+  fn apply(
+    &self,
+    conn: &mut SqliteConnection,
+    step: &MigrationStep,
+  ) -> Result<(), ModuleMigrationError> {
+    let as_step_error = |source| ModuleMigrationError::Step {
+      module: self.module.clone(),
+      version: step.version,
+      name: step.name.clone(),
+      source,
+    };
+    (step.up)(conn).map_err(as_step_error)?;
+    diesel::insert_into(module_migration_table::table)
+      .values((
+        module_migration_table::module.eq(&self.module),
+        module_migration_table::version.eq(step.version),
+        module_migration_table::name.eq(&step.name),
+        module_migration_table::applied_at.eq(now()),
+      ))
+      .execute(conn)
+      .map_err(as_step_error)?;
+    Ok(())
+  }
+
+  fn validate(&self) -> Result<(), ModuleMigrationError> {
+    let mut last_version = 0;
+    for step in &self.steps {
+      if step.version <= last_version {
+        return Err(ModuleMigrationError::InvalidSteps {
+          module: self.module.clone(),
+          reason: format!(
+            "version {} ({}) must be greater than {}",
+            step.version, step.name, last_version
+          ),
+        });
+      }
+      last_version = step.version;
+    }
+    Ok(())
+  }
+}
+
+fn now() -> i64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|duration| duration.as_secs() as i64)
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+  use diesel::connection::SimpleConnection;
+  use diesel::{Connection, QueryResult, SqliteConnection};
+
+  use crate::migration::{MigrationRecord, ModuleMigrationError, ModuleMigrations};
+
+  const CREATE_NOTE_SQL: &str = "CREATE TABLE note (id TEXT PRIMARY KEY NOT NULL);";
+  const ADD_NOTE_TITLE_SQL: &str = "ALTER TABLE note ADD COLUMN title TEXT NOT NULL DEFAULT '';";
+
+  fn has_table(conn: &mut SqliteConnection, table: &str) -> bool {
+    conn
+      .batch_execute(&format!("SELECT * FROM {} LIMIT 1;", table))
+      .is_ok()
+  }
+
+  fn failing_step(_conn: &mut SqliteConnection) -> QueryResult<()> {
+    Err(diesel::result::Error::RollbackTransaction)
+  }
+
+  #[test]
+  fn module_migration_test() {
+    let mut conn = SqliteConnection::establish(":memory:").unwrap();
+    let migrations = ModuleMigrations::new("note").sql(1, "create_note", CREATE_NOTE_SQL);
+    let report = migrations.run(&mut conn).unwrap();
+    assert_eq!((report.from_version, report.to_version), (0, 1));
+    assert!(has_table(&mut conn, "note"));
+
+    // The applied steps are not applied again
+    let report = migrations.run(&mut conn).unwrap();
+    assert!(report.applied.is_empty());
+
+    let migrations = migrations.sql(2, "add_note_title", ADD_NOTE_TITLE_SQL);
+    let report = migrations.run(&mut conn).unwrap();
+    assert_eq!(
+      report.applied,
+      vec![MigrationRecord {
+        version: 2,
+        name: "add_note_title".to_string()
+      }]
+    );
+    assert_eq!(migrations.current_version(&mut conn).unwrap(), 2);
+
+    // The versions are recorded per module
+    let other = ModuleMigrations::new("other");
+    assert_eq!(other.current_version(&mut conn).unwrap(), 0);
+  }
+
+  #[test]
+  fn module_migration_dry_run_test() {
+    let mut conn = SqliteConnection::establish(":memory:").unwrap();
+    let migrations = ModuleMigrations::new("note")
+      .sql(1, "create_note", CREATE_NOTE_SQL)
+      .sql(2, "add_note_title", ADD_NOTE_TITLE_SQL);
+    let report = migrations.dry_run(&mut conn).unwrap();
+    assert!(report.dry_run);
+    assert_eq!((report.from_version, report.to_version), (0, 2));
+    assert_eq!(report.applied.len(), 2);
+    assert_eq!(migrations.current_version(&mut conn).unwrap(), 0);
+    assert!(!has_table(&mut conn, "note"));
+  }
+
+  #[test]
+  fn module_migration_rollback_test() {
+    let mut conn = SqliteConnection::establish(":memory:").unwrap();
+    let migrations = ModuleMigrations::new("note")
+      .sql(1, "create_note", CREATE_NOTE_SQL)
+      .step(2, "broken", failing_step);
+    let err = migrations.run(&mut conn).unwrap_err();
+    assert!(matches!(err, ModuleMigrationError::Step { version: 2, .. }));
+    assert_eq!(migrations.current_version(&mut conn).unwrap(), 0);
+    assert!(!has_table(&mut conn, "note"));
+
+    let unordered = ModuleMigrations::new("note")
+      .sql(2, "add_note_title", ADD_NOTE_TITLE_SQL)
+      .sql(1, "create_note", CREATE_NOTE_SQL);
+    assert!(matches!(
+      unordered.run(&mut conn),
+      Err(ModuleMigrationError::InvalidSteps { .. })
+    ));
+  }
+}
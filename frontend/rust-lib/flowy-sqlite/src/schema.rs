@@ -45,6 +45,49 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    dispatch_journal_table (id) {
+        id -> Integer,
+        event -> Text,
+        payload -> Binary,
+        version -> Nullable<Integer>,
+        status -> Integer,
+        attempts -> Integer,
+        created_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    dispatch_idempotency_table (idempotency_key, event) {
+        idempotency_key -> Text,
+        event -> Text,
+        status_code -> Integer,
+        content_type -> Nullable<Text>,
+        payload -> Binary,
+        created_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    document_edit_table (seq) {
+        seq -> BigInt,
+        document_id -> Text,
+        kind -> Integer,
+        payload -> Binary,
+        created_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    document_history_snapshot_table (document_id, seq) {
+        document_id -> Text,
+        seq -> BigInt,
+        state_vector -> Binary,
+        doc_state -> Binary,
+        created_at -> BigInt,
+    }
+}
+
 diesel::table! {
     upload_file_part (upload_id, e_tag) {
         upload_id -> Text,
@@ -93,6 +136,14 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    user_workspace_order_table (uid, workspace_id) {
+        uid -> BigInt,
+        workspace_id -> Text,
+        position -> Integer,
+    }
+}
+
 diesel::table! {
     user_workspace_table (id) {
         id -> Text,
@@ -121,10 +172,15 @@ diesel::allow_tables_to_appear_in_same_query!(
   chat_message_table,
   chat_table,
   collab_snapshot,
+  dispatch_idempotency_table,
+  dispatch_journal_table,
+  document_edit_table,
+  document_history_snapshot_table,
   upload_file_part,
   upload_file_table,
   user_data_migration_records,
   user_table,
+  user_workspace_order_table,
   user_workspace_table,
   workspace_members_table,
 );
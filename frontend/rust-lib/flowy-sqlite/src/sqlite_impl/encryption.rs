@@ -0,0 +1,143 @@
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use diesel::connection::SimpleConnection;
+use diesel::{Connection, SqliteConnection};
+
+use crate::sqlite_impl::database::db_file_uri;
+use crate::sqlite_impl::errors::*;
+use crate::sqlite_impl::pragma::PragmaExtension;
+
+/// The first bytes of the plaintext SQLite files. The SQLCipher files start with the salt.
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+pub(crate) fn hex_key(key: &[u8]) -> String {
+  key.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Whether the database file exists and isn't encrypted.
+pub fn is_plaintext_database<P: AsRef<Path>>(path: P) -> Result<bool> {
+  let mut header = [0u8; 16];
+  match fs::File::open(path) {
+    Ok(mut file) => match file.read_exact(&mut header) {
+      Ok(_) => Ok(&header == SQLITE_HEADER),
+      Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+      Err(err) => Err(err.into()),
+    },
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
+    Err(err) => Err(err.into()),
+  }
+}
+
+/// Encrypts the plaintext database `name` in `dir` with the raw `key`, the migration path of
+/// the databases created before the encryption was turned on. Returns whether the database was
+/// encrypted, nothing is done if it's missing or already encrypted.
+///
+/// It must run before the database is opened. The database is exported to a new file replacing
+/// the plaintext one once complete, so an interrupted migration leaves the plaintext database
+/// untouched.
+pub fn encrypt_database(dir: &str, name: &str, key: &[u8]) -> Result<bool> {
+  let path = db_file_uri(dir, name);
+  if !is_plaintext_database(&path)? {
+    return Ok(false);
+  }
+
+  let encrypted_path = format!("{}.encrypting", path);
+  if Path::new(&encrypted_path).exists() {
+    fs::remove_file(&encrypted_path)?;
+  }
+  {
+    let mut conn = SqliteConnection::establish(&path)?;
+    conn.pragma_check_cipher()?;
+    let export = conn.batch_execute(&format!(
+      "ATTACH DATABASE '{}' AS encrypted KEY \"x'{}'\";
+       SELECT sqlcipher_export('encrypted');
+       DETACH DATABASE encrypted;",
+      encrypted_path.replace('\'', "''"),
+      hex_key(key)
+    ));
+    if let Err(err) = export {
+      let _ = fs::remove_file(&encrypted_path);
+      return Err(err.into());
+    }
+  }
+
+  // The write-ahead log of the plaintext database must not be applied to the encrypted one
+  for suffix in ["-wal", "-shm"] {
+    let path = format!("{}{}", path, suffix);
+    if Path::new(&path).exists() {
+      fs::remove_file(path)?;
+    }
+  }
+  fs::rename(&encrypted_path, &path)?;
+  tracing::info!("Encrypted the database {}", path);
+  Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+  use diesel::connection::SimpleConnection;
+  use diesel::{Connection, SqliteConnection};
+  use tempfile::TempDir;
+
+  use crate::sqlite_impl::pragma::PragmaExtension;
+  use crate::sqlite_impl::{db_file_uri, encrypt_database, hex_key, is_plaintext_database};
+
+  const KEY: [u8; 32] = [7; 32];
+
+  fn create_database(dir: &str, name: &str) -> String {
+    let path = db_file_uri(dir, name);
+    let mut conn = SqliteConnection::establish(&path).unwrap();
+    conn
+      .batch_execute("CREATE TABLE test (id INTEGER PRIMARY KEY); INSERT INTO test VALUES (42);")
+      .unwrap();
+    path
+  }
+
+  #[test]
+  fn plaintext_database_test() {
+    let tempdir = TempDir::new().unwrap();
+    let dir = tempdir.path().to_str().unwrap();
+    assert!(!is_plaintext_database(db_file_uri(dir, "test.db")).unwrap());
+    let path = create_database(dir, "test.db");
+    assert!(is_plaintext_database(path).unwrap());
+  }
+
+  #[cfg(not(feature = "sqlcipher"))]
+  #[test]
+  fn encryption_requires_sqlcipher_test() {
+    let tempdir = TempDir::new().unwrap();
+    let dir = tempdir.path().to_str().unwrap();
+    let path = create_database(dir, "test.db");
+
+    // Nothing is written in plaintext when the key can't be applied
+    assert!(encrypt_database(dir, "test.db", &KEY).is_err());
+    assert!(is_plaintext_database(&path).unwrap());
+    let mut conn = SqliteConnection::establish(&db_file_uri(dir, "new.db")).unwrap();
+    assert!(conn.pragma_set_key(&hex_key(&KEY)).is_err());
+  }
+
+  #[cfg(feature = "sqlcipher")]
+  #[test]
+  fn encrypt_database_test() {
+    use diesel::sql_types::Integer;
+
+    use crate::sqlite_impl::conn_ext::ConnectionExtension;
+
+    let tempdir = TempDir::new().unwrap();
+    let dir = tempdir.path().to_str().unwrap();
+    let path = create_database(dir, "test.db");
+    assert!(encrypt_database(dir, "test.db", &KEY).unwrap());
+    assert!(!encrypt_database(dir, "test.db", &KEY).unwrap());
+    assert!(!is_plaintext_database(&path).unwrap());
+
+    let mut conn = SqliteConnection::establish(&path).unwrap();
+    assert!(conn.pragma_set_key(&hex_key(&[8; 32])).is_err());
+
+    let mut conn = SqliteConnection::establish(&path).unwrap();
+    conn.pragma_set_key(&hex_key(&KEY)).unwrap();
+    let id = conn.query::<Integer, i32>("SELECT id FROM test").unwrap();
+    assert_eq!(id, 42);
+  }
+}
@@ -8,6 +8,8 @@ pub enum Error {
   Diesel(#[from] diesel::result::Error),
   #[error("diesel connect error: {0}")]
   Connect(#[from] diesel::ConnectionError),
+  #[error("io error: {0}")]
+  Io(#[from] std::io::Error),
   #[error("internal error: {0}")]
   Internal(#[from] anyhow::Error),
 }
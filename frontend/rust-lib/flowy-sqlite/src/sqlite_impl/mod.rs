@@ -1,11 +1,13 @@
 mod conn_ext;
 mod database;
+mod encryption;
 #[allow(deprecated, clippy::large_enum_variant)]
 mod errors;
 mod pool;
 mod pragma;
 
 pub use database::*;
+pub use encryption::*;
 pub use pool::*;
 
 pub use errors::Error;
@@ -4,7 +4,7 @@ use diesel::{connection::Connection, r2d2::R2D2Connection, SqliteConnection};
 use r2d2::{CustomizeConnection, ManageConnection, Pool};
 use scheduled_thread_pool::ScheduledThreadPool;
 
-use crate::sqlite_impl::{errors::*, pragma::*};
+use crate::sqlite_impl::{encryption::*, errors::*, pragma::*};
 
 pub struct ConnectionPool {
   pub(crate) inner: Pool<ConnectionManager>,
@@ -31,7 +31,10 @@ impl ConnectionPool {
         .build(),
     );
     let config = Arc::new(config);
-    let customizer_config = DatabaseCustomizerConfig::default();
+    let customizer_config = DatabaseCustomizerConfig {
+      key: config.key.clone(),
+      ..Default::default()
+    };
 
     let pool = r2d2::Pool::builder()
       .thread_pool(thread_pool)
@@ -54,6 +57,7 @@ pub struct PoolConfig {
   max_size: u32,
   connection_timeout: Duration,
   idle_timeout: Duration,
+  key: Option<String>,
 }
 
 impl Default for PoolConfig {
@@ -63,6 +67,7 @@ impl Default for PoolConfig {
       max_size: 10,
       connection_timeout: Duration::from_secs(10),
       idle_timeout: Duration::from_secs(5 * 60),
+      key: None,
     }
   }
 }
@@ -79,6 +84,14 @@ impl PoolConfig {
     self.max_size = max_size;
     self
   }
+
+  /// Opens the database encrypted with the raw `key` by SQLCipher. The connections fail unless
+  /// the crate is built with the `sqlcipher` feature. See [encrypt_database] to encrypt an
+  /// existing database.
+  pub fn encryption_key(mut self, key: &[u8]) -> Self {
+    self.key = Some(hex_key(key));
+    self
+  }
 }
 
 pub struct ConnectionManager {
@@ -108,13 +121,25 @@ impl ConnectionManager {
   }
 }
 
-#[derive(Debug)]
 pub struct DatabaseCustomizerConfig {
   pub(crate) journal_mode: SQLiteJournalMode,
   pub(crate) synchronous: SQLiteSynchronous,
   pub(crate) busy_timeout: i32,
   #[allow(dead_code)]
   pub(crate) secure_delete: bool,
+  pub(crate) key: Option<String>,
+}
+
+impl std::fmt::Debug for DatabaseCustomizerConfig {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("DatabaseCustomizerConfig")
+      .field("journal_mode", &self.journal_mode)
+      .field("synchronous", &self.synchronous)
+      .field("busy_timeout", &self.busy_timeout)
+      .field("secure_delete", &self.secure_delete)
+      .field("encrypted", &self.key.is_some())
+      .finish()
+  }
 }
 
 impl Default for DatabaseCustomizerConfig {
@@ -124,6 +149,7 @@ impl Default for DatabaseCustomizerConfig {
       synchronous: SQLiteSynchronous::NORMAL,
       busy_timeout: 5000,
       secure_delete: true,
+      key: None,
     }
   }
 }
@@ -144,6 +170,10 @@ impl DatabaseCustomizer {
 
 impl CustomizeConnection<SqliteConnection, crate::sqlite_impl::Error> for DatabaseCustomizer {
   fn on_acquire(&self, conn: &mut SqliteConnection) -> Result<()> {
+    // The key must be set before anything is read from the database
+    if let Some(key) = &self.config.key {
+      conn.pragma_set_key(key)?;
+    }
     conn.pragma_set_busy_timeout(self.config.busy_timeout)?;
     if self.config.journal_mode != SQLiteJournalMode::WAL {
       conn.pragma_set_journal_mode(self.config.journal_mode, None)?;
@@ -64,6 +64,28 @@ pub trait PragmaExtension: ConnectionExtension {
     self.query::<ST, T>(&query)
   }
 
+  /// Sets the SQLCipher key, given as hex, of the connection. Fails if SQLCipher isn't linked,
+  /// as SQLite ignores the unknown pragmas and would write the data in plaintext, or if the key
+  /// doesn't decrypt the database.
+  fn pragma_set_key(&mut self, hex_key: &str) -> Result<()> {
+    self.exec(format!("PRAGMA key = \"x'{}'\"", hex_key))?;
+    self.pragma_check_cipher()?;
+    self.query::<Integer, i32>("SELECT count(*) FROM sqlite_master")?;
+    Ok(())
+  }
+
+  fn pragma_get_cipher_version(&mut self) -> Result<String> {
+    self.pragma_get::<Text, String>("cipher_version", None)
+  }
+
+  /// Fails unless SQLCipher is linked.
+  fn pragma_check_cipher(&mut self) -> Result<()> {
+    match self.pragma_get_cipher_version() {
+      Ok(_) => Ok(()),
+      Err(_) => Err(anyhow!("SQLCipher is not available, enable the sqlcipher feature").into()),
+    }
+  }
+
   fn pragma_set_busy_timeout(&mut self, timeout_ms: i32) -> Result<i32> {
     self.pragma_ret::<Integer, i32, i32>("busy_timeout", timeout_ms, None)
   }
@@ -0,0 +1,379 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use diesel::{ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+use flowy_error::{internal_error, FlowyError, FlowyResult};
+use flowy_sqlite::migration::ModuleMigrations;
+use flowy_sqlite::{DBConnection, Database, PoolConfig};
+use futures_util::stream::{self, Stream};
+use lib_dispatch::metrics::{Counter, Gauge, MetricsRegistry};
+use lib_dispatch::prelude::AFPluginDispatcher;
+use lib_infra::util::timestamp;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncReadExt;
+use tracing::{trace, warn};
+
+const DB_NAME: &str = "attachment.db";
+const ATTACHMENT_DIR: &str = "attachments";
+
+/// The total size, in bytes, of the attachments kept on disk.
+pub const ATTACHMENT_BYTES: &str = "attachment_bytes";
+/// The number of attachments kept on disk.
+pub const ATTACHMENT_COUNT: &str = "attachment_count";
+/// The bytes written to disk, the attachments already stored are not written again.
+pub const ATTACHMENT_WRITTEN_BYTES_TOTAL: &str = "attachment_written_bytes_total";
+/// The bytes freed by the garbage collection.
+pub const ATTACHMENT_COLLECTED_BYTES_TOTAL: &str = "attachment_collected_bytes_total";
+
+/// The size of the chunks of [AttachmentStore::read_chunks].
+pub const ATTACHMENT_CHUNK_SIZE: usize = 64 * 1024;
+
+const CREATE_ATTACHMENT_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS attachment_table (
+  id TEXT NOT NULL PRIMARY KEY,
+  size BIGINT NOT NULL,
+  mime TEXT NOT NULL,
+  created_at BIGINT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS attachment_ref_table (
+  attachment_id TEXT NOT NULL,
+  owner_id TEXT NOT NULL,
+  created_at BIGINT NOT NULL,
+  PRIMARY KEY (attachment_id, owner_id)
+);
+"#;
+
+diesel::table! {
+    attachment_table (id) {
+        id -> Text,
+        size -> BigInt,
+        mime -> Text,
+        created_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    attachment_ref_table (attachment_id, owner_id) {
+        attachment_id -> Text,
+        owner_id -> Text,
+        created_at -> BigInt,
+    }
+}
+
+/// A file stored by [AttachmentStore::store].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+  /// The SHA-256 of the content, in hex.
+  pub id: String,
+  pub size: i64,
+  pub mime: String,
+  pub path: PathBuf,
+  pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AttachmentUsage {
+  pub count: i64,
+  pub total_bytes: i64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GarbageCollection {
+  pub removed: i64,
+  pub freed_bytes: i64,
+}
+
+struct AttachmentMetrics {
+  bytes: Arc<Gauge>,
+  count: Arc<Gauge>,
+  written_bytes: Arc<Counter>,
+  collected_bytes: Arc<Counter>,
+}
+
+impl AttachmentMetrics {
+  fn new(registry: &MetricsRegistry) -> Self {
+    Self {
+      bytes: registry.gauge(ATTACHMENT_BYTES, &[]),
+      count: registry.gauge(ATTACHMENT_COUNT, &[]),
+      written_bytes: registry.counter(ATTACHMENT_WRITTEN_BYTES_TOTAL, &[]),
+      collected_bytes: registry.counter(ATTACHMENT_COLLECTED_BYTES_TOTAL, &[]),
+    }
+  }
+
+  fn set_usage(&self, usage: &AttachmentUsage) {
+    self.bytes.set(usage.total_bytes);
+    self.count.set(usage.count);
+  }
+}
+
+/// Keeps the files attached to the documents, e.g. the pasted images, on disk. The files are
+/// content-addressed: the same content is stored once whatever the number of documents it's
+/// attached to.
+///
+/// Each document holding an attachment is recorded as one of its owners. An attachment is
+/// removed by [AttachmentStore::collect_garbage] once all its owners released it.
+///
+/// The store is registered in the dispatcher as app data with [AttachmentStore::register].
+#[derive(Clone)]
+pub struct AttachmentStore {
+  database: Database,
+  dir: PathBuf,
+  /// Serializes the writes, so two stores of the same content don't write the same file.
+  write_lock: Arc<Mutex<()>>,
+  metrics: Arc<AttachmentMetrics>,
+}
+
+impl AttachmentStore {
+  pub fn new(root: &str, metrics: Arc<MetricsRegistry>) -> FlowyResult<Self> {
+    if !Path::new(root).exists() {
+      return Err(FlowyError::internal().with_context(format!("{} not exists", root)));
+    }
+    let dir = Path::new(root).join(ATTACHMENT_DIR);
+    fs::create_dir_all(&dir)?;
+    let database = Database::new(root, DB_NAME, PoolConfig::default()).map_err(internal_error)?;
+    let mut conn = database.get_connection().map_err(internal_error)?;
+    migrations().run(&mut *conn)?;
+
+    let store = Self {
+      database,
+      dir,
+      write_lock: Default::default(),
+      metrics: Arc::new(AttachmentMetrics::new(&metrics)),
+    };
+    store.metrics.set_usage(&store.usage()?);
+    Ok(store)
+  }
+
+  /// Registers the store in `dispatcher` as app data.
+  pub fn register(&self, dispatcher: AFPluginDispatcher) -> AFPluginDispatcher {
+    dispatcher.data(self.clone())
+  }
+
+  /// Stores `data` and records `owner_id` as one of its owners. `file_name` is only used to
+  /// guess the mime type.
+  pub fn store(&self, owner_id: &str, file_name: &str, data: &[u8]) -> FlowyResult<Attachment> {
+    let id = format!("{:x}", Sha256::digest(data));
+    let _guard = self.write_lock.lock().unwrap();
+    let mut conn = self.connection()?;
+    if self.select(&mut conn, &id)?.is_none() {
+      let path = self.path(&id);
+      if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+      }
+      // The file is only visible once it's complete
+      let temp_path = path.with_extension("tmp");
+      let mut file = fs::File::create(&temp_path)?;
+      file.write_all(data)?;
+      file.sync_all()?;
+      fs::rename(&temp_path, &path)?;
+
+      let mime = mime_guess::from_path(file_name)
+        .first_or_octet_stream()
+        .to_string();
+      diesel::insert_into(attachment_table::table)
+        .values((
+          attachment_table::id.eq(&id),
+          attachment_table::size.eq(data.len() as i64),
+          attachment_table::mime.eq(mime),
+          attachment_table::created_at.eq(timestamp()),
+        ))
+        .execute(&mut *conn)?;
+      self.metrics.written_bytes.inc_by(data.len() as u64);
+      self
+        .metrics
+        .bytes
+        .set(self.metrics.bytes.get() + data.len() as i64);
+      self.metrics.count.inc();
+    }
+
+    diesel::replace_into(attachment_ref_table::table)
+      .values((
+        attachment_ref_table::attachment_id.eq(&id),
+        attachment_ref_table::owner_id.eq(owner_id),
+        attachment_ref_table::created_at.eq(timestamp()),
+      ))
+      .execute(&mut *conn)?;
+    self.get(&id)
+  }
+
+  /// Like [AttachmentStore::store], with the content of the file at `path`.
+  pub fn store_file(&self, owner_id: &str, path: &Path) -> FlowyResult<Attachment> {
+    let data = fs::read(path)?;
+    let file_name = path
+      .file_name()
+      .and_then(|name| name.to_str())
+      .unwrap_or_default();
+    self.store(owner_id, file_name, &data)
+  }
+
+  pub fn get(&self, id: &str) -> FlowyResult<Attachment> {
+    let mut conn = self.connection()?;
+    self
+      .select(&mut conn, id)?
+      .ok_or_else(|| FlowyError::record_not_found().with_context(format!("No attachment {}", id)))
+  }
+
+  /// Reads the content of the attachment in chunks of [ATTACHMENT_CHUNK_SIZE], so a large file
+  /// doesn't have to be loaded in memory at once.
+  pub fn read_chunks(&self, id: &str) -> FlowyResult<impl Stream<Item = FlowyResult<Vec<u8>>>> {
+    let attachment = self.get(id)?;
+    Ok(stream::unfold(
+      ChunkReader::Closed(attachment.path),
+      |reader| async move {
+        let mut file = match reader {
+          ChunkReader::Closed(path) => match tokio::fs::File::open(&path).await {
+            Ok(file) => file,
+            Err(err) => return Some((Err(err.into()), ChunkReader::Done)),
+          },
+          ChunkReader::Open(file) => file,
+          ChunkReader::Done => return None,
+        };
+        let mut chunk = vec![0; ATTACHMENT_CHUNK_SIZE];
+        match file.read(&mut chunk).await {
+          Ok(0) => None,
+          Ok(len) => {
+            chunk.truncate(len);
+            Some((Ok(chunk), ChunkReader::Open(file)))
+          },
+          Err(err) => Some((Err(err.into()), ChunkReader::Done)),
+        }
+      },
+    ))
+  }
+
+  /// Removes `owner_id` from the owners of the attachment.
+  pub fn release(&self, owner_id: &str, attachment_id: &str) -> FlowyResult<()> {
+    let mut conn = self.connection()?;
+    diesel::delete(
+      attachment_ref_table::table
+        .filter(attachment_ref_table::attachment_id.eq(attachment_id))
+        .filter(attachment_ref_table::owner_id.eq(owner_id)),
+    )
+    .execute(&mut *conn)?;
+    Ok(())
+  }
+
+  /// Removes `owner_id` from the owners of all its attachments, e.g. once the document is
+  /// deleted.
+  pub fn release_owner(&self, owner_id: &str) -> FlowyResult<()> {
+    let mut conn = self.connection()?;
+    diesel::delete(attachment_ref_table::table.filter(attachment_ref_table::owner_id.eq(owner_id)))
+      .execute(&mut *conn)?;
+    Ok(())
+  }
+
+  /// Removes the attachments without owner, and the files left on disk without attachment, e.g.
+  /// when the app stopped in the middle of a store.
+  pub fn collect_garbage(&self) -> FlowyResult<GarbageCollection> {
+    let _guard = self.write_lock.lock().unwrap();
+    let mut conn = self.connection()?;
+    let owned = attachment_ref_table::table
+      .select(attachment_ref_table::attachment_id)
+      .distinct()
+      .load::<String>(&mut *conn)?
+      .into_iter()
+      .collect::<HashSet<_>>();
+    let attachments = attachment_table::table
+      .select((attachment_table::id, attachment_table::size))
+      .load::<(String, i64)>(&mut *conn)?;
+
+    let mut collection = GarbageCollection::default();
+    let mut kept = HashSet::new();
+    for (id, size) in attachments {
+      if owned.contains(&id) {
+        kept.insert(id);
+        continue;
+      }
+      diesel::delete(attachment_table::table.filter(attachment_table::id.eq(&id)))
+        .execute(&mut *conn)?;
+      if let Err(err) = fs::remove_file(self.path(&id)) {
+        warn!("[Attachment]: failed to remove {}: {}", id, err);
+      }
+      collection.removed += 1;
+      collection.freed_bytes += size;
+    }
+
+    // The files without attachment
+    for entry in fs::read_dir(&self.dir)?.flatten() {
+      if !entry.path().is_dir() {
+        continue;
+      }
+      for file in fs::read_dir(entry.path())?.flatten() {
+        let name = file.file_name().to_string_lossy().to_string();
+        if !kept.contains(&name) {
+          let size = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+          if fs::remove_file(file.path()).is_ok() {
+            collection.freed_bytes += size as i64;
+          }
+        }
+      }
+    }
+
+    trace!(
+      "[Attachment]: removed {} attachments, freed {} bytes",
+      collection.removed,
+      collection.freed_bytes
+    );
+    self
+      .metrics
+      .collected_bytes
+      .inc_by(collection.freed_bytes as u64);
+    self.metrics.set_usage(&self.usage()?);
+    Ok(collection)
+  }
+
+  pub fn usage(&self) -> FlowyResult<AttachmentUsage> {
+    let mut conn = self.connection()?;
+    let sizes = attachment_table::table
+      .select(attachment_table::size)
+      .load::<i64>(&mut *conn)?;
+    Ok(AttachmentUsage {
+      count: sizes.len() as i64,
+      total_bytes: sizes.iter().sum(),
+    })
+  }
+
+  /// The attachments are spread in sub directories named after the first two characters of
+  /// their id, to keep the directories small.
+  fn path(&self, id: &str) -> PathBuf {
+    self.dir.join(&id[..2]).join(id)
+  }
+
+  fn select(&self, conn: &mut DBConnection, id: &str) -> FlowyResult<Option<Attachment>> {
+    let row = attachment_table::table
+      .filter(attachment_table::id.eq(id))
+      .select((
+        attachment_table::id,
+        attachment_table::size,
+        attachment_table::mime,
+        attachment_table::created_at,
+      ))
+      .first::<(String, i64, String, i64)>(&mut **conn)
+      .optional()?;
+    Ok(row.map(|(id, size, mime, created_at)| Attachment {
+      path: self.path(&id),
+      id,
+      size,
+      mime,
+      created_at,
+    }))
+  }
+
+  fn connection(&self) -> FlowyResult<DBConnection> {
+    self.database.get_connection().map_err(internal_error)
+  }
+}
+
+enum ChunkReader {
+  Closed(PathBuf),
+  Open(tokio::fs::File),
+  Done,
+}
+
+/// The schema of the store. Append the new steps, never change the applied ones.
+fn migrations() -> ModuleMigrations {
+  ModuleMigrations::new("attachment").sql(1, "create_attachment_tables", CREATE_ATTACHMENT_SQL)
+}
@@ -1,5 +1,7 @@
 use flowy_derive::ProtoBuf;
 
+use crate::attachment::{Attachment, AttachmentUsage, GarbageCollection};
+
 #[derive(Default, ProtoBuf, Clone, Debug)]
 pub struct RegisterStreamPB {
   #[pb(index = 1)]
@@ -20,3 +22,114 @@ pub struct FileStatePB {
   #[pb(index = 2)]
   pub is_finish: bool,
 }
+
+#[derive(Default, ProtoBuf, Clone, Debug)]
+pub struct StoreAttachmentPB {
+  /// The object the attachment is attached to, e.g. the id of the document.
+  #[pb(index = 1)]
+  pub owner_id: String,
+
+  /// Used to guess the mime type of the attachment.
+  #[pb(index = 2)]
+  pub file_name: String,
+
+  #[pb(index = 3)]
+  pub data: Vec<u8>,
+
+  /// Stores the content of the file instead of the `data` if set.
+  #[pb(index = 4, one_of)]
+  pub local_file_path: Option<String>,
+}
+
+#[derive(Default, ProtoBuf, Clone, Debug)]
+pub struct AttachmentPB {
+  #[pb(index = 1)]
+  pub id: String,
+
+  #[pb(index = 2)]
+  pub size: i64,
+
+  #[pb(index = 3)]
+  pub mime: String,
+
+  #[pb(index = 4)]
+  pub local_file_path: String,
+
+  #[pb(index = 5)]
+  pub created_at: i64,
+}
+
+impl From<Attachment> for AttachmentPB {
+  fn from(attachment: Attachment) -> Self {
+    Self {
+      id: attachment.id,
+      size: attachment.size,
+      mime: attachment.mime,
+      local_file_path: attachment.path.to_string_lossy().to_string(),
+      created_at: attachment.created_at,
+    }
+  }
+}
+
+#[derive(Default, ProtoBuf, Clone, Debug)]
+pub struct AttachmentIdPB {
+  #[pb(index = 1)]
+  pub id: String,
+}
+
+/// Streams the content of the attachment to the Dart port. Each message is a chunk of the
+/// content, an empty chunk ends the stream.
+#[derive(Default, ProtoBuf, Clone, Debug)]
+pub struct StreamAttachmentPB {
+  #[pb(index = 1)]
+  pub id: String,
+
+  #[pb(index = 2)]
+  pub port: i64,
+}
+
+#[derive(Default, ProtoBuf, Clone, Debug)]
+pub struct ReleaseAttachmentPB {
+  #[pb(index = 1)]
+  pub owner_id: String,
+
+  /// Releases all the attachments of the owner if not set.
+  #[pb(index = 2, one_of)]
+  pub attachment_id: Option<String>,
+}
+
+#[derive(Default, ProtoBuf, Clone, Debug)]
+pub struct AttachmentUsagePB {
+  #[pb(index = 1)]
+  pub count: i64,
+
+  #[pb(index = 2)]
+  pub total_bytes: i64,
+}
+
+impl From<AttachmentUsage> for AttachmentUsagePB {
+  fn from(usage: AttachmentUsage) -> Self {
+    Self {
+      count: usage.count,
+      total_bytes: usage.total_bytes,
+    }
+  }
+}
+
+#[derive(Default, ProtoBuf, Clone, Debug)]
+pub struct AttachmentGarbageCollectionPB {
+  #[pb(index = 1)]
+  pub removed: i64,
+
+  #[pb(index = 2)]
+  pub freed_bytes: i64,
+}
+
+impl From<GarbageCollection> for AttachmentGarbageCollectionPB {
+  fn from(collection: GarbageCollection) -> Self {
+    Self {
+      removed: collection.removed,
+      freed_bytes: collection.freed_bytes,
+    }
+  }
+}
@@ -1,8 +1,17 @@
-use crate::entities::{FileStatePB, QueryFilePB, RegisterStreamPB};
+use crate::attachment::AttachmentStore;
+use crate::entities::{
+  AttachmentGarbageCollectionPB, AttachmentIdPB, AttachmentPB, AttachmentUsagePB, FileStatePB,
+  QueryFilePB, RegisterStreamPB, ReleaseAttachmentPB, StoreAttachmentPB, StreamAttachmentPB,
+};
 use crate::manager::StorageManager;
+use allo_isolate::Isolate;
 use flowy_error::{FlowyError, FlowyResult};
-use lib_dispatch::prelude::{data_result_ok, AFPluginData, AFPluginState, DataResult};
+use futures_util::StreamExt;
+use lib_dispatch::prelude::{data_result_ok, AFPluginData, AFPluginState, AppData, DataResult};
+use lib_infra::isolate_stream::{IsolateSink, SinkExt};
+use std::path::Path;
 use std::sync::{Arc, Weak};
+use tracing::error;
 
 fn upgrade_storage_manager(
   ai_manager: AFPluginState<Weak<StorageManager>>,
@@ -36,3 +45,81 @@ pub(crate) async fn query_file_handler(
   })?;
   data_result_ok(pb)
 }
+
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub(crate) async fn store_attachment_handler(
+  data: AFPluginData<StoreAttachmentPB>,
+  store: AppData<AttachmentStore>,
+) -> DataResult<AttachmentPB, FlowyError> {
+  let data = data.into_inner();
+  let attachment = match data.local_file_path {
+    Some(path) => store.store_file(&data.owner_id, Path::new(&path))?,
+    None => store.store(&data.owner_id, &data.file_name, &data.data)?,
+  };
+  data_result_ok(attachment.into())
+}
+
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub(crate) async fn get_attachment_handler(
+  data: AFPluginData<AttachmentIdPB>,
+  store: AppData<AttachmentStore>,
+) -> DataResult<AttachmentPB, FlowyError> {
+  let attachment = store.get(&data.into_inner().id)?;
+  data_result_ok(attachment.into())
+}
+
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub(crate) async fn stream_attachment_handler(
+  data: AFPluginData<StreamAttachmentPB>,
+  store: AppData<AttachmentStore>,
+) -> Result<(), FlowyError> {
+  let data = data.into_inner();
+  let mut chunks = Box::pin(store.read_chunks(&data.id)?);
+  let mut sink = IsolateSink::new(Isolate::new(data.port));
+  tokio::spawn(async move {
+    while let Some(chunk) = chunks.next().await {
+      match chunk {
+        Ok(chunk) => {
+          if let Err(err) = sink.send(chunk).await {
+            error!("[Attachment]: send chunk failed: {}", err);
+            return;
+          }
+        },
+        Err(err) => {
+          error!("[Attachment]: read {} failed: {}", data.id, err);
+          break;
+        },
+      }
+    }
+    let _ = sink.send(Vec::<u8>::new()).await;
+  });
+  Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub(crate) async fn release_attachment_handler(
+  data: AFPluginData<ReleaseAttachmentPB>,
+  store: AppData<AttachmentStore>,
+) -> Result<(), FlowyError> {
+  let data = data.into_inner();
+  match data.attachment_id {
+    Some(attachment_id) => store.release(&data.owner_id, &attachment_id),
+    None => store.release_owner(&data.owner_id),
+  }
+}
+
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub(crate) async fn collect_attachment_garbage_handler(
+  store: AppData<AttachmentStore>,
+) -> DataResult<AttachmentGarbageCollectionPB, FlowyError> {
+  let collection = store.collect_garbage()?;
+  data_result_ok(collection.into())
+}
+
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub(crate) async fn get_attachment_usage_handler(
+  store: AppData<AttachmentStore>,
+) -> DataResult<AttachmentUsagePB, FlowyError> {
+  let usage = store.usage()?;
+  data_result_ok(usage.into())
+}
@@ -1,16 +1,42 @@
-use crate::event_handler::{query_file_handler, register_stream_handler};
+use crate::event_handler::*;
 use crate::manager::StorageManager;
 use flowy_derive::{Flowy_Event, ProtoBuf_Enum};
 use lib_dispatch::prelude::*;
 use std::sync::Weak;
 use strum_macros::Display;
 
+/// The attachment handlers read the [AttachmentStore](crate::attachment::AttachmentStore)
+/// registered in the dispatcher with
+/// [AttachmentStore::register](crate::attachment::AttachmentStore::register).
 pub fn init(manager: Weak<StorageManager>) -> AFPlugin {
   AFPlugin::new()
     .name("file-storage")
+    .proto_descriptors(crate::protobuf::file_descriptors())
     .state(manager)
     .event(FileStorageEvent::RegisterStream, register_stream_handler)
     .event(FileStorageEvent::QueryFile, query_file_handler)
+    .event(FileStorageEvent::StoreAttachment, store_attachment_handler)
+    .event(FileStorageEvent::GetAttachment, get_attachment_handler)
+    .event(
+      FileStorageEvent::StreamAttachment,
+      stream_attachment_handler,
+    )
+    .event(
+      FileStorageEvent::ReleaseAttachment,
+      release_attachment_handler,
+    )
+    .event(
+      FileStorageEvent::CollectAttachmentGarbage,
+      collect_attachment_garbage_handler,
+    )
+    .event(
+      FileStorageEvent::GetAttachmentUsage,
+      get_attachment_usage_handler,
+    )
+    // Recorded in the audit log
+    .mutating(FileStorageEvent::StoreAttachment)
+    .mutating(FileStorageEvent::ReleaseAttachment)
+    .mutating(FileStorageEvent::CollectAttachmentGarbage)
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Display, Hash, ProtoBuf_Enum, Flowy_Event)]
@@ -22,4 +48,23 @@ pub enum FileStorageEvent {
 
   #[event(input = "QueryFilePB", output = "FileStatePB")]
   QueryFile = 1,
+
+  #[event(input = "StoreAttachmentPB", output = "AttachmentPB")]
+  StoreAttachment = 2,
+
+  #[event(input = "AttachmentIdPB", output = "AttachmentPB")]
+  GetAttachment = 3,
+
+  #[event(input = "StreamAttachmentPB")]
+  StreamAttachment = 4,
+
+  #[event(input = "ReleaseAttachmentPB")]
+  ReleaseAttachment = 5,
+
+  /// Removes the attachments released by all their owners.
+  #[event(output = "AttachmentGarbageCollectionPB")]
+  CollectAttachmentGarbage = 6,
+
+  #[event(output = "AttachmentUsagePB")]
+  GetAttachmentUsage = 7,
 }
@@ -1,3 +1,4 @@
+pub mod attachment;
 mod entities;
 mod event_handler;
 pub mod event_map;
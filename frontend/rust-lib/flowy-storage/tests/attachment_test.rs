@@ -0,0 +1,94 @@
+use std::sync::Arc;
+
+use flowy_storage::attachment::{
+  AttachmentStore, ATTACHMENT_BYTES, ATTACHMENT_CHUNK_SIZE, ATTACHMENT_COLLECTED_BYTES_TOTAL,
+  ATTACHMENT_COUNT, ATTACHMENT_WRITTEN_BYTES_TOTAL,
+};
+use futures_util::StreamExt;
+use lib_dispatch::metrics::MetricsRegistry;
+use tempfile::TempDir;
+
+fn test_store() -> (AttachmentStore, Arc<MetricsRegistry>, TempDir) {
+  let tempdir = TempDir::new().unwrap();
+  let metrics = Arc::new(MetricsRegistry::new());
+  let store = AttachmentStore::new(tempdir.path().to_str().unwrap(), metrics.clone()).unwrap();
+  (store, metrics, tempdir)
+}
+
+#[tokio::test]
+async fn attachment_dedup_test() {
+  let (store, metrics, _tempdir) = test_store();
+  let data = b"image content".to_vec();
+  let first = store.store("doc_1", "image.png", &data).unwrap();
+  let second = store.store("doc_2", "copy.png", &data).unwrap();
+  assert_eq!(first, second);
+  assert_eq!(first.mime, "image/png");
+  assert_eq!(first.size, data.len() as i64);
+  assert_eq!(std::fs::read(&first.path).unwrap(), data);
+
+  let usage = store.usage().unwrap();
+  assert_eq!(usage.count, 1);
+  assert_eq!(usage.total_bytes, data.len() as i64);
+  assert_eq!(metrics.gauge(ATTACHMENT_COUNT, &[]).get(), 1);
+  assert_eq!(
+    metrics.gauge(ATTACHMENT_BYTES, &[]).get(),
+    data.len() as i64
+  );
+  assert_eq!(
+    metrics.counter(ATTACHMENT_WRITTEN_BYTES_TOTAL, &[]).get(),
+    data.len() as u64
+  );
+}
+
+#[tokio::test]
+async fn attachment_read_chunks_test() {
+  let (store, _, _tempdir) = test_store();
+  let data = (0..ATTACHMENT_CHUNK_SIZE * 2 + 10)
+    .map(|i| (i % 251) as u8)
+    .collect::<Vec<_>>();
+  let attachment = store.store("doc_1", "file.bin", &data).unwrap();
+
+  let chunks = store
+    .read_chunks(&attachment.id)
+    .unwrap()
+    .collect::<Vec<_>>()
+    .await
+    .into_iter()
+    .collect::<Result<Vec<_>, _>>()
+    .unwrap();
+  assert_eq!(chunks.len(), 3);
+  assert_eq!(chunks.concat(), data);
+
+  assert!(store.get("unknown").is_err());
+  assert!(store.read_chunks("unknown").is_err());
+}
+
+#[tokio::test]
+async fn attachment_garbage_collection_test() {
+  let (store, metrics, _tempdir) = test_store();
+  let shared = store.store("doc_1", "shared.png", b"shared").unwrap();
+  store.store("doc_2", "shared.png", b"shared").unwrap();
+  let owned = store.store("doc_1", "owned.png", b"owned").unwrap();
+
+  // The shared attachment is still owned by doc_2
+  store.release_owner("doc_1").unwrap();
+  let collection = store.collect_garbage().unwrap();
+  assert_eq!(collection.removed, 1);
+  assert_eq!(collection.freed_bytes, owned.size);
+  assert!(store.get(&owned.id).is_err());
+  assert!(!owned.path.exists());
+  assert!(shared.path.exists());
+
+  store.release("doc_2", &shared.id).unwrap();
+  let collection = store.collect_garbage().unwrap();
+  assert_eq!(collection.removed, 1);
+  assert!(!shared.path.exists());
+
+  assert_eq!(store.usage().unwrap().count, 0);
+  assert_eq!(metrics.gauge(ATTACHMENT_COUNT, &[]).get(), 0);
+  assert_eq!(metrics.gauge(ATTACHMENT_BYTES, &[]).get(), 0);
+  assert_eq!(
+    metrics.counter(ATTACHMENT_COLLECTED_BYTES_TOTAL, &[]).get(),
+    (shared.size + owned.size) as u64
+  );
+}
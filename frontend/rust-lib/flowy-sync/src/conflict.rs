@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use flowy_error::{FlowyError, FlowyResult};
+use tokio::sync::mpsc;
+
+use crate::engine::SyncMessage;
+use crate::entities::SyncConflictPB;
+use crate::notification::{send_notification, SyncNotification};
+use crate::offline::Outbox;
+
+/// How a conflict between a local mutation and a remote one is settled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution {
+  /// The remote mutation is not applied.
+  KeepLocal,
+  /// The remote mutation is applied over the local one, and sent again by this device so the
+  /// devices that received the local one after it converge.
+  KeepRemote,
+  /// The mutation is applied instead of both, and sent to the other devices.
+  Merge(SyncMessage),
+  /// The remote mutation is held until the user picks one of them. The client is told with the
+  /// `DidReceiveSyncConflict` notification and answers with the `ResolveSyncConflict` event.
+  Prompt,
+}
+
+/// Implemented by the modules for the events whose mutations can collide, e.g. two devices
+/// renaming the same view, and registered with
+/// [SyncEngine::resolve](crate::SyncEngine::resolve).
+///
+/// A mutation queued while the device was offline collides with the remote mutations of the
+/// same object received while it's replayed. The mutations of the events without a resolver
+/// never collide: both are applied, in the order the server received them.
+pub trait ConflictResolver: Send + Sync + 'static {
+  /// The object the mutation changes, e.g. the id of the view. `None` if it never collides.
+  fn object_id(&self, message: &SyncMessage) -> Option<String>;
+
+  /// The last write wins by default.
+  fn resolve(&self, local: &SyncMessage, remote: &SyncMessage) -> Resolution {
+    last_write_wins(local, remote)
+  }
+}
+
+/// Keeps the latest mutation, or the one of the greatest origin if both were made at the same
+/// time.
+pub fn last_write_wins(local: &SyncMessage, remote: &SyncMessage) -> Resolution {
+  if (local.created_at, &local.origin) >= (remote.created_at, &remote.origin) {
+    Resolution::KeepLocal
+  } else {
+    Resolution::KeepRemote
+  }
+}
+
+/// A conflict the user is prompted for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncConflict {
+  pub id: String,
+  pub object_id: String,
+  pub local: SyncMessage,
+  pub remote: SyncMessage,
+}
+
+/// The conflicts waiting for the user, registered in the dispatcher as app data by
+/// [SyncEngine::register](crate::SyncEngine::register).
+#[derive(Clone, Default)]
+pub struct SyncConflicts {
+  conflicts: Arc<Mutex<HashMap<String, SyncConflict>>>,
+  sink: Option<ConflictSink>,
+}
+
+/// Where the settled mutations go: the outbox of the engine for the ones sent again, and the
+/// remote changes for the ones applied on this device.
+#[derive(Clone)]
+struct ConflictSink {
+  origin: String,
+  outbox: Outbox,
+  remote_changes: mpsc::UnboundedSender<SyncMessage>,
+}
+
+impl SyncConflicts {
+  pub(crate) fn new(
+    origin: &str,
+    outbox: Outbox,
+    remote_changes: mpsc::UnboundedSender<SyncMessage>,
+  ) -> Self {
+    Self {
+      conflicts: Default::default(),
+      sink: Some(ConflictSink {
+        origin: origin.to_owned(),
+        outbox,
+        remote_changes,
+      }),
+    }
+  }
+
+  /// The conflicts waiting for the user, the oldest remote mutation first.
+  pub fn list(&self) -> Vec<SyncConflict> {
+    let mut conflicts = self
+      .conflicts
+      .lock()
+      .unwrap()
+      .values()
+      .cloned()
+      .collect::<Vec<_>>();
+    conflicts.sort_by_key(|conflict| conflict.remote.created_at);
+    conflicts
+  }
+
+  /// Settles the conflict with the mutation the user picked.
+  pub fn resolve(&self, conflict_id: &str, keep_local: bool) -> FlowyResult<()> {
+    let conflict = self
+      .conflicts
+      .lock()
+      .unwrap()
+      .remove(conflict_id)
+      .ok_or_else(|| {
+        FlowyError::record_not_found().with_context(format!("No sync conflict {}", conflict_id))
+      })?;
+    let resolution = if keep_local {
+      Resolution::KeepLocal
+    } else {
+      Resolution::KeepRemote
+    };
+    self.settle(
+      conflict.object_id,
+      conflict.local,
+      conflict.remote,
+      resolution,
+    );
+    Ok(())
+  }
+
+  pub(crate) fn settle(
+    &self,
+    object_id: String,
+    local: SyncMessage,
+    remote: SyncMessage,
+    resolution: Resolution,
+  ) {
+    let sink = match &self.sink {
+      Some(sink) => sink,
+      None => return,
+    };
+    match resolution {
+      Resolution::KeepLocal => {},
+      Resolution::KeepRemote => {
+        sink.outbox.push(&remote.reissue(&sink.origin));
+        let _ = sink.remote_changes.send(remote);
+      },
+      Resolution::Merge(merged) => {
+        let merged = merged.reissue(&sink.origin);
+        sink.outbox.push(&merged);
+        let _ = sink.remote_changes.send(merged);
+      },
+      Resolution::Prompt => {
+        let conflict = SyncConflict {
+          id: uuid::Uuid::new_v4().to_string(),
+          object_id,
+          local,
+          remote,
+        };
+        send_notification(SyncNotification::DidReceiveSyncConflict)
+          .payload(SyncConflictPB::from(&conflict))
+          .send();
+        self
+          .conflicts
+          .lock()
+          .unwrap()
+          .insert(conflict.id.clone(), conflict);
+      },
+    }
+  }
+}
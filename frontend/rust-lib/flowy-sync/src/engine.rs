@@ -0,0 +1,689 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use futures_util::stream::SplitSink;
+use futures_util::{SinkExt, StreamExt};
+use lib_dispatch::prelude::*;
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::time::{Interval, MissedTickBehavior};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use crate::conflict::{ConflictResolver, SyncConflicts};
+use crate::entities::SyncStatePB;
+use crate::notification::{send_notification, SyncNotification};
+use crate::offline::{MemoryOfflineStore, OfflineStore, Outbox};
+use crate::telemetry::Telemetry;
+
+type WebSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// The number of queued mutations read from the store at once.
+const SEND_BATCH_SIZE: usize = 64;
+
+/// The server the [SyncEngine] connects to.
+#[derive(Debug, Clone)]
+pub struct SyncConfig {
+  /// e.g. `wss://sync.appflowy.io/ws`.
+  pub url: String,
+  /// Sent in the first text message of each connection, like the clients of the
+  /// `WebSocketBridge` do.
+  pub token: String,
+  /// How long the engine waits before connecting again once the connection is lost. 5 seconds
+  /// by default.
+  pub reconnect_interval: Duration,
+  /// How long after reconnecting the remote mutations can collide with the mutations queued
+  /// while the device was offline, see [ConflictResolver]. 30 seconds by default.
+  pub replay_window: Duration,
+}
+
+impl SyncConfig {
+  pub fn new(url: &str, token: &str) -> Self {
+    Self {
+      url: url.to_owned(),
+      token: token.to_owned(),
+      reconnect_interval: Duration::from_secs(5),
+      replay_window: Duration::from_secs(30),
+    }
+  }
+}
+
+/// A mutation sent to or received from the server: the request of the event, as it was
+/// dispatched on the device it was made on. Sent as JSON in binary messages.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncMessage {
+  /// The id of the request.
+  pub id: String,
+  /// The device the mutation was made on.
+  pub origin: String,
+  pub event: String,
+  pub payload: Vec<u8>,
+  pub version: Option<u32>,
+  /// When the mutation was made, in milliseconds since the epoch.
+  #[serde(default)]
+  pub created_at: i64,
+}
+
+impl SyncMessage {
+  fn from_request(request: &AFPluginRequest, origin: &str) -> Self {
+    Self {
+      id: request.id.clone(),
+      origin: origin.to_owned(),
+      event: request.event.as_str().to_owned(),
+      payload: request.payload_bytes().to_vec(),
+      version: request.version,
+      created_at: now(),
+    }
+  }
+
+  /// The same mutation, made again by `origin` now.
+  pub(crate) fn reissue(&self, origin: &str) -> Self {
+    Self {
+      id: uuid::Uuid::new_v4().to_string(),
+      origin: origin.to_owned(),
+      created_at: now(),
+      ..self.clone()
+    }
+  }
+
+  /// The request applying the mutation on this device. It carries a [RemoteChange], so it's not
+  /// sent back to the server.
+  pub fn into_request(self) -> AFPluginRequest {
+    let mut request = AFPluginRequest::new(self.event.as_str()).payload(self.payload);
+    if let Some(version) = self.version {
+      request = request.version(version);
+    }
+    request.extensions().insert(RemoteChange {
+      origin: self.origin,
+    });
+    request
+  }
+}
+
+/// Attached to the requests that apply a mutation received from the server. The handlers read it
+/// with the `Extension<RemoteChange>` extractor, e.g. to skip the side effects that only the
+/// device of the mutation should run.
+#[derive(Debug, Clone)]
+pub struct RemoteChange {
+  pub origin: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+  Disconnected,
+  Connecting,
+  Connected,
+}
+
+/// The connection state of the engine, registered in the dispatcher as app data. Each change is
+/// sent with a `DidUpdateSyncState` notification.
+#[derive(Clone)]
+pub struct SyncState {
+  url: String,
+  state: Arc<RwLock<ConnectionState>>,
+}
+
+impl Default for SyncState {
+  /// The state when no sync server is configured.
+  fn default() -> Self {
+    Self::new("")
+  }
+}
+
+impl SyncState {
+  fn new(url: &str) -> Self {
+    Self {
+      url: url.to_owned(),
+      state: Arc::new(RwLock::new(ConnectionState::Disconnected)),
+    }
+  }
+
+  pub fn get(&self) -> ConnectionState {
+    *self.state.read().unwrap()
+  }
+
+  pub fn to_pb(&self) -> SyncStatePB {
+    SyncStatePB {
+      state: self.get().into(),
+      url: self.url.clone(),
+    }
+  }
+
+  fn set(&self, state: ConnectionState) {
+    let previous = std::mem::replace(&mut *self.state.write().unwrap(), state);
+    if previous != state {
+      send_notification(SyncNotification::DidUpdateSyncState)
+        .payload(self.to_pb())
+        .send();
+    }
+  }
+}
+
+/// Keeps the device in sync with the other devices of the user through a sync server.
+///
+/// The successful requests of the synced events are sent to the server, which forwards them to
+/// the other devices, and the requests received from the server are dispatched like the local
+/// ones:
+///
+/// ```ignore
+/// let engine = Arc::new(
+///   SyncEngine::new(SyncConfig::new("wss://sync.appflowy.io/ws", &token), &device_id)
+///     .sync(DocumentEvent::ApplyAction)
+///     .sync(FolderEvent::CreateView),
+/// );
+/// let dispatcher = engine.register(dispatcher);
+/// tokio::spawn({
+///   let engine = engine.clone();
+///   async move { engine.run().await }
+/// });
+/// ```
+///
+/// [SyncEngine::run] only does the IO, the received mutations are applied by
+/// [SyncEngine::apply_remote_changes] on the task that drives the dispatcher.
+///
+/// The mutations are queued in the [OfflineStore] until they are sent. The ones made while the
+/// device is offline are replayed once it reconnects, and the [ConflictResolver] of their event
+/// settles their collisions with the remote mutations.
+///
+/// The [Telemetry] given to the engine is uploaded on the same connection, see
+/// [SyncEngine::telemetry].
+pub struct SyncEngine {
+  config: SyncConfig,
+  origin: String,
+  events: Arc<HashSet<String>>,
+  resolvers: HashMap<String, Arc<dyn ConflictResolver>>,
+  state: SyncState,
+  outbox: Outbox,
+  conflicts: SyncConflicts,
+  telemetry: Option<Telemetry>,
+  running: AtomicBool,
+  remote_changes: mpsc::UnboundedSender<SyncMessage>,
+  remote_changes_rx: Mutex<Option<mpsc::UnboundedReceiver<SyncMessage>>>,
+}
+
+impl SyncEngine {
+  /// `origin` identifies the device, the server sends the mutations back to every device.
+  pub fn new(config: SyncConfig, origin: &str) -> Self {
+    let outbox = Outbox::new(Arc::new(MemoryOfflineStore::default()));
+    let (remote_changes, remote_changes_rx) = mpsc::unbounded_channel();
+    Self {
+      state: SyncState::new(&config.url),
+      config,
+      origin: origin.to_owned(),
+      events: Arc::new(HashSet::new()),
+      resolvers: HashMap::new(),
+      conflicts: SyncConflicts::new(origin, outbox.clone(), remote_changes.clone()),
+      outbox,
+      telemetry: None,
+      running: AtomicBool::new(false),
+      remote_changes,
+      remote_changes_rx: Mutex::new(Some(remote_changes_rx)),
+    }
+  }
+
+  /// Sends the successful requests of `event` to the server.
+  pub fn sync<E: Into<AFPluginEvent>>(mut self, event: E) -> Self {
+    Arc::make_mut(&mut self.events).insert(event.into().as_str().to_owned());
+    self
+  }
+
+  /// Settles the collisions of the mutations of `event` with `resolver`.
+  pub fn resolve<E, R>(mut self, event: E, resolver: R) -> Self
+  where
+    E: Into<AFPluginEvent>,
+    R: ConflictResolver,
+  {
+    let event = event.into().as_str().to_owned();
+    self.resolvers.insert(event, Arc::new(resolver));
+    self
+  }
+
+  /// Queues the mutations in `store` instead of in memory, so the ones made while the device is
+  /// offline are sent after a restart of the app.
+  pub fn offline_store(mut self, store: Arc<dyn OfflineStore>) -> Self {
+    self.outbox = Outbox::new(store);
+    self.conflicts = SyncConflicts::new(
+      &self.origin,
+      self.outbox.clone(),
+      self.remote_changes.clone(),
+    );
+    self
+  }
+
+  /// Uploads the counts of `telemetry` to the server, as a [SyncMessage] of the
+  /// [TELEMETRY_EVENT](crate::telemetry::TELEMETRY_EVENT), each time its interval elapses and
+  /// once connected. The counts that couldn't be sent are kept for the next upload.
+  pub fn telemetry(mut self, telemetry: Telemetry) -> Self {
+    self.telemetry = Some(telemetry);
+    self
+  }
+
+  /// Registers the middleware that records the mutations, and the [SyncState] and the
+  /// [SyncConflicts] as app data.
+  pub fn register(&self, dispatcher: AFPluginDispatcher) -> AFPluginDispatcher {
+    dispatcher
+      .with_middleware(SyncMiddleware {
+        origin: self.origin.clone(),
+        events: self.events.clone(),
+        outbox: self.outbox.clone(),
+      })
+      .data(self.state.clone())
+      .data(self.conflicts.clone())
+  }
+
+  pub fn state(&self) -> SyncState {
+    self.state.clone()
+  }
+
+  pub fn conflicts(&self) -> SyncConflicts {
+    self.conflicts.clone()
+  }
+
+  /// Connects to the server and sends the recorded mutations, reconnecting after the reconnect
+  /// interval each time the connection is lost. The mutations stay queued until they are sent.
+  /// Only returns if the engine is already running.
+  pub async fn run(&self) {
+    if self.running.swap(true, Ordering::SeqCst) {
+      tracing::warn!("[Sync]: the engine is already running");
+      return;
+    }
+    loop {
+      self.state.set(ConnectionState::Connecting);
+      match self.connect().await {
+        Ok(ws) => {
+          self.state.set(ConnectionState::Connected);
+          if let Err(err) = self.sync_with(ws).await {
+            tracing::debug!("[Sync]: connection to {} lost: {}", self.config.url, err);
+          }
+        },
+        Err(err) => tracing::debug!("[Sync]: failed to connect to {}: {}", self.config.url, err),
+      }
+      self.state.set(ConnectionState::Disconnected);
+      tokio::time::sleep(self.config.reconnect_interval).await;
+    }
+  }
+
+  /// The mutations received from the server, for the hosts that dispatch the requests
+  /// themselves, e.g. through their own queue. Returns `None` once taken.
+  pub fn take_remote_changes(&self) -> Option<mpsc::UnboundedReceiver<SyncMessage>> {
+    self.remote_changes_rx.lock().unwrap().take()
+  }
+
+  /// Dispatches the mutations received from the server. With the `local_set` feature, it must
+  /// run in a `LocalSet`.
+  pub async fn apply_remote_changes(&self, dispatch: &AFPluginDispatcher) {
+    let mut changes = match self.take_remote_changes() {
+      Some(changes) => changes,
+      None => return,
+    };
+    while let Some(message) = changes.recv().await {
+      let event = message.event.clone();
+      let response =
+        AFPluginDispatcher::async_send_with_callback(dispatch, message.into_request(), |_| {
+          Box::pin(async {})
+        })
+        .await;
+      if response.status_code != StatusCode::Ok {
+        tracing::warn!("[Sync]: failed to apply a remote change of {}", event);
+      }
+    }
+  }
+
+  async fn connect(&self) -> Result<WebSocket, String> {
+    let (mut ws, _) = tokio_tungstenite::connect_async(self.config.url.as_str())
+      .await
+      .map_err(|e| e.to_string())?;
+    ws.send(Message::Text(self.config.token.clone()))
+      .await
+      .map_err(|e| e.to_string())?;
+    Ok(ws)
+  }
+
+  async fn sync_with(&self, ws: WebSocket) -> Result<(), String> {
+    let (mut sink, mut stream) = ws.split();
+    let mut replay = self.start_replay()?;
+    let mut uploads = self.telemetry.as_ref().map(|telemetry| {
+      let mut uploads = tokio::time::interval(telemetry.upload_interval());
+      uploads.set_missed_tick_behavior(MissedTickBehavior::Delay);
+      uploads
+    });
+    loop {
+      // The mutations queued while offline are replayed first, then the new ones are sent as
+      // they are recorded.
+      loop {
+        let messages = self
+          .outbox
+          .pending(SEND_BATCH_SIZE)
+          .map_err(|e| e.to_string())?;
+        if messages.is_empty() {
+          break;
+        }
+        for message in messages {
+          let bytes = serde_json::to_vec(&message).map_err(|e| e.to_string())?;
+          sink
+            .send(Message::Binary(bytes))
+            .await
+            .map_err(|e| e.to_string())?;
+          self.outbox.remove(&message.id).map_err(|e| e.to_string())?;
+        }
+      }
+
+      tokio::select! {
+        _ = self.outbox.queued() => {},
+        _ = next_upload(&mut uploads) => self.upload_telemetry(&mut sink).await?,
+        message = stream.next() => match message {
+          Some(Ok(Message::Binary(bytes))) => self.receive(&bytes, &mut replay),
+          Some(Ok(Message::Close(_))) | None => return Err("closed by the server".to_owned()),
+          Some(Ok(_)) => {},
+          Some(Err(err)) => return Err(err.to_string()),
+        },
+      }
+    }
+  }
+
+  async fn upload_telemetry(&self, sink: &mut SplitSink<WebSocket, Message>) -> Result<(), String> {
+    let telemetry = match &self.telemetry {
+      Some(telemetry) => telemetry,
+      None => return Ok(()),
+    };
+    let message = match telemetry.take_message() {
+      Some(message) => message,
+      None => return Ok(()),
+    };
+    let bytes = serde_json::to_vec(&message).map_err(|e| e.to_string())?;
+    if let Err(err) = sink.send(Message::Binary(bytes)).await {
+      telemetry.restore(&message);
+      return Err(err.to_string());
+    }
+    Ok(())
+  }
+
+  fn receive(&self, bytes: &[u8], replay: &mut Replay) {
+    let remote = match serde_json::from_slice::<SyncMessage>(bytes) {
+      Ok(message) => message,
+      Err(err) => {
+        tracing::warn!("[Sync]: invalid message: {}", err);
+        return;
+      },
+    };
+    // The server sends the mutations back to the device they were made on.
+    if remote.origin == self.origin {
+      return;
+    }
+    if let Some((object_id, local)) = self.collision(&remote, replay) {
+      if let Some(resolver) = self.resolvers.get(&local.event) {
+        let resolution = resolver.resolve(&local, &remote);
+        self.conflicts.settle(object_id, local, remote, resolution);
+        return;
+      }
+    }
+    let _ = self.remote_changes.send(remote);
+  }
+
+  /// The mutations queued while the device was offline, by the object they change.
+  fn start_replay(&self) -> Result<Replay, String> {
+    let mut messages = HashMap::new();
+    if !self.resolvers.is_empty() {
+      for message in self.outbox.pending(usize::MAX).map_err(|e| e.to_string())? {
+        if let Some(object_id) = self.object_id(&message) {
+          messages.insert(object_id, message);
+        }
+      }
+    }
+    Ok(Replay {
+      until: Instant::now() + self.config.replay_window,
+      messages,
+    })
+  }
+
+  fn collision(&self, remote: &SyncMessage, replay: &mut Replay) -> Option<(String, SyncMessage)> {
+    if Instant::now() > replay.until {
+      replay.messages.clear();
+      return None;
+    }
+    let object_id = self.object_id(remote)?;
+    let local = replay.messages.get(&object_id)?.clone();
+    Some((object_id, local))
+  }
+
+  fn object_id(&self, message: &SyncMessage) -> Option<String> {
+    self.resolvers.get(&message.event)?.object_id(message)
+  }
+}
+
+/// The mutations replayed on a connection, that the remote mutations received until `until`
+/// collide with.
+struct Replay {
+  until: Instant,
+  messages: HashMap<String, SyncMessage>,
+}
+
+/// Waits for the next upload of the telemetry, forever if there is none.
+async fn next_upload(uploads: &mut Option<Interval>) {
+  match uploads {
+    Some(uploads) => {
+      uploads.tick().await;
+    },
+    None => std::future::pending().await,
+  }
+}
+
+pub(crate) fn now() -> i64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|duration| duration.as_millis() as i64)
+    .unwrap_or_default()
+}
+
+#[derive(Clone)]
+struct SyncMiddleware {
+  origin: String,
+  events: Arc<HashSet<String>>,
+  outbox: Outbox,
+}
+
+impl AFPluginMiddleware for SyncMiddleware {
+  fn on_response(&self, request: &AFPluginRequest, response: &mut AFPluginEventResponse) {
+    if response.status_code != StatusCode::Ok
+      || !self.events.contains(request.event.as_str())
+      || request.extensions().contains::<RemoteChange>()
+    {
+      return;
+    }
+    self
+      .outbox
+      .push(&SyncMessage::from_request(request, &self.origin));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::{Arc, Mutex};
+  use std::time::Duration;
+
+  use futures_util::{SinkExt, StreamExt};
+  use lib_dispatch::prelude::*;
+  use lib_dispatch::runtime::AFPluginRuntime;
+  use tokio::net::{TcpListener, TcpStream};
+  use tokio::task::LocalSet;
+  use tokio_tungstenite::tungstenite::Message;
+  use tokio_tungstenite::WebSocketStream;
+
+  use crate::conflict::ConflictResolver;
+  use crate::engine::{ConnectionState, RemoteChange, SyncConfig, SyncEngine, SyncMessage};
+
+  #[derive(Clone, Default)]
+  struct Rows(Arc<Mutex<Vec<(String, bool)>>>);
+
+  async fn add_row(
+    name: String,
+    remote: Result<Extension<RemoteChange>, DispatchError>,
+    rows: AppData<Rows>,
+  ) -> Result<(), DispatchError> {
+    rows.0.lock().unwrap().push((name, remote.is_ok()));
+    Ok(())
+  }
+
+  async fn next_message(ws: &mut WebSocketStream<TcpStream>) -> Message {
+    tokio::time::timeout(Duration::from_secs(5), ws.next())
+      .await
+      .unwrap()
+      .unwrap()
+      .unwrap()
+  }
+
+  async fn next_sync_message(ws: &mut WebSocketStream<TcpStream>) -> SyncMessage {
+    match next_message(ws).await {
+      Message::Binary(bytes) => serde_json::from_slice::<SyncMessage>(&bytes).unwrap(),
+      message => panic!("unexpected message: {:?}", message),
+    }
+  }
+
+  /// The rows are renamed with a `row_id:name` payload.
+  struct RenameResolver;
+
+  impl ConflictResolver for RenameResolver {
+    fn object_id(&self, message: &SyncMessage) -> Option<String> {
+      let payload = String::from_utf8(message.payload.clone()).ok()?;
+      payload.split(':').next().map(str::to_owned)
+    }
+  }
+
+  #[tokio::test]
+  async fn sync_engine_test() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    let engine =
+      Arc::new(SyncEngine::new(SyncConfig::new(&url, "token"), "device_1").sync("add_row"));
+    let rows = Rows::default();
+    let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+    let plugin = AFPlugin::new()
+      .event("add_row", add_row)
+      .event("remove_row", add_row);
+    let dispatcher =
+      engine.register(AFPluginDispatcher::new(runtime, vec![plugin]).data(rows.clone()));
+
+    let local_set = LocalSet::new();
+    local_set.spawn_local({
+      let engine = engine.clone();
+      async move { engine.run().await }
+    });
+    local_set
+      .run_until(async {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+        assert_eq!(
+          next_message(&mut ws).await,
+          Message::Text("token".to_owned())
+        );
+
+        // Only the synced events are sent
+        for event in ["remove_row", "add_row"] {
+          AFPluginDispatcher::async_send(&dispatcher, AFPluginRequest::new(event).payload("a"))
+            .await;
+        }
+        let message = next_sync_message(&mut ws).await;
+        assert_eq!(message.event, "add_row");
+        assert_eq!(message.origin, "device_1");
+        assert_eq!(message.payload, b"a".to_vec());
+        assert_eq!(engine.state().get(), ConnectionState::Connected);
+
+        // The mutations of the other devices are applied, the ones of this device are skipped
+        for (origin, payload) in [("device_1", "a"), ("device_2", "b")] {
+          let message = SyncMessage {
+            origin: origin.to_owned(),
+            payload: payload.as_bytes().to_vec(),
+            ..message.clone()
+          };
+          let bytes = serde_json::to_vec(&message).unwrap();
+          ws.send(Message::Binary(bytes)).await.unwrap();
+        }
+        let mut changes = engine.take_remote_changes().unwrap();
+        let change = tokio::time::timeout(Duration::from_secs(5), changes.recv())
+          .await
+          .unwrap()
+          .unwrap();
+        assert_eq!(change.origin, "device_2");
+        AFPluginDispatcher::async_send(&dispatcher, change.into_request()).await;
+
+        // The applied mutations are not sent back
+        let echo = tokio::time::timeout(Duration::from_millis(200), ws.next()).await;
+        assert!(echo.is_err());
+      })
+      .await;
+
+    assert_eq!(
+      *rows.0.lock().unwrap(),
+      vec![
+        ("a".to_string(), false),
+        ("a".to_string(), false),
+        ("b".to_string(), true)
+      ]
+    );
+  }
+
+  #[tokio::test]
+  async fn offline_replay_conflict_test() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    let engine = SyncEngine::new(SyncConfig::new(&url, "token"), "device_1")
+      .sync("rename_row")
+      .resolve("rename_row", RenameResolver);
+    let engine = Arc::new(engine);
+    let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+    let plugin = AFPlugin::new().event("rename_row", add_row);
+    let dispatcher =
+      engine.register(AFPluginDispatcher::new(runtime, vec![plugin]).data(Rows::default()));
+
+    LocalSet::new()
+      .run_until(async {
+        // Made while offline
+        let request = AFPluginRequest::new("rename_row").payload("row_1:local");
+        AFPluginDispatcher::async_send(&dispatcher, request).await;
+        tokio::task::spawn_local({
+          let engine = engine.clone();
+          async move { engine.run().await }
+        });
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+        next_message(&mut ws).await;
+        let local = next_sync_message(&mut ws).await;
+        assert_eq!(local.payload, b"row_1:local".to_vec());
+
+        // An older rename of the same row loses, a newer one wins and is sent again by this
+        // device, the renames of the other rows don't collide
+        for (payload, created_at) in [
+          ("row_1:older", local.created_at - 1),
+          ("row_1:newer", local.created_at + 1),
+          ("row_2:other", local.created_at - 1),
+        ] {
+          let remote = SyncMessage {
+            id: payload.to_owned(),
+            origin: "device_2".to_owned(),
+            payload: payload.as_bytes().to_vec(),
+            created_at,
+            ..local.clone()
+          };
+          let bytes = serde_json::to_vec(&remote).unwrap();
+          ws.send(Message::Binary(bytes)).await.unwrap();
+        }
+        let resent = next_sync_message(&mut ws).await;
+        assert_eq!(resent.origin, "device_1");
+        assert_eq!(resent.payload, b"row_1:newer".to_vec());
+
+        let mut changes = engine.take_remote_changes().unwrap();
+        for payload in ["row_1:newer", "row_2:other"] {
+          let change = tokio::time::timeout(Duration::from_secs(5), changes.recv())
+            .await
+            .unwrap()
+            .unwrap();
+          assert_eq!(change.payload, payload.as_bytes().to_vec());
+        }
+      })
+      .await;
+  }
+}
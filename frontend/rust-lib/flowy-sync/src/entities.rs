@@ -0,0 +1,97 @@
+use flowy_derive::{ProtoBuf, ProtoBuf_Enum};
+
+use crate::conflict::SyncConflict;
+use crate::engine::ConnectionState;
+
+#[derive(ProtoBuf_Enum, Eq, PartialEq, Debug, Clone, Copy, Default)]
+pub enum SyncConnectionStatePB {
+  #[default]
+  Disconnected = 0,
+  Connecting = 1,
+  Connected = 2,
+}
+
+impl From<ConnectionState> for SyncConnectionStatePB {
+  fn from(state: ConnectionState) -> Self {
+    match state {
+      ConnectionState::Disconnected => SyncConnectionStatePB::Disconnected,
+      ConnectionState::Connecting => SyncConnectionStatePB::Connecting,
+      ConnectionState::Connected => SyncConnectionStatePB::Connected,
+    }
+  }
+}
+
+#[derive(Default, ProtoBuf)]
+pub struct SyncStatePB {
+  #[pb(index = 1)]
+  pub state: SyncConnectionStatePB,
+
+  /// Empty if no sync server is configured.
+  #[pb(index = 2)]
+  pub url: String,
+}
+
+#[derive(Default, ProtoBuf)]
+pub struct SyncConflictPB {
+  #[pb(index = 1)]
+  pub conflict_id: String,
+
+  /// The event of the local mutation.
+  #[pb(index = 2)]
+  pub event: String,
+
+  /// The object both mutations change, e.g. the id of the view.
+  #[pb(index = 3)]
+  pub object_id: String,
+
+  /// The device the remote mutation was made on.
+  #[pb(index = 4)]
+  pub remote_origin: String,
+
+  /// In milliseconds since the epoch.
+  #[pb(index = 5)]
+  pub local_created_at: i64,
+
+  #[pb(index = 6)]
+  pub remote_created_at: i64,
+}
+
+impl From<&SyncConflict> for SyncConflictPB {
+  fn from(conflict: &SyncConflict) -> Self {
+    Self {
+      conflict_id: conflict.id.clone(),
+      event: conflict.local.event.clone(),
+      object_id: conflict.object_id.clone(),
+      remote_origin: conflict.remote.origin.clone(),
+      local_created_at: conflict.local.created_at,
+      remote_created_at: conflict.remote.created_at,
+    }
+  }
+}
+
+#[derive(Default, ProtoBuf)]
+pub struct RepeatedSyncConflictPB {
+  #[pb(index = 1)]
+  pub items: Vec<SyncConflictPB>,
+}
+
+#[derive(Default, ProtoBuf)]
+pub struct TelemetryStatePB {
+  /// Whether the user opted in.
+  #[pb(index = 1)]
+  pub enabled: bool,
+
+  /// The requests counted since the previous upload.
+  #[pb(index = 2)]
+  pub pending_requests: i64,
+}
+
+#[derive(Default, ProtoBuf)]
+pub struct ResolveSyncConflictPB {
+  #[pb(index = 1)]
+  pub conflict_id: String,
+
+  /// Keeps the local mutation if true, the remote one otherwise.
+  #[pb(index = 2)]
+  pub keep_local: bool,
+}
@@ -0,0 +1,51 @@
+use flowy_error::{FlowyError, FlowyResult};
+use lib_dispatch::prelude::{data_result_ok, AFPluginData, AppData, DataResult};
+
+use crate::conflict::SyncConflicts;
+use crate::engine::SyncState;
+use crate::entities::{
+  RepeatedSyncConflictPB, ResolveSyncConflictPB, SyncStatePB, TelemetryStatePB,
+};
+use crate::telemetry::Telemetry;
+
+pub(crate) async fn get_sync_state_handler(
+  state: AppData<SyncState>,
+) -> DataResult<SyncStatePB, FlowyError> {
+  data_result_ok(state.to_pb())
+}
+
+pub(crate) async fn get_sync_conflicts_handler(
+  conflicts: AppData<SyncConflicts>,
+) -> DataResult<RepeatedSyncConflictPB, FlowyError> {
+  let items = conflicts.list().iter().map(Into::into).collect();
+  data_result_ok(RepeatedSyncConflictPB { items })
+}
+
+pub(crate) async fn resolve_sync_conflict_handler(
+  conflicts: AppData<SyncConflicts>,
+  data: AFPluginData<ResolveSyncConflictPB>,
+) -> FlowyResult<()> {
+  let data = data.into_inner();
+  conflicts.resolve(&data.conflict_id, data.keep_local)
+}
+
+pub(crate) async fn get_telemetry_state_handler(
+  telemetry: AppData<Telemetry>,
+) -> DataResult<TelemetryStatePB, FlowyError> {
+  data_result_ok(telemetry.to_pb())
+}
+
+pub(crate) async fn enable_telemetry_handler(telemetry: AppData<Telemetry>) -> FlowyResult<()> {
+  telemetry.enable();
+  Ok(())
+}
+
+pub(crate) async fn disable_telemetry_handler(telemetry: AppData<Telemetry>) -> FlowyResult<()> {
+  telemetry.disable();
+  Ok(())
+}
+
+pub(crate) async fn purge_telemetry_handler(telemetry: AppData<Telemetry>) -> FlowyResult<()> {
+  telemetry.purge();
+  Ok(())
+}
@@ -0,0 +1,62 @@
+use strum_macros::Display;
+
+use flowy_derive::{Flowy_Event, ProtoBuf_Enum};
+use lib_dispatch::prelude::AFPlugin;
+
+use crate::event_handler::*;
+
+/// The handlers read the [SyncState](crate::SyncState) and the
+/// [SyncConflicts](crate::SyncConflicts) registered in the dispatcher with
+/// [SyncEngine::register](crate::SyncEngine::register), and the [Telemetry](crate::Telemetry)
+/// registered with [Telemetry::register](crate::Telemetry::register).
+pub fn init() -> AFPlugin {
+  AFPlugin::new()
+    .name(env!("CARGO_PKG_NAME"))
+    .proto_descriptors(crate::protobuf::file_descriptors())
+    .event(SyncEvent::GetSyncState, get_sync_state_handler)
+    .event(SyncEvent::GetSyncConflicts, get_sync_conflicts_handler)
+    .event(
+      SyncEvent::ResolveSyncConflict,
+      resolve_sync_conflict_handler,
+    )
+    .event(SyncEvent::GetTelemetryState, get_telemetry_state_handler)
+    .event(SyncEvent::EnableTelemetry, enable_telemetry_handler)
+    .event(SyncEvent::DisableTelemetry, disable_telemetry_handler)
+    .event(SyncEvent::PurgeTelemetry, purge_telemetry_handler)
+    // Recorded in the audit log
+    .mutating(SyncEvent::ResolveSyncConflict)
+    .mutating(SyncEvent::EnableTelemetry)
+    .mutating(SyncEvent::DisableTelemetry)
+    .mutating(SyncEvent::PurgeTelemetry)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Display, ProtoBuf_Enum, Flowy_Event)]
+#[event_err = "FlowyError"]
+pub enum SyncEvent {
+  /// The changes are then sent with the `DidUpdateSyncState` notification.
+  #[event(output = "SyncStatePB")]
+  GetSyncState = 0,
+
+  /// The conflicts waiting for the user, also sent one by one with the `DidReceiveSyncConflict`
+  /// notification.
+  #[event(output = "RepeatedSyncConflictPB")]
+  GetSyncConflicts = 1,
+
+  #[event(input = "ResolveSyncConflictPB")]
+  ResolveSyncConflict = 2,
+
+  #[event(output = "TelemetryStatePB")]
+  GetTelemetryState = 3,
+
+  /// The user opted in to the anonymous usage and error telemetry.
+  #[event()]
+  EnableTelemetry = 4,
+
+  /// The user opted out, the counts that weren't uploaded are dropped.
+  #[event()]
+  DisableTelemetry = 5,
+
+  /// Drops the counts that weren't uploaded, without opting out.
+  #[event()]
+  PurgeTelemetry = 6,
+}
@@ -0,0 +1,14 @@
+pub mod conflict;
+pub mod engine;
+pub mod entities;
+mod event_handler;
+pub mod event_map;
+mod notification;
+pub mod offline;
+mod protobuf;
+pub mod telemetry;
+
+pub use conflict::{last_write_wins, ConflictResolver, Resolution, SyncConflict, SyncConflicts};
+pub use engine::{ConnectionState, RemoteChange, SyncConfig, SyncEngine, SyncMessage, SyncState};
+pub use offline::{MemoryOfflineStore, OfflineStore, SqliteOfflineStore};
+pub use telemetry::{Telemetry, TelemetryBatch};
@@ -0,0 +1,43 @@
+use flowy_derive::ProtoBuf_Enum;
+use flowy_notification::{NotificationBuilder, NotificationType};
+
+const SYNC_OBSERVABLE_SOURCE: &str = "Sync";
+
+/// The id the sync notifications are sent with, there is a single engine.
+pub(crate) const SYNC_NOTIFICATION_ID: &str = "sync";
+
+#[derive(ProtoBuf_Enum, Debug, Default)]
+pub enum SyncNotification {
+  #[default]
+  Unknown = 0,
+
+  /// Sent with a `SyncStatePB` each time the connection state of the engine changes.
+  DidUpdateSyncState = 1,
+  /// Sent with a `SyncConflictPB` when a conflict needs the user to pick a mutation.
+  DidReceiveSyncConflict = 2,
+}
+
+impl std::convert::From<SyncNotification> for i32 {
+  fn from(notification: SyncNotification) -> Self {
+    notification as i32
+  }
+}
+
+impl std::convert::From<i32> for SyncNotification {
+  fn from(notification: i32) -> Self {
+    match notification {
+      1 => SyncNotification::DidUpdateSyncState,
+      2 => SyncNotification::DidReceiveSyncConflict,
+      _ => SyncNotification::Unknown,
+    }
+  }
+}
+
+impl NotificationType for SyncNotification {
+  const SOURCE: &'static str = SYNC_OBSERVABLE_SOURCE;
+}
+
+#[tracing::instrument(level = "trace")]
+pub(crate) fn send_notification(ty: SyncNotification) -> NotificationBuilder {
+  NotificationBuilder::typed(SYNC_NOTIFICATION_ID, ty)
+}
@@ -0,0 +1,234 @@
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+use flowy_error::{internal_error, FlowyError, FlowyResult};
+use flowy_sqlite::migration::ModuleMigrations;
+use flowy_sqlite::{DBConnection, Database, PoolConfig};
+use tokio::sync::Notify;
+
+use crate::engine::SyncMessage;
+
+const DB_NAME: &str = "sync_offline.db";
+
+const CREATE_SYNC_OFFLINE_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS sync_offline_table (
+  seq INTEGER PRIMARY KEY AUTOINCREMENT,
+  id TEXT NOT NULL UNIQUE,
+  origin TEXT NOT NULL,
+  event TEXT NOT NULL,
+  payload BLOB NOT NULL,
+  version INTEGER,
+  created_at BIGINT NOT NULL
+);
+"#;
+
+diesel::table! {
+    sync_offline_table (seq) {
+        seq -> BigInt,
+        id -> Text,
+        origin -> Text,
+        event -> Text,
+        payload -> Binary,
+        version -> Nullable<Integer>,
+        created_at -> BigInt,
+    }
+}
+
+/// Keeps the local mutations until they are sent to the server. The mutations made while the
+/// device is offline stay in the store and are replayed, in order, once it reconnects.
+pub trait OfflineStore: Send + Sync {
+  fn push(&self, message: &SyncMessage) -> FlowyResult<()>;
+
+  /// The oldest mutations of the store, in order, at most `limit`.
+  fn pending(&self, limit: usize) -> FlowyResult<Vec<SyncMessage>>;
+
+  /// Removes the mutation once it was sent.
+  fn remove(&self, id: &str) -> FlowyResult<()>;
+}
+
+/// Loses the queued mutations when the app stops. Used when no store is configured.
+#[derive(Default)]
+pub struct MemoryOfflineStore {
+  messages: Mutex<VecDeque<SyncMessage>>,
+}
+
+impl OfflineStore for MemoryOfflineStore {
+  fn push(&self, message: &SyncMessage) -> FlowyResult<()> {
+    self.messages.lock().unwrap().push_back(message.clone());
+    Ok(())
+  }
+
+  fn pending(&self, limit: usize) -> FlowyResult<Vec<SyncMessage>> {
+    let messages = self.messages.lock().unwrap();
+    Ok(messages.iter().take(limit).cloned().collect())
+  }
+
+  fn remove(&self, id: &str) -> FlowyResult<()> {
+    self
+      .messages
+      .lock()
+      .unwrap()
+      .retain(|message| message.id != id);
+    Ok(())
+  }
+}
+
+/// Keeps the queued mutations in their own database, so they survive a restart of the app.
+pub struct SqliteOfflineStore {
+  database: Database,
+}
+
+impl SqliteOfflineStore {
+  pub fn new(root: &str) -> FlowyResult<Self> {
+    if !Path::new(root).exists() {
+      return Err(FlowyError::internal().with_context(format!("{} not exists", root)));
+    }
+    let database = Database::new(root, DB_NAME, PoolConfig::default()).map_err(internal_error)?;
+    let mut conn = database.get_connection().map_err(internal_error)?;
+    migrations().run(&mut *conn)?;
+    Ok(Self { database })
+  }
+
+  fn connection(&self) -> FlowyResult<DBConnection> {
+    self.database.get_connection().map_err(internal_error)
+  }
+}
+
+impl OfflineStore for SqliteOfflineStore {
+  fn push(&self, message: &SyncMessage) -> FlowyResult<()> {
+    let mut conn = self.connection()?;
+    diesel::insert_into(sync_offline_table::table)
+      .values((
+        sync_offline_table::id.eq(&message.id),
+        sync_offline_table::origin.eq(&message.origin),
+        sync_offline_table::event.eq(&message.event),
+        sync_offline_table::payload.eq(&message.payload),
+        sync_offline_table::version.eq(message.version.map(|version| version as i32)),
+        sync_offline_table::created_at.eq(message.created_at),
+      ))
+      .execute(&mut *conn)?;
+    Ok(())
+  }
+
+  fn pending(&self, limit: usize) -> FlowyResult<Vec<SyncMessage>> {
+    let mut conn = self.connection()?;
+    let rows = sync_offline_table::table
+      .select((
+        sync_offline_table::id,
+        sync_offline_table::origin,
+        sync_offline_table::event,
+        sync_offline_table::payload,
+        sync_offline_table::version,
+        sync_offline_table::created_at,
+      ))
+      .order(sync_offline_table::seq.asc())
+      .limit(limit.min(i64::MAX as usize) as i64)
+      .load::<(String, String, String, Vec<u8>, Option<i32>, i64)>(&mut *conn)?;
+    Ok(
+      rows
+        .into_iter()
+        .map(
+          |(id, origin, event, payload, version, created_at)| SyncMessage {
+            id,
+            origin,
+            event,
+            payload,
+            version: version.map(|version| version as u32),
+            created_at,
+          },
+        )
+        .collect(),
+    )
+  }
+
+  fn remove(&self, id: &str) -> FlowyResult<()> {
+    let mut conn = self.connection()?;
+    diesel::delete(sync_offline_table::table.filter(sync_offline_table::id.eq(id)))
+      .execute(&mut *conn)?;
+    Ok(())
+  }
+}
+
+/// The schema of the store. Append the new steps, never change the applied ones.
+fn migrations() -> ModuleMigrations {
+  ModuleMigrations::new("sync").sql(1, "create_sync_offline_table", CREATE_SYNC_OFFLINE_SQL)
+}
+
+/// The [OfflineStore] of the engine, along with the signal that wakes the engine up when a
+/// mutation is queued.
+#[derive(Clone)]
+pub(crate) struct Outbox {
+  store: Arc<dyn OfflineStore>,
+  queued: Arc<Notify>,
+}
+
+impl Outbox {
+  pub(crate) fn new(store: Arc<dyn OfflineStore>) -> Self {
+    Self {
+      store,
+      queued: Arc::new(Notify::new()),
+    }
+  }
+
+  /// A failure is only logged, the mutation itself was already applied.
+  pub(crate) fn push(&self, message: &SyncMessage) {
+    match self.store.push(message) {
+      Ok(()) => self.queued.notify_one(),
+      Err(err) => tracing::error!(
+        "[Sync]: failed to queue a mutation of {}: {}",
+        message.event,
+        err
+      ),
+    }
+  }
+
+  pub(crate) fn pending(&self, limit: usize) -> FlowyResult<Vec<SyncMessage>> {
+    self.store.pending(limit)
+  }
+
+  pub(crate) fn remove(&self, id: &str) -> FlowyResult<()> {
+    self.store.remove(id)
+  }
+
+  /// Resolves once a mutation was queued since the last call.
+  pub(crate) async fn queued(&self) {
+    self.queued.notified().await
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use tempfile::TempDir;
+
+  use crate::engine::SyncMessage;
+  use crate::offline::{OfflineStore, SqliteOfflineStore};
+
+  fn message(id: &str) -> SyncMessage {
+    SyncMessage {
+      id: id.to_owned(),
+      origin: "device_1".to_owned(),
+      event: "add_row".to_owned(),
+      payload: id.as_bytes().to_vec(),
+      version: Some(2),
+      created_at: 1,
+    }
+  }
+
+  #[test]
+  fn sqlite_offline_store_test() {
+    let tempdir = TempDir::new().unwrap();
+    let root = tempdir.path().to_str().unwrap();
+    let store = SqliteOfflineStore::new(root).unwrap();
+    for id in ["b", "a", "c"] {
+      store.push(&message(id)).unwrap();
+    }
+    store.remove("a").unwrap();
+
+    // The queued mutations survive a restart, in order
+    let store = SqliteOfflineStore::new(root).unwrap();
+    assert_eq!(store.pending(10).unwrap(), vec![message("b"), message("c")]);
+    assert_eq!(store.pending(1).unwrap(), vec![message("b")]);
+  }
+}
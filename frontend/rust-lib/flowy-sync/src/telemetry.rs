@@ -0,0 +1,349 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use flowy_error::FlowyError;
+use flowy_sqlite::kv::KVStorePreferences;
+use lib_dispatch::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::engine::{now, RemoteChange, SyncMessage};
+use crate::entities::TelemetryStatePB;
+
+/// The event of the [SyncMessage]s carrying a [TelemetryBatch]. The server keeps them instead of
+/// forwarding them to the other devices.
+pub const TELEMETRY_EVENT: &str = "Telemetry";
+
+/// The key the consent of the user is kept under, see [Telemetry::consent_store].
+pub const TELEMETRY_CONSENT_KEY: &str = "telemetry_consent";
+
+/// How often the [SyncEngine](crate::SyncEngine) uploads the telemetry by default.
+pub const DEFAULT_TELEMETRY_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// The usage of the app since the previous upload. It only holds counts: no payload, no error
+/// message and nothing identifying the user or the device.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TelemetryBatch {
+  /// When the first request of the batch was recorded, in milliseconds since the epoch.
+  pub started_at: i64,
+  pub ended_at: i64,
+  /// The number of requests of each event.
+  pub events: BTreeMap<String, u64>,
+  /// The number of failed requests of each event, by error code.
+  pub errors: BTreeMap<String, BTreeMap<String, u64>>,
+}
+
+impl TelemetryBatch {
+  pub fn is_empty(&self) -> bool {
+    self.events.is_empty()
+  }
+
+  /// The number of requests of the batch.
+  pub fn len(&self) -> u64 {
+    self.events.values().sum()
+  }
+
+  fn record(&mut self, event: &str, error_code: Option<String>) {
+    let now = now();
+    if self.is_empty() {
+      self.started_at = now;
+    }
+    self.ended_at = now;
+    *self.events.entry(event.to_owned()).or_default() += 1;
+    if let Some(code) = error_code {
+      let codes = self.errors.entry(event.to_owned()).or_default();
+      *codes.entry(code).or_default() += 1;
+    }
+  }
+
+  fn merge(&mut self, other: TelemetryBatch) {
+    if other.is_empty() {
+      return;
+    }
+    if self.is_empty() || other.started_at < self.started_at {
+      self.started_at = other.started_at;
+    }
+    self.ended_at = self.ended_at.max(other.ended_at);
+    for (event, count) in other.events {
+      *self.events.entry(event).or_default() += count;
+    }
+    for (event, codes) in other.errors {
+      let entry = self.errors.entry(event).or_default();
+      for (code, count) in codes {
+        *entry.entry(code).or_default() += count;
+      }
+    }
+  }
+}
+
+/// Counts the requests of the events and the error codes of the failed ones, once the user
+/// opted in. The counts are uploaded in batches by the [SyncEngine](crate::SyncEngine) it's
+/// given to, see [SyncEngine::telemetry](crate::SyncEngine::telemetry), and kept in memory
+/// until then.
+///
+/// Nothing is recorded until [Telemetry::enable] is called, and [Telemetry::disable] drops
+/// what wasn't uploaded yet. Registered in the dispatcher as app data with
+/// [Telemetry::register], for the `EnableTelemetry`, `DisableTelemetry` and `PurgeTelemetry`
+/// events.
+#[derive(Clone)]
+pub struct Telemetry {
+  enabled: Arc<AtomicBool>,
+  batch: Arc<Mutex<TelemetryBatch>>,
+  consent_store: Option<Arc<KVStorePreferences>>,
+  interval: Duration,
+}
+
+impl Default for Telemetry {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Telemetry {
+  /// Disabled until the user opts in.
+  pub fn new() -> Self {
+    Self {
+      enabled: Arc::new(AtomicBool::new(false)),
+      batch: Arc::new(Mutex::new(TelemetryBatch::default())),
+      consent_store: None,
+      interval: DEFAULT_TELEMETRY_INTERVAL,
+    }
+  }
+
+  /// Keeps the consent of the user in `store`, so it survives a restart of the app. The consent
+  /// given before is restored.
+  pub fn consent_store(mut self, store: Arc<KVStorePreferences>) -> Self {
+    let enabled = store.get_bool_or_default(TELEMETRY_CONSENT_KEY);
+    self.enabled.store(enabled, Ordering::SeqCst);
+    self.consent_store = Some(store);
+    self
+  }
+
+  /// Uploads the telemetry every `interval` instead of every [DEFAULT_TELEMETRY_INTERVAL].
+  pub fn interval(mut self, interval: Duration) -> Self {
+    self.interval = interval;
+    self
+  }
+
+  /// Registers the middleware that counts the requests, and the telemetry as app data.
+  pub fn register(&self, dispatcher: AFPluginDispatcher) -> AFPluginDispatcher {
+    dispatcher
+      .with_middleware(TelemetryMiddleware(self.clone()))
+      .data(self.clone())
+  }
+
+  pub fn is_enabled(&self) -> bool {
+    self.enabled.load(Ordering::SeqCst)
+  }
+
+  pub fn enable(&self) {
+    self.set_consent(true);
+  }
+
+  /// Stops recording and drops the counts that weren't uploaded.
+  pub fn disable(&self) {
+    self.set_consent(false);
+    self.purge();
+  }
+
+  /// Drops the counts that weren't uploaded.
+  pub fn purge(&self) {
+    *self.batch.lock().unwrap() = TelemetryBatch::default();
+  }
+
+  /// The counts recorded since the previous upload.
+  pub fn pending(&self) -> TelemetryBatch {
+    self.batch.lock().unwrap().clone()
+  }
+
+  pub fn to_pb(&self) -> TelemetryStatePB {
+    TelemetryStatePB {
+      enabled: self.is_enabled(),
+      pending_requests: self.pending().len() as i64,
+    }
+  }
+
+  pub(crate) fn upload_interval(&self) -> Duration {
+    self.interval
+  }
+
+  /// The message uploading the pending counts, `None` if there are none or the user opted out.
+  pub(crate) fn take_message(&self) -> Option<SyncMessage> {
+    if !self.is_enabled() {
+      return None;
+    }
+    let batch = std::mem::take(&mut *self.batch.lock().unwrap());
+    if batch.is_empty() {
+      return None;
+    }
+    let payload = match serde_json::to_vec(&batch) {
+      Ok(payload) => payload,
+      Err(err) => {
+        tracing::warn!("[Telemetry]: failed to encode a batch: {}", err);
+        return None;
+      },
+    };
+    // The origin is left empty, the batches are anonymous.
+    Some(SyncMessage {
+      id: uuid::Uuid::new_v4().to_string(),
+      origin: String::new(),
+      event: TELEMETRY_EVENT.to_owned(),
+      payload,
+      version: None,
+      created_at: now(),
+    })
+  }
+
+  /// Puts back the counts of a message that couldn't be sent, unless the user opted out since.
+  pub(crate) fn restore(&self, message: &SyncMessage) {
+    if !self.is_enabled() {
+      return;
+    }
+    if let Ok(batch) = serde_json::from_slice::<TelemetryBatch>(&message.payload) {
+      self.batch.lock().unwrap().merge(batch);
+    }
+  }
+
+  fn set_consent(&self, enabled: bool) {
+    self.enabled.store(enabled, Ordering::SeqCst);
+    if let Some(store) = &self.consent_store {
+      if let Err(err) = store.set_bool(TELEMETRY_CONSENT_KEY, enabled) {
+        tracing::error!("[Telemetry]: failed to save the consent: {}", err);
+      }
+    }
+  }
+
+  fn record(&self, event: &str, error_code: Option<String>) {
+    if self.is_enabled() {
+      self.batch.lock().unwrap().record(event, error_code);
+    }
+  }
+}
+
+/// The code of the error of a failed response: the [ErrorCode](flowy_error::ErrorCode) of a
+/// [FlowyError], or the origin of the errors of the dispatcher.
+fn error_code(response: &AFPluginEventResponse) -> String {
+  if let Some(origin) = response.error_origin {
+    return format!("{:?}", origin);
+  }
+  match &response.payload {
+    Payload::Bytes(bytes) => match FlowyError::try_from(bytes.clone()) {
+      Ok(error) => format!("{:?}", error.code),
+      Err(_) => "Unknown".to_owned(),
+    },
+    Payload::None => "Unknown".to_owned(),
+  }
+}
+
+struct TelemetryMiddleware(Telemetry);
+
+impl AFPluginMiddleware for TelemetryMiddleware {
+  fn on_response(&self, request: &AFPluginRequest, response: &mut AFPluginEventResponse) {
+    // The mutations of the other devices are not the usage of this one.
+    if !self.0.is_enabled() || request.extensions().contains::<RemoteChange>() {
+      return;
+    }
+    let error_code = match response.status_code {
+      StatusCode::Ok => None,
+      StatusCode::Err => Some(error_code(response)),
+    };
+    self.0.record(request.event.as_str(), error_code);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+  use std::time::Duration;
+
+  use flowy_error::{ErrorCode, FlowyError};
+  use futures_util::StreamExt;
+  use lib_dispatch::prelude::*;
+  use lib_dispatch::runtime::AFPluginRuntime;
+  use tokio::net::TcpListener;
+  use tokio::task::LocalSet;
+  use tokio_tungstenite::tungstenite::Message;
+
+  use crate::engine::{SyncConfig, SyncEngine, SyncMessage};
+  use crate::telemetry::{Telemetry, TelemetryBatch, TELEMETRY_EVENT};
+
+  async fn open_view(id: String) -> Result<(), FlowyError> {
+    if id == "missing" {
+      return Err(FlowyError::new(ErrorCode::RecordNotFound, "secret view"));
+    }
+    Ok(())
+  }
+
+  #[tokio::test]
+  async fn telemetry_upload_test() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    let telemetry = Telemetry::new().interval(Duration::from_millis(50));
+    let engine = Arc::new(
+      SyncEngine::new(SyncConfig::new(&url, "token"), "device_1").telemetry(telemetry.clone()),
+    );
+    let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+    let plugin = AFPlugin::new().event("open_view", open_view);
+    let dispatcher = telemetry.register(AFPluginDispatcher::new(runtime, vec![plugin]));
+
+    let local_set = LocalSet::new();
+    local_set
+      .run_until(async {
+        let send = |id: &str| {
+          AFPluginDispatcher::async_send(
+            &dispatcher,
+            AFPluginRequest::new("open_view").payload(id.to_owned()),
+          )
+        };
+        // Nothing is recorded before the user opts in.
+        send("view_1").await;
+        assert!(telemetry.pending().is_empty());
+
+        telemetry.enable();
+        send("view_1").await;
+        send("missing").await;
+        send("view_2").await;
+        let pending = telemetry.pending();
+        assert_eq!(pending.events["open_view"], 3);
+        assert_eq!(pending.errors["open_view"]["RecordNotFound"], 1);
+        telemetry.purge();
+        assert!(telemetry.pending().is_empty());
+        send("missing").await;
+
+        tokio::task::spawn_local({
+          let engine = engine.clone();
+          async move { engine.run().await }
+        });
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+        ws.next().await;
+        let message = tokio::time::timeout(Duration::from_secs(5), ws.next())
+          .await
+          .unwrap()
+          .unwrap()
+          .unwrap();
+        let message = match message {
+          Message::Binary(bytes) => serde_json::from_slice::<SyncMessage>(&bytes).unwrap(),
+          message => panic!("unexpected message: {:?}", message),
+        };
+        assert_eq!(message.event, TELEMETRY_EVENT);
+        assert!(message.origin.is_empty());
+        // Neither the payloads nor the messages of the errors are uploaded.
+        let text = String::from_utf8(message.payload.clone()).unwrap();
+        assert!(!text.contains("secret view"));
+        let batch = serde_json::from_slice::<TelemetryBatch>(&message.payload).unwrap();
+        assert_eq!(batch.events["open_view"], 1);
+        assert_eq!(batch.errors["open_view"]["RecordNotFound"], 1);
+        assert!(telemetry.pending().is_empty());
+
+        send("view_1").await;
+        telemetry.disable();
+        assert!(telemetry.pending().is_empty());
+        send("view_1").await;
+        assert!(telemetry.pending().is_empty());
+      })
+      .await;
+  }
+}
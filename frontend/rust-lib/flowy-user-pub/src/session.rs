@@ -1,12 +1,23 @@
 use crate::entities::{UserAuthResponse, UserWorkspace};
+use arc_swap::ArcSwapOption;
 use base64::engine::general_purpose::STANDARD;
 use base64::Engine;
 use chrono::Utc;
+use flowy_error::{ErrorCode, FlowyError, FlowyResult};
+use lib_dispatch::capability::Capabilities;
+use lib_dispatch::prelude::{
+  AFPluginEventRequest, AFPluginMiddleware, AFPluginRequest, DispatchError, FromAFPluginRequest,
+  Identity, Payload, SessionStore, Shared,
+};
 use serde::de::{MapAccess, Visitor};
 use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::fmt::Display;
+use std::future::{ready, Ready};
+use std::ops::Deref;
+use std::sync::{Arc, RwLock};
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize)]
@@ -119,3 +130,168 @@ impl std::convert::From<Session> for String {
     })
   }
 }
+
+/// The session of the signed in user. The user plugin updates it when the user signs in, signs
+/// out or switches workspace, and shares it with the other plugins, whose handlers read it with
+/// the `Shared<SessionState>` or the [CurrentSession] extractor.
+///
+/// The clones share the same session.
+#[derive(Clone, Default)]
+pub struct SessionState {
+  session: Arc<ArcSwapOption<Session>>,
+}
+
+impl SessionState {
+  pub fn new(session: Option<Arc<Session>>) -> Self {
+    Self {
+      session: Arc::new(ArcSwapOption::from(session)),
+    }
+  }
+
+  pub fn get(&self) -> Option<Arc<Session>> {
+    self.session.load_full()
+  }
+
+  /// Replaces the session and returns the previous one.
+  pub fn set(&self, session: Option<Arc<Session>>) -> Option<Arc<Session>> {
+    self.session.swap(session)
+  }
+
+  pub fn is_signed_in(&self) -> bool {
+    self.session.load().is_some()
+  }
+
+  /// The session, or the error returned to the client when no user is signed in.
+  pub fn require(&self) -> FlowyResult<Arc<Session>> {
+    self.get().ok_or_else(not_logged_in)
+  }
+}
+
+pub fn not_logged_in() -> FlowyError {
+  FlowyError::new(ErrorCode::RecordNotFound, "User is not logged in")
+}
+
+/// Extracts the session of the signed in user. The request is rejected when no user is signed
+/// in.
+pub struct CurrentSession(pub Arc<Session>);
+
+impl Deref for CurrentSession {
+  type Target = Session;
+
+  fn deref(&self) -> &Session {
+    &self.0
+  }
+}
+
+impl FromAFPluginRequest for CurrentSession {
+  type Error = DispatchError;
+  type Future = Ready<Result<Self, DispatchError>>;
+
+  fn from_request(req: &AFPluginEventRequest, _: &mut Payload) -> Self::Future {
+    let result = match req.get_app_data::<Shared<SessionState>>() {
+      Some(state) => state.require().map(CurrentSession),
+      None => Err(FlowyError::internal().with_context("The user plugin is not registered")),
+    };
+    ready(result.map_err(DispatchError::from))
+  }
+}
+
+/// Rejects the requests of the events that need a signed in user when no user is signed in,
+/// before they reach their plugin. The dispatcher registers it with
+/// [AFPluginDispatcher::with_middleware](lib_dispatch::prelude::AFPluginDispatcher::with_middleware).
+pub struct SessionGuard {
+  state: SessionState,
+  events: HashSet<String>,
+}
+
+impl SessionGuard {
+  pub fn new(state: SessionState) -> Self {
+    Self {
+      state,
+      events: HashSet::new(),
+    }
+  }
+
+  /// Requires a signed in user to handle `event`.
+  pub fn require<E: ToString>(mut self, event: E) -> Self {
+    self.events.insert(event.to_string());
+    self
+  }
+}
+
+impl AFPluginMiddleware for SessionGuard {
+  fn on_request(&self, request: &mut AFPluginRequest) -> Result<(), DispatchError> {
+    if self.events.contains(request.event.as_str()) {
+      self.state.require()?;
+    }
+    Ok(())
+  }
+}
+
+/// Required to issue and revoke the [SessionTokens].
+pub const SESSION_TOKEN_CAPABILITY: &str = "user.session";
+
+struct IssuedToken {
+  user_id: i64,
+  capabilities: Capabilities,
+}
+
+/// The tokens the network clients authenticate their requests with, on behalf of the signed in
+/// user. The [AuthMiddleware](lib_dispatch::prelude::AuthMiddleware) verifies them. A token is
+/// only valid while the user it was issued to is signed in, and only grants the capabilities it
+/// was issued with.
+///
+/// The clones share the same tokens.
+#[derive(Clone)]
+pub struct SessionTokens {
+  state: SessionState,
+  tokens: Arc<RwLock<HashMap<String, IssuedToken>>>,
+}
+
+impl SessionTokens {
+  pub fn new(state: SessionState) -> Self {
+    Self {
+      state,
+      tokens: Arc::new(RwLock::new(HashMap::new())),
+    }
+  }
+
+  /// Issues a token for the signed in user. Its requests may only call the events requiring
+  /// none of the capabilities missing from `capabilities`.
+  pub fn issue(&self, capabilities: Capabilities) -> FlowyResult<String> {
+    let session = self.state.require()?;
+    let token = Uuid::new_v4().to_string();
+    self.tokens.write().unwrap().insert(
+      token.clone(),
+      IssuedToken {
+        user_id: session.user_id,
+        capabilities,
+      },
+    );
+    Ok(token)
+  }
+
+  /// Returns whether the token was issued.
+  pub fn revoke(&self, token: &str) -> bool {
+    self.tokens.write().unwrap().remove(token).is_some()
+  }
+
+  pub fn revoke_all(&self) {
+    self.tokens.write().unwrap().clear();
+  }
+}
+
+impl SessionStore for SessionTokens {
+  fn verify(&self, token: &str) -> Option<Identity> {
+    let tokens = self.tokens.read().unwrap();
+    let issued = tokens.get(token)?;
+    let session = self.state.get()?;
+    if session.user_id != issued.user_id {
+      return None;
+    }
+    Some(Identity {
+      user_id: issued.user_id.to_string(),
+      capabilities: Some(issued.capabilities.clone()),
+    })
+  }
+}
@@ -285,3 +285,34 @@ impl Default for AuthStatePB {
     Self::AuthStateUnknown
   }
 }
+
+#[derive(ProtoBuf, Default, Debug, Clone)]
+pub struct IssueSessionTokenPB {
+  /// Granted to the requests sent with the token. A token issued without capabilities may only
+  /// call the events that require none.
+  #[pb(index = 1)]
+  pub capabilities: Vec<String>,
+}
+
+#[derive(ProtoBuf, Default, Debug, Clone)]
+pub struct SessionTokenPB {
+  #[pb(index = 1)]
+  pub token: String,
+}
+
+#[derive(ProtoBuf, Default)]
+pub struct EncryptionPassphrasePB {
+  #[pb(index = 1)]
+  pub passphrase: String,
+}
+
+#[derive(ProtoBuf, Default, Debug, Clone)]
+pub struct EncryptionStatePB {
+  /// Whether a passphrase encrypts the local data.
+  #[pb(index = 1)]
+  pub configured: bool,
+
+  /// Whether the key of the passphrase is available, the local data can't be opened otherwise.
+  #[pb(index = 2)]
+  pub unlocked: bool,
+}
@@ -109,6 +109,17 @@ pub struct LocaleSettingsPB {
   pub country_code: String,
 }
 
+impl LocaleSettingsPB {
+  /// The locale as a language tag, e.g. `pt-BR`.
+  pub fn language_tag(&self) -> String {
+    if self.country_code.is_empty() {
+      self.language_code.clone()
+    } else {
+      format!("{}-{}", self.language_code, self.country_code)
+    }
+  }
+}
+
 impl std::default::Default for LocaleSettingsPB {
   fn default() -> Self {
     Self {
@@ -218,6 +218,14 @@ pub struct CreateWorkspacePB {
   pub name: String,
 }
 
+/// The workspaces of the user in the order they are listed in.
+#[derive(ProtoBuf, Default, Clone, Validate)]
+pub struct ReorderWorkspacesPB {
+  #[pb(index = 1)]
+  #[validate(length(min = 1))]
+  pub workspace_ids: Vec<String>,
+}
+
 #[derive(ProtoBuf, Default, Clone, Validate)]
 pub struct RenameWorkspacePB {
   #[pb(index = 1)]
@@ -1,7 +1,10 @@
+use flowy_error::i18n::error_catalog;
 use flowy_error::{ErrorCode, FlowyError, FlowyResult};
 use flowy_sqlite::kv::KVStorePreferences;
 use flowy_user_pub::cloud::UserCloudConfig;
 use flowy_user_pub::entities::*;
+use flowy_user_pub::session::CurrentSession;
+use lib_dispatch::capability::Capabilities;
 use lib_dispatch::prelude::*;
 use lib_infra::box_any::BoxAny;
 use serde_json::Value;
@@ -97,10 +100,11 @@ pub async fn init_user_handler(
 
 #[tracing::instrument(level = "debug", skip(manager))]
 pub async fn get_user_profile_handler(
+  session: CurrentSession,
   manager: AFPluginState<Weak<UserManager>>,
 ) -> DataResult<UserProfilePB, FlowyError> {
   let manager = upgrade_manager(manager)?;
-  let uid = manager.get_session()?.user_id;
+  let uid = session.user_id;
   let mut user_profile = manager.get_user_profile_from_disk(uid).await?;
 
   let weak_manager = Arc::downgrade(&manager);
@@ -171,6 +175,7 @@ pub async fn set_appearance_setting(
     setting.theme = APPEARANCE_DEFAULT_THEME.to_string();
   }
   store_preferences.set_object(APPEARANCE_SETTING_CACHE_KEY, &setting)?;
+  error_catalog().set_default_locale(&setting.locale.language_tag());
   Ok(())
 }
 
@@ -504,6 +509,20 @@ pub async fn get_all_workspace_handler(
   data_result_ok(user_workspaces.into())
 }
 
+#[tracing::instrument(level = "debug", skip(data, manager), err)]
+pub async fn reorder_workspaces_handler(
+  data: AFPluginData<ReorderWorkspacesPB>,
+  manager: AFPluginState<Weak<UserManager>>,
+) -> Result<(), FlowyError> {
+  let params = data.try_into_inner()?;
+  let manager = upgrade_manager(manager)?;
+  let uid = manager.get_session()?.user_id;
+  manager
+    .reorder_workspaces(uid, params.workspace_ids)
+    .await?;
+  Ok(())
+}
+
 #[tracing::instrument(level = "info", skip(data, manager), err)]
 pub async fn open_workspace_handler(
   data: AFPluginData<UserWorkspaceIdPB>,
@@ -902,3 +921,71 @@ pub async fn notify_did_switch_plan_handler(
   manager.notify_did_switch_plan(success).await?;
   Ok(())
 }
+
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn issue_session_token_handler(
+  data: AFPluginData<IssueSessionTokenPB>,
+  manager: AFPluginState<Weak<UserManager>>,
+) -> DataResult<SessionTokenPB, FlowyError> {
+  let params = data.into_inner();
+  let manager = upgrade_manager(manager)?;
+  let capabilities = params.capabilities.into_iter().collect::<Capabilities>();
+  let token = manager
+    .authenticate_user
+    .session_tokens()
+    .issue(capabilities)?;
+  data_result_ok(SessionTokenPB { token })
+}
+
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn revoke_session_token_handler(
+  data: AFPluginData<SessionTokenPB>,
+  manager: AFPluginState<Weak<UserManager>>,
+) -> Result<(), FlowyError> {
+  let params = data.into_inner();
+  let manager = upgrade_manager(manager)?;
+  manager
+    .authenticate_user
+    .session_tokens()
+    .revoke(&params.token);
+  Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn setup_encryption_passphrase_handler(
+  data: AFPluginData<EncryptionPassphrasePB>,
+  manager: AFPluginState<Weak<UserManager>>,
+) -> Result<(), FlowyError> {
+  let params = data.into_inner();
+  if params.passphrase.is_empty() {
+    return Err(FlowyError::password_empty());
+  }
+  let manager = upgrade_manager(manager)?;
+  manager
+    .authenticate_user
+    .setup_encryption(&params.passphrase)
+}
+
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn unlock_encryption_handler(
+  data: AFPluginData<EncryptionPassphrasePB>,
+  manager: AFPluginState<Weak<UserManager>>,
+) -> Result<(), FlowyError> {
+  let params = data.into_inner();
+  let manager = upgrade_manager(manager)?;
+  manager
+    .authenticate_user
+    .unlock_encryption(&params.passphrase)
+}
+
+#[tracing::instrument(level = "debug", skip_all, err)]
+pub async fn get_encryption_state_handler(
+  manager: AFPluginState<Weak<UserManager>>,
+) -> DataResult<EncryptionStatePB, FlowyError> {
+  let manager = upgrade_manager(manager)?;
+  let (configured, unlocked) = manager.authenticate_user.encryption_state()?;
+  data_result_ok(EncryptionStatePB {
+    configured,
+    unlocked,
+  })
+}
@@ -6,22 +6,32 @@ use flowy_derive::{Flowy_Event, ProtoBuf_Enum};
 use flowy_error::FlowyResult;
 use flowy_user_pub::cloud::UserCloudConfig;
 use flowy_user_pub::entities::*;
+use flowy_user_pub::session::SESSION_TOKEN_CAPABILITY;
 use lib_dispatch::prelude::*;
 use lib_infra::async_trait::async_trait;
 
 use crate::event_handler::*;
 use crate::user_manager::UserManager;
 
+/// Required to set up and unlock the encryption of the local data.
+pub const ENCRYPTION_CAPABILITY: &str = "user.encryption";
+
 #[rustfmt::skip]
 pub fn init(user_manager: Weak<UserManager>) -> AFPlugin {
   let store_preferences = user_manager
     .upgrade()
     .map(|session| session.get_store_preferences())
     .unwrap();
+  let session_state = user_manager
+    .upgrade()
+    .map(|manager| manager.authenticate_user.session_state())
+    .unwrap();
   AFPlugin::new()
     .name("Flowy-User")
+    .proto_descriptors(crate::protobuf::file_descriptors())
     .state(user_manager)
     .state(store_preferences)
+    .shared_state(session_state)
     .event(UserEvent::SignInWithEmailPassword, sign_in_with_email_password_handler)
     .event(UserEvent::MagicLinkSignIn, sign_in_with_magic_link_handler)
     .event(UserEvent::SignUp, sign_up)
@@ -65,6 +75,7 @@ pub fn init(user_manager: Weak<UserManager>) -> AFPlugin {
     .event(UserEvent::DeleteWorkspace, delete_workspace_handler)
     .event(UserEvent::RenameWorkspace, rename_workspace_handler)
     .event(UserEvent::ChangeWorkspaceIcon, change_workspace_icon_handler)
+    .event(UserEvent::ReorderWorkspaces, reorder_workspaces_handler)
     .event(UserEvent::LeaveWorkspace, leave_workspace_handler)
     .event(UserEvent::InviteWorkspaceMember, invite_workspace_member_handler)
     .event(UserEvent::ListWorkspaceInvitations, list_workspace_invitations_handler)
@@ -81,6 +92,47 @@ pub fn init(user_manager: Weak<UserManager>) -> AFPlugin {
     .event(UserEvent::UpdateWorkspaceSetting, update_workspace_setting)
     .event(UserEvent::GetWorkspaceSetting, get_workspace_setting)
     .event(UserEvent::NotifyDidSwitchPlan, notify_did_switch_plan_handler)
+    // Session tokens
+    .event(UserEvent::IssueSessionToken, issue_session_token_handler)
+    .event(UserEvent::RevokeSessionToken, revoke_session_token_handler)
+    .requires_capability(UserEvent::IssueSessionToken, SESSION_TOKEN_CAPABILITY)
+    .requires_capability(UserEvent::RevokeSessionToken, SESSION_TOKEN_CAPABILITY)
+    // Encryption of the local data
+    .event(UserEvent::SetupEncryptionPassphrase, setup_encryption_passphrase_handler)
+    .event(UserEvent::UnlockEncryption, unlock_encryption_handler)
+    .event(UserEvent::GetEncryptionState, get_encryption_state_handler)
+    .requires_capability(UserEvent::SetupEncryptionPassphrase, ENCRYPTION_CAPABILITY)
+    .requires_capability(UserEvent::UnlockEncryption, ENCRYPTION_CAPABILITY)
+    // Recorded in the audit log
+    .mutating(UserEvent::SignUp)
+    .mutating(UserEvent::DeleteAccount)
+    .mutating(UserEvent::UpdateUserProfile)
+    .mutating(UserEvent::SetAppearanceSetting)
+    .mutating(UserEvent::SetCloudConfig)
+    .mutating(UserEvent::SetEncryptionSecret)
+    .mutating(UserEvent::CreateReminder)
+    .mutating(UserEvent::RemoveReminder)
+    .mutating(UserEvent::UpdateReminder)
+    .mutating(UserEvent::ResetWorkspace)
+    .mutating(UserEvent::SetDateTimeSettings)
+    .mutating(UserEvent::SetNotificationSettings)
+    .mutating(UserEvent::ImportAppFlowyDataFolder)
+    .mutating(UserEvent::RemoveWorkspaceMember)
+    .mutating(UserEvent::UpdateWorkspaceMember)
+    .mutating(UserEvent::CreateWorkspace)
+    .mutating(UserEvent::DeleteWorkspace)
+    .mutating(UserEvent::RenameWorkspace)
+    .mutating(UserEvent::ChangeWorkspaceIcon)
+    .mutating(UserEvent::ReorderWorkspaces)
+    .mutating(UserEvent::LeaveWorkspace)
+    .mutating(UserEvent::InviteWorkspaceMember)
+    .mutating(UserEvent::AcceptWorkspaceInvitation)
+    .mutating(UserEvent::SubscribeWorkspace)
+    .mutating(UserEvent::CancelWorkspaceSubscription)
+    .mutating(UserEvent::UpdateWorkspaceSubscriptionPaymentPeriod)
+    .mutating(UserEvent::UpdateWorkspaceSetting)
+    .mutating(UserEvent::SetupEncryptionPassphrase)
+    .mutating(UserEvent::UnlockEncryption)
 
 }
 
@@ -278,6 +330,30 @@ pub enum UserEvent {
 
   #[event()]
   DeleteAccount = 64,
+
+  #[event(input = "ReorderWorkspacesPB")]
+  ReorderWorkspaces = 65,
+
+  /// Issues a token for a network client acting on behalf of the signed in user. The requests
+  /// sent with it only get the capabilities listed in the payload.
+  #[event(input = "IssueSessionTokenPB", output = "SessionTokenPB")]
+  IssueSessionToken = 66,
+
+  #[event(input = "SessionTokenPB")]
+  RevokeSessionToken = 67,
+
+  /// Encrypts the local data with a passphrase, from the next time the databases are opened.
+  /// Fails if the app has no keyring or the passphrase is already set up.
+  #[event(input = "EncryptionPassphrasePB")]
+  SetupEncryptionPassphrase = 68,
+
+  /// Unlocks the local data with the passphrase set up before, when opening it failed with
+  /// `EncryptionLocked` because the keyring lost the key.
+  #[event(input = "EncryptionPassphrasePB")]
+  UnlockEncryption = 69,
+
+  #[event(output = "EncryptionStatePB")]
+  GetEncryptionState = 70,
 }
 
 #[async_trait]
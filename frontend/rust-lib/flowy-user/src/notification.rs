@@ -36,5 +36,5 @@ pub(crate) fn send_auth_state_notification(payload: AuthStateChangedPB) {
     USER_OBSERVABLE_SOURCE,
   )
   .payload(payload)
-  .send()
+  .send_with_ack();
 }
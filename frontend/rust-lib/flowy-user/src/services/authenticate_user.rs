@@ -4,14 +4,14 @@ use crate::services::entities::{UserConfig, UserPaths};
 use crate::services::sqlite_sql::user_sql::vacuum_database;
 use collab_integrate::CollabKVDB;
 
-use arc_swap::ArcSwapOption;
 use collab_plugins::local_storage::kv::doc::CollabKVAction;
 use collab_plugins::local_storage::kv::KVTransactionDB;
+use flowy_encrypt::KeyManager;
 use flowy_error::{internal_error, ErrorCode, FlowyError, FlowyResult};
 use flowy_sqlite::kv::KVStorePreferences;
 use flowy_sqlite::DBConnection;
 use flowy_user_pub::entities::UserWorkspace;
-use flowy_user_pub::session::Session;
+use flowy_user_pub::session::{not_logged_in, Session, SessionState, SessionTokens};
 use std::path::PathBuf;
 use std::sync::{Arc, Weak};
 use tracing::{error, info};
@@ -23,22 +23,29 @@ pub struct AuthenticateUser {
   pub(crate) database: Arc<UserDB>,
   pub(crate) user_paths: UserPaths,
   store_preferences: Arc<KVStorePreferences>,
-  session: ArcSwapOption<Session>,
+  session: SessionState,
+  session_tokens: SessionTokens,
 }
 
 impl AuthenticateUser {
-  pub fn new(user_config: UserConfig, store_preferences: Arc<KVStorePreferences>) -> Self {
+  pub fn new(
+    user_config: UserConfig,
+    store_preferences: Arc<KVStorePreferences>,
+    key_manager: Option<Arc<KeyManager>>,
+  ) -> Self {
     let user_paths = UserPaths::new(user_config.storage_path.clone());
-    let database = Arc::new(UserDB::new(user_paths.clone()));
+    let database = Arc::new(UserDB::new(user_paths.clone(), key_manager));
     let session =
       migrate_session_with_user_uuid(&user_config.session_cache_key, &store_preferences)
         .map(Arc::new);
+    let session = SessionState::new(session);
     Self {
       user_config,
       database,
       user_paths,
       store_preferences,
-      session: ArcSwapOption::from(session),
+      session_tokens: SessionTokens::new(session.clone()),
+      session,
     }
   }
 
@@ -116,17 +123,65 @@ impl AuthenticateUser {
     Ok(read_txn.is_exist(uid, &object_id))
   }
 
+  /// The container of the session, shared with the other plugins.
+  pub fn session_state(&self) -> SessionState {
+    self.session.clone()
+  }
+
+  /// The tokens of the network clients acting on behalf of the current user.
+  pub fn session_tokens(&self) -> SessionTokens {
+    self.session_tokens.clone()
+  }
+
+  /// Whether a passphrase encrypts the local data and whether its key is available.
+  pub fn encryption_state(&self) -> FlowyResult<(bool, bool)> {
+    let key_manager = self.key_manager()?;
+    let unlocked = key_manager.key().map_err(internal_error)?.is_some();
+    Ok((key_manager.is_configured(), unlocked))
+  }
+
+  /// Sets up the passphrase encrypting the local data. The databases opened already stay in
+  /// plaintext until they are opened again, i.e. on the next sign in or launch.
+  pub fn setup_encryption(&self, passphrase: &str) -> FlowyResult<()> {
+    let key_manager = self.key_manager()?;
+    if key_manager.is_configured() {
+      return Err(FlowyError::new(
+        ErrorCode::InvalidEncryptSecret,
+        "The encryption passphrase is already set up",
+      ));
+    }
+    key_manager.setup(passphrase).map_err(internal_error)?;
+    Ok(())
+  }
+
+  /// Unlocks the local data when the keyring lost its key, the databases failing to open with
+  /// [ErrorCode::EncryptionLocked] can be opened again.
+  pub fn unlock_encryption(&self, passphrase: &str) -> FlowyResult<()> {
+    self
+      .key_manager()?
+      .unlock(passphrase)
+      .map_err(|err| FlowyError::new(ErrorCode::InvalidEncryptSecret, err))?;
+    Ok(())
+  }
+
+  fn key_manager(&self) -> FlowyResult<&Arc<KeyManager>> {
+    self.database.key_manager().ok_or_else(|| {
+      FlowyError::not_support().with_context("No keyring to keep the encryption key")
+    })
+  }
+
   pub fn set_session(&self, session: Option<Arc<Session>>) -> Result<(), FlowyError> {
     match session {
       None => {
-        let previous = self.session.swap(session);
+        let previous = self.session.set(session);
+        self.session_tokens.revoke_all();
         info!("remove session: {:?}", previous);
         self
           .store_preferences
           .remove(self.user_config.session_cache_key.as_ref());
       },
       Some(session) => {
-        self.session.swap(Some(session.clone()));
+        self.session.set(Some(session.clone()));
         info!("Set current session: {:?}", session);
         self
           .store_preferences
@@ -147,7 +202,7 @@ impl AuthenticateUser {
   }
 
   pub fn get_session(&self) -> FlowyResult<Arc<Session>> {
-    if let Some(session) = self.session.load_full() {
+    if let Some(session) = self.session.get() {
       return Ok(session);
     }
 
@@ -155,12 +210,9 @@ impl AuthenticateUser {
       .store_preferences
       .get_object::<Arc<Session>>(&self.user_config.session_cache_key)
     {
-      None => Err(FlowyError::new(
-        ErrorCode::RecordNotFound,
-        "User is not logged in",
-      )),
+      None => Err(not_logged_in()),
       Some(session) => {
-        self.session.store(Some(session.clone()));
+        self.session.set(Some(session.clone()));
         Ok(session)
       },
     }
@@ -6,6 +6,7 @@ use collab_integrate::{CollabKVAction, CollabKVDB, PersistenceError};
 use collab_plugins::local_storage::kv::KVTransactionDB;
 use dashmap::mapref::entry::Entry;
 use dashmap::DashMap;
+use flowy_encrypt::{EncryptionKey, KeyManager};
 use flowy_error::FlowyError;
 use flowy_sqlite::schema::user_workspace_table;
 use flowy_sqlite::ConnectionPool;
@@ -32,17 +33,24 @@ pub struct UserDB {
   paths: Box<dyn UserDBPath>,
   sqlite_map: DashMap<i64, Database>,
   collab_db_map: DashMap<i64, Arc<CollabKVDB>>,
+  /// Encrypts the sqlite db once a passphrase is set up.
+  key_manager: Option<Arc<KeyManager>>,
 }
 
 impl UserDB {
-  pub fn new(paths: impl UserDBPath) -> Self {
+  pub fn new(paths: impl UserDBPath, key_manager: Option<Arc<KeyManager>>) -> Self {
     Self {
       paths: Box::new(paths),
       sqlite_map: Default::default(),
       collab_db_map: Default::default(),
+      key_manager,
     }
   }
 
+  pub(crate) fn key_manager(&self) -> Option<&Arc<KeyManager>> {
+    self.key_manager.as_ref()
+  }
+
   /// Performs a conditional backup or restoration of the collaboration database (CollabDB) for a specific user.
   #[instrument(level = "debug", skip_all)]
   pub fn backup(&self, uid: i64, workspace_id: &str) {
@@ -119,7 +127,12 @@ impl UserDB {
       Entry::Occupied(e) => Ok(e.get().get_pool()),
       Entry::Vacant(e) => {
         tracing::debug!("open sqlite db {} at path: {:?}", user_id, db_path.as_ref());
-        let db = flowy_sqlite::init(&db_path).map_err(|e| {
+        let db = match self.encryption_key()? {
+          // Encrypts the plaintext db on its first open after the passphrase was set up
+          Some(key) => flowy_sqlite::init_encrypted(&db_path, key.as_bytes()),
+          None => flowy_sqlite::init(&db_path),
+        }
+        .map_err(|e| {
           FlowyError::internal().with_context(format!("open user db failed, {:?}", e))
         })?;
         let pool = db.get_pool();
@@ -129,6 +142,22 @@ impl UserDB {
     }
   }
 
+  /// The key of the sqlite db, `None` if no passphrase was set up. Fails while the passphrase
+  /// is set up but the key isn't in the keyring, until the user unlocks it.
+  fn encryption_key(&self) -> Result<Option<EncryptionKey>, FlowyError> {
+    let key_manager = match &self.key_manager {
+      None => return Ok(None),
+      Some(key_manager) => key_manager,
+    };
+    let key = key_manager.key().map_err(|e| {
+      FlowyError::internal().with_context(format!("read the encryption key failed, {:?}", e))
+    })?;
+    if key.is_none() && key_manager.is_configured() {
+      return Err(FlowyError::encryption_locked());
+    }
+    Ok(key)
+  }
+
   pub fn get_user_profile(
     &self,
     pool: &Arc<ConnectionPool>,
@@ -1,10 +1,11 @@
 use chrono::{TimeZone, Utc};
 use diesel::{RunQueryDsl, SqliteConnection};
 use flowy_error::FlowyError;
-use flowy_sqlite::schema::user_workspace_table;
+use flowy_sqlite::schema::{user_workspace_order_table, user_workspace_table};
 use flowy_sqlite::DBConnection;
 use flowy_sqlite::{query_dsl::*, ExpressionMethods};
 use flowy_user_pub::entities::UserWorkspace;
+use std::collections::HashMap;
 use std::convert::TryFrom;
 
 #[derive(Clone, Default, Queryable, Identifiable, Insertable)]
@@ -33,7 +34,59 @@ pub fn get_all_user_workspace_op(
   let rows = user_workspace_table::dsl::user_workspace_table
     .filter(user_workspace_table::uid.eq(user_id))
     .load::<UserWorkspaceTable>(&mut *conn)?;
-  Ok(rows.into_iter().map(UserWorkspace::from).collect())
+  let mut user_workspaces = rows
+    .into_iter()
+    .map(UserWorkspace::from)
+    .collect::<Vec<_>>();
+  sort_user_workspaces_op(user_id, &mut user_workspaces, &mut conn)?;
+  Ok(user_workspaces)
+}
+
+/// Sorts the workspaces in the order saved with [save_user_workspace_order_op]. The workspaces
+/// that were never reordered keep their order, after the others.
+pub fn sort_user_workspaces_op(
+  uid: i64,
+  user_workspaces: &mut [UserWorkspace],
+  conn: &mut SqliteConnection,
+) -> Result<(), FlowyError> {
+  let positions = user_workspace_order_table::dsl::user_workspace_order_table
+    .filter(user_workspace_order_table::uid.eq(uid))
+    .select((
+      user_workspace_order_table::workspace_id,
+      user_workspace_order_table::position,
+    ))
+    .load::<(String, i32)>(conn)?
+    .into_iter()
+    .collect::<HashMap<_, _>>();
+  user_workspaces
+    .sort_by_key(|workspace| positions.get(&workspace.id).copied().unwrap_or(i32::MAX));
+  Ok(())
+}
+
+/// Saves the order of the workspaces of the user, the position of each workspace being its
+/// index in `workspace_ids`.
+pub fn save_user_workspace_order_op(
+  uid: i64,
+  workspace_ids: &[String],
+  mut conn: DBConnection,
+) -> Result<(), FlowyError> {
+  conn.immediate_transaction(|conn| {
+    diesel::delete(
+      user_workspace_order_table::dsl::user_workspace_order_table
+        .filter(user_workspace_order_table::uid.eq(uid)),
+    )
+    .execute(conn)?;
+    for (position, workspace_id) in workspace_ids.iter().enumerate() {
+      diesel::insert_into(user_workspace_order_table::table)
+        .values((
+          user_workspace_order_table::uid.eq(uid),
+          user_workspace_order_table::workspace_id.eq(workspace_id),
+          user_workspace_order_table::position.eq(position as i32),
+        ))
+        .execute(conn)?;
+    }
+    Ok(())
+  })
 }
 
 /// Remove all existing workspaces for given user and insert the new ones.
@@ -34,7 +34,8 @@ use crate::services::sqlite_sql::member_sql::{
 };
 use crate::services::sqlite_sql::user_sql::UserTableChangeset;
 use crate::services::sqlite_sql::workspace_sql::{
-  get_all_user_workspace_op, get_user_workspace_op, insert_new_workspaces_op, UserWorkspaceTable,
+  get_all_user_workspace_op, get_user_workspace_op, insert_new_workspaces_op,
+  save_user_workspace_order_op, sort_user_workspaces_op, UserWorkspaceTable,
 };
 use crate::user_manager::{upsert_user_profile_change, UserManager};
 use flowy_user_pub::session::Session;
@@ -401,9 +402,12 @@ impl UserManager {
     if let Ok(service) = self.cloud_services.get_user_service() {
       if let Ok(pool) = self.db_pool(uid) {
         af_spawn(async move {
-          if let Ok(new_user_workspaces) = service.get_all_workspace(uid).await {
+          if let Ok(mut new_user_workspaces) = service.get_all_workspace(uid).await {
             if let Ok(conn) = pool.get() {
               let _ = save_all_user_workspaces(uid, conn, &new_user_workspaces);
+              if let Ok(mut conn) = pool.get() {
+                let _ = sort_user_workspaces_op(uid, &mut new_user_workspaces, &mut conn);
+              }
               let repeated_workspace_pbs = RepeatedUserWorkspacePB::from(new_user_workspaces);
               send_notification(&uid.to_string(), UserNotification::DidUpdateUserWorkspaces)
                 .payload(repeated_workspace_pbs)
@@ -416,6 +420,17 @@ impl UserManager {
     Ok(workspaces)
   }
 
+  /// Saves the order of the workspaces of the user, the first id being the first workspace of
+  /// the list, and notifies the client with the reordered workspaces.
+  pub async fn reorder_workspaces(&self, uid: i64, workspace_ids: Vec<String>) -> FlowyResult<()> {
+    save_user_workspace_order_op(uid, &workspace_ids, self.db_connection(uid)?)?;
+    let workspaces = get_all_user_workspace_op(uid, self.db_connection(uid)?)?;
+    send_notification(&uid.to_string(), UserNotification::DidUpdateUserWorkspaces)
+      .payload(RepeatedUserWorkspacePB::from(workspaces))
+      .send();
+    Ok(())
+  }
+
   /// Reset the remote workspace using local workspace data. This is useful when a user wishes to
   /// open a workspace on a new device that hasn't fully synchronized with the server.
   pub async fn reset_workspace(&self, reset: ResetWorkspacePB) -> FlowyResult<()> {
@@ -0,0 +1,88 @@
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use futures::future::join_all;
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use tokio::task::LocalSet;
+
+const SMALL_PAYLOAD_SIZE: usize = 64;
+const LARGE_PAYLOAD_SIZE: usize = 1024 * 1024;
+const BATCH_SIZE: usize = 128;
+
+async fn empty() {}
+
+async fn echo(data: String) -> String {
+  data
+}
+
+fn dispatcher() -> (Arc<AFPluginRuntime>, Arc<AFPluginDispatcher>) {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime.clone(),
+    vec![AFPlugin::new()
+      .name("bench")
+      .event("empty", empty)
+      .event("echo", echo)],
+  ));
+  (runtime, dispatch)
+}
+
+fn request(payload_size: usize) -> AFPluginRequest {
+  if payload_size == 0 {
+    AFPluginRequest::new("empty")
+  } else {
+    AFPluginRequest::new("echo").payload("x".repeat(payload_size))
+  }
+}
+
+/// The time from queueing a request to getting its response back, one request at a time.
+fn latency(c: &mut Criterion) {
+  let (runtime, dispatch) = dispatcher();
+  let local_set = LocalSet::new();
+  let mut group = c.benchmark_group("dispatch_latency");
+  for (name, size) in [
+    ("empty", 0),
+    ("small", SMALL_PAYLOAD_SIZE),
+    ("large", LARGE_PAYLOAD_SIZE),
+  ] {
+    group.throughput(Throughput::Bytes(size as u64));
+    group.bench_function(BenchmarkId::from_parameter(name), |b| {
+      b.iter(|| {
+        runtime.block_on(local_set.run_until(AFPluginDispatcher::async_send(
+          dispatch.as_ref(),
+          request(size),
+        )))
+      })
+    });
+  }
+  group.finish();
+  std::mem::forget(dispatch);
+}
+
+/// The number of requests handled per second when [BATCH_SIZE] requests are in flight at once.
+fn throughput(c: &mut Criterion) {
+  let (runtime, dispatch) = dispatcher();
+  let local_set = LocalSet::new();
+  let mut group = c.benchmark_group("dispatch_throughput");
+  group.throughput(Throughput::Elements(BATCH_SIZE as u64));
+  for (name, size) in [
+    ("empty", 0),
+    ("small", SMALL_PAYLOAD_SIZE),
+    ("large", LARGE_PAYLOAD_SIZE),
+  ] {
+    group.bench_function(BenchmarkId::from_parameter(name), |b| {
+      b.iter(|| {
+        let requests =
+          (0..BATCH_SIZE).map(|_| AFPluginDispatcher::async_send(dispatch.as_ref(), request(size)));
+        runtime.block_on(local_set.run_until(join_all(requests)))
+      })
+    });
+  }
+  group.finish();
+  std::mem::forget(dispatch);
+}
+
+criterion_group!(benches, latency, throughput);
+criterion_main!(benches);
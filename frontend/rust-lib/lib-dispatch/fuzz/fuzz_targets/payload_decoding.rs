@@ -0,0 +1,70 @@
+//! Feeds random payloads to the extractors of the built-in payload encodings.
+//!
+//! Run with `cargo +nightly fuzz run payload_decoding` from `lib-dispatch`.
+#![no_main]
+
+use std::sync::Arc;
+
+use lib_dispatch::fuzz::{fuzz_event, FuzzInput, FuzzOutcome};
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use libfuzzer_sys::fuzz_target;
+use serde::{Deserialize, Serialize};
+use tokio::task::LocalSet;
+
+#[derive(Serialize, Deserialize)]
+struct Cells {
+  row: u32,
+  values: Vec<f64>,
+}
+
+async fn sum_json(cells: Json<Cells>) -> Json<f64> {
+  Json(cells.values.iter().sum())
+}
+
+async fn sum_msgpack(cells: MsgPack<Cells>) -> MsgPack<f64> {
+  MsgPack(cells.values.iter().sum())
+}
+
+struct Target {
+  runtime: tokio::runtime::Runtime,
+  dispatcher: Arc<AFPluginDispatcher>,
+  events: Vec<AFPluginEvent>,
+}
+
+thread_local! {
+  static TARGET: Target = {
+    let plugin = AFPlugin::new()
+      .name("cells")
+      .event("sum_json", sum_json)
+      .event("sum_msgpack", sum_msgpack);
+    #[allow(clippy::arc_with_non_send_sync)]
+    let dispatcher = Arc::new(AFPluginDispatcher::new(
+      Arc::new(AFPluginRuntime::new().unwrap()),
+      vec![plugin],
+    ));
+    let events = dispatcher.events();
+    Target {
+      runtime: tokio::runtime::Builder::new_current_thread().build().unwrap(),
+      dispatcher,
+      events,
+    }
+  };
+}
+
+fuzz_target!(|input: FuzzInput| {
+  TARGET.with(|target| {
+    if let Some(event) = input.event(&target.events) {
+      let outcome = target
+        .runtime
+        .block_on(LocalSet::new().run_until(fuzz_event(
+          target.dispatcher.as_ref(),
+          event,
+          input.payload.clone(),
+        )));
+      if let FuzzOutcome::Crashed(msg) = outcome {
+        panic!("{} crashed: {}", event.as_str(), msg);
+      }
+    }
+  });
+});
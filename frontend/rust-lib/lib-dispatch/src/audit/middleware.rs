@@ -0,0 +1,74 @@
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::audit::{AuditRecord, AuditStore};
+use crate::middleware::AFPluginMiddleware;
+use crate::module::AFPluginRequest;
+use crate::response::{AFPluginEventResponse, StatusCode};
+
+type RequestFilter = Box<dyn Fn(&AFPluginRequest) -> bool + Send + Sync>;
+type IdentityProvider = Box<dyn Fn() -> Option<String> + Send + Sync>;
+
+/// Writes an [AuditRecord] for every selected request once its response is ready.
+///
+/// The records are appended on the dispatch thread: the stores writing to the disk, like the
+/// [FileAuditStore](crate::audit::FileAuditStore), should be wrapped in a
+/// [BufferedAuditStore](crate::audit::BufferedAuditStore).
+pub struct AuditMiddleware {
+  store: Arc<dyn AuditStore>,
+  filter: RequestFilter,
+  identity: IdentityProvider,
+}
+
+impl AuditMiddleware {
+  /// Audits the requests of the events their plugin declared as
+  /// [mutating](crate::prelude::AFPlugin::mutating).
+  pub fn new(store: Arc<dyn AuditStore>) -> Self {
+    Self {
+      store,
+      filter: Box::new(AFPluginRequest::is_mutating),
+      identity: Box::new(|| None),
+    }
+  }
+
+  pub fn with_filter<F>(mut self, filter: F) -> Self
+  where
+    F: Fn(&AFPluginRequest) -> bool + Send + Sync + 'static,
+  {
+    self.filter = Box::new(filter);
+    self
+  }
+
+  /// Resolves who sent the request, for example the id of the current user.
+  pub fn with_identity<F>(mut self, identity: F) -> Self
+  where
+    F: Fn() -> Option<String> + Send + Sync + 'static,
+  {
+    self.identity = Box::new(identity);
+    self
+  }
+}
+
+impl AFPluginMiddleware for AuditMiddleware {
+  fn on_response(&self, request: &AFPluginRequest, response: &mut AFPluginEventResponse) {
+    if !(self.filter)(request) {
+      return;
+    }
+
+    let timestamp = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map(|d| d.as_millis() as u64)
+      .unwrap_or_default();
+    let record = AuditRecord {
+      timestamp,
+      user: (self.identity)(),
+      event: request.event.as_str().to_owned(),
+      request_id: request.id.clone(),
+      payload_size: request.payload.as_ref().len(),
+      success: response.status_code == StatusCode::Ok,
+    };
+    if let Err(err) = self.store.append(record) {
+      tracing::error!("[audit]: append record failed: {}", err);
+    }
+  }
+}
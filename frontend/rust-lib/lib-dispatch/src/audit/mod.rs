@@ -0,0 +1,102 @@
+//! The audit log keeps a durable, append-only record of the mutating events: who sent them, what
+//! was sent and when.
+//!
+//! The [AuditMiddleware] writes the events the plugins declared as
+//! [mutating](crate::prelude::AFPlugin::mutating) to an [AuditStore] and the [audit_plugin]
+//! exposes the records through the [AuditEvent::Query] event.
+pub use middleware::*;
+pub use store::*;
+
+mod middleware;
+mod store;
+
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use crate::byte_trait::AFPluginFromBytes;
+use crate::data::AFPluginData;
+use crate::errors::{DispatchError, InternalError};
+use crate::module::{AFPlugin, AFPluginState};
+
+pub const AUDIT_PLUGIN_NAME: &str = "lib-dispatch-audit";
+
+/// Required to read the audit log.
+pub const AUDIT_CAPABILITY: &str = "audit.read";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AuditEvent {
+  /// Returns the audit records matching the [AuditQuery] payload as a JSON array.
+  Query,
+}
+
+impl Display for AuditEvent {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      AuditEvent::Query => f.write_str("AuditQuery"),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AuditRecord {
+  /// The time the request finished, in milliseconds since the unix epoch.
+  pub timestamp: u64,
+  pub user: Option<String>,
+  pub event: String,
+  pub request_id: String,
+  pub payload_size: usize,
+  pub success: bool,
+}
+
+/// Filters the audit records. All the conditions are optional.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditQuery {
+  #[serde(default)]
+  pub event: Option<String>,
+  #[serde(default)]
+  pub user: Option<String>,
+  /// Only returns the records created at or after this time, in milliseconds since the unix
+  /// epoch.
+  #[serde(default)]
+  pub since: Option<u64>,
+  /// Returns at most `limit` records, the most recent ones.
+  #[serde(default)]
+  pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+  pub fn matches(&self, record: &AuditRecord) -> bool {
+    self.event.as_ref().map_or(true, |e| e == &record.event)
+      && self
+        .user
+        .as_ref()
+        .map_or(true, |u| Some(u) == record.user.as_ref())
+      && self.since.map_or(true, |since| record.timestamp >= since)
+  }
+}
+
+impl AFPluginFromBytes for AuditQuery {
+  fn parse_from_bytes(bytes: Bytes) -> Result<Self, DispatchError> {
+    serde_json::from_slice(&bytes)
+      .map_err(|e| InternalError::DeserializeFromBytes(e.to_string()).into())
+  }
+}
+
+pub fn audit_plugin(store: Arc<dyn AuditStore>) -> AFPlugin {
+  AFPlugin::new()
+    .name(AUDIT_PLUGIN_NAME)
+    .state(store)
+    .event(AuditEvent::Query, query_audit_handler)
+    .requires_capability(AuditEvent::Query, AUDIT_CAPABILITY)
+}
+
+async fn query_audit_handler(
+  query: AFPluginData<AuditQuery>,
+  store: AFPluginState<Arc<dyn AuditStore>>,
+) -> Result<String, DispatchError> {
+  let records = store.query(&query.into_inner())?;
+  serde_json::to_string(&records).map_err(|e| InternalError::Other(e.to_string()).into())
+}
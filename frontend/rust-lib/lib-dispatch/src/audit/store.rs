@@ -0,0 +1,213 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::JoinHandle;
+
+use crate::audit::{AuditQuery, AuditRecord};
+use crate::errors::{DispatchError, InternalError};
+
+/// An append-only store for the [AuditRecord]s.
+pub trait AuditStore: Send + Sync + 'static {
+  fn append(&self, record: AuditRecord) -> Result<(), DispatchError>;
+
+  /// Returns the records matching the query, from the oldest to the newest.
+  fn query(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>, DispatchError>;
+}
+
+const AUDIT_FILE_NAME: &str = "audit.log";
+const DEFAULT_MAX_FILE_SIZE: u64 = 1024 * 1024;
+const DEFAULT_MAX_FILES: usize = 5;
+
+/// Stores the records as JSON lines in `audit.log`. Once the file exceeds the maximum size, it's
+/// rotated to `audit.log.1`, the previous `audit.log.1` becomes `audit.log.2` and so on. The
+/// oldest file is removed when there are more than `max_files` files.
+pub struct FileAuditStore {
+  dir: PathBuf,
+  max_file_size: u64,
+  max_files: usize,
+  file: Mutex<Option<File>>,
+}
+
+impl FileAuditStore {
+  pub fn new<P: AsRef<Path>>(dir: P) -> Result<Self, DispatchError> {
+    let dir = dir.as_ref().to_path_buf();
+    fs::create_dir_all(&dir).map_err(io_error)?;
+    Ok(Self {
+      dir,
+      max_file_size: DEFAULT_MAX_FILE_SIZE,
+      max_files: DEFAULT_MAX_FILES,
+      file: Mutex::new(None),
+    })
+  }
+
+  pub fn with_rotation(mut self, max_file_size: u64, max_files: usize) -> Self {
+    self.max_file_size = max_file_size;
+    self.max_files = max_files.max(1);
+    self
+  }
+
+  fn path(&self, index: usize) -> PathBuf {
+    if index == 0 {
+      self.dir.join(AUDIT_FILE_NAME)
+    } else {
+      self.dir.join(format!("{}.{}", AUDIT_FILE_NAME, index))
+    }
+  }
+
+  fn rotate_if_needed(&self, file: &mut Option<File>) -> Result<(), DispatchError> {
+    let size = fs::metadata(self.path(0)).map(|m| m.len()).unwrap_or(0);
+    if size < self.max_file_size {
+      return Ok(());
+    }
+
+    // Close the current file before renaming it.
+    file.take();
+    let _ = fs::remove_file(self.path(self.max_files - 1));
+    for index in (0..self.max_files - 1).rev() {
+      let from = self.path(index);
+      if from.exists() {
+        fs::rename(&from, self.path(index + 1)).map_err(io_error)?;
+      }
+    }
+    Ok(())
+  }
+}
+
+impl AuditStore for FileAuditStore {
+  fn append(&self, record: AuditRecord) -> Result<(), DispatchError> {
+    let mut line =
+      serde_json::to_string(&record).map_err(|e| InternalError::Other(e.to_string()))?;
+    line.push('\n');
+
+    let mut file = self
+      .file
+      .lock()
+      .map_err(|e| InternalError::Other(e.to_string()))?;
+    self.rotate_if_needed(&mut file)?;
+    if file.is_none() {
+      let f = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(self.path(0))
+        .map_err(io_error)?;
+      *file = Some(f);
+    }
+
+    if let Some(f) = file.as_mut() {
+      f.write_all(line.as_bytes()).map_err(io_error)?;
+    }
+    Ok(())
+  }
+
+  fn query(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>, DispatchError> {
+    let mut records = vec![];
+    for index in (0..self.max_files).rev() {
+      let file = match File::open(self.path(index)) {
+        Ok(file) => file,
+        Err(_) => continue,
+      };
+      for line in BufReader::new(file).lines() {
+        let line = line.map_err(io_error)?;
+        match serde_json::from_str::<AuditRecord>(&line) {
+          Ok(record) if query.matches(&record) => records.push(record),
+          Ok(_) => {},
+          // A partially written line after a crash should not make the whole log unreadable.
+          Err(err) => tracing::warn!("[audit]: skip invalid record: {}", err),
+        }
+      }
+    }
+
+    if let Some(limit) = query.limit {
+      let skip = records.len().saturating_sub(limit);
+      records.drain(..skip);
+    }
+    Ok(records)
+  }
+}
+
+enum AuditCommand {
+  Append(AuditRecord),
+  /// Answered once the records sent before it are written.
+  Flush(mpsc::SyncSender<()>),
+}
+
+/// Appends the records to another store on a background thread, so recording a request never
+/// waits for the disk. The records are written in order, [AuditStore::query] waits for the
+/// pending ones first, and dropping the store writes the pending ones.
+pub struct BufferedAuditStore<S> {
+  inner: Arc<S>,
+  commands: Option<mpsc::Sender<AuditCommand>>,
+  writer: Option<JoinHandle<()>>,
+}
+
+impl<S: AuditStore> BufferedAuditStore<S> {
+  pub fn new(inner: S) -> Result<Self, DispatchError> {
+    let inner = Arc::new(inner);
+    let (tx, rx) = mpsc::channel::<AuditCommand>();
+    let store = inner.clone();
+    let writer = std::thread::Builder::new()
+      .name("audit-writer".to_owned())
+      .spawn(move || {
+        for command in rx {
+          match command {
+            AuditCommand::Append(record) => {
+              if let Err(err) = store.append(record) {
+                tracing::error!("[audit]: append record failed: {}", err);
+              }
+            },
+            AuditCommand::Flush(done) => {
+              let _ = done.send(());
+            },
+          }
+        }
+      })
+      .map_err(io_error)?;
+    Ok(Self {
+      inner,
+      commands: Some(tx),
+      writer: Some(writer),
+    })
+  }
+
+  /// Waits until the records appended so far are written.
+  pub fn flush(&self) {
+    let (tx, rx) = mpsc::sync_channel(1);
+    if self.send(AuditCommand::Flush(tx)).is_ok() {
+      let _ = rx.recv();
+    }
+  }
+
+  fn send(&self, command: AuditCommand) -> Result<(), DispatchError> {
+    self
+      .commands
+      .as_ref()
+      .and_then(|commands| commands.send(command).ok())
+      .ok_or_else(|| InternalError::Other("the audit writer stopped".to_owned()).into())
+  }
+}
+
+impl<S: AuditStore> AuditStore for BufferedAuditStore<S> {
+  fn append(&self, record: AuditRecord) -> Result<(), DispatchError> {
+    self.send(AuditCommand::Append(record))
+  }
+
+  fn query(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>, DispatchError> {
+    self.flush();
+    self.inner.query(query)
+  }
+}
+
+impl<S> Drop for BufferedAuditStore<S> {
+  fn drop(&mut self) {
+    // Closing the channel stops the writer once it wrote the pending records.
+    self.commands.take();
+    if let Some(writer) = self.writer.take() {
+      let _ = writer.join();
+    }
+  }
+}
+
+fn io_error(err: std::io::Error) -> DispatchError {
+  InternalError::Other(format!("audit store: {}", err)).into()
+}
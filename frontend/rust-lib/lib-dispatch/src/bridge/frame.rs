@@ -0,0 +1,222 @@
+//! The binary frames shared by the WebSocket and the local socket bridges. Their format is
+//! described in [serve_framed](super::serve_framed).
+
+use std::convert::TryFrom;
+
+use bytes::Bytes;
+
+use crate::capability::Capabilities;
+use crate::encoding::ContentType;
+use crate::middleware::{AUTHORIZATION_METADATA, SIGNATURE_METADATA, TRANSPORT_METADATA};
+use crate::module::AFPluginRequest;
+use crate::response::{AFPluginEventResponse, StatusCode};
+
+/// The first byte of the frames sent by the server.
+pub(crate) const RESPONSE_FRAME: u8 = 0;
+#[cfg_attr(not(feature = "ws_bridge"), allow(dead_code))]
+pub(crate) const NOTIFICATION_FRAME: u8 = 1;
+
+/// Set on the event length of a request frame whose event is followed by a content type byte.
+const TAGGED_EVENT: u16 = 0x8000;
+/// Set on the event length of a request frame whose event, and content type byte, are followed
+/// by a signature.
+const SIGNED_EVENT: u16 = 0x4000;
+/// Set on the event length of a request frame whose event, content type byte and signature are
+/// followed by a session token.
+const AUTHORIZED_EVENT: u16 = 0x2000;
+/// The longest event a request frame can carry, in the bits of the event length left by the flags.
+pub(crate) const MAX_EVENT_LEN: usize = 0x1FFF;
+/// Set on the status code of a response frame followed by a content type byte. The responses
+/// are only tagged when their request is, so the clients that don't tag their requests read the
+/// frames they always did.
+const TAGGED_STATUS: u8 = 0x80;
+
+/// The byte a content type is tagged with in the frames, 0 when it's unknown.
+fn content_type_byte(content_type: Option<ContentType>) -> u8 {
+  match content_type {
+    None => 0,
+    Some(ContentType::Protobuf) => 1,
+    Some(ContentType::Json) => 2,
+    #[cfg(feature = "use_msgpack")]
+    Some(ContentType::MessagePack) => 3,
+    #[cfg(feature = "use_cbor")]
+    Some(ContentType::Cbor) => 4,
+    #[cfg(feature = "use_bincode")]
+    Some(ContentType::Bincode) => 5,
+  }
+}
+
+fn content_type_from_byte(byte: u8) -> Result<Option<ContentType>, String> {
+  if byte == 0 {
+    return Ok(None);
+  }
+  ContentType::supported()
+    .into_iter()
+    .find(|content_type| content_type_byte(Some(*content_type)) == byte)
+    .map(Some)
+    .ok_or_else(|| format!("unsupported content type {}", byte))
+}
+
+/// Reads a string prefixed by its length on a u16 at `start`, returns it with its end.
+fn read_str<'a>(frame: &'a [u8], start: usize, what: &str) -> Result<(&'a str, usize), String> {
+  let out_of_frame = || format!("{} out of the frame", what);
+  let len = frame.get(start..start + 2).ok_or_else(out_of_frame)?;
+  let end = start + 2 + u16::from_be_bytes([len[0], len[1]]) as usize;
+  let value = frame.get(start + 2..end).ok_or_else(out_of_frame)?;
+  let value = std::str::from_utf8(value).map_err(|e| e.to_string())?;
+  Ok((value, end))
+}
+
+/// A request frame: the request id, the request and whether the frame was tagged, even with the
+/// unknown content type 0, in which case its response must be tagged too.
+pub(crate) type RequestFrame = (u32, AFPluginRequest, bool);
+
+/// The request is tagged with the `transport` of the bridge that received the frame, see
+/// [TRANSPORT_METADATA], and granted no capability until it's authenticated.
+pub(crate) fn decode_request(frame: &[u8], transport: &str) -> Result<RequestFrame, String> {
+  if frame.len() < 6 {
+    return Err(format!("frame too short: {} bytes", frame.len()));
+  }
+  let id = u32::from_be_bytes([frame[0], frame[1], frame[2], frame[3]]);
+  let event_len = u16::from_be_bytes([frame[4], frame[5]]);
+  let tagged = event_len & TAGGED_EVENT != 0;
+  let signed = event_len & SIGNED_EVENT != 0;
+  let authorized = event_len & AUTHORIZED_EVENT != 0;
+  let event_len = (event_len & !(TAGGED_EVENT | SIGNED_EVENT | AUTHORIZED_EVENT)) as usize;
+  let event = frame
+    .get(6..6 + event_len)
+    .ok_or_else(|| "event out of the frame".to_owned())?;
+  let event = std::str::from_utf8(event).map_err(|e| e.to_string())?;
+  let mut payload_start = 6 + event_len;
+  let mut request = AFPluginRequest::new(event)
+    .metadata(TRANSPORT_METADATA, transport)
+    .capabilities(Capabilities::new());
+  if tagged {
+    let byte = frame
+      .get(payload_start)
+      .ok_or_else(|| "content type out of the frame".to_owned())?;
+    if let Some(content_type) = content_type_from_byte(*byte)? {
+      request = request.content_type(content_type);
+    }
+    payload_start += 1;
+  }
+  if signed {
+    let (signature, end) = read_str(frame, payload_start, "signature")?;
+    request = request.metadata(SIGNATURE_METADATA, signature);
+    payload_start = end;
+  }
+  if authorized {
+    let (token, end) = read_str(frame, payload_start, "token")?;
+    request = request.metadata(AUTHORIZATION_METADATA, token);
+    payload_start = end;
+  }
+  let payload = frame[payload_start..].to_vec();
+  if !payload.is_empty() {
+    request = request.payload(payload);
+  }
+  Ok((id, request, tagged))
+}
+
+/// The content type is only tagged when it's known, like the clients that predate the tag do.
+/// Fails if the event is longer than [MAX_EVENT_LEN], or the token longer than a u16 can tell.
+#[cfg_attr(not(unix), allow(dead_code))]
+pub(crate) fn encode_request(
+  id: u32,
+  event: &str,
+  content_type: Option<ContentType>,
+  token: Option<&str>,
+  payload: &[u8],
+) -> Result<Vec<u8>, String> {
+  if event.len() > MAX_EVENT_LEN {
+    return Err(format!(
+      "event of {} bytes exceeds {} bytes",
+      event.len(),
+      MAX_EVENT_LEN
+    ));
+  }
+  let token = match token {
+    Some(token) => match u16::try_from(token.len()) {
+      Ok(len) => Some((token, len)),
+      Err(_) => return Err(format!("token of {} bytes", token.len())),
+    },
+    None => None,
+  };
+  let mut frame = Vec::with_capacity(event.len() + payload.len() + 7);
+  frame.extend_from_slice(&id.to_be_bytes());
+  let mut event_len = event.len() as u16;
+  if content_type.is_some() {
+    event_len |= TAGGED_EVENT;
+  }
+  if token.is_some() {
+    event_len |= AUTHORIZED_EVENT;
+  }
+  frame.extend_from_slice(&event_len.to_be_bytes());
+  frame.extend_from_slice(event.as_bytes());
+  if content_type.is_some() {
+    frame.push(content_type_byte(content_type));
+  }
+  if let Some((token, len)) = token {
+    frame.extend_from_slice(&len.to_be_bytes());
+    frame.extend_from_slice(token.as_bytes());
+  }
+  frame.extend_from_slice(payload);
+  Ok(frame)
+}
+
+/// Tags the content type of the response if `tagged`, i.e. if its request was tagged.
+pub(crate) fn encode_response(id: u32, response: AFPluginEventResponse, tagged: bool) -> Vec<u8> {
+  let payload = response.payload.as_ref();
+  let mut frame = Vec::with_capacity(payload.len() + 7);
+  frame.push(RESPONSE_FRAME);
+  frame.extend_from_slice(&id.to_be_bytes());
+  let status = match response.status_code {
+    StatusCode::Ok => 0,
+    StatusCode::Err => 1,
+  };
+  if tagged {
+    frame.push(status | TAGGED_STATUS);
+    frame.push(content_type_byte(response.content_type));
+  } else {
+    frame.push(status);
+  }
+  frame.extend_from_slice(payload);
+  frame
+}
+
+/// A response frame: the request id, the status code, the content type if tagged and the payload.
+pub(crate) type ResponseFrame = (u32, StatusCode, Option<ContentType>, Bytes);
+
+/// Returns `None` for the notification frames.
+#[cfg_attr(not(unix), allow(dead_code))]
+pub(crate) fn decode_response(frame: &[u8]) -> Result<Option<ResponseFrame>, String> {
+  match frame.first() {
+    None => Err("empty frame".to_owned()),
+    Some(&RESPONSE_FRAME) if frame.len() >= 6 => {
+      let id = u32::from_be_bytes([frame[1], frame[2], frame[3], frame[4]]);
+      let status_code = match frame[5] & !TAGGED_STATUS {
+        0 => StatusCode::Ok,
+        _ => StatusCode::Err,
+      };
+      if frame[5] & TAGGED_STATUS == 0 {
+        return Ok(Some((
+          id,
+          status_code,
+          None,
+          Bytes::copy_from_slice(&frame[6..]),
+        )));
+      }
+      let byte = frame
+        .get(6)
+        .ok_or_else(|| "content type out of the frame".to_owned())?;
+      let content_type = content_type_from_byte(*byte)?;
+      Ok(Some((
+        id,
+        status_code,
+        content_type,
+        Bytes::copy_from_slice(&frame[7..]),
+      )))
+    },
+    Some(&RESPONSE_FRAME) => Err(format!("frame too short: {} bytes", frame.len())),
+    Some(_) => Ok(None),
+  }
+}
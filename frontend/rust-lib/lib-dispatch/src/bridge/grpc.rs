@@ -0,0 +1,262 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use hyper::header::{HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use hyper::server::conn::Http;
+use hyper::service::service_fn;
+use hyper::{Body, HeaderMap, Method, Request, Response};
+use tokio::net::TcpListener;
+
+use super::{LocalExec, SIGNATURE_HEADER};
+use crate::capability::Capabilities;
+use crate::encoding::ContentType;
+use crate::middleware::{
+  Unauthorized, AUTHORIZATION_METADATA, SIGNATURE_METADATA, TRANSPORT_METADATA,
+};
+use crate::module::{AFPluginEvent, AFPluginRequest};
+use crate::payload_schema::InvalidPayload;
+use crate::prelude::AFPluginDispatcher;
+use crate::quota::QuotaExceeded;
+use crate::response::{AFPluginEventResponse, StatusCode};
+
+pub const DEFAULT_GRPC_SERVICE: &str = "appflowy.Dispatcher";
+
+const GRPC_CONTENT_TYPE: &str = "application/grpc";
+const GRPC_OK: u32 = 0;
+const GRPC_UNKNOWN: u32 = 2;
+const GRPC_INVALID_ARGUMENT: u32 = 3;
+const GRPC_RESOURCE_EXHAUSTED: u32 = 8;
+const GRPC_UNIMPLEMENTED: u32 = 12;
+const GRPC_UNAUTHENTICATED: u32 = 16;
+
+type ErrorMessage = Arc<dyn Fn(&AFPluginEventResponse) -> String + Send + Sync>;
+
+/// Serves the dispatcher as a gRPC service over HTTP/2.
+///
+/// Every registered event is a unary method of the service, see [grpc_methods]. The messages are
+/// the protobuf payloads of the events, so the requests go through the same middlewares as the
+/// ones sent by the other frontends. The responses whose handler returned an error get the
+/// `UNKNOWN` status, with the message built by [GrpcGateway::with_error_message].
+pub struct GrpcGateway {
+  addr: SocketAddr,
+  service: String,
+  error_message: Option<ErrorMessage>,
+}
+
+impl GrpcGateway {
+  pub fn new(addr: SocketAddr) -> Self {
+    Self {
+      addr,
+      service: DEFAULT_GRPC_SERVICE.to_owned(),
+      error_message: None,
+    }
+  }
+
+  /// The fully qualified name of the service, [DEFAULT_GRPC_SERVICE] by default.
+  pub fn with_service(mut self, service: &str) -> Self {
+    self.service = service.to_owned();
+    self
+  }
+
+  /// Builds the `grpc-message` of the error responses, which is empty by default.
+  pub fn with_error_message<F>(mut self, f: F) -> Self
+  where
+    F: Fn(&AFPluginEventResponse) -> String + Send + Sync + 'static,
+  {
+    self.error_message = Some(Arc::new(f));
+    self
+  }
+
+  /// Accepts the connections until the listener fails. Like [AFPluginDispatcher::async_send],
+  /// it must run inside a `LocalSet`.
+  pub async fn serve(self, dispatcher: Arc<AFPluginDispatcher>) -> std::io::Result<()> {
+    let listener = TcpListener::bind(self.addr).await?;
+    self.serve_listener(listener, dispatcher).await
+  }
+
+  pub async fn serve_listener(
+    self,
+    listener: TcpListener,
+    dispatcher: Arc<AFPluginDispatcher>,
+  ) -> std::io::Result<()> {
+    let gateway = Arc::new(self);
+    loop {
+      let (stream, peer) = listener.accept().await?;
+      let dispatcher = dispatcher.clone();
+      let gateway = gateway.clone();
+      tokio::task::spawn_local(async move {
+        let service = service_fn(move |request| {
+          let gateway = gateway.clone();
+          let dispatcher = dispatcher.clone();
+          async move { gateway.handle_request(&dispatcher, request).await }
+        });
+        if let Err(err) = Http::new()
+          .with_executor(LocalExec)
+          .http2_only(true)
+          .serve_connection(stream, service)
+          .await
+        {
+          tracing::debug!("[gRPC]: connection {} closed: {}", peer, err);
+        }
+      });
+    }
+  }
+
+  async fn handle_request(
+    &self,
+    dispatcher: &AFPluginDispatcher,
+    request: Request<Body>,
+  ) -> Result<Response<Body>, Infallible> {
+    let is_grpc = request
+      .headers()
+      .get(CONTENT_TYPE)
+      .and_then(|value| value.to_str().ok())
+      .map(|value| value.starts_with(GRPC_CONTENT_TYPE))
+      .unwrap_or(false);
+    if request.method() != Method::POST || !is_grpc {
+      return Ok(Response::builder().status(415).body(Body::empty()).unwrap());
+    }
+
+    let event = request
+      .uri()
+      .path()
+      .strip_prefix('/')
+      .and_then(|path| path.split_once('/'))
+      .filter(|(service, _)| *service == self.service)
+      .map(|(_, method)| AFPluginEvent::from(method));
+    let event = match event {
+      Some(event) if dispatcher.has_event(&event) => event,
+      _ => return Ok(reply(None, GRPC_UNIMPLEMENTED, "")),
+    };
+
+    let authorization = request
+      .headers()
+      .get(AUTHORIZATION)
+      .and_then(|value| value.to_str().ok())
+      .map(|value| value.to_owned());
+    let signature = request
+      .headers()
+      .get(SIGNATURE_HEADER)
+      .and_then(|value| value.to_str().ok())
+      .map(|value| value.to_owned());
+    let body = match hyper::body::to_bytes(request.into_body()).await {
+      Ok(body) => body,
+      Err(err) => return Ok(reply(None, GRPC_INVALID_ARGUMENT, &err.to_string())),
+    };
+    let payload = match decode_message(&body) {
+      Ok(payload) => payload,
+      Err(msg) => return Ok(reply(None, GRPC_INVALID_ARGUMENT, msg)),
+    };
+
+    let mut request = AFPluginRequest::new(event)
+      .content_type(ContentType::Protobuf)
+      .metadata(TRANSPORT_METADATA, "grpc")
+      .capabilities(Capabilities::new());
+    if let Some(authorization) = authorization {
+      request = request.metadata(AUTHORIZATION_METADATA, authorization);
+    }
+    if let Some(signature) = signature {
+      request = request.metadata(SIGNATURE_METADATA, signature);
+    }
+    if !payload.is_empty() {
+      request = request.payload(payload.to_vec());
+    }
+    let response = AFPluginDispatcher::async_send(dispatcher, request).await;
+    if let Some(invalid) = InvalidPayload::from_response(&response) {
+      return Ok(reply(None, GRPC_INVALID_ARGUMENT, &invalid.to_string()));
+    }
+    Ok(match response.status_code {
+      StatusCode::Ok => reply(Some(response.payload.as_ref()), GRPC_OK, ""),
+      StatusCode::Err if Unauthorized::from_response(&response).is_some() => {
+        reply(None, GRPC_UNAUTHENTICATED, "unauthenticated")
+      },
+      StatusCode::Err if QuotaExceeded::from_response(&response).is_some() => {
+        reply(None, GRPC_RESOURCE_EXHAUSTED, "quota exceeded")
+      },
+      StatusCode::Err => {
+        let msg = self
+          .error_message
+          .as_ref()
+          .map(|f| f(&response))
+          .unwrap_or_default();
+        reply(None, GRPC_UNKNOWN, &msg)
+      },
+    })
+  }
+}
+
+/// The paths of the gRPC methods of the events handled by `dispatcher`.
+pub fn grpc_methods(dispatcher: &AFPluginDispatcher, service: &str) -> Vec<String> {
+  dispatcher
+    .events()
+    .iter()
+    .map(|event| format!("/{}/{}", service, event.as_str()))
+    .collect()
+}
+
+/// Decodes a unary request body: a single uncompressed message prefixed by its compression flag
+/// and its length.
+fn decode_message(body: &[u8]) -> Result<&[u8], &'static str> {
+  if body.is_empty() {
+    return Ok(body);
+  }
+  if body.len() < 5 {
+    return Err("truncated message");
+  }
+  if body[0] != 0 {
+    return Err("compressed messages are not supported");
+  }
+  let len = u32::from_be_bytes([body[1], body[2], body[3], body[4]]) as usize;
+  match body.get(5..) {
+    Some(message) if message.len() == len => Ok(message),
+    _ => Err("only one message is supported"),
+  }
+}
+
+fn encode_message(payload: &[u8]) -> Vec<u8> {
+  let mut frame = Vec::with_capacity(payload.len() + 5);
+  frame.push(0);
+  frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+  frame.extend_from_slice(payload);
+  frame
+}
+
+fn reply(payload: Option<&[u8]>, status: u32, message: &str) -> Response<Body> {
+  let mut trailers = HeaderMap::new();
+  trailers.insert("grpc-status", HeaderValue::from(status));
+  if !message.is_empty() {
+    if let Ok(value) = HeaderValue::from_str(&percent_encode(message)) {
+      trailers.insert("grpc-message", value);
+    }
+  }
+
+  let (mut sender, body) = Body::channel();
+  let data = payload.map(encode_message);
+  tokio::task::spawn_local(async move {
+    if let Some(data) = data {
+      if sender.send_data(data.into()).await.is_err() {
+        return;
+      }
+    }
+    let _ = sender.send_trailers(trailers).await;
+  });
+  Response::builder()
+    .status(200)
+    .header(CONTENT_TYPE, GRPC_CONTENT_TYPE)
+    .body(body)
+    .unwrap()
+}
+
+/// The `grpc-message` is percent-encoded, except for the printable ASCII characters.
+fn percent_encode(message: &str) -> String {
+  let mut encoded = String::with_capacity(message.len());
+  for byte in message.bytes() {
+    if (0x20..0x7f).contains(&byte) && byte != b'%' {
+      encoded.push(byte as char);
+    } else {
+      encoded.push_str(&format!("%{:02X}", byte));
+    }
+  }
+  encoded
+}
@@ -0,0 +1,224 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use hyper::body::HttpBody;
+use hyper::header::{HeaderName, HeaderValue, AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE};
+use hyper::server::conn::Http;
+use hyper::service::service_fn;
+use hyper::{Body, Method, Request, Response};
+use tokio::net::TcpListener;
+
+use super::{LocalExec, SIGNATURE_HEADER};
+use crate::capability::Capabilities;
+use crate::encoding::ContentType;
+use crate::middleware::{
+  Unauthorized, AUTHORIZATION_METADATA, SIGNATURE_METADATA, TRANSPORT_METADATA,
+};
+use crate::module::{AFPluginEvent, AFPluginRequest};
+use crate::prelude::AFPluginDispatcher;
+use crate::quota::QuotaExceeded;
+use crate::response::{AFPluginEventResponse, StatusCode};
+
+const API_PREFIX: &str = "/api";
+const OCTET_STREAM: &str = "application/octet-stream";
+const DEFAULT_MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+
+/// Serves the dispatcher over HTTP/1.1, for local automation.
+///
+/// Every registered event is mapped to `POST /api/{event}`, see [http_routes]. The request body is
+/// the payload of the event and its `Content-Type`, if it is one of the [ContentType]s, tags the
+/// payload. `GET /api` lists the routes as a JSON array. The bodies bigger than the
+/// [max body size](HttpGateway::with_max_body_size), 16 MiB by default, are rejected with `413`
+/// before they are read.
+pub struct HttpGateway {
+  addr: SocketAddr,
+  max_body_size: usize,
+}
+
+impl HttpGateway {
+  pub fn new(addr: SocketAddr) -> Self {
+    Self {
+      addr,
+      max_body_size: DEFAULT_MAX_BODY_SIZE,
+    }
+  }
+
+  /// The size of the biggest request body accepted, in bytes.
+  pub fn with_max_body_size(mut self, max_body_size: usize) -> Self {
+    self.max_body_size = max_body_size;
+    self
+  }
+
+  /// Accepts the connections until the listener fails. Like [AFPluginDispatcher::async_send],
+  /// it must run inside a `LocalSet`.
+  pub async fn serve(self, dispatcher: Arc<AFPluginDispatcher>) -> std::io::Result<()> {
+    let listener = TcpListener::bind(self.addr).await?;
+    self.serve_listener(listener, dispatcher).await
+  }
+
+  pub async fn serve_listener(
+    self,
+    listener: TcpListener,
+    dispatcher: Arc<AFPluginDispatcher>,
+  ) -> std::io::Result<()> {
+    loop {
+      let (stream, peer) = listener.accept().await?;
+      let dispatcher = dispatcher.clone();
+      let max_body_size = self.max_body_size;
+      tokio::task::spawn_local(async move {
+        let service =
+          service_fn(move |request| handle_request(dispatcher.clone(), max_body_size, request));
+        if let Err(err) = Http::new()
+          .with_executor(LocalExec)
+          .http1_only(true)
+          .serve_connection(stream, service)
+          .await
+        {
+          tracing::debug!("[HTTP]: connection {} closed: {}", peer, err);
+        }
+      });
+    }
+  }
+}
+
+/// The routes of the events handled by `dispatcher`.
+pub fn http_routes(dispatcher: &AFPluginDispatcher) -> Vec<String> {
+  dispatcher
+    .events()
+    .iter()
+    .map(|event| format!("{}/{}", API_PREFIX, event.as_str()))
+    .collect()
+}
+
+/// The HTTP status code of a response: `200` for the successful responses, `401` for the
+/// requests rejected by the [AuthMiddleware], `429` for the throttled requests, see
+/// [QuotaExceeded], and `400` for the other errors.
+///
+/// [AuthMiddleware]: crate::middleware::AuthMiddleware
+pub fn http_status(response: &AFPluginEventResponse) -> u16 {
+  match response.status_code {
+    StatusCode::Ok => 200,
+    StatusCode::Err if Unauthorized::from_response(response).is_some() => 401,
+    StatusCode::Err if QuotaExceeded::from_response(response).is_some() => 429,
+    StatusCode::Err => 400,
+  }
+}
+
+async fn handle_request(
+  dispatcher: Arc<AFPluginDispatcher>,
+  max_body_size: usize,
+  request: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+  let path = request.uri().path().trim_end_matches('/');
+  if path == API_PREFIX {
+    return Ok(match *request.method() {
+      Method::GET => {
+        let routes = serde_json::to_vec(&http_routes(&dispatcher)).unwrap_or_default();
+        reply(200, ContentType::Json.as_str(), routes)
+      },
+      _ => reply(405, OCTET_STREAM, vec![]),
+    });
+  }
+
+  let event = match path
+    .strip_prefix(API_PREFIX)
+    .and_then(|e| e.strip_prefix('/'))
+  {
+    Some(event) if !event.is_empty() => AFPluginEvent::from(event),
+    _ => return Ok(reply(404, OCTET_STREAM, vec![])),
+  };
+  if !dispatcher.has_event(&event) {
+    return Ok(reply(404, OCTET_STREAM, vec![]));
+  }
+  if request.method() != Method::POST {
+    return Ok(reply(405, OCTET_STREAM, vec![]));
+  }
+
+  let content_type = request
+    .headers()
+    .get(CONTENT_TYPE)
+    .and_then(|value| value.to_str().ok())
+    .and_then(ContentType::from_mime);
+  let authorization = request
+    .headers()
+    .get(AUTHORIZATION)
+    .and_then(|value| value.to_str().ok())
+    .map(|value| value.to_owned());
+  let signature = request
+    .headers()
+    .get(SIGNATURE_HEADER)
+    .and_then(|value| value.to_str().ok())
+    .map(|value| value.to_owned());
+  let content_length = request
+    .headers()
+    .get(CONTENT_LENGTH)
+    .and_then(|value| value.to_str().ok())
+    .and_then(|value| value.parse::<u64>().ok());
+  if content_length.map_or(false, |len| len > max_body_size as u64) {
+    return Ok(reply(413, OCTET_STREAM, vec![]));
+  }
+  let body = match read_body(request.into_body(), max_body_size).await {
+    Ok(Some(body)) => body,
+    Ok(None) => return Ok(reply(413, OCTET_STREAM, vec![])),
+    Err(err) => return Ok(reply(400, OCTET_STREAM, err.to_string().into_bytes())),
+  };
+
+  let mut request = AFPluginRequest::new(event)
+    .metadata(TRANSPORT_METADATA, "http")
+    .capabilities(Capabilities::new());
+  if let Some(authorization) = authorization {
+    request = request.metadata(AUTHORIZATION_METADATA, authorization);
+  }
+  if let Some(signature) = signature {
+    request = request.metadata(SIGNATURE_METADATA, signature);
+  }
+  if !body.is_empty() {
+    request = request.payload(body);
+  }
+  if let Some(content_type) = content_type {
+    request = request.content_type(content_type);
+  }
+  let response = AFPluginDispatcher::async_send(dispatcher.as_ref(), request).await;
+  let mime = response
+    .content_type
+    .map(|content_type| content_type.as_str())
+    .unwrap_or(OCTET_STREAM);
+  let mut reply = reply(
+    http_status(&response),
+    mime,
+    response.payload.as_ref().to_vec(),
+  );
+  // The metadata that isn't a valid header is dropped.
+  for (key, value) in &response.metadata {
+    if let (Ok(name), Ok(value)) = (
+      HeaderName::from_bytes(key.as_bytes()),
+      HeaderValue::from_str(value),
+    ) {
+      reply.headers_mut().insert(name, value);
+    }
+  }
+  Ok(reply)
+}
+
+/// Reads the chunks of `body` until they exceed `max_size`, which returns `None`. The body may
+/// have no `Content-Length`, or lie about it.
+async fn read_body(mut body: Body, max_size: usize) -> Result<Option<Vec<u8>>, hyper::Error> {
+  let mut bytes = Vec::new();
+  while let Some(chunk) = body.data().await {
+    let chunk = chunk?;
+    if bytes.len() + chunk.len() > max_size {
+      return Ok(None);
+    }
+    bytes.extend_from_slice(&chunk);
+  }
+  Ok(Some(bytes))
+}
+
+fn reply(status: u16, mime: &str, body: Vec<u8>) -> Response<Body> {
+  Response::builder()
+    .status(status)
+    .header(CONTENT_TYPE, mime)
+    .body(Body::from(body))
+    .unwrap()
+}
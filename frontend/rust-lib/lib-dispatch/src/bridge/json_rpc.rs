@@ -0,0 +1,165 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+
+use crate::capability::Capabilities;
+use crate::encoding::ContentType;
+use crate::middleware::{AUTHORIZATION_METADATA, SIGNATURE_METADATA, TRANSPORT_METADATA};
+use crate::module::{AFPluginEvent, AFPluginRequest};
+use crate::prelude::AFPluginDispatcher;
+use crate::request::Payload;
+use crate::response::{AFPluginEventResponse, StatusCode};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+/// The error code of the requests whose handler returned an error.
+const HANDLER_ERROR: i64 = -32000;
+
+#[derive(Deserialize)]
+struct JsonRpcRequest {
+  jsonrpc: String,
+  method: String,
+  #[serde(default)]
+  params: Option<Value>,
+  /// The notifications have no id and get no response.
+  #[serde(default)]
+  id: Option<Value>,
+  /// The session token of the user, an extension of JSON-RPC.
+  #[serde(default)]
+  authorization: Option<String>,
+  /// The signature of the request, an extension of JSON-RPC, see the `SignatureMiddleware` of
+  /// the `request_signing` feature.
+  #[serde(default)]
+  signature: Option<String>,
+}
+
+/// Reads one JSON-RPC 2.0 request per line from `reader`, dispatches it and writes its response,
+/// on a single line, to `writer`.
+///
+/// The method is the name of the event. String params are sent as the raw payload, any other
+/// params are sent as JSON, which is what the [Json](crate::prelude::Json) extractor expects. The
+/// result is the JSON payload of the response, or the payload as a string otherwise. The
+/// requests are tagged with the `json_rpc` [TRANSPORT_METADATA], and the session token in their
+/// `authorization` member is checked by the [AuthMiddleware](crate::prelude::AuthMiddleware),
+/// like the one in their `signature` member by the `SignatureMiddleware`:
+///
+/// ```json
+/// {"jsonrpc": "2.0", "id": 1, "method": "whoami", "authorization": "Bearer <token>"}
+/// ```
+///
+/// The requests are handled one after the other. Like [AFPluginDispatcher::async_send], it must
+/// run inside a `LocalSet`. It returns once `reader` is closed.
+pub async fn serve_json_rpc<R, W>(
+  dispatcher: &AFPluginDispatcher,
+  reader: R,
+  mut writer: W,
+) -> std::io::Result<()>
+where
+  R: AsyncRead + Unpin,
+  W: AsyncWrite + Unpin,
+{
+  let mut lines = BufReader::new(reader).lines();
+  while let Some(line) = lines.next_line().await? {
+    if line.trim().is_empty() {
+      continue;
+    }
+    if let Some(response) = handle_line(dispatcher, &line).await {
+      writer.write_all(response.to_string().as_bytes()).await?;
+      writer.write_all(b"\n").await?;
+      writer.flush().await?;
+    }
+  }
+  Ok(())
+}
+
+/// Serves the JSON-RPC requests read from stdin, see [serve_json_rpc].
+pub async fn serve_json_rpc_stdio(dispatcher: &AFPluginDispatcher) -> std::io::Result<()> {
+  serve_json_rpc(dispatcher, tokio::io::stdin(), tokio::io::stdout()).await
+}
+
+async fn handle_line(dispatcher: &AFPluginDispatcher, line: &str) -> Option<Value> {
+  let value = match serde_json::from_str::<Value>(line) {
+    Ok(value) => value,
+    Err(e) => return Some(error_response(Value::Null, PARSE_ERROR, &e.to_string())),
+  };
+  let request = match serde_json::from_value::<JsonRpcRequest>(value) {
+    Ok(request) if request.jsonrpc == "2.0" => request,
+    Ok(_) => {
+      let msg = "jsonrpc must be \"2.0\"";
+      return Some(error_response(Value::Null, INVALID_REQUEST, msg));
+    },
+    Err(e) => return Some(error_response(Value::Null, INVALID_REQUEST, &e.to_string())),
+  };
+
+  let event = AFPluginEvent::from(request.method.as_str());
+  let response = if !dispatcher.has_event(&event) {
+    let msg = format!("No handler for {}", request.method);
+    error_response(
+      request.id.clone().unwrap_or_default(),
+      METHOD_NOT_FOUND,
+      &msg,
+    )
+  } else {
+    let mut af_request = AFPluginRequest::new(event)
+      .metadata(TRANSPORT_METADATA, "json_rpc")
+      .capabilities(Capabilities::new());
+    if let Some(authorization) = request.authorization {
+      af_request = af_request.metadata(AUTHORIZATION_METADATA, authorization);
+    }
+    if let Some(signature) = request.signature {
+      af_request = af_request.metadata(SIGNATURE_METADATA, signature);
+    }
+    match request.params {
+      None | Some(Value::Null) => {},
+      Some(Value::String(params)) => af_request = af_request.payload(params),
+      Some(params) => {
+        af_request = af_request
+          .payload(params.to_string())
+          .content_type(ContentType::Json)
+      },
+    }
+    let response =
+      AFPluginDispatcher::async_send_with_callback(dispatcher, af_request, |_| Box::pin(async {}))
+        .await;
+    to_json_rpc_response(request.id.clone().unwrap_or_default(), response)
+  };
+  request.id.map(|_| response)
+}
+
+fn to_json_rpc_response(id: Value, response: AFPluginEventResponse) -> Value {
+  match response.status_code {
+    StatusCode::Ok => {
+      let result = payload_to_json(&response.payload, response.content_type);
+      json!({ "jsonrpc": "2.0", "id": id, "result": result })
+    },
+    StatusCode::Err => {
+      let msg = String::from_utf8_lossy(response.payload.as_ref()).to_string();
+      error_response(id, HANDLER_ERROR, &msg)
+    },
+  }
+}
+
+fn payload_to_json(payload: &Payload, content_type: Option<ContentType>) -> Value {
+  let bytes = match payload {
+    Payload::None => return Value::Null,
+    Payload::Bytes(bytes) => bytes,
+  };
+  if content_type == Some(ContentType::Json) {
+    if let Ok(value) = serde_json::from_slice(bytes) {
+      return value;
+    }
+  }
+  match std::str::from_utf8(bytes) {
+    Ok(s) => Value::String(s.to_owned()),
+    Err(_) => Value::from(bytes.to_vec()),
+  }
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+  json!({
+    "jsonrpc": "2.0",
+    "id": id,
+    "error": { "code": code, "message": message },
+  })
+}
@@ -0,0 +1,165 @@
+use std::sync::Arc;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::{mpsc, OwnedSemaphorePermit, Semaphore};
+
+use super::frame::{decode_request, encode_response};
+use crate::prelude::AFPluginDispatcher;
+
+/// The frames bigger than this are rejected and close the connection.
+pub const MAX_FRAME_SIZE: usize = 64 * 1024 * 1024;
+
+/// The requests of a connection handled at once. The next frames are only read once one of them
+/// is answered.
+pub const MAX_IN_FLIGHT_REQUESTS: usize = 64;
+
+/// Serves the dispatcher to the local processes connecting to the Unix domain socket at `path`,
+/// see [serve_framed]. The socket file must not exist. Like [AFPluginDispatcher::async_send], it
+/// must run inside a `LocalSet`.
+#[cfg(unix)]
+pub async fn serve_unix_socket<P: AsRef<std::path::Path>>(
+  path: P,
+  dispatcher: Arc<AFPluginDispatcher>,
+) -> std::io::Result<()> {
+  let listener = tokio::net::UnixListener::bind(path)?;
+  loop {
+    let (stream, _) = listener.accept().await?;
+    spawn_connection(stream, dispatcher.clone());
+  }
+}
+
+/// Serves the dispatcher to the local processes connecting to the named pipe `name`, like
+/// `\\.\pipe\appflowy`, see [serve_framed]. Like [AFPluginDispatcher::async_send], it must run
+/// inside a `LocalSet`.
+#[cfg(windows)]
+pub async fn serve_named_pipe(
+  name: &str,
+  dispatcher: Arc<AFPluginDispatcher>,
+) -> std::io::Result<()> {
+  use tokio::net::windows::named_pipe::ServerOptions;
+
+  let mut server = ServerOptions::new()
+    .first_pipe_instance(true)
+    .create(name)?;
+  loop {
+    server.connect().await?;
+    // The next instance must exist before the connected one is handed over, otherwise the
+    // clients connecting in between would find no pipe.
+    let connected = std::mem::replace(&mut server, ServerOptions::new().create(name)?);
+    spawn_connection(connected, dispatcher.clone());
+  }
+}
+
+fn spawn_connection<S>(stream: S, dispatcher: Arc<AFPluginDispatcher>)
+where
+  S: AsyncRead + AsyncWrite + 'static,
+{
+  tokio::task::spawn_local(async move {
+    if let Err(err) = serve_framed(stream, dispatcher).await {
+      tracing::debug!("[LocalSocket]: connection closed: {}", err);
+    }
+  });
+}
+
+/// Reads the request frames from `stream` and writes back their response frames, each prefixed by
+/// its length on four big endian bytes. A client sends request frames:
+///
+/// ```text
+/// request id: u32 | event length: u16 | event: utf8 | payload
+/// ```
+///
+/// and the server answers with response frames. The WebSocket bridge also pushes notification
+/// frames:
+///
+/// ```text
+/// 0: u8 | request id: u32 | status code: u8 | payload
+/// 1: u8 | notification payload
+/// ```
+///
+/// The three highest bits of the event length are flags, so the event is at most 8191 bytes.
+///
+/// A request is tagged with the [ContentType](crate::prelude::ContentType) of its payload by
+/// setting the high bit of the event length and adding a content type byte after the event: 0
+/// when unknown, 1 for protobuf, 2 for JSON, 3 for MessagePack, 4 for CBOR and 5 for bincode. The
+/// response of a tagged request is tagged the same way, with the high bit of the status code and
+/// a content type byte after it, 0 if the response has none.
+///
+/// A request is signed by setting the second highest bit of the event length and adding the
+/// signature after the event, and its content type byte if tagged: its length on a u16, then the
+/// signature in UTF-8, see the `security` module of the `request_signing` feature. A request
+/// carries the session token of its user, see [AuthMiddleware](crate::prelude::AuthMiddleware),
+/// by setting the third highest bit of the event length and adding the token after the event,
+/// its content type byte and its signature, if any, in the same way as the signature.
+///
+/// The requests are tagged with the `local_socket`
+/// [TRANSPORT_METADATA](crate::middleware::TRANSPORT_METADATA), or `websocket` for the WebSocket
+/// bridge, so the middlewares handle them as the requests of other processes.
+///
+/// All the integers are big endian. The requests are handled concurrently, up to
+/// [MAX_IN_FLIGHT_REQUESTS], so the responses are written as soon as they are ready and the client
+/// matches them with the request id, which it chooses. It returns once the client closes the
+/// stream.
+pub async fn serve_framed<S>(stream: S, dispatcher: Arc<AFPluginDispatcher>) -> std::io::Result<()>
+where
+  S: AsyncRead + AsyncWrite + 'static,
+{
+  let (mut reader, mut writer) = tokio::io::split(stream);
+  // A request holds its permit until its response is written, so the responses waiting to be
+  // written count as in flight too.
+  let in_flight = Arc::new(Semaphore::new(MAX_IN_FLIGHT_REQUESTS));
+  let (tx, mut rx) = mpsc::channel::<(Vec<u8>, OwnedSemaphorePermit)>(MAX_IN_FLIGHT_REQUESTS);
+  let write_task = tokio::task::spawn_local(async move {
+    while let Some((frame, _permit)) = rx.recv().await {
+      writer
+        .write_all(&(frame.len() as u32).to_be_bytes())
+        .await?;
+      writer.write_all(&frame).await?;
+      writer.flush().await?;
+    }
+    Ok::<(), std::io::Error>(())
+  });
+
+  let result = async {
+    loop {
+      let permit = in_flight
+        .clone()
+        .acquire_owned()
+        .await
+        .expect("the semaphore is never closed");
+      let mut len = [0u8; 4];
+      match reader.read_exact(&mut len).await {
+        Ok(_) => {},
+        Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(()),
+        Err(err) => return Err(err),
+      }
+      let len = u32::from_be_bytes(len) as usize;
+      if len > MAX_FRAME_SIZE {
+        return Err(std::io::Error::new(
+          std::io::ErrorKind::InvalidData,
+          format!("frame of {} bytes exceeds {} bytes", len, MAX_FRAME_SIZE),
+        ));
+      }
+      let mut frame = vec![0u8; len];
+      reader.read_exact(&mut frame).await?;
+
+      match decode_request(&frame, "local_socket") {
+        Ok((id, request, tagged)) => {
+          let dispatcher = dispatcher.clone();
+          let tx = tx.clone();
+          tokio::task::spawn_local(async move {
+            let response = AFPluginDispatcher::async_send(dispatcher.as_ref(), request).await;
+            let frame = encode_response(id, response, tagged);
+            let _ = tx.send((frame, permit)).await;
+          });
+        },
+        Err(err) => tracing::warn!("[LocalSocket]: invalid request frame: {}", err),
+      }
+    }
+  }
+  .await;
+
+  // Let the pending responses be written before closing the stream.
+  drop(tx);
+  let _ = write_task.await;
+  result
+}
@@ -0,0 +1,59 @@
+//! Frontends that let other processes send events to the dispatcher.
+
+#[cfg(all(feature = "grpc_bridge", not(target_arch = "wasm32")))]
+pub use grpc::*;
+#[cfg(all(feature = "http_bridge", not(target_arch = "wasm32")))]
+pub use http::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use json_rpc::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use local_socket::*;
+#[cfg(target_arch = "wasm32")]
+pub use wasm::*;
+#[cfg(all(feature = "ws_bridge", not(target_arch = "wasm32")))]
+pub use websocket::*;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod frame;
+#[cfg(all(feature = "grpc_bridge", not(target_arch = "wasm32")))]
+mod grpc;
+#[cfg(all(feature = "http_bridge", not(target_arch = "wasm32")))]
+mod http;
+#[cfg(not(target_arch = "wasm32"))]
+mod json_rpc;
+#[cfg(not(target_arch = "wasm32"))]
+mod local_socket;
+#[cfg(target_arch = "wasm32")]
+mod wasm;
+#[cfg(all(feature = "ws_bridge", not(target_arch = "wasm32")))]
+mod websocket;
+
+/// The header carrying the signature of the HTTP and gRPC requests, passed on in their
+/// [SIGNATURE_METADATA](crate::middleware::SIGNATURE_METADATA).
+#[cfg(all(
+  any(feature = "http_bridge", feature = "grpc_bridge"),
+  not(target_arch = "wasm32")
+))]
+pub const SIGNATURE_HEADER: &str = "x-signature";
+
+/// The responses are built on the dispatcher's thread, so the connections can't be driven by
+/// hyper's default executor, which requires `Send` futures.
+#[cfg(all(
+  any(feature = "http_bridge", feature = "grpc_bridge"),
+  not(target_arch = "wasm32")
+))]
+#[derive(Clone, Copy)]
+struct LocalExec;
+
+#[cfg(all(
+  any(feature = "http_bridge", feature = "grpc_bridge"),
+  not(target_arch = "wasm32")
+))]
+impl<F> hyper::rt::Executor<F> for LocalExec
+where
+  F: std::future::Future + 'static,
+{
+  fn execute(&self, fut: F) {
+    tokio::task::spawn_local(fut);
+  }
+}
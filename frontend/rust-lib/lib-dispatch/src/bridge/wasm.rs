@@ -0,0 +1,64 @@
+use std::cell::RefCell;
+use std::sync::Arc;
+
+use js_sys::{Function, Promise, Uint8Array};
+use tokio::task::LocalSet;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::future_to_promise;
+
+use crate::module::AFPluginRequest;
+use crate::prelude::AFPluginDispatcher;
+use crate::response::StatusCode;
+
+thread_local! {
+  static WASM_DISPATCHER: RefCell<Option<Arc<AFPluginDispatcher>>> = const { RefCell::new(None) };
+  static NOTIFICATION_CALLBACKS: RefCell<Vec<Function>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Sets the dispatcher used by [dispatch]. Called by the WASM build of the core once its plugins
+/// are ready.
+pub fn register_wasm_dispatcher(dispatcher: Arc<AFPluginDispatcher>) {
+  WASM_DISPATCHER.with(|d| *d.borrow_mut() = Some(dispatcher));
+}
+
+/// Calls the callbacks registered with [on_notification] with `payload`.
+pub fn post_wasm_notification(payload: &[u8]) {
+  NOTIFICATION_CALLBACKS.with(|callbacks| {
+    let payload = JsValue::from(Uint8Array::from(payload));
+    for callback in callbacks.borrow().iter() {
+      if let Err(err) = callback.call1(&JsValue::NULL, &payload) {
+        tracing::error!("[WASM]: notification callback failed: {:?}", err);
+      }
+    }
+  });
+}
+
+/// Dispatches `event` with `payload`, the encoded request. The promise resolves with the encoded
+/// response, or rejects with the encoded error when the handler failed.
+#[wasm_bindgen]
+pub fn dispatch(event: String, payload: Vec<u8>) -> Promise {
+  future_to_promise(async move {
+    let dispatcher = WASM_DISPATCHER
+      .with(|d| d.borrow().clone())
+      .ok_or_else(|| JsValue::from_str("The dispatcher is not registered"))?;
+    let mut request = AFPluginRequest::new(event);
+    if !payload.is_empty() {
+      request = request.payload(payload);
+    }
+    // The dispatcher spawns the requests on the current LocalSet.
+    let response = LocalSet::new()
+      .run_until(AFPluginDispatcher::async_send(dispatcher.as_ref(), request))
+      .await;
+    let bytes = JsValue::from(Uint8Array::from(response.payload.as_ref()));
+    match response.status_code {
+      StatusCode::Ok => Ok(bytes),
+      StatusCode::Err => Err(bytes),
+    }
+  })
+}
+
+/// Registers a callback that is called with every notification posted by the core.
+#[wasm_bindgen(js_name = onNotification)]
+pub fn on_notification(callback: Function) {
+  NOTIFICATION_CALLBACKS.with(|callbacks| callbacks.borrow_mut().push(callback));
+}
@@ -0,0 +1,165 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures_util::{SinkExt, StreamExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, mpsc, OwnedSemaphorePermit, Semaphore};
+use tokio_tungstenite::tungstenite::Message;
+
+use super::frame::{decode_request, encode_response, NOTIFICATION_FRAME};
+use super::local_socket::MAX_IN_FLIGHT_REQUESTS;
+use crate::prelude::AFPluginDispatcher;
+
+/// Serves the dispatcher over WebSocket.
+///
+/// A client authenticates by sending the token in a text message, then sends the request frames
+/// described in [serve_framed](super::serve_framed) as binary messages, without their length.
+/// The server answers each request with a response frame and pushes the notifications to every
+/// authenticated client as notification frames. Like the local socket, a connection has at most
+/// [MAX_IN_FLIGHT_REQUESTS] requests in flight, its next messages are read once one is answered.
+/// The token only opens the connection: the
+/// requests still carry the session token of their user for the
+/// [AuthMiddleware](crate::prelude::AuthMiddleware).
+pub struct WebSocketBridge {
+  addr: SocketAddr,
+  token: String,
+  notifications: Option<broadcast::Sender<Bytes>>,
+}
+
+impl WebSocketBridge {
+  pub fn new(addr: SocketAddr, token: &str) -> Self {
+    Self {
+      addr,
+      token: token.to_owned(),
+      notifications: None,
+    }
+  }
+
+  /// The payloads sent to this channel are forwarded to the clients as notification frames.
+  pub fn with_notifications(mut self, notifications: broadcast::Sender<Bytes>) -> Self {
+    self.notifications = Some(notifications);
+    self
+  }
+
+  /// Accepts the connections until the listener fails. Like [AFPluginDispatcher::async_send],
+  /// it must run inside a `LocalSet`.
+  pub async fn serve(self, dispatcher: Arc<AFPluginDispatcher>) -> std::io::Result<()> {
+    let listener = TcpListener::bind(self.addr).await?;
+    self.serve_listener(listener, dispatcher).await
+  }
+
+  pub async fn serve_listener(
+    self,
+    listener: TcpListener,
+    dispatcher: Arc<AFPluginDispatcher>,
+  ) -> std::io::Result<()> {
+    let token = Arc::new(self.token);
+    loop {
+      let (stream, peer) = listener.accept().await?;
+      let dispatcher = dispatcher.clone();
+      let token = token.clone();
+      let notifications = self.notifications.as_ref().map(|n| n.subscribe());
+      tokio::task::spawn_local(async move {
+        if let Err(err) = handle_connection(stream, dispatcher, &token, notifications).await {
+          tracing::debug!("[WebSocket]: connection {} closed: {}", peer, err);
+        }
+      });
+    }
+  }
+}
+
+/// Compares the tokens in constant time, so the time it takes doesn't tell how much of the token
+/// a client got right.
+fn token_matches(sent: &str, token: &str) -> bool {
+  ring::constant_time::verify_slices_are_equal(sent.as_bytes(), token.as_bytes()).is_ok()
+}
+
+async fn handle_connection(
+  stream: TcpStream,
+  dispatcher: Arc<AFPluginDispatcher>,
+  token: &str,
+  mut notifications: Option<broadcast::Receiver<Bytes>>,
+) -> Result<(), String> {
+  let ws = tokio_tungstenite::accept_async(stream)
+    .await
+    .map_err(|e| e.to_string())?;
+  let (mut sink, mut stream) = ws.split();
+
+  match stream.next().await {
+    Some(Ok(Message::Text(text))) if token_matches(&text, token) => {},
+    _ => {
+      let _ = sink.send(Message::Close(None)).await;
+      return Err("authentication failed".to_owned());
+    },
+  }
+
+  // A request holds its permit until its response is sent, so the responses waiting to be sent
+  // count as in flight too.
+  let in_flight = Arc::new(Semaphore::new(MAX_IN_FLIGHT_REQUESTS));
+  let (tx, mut rx) = mpsc::channel::<(Vec<u8>, OwnedSemaphorePermit)>(MAX_IN_FLIGHT_REQUESTS);
+  loop {
+    tokio::select! {
+      message = stream.next(), if in_flight.available_permits() > 0 => match message {
+        Some(Ok(Message::Binary(frame))) => match decode_request(&frame, "websocket") {
+          Ok((id, request, tagged)) => {
+            let dispatcher = dispatcher.clone();
+            let tx = tx.clone();
+            let permit = in_flight
+              .clone()
+              .try_acquire_owned()
+              .expect("a permit is available");
+            tokio::task::spawn_local(async move {
+              let response = AFPluginDispatcher::async_send_with_callback(
+                dispatcher.as_ref(),
+                request,
+                |_| Box::pin(async {}),
+              )
+              .await;
+              let frame = encode_response(id, response, tagged);
+              let _ = tx.send((frame, permit)).await;
+            });
+          },
+          Err(err) => tracing::warn!("[WebSocket]: invalid request frame: {}", err),
+        },
+        Some(Ok(Message::Close(_))) | None => return Ok(()),
+        Some(Ok(_)) => {},
+        Some(Err(err)) => return Err(err.to_string()),
+      },
+      Some((frame, _permit)) = rx.recv() => {
+        sink.send(Message::Binary(frame)).await.map_err(|e| e.to_string())?;
+      },
+      notification = next_notification(&mut notifications) => {
+        if let Some(payload) = notification {
+          let mut frame = Vec::with_capacity(payload.len() + 1);
+          frame.push(NOTIFICATION_FRAME);
+          frame.extend_from_slice(&payload);
+          sink.send(Message::Binary(frame)).await.map_err(|e| e.to_string())?;
+        }
+      },
+    }
+  }
+}
+
+/// Waits for the next notification. Never resolves when there is no notification channel.
+async fn next_notification(
+  notifications: &mut Option<broadcast::Receiver<Bytes>>,
+) -> Option<Bytes> {
+  match notifications {
+    None => std::future::pending().await,
+    Some(rx) => match rx.recv().await {
+      Ok(payload) => Some(payload),
+      Err(broadcast::error::RecvError::Lagged(count)) => {
+        tracing::warn!(
+          "[WebSocket]: client lagged, {} notifications dropped",
+          count
+        );
+        None
+      },
+      Err(broadcast::error::RecvError::Closed) => {
+        *notifications = None;
+        None
+      },
+    },
+  }
+}
@@ -1,5 +1,8 @@
 use bytes::Bytes;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 
+use crate::encoding::SerdeCodec;
 use crate::errors::{DispatchError, InternalError};
 
 // To bytes
@@ -29,6 +32,80 @@ where
 
 pub trait AFPluginFromBytes: Sized {
   fn parse_from_bytes(bytes: Bytes) -> Result<Self, DispatchError>;
+
+  /// Called when the response carries no payload, which only the types without data accept.
+  fn parse_from_none() -> Result<Self, DispatchError> {
+    Err(
+      InternalError::UnexpectedNone(format!(
+        "Parse fail, expected payload:{:?}",
+        std::any::type_name::<Self>()
+      ))
+      .into(),
+    )
+  }
+}
+
+/// The payload of the events without input or output, for the typed
+/// [AFPluginDispatcher::request](crate::prelude::AFPluginDispatcher::request).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Empty;
+
+impl ToBytes for Empty {
+  fn into_bytes(self) -> Result<Bytes, DispatchError> {
+    Ok(Bytes::new())
+  }
+}
+
+impl AFPluginFromBytes for Empty {
+  fn parse_from_bytes(_: Bytes) -> Result<Self, DispatchError> {
+    Ok(Empty)
+  }
+
+  fn parse_from_none() -> Result<Self, DispatchError> {
+    Ok(Empty)
+  }
+}
+
+/// Encodes `value` with the codec `C`. It's what the `ToBytes` derive of flowy-derive expands to:
+///
+/// ```ignore
+/// #[derive(Serialize, Deserialize, ToBytes, FromBytes)]
+/// #[codec(json)]
+/// pub struct ReindexParams {
+///   pub workspace_id: String,
+/// }
+/// ```
+pub fn encode_bytes<C, T>(value: &T) -> Result<Bytes, DispatchError>
+where
+  C: SerdeCodec,
+  T: Serialize,
+{
+  C::encode(value).map(Bytes::from).map_err(|e| {
+    InternalError::Other(format!(
+      "Serialize {} to {} failed: {}",
+      std::any::type_name::<T>(),
+      C::CONTENT_TYPE,
+      e
+    ))
+    .into()
+  })
+}
+
+/// Decodes a value encoded by [encode_bytes], for the `FromBytes` derive of flowy-derive.
+pub fn decode_bytes<C, T>(bytes: &[u8]) -> Result<T, DispatchError>
+where
+  C: SerdeCodec,
+  T: DeserializeOwned,
+{
+  C::decode(bytes).map_err(|e| {
+    InternalError::DeserializeFromBytes(format!(
+      "Parse {} payload to {} failed: {}",
+      C::CONTENT_TYPE,
+      std::any::type_name::<T>(),
+      e
+    ))
+    .into()
+  })
 }
 
 #[cfg(feature = "use_protobuf")]
@@ -0,0 +1,100 @@
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+use std::iter::FromIterator;
+
+use serde::{Deserialize, Serialize};
+
+use crate::encoding::ContentType;
+use crate::errors::Error;
+use crate::response::{AFPluginEventResponse, ErrorOrigin, ResponseBuilder};
+
+/// The capabilities granted to a caller, e.g. `folder.read` or `user.write`. The requests of the
+/// callers that can't be trusted with every event, like the plugins or the remote clients, carry
+/// them, see [AFPluginRequest::capabilities](crate::prelude::AFPluginRequest::capabilities), and
+/// the events declare the ones they require with
+/// [AFPlugin::requires_capability](crate::prelude::AFPlugin::requires_capability).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Capabilities(BTreeSet<String>);
+
+impl Capabilities {
+  /// No capability: only the events that require none can be called.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn grant<T: ToString>(mut self, capability: T) -> Self {
+    self.0.insert(capability.to_string());
+    self
+  }
+
+  pub fn contains(&self, capability: &str) -> bool {
+    self.0.contains(capability)
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = &str> {
+    self.0.iter().map(|capability| capability.as_str())
+  }
+
+  /// The `required` capabilities that aren't granted.
+  pub(crate) fn missing(&self, required: &[String]) -> Vec<String> {
+    required
+      .iter()
+      .filter(|capability| !self.0.contains(capability.as_str()))
+      .cloned()
+      .collect()
+  }
+}
+
+impl<T: ToString> FromIterator<T> for Capabilities {
+  fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+    Self(
+      iter
+        .into_iter()
+        .map(|capability| capability.to_string())
+        .collect(),
+    )
+  }
+}
+
+/// The error of the requests rejected because their caller lacks the capabilities their event
+/// requires. The response carries it as JSON, read it back with
+/// [PermissionDenied::from_response].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PermissionDenied {
+  pub event: String,
+  /// The capabilities of the event that weren't granted.
+  pub missing: Vec<String>,
+}
+
+impl PermissionDenied {
+  pub fn from_response(response: &AFPluginEventResponse) -> Option<Self> {
+    if response.error_origin != Some(ErrorOrigin::Dispatcher)
+      || response.content_type != Some(ContentType::Json)
+    {
+      return None;
+    }
+    serde_json::from_slice(response.payload.as_ref()).ok()
+  }
+}
+
+impl Display for PermissionDenied {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "PermissionDenied: {} requires {}",
+      self.event,
+      self.missing.join(", ")
+    )
+  }
+}
+
+impl Error for PermissionDenied {
+  fn as_response(&self) -> AFPluginEventResponse {
+    let data = serde_json::to_vec(self).unwrap_or_else(|_| self.to_string().into_bytes());
+    ResponseBuilder::Err()
+      .data(data)
+      .content_type(ContentType::Json)
+      .error_origin(ErrorOrigin::Dispatcher)
+      .build()
+  }
+}
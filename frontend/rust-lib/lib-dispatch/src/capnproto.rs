@@ -0,0 +1,99 @@
+use std::fmt::{Debug, Formatter};
+
+use bytes::Bytes;
+use capnp::message::{Allocator, Builder, Reader, ReaderOptions, TypedReader};
+use capnp::serialize::BufferSegments;
+use capnp::traits::Owned;
+
+use crate::{
+  errors::{DispatchError, InternalError},
+  request::{unexpected_none_payload, AFPluginEventRequest, FromAFPluginRequest, Payload},
+  response::{AFPluginEventResponse, AFPluginResponder, ResponseBuilder},
+  util::ready::{ready, Ready},
+};
+
+/// A Cap'n Proto payload, where `T` is the `Owned` type that capnpc generates for the root
+/// struct of the message. The segment table and the root pointer are checked once when the
+/// request is extracted, then the handler reads the fields of the [root](Capnp::root) in place.
+/// The fields added by newer versions of the schema are ignored by the older readers.
+pub struct Capnp<T: Owned> {
+  bytes: Bytes,
+  message: TypedReader<BufferSegments<Bytes>, T>,
+}
+
+impl<T: Owned> Capnp<T> {
+  /// Checks that `bytes` contains a message serialized with `capnp::serialize` whose root is a
+  /// `T`.
+  pub fn from_bytes(bytes: Bytes) -> Result<Self, DispatchError> {
+    let invalid = |e: capnp::Error| {
+      InternalError::DeserializeFromBytes(format!(
+        "Invalid {} capnp message: {}",
+        std::any::type_name::<T>(),
+        e
+      ))
+    };
+    let segments = BufferSegments::new(bytes.clone(), ReaderOptions::new()).map_err(invalid)?;
+    let message = Reader::new(segments, ReaderOptions::new());
+    message.get_root::<T::Reader<'_>>().map_err(invalid)?;
+    Ok(Self {
+      bytes,
+      message: TypedReader::new(message),
+    })
+  }
+
+  /// Serializes the message of `builder`.
+  pub fn from_builder<A: Allocator>(builder: &Builder<A>) -> Result<Self, DispatchError> {
+    Self::from_bytes(Bytes::from(capnp::serialize::write_message_to_words(
+      builder,
+    )))
+  }
+
+  pub fn root(&self) -> Result<T::Reader<'_>, DispatchError> {
+    self.message.get().map_err(|e| {
+      InternalError::DeserializeFromBytes(format!(
+        "Invalid {} capnp message: {}",
+        std::any::type_name::<T>(),
+        e
+      ))
+      .into()
+    })
+  }
+
+  pub fn as_bytes(&self) -> &Bytes {
+    &self.bytes
+  }
+
+  pub fn into_bytes(self) -> Bytes {
+    self.bytes
+  }
+}
+
+impl<T: Owned> Debug for Capnp<T> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "Capnp<{}>({} bytes)",
+      std::any::type_name::<T>(),
+      self.bytes.len()
+    )
+  }
+}
+
+impl<T: Owned> FromAFPluginRequest for Capnp<T> {
+  type Error = DispatchError;
+  type Future = Ready<Result<Self, DispatchError>>;
+
+  #[inline]
+  fn from_request(req: &AFPluginEventRequest, payload: &mut Payload) -> Self::Future {
+    match payload {
+      Payload::None => ready(Err(unexpected_none_payload(req))),
+      Payload::Bytes(bytes) => ready(Self::from_bytes(bytes.clone())),
+    }
+  }
+}
+
+impl<T: Owned> AFPluginResponder for Capnp<T> {
+  fn respond_to(self, _request: &AFPluginEventRequest) -> AFPluginEventResponse {
+    ResponseBuilder::Ok().data(self.bytes).build()
+  }
+}
@@ -0,0 +1,84 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::checkpoint::{CheckpointRecord, CheckpointStore};
+use crate::errors::{DispatchError, InternalError};
+
+const CHECKPOINT_FILE_EXTENSION: &str = "checkpoint";
+
+/// Stores each record as MessagePack in its own file, named after the key. A record is written to
+/// a temporary file first and renamed over the previous one, so a crash while saving leaves the
+/// previous checkpoint intact. Requires the `use_msgpack` feature.
+pub struct FileCheckpointStore {
+  dir: PathBuf,
+}
+
+impl FileCheckpointStore {
+  pub fn new<P: AsRef<Path>>(dir: P) -> Result<Self, DispatchError> {
+    let dir = dir.as_ref().to_path_buf();
+    fs::create_dir_all(&dir).map_err(io_error)?;
+    Ok(Self { dir })
+  }
+
+  /// The keys are hex encoded, they may hold any character.
+  fn path(&self, key: &str) -> PathBuf {
+    let name = key
+      .bytes()
+      .map(|byte| format!("{:02x}", byte))
+      .collect::<String>();
+    self
+      .dir
+      .join(format!("{}.{}", name, CHECKPOINT_FILE_EXTENSION))
+  }
+
+  fn read(path: &Path) -> Result<CheckpointRecord, DispatchError> {
+    let bytes = fs::read(path).map_err(io_error)?;
+    rmp_serde::from_slice(&bytes)
+      .map_err(|e| InternalError::DeserializeFromBytes(e.to_string()).into())
+  }
+}
+
+impl CheckpointStore for FileCheckpointStore {
+  fn save(&self, record: &CheckpointRecord) -> Result<(), DispatchError> {
+    let bytes = rmp_serde::to_vec(record).map_err(|e| InternalError::Other(e.to_string()))?;
+    let path = self.path(&record.key);
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, bytes).map_err(io_error)?;
+    fs::rename(&tmp_path, &path).map_err(io_error)
+  }
+
+  fn load(&self, key: &str) -> Result<Option<CheckpointRecord>, DispatchError> {
+    let path = self.path(key);
+    if !path.exists() {
+      return Ok(None);
+    }
+    Self::read(&path).map(Some)
+  }
+
+  fn remove(&self, key: &str) -> Result<(), DispatchError> {
+    match fs::remove_file(self.path(key)) {
+      Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(io_error(err)),
+      _ => Ok(()),
+    }
+  }
+
+  fn list(&self) -> Result<Vec<CheckpointRecord>, DispatchError> {
+    let mut records = vec![];
+    for entry in fs::read_dir(&self.dir).map_err(io_error)? {
+      let path = entry.map_err(io_error)?.path();
+      if path.extension().and_then(|ext| ext.to_str()) != Some(CHECKPOINT_FILE_EXTENSION) {
+        continue;
+      }
+      match Self::read(&path) {
+        Ok(record) => records.push(record),
+        Err(err) => tracing::warn!("[checkpoint]: skip {}: {}", path.display(), err),
+      }
+    }
+    records.sort_by_key(|record| record.updated_at);
+    Ok(records)
+  }
+}
+
+fn io_error(err: std::io::Error) -> DispatchError {
+  InternalError::Other(format!("checkpoint store: {}", err)).into()
+}
@@ -0,0 +1,192 @@
+//! Lets the long-running handlers, like an import, resume where they stopped instead of starting
+//! over when the app dies halfway.
+//!
+//! The handlers of the events registered with [CheckpointMiddleware::checkpointed] read their
+//! [Checkpoint] with the `Extension<Checkpoint>` extractor and save their progress with
+//! [Checkpoint::save]. The checkpoint is removed once the handler responds. The ones left behind
+//! by a crash are found on the next startup by [CheckpointMiddleware::resume], which dispatches
+//! their event again with the last progress attached.
+#[cfg(feature = "use_msgpack")]
+pub use file_store::*;
+pub use store::*;
+
+#[cfg(feature = "use_msgpack")]
+mod file_store;
+mod store;
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use crate::dispatcher::AFPluginDispatcher;
+use crate::errors::{DispatchError, InternalError};
+use crate::middleware::AFPluginMiddleware;
+use crate::module::AFPluginRequest;
+use crate::response::AFPluginEventResponse;
+
+/// The metadata naming the checkpoint of a request. The requests without it are checkpointed
+/// under their id.
+pub const CHECKPOINT_KEY_METADATA: &str = "checkpoint-key";
+
+/// The last progress saved by a handler, along with what's needed to dispatch its request again.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckpointRecord {
+  pub key: String,
+  pub event: String,
+  pub payload: Bytes,
+  pub progress: Bytes,
+  /// In milliseconds since the unix epoch.
+  pub updated_at: u64,
+}
+
+/// The checkpoint of a request, attached by the [CheckpointMiddleware].
+#[derive(Clone)]
+pub struct Checkpoint {
+  key: String,
+  event: String,
+  payload: Bytes,
+  resumed: bool,
+  progress: Arc<Mutex<Option<Bytes>>>,
+  store: Arc<dyn CheckpointStore>,
+}
+
+impl Checkpoint {
+  pub fn key(&self) -> &str {
+    &self.key
+  }
+
+  /// Whether the request was dispatched again after it was interrupted, see
+  /// [CheckpointMiddleware::resume].
+  pub fn is_resumed(&self) -> bool {
+    self.resumed
+  }
+
+  /// The progress saved last, `None` until the handler saves one.
+  pub fn progress(&self) -> Option<Bytes> {
+    self.progress.lock().unwrap().clone()
+  }
+
+  /// Persists `progress`, replacing the previous one. It's what the handler gets back from
+  /// [Checkpoint::progress] if the request is resumed.
+  pub fn save<T: Into<Bytes>>(&self, progress: T) -> Result<(), DispatchError> {
+    let progress = progress.into();
+    let record = CheckpointRecord {
+      key: self.key.clone(),
+      event: self.event.clone(),
+      payload: self.payload.clone(),
+      progress: progress.clone(),
+      updated_at: SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default(),
+    };
+    self.store.save(&record)?;
+    *self.progress.lock().unwrap() = Some(progress);
+    Ok(())
+  }
+}
+
+/// Attaches a [Checkpoint] to the requests of the checkpointed events and removes it once their
+/// handler responds, whether it succeeded or not. The requests rejected by the dispatcher, or
+/// interrupted, keep their checkpoint.
+///
+/// The middleware is registered with [CheckpointMiddleware::register].
+#[derive(Clone)]
+pub struct CheckpointMiddleware {
+  store: Arc<dyn CheckpointStore>,
+  events: Arc<HashSet<String>>,
+}
+
+impl CheckpointMiddleware {
+  pub fn new(store: Arc<dyn CheckpointStore>) -> Self {
+    Self {
+      store,
+      events: Arc::new(HashSet::new()),
+    }
+  }
+
+  pub fn checkpointed<E: ToString>(mut self, event: E) -> Self {
+    Arc::make_mut(&mut self.events).insert(event.to_string());
+    self
+  }
+
+  /// Registers the middleware in `dispatcher`.
+  pub fn register(&self, dispatcher: AFPluginDispatcher) -> AFPluginDispatcher {
+    dispatcher.with_middleware(self.clone())
+  }
+
+  /// Dispatches the requests interrupted before their handler responded again, one after the
+  /// other, from the least recently saved. It's called once on startup, before the client sends
+  /// new requests. With the `local_set` feature, it must run in a `LocalSet`.
+  ///
+  /// Returns the key of each checkpoint along with the response of its request.
+  pub async fn resume(
+    &self,
+    dispatcher: &AFPluginDispatcher,
+  ) -> Result<Vec<(String, AFPluginEventResponse)>, DispatchError> {
+    let mut responses = vec![];
+    for record in self.store.list()? {
+      if !self.events.contains(&record.event) {
+        tracing::warn!(
+          "[checkpoint]: drop {}, {} is not checkpointed",
+          record.key,
+          record.event
+        );
+        self.store.remove(&record.key)?;
+        continue;
+      }
+      tracing::info!("[checkpoint]: resume {} of {}", record.key, record.event);
+      let request = AFPluginRequest::new(record.event.as_str())
+        .payload(record.payload)
+        .metadata(CHECKPOINT_KEY_METADATA, &record.key);
+      let response =
+        AFPluginDispatcher::async_send_with_callback(dispatcher, request, |_| Box::pin(async {}))
+          .await;
+      responses.push((record.key, response));
+    }
+    Ok(responses)
+  }
+}
+
+impl AFPluginMiddleware for CheckpointMiddleware {
+  fn on_request(&self, request: &mut AFPluginRequest) -> Result<(), DispatchError> {
+    if !self.events.contains(request.event.as_str()) {
+      return Ok(());
+    }
+    let key = request
+      .metadata
+      .get(CHECKPOINT_KEY_METADATA)
+      .cloned()
+      .unwrap_or_else(|| request.id.clone());
+    let record = self.store.load(&key).map_err(|err| {
+      DispatchError::from(InternalError::Other(format!(
+        "Failed to load the checkpoint {}: {}",
+        key, err
+      )))
+    })?;
+    let progress = record.map(|record| record.progress);
+    request.extensions.insert(Checkpoint {
+      key,
+      event: request.event.as_str().to_owned(),
+      payload: Bytes::copy_from_slice(request.payload_bytes()),
+      resumed: progress.is_some(),
+      progress: Arc::new(Mutex::new(progress)),
+      store: self.store.clone(),
+    });
+    Ok(())
+  }
+
+  fn on_response(&self, request: &AFPluginRequest, response: &mut AFPluginEventResponse) {
+    if response.error_origin.is_some() {
+      return;
+    }
+    if let Some(checkpoint) = request.extensions.get::<Checkpoint>() {
+      if let Err(err) = self.store.remove(&checkpoint.key) {
+        tracing::error!("[checkpoint]: failed to remove {}: {}", checkpoint.key, err);
+      }
+    }
+  }
+}
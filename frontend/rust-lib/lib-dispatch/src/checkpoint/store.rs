@@ -0,0 +1,16 @@
+use crate::checkpoint::CheckpointRecord;
+use crate::errors::DispatchError;
+
+/// Keeps the last [CheckpointRecord] of each key until the handler completes.
+pub trait CheckpointStore: Send + Sync + 'static {
+  /// Replaces the record of `record.key`.
+  fn save(&self, record: &CheckpointRecord) -> Result<(), DispatchError>;
+
+  fn load(&self, key: &str) -> Result<Option<CheckpointRecord>, DispatchError>;
+
+  /// Removing a key without a record is not an error.
+  fn remove(&self, key: &str) -> Result<(), DispatchError>;
+
+  /// Returns all the records, from the least to the most recently saved.
+  fn list(&self) -> Result<Vec<CheckpointRecord>, DispatchError>;
+}
@@ -0,0 +1,128 @@
+//! The client of the `flowy-cli` tool, which sends events to a running app serving its
+//! dispatcher with [serve_unix_socket](crate::bridge::serve_unix_socket):
+//!
+//! ```text
+//! flowy-cli --socket /tmp/appflowy.sock --token <token> events
+//! flowy-cli --socket /tmp/appflowy.sock --token <token> send GetUserProfile
+//! flowy-cli --socket /tmp/appflowy.sock --token <token> send CreateView '{"name": "Untitled"}'
+//! ```
+//!
+//! The debug builds of the app serve it on the socket set by `APPFLOWY_CLI_SOCKET`, and
+//! authenticate the requests with the session tokens issued by the `IssueSessionToken` event.
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+
+use bytes::Bytes;
+
+use crate::encoding::ContentType;
+use crate::module::EventSchema;
+use crate::proxy::{RemoteRequest, RemoteResponse, RemoteTransport, UnixSocketTransport};
+use crate::response::StatusCode;
+use crate::system::SysEvent;
+
+#[derive(Debug)]
+pub enum CliError {
+  /// The app isn't listening on the socket, or closed the connection.
+  Transport(String),
+  /// The payload to send isn't valid JSON.
+  InvalidPayload(String),
+  /// The app answered with an error.
+  Event(String),
+}
+
+impl Display for CliError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      CliError::Transport(msg) => write!(f, "Can not reach the app: {}", msg),
+      CliError::InvalidPayload(msg) => write!(f, "The payload is not valid JSON: {}", msg),
+      CliError::Event(msg) => write!(f, "The app failed: {}", msg),
+    }
+  }
+}
+
+impl std::error::Error for CliError {}
+
+/// Sends events to the app listening on a Unix domain socket. The payloads are tagged as JSON:
+/// the plugins convert them into the protobuf messages their handlers decode, see
+/// [AFPlugin::proto_descriptors](crate::prelude::AFPlugin::proto_descriptors), and the other
+/// handlers get them as they are.
+pub struct EventClient {
+  transport: UnixSocketTransport,
+}
+
+impl EventClient {
+  pub fn new<P: Into<PathBuf>>(socket: P) -> Self {
+    Self {
+      transport: UnixSocketTransport::new(socket),
+    }
+  }
+
+  /// Authenticates the requests with the session `token`, see
+  /// [AuthMiddleware](crate::prelude::AuthMiddleware).
+  pub fn with_token<T: ToString>(mut self, token: T) -> Self {
+    self.transport = self.transport.with_token(token);
+    self
+  }
+
+  /// The events registered in the app, sorted by event.
+  pub async fn events(&self) -> Result<Vec<EventSchema>, CliError> {
+    let response = self
+      .send_bytes(&SysEvent::Schema.to_string(), Bytes::new(), None)
+      .await?;
+    if response.status_code != StatusCode::Ok {
+      return Err(CliError::Event(format_payload(&response.payload)));
+    }
+    serde_json::from_slice(&response.payload).map_err(|e| CliError::Event(e.to_string()))
+  }
+
+  /// Sends `event` with `payload`, which must be JSON if given.
+  pub async fn send(&self, event: &str, payload: Option<&str>) -> Result<RemoteResponse, CliError> {
+    match payload {
+      Some(payload) => {
+        let value = serde_json::from_str::<serde_json::Value>(payload)
+          .map_err(|e| CliError::InvalidPayload(e.to_string()))?;
+        let payload = Bytes::from(value.to_string());
+        self
+          .send_bytes(event, payload, Some(ContentType::Json))
+          .await
+      },
+      None => self.send_bytes(event, Bytes::new(), None).await,
+    }
+  }
+
+  async fn send_bytes(
+    &self,
+    event: &str,
+    payload: Bytes,
+    content_type: Option<ContentType>,
+  ) -> Result<RemoteResponse, CliError> {
+    let request = RemoteRequest {
+      event: event.to_owned(),
+      payload,
+      content_type,
+    };
+    self
+      .transport
+      .send(request)
+      .await
+      .map_err(|err| CliError::Transport(err.0))
+  }
+}
+
+/// The payload of a response as a human would read it: indented if it's JSON, as text if it's
+/// UTF-8, in hexadecimal otherwise.
+pub fn format_payload(payload: &[u8]) -> String {
+  if let Ok(value) = serde_json::from_slice::<serde_json::Value>(payload) {
+    if let Ok(json) = serde_json::to_string_pretty(&value) {
+      return json;
+    }
+  }
+  match std::str::from_utf8(payload) {
+    Ok(text) => text.to_owned(),
+    Err(_) => payload
+      .iter()
+      .map(|byte| format!("{:02x}", byte))
+      .collect::<Vec<_>>()
+      .join(" "),
+  }
+}
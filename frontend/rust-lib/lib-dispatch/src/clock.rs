@@ -0,0 +1,157 @@
+use std::fmt::{Debug, Formatter};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::future::{select, Either};
+use futures_channel::oneshot;
+use futures_core::future::BoxFuture;
+
+/// The source of time of the dispatcher. The durations it measures, like the time a request
+/// waited in the queue or took to be handled, and its timers are read from the clock, so the
+/// tests can replace the [SystemClock] with a [MockClock] and move the time forward by hand
+/// instead of sleeping.
+pub trait Clock: Send + Sync + 'static {
+  fn now(&self) -> Instant;
+
+  /// Resolves once `duration` has passed according to the clock.
+  fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+}
+
+/// The wall clock, backed by tokio's timers.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+  fn now(&self) -> Instant {
+    Instant::now()
+  }
+
+  fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+    Box::pin(tokio::time::sleep(duration))
+  }
+}
+
+/// The error returned by [timeout] when the future did not finish in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Elapsed(pub Duration);
+
+impl std::fmt::Display for Elapsed {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "timed out after {:?}", self.0)
+  }
+}
+
+impl std::error::Error for Elapsed {}
+
+/// Runs `future` until it finishes or `duration` passes according to `clock`.
+pub async fn timeout<F>(
+  clock: &dyn Clock,
+  duration: Duration,
+  future: F,
+) -> Result<F::Output, Elapsed>
+where
+  F: Future,
+{
+  let future = std::pin::pin!(future);
+  match select(future, clock.sleep(duration)).await {
+    Either::Left((output, _)) => Ok(output),
+    Either::Right(_) => Err(Elapsed(duration)),
+  }
+}
+
+/// A clock that only moves when [MockClock::advance] is called. The clones share the same time,
+/// so a test can keep one and hand the other to the dispatcher with
+/// [AFPluginDispatcher::with_clock](crate::prelude::AFPluginDispatcher::with_clock).
+#[derive(Clone)]
+pub struct MockClock {
+  inner: Arc<MockClockInner>,
+}
+
+struct MockClockInner {
+  origin: Instant,
+  state: Mutex<MockClockState>,
+}
+
+#[derive(Default)]
+struct MockClockState {
+  elapsed: Duration,
+  /// The pending sleeps, with the elapsed time at which they resolve.
+  sleepers: Vec<(Duration, oneshot::Sender<()>)>,
+}
+
+impl Default for MockClock {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Debug for MockClock {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("MockClock")
+      .field("elapsed", &self.elapsed())
+      .field("pending_sleeps", &self.pending_sleeps())
+      .finish()
+  }
+}
+
+impl MockClock {
+  pub fn new() -> Self {
+    Self {
+      inner: Arc::new(MockClockInner {
+        origin: Instant::now(),
+        state: Mutex::new(MockClockState::default()),
+      }),
+    }
+  }
+
+  /// Moves the time forward by `duration` and wakes up the sleeps that are due.
+  pub fn advance(&self, duration: Duration) {
+    let due = {
+      let mut state = self.inner.state.lock().unwrap();
+      state.elapsed += duration;
+      let elapsed = state.elapsed;
+      let (due, pending) = std::mem::take(&mut state.sleepers)
+        .into_iter()
+        .partition::<Vec<_>, _>(|(deadline, _)| *deadline <= elapsed);
+      state.sleepers = pending;
+      due
+    };
+    // The sleeps are woken up outside of the lock, their tasks may read the clock right away.
+    for (_, sender) in due {
+      let _ = sender.send(());
+    }
+  }
+
+  /// How far the clock moved since it was created.
+  pub fn elapsed(&self) -> Duration {
+    self.inner.state.lock().unwrap().elapsed
+  }
+
+  /// The number of sleeps that are still waiting for the clock to move.
+  pub fn pending_sleeps(&self) -> usize {
+    let mut state = self.inner.state.lock().unwrap();
+    state.sleepers.retain(|(_, sender)| !sender.is_canceled());
+    state.sleepers.len()
+  }
+}
+
+impl Clock for MockClock {
+  fn now(&self) -> Instant {
+    self.inner.origin + self.elapsed()
+  }
+
+  fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+    if duration.is_zero() {
+      return Box::pin(async {});
+    }
+
+    let (sender, receiver) = oneshot::channel();
+    let mut state = self.inner.state.lock().unwrap();
+    let deadline = state.elapsed + duration;
+    state.sleepers.push((deadline, sender));
+    Box::pin(async move {
+      let _ = receiver.await;
+    })
+  }
+}
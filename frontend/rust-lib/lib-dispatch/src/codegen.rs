@@ -0,0 +1,402 @@
+//! Generates the Dart side of the events from the [EventSchema]s of the plugins, so the Flutter
+//! app doesn't hand-write event names that drift from the ones registered in Rust.
+//!
+//! Every plugin becomes a Dart enum, named after the plugin, whose values carry the names of
+//! its events. The request and response types of the handlers are mapped to Dart types: the
+//! primitives, the collections and the options to their Dart counterparts, the payload wrappers
+//! like [AFPluginData](crate::prelude::AFPluginData) or [Json](crate::prelude::Json) to the
+//! type they wrap. The remaining types get a model stub, a class holding the JSON of the value,
+//! unless they are declared as [external](DartCodegen::external_model), like the protobuf
+//! messages generated elsewhere.
+//!
+//! Call it from a build script or an xtask with the plugins of the app, or from `flowy-cli dart`
+//! with the schemas of a running app:
+//!
+//! ```ignore
+//! DartCodegen::from_plugins(&plugins)
+//!   .import("package:appflowy_backend/protobuf/flowy-folder/protobuf.dart")
+//!   .external_model("ViewPB")
+//!   .write_to("appflowy_flutter/lib/generated/events.dart")?;
+//! ```
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::module::{AFPlugin, EventSchema};
+
+/// The extractors that read the context of a request rather than its payload. They're left out
+/// of the request type of an event.
+const CONTEXT_EXTRACTORS: &[&str] = &[
+  "AFPluginState",
+  "AppData",
+  "CancellationToken",
+  "Config",
+  "Extension",
+  "KeyedState",
+  "Shared",
+  "Transaction",
+];
+
+/// The types standing for the value they wrap on the Dart side.
+const WRAPPERS: &[&str] = &[
+  "AFPluginData",
+  "Arc",
+  "Bincode",
+  "Box",
+  "Capnp",
+  "Cbor",
+  "Flatbuffer",
+  "Json",
+  "MsgPack",
+  "ProtoLite",
+  "Rc",
+];
+
+/// The names an enum value can't take, either Dart keywords or the members of the generated
+/// enums.
+const RESERVED: &str = "assert break case catch class const continue default do else enum event \
+  extends false final finally for hashCode if in index is new null rethrow return runtimeType \
+  super switch this throw true try values var void while with";
+
+/// Generates a Dart file from the schemas of the events, see the [module](self) documentation.
+#[derive(Debug, Clone, Default)]
+pub struct DartCodegen {
+  schemas: Vec<EventSchema>,
+  imports: Vec<String>,
+  external_models: HashSet<String>,
+}
+
+impl DartCodegen {
+  pub fn new(schemas: Vec<EventSchema>) -> Self {
+    Self {
+      schemas,
+      ..Default::default()
+    }
+  }
+
+  /// The schemas of the events of `plugins`, before they are handed to the dispatcher.
+  pub fn from_plugins(plugins: &[AFPlugin]) -> Self {
+    Self::new(plugins.iter().flat_map(AFPlugin::schemas).collect())
+  }
+
+  /// Imports `uri` in the generated file, e.g. the file defining the external models.
+  pub fn import(mut self, uri: &str) -> Self {
+    self.imports.push(uri.to_owned());
+    self
+  }
+
+  /// Uses the Dart class `name`, the last segment of the Rust type, as it is instead of
+  /// generating a stub of it.
+  pub fn external_model(mut self, name: &str) -> Self {
+    self.external_models.insert(name.to_owned());
+    self
+  }
+
+  /// Returns the Dart source. The same schemas always give the same source.
+  pub fn generate(&self) -> String {
+    let mut plugins = BTreeMap::<&str, Vec<&EventSchema>>::new();
+    for schema in &self.schemas {
+      plugins
+        .entry(schema.plugin.as_str())
+        .or_default()
+        .push(schema);
+    }
+    let mut models = BTreeMap::new();
+    let mut out = String::new();
+    out.push_str("// GENERATED CODE - DO NOT MODIFY BY HAND\n");
+    out.push_str("// Generated by lib-dispatch from the event schemas of the plugins.\n");
+    if !self.imports.is_empty() {
+      out.push('\n');
+    }
+    for uri in &self.imports {
+      let _ = writeln!(out, "import '{}';", escape(uri));
+    }
+
+    for (plugin, mut schemas) in plugins {
+      schemas.sort_by(|a, b| a.event.cmp(&b.event));
+      let enum_name = format!("{}Event", pascal_case(plugin));
+      let mut taken = HashSet::new();
+      out.push('\n');
+      if !plugin.is_empty() {
+        let _ = writeln!(out, "/// The events of the `{}` plugin.", plugin);
+      }
+      let _ = writeln!(out, "enum {} {{", enum_name);
+      for (i, schema) in schemas.iter().enumerate() {
+        let request = self.dart_type(&parse_type(&schema.payload), true, &mut models);
+        let response = self.dart_type(&parse_type(&schema.response), false, &mut models);
+        let mut doc = format!("`{}` -> `{}`", request, response);
+        if let Some(versions) = &schema.versions {
+          let _ = write!(doc, ", versions {} to {}", versions.start(), versions.end());
+        }
+        let mut value = camel_case(&schema.event);
+        while RESERVED.split_whitespace().any(|word| word == value) || !taken.insert(value.clone())
+        {
+          value.push('_');
+        }
+        let separator = if i + 1 == schemas.len() { ';' } else { ',' };
+        let _ = writeln!(out, "  /// {}.", doc);
+        let _ = writeln!(out, "  {}('{}'){}", value, escape(&schema.event), separator);
+      }
+      let _ = writeln!(out, "\n  const {}(this.event);\n", enum_name);
+      out.push_str("  /// The name of the event in the Rust registry.\n");
+      out.push_str("  final String event;\n}\n");
+    }
+
+    for (name, path) in models {
+      out.push('\n');
+      let _ = writeln!(out, "/// A stub of the Rust type `{}`.", path);
+      let _ = writeln!(out, "class {} {{", name);
+      let _ = writeln!(out, "  const {}(this.json);\n", name);
+      let _ = writeln!(
+        out,
+        "  factory {}.fromJson(Map<String, dynamic> json) => {}(json);\n",
+        name, name
+      );
+      out.push_str("  final Map<String, dynamic> json;\n\n");
+      out.push_str("  Map<String, dynamic> toJson() => json;\n}\n");
+    }
+    out
+  }
+
+  /// Writes the Dart source to `path`, creating its directory. The file is left untouched if it
+  /// didn't change, so the Flutter build doesn't pick it up again. Returns whether it was
+  /// written.
+  pub fn write_to<P: AsRef<Path>>(&self, path: P) -> std::io::Result<bool> {
+    let path = path.as_ref();
+    let source = self.generate();
+    if std::fs::read_to_string(path).ok().as_deref() == Some(source.as_str()) {
+      return Ok(false);
+    }
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+      std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, source)?;
+    Ok(true)
+  }
+
+  /// The Dart type of `ty`, recording the models it needs a stub of. The request of an event is
+  /// the tuple of the arguments of its handler, the ones reading the payload.
+  fn dart_type(
+    &self,
+    ty: &RustType,
+    request: bool,
+    models: &mut BTreeMap<String, String>,
+  ) -> String {
+    let (path, args) = match ty {
+      RustType::Tuple(types) => {
+        let mut types = types
+          .iter()
+          .filter(|ty| !request || !is_context_extractor(ty))
+          .map(|ty| self.dart_type(ty, request, models))
+          .filter(|ty| ty != "void")
+          .collect::<Vec<_>>();
+        return match types.len() {
+          0 => "void".to_owned(),
+          1 => types.remove(0),
+          _ => "dynamic".to_owned(),
+        };
+      },
+      RustType::Slice(ty) => return format!("List<{}>", self.dart_type(ty, request, models)),
+      RustType::Named { path, args } => (path.as_str(), args.as_slice()),
+    };
+    let mut arg = |i: usize| {
+      args
+        .get(i)
+        .map(|ty| self.dart_type(ty, request, models))
+        .unwrap_or_else(|| "dynamic".to_owned())
+    };
+    let name = path.rsplit("::").next().unwrap_or(path);
+    match name {
+      "String" | "str" | "char" => "String".to_owned(),
+      "bool" => "bool".to_owned(),
+      "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128"
+      | "usize" => "int".to_owned(),
+      "f32" | "f64" => "double".to_owned(),
+      "Bytes" => "List<int>".to_owned(),
+      "Vec" | "VecDeque" | "HashSet" | "BTreeSet" => format!("List<{}>", arg(0)),
+      "HashMap" | "BTreeMap" => format!("Map<{}, {}>", arg(0), arg(1)),
+      "Option" => match arg(0) {
+        ty if ty == "dynamic" || ty == "void" || ty.ends_with('?') => ty,
+        ty => format!("{}?", ty),
+      },
+      // The error of a handler is sent as a failed response, not as its response.
+      "Result" => arg(0),
+      _ if WRAPPERS.contains(&name) => arg(0),
+      _ if path.starts_with("lib_dispatch::") || path.starts_with("serde_json::") => {
+        "dynamic".to_owned()
+      },
+      _ => {
+        if !self.external_models.contains(name) {
+          models
+            .entry(name.to_owned())
+            .or_insert_with(|| path.to_owned());
+        }
+        name.to_owned()
+      },
+    }
+  }
+}
+
+fn is_context_extractor(ty: &RustType) -> bool {
+  match ty {
+    RustType::Named { path, .. } => {
+      path.starts_with("lib_dispatch::")
+        && CONTEXT_EXTRACTORS.contains(&path.rsplit("::").next().unwrap_or(path))
+    },
+    _ => false,
+  }
+}
+
+/// A type as named by [std::any::type_name].
+#[derive(Debug, PartialEq)]
+enum RustType {
+  Named { path: String, args: Vec<RustType> },
+  Tuple(Vec<RustType>),
+  Slice(Box<RustType>),
+}
+
+type Chars<'a> = std::iter::Peekable<std::str::Chars<'a>>;
+
+fn parse_type(name: &str) -> RustType {
+  parse_next(&mut name.chars().peekable())
+}
+
+/// Skips the spaces and the references, which are the same type on the Dart side.
+fn skip_spaces(chars: &mut Chars) {
+  while chars
+    .peek()
+    .map_or(false, |c| c.is_whitespace() || *c == '&')
+  {
+    chars.next();
+  }
+}
+
+fn parse_list(chars: &mut Chars, close: char) -> Vec<RustType> {
+  let mut types = vec![];
+  skip_spaces(chars);
+  while let Some(c) = chars.peek() {
+    if *c == close {
+      chars.next();
+      break;
+    }
+    if *c == ',' {
+      chars.next();
+    } else {
+      types.push(parse_next(chars));
+    }
+    skip_spaces(chars);
+  }
+  types
+}
+
+fn parse_next(chars: &mut Chars) -> RustType {
+  skip_spaces(chars);
+  match chars.peek() {
+    Some('(') => {
+      chars.next();
+      RustType::Tuple(parse_list(chars, ')'))
+    },
+    Some('[') => {
+      chars.next();
+      let ty = parse_next(chars);
+      // Skips the length of an array.
+      for c in chars.by_ref() {
+        if c == ']' {
+          break;
+        }
+      }
+      RustType::Slice(Box::new(ty))
+    },
+    _ => {
+      let mut path = String::new();
+      while let Some(c) = chars.peek() {
+        if "<>,()[];".contains(*c) {
+          break;
+        }
+        path.push(*c);
+        chars.next();
+      }
+      let path = path.trim();
+      let path = path.strip_prefix("mut ").unwrap_or(path);
+      let path = path.strip_prefix("dyn ").unwrap_or(path).to_owned();
+      let args = if chars.peek() == Some(&'<') {
+        chars.next();
+        parse_list(chars, '>')
+      } else {
+        vec![]
+      };
+      RustType::Named { path, args }
+    },
+  }
+}
+
+fn words(name: &str) -> Vec<String> {
+  let mut words = vec![];
+  let mut word = String::new();
+  let mut prev_lower = false;
+  for c in name.chars() {
+    if !c.is_ascii_alphanumeric() {
+      prev_lower = false;
+      if !word.is_empty() {
+        words.push(std::mem::take(&mut word));
+      }
+      continue;
+    }
+    if c.is_ascii_uppercase() && prev_lower {
+      words.push(std::mem::take(&mut word));
+    }
+    prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+    word.push(c);
+  }
+  if !word.is_empty() {
+    words.push(word);
+  }
+  words
+}
+
+fn capitalize(word: &str) -> String {
+  let mut chars = word.chars();
+  match chars.next() {
+    Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+    None => String::new(),
+  }
+}
+
+/// `lib-dispatch` and `lib_dispatch` are `LibDispatch`.
+fn pascal_case(name: &str) -> String {
+  let name = words(name)
+    .iter()
+    .map(|word| capitalize(word))
+    .collect::<String>();
+  match name.chars().next() {
+    Some(c) if c.is_ascii_digit() => format!("E{}", name),
+    _ => name,
+  }
+}
+
+/// `create_view` and `CreateView` are `createView`.
+fn camel_case(name: &str) -> String {
+  let name = words(name)
+    .iter()
+    .enumerate()
+    .map(|(i, word)| {
+      if i == 0 {
+        word.to_ascii_lowercase()
+      } else {
+        capitalize(word)
+      }
+    })
+    .collect::<String>();
+  match name.chars().next() {
+    None => "unnamed".to_owned(),
+    Some(c) if c.is_ascii_digit() => format!("e{}", name),
+    _ => name,
+  }
+}
+
+/// Escapes `s` for a single quoted Dart string.
+fn escape(s: &str) -> String {
+  s.replace('\\', "\\\\")
+    .replace('\'', "\\'")
+    .replace('$', "\\$")
+}
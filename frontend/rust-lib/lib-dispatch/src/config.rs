@@ -0,0 +1,356 @@
+use std::any::type_name;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::ops::Deref;
+#[cfg(feature = "toml_config")]
+use std::path::Path;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::dispatcher::DEFAULT_SLOW_HANDLER_THRESHOLD;
+use crate::errors::{DispatchError, InternalError};
+use crate::module::AppData;
+use crate::request::{AFPluginEventRequest, FromAFPluginRequest, Payload};
+use crate::util::pool::DEFAULT_POOL_CAPACITY;
+use crate::util::ready::{ready, Ready};
+
+/// A section of the [ConfigStore], read by the handlers with the [Config] extractor. Each module
+/// declares the fields it needs, two modules can read the same section with different types.
+///
+/// ```ignore
+/// #[derive(Deserialize)]
+/// struct ServerConfig {
+///   base_url: String,
+/// }
+///
+/// impl ConfigSection for ServerConfig {
+///   const SECTION: &'static str = "server";
+/// }
+///
+/// async fn handler(config: Config<ServerConfig>) { .. }
+/// ```
+pub trait ConfigSection: DeserializeOwned + 'static {
+  const SECTION: &'static str;
+}
+
+/// The configuration of the app, loaded once when the SDK starts and registered in the
+/// dispatcher with [AFPluginDispatcher::config](crate::prelude::AFPluginDispatcher::config).
+/// The sections are kept as JSON and deserialized into the type asked by the handler.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigStore {
+  sections: Map<String, Value>,
+}
+
+impl ConfigStore {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Loads the sections from a JSON object, each key being a section.
+  pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+    let sections = serde_json::from_str::<Map<String, Value>>(json)?;
+    Ok(Self { sections })
+  }
+
+  /// Sets the section `name` to `value`, replacing the previous one.
+  pub fn set_section<T: Serialize>(&mut self, name: &str, value: T) -> Result<(), DispatchError> {
+    let value = serde_json::to_value(value).map_err(|err| {
+      InternalError::Other(format!(
+        "Serialize the config section {} failed: {}",
+        name, err
+      ))
+    })?;
+    self.sections.insert(name.to_owned(), value);
+    Ok(())
+  }
+
+  pub fn with_section<T: Serialize>(mut self, name: &str, value: T) -> Result<Self, DispatchError> {
+    self.set_section(name, value)?;
+    Ok(self)
+  }
+
+  pub fn contains(&self, name: &str) -> bool {
+    self.sections.contains_key(name)
+  }
+
+  /// Deserializes the section of `T`. A missing section is deserialized from `null`, so the
+  /// sections whose fields are all optional don't have to be set.
+  pub fn section<T: ConfigSection>(&self) -> Result<T, DispatchError> {
+    let value = self
+      .sections
+      .get(T::SECTION)
+      .cloned()
+      .unwrap_or(Value::Null);
+    serde_json::from_value(value).map_err(|err| {
+      InternalError::Other(format!(
+        "Deserialize the config section {} into {} failed: {}",
+        T::SECTION,
+        type_name::<T>(),
+        err
+      ))
+      .into()
+    })
+  }
+}
+
+/// Extracts the [ConfigSection] `T` from the [ConfigStore] registered in the dispatcher.
+pub struct Config<T>(pub T);
+
+impl<T> Config<T> {
+  pub fn into_inner(self) -> T {
+    self.0
+  }
+}
+
+impl<T> Deref for Config<T> {
+  type Target = T;
+
+  fn deref(&self) -> &T {
+    &self.0
+  }
+}
+
+impl<T> FromAFPluginRequest for Config<T>
+where
+  T: ConfigSection,
+{
+  type Error = DispatchError;
+  type Future = Ready<Result<Self, DispatchError>>;
+
+  #[inline]
+  fn from_request(req: &AFPluginEventRequest, _: &mut Payload) -> Self::Future {
+    let result = match req.get_app_data::<AppData<ConfigStore>>() {
+      Some(store) => store.section::<T>().map(Config),
+      None => Err(InternalError::Other("The config store is not registered".to_string()).into()),
+    };
+    if let Err(err) = &result {
+      tracing::error!("{}", err);
+    }
+    ready(result)
+  }
+}
+
+/// The name of the file the [SystemConfig] is read from, in the root directory of the app.
+pub const SYSTEM_CONFIG_FILE: &str = "dispatch.toml";
+
+/// The prefix of the environment variables overriding the [SystemConfig], followed by the name
+/// of the field in upper case, e.g. `APPFLOWY_DISPATCH_WORKER_THREADS=4`.
+pub const SYSTEM_CONFIG_ENV_PREFIX: &str = "APPFLOWY_DISPATCH_";
+
+const LOG_LEVELS: [&str; 6] = ["trace", "debug", "info", "warn", "error", "off"];
+
+/// The tuning of the dispatcher itself, loaded when the SDK starts so it can be changed without
+/// recompiling. Applied with `AFPluginRuntime::with_config` and
+/// `AFPluginDispatcher::with_system_config`.
+///
+/// ```toml
+/// worker_threads = 4
+/// request_timeout_ms = 30000
+/// log_level = "debug"
+///
+/// [max_pending]
+/// database = 64
+///
+/// [log_levels]
+/// flowy_folder = "trace"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SystemConfig {
+  /// The worker threads of the runtime, one per core when not set.
+  pub worker_threads: Option<usize>,
+  /// The idle per-request services kept for the next requests.
+  pub request_pool_capacity: usize,
+  /// The requests taking longer than this to be handled are reported as slow.
+  pub slow_handler_threshold_ms: u64,
+  /// The deadline of the requests sent without one, none when not set.
+  pub request_timeout_ms: Option<u64>,
+  /// The level of the logs of the core, e.g. `info`.
+  pub log_level: Option<String>,
+  /// The levels of the logs of some modules, by target, e.g. `flowy_folder` or
+  /// `lib_dispatch::dispatcher`. They take precedence over the [SystemConfig::log_level].
+  pub log_levels: BTreeMap<String, String>,
+  /// The requests each plugin may have queued or being handled, by plugin name. The requests
+  /// over the bound are rejected, see `ModuleQuota::max_pending`.
+  pub max_pending: BTreeMap<String, usize>,
+}
+
+impl Default for SystemConfig {
+  fn default() -> Self {
+    Self {
+      worker_threads: None,
+      request_pool_capacity: DEFAULT_POOL_CAPACITY,
+      slow_handler_threshold_ms: DEFAULT_SLOW_HANDLER_THRESHOLD.as_millis() as u64,
+      request_timeout_ms: None,
+      log_level: None,
+      log_levels: BTreeMap::new(),
+      max_pending: BTreeMap::new(),
+    }
+  }
+}
+
+impl SystemConfig {
+  /// Reads the config from the TOML file at `path`, then overrides it with the `vars` starting
+  /// with [SYSTEM_CONFIG_ENV_PREFIX], usually `std::env::vars()`. A missing file leaves the
+  /// defaults. All the invalid values are reported at once. Requires the `toml_config` feature.
+  #[cfg(feature = "toml_config")]
+  pub fn load<I>(path: &Path, vars: I) -> Result<Self, SystemConfigError>
+  where
+    I: IntoIterator<Item = (String, String)>,
+  {
+    let mut config = match std::fs::read_to_string(path) {
+      Ok(toml) => Self::from_toml(&toml).map_err(|err| err.in_file(path))?,
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => Self::default(),
+      Err(err) => {
+        return Err(SystemConfigError::new(format!(
+          "{}: {}",
+          path.display(),
+          err
+        )))
+      },
+    };
+    config.apply_env(vars)?;
+    config.validate()?;
+    Ok(config)
+  }
+
+  #[cfg(feature = "toml_config")]
+  pub fn from_toml(toml: &str) -> Result<Self, SystemConfigError> {
+    let config: Self =
+      toml::from_str(toml).map_err(|err| SystemConfigError::new(err.to_string()))?;
+    config.validate()?;
+    Ok(config)
+  }
+
+  /// Overrides the fields with the `vars` starting with [SYSTEM_CONFIG_ENV_PREFIX]. The
+  /// `max_pending` bounds and the `log_levels` can't be overridden.
+  pub fn apply_env<I>(&mut self, vars: I) -> Result<(), SystemConfigError>
+  where
+    I: IntoIterator<Item = (String, String)>,
+  {
+    let mut errors = vec![];
+    for (key, value) in vars {
+      let field = match key.strip_prefix(SYSTEM_CONFIG_ENV_PREFIX) {
+        Some(field) => field.to_lowercase(),
+        None => continue,
+      };
+      let result = match field.as_str() {
+        "worker_threads" => parse_env(&key, &value).map(|v| self.worker_threads = Some(v)),
+        "request_pool_capacity" => parse_env(&key, &value).map(|v| self.request_pool_capacity = v),
+        "slow_handler_threshold_ms" => {
+          parse_env(&key, &value).map(|v| self.slow_handler_threshold_ms = v)
+        },
+        "request_timeout_ms" => parse_env(&key, &value).map(|v| self.request_timeout_ms = Some(v)),
+        "log_level" => {
+          self.log_level = Some(value);
+          Ok(())
+        },
+        _ => Err(format!("{}: unknown setting", key)),
+      };
+      errors.extend(result.err());
+    }
+    SystemConfigError::from_errors(errors)
+  }
+
+  pub fn validate(&self) -> Result<(), SystemConfigError> {
+    let mut errors = vec![];
+    if self.worker_threads == Some(0) {
+      errors.push("worker_threads: must be greater than 0".to_string());
+    }
+    if self.request_timeout_ms == Some(0) {
+      errors.push("request_timeout_ms: must be greater than 0".to_string());
+    }
+    if let Some(level) = &self.log_level {
+      if !is_log_level(level) {
+        errors.push(format!(
+          "log_level: expected one of {:?}, got {}",
+          LOG_LEVELS, level
+        ));
+      }
+    }
+    for (target, level) in &self.log_levels {
+      if !is_log_target(target) {
+        errors.push(format!("log_levels: invalid target {:?}", target));
+      } else if !is_log_level(level) {
+        errors.push(format!(
+          "log_levels.{}: expected one of {:?}, got {}",
+          target, LOG_LEVELS, level
+        ));
+      }
+    }
+    for (plugin, max_pending) in &self.max_pending {
+      if *max_pending == 0 {
+        errors.push(format!("max_pending.{}: must be greater than 0", plugin));
+      }
+    }
+    SystemConfigError::from_errors(errors)
+  }
+
+  pub fn slow_handler_threshold(&self) -> Duration {
+    Duration::from_millis(self.slow_handler_threshold_ms)
+  }
+
+  pub fn request_timeout(&self) -> Option<Duration> {
+    self.request_timeout_ms.map(Duration::from_millis)
+  }
+}
+
+fn is_log_level(level: &str) -> bool {
+  LOG_LEVELS.contains(&level.to_lowercase().as_str())
+}
+
+/// A target is a module path, it can't hold the separators of the filter directives.
+fn is_log_target(target: &str) -> bool {
+  !target.is_empty()
+    && target
+      .chars()
+      .all(|c| c.is_alphanumeric() || c == '_' || c == ':' || c == '-')
+}
+
+fn parse_env<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, String> {
+  value
+    .trim()
+    .parse()
+    .map_err(|_| format!("{}: invalid value {}", key, value))
+}
+
+/// The invalid values of a [SystemConfig], one message per value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemConfigError {
+  pub errors: Vec<String>,
+}
+
+impl SystemConfigError {
+  pub(crate) fn new(error: String) -> Self {
+    Self {
+      errors: vec![error],
+    }
+  }
+
+  fn from_errors(errors: Vec<String>) -> Result<(), Self> {
+    if errors.is_empty() {
+      Ok(())
+    } else {
+      Err(Self { errors })
+    }
+  }
+
+  #[cfg(feature = "toml_config")]
+  fn in_file(mut self, path: &Path) -> Self {
+    for error in &mut self.errors {
+      *error = format!("{}: {}", path.display(), error);
+    }
+    self
+  }
+}
+
+impl Display for SystemConfigError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "Invalid dispatcher config: {}", self.errors.join(", "))
+  }
+}
+
+impl std::error::Error for SystemConfigError {}
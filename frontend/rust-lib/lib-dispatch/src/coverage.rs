@@ -0,0 +1,148 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use serde::Serialize;
+
+use crate::module::AFPluginEvent;
+
+/// How many times an event was dispatched and how many times its handler ran. The handler does
+/// not run when the event is mocked, rejected by a middleware or has an incompatible version.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct EventCoverageEntry {
+  pub dispatched: u64,
+  pub handled: u64,
+}
+
+/// Records the events dispatched while the tests run, see
+/// [AFPluginDispatcher::with_coverage](crate::prelude::AFPluginDispatcher::with_coverage).
+/// The same instance can be shared by the dispatchers of a whole test suite, usually the one
+/// returned by [EventCoverage::global], and reported once all the tests are done.
+#[derive(Debug, Default)]
+pub struct EventCoverage {
+  entries: Mutex<BTreeMap<String, EventCoverageEntry>>,
+}
+
+impl EventCoverage {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// The instance shared by all the dispatchers of the process.
+  pub fn global() -> Arc<EventCoverage> {
+    static GLOBAL: OnceLock<Arc<EventCoverage>> = OnceLock::new();
+    GLOBAL
+      .get_or_init(|| Arc::new(EventCoverage::new()))
+      .clone()
+  }
+
+  /// Adds `events` to the events that are expected to be covered.
+  pub(crate) fn register<'a>(&self, events: impl IntoIterator<Item = &'a AFPluginEvent>) {
+    let mut entries = self.entries.lock().unwrap();
+    for event in events {
+      entries.entry(event.as_str().to_owned()).or_default();
+    }
+  }
+
+  pub(crate) fn dispatched(&self, event: &AFPluginEvent) {
+    let mut entries = self.entries.lock().unwrap();
+    entries
+      .entry(event.as_str().to_owned())
+      .or_default()
+      .dispatched += 1;
+  }
+
+  pub(crate) fn handled(&self, event: &AFPluginEvent) {
+    let mut entries = self.entries.lock().unwrap();
+    entries
+      .entry(event.as_str().to_owned())
+      .or_default()
+      .handled += 1;
+  }
+
+  pub fn get(&self, event: &str) -> Option<EventCoverageEntry> {
+    self.entries.lock().unwrap().get(event).cloned()
+  }
+
+  pub fn report(&self) -> CoverageReport {
+    let entries = self.entries.lock().unwrap().clone();
+    let never_dispatched = entries
+      .iter()
+      .filter(|(_, entry)| entry.dispatched == 0)
+      .map(|(event, _)| event.clone())
+      .collect();
+    let never_handled = entries
+      .iter()
+      .filter(|(_, entry)| entry.handled == 0)
+      .map(|(event, _)| event.clone())
+      .collect();
+    CoverageReport {
+      entries,
+      never_dispatched,
+      never_handled,
+    }
+  }
+
+  /// Writes the JSON report to `path`, so a CI job can pick it up once the test suite is done.
+  pub fn write_report<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+    std::fs::write(path, self.report().to_json())
+  }
+
+  pub fn clear(&self) {
+    self.entries.lock().unwrap().clear();
+  }
+}
+
+/// The coverage of the events, sorted by event.
+#[derive(Debug, Clone, Serialize)]
+pub struct CoverageReport {
+  pub entries: BTreeMap<String, EventCoverageEntry>,
+  /// The events that no test dispatched.
+  pub never_dispatched: Vec<String>,
+  /// The events whose handler never ran, including the ones that were never dispatched.
+  pub never_handled: Vec<String>,
+}
+
+impl CoverageReport {
+  /// The share of the events whose handler ran at least once, between 0 and 1.
+  pub fn ratio(&self) -> f64 {
+    if self.entries.is_empty() {
+      return 1.0;
+    }
+    let handled = self.entries.len() - self.never_handled.len();
+    handled as f64 / self.entries.len() as f64
+  }
+
+  pub fn to_json(&self) -> String {
+    serde_json::to_string_pretty(self).unwrap_or_default()
+  }
+}
+
+impl Display for CoverageReport {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    writeln!(
+      f,
+      "event coverage: {}/{} handled ({:.1}%)",
+      self.entries.len() - self.never_handled.len(),
+      self.entries.len(),
+      self.ratio() * 100.0
+    )?;
+    for (event, entry) in &self.entries {
+      let status = if entry.dispatched == 0 {
+        "never dispatched"
+      } else if entry.handled == 0 {
+        "never handled"
+      } else {
+        "ok"
+      };
+      writeln!(
+        f,
+        "  {}: dispatched {}, handled {}, {}",
+        event, entry.dispatched, entry.handled, status
+      )?;
+    }
+    Ok(())
+  }
+}
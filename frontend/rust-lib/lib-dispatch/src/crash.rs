@@ -0,0 +1,249 @@
+//! Makes the panics of the handlers show up in the crash reports with the event that caused
+//! them. The dispatcher names its worker threads after [WORKER_THREAD_PREFIX], records the panics
+//! of the handlers in its [EventRecorder] and answers the request with an error, so the worker
+//! goes on with the next request instead of dying with it.
+//!
+//! The panics the core doesn't recover from are written to a [CrashReport], along with what the
+//! dispatcher was doing, see [AFPluginDispatcher::with_crash_file]. The app reads it on the next
+//! launch with [take_crash_report] and can offer to submit it.
+//!
+//! [AFPluginDispatcher::with_crash_file]: crate::prelude::AFPluginDispatcher::with_crash_file
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::future::Future;
+use std::panic::{AssertUnwindSafe, PanicInfo};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Once, OnceLock, Weak};
+
+use futures::FutureExt;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{DispatchError, InternalError};
+use crate::recorder::{now_millis, EventRecord, EventRecorder, PanicRecord};
+use crate::system::{
+  InFlightRequestSnapshot, InFlightRequests, ModuleHealth, ModuleInit, SystemState,
+};
+
+/// The worker threads of the dispatcher are named `flowy-dispatch-{n}`.
+pub const WORKER_THREAD_PREFIX: &str = "flowy-dispatch";
+
+/// What the core was doing when it crashed, written to the crash file of the dispatcher.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CrashReport {
+  /// The time of the crash, in milliseconds since the unix epoch.
+  pub timestamp: u64,
+  pub thread: String,
+  pub message: String,
+  /// The `file:line:column` of the panic.
+  pub location: Option<String>,
+  pub backtrace: String,
+  /// The event whose handler panicked, `None` if the panic happened outside of the handlers.
+  pub event: Option<String>,
+  /// The last requests handled, from the oldest to the newest, see [EventRecorder].
+  pub records: Vec<EventRecord>,
+  /// The panics of the handlers the dispatcher recovered from before.
+  pub panics: Vec<PanicRecord>,
+  /// The requests being handled, from the oldest to the newest.
+  pub in_flight: Vec<InFlightRequestSnapshot>,
+  /// The initialization of the lazy states of the plugins.
+  pub modules: Vec<ModuleHealth>,
+}
+
+/// The dispatcher state captured in a crash file. The dispatcher isn't kept alive by it.
+struct CrashFile {
+  path: PathBuf,
+  recorder: Weak<EventRecorder>,
+  in_flight: Weak<InFlightRequests>,
+  modules: Weak<OnceLock<Vec<ModuleInit>>>,
+}
+
+impl CrashFile {
+  fn is_alive(&self) -> bool {
+    self.recorder.strong_count() > 0
+  }
+
+  fn report(&self, info: &PanicInfo, event: Option<String>) -> Option<CrashReport> {
+    let recorder = self.recorder.upgrade()?;
+    let in_flight = self
+      .in_flight
+      .upgrade()
+      .map(|in_flight| in_flight.snapshot())
+      .unwrap_or_default()
+      .into_iter()
+      .map(|request| InFlightRequestSnapshot {
+        age_ms: request.age().as_millis() as u64,
+        id: request.id,
+        event: request.event,
+      })
+      .collect();
+    let modules = self
+      .modules
+      .upgrade()
+      .and_then(|modules| modules.get().cloned())
+      .unwrap_or_default()
+      .into_iter()
+      .map(|module| ModuleHealth {
+        status: module.status(),
+        plugin: module.plugin,
+        state: module.state,
+      })
+      .collect();
+    Some(CrashReport {
+      timestamp: now_millis(),
+      thread: std::thread::current()
+        .name()
+        .unwrap_or("<unnamed>")
+        .to_owned(),
+      message: panic_message(info.payload()),
+      location: info.location().map(ToString::to_string),
+      backtrace: Backtrace::force_capture().to_string(),
+      event,
+      records: recorder.dump(),
+      panics: recorder.panics(),
+      in_flight,
+      modules,
+    })
+  }
+}
+
+static CRASH_FILES: Mutex<Vec<CrashFile>> = Mutex::new(Vec::new());
+
+/// Writes the crash reports of the dispatchers of `state` to `path`, see
+/// [AFPluginDispatcher::with_crash_file](crate::prelude::AFPluginDispatcher::with_crash_file).
+pub(crate) fn register_crash_file(path: PathBuf, state: &SystemState) {
+  let _ = state.crash_file.set(path.clone());
+  if let Ok(mut files) = CRASH_FILES.lock() {
+    files.retain(CrashFile::is_alive);
+    files.push(CrashFile {
+      path,
+      recorder: Arc::downgrade(&state.recorder),
+      in_flight: Arc::downgrade(&state.in_flight),
+      modules: Arc::downgrade(&state.modules),
+    });
+  }
+}
+
+/// Writes the crash reports, unless a crash file wasn't taken yet: the first crash is the one
+/// that matters, the next panics are usually its consequences.
+fn write_crash_reports(info: &PanicInfo, event: Option<String>) {
+  // A panic while writing a report must not wait for the lock it holds.
+  let files = match CRASH_FILES.try_lock() {
+    Ok(files) => files,
+    Err(_) => return,
+  };
+  for file in files.iter().filter(|file| !file.path.exists()) {
+    let report = match file.report(info, event.clone()) {
+      Some(report) => report,
+      None => continue,
+    };
+    let json = match serde_json::to_vec_pretty(&report) {
+      Ok(json) => json,
+      Err(_) => continue,
+    };
+    // Written next to it first, so a crash file is never read half written.
+    let tmp = file.path.with_extension("tmp");
+    if std::fs::write(&tmp, json).is_ok() {
+      let _ = std::fs::rename(&tmp, &file.path);
+    }
+  }
+}
+
+/// Reads the crash report written to `path` by the previous run of the app, and removes it.
+/// Returns `None` if the app didn't crash.
+pub fn take_crash_report<P: AsRef<Path>>(path: P) -> Option<CrashReport> {
+  let path = path.as_ref();
+  let bytes = std::fs::read(path).ok()?;
+  let _ = std::fs::remove_file(path);
+  match serde_json::from_slice(&bytes) {
+    Ok(report) => Some(report),
+    Err(err) => {
+      tracing::warn!(
+        "[dispatch]: invalid crash report {}: {}",
+        path.display(),
+        err
+      );
+      None
+    },
+  }
+}
+
+/// The request whose handler is running on the current task.
+struct HandlerContext {
+  event: String,
+  id: String,
+  recorder: Arc<EventRecorder>,
+}
+
+tokio::task_local! {
+  static CURRENT_HANDLER: HandlerContext;
+}
+
+/// Installs the panic hook recording the panics of the handlers, with their backtrace, in the
+/// recorder of their dispatcher. The other panics, and the ones of the handlers if the panics
+/// abort, are written to the crash files. It runs the hook that was installed before it, so the
+/// panics are still printed. Only the first call installs it, the dispatcher calls it when it's
+/// created.
+///
+/// Every panic outside of the handlers is taken for a crash, even if it's caught, like the ones
+/// of the tasks spawned on tokio.
+pub fn install_panic_hook() {
+  static INSTALL: Once = Once::new();
+  INSTALL.call_once(|| {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+      let event = CURRENT_HANDLER
+        .try_with(|handler| {
+          let thread = std::thread::current();
+          handler.recorder.record_panic(
+            &handler.event,
+            &handler.id,
+            thread.name().unwrap_or("<unnamed>"),
+            &panic_message(info.payload()),
+            Backtrace::force_capture().to_string(),
+          );
+          handler.event.clone()
+        })
+        .ok();
+      if event.is_none() || cfg!(panic = "abort") {
+        write_crash_reports(info, event);
+      }
+      previous(info);
+    }));
+  });
+}
+
+/// Runs the handling of a request, turning its panic into an error.
+pub(crate) async fn catch_handler_panic<F: Future>(
+  event: &str,
+  id: &str,
+  recorder: Arc<EventRecorder>,
+  future: F,
+) -> Result<F::Output, DispatchError> {
+  let handler = HandlerContext {
+    event: event.to_owned(),
+    id: id.to_owned(),
+    recorder,
+  };
+  CURRENT_HANDLER
+    .scope(handler, AssertUnwindSafe(future).catch_unwind())
+    .await
+    .map_err(|payload| {
+      let message = panic_message(payload.as_ref());
+      tracing::error!("[dispatch]: {} panicked: {}", event, message);
+      InternalError::HandlerPanicked {
+        event: event.to_owned(),
+        message,
+      }
+      .into()
+    })
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+  if let Some(message) = payload.downcast_ref::<&str>() {
+    message.to_string()
+  } else if let Some(message) = payload.downcast_ref::<String>() {
+    message.clone()
+  } else {
+    "Box<dyn Any>".to_string()
+  }
+}
@@ -0,0 +1,194 @@
+//! A terminal dashboard to watch the events flow during development, fed by the metrics, the
+//! recorder and the in-flight requests of a dispatcher.
+//!
+//! ```ignore
+//! let dashboard = Dashboard::new(&dispatcher);
+//! af_spawn(dashboard.run(Duration::from_secs(1), std::io::stderr()));
+//! ```
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::clock::Clock;
+use crate::metrics::{
+  MetricsRegistry, DISPATCH_DURATION_SECONDS, DISPATCH_ERRORS_TOTAL, DISPATCH_REQUESTS_TOTAL,
+};
+use crate::prelude::AFPluginDispatcher;
+use crate::recorder::{EventRecord, EventRecorder};
+use crate::system::InFlightRequests;
+
+/// The number of rows of each list of the dashboard, unless set with [Dashboard::rows].
+pub const DEFAULT_DASHBOARD_ROWS: usize = 5;
+
+/// Clears the terminal and moves the cursor to the top left corner.
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+pub struct Dashboard {
+  metrics: Arc<MetricsRegistry>,
+  recorder: Arc<EventRecorder>,
+  in_flight: Arc<InFlightRequests>,
+  clock: Arc<dyn Clock>,
+  rows: usize,
+  /// The time of the previous sample and the number of requests handled then.
+  last_sample: (Instant, u64),
+}
+
+impl Dashboard {
+  pub fn new(dispatcher: &AFPluginDispatcher) -> Self {
+    let metrics = dispatcher.metrics();
+    let clock = dispatcher.clock();
+    let last_sample = (clock.now(), metrics.counter_total(DISPATCH_REQUESTS_TOTAL));
+    Self {
+      metrics,
+      recorder: dispatcher.recorder(),
+      in_flight: dispatcher.in_flight(),
+      clock,
+      rows: DEFAULT_DASHBOARD_ROWS,
+      last_sample,
+    }
+  }
+
+  pub fn rows(mut self, rows: usize) -> Self {
+    self.rows = rows;
+    self
+  }
+
+  /// The activity of the dispatcher now. The rate is the one since the previous sample, or since
+  /// the dashboard was created.
+  pub fn sample(&mut self) -> DashboardFrame {
+    let now = self.clock.now();
+    let requests_total = self.metrics.counter_total(DISPATCH_REQUESTS_TOTAL);
+    let (last_at, last_total) = std::mem::replace(&mut self.last_sample, (now, requests_total));
+    let elapsed = now.saturating_duration_since(last_at).as_secs_f64();
+    let requests_per_second = if elapsed > 0.0 {
+      requests_total.saturating_sub(last_total) as f64 / elapsed
+    } else {
+      0.0
+    };
+
+    let in_flight = self
+      .in_flight
+      .snapshot()
+      .into_iter()
+      .take(self.rows)
+      .map(|request| InFlightRow {
+        age_ms: request.age().as_millis() as u64,
+        id: request.id,
+        event: request.event,
+      })
+      .collect();
+
+    let mut slowest = self
+      .metrics
+      .histograms_by(DISPATCH_DURATION_SECONDS, "event")
+      .into_iter()
+      .filter(|(_, histogram)| histogram.count() > 0)
+      .map(|(event, histogram)| EventLatency {
+        event,
+        count: histogram.count(),
+        mean_ms: histogram.sum().as_secs_f64() * 1000.0 / histogram.count() as f64,
+      })
+      .collect::<Vec<_>>();
+    slowest.sort_by(|a, b| b.mean_ms.total_cmp(&a.mean_ms));
+    slowest.truncate(self.rows);
+
+    let recent_errors = self
+      .recorder
+      .dump()
+      .into_iter()
+      .rev()
+      .filter(|record| !record.success)
+      .take(self.rows)
+      .collect();
+
+    DashboardFrame {
+      requests_per_second,
+      requests_total,
+      errors_total: self.metrics.counter_total(DISPATCH_ERRORS_TOTAL),
+      in_flight_total: self.in_flight.len(),
+      in_flight,
+      slowest,
+      recent_errors,
+    }
+  }
+
+  /// Redraws the dashboard on `out`, a terminal, every `interval` according to the clock of the
+  /// dispatcher. Only returns if writing fails.
+  pub async fn run<W: std::io::Write>(
+    mut self,
+    interval: Duration,
+    mut out: W,
+  ) -> std::io::Result<()> {
+    loop {
+      let frame = self.sample();
+      write!(out, "{}{}", CLEAR_SCREEN, frame)?;
+      out.flush()?;
+      self.clock.sleep(interval).await;
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InFlightRow {
+  pub id: String,
+  pub event: String,
+  pub age_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EventLatency {
+  pub event: String,
+  pub count: u64,
+  pub mean_ms: f64,
+}
+
+/// What the [Dashboard] shows at a point in time.
+#[derive(Debug, Clone, Serialize)]
+pub struct DashboardFrame {
+  pub requests_per_second: f64,
+  pub requests_total: u64,
+  pub errors_total: u64,
+  pub in_flight_total: usize,
+  /// The oldest requests being handled.
+  pub in_flight: Vec<InFlightRow>,
+  /// The events that take the longest on average.
+  pub slowest: Vec<EventLatency>,
+  /// The last requests that failed, the newest first.
+  pub recent_errors: Vec<EventRecord>,
+}
+
+impl Display for DashboardFrame {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    writeln!(
+      f,
+      "{:.1} req/s  total: {}  errors: {}  in flight: {}",
+      self.requests_per_second, self.requests_total, self.errors_total, self.in_flight_total
+    )?;
+
+    writeln!(f, "\nIn flight")?;
+    for request in &self.in_flight {
+      writeln!(
+        f,
+        "  {:<40} {:>8}ms  {}",
+        request.event, request.age_ms, request.id
+      )?;
+    }
+
+    writeln!(f, "\nSlowest events")?;
+    for latency in &self.slowest {
+      writeln!(
+        f,
+        "  {:<40} {:>8.3}ms  x{}",
+        latency.event, latency.mean_ms, latency.count
+      )?;
+    }
+
+    writeln!(f, "\nRecent errors")?;
+    for record in &self.recent_errors {
+      writeln!(f, "  {}", record)?;
+    }
+    Ok(())
+  }
+}
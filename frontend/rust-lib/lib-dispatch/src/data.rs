@@ -124,13 +124,7 @@ where
   T: AFPluginFromBytes,
 {
   match payload {
-    Payload::None => Err(
-      InternalError::UnexpectedNone(format!(
-        "Parse fail, expected payload:{:?}",
-        std::any::type_name::<T>()
-      ))
-      .into(),
-    ),
+    Payload::None => Ok(AFPluginData(T::parse_from_none()?)),
     Payload::Bytes(bytes) => {
       let data = T::parse_from_bytes(bytes.clone())?;
       Ok(AFPluginData(data))
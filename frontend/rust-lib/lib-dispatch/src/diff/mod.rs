@@ -0,0 +1,183 @@
+//! Answers the repeated fetches of a large payload, like the snapshot of a document, with a
+//! binary patch against the payload the client already holds instead of the full payload.
+//!
+//! Every response of the events registered with [DiffResponses::event] carries the hash of its
+//! payload in the [PAYLOAD_HASH_METADATA]. The client keeps the payload and sends its hash along
+//! with the next request, see [AFPluginRequest::diff_base]. If the dispatcher still has that
+//! payload, and the patch is smaller, the response carries the patch with the base hash in the
+//! [DIFF_BASE_METADATA]. Otherwise it carries the full payload. [HeldPayload] does the client
+//! side.
+pub use patch::*;
+
+mod patch;
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+
+use bytes::Bytes;
+
+use crate::middleware::ResponseMapper;
+use crate::module::{AFPluginEvent, AFPluginRequest};
+use crate::response::{AFPluginEventResponse, StatusCode};
+
+/// The metadata of the requests holding the hash of the payload the client holds, and of the
+/// diff responses holding the hash of the payload their patch applies to.
+pub const DIFF_BASE_METADATA: &str = "diff-base";
+
+/// The metadata of the responses holding the hash of their full payload.
+pub const PAYLOAD_HASH_METADATA: &str = "payload-hash";
+
+/// The hash identifying a payload: its 64-bit FNV-1a hash, in hexadecimal.
+pub fn payload_hash(payload: &[u8]) -> String {
+  let hash = payload.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+    (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+  });
+  format!("{:016x}", hash)
+}
+
+/// Turns the responses of the registered events into patches against the payload the client
+/// holds. It's a [ResponseMapper], registered with `AFPluginDispatcher::with_response_mapper`.
+///
+/// The last payloads returned are kept in memory to diff the next ones against, shared by all
+/// the events.
+pub struct DiffResponses {
+  events: HashSet<String>,
+  capacity: usize,
+  min_size: usize,
+  payloads: Mutex<VecDeque<(String, Bytes)>>,
+}
+
+impl Default for DiffResponses {
+  fn default() -> Self {
+    Self {
+      events: HashSet::new(),
+      capacity: 16,
+      min_size: 4096,
+      payloads: Mutex::new(VecDeque::new()),
+    }
+  }
+}
+
+impl DiffResponses {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn event<E: ToString>(mut self, event: E) -> Self {
+    self.events.insert(event.to_string());
+    self
+  }
+
+  /// How many payloads are kept to diff against, 16 by default.
+  pub fn capacity(mut self, capacity: usize) -> Self {
+    self.capacity = capacity;
+    self
+  }
+
+  /// The payloads smaller than `min_size` are always sent in full, 4KB by default.
+  pub fn min_size(mut self, min_size: usize) -> Self {
+    self.min_size = min_size;
+    self
+  }
+
+  /// Keeps `payload` as the most recent one and returns the one whose hash is `base`, if kept.
+  fn remember(&self, hash: &str, payload: &Bytes, base: Option<&str>) -> Option<Bytes> {
+    let mut payloads = self.payloads.lock().unwrap();
+    let base = base.and_then(|base| {
+      payloads
+        .iter()
+        .find(|(hash, _)| hash == base)
+        .map(|(_, payload)| payload.clone())
+    });
+    payloads.retain(|(kept, _)| kept != hash);
+    payloads.push_front((hash.to_owned(), payload.clone()));
+    payloads.truncate(self.capacity);
+    base
+  }
+}
+
+impl ResponseMapper for DiffResponses {
+  fn map(
+    &self,
+    request: &AFPluginRequest,
+    mut response: AFPluginEventResponse,
+  ) -> AFPluginEventResponse {
+    if !self.events.contains(request.event.as_str()) || response.status_code != StatusCode::Ok {
+      return response;
+    }
+    let payload = Bytes::copy_from_slice(response.payload.as_ref());
+    let hash = payload_hash(&payload);
+    let base_hash = request.metadata.get(DIFF_BASE_METADATA);
+    let base = self.remember(&hash, &payload, base_hash.map(String::as_str));
+    response
+      .metadata
+      .insert(PAYLOAD_HASH_METADATA.to_owned(), hash);
+    if let (Some(base_hash), Some(base)) = (base_hash, base) {
+      if payload.len() >= self.min_size {
+        let patch = BinaryPatch::diff(&base, &payload).to_bytes();
+        if patch.len() < payload.len() {
+          response.payload = patch.into();
+          response
+            .metadata
+            .insert(DIFF_BASE_METADATA.to_owned(), base_hash.clone());
+        }
+      }
+    }
+    response
+  }
+}
+
+/// The payload a client holds, to fetch the next version of it as a patch.
+///
+/// ```ignore
+/// let mut snapshot = HeldPayload::default();
+/// let request = snapshot.request(DocumentEvent::GetDocSnapshot).payload(doc_id);
+/// let response = AFPluginDispatcher::async_send(&dispatcher, request).await;
+/// let data = snapshot.update(&response)?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct HeldPayload {
+  hash: Option<String>,
+  payload: Bytes,
+}
+
+impl HeldPayload {
+  pub fn payload(&self) -> &Bytes {
+    &self.payload
+  }
+
+  /// A request asking for a patch against the held payload.
+  pub fn request<E: Into<AFPluginEvent>>(&self, event: E) -> AFPluginRequest {
+    let request = AFPluginRequest::new(event);
+    match &self.hash {
+      Some(hash) => request.diff_base(hash),
+      None => request,
+    }
+  }
+
+  /// Rebuilds the full payload of `response`, applying its patch if it carries one, and holds
+  /// it for the next request. The payload of an error is returned as it is, the held payload
+  /// is kept.
+  pub fn update(&mut self, response: &AFPluginEventResponse) -> Result<Bytes, PatchError> {
+    if response.status_code != StatusCode::Ok {
+      return Ok(Bytes::copy_from_slice(response.payload.as_ref()));
+    }
+    let payload = match response.metadata.get(DIFF_BASE_METADATA) {
+      Some(base) if self.hash.as_ref() == Some(base) => {
+        let patch = BinaryPatch::from_bytes(response.payload.as_ref())?;
+        Bytes::from(patch.apply(&self.payload)?)
+      },
+      Some(_) => return Err(PatchError::BaseMismatch),
+      None => Bytes::copy_from_slice(response.payload.as_ref()),
+    };
+    self.hash = response.metadata.get(PAYLOAD_HASH_METADATA).cloned();
+    if let Some(hash) = &self.hash {
+      if *hash != payload_hash(&payload) {
+        self.hash = None;
+        return Err(PatchError::Malformed);
+      }
+    }
+    self.payload = payload.clone();
+    Ok(payload)
+  }
+}
@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+/// The size of the blocks of the base looked up in the target. The changes closer than this
+/// are sent as a single insertion.
+const BLOCK_SIZE: usize = 32;
+const HASH_BASE: u64 = 0x0100_0000_01b3;
+
+const OP_COPY: u8 = 0;
+const OP_INSERT: u8 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchOp {
+  /// Copies `len` bytes of the base, from `offset`.
+  Copy {
+    offset: usize,
+    len: usize,
+  },
+  Insert(Vec<u8>),
+}
+
+/// Turns a base payload into a target one, see [BinaryPatch::diff]. The unchanged parts are
+/// copied from the base, so the patch of a small edit of a large payload stays small.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryPatch {
+  base_len: usize,
+  target_len: usize,
+  ops: Vec<PatchOp>,
+}
+
+impl BinaryPatch {
+  pub fn diff(base: &[u8], target: &[u8]) -> Self {
+    let mut patch = Self {
+      base_len: base.len(),
+      target_len: target.len(),
+      ops: vec![],
+    };
+    if base.len() < BLOCK_SIZE || target.len() < BLOCK_SIZE {
+      patch.insert(target);
+      return patch;
+    }
+
+    // The first offset of every block of the base, by hash.
+    let mut blocks = HashMap::new();
+    for offset in (0..=base.len() - BLOCK_SIZE).step_by(BLOCK_SIZE) {
+      blocks
+        .entry(block_hash(&base[offset..offset + BLOCK_SIZE]))
+        .or_insert(offset);
+    }
+    let top = HASH_BASE.wrapping_pow(BLOCK_SIZE as u32 - 1);
+
+    let mut literal_start = 0;
+    let mut pos = 0;
+    let mut hash = block_hash(&target[..BLOCK_SIZE]);
+    while pos + BLOCK_SIZE <= target.len() {
+      let matched = blocks
+        .get(&hash)
+        .filter(|offset| base[**offset..**offset + BLOCK_SIZE] == target[pos..pos + BLOCK_SIZE]);
+      if let Some(&offset) = matched {
+        // Extends the match both ways, backwards over the pending literal.
+        let mut start = pos;
+        let mut base_start = offset;
+        while start > literal_start && base_start > 0 && base[base_start - 1] == target[start - 1] {
+          start -= 1;
+          base_start -= 1;
+        }
+        let mut len = pos + BLOCK_SIZE - start;
+        while start + len < target.len()
+          && base_start + len < base.len()
+          && base[base_start + len] == target[start + len]
+        {
+          len += 1;
+        }
+        patch.insert(&target[literal_start..start]);
+        patch.copy(base_start, len);
+        pos = start + len;
+        literal_start = pos;
+        if pos + BLOCK_SIZE <= target.len() {
+          hash = block_hash(&target[pos..pos + BLOCK_SIZE]);
+        }
+        continue;
+      }
+      if pos + BLOCK_SIZE < target.len() {
+        hash = hash
+          .wrapping_sub((target[pos] as u64).wrapping_mul(top))
+          .wrapping_mul(HASH_BASE)
+          .wrapping_add(target[pos + BLOCK_SIZE] as u64);
+      }
+      pos += 1;
+    }
+    patch.insert(&target[literal_start..]);
+    patch
+  }
+
+  fn insert(&mut self, bytes: &[u8]) {
+    if bytes.is_empty() {
+      return;
+    }
+    match self.ops.last_mut() {
+      Some(PatchOp::Insert(literal)) => literal.extend_from_slice(bytes),
+      _ => self.ops.push(PatchOp::Insert(bytes.to_vec())),
+    }
+  }
+
+  fn copy(&mut self, offset: usize, len: usize) {
+    match self.ops.last_mut() {
+      Some(PatchOp::Copy {
+        offset: last_offset,
+        len: last_len,
+      }) if *last_offset + *last_len == offset => *last_len += len,
+      _ => self.ops.push(PatchOp::Copy { offset, len }),
+    }
+  }
+
+  pub fn ops(&self) -> &[PatchOp] {
+    &self.ops
+  }
+
+  /// Rebuilds the target from `base`, which must be the base the patch was computed from.
+  pub fn apply(&self, base: &[u8]) -> Result<Vec<u8>, PatchError> {
+    if base.len() != self.base_len {
+      return Err(PatchError::BaseMismatch);
+    }
+    let mut target = Vec::with_capacity(self.target_len);
+    for op in &self.ops {
+      match op {
+        PatchOp::Copy { offset, len } => {
+          let bytes = offset
+            .checked_add(*len)
+            .and_then(|end| base.get(*offset..end))
+            .ok_or(PatchError::Malformed)?;
+          target.extend_from_slice(bytes);
+        },
+        PatchOp::Insert(bytes) => target.extend_from_slice(bytes),
+      }
+    }
+    if target.len() != self.target_len {
+      return Err(PatchError::Malformed);
+    }
+    Ok(target)
+  }
+
+  /// Encodes the lengths and offsets as LEB128 varints: the base and target lengths, then each
+  /// operation, a copy as `0, offset, len` and an insertion as `1, len, bytes`.
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut bytes = vec![];
+    write_varint(&mut bytes, self.base_len);
+    write_varint(&mut bytes, self.target_len);
+    for op in &self.ops {
+      match op {
+        PatchOp::Copy { offset, len } => {
+          bytes.push(OP_COPY);
+          write_varint(&mut bytes, *offset);
+          write_varint(&mut bytes, *len);
+        },
+        PatchOp::Insert(literal) => {
+          bytes.push(OP_INSERT);
+          write_varint(&mut bytes, literal.len());
+          bytes.extend_from_slice(literal);
+        },
+      }
+    }
+    bytes
+  }
+
+  pub fn from_bytes(mut bytes: &[u8]) -> Result<Self, PatchError> {
+    let base_len = read_varint(&mut bytes)?;
+    let target_len = read_varint(&mut bytes)?;
+    let mut ops = vec![];
+    while let Some((&tag, rest)) = bytes.split_first() {
+      bytes = rest;
+      match tag {
+        OP_COPY => {
+          let offset = read_varint(&mut bytes)?;
+          let len = read_varint(&mut bytes)?;
+          ops.push(PatchOp::Copy { offset, len });
+        },
+        OP_INSERT => {
+          let len = read_varint(&mut bytes)?;
+          if bytes.len() < len {
+            return Err(PatchError::Malformed);
+          }
+          let (literal, rest) = bytes.split_at(len);
+          ops.push(PatchOp::Insert(literal.to_vec()));
+          bytes = rest;
+        },
+        _ => return Err(PatchError::Malformed),
+      }
+    }
+    Ok(Self {
+      base_len,
+      target_len,
+      ops,
+    })
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchError {
+  /// The patch wasn't computed from the given base.
+  BaseMismatch,
+  Malformed,
+}
+
+impl Display for PatchError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      PatchError::BaseMismatch => write!(f, "The patch doesn't apply to this base"),
+      PatchError::Malformed => write!(f, "Malformed patch"),
+    }
+  }
+}
+
+impl std::error::Error for PatchError {}
+
+fn block_hash(block: &[u8]) -> u64 {
+  block.iter().fold(0u64, |hash, byte| {
+    hash.wrapping_mul(HASH_BASE).wrapping_add(*byte as u64)
+  })
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: usize) {
+  while value >= 0x80 {
+    bytes.push((value as u8) | 0x80);
+    value >>= 7;
+  }
+  bytes.push(value as u8);
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Result<usize, PatchError> {
+  let mut value = 0usize;
+  let mut shift = 0;
+  loop {
+    let (&byte, rest) = bytes.split_first().ok_or(PatchError::Malformed)?;
+    *bytes = rest;
+    if shift >= usize::BITS {
+      return Err(PatchError::Malformed);
+    }
+    value |= ((byte & 0x7f) as usize) << shift;
+    if byte & 0x80 == 0 {
+      return Ok(value);
+    }
+    shift += 7;
+  }
+}
@@ -1,19 +1,64 @@
 use derivative::*;
 use pin_project::pin_project;
 use std::any::Any;
+use std::collections::BTreeMap;
+#[cfg(any(test, feature = "mock"))]
+use std::fmt::{Debug, Display};
 use std::future::Future;
+#[cfg(any(test, feature = "mock"))]
+use std::hash::Hash;
+use std::path::PathBuf;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
-use tracing::event;
+use std::time::{Duration, Instant};
+use tracing::{event, Instrument};
 
-use crate::module::AFPluginStateMap;
+use crate::clock::{timeout, Clock};
+use crate::config::{ConfigStore, SystemConfig};
+#[cfg(feature = "testing")]
+use crate::coverage::EventCoverage;
+use crate::crash::{catch_handler_panic, install_panic_hook, register_crash_file};
+use crate::executor::{Executor, ExecutorClock, ExecutorExt};
+#[cfg(feature = "fault_injection")]
+use crate::fault::{FaultInjector, InjectedFault};
+use crate::feature_flag::{FeatureFlags, FlagStore};
+use crate::gate::AppStates;
+use crate::lifecycle::{Lifecycle, LifecycleEvent, BACKGROUND_STATE};
+use crate::memory::{MemoryReport, MemoryReporters};
+use crate::metrics::{
+  MetricsRegistry, DISPATCH_DURATION_SECONDS, DISPATCH_ERRORS_TOTAL, DISPATCH_IN_FLIGHT,
+  DISPATCH_QUEUED, DISPATCH_REQUESTS_TOTAL, SLOW_HANDLER_TOTAL, UNREGISTERED_EVENT_LABEL,
+};
+use crate::middleware::{
+  map_response, middleware_response, run_request_middlewares, run_response_middlewares,
+  AFPluginMiddleware, AFPluginMiddlewares, ResponseMapper, ResponseMappers, DEPRECATION_METADATA,
+};
+#[cfg(any(test, feature = "mock"))]
+use crate::mock::EventMocks;
+use crate::module::{AFPluginStateMap, AppData, ErasedStateSnapshot, StateBus, StatesSnapshot};
+use crate::pipeline::Pipeline;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::proxy::RoutingTable;
+use crate::quota::{Metered, ModuleAccount, ModuleQuota, ResourceAccounting};
+use crate::recorder::EventRecorder;
 use crate::runtime::AFPluginRuntime;
+use crate::saga::{Saga, SagaError};
+use crate::system::{system_plugin, InFlightRequests, LiveConfig, SystemState, SYSTEM_PLUGIN_NAME};
+use crate::transaction::Transaction;
+use crate::util::budget::Budgeted;
+use crate::util::pool::{ObjectPool, Pooled};
 use crate::{
-  errors::{DispatchError, Error, InternalError},
-  module::{plugin_map_or_crash, AFPlugin, AFPluginMap, AFPluginRequest},
-  response::AFPluginEventResponse,
-  service::{AFPluginServiceFactory, Service},
+  byte_trait::{AFPluginFromBytes, ToBytes},
+  errors::{DispatchError, Error, InternalError, ResponseError},
+  module::{
+    plugin_map_or_crash, shared_states_or_crash, AFPlugin, AFPluginEvent, AFPluginMap,
+    AFPluginRegistry, AFPluginRequest, EventSchema, RequestPriority,
+  },
+  probe::{ChromeTracer, DispatchPhase, DispatchProbe, DispatchProbes},
+  request::{cancellable, cancellation_scope, current_cancellation, CancellationToken, Cancelled},
+  response::{AFPluginEventResponse, StatusCode},
+  service::Service,
 };
 
 #[cfg(feature = "local_set")]
@@ -60,28 +105,667 @@ pub type BoxFutureCallback =
 pub type BoxFutureCallback =
   Box<dyn FnOnce(AFPluginEventResponse) -> AFBoxFuture<'static, ()> + Send + Sync + 'static>;
 
+/// Spawns `future` as part of the current operation, see [current_cancellation]: the requests it
+/// sends are cancelled along with the operation. The future itself keeps running, use
+/// [af_spawn_cancellable] to stop it as well.
 #[track_caller]
 pub fn af_spawn<T>(future: T) -> tokio::task::JoinHandle<T::Output>
 where
   T: Future + Send + 'static,
   T::Output: Send + 'static,
 {
-  tokio::spawn(future)
+  match current_cancellation() {
+    Some(token) => tokio::spawn(cancellation_scope(token, future)),
+    None => tokio::spawn(future),
+  }
+}
+
+/// Spawns `future` under a child of the current operation's token. The future is dropped once the
+/// operation is cancelled, the task then resolves to [Cancelled].
+#[track_caller]
+pub fn af_spawn_cancellable<T>(future: T) -> tokio::task::JoinHandle<Result<T::Output, Cancelled>>
+where
+  T: Future + Send + 'static,
+  T::Output: Send + 'static,
+{
+  let token = current_cancellation()
+    .map(|token| token.child_token())
+    .unwrap_or_default();
+  tokio::spawn(cancellation_scope(token, cancellable(future)))
+}
+
+/// Runs `f` on the blocking pool with a child of the current operation's token. A blocking task
+/// can't be interrupted, `f` checks the token to stop early.
+#[cfg(not(target_arch = "wasm32"))]
+#[track_caller]
+pub fn af_spawn_blocking<F, R>(f: F) -> tokio::task::JoinHandle<R>
+where
+  F: FnOnce(CancellationToken) -> R + Send + 'static,
+  R: Send + 'static,
+{
+  let token = current_cancellation()
+    .map(|token| token.child_token())
+    .unwrap_or_default();
+  tokio::task::spawn_blocking(move || f(token))
 }
 
 pub struct AFPluginDispatcher {
-  plugins: AFPluginMap,
-  #[allow(dead_code)]
-  runtime: Arc<AFPluginRuntime>,
+  executor: Arc<dyn Executor>,
+  shared: Arc<DispatchShared>,
+  snapshotters: Vec<Arc<dyn ErasedStateSnapshot>>,
+  state_bus: StateBus,
+  /// Recycles the services built for every request, see [ObjectPool].
+  service_pool: ObjectPool<DispatchService>,
+}
+
+/// The requests that take longer than this to be handled are reported as slow.
+pub const DEFAULT_SLOW_HANDLER_THRESHOLD: Duration = Duration::from_millis(500);
+
+/// The part of the dispatcher every request reads. It's set up by the builder methods and
+/// never changes once the requests are flowing, so the requests share it behind a single [Arc]
+/// instead of taking a reference on each of its fields. The settings that can be changed while
+/// the app runs are in the [LiveConfig](crate::system::LiveConfig) of the system state.
+#[derive(Clone)]
+pub(crate) struct DispatchShared {
+  plugins: AFPluginRegistry,
+  system: SystemState,
+  middlewares: AFPluginMiddlewares,
+  response_mappers: ResponseMappers,
+  probes: DispatchProbes,
+  clock: Arc<dyn Clock>,
+  executor: Arc<dyn Executor>,
+  #[cfg(feature = "testing")]
+  coverage: Option<Arc<EventCoverage>>,
+  app_data: AFStateMap,
 }
 
 impl AFPluginDispatcher {
   pub fn new(runtime: Arc<AFPluginRuntime>, plugins: Vec<AFPlugin>) -> AFPluginDispatcher {
+    Self::with_executor(runtime, plugins)
+  }
+
+  /// A dispatcher running the requests and its timers on `executor` instead of a tokio runtime,
+  /// see [Executor].
+  pub fn with_executor(
+    executor: Arc<dyn Executor>,
+    mut plugins: Vec<AFPlugin>,
+  ) -> AFPluginDispatcher {
+    install_panic_hook();
+    let system = SystemState::new(executor.num_workers());
+    // The shared states are resolved from the same map as the app data.
+    let mut app_data = shared_states_or_crash(&mut plugins);
+    let state_bus = StateBus::new();
+    app_data.insert(AppData::new(state_bus.clone()));
+    app_data.insert(AppData::from(system.uploads.clone()));
+    let snapshotters = plugins
+      .iter()
+      .flat_map(|plugin| plugin.snapshotters().iter().cloned())
+      .collect();
+    for plugin in &plugins {
+      for (name, reporter) in plugin.memory_reporters() {
+        system.memory.register_arc(name, reporter.clone());
+        system
+          .accounting
+          .register_memory(&plugin.name, reporter.clone());
+      }
+      for observer in plugin.lifecycle_observers() {
+        system.lifecycle.observe_arc(&plugin.name, observer.clone());
+      }
+    }
+    plugins.push(system_plugin(system.clone()));
+    system.set_plugins(&plugins);
     tracing::trace!("{}", plugin_info(&plugins));
+    let mut probes = DispatchProbes::default();
+    probes.push(system.tracer.clone());
+    #[allow(clippy::arc_with_non_send_sync)]
+    let shared = Arc::new(DispatchShared {
+      plugins: AFPluginRegistry::new(plugin_map_or_crash(plugins)),
+      #[cfg(feature = "fault_injection")]
+      middlewares: Arc::new(vec![system.faults.clone() as Arc<dyn AFPluginMiddleware>]),
+      #[cfg(not(feature = "fault_injection"))]
+      middlewares: Arc::new(vec![]),
+      system,
+      response_mappers: Arc::new(vec![]),
+      probes,
+      clock: Arc::new(ExecutorClock::new(executor.clone())),
+      executor: executor.clone(),
+      #[cfg(feature = "testing")]
+      coverage: None,
+      app_data: Arc::new(app_data),
+    });
     AFPluginDispatcher {
-      plugins: plugin_map_or_crash(plugins),
-      runtime,
+      executor,
+      shared,
+      snapshotters,
+      state_bus,
+      service_pool: ObjectPool::default(),
+    }
+  }
+
+  /// Register `data` for the handlers of all the plugins. The handlers read it with the
+  /// [AppData] extractor.
+  pub fn data<D: Send + Sync + 'static>(mut self, data: D) -> Self {
+    Arc::get_mut(&mut self.shared_mut().app_data)
+      .expect("the app data must be registered before the dispatcher handles any request")
+      .insert(AppData::new(data));
+    self
+  }
+
+  /// Register an [ObservableState](crate::module::ObservableState) holding `value`, whose
+  /// changes are published on the [StateBus] of the dispatcher. The handlers read it with the
+  /// `AppData<ObservableState<T>>` extractor.
+  pub fn observable_state<T: Send + Sync + 'static>(self, value: T) -> Self {
+    let state = self.state_bus.observable(value);
+    self.data(state)
+  }
+
+  /// The bus the changes of the observable states are published on.
+  pub fn state_bus(&self) -> StateBus {
+    self.state_bus.clone()
+  }
+
+  /// Register the configuration read by the handlers with the [Config](crate::config::Config)
+  /// extractor.
+  pub fn config(self, store: ConfigStore) -> Self {
+    self.data(store)
+  }
+
+  /// Saves the states registered with [AFPlugin::snapshot_state].
+  pub fn snapshot_states(&self) -> StatesSnapshot {
+    StatesSnapshot::capture(&self.snapshotters)
+  }
+
+  /// Puts back the states saved by [AFPluginDispatcher::snapshot_states].
+  pub fn restore_states(&self, snapshot: &StatesSnapshot) {
+    snapshot.restore();
+  }
+
+  /// The states registered with [AFPlugin::snapshot_state] as they are now, by type name, see
+  /// [StateSnapshot::inspect](crate::module::StateSnapshot::inspect).
+  pub fn inspect_states(&self) -> BTreeMap<String, serde_json::Value> {
+    self
+      .snapshotters
+      .iter()
+      .map(|snapshotter| (snapshotter.name().to_owned(), snapshotter.inspect()))
+      .collect()
+  }
+
+  /// Keep up to `capacity` idle per-request services for the next requests, instead of
+  /// [DEFAULT_POOL_CAPACITY](crate::util::pool::DEFAULT_POOL_CAPACITY). Zero disables the
+  /// recycling.
+  pub fn with_request_pool_capacity(mut self, capacity: usize) -> Self {
+    self.service_pool = ObjectPool::new(capacity);
+    self
+      .shared
+      .system
+      .config
+      .modify(|config| config.request_pool_capacity = capacity);
+    self
+  }
+
+  /// Register a middleware that runs around every request. See [AFPluginMiddleware].
+  pub fn with_middleware<M: AFPluginMiddleware>(mut self, middleware: M) -> Self {
+    Arc::make_mut(&mut self.shared_mut().middlewares).push(Arc::new(middleware));
+    self
+  }
+
+  /// Register a mapper that rewrites every response once the middlewares are done with it. See
+  /// [ResponseMapper].
+  pub fn with_response_mapper<M: ResponseMapper>(mut self, mapper: M) -> Self {
+    Arc::make_mut(&mut self.shared_mut().response_mappers).push(Arc::new(mapper));
+    self
+  }
+
+  /// Throttles the events of the plugin named `module` once it exceeds `quota`, see
+  /// [ResourceAccounting].
+  pub fn with_quota(self, module: &str, quota: ModuleQuota) -> Self {
+    self.shared.system.accounting.set_quota(module, quota);
+    self
+  }
+
+  /// Register a probe that is notified of the [DispatchPhase]s of every request.
+  pub fn with_probe<P: DispatchProbe>(mut self, probe: P) -> Self {
+    self.shared_mut().probes.push(Arc::new(probe));
+    self
+  }
+
+  /// Reports the `version` of the crate `name`, e.g. the one of the app core, in the
+  /// [SysEvent::Info](crate::system::SysEvent::Info) event.
+  pub fn with_crate_version(self, name: &str, version: &str) -> Self {
+    if let Ok(mut versions) = self.shared.system.crate_versions.write() {
+      versions.insert(name.to_owned(), version.to_owned());
+    }
+    self
+  }
+
+  /// Writes a [CrashReport](crate::crash::CrashReport) to `path` when the core panics outside of
+  /// the handlers, with the last requests of the [EventRecorder], the in-flight requests and the
+  /// initialization of the lazy states. Read it on the next launch with
+  /// [take_crash_report](crate::crash::take_crash_report).
+  pub fn with_crash_file<P: Into<PathBuf>>(self, path: P) -> Self {
+    register_crash_file(path.into(), &self.shared.system);
+    self
+  }
+
+  /// Keeps the overrides of the [FeatureFlags] in `store` and restores the ones saved there.
+  pub fn with_flag_store<S: FlagStore>(self, store: S) -> Self {
+    if let Err(err) = self.shared.system.feature_flags.set_store(Arc::new(store)) {
+      tracing::error!("[dispatch]: failed to restore the feature flags: {}", err);
+    }
+    self
+  }
+
+  /// Requests whose handling takes longer than `threshold` are logged with a warning and counted
+  /// by the `slow_handler_total` metric.
+  pub fn with_slow_handler_threshold(self, threshold: Duration) -> Self {
+    self.shared.system.config.modify(|config| {
+      config.slow_handler_threshold_ms = threshold.as_millis() as u64;
+    });
+    self
+  }
+
+  /// Fail the requests sent without a deadline once they have been queued or handled for
+  /// `timeout`. The requests handled inline, see [AFPluginDispatcher::try_call_inline], are
+  /// not timed out.
+  pub fn with_default_timeout(self, timeout: Duration) -> Self {
+    self.shared.system.config.modify(|config| {
+      config.request_timeout_ms = Some(timeout.as_millis() as u64);
+    });
+    self
+  }
+
+  /// Applies the settings of `config` loaded when the SDK starts. The runtime is created from
+  /// it beforehand, see [AFPluginRuntime::with_config]. Most of them can be changed later with
+  /// [SysEvent::Reconfigure](crate::system::SysEvent::Reconfigure).
+  pub fn with_system_config(self, config: &SystemConfig) -> Self {
+    let mut dispatcher = self.with_request_pool_capacity(config.request_pool_capacity);
+    for (plugin, max_pending) in &config.max_pending {
+      dispatcher = dispatcher.with_quota(plugin, ModuleQuota::new().max_pending(*max_pending));
+    }
+    dispatcher
+      .shared
+      .system
+      .config
+      .modify(|live| *live = config.clone());
+    dispatcher
+  }
+
+  /// Measure the queue wait and the handling time of the requests with `clock` instead of the
+  /// [ExecutorClock]. See [MockClock](crate::clock::MockClock).
+  pub fn with_clock<C: Clock>(mut self, clock: C) -> Self {
+    self.shared_mut().clock = Arc::new(clock);
+    self
+  }
+
+  /// The clock the dispatcher measures the time with.
+  pub fn clock(&self) -> Arc<dyn Clock> {
+    self.shared.clock.clone()
+  }
+
+  /// The executor the requests run on.
+  pub fn executor(&self) -> Arc<dyn Executor> {
+    self.executor.clone()
+  }
+
+  /// Count the dispatched events and the handler calls in `coverage`. The events of the plugins
+  /// are registered right away, so the ones that are never dispatched show up in the report.
+  #[cfg(feature = "testing")]
+  pub fn with_coverage(mut self, coverage: Arc<EventCoverage>) -> Self {
+    coverage.register(
+      self
+        .shared
+        .plugins
+        .snapshot()
+        .iter()
+        .filter(|(_, plugin)| plugin.name != SYSTEM_PLUGIN_NAME)
+        .map(|(event, _)| event),
+    );
+    self.shared_mut().coverage = Some(coverage);
+    self
+  }
+
+  /// Answers `event` with a copy of `response` instead of calling its handler. See [EventMocks].
+  #[cfg(any(test, feature = "mock"))]
+  pub fn with_mock<E>(self, event: E, response: AFPluginEventResponse) -> Self
+  where
+    E: Eq + Hash + Debug + Clone + Display,
+  {
+    self.shared.system.mocks.mock(event, response);
+    self
+  }
+
+  /// Routes the events of `plugin` to it instead of their current plugin, and drops the events
+  /// of the plugin named like it that `plugin` does not handle. Lets a test run against a stub of
+  /// a whole plugin. The system events keep describing the replaced plugin.
+  pub fn replace_plugin(self, plugin: AFPlugin) -> Self {
+    self.shared.plugins.replace(plugin);
+    self
+  }
+
+  /// Adds `plugin` while the dispatcher is running. The requests dispatched after this call are
+  /// routed to it, the ones in flight are not affected. Fails if one of its events is already
+  /// handled, or if it shares states with [AFPlugin::shared_state], which must be known when the
+  /// dispatcher is created. The plugins registered this way are not described by the system
+  /// events.
+  pub fn register_plugin(&self, plugin: AFPlugin) -> Result<(), DispatchError> {
+    if plugin.has_shared_states() {
+      let msg = format!(
+        "{} shares states, it must be passed to AFPluginDispatcher::new",
+        plugin.name
+      );
+      return Err(InternalError::Other(msg).into());
     }
+    let events = plugin.events();
+    let name = plugin.name.clone();
+    let reporters = plugin.memory_reporters().to_vec();
+    let observers = plugin.lifecycle_observers().to_vec();
+    self
+      .shared
+      .plugins
+      .register(plugin)
+      .map_err(|msg| DispatchError::from(InternalError::Other(msg)))?;
+    #[cfg(feature = "testing")]
+    if let Some(coverage) = &self.shared.coverage {
+      coverage.register(&events);
+    }
+    let system = &self.shared.system;
+    for (reporter_name, reporter) in reporters {
+      system.accounting.register_memory(&name, reporter.clone());
+      system.memory.register_arc(&reporter_name, reporter);
+    }
+    for observer in observers {
+      system.lifecycle.observe_arc(&name, observer);
+    }
+    Ok(())
+  }
+
+  /// Removes the plugin named `name` while the dispatcher is running, along with its lifecycle
+  /// observers. Returns whether there was one.
+  pub fn unregister_plugin(&self, name: &str) -> bool {
+    let unregistered = self.shared.plugins.unregister(name);
+    if unregistered {
+      self.shared.system.lifecycle.unobserve(name);
+    }
+    unregistered
+  }
+
+  /// The mocks that answer the events in place of their handlers.
+  #[cfg(any(test, feature = "mock"))]
+  pub fn mocks(&self) -> Arc<EventMocks> {
+    self.shared.system.mocks.clone()
+  }
+
+  /// Whether one of the plugins handles `event`.
+  pub fn has_event(&self, event: &AFPluginEvent) -> bool {
+    self.shared.plugins.contains(event)
+  }
+
+  /// The name of the plugin that handles `event`.
+  pub fn plugin_name(&self, event: &AFPluginEvent) -> Option<String> {
+    self
+      .shared
+      .plugins
+      .get(event)
+      .map(|plugin| plugin.name.clone())
+  }
+
+  /// What `event` expects and returns, if one of the plugins handles it.
+  pub fn schema(&self, event: &AFPluginEvent) -> Option<EventSchema> {
+    let schemas = self.shared.system.schemas.get()?;
+    schemas
+      .iter()
+      .find(|schema| schema.event == event.as_str())
+      .cloned()
+  }
+
+  /// The schemas of all the events, sorted by event.
+  pub fn schemas(&self) -> Vec<EventSchema> {
+    self
+      .shared
+      .system
+      .schemas
+      .get()
+      .cloned()
+      .unwrap_or_default()
+  }
+
+  /// The events handled by the plugins, sorted by name.
+  pub fn events(&self) -> Vec<AFPluginEvent> {
+    let mut events = self
+      .shared
+      .plugins
+      .snapshot()
+      .keys()
+      .cloned()
+      .collect::<Vec<_>>();
+    events.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+    events
+  }
+
+  /// The metrics recorded while dispatching the requests. Plugins can register their own
+  /// metrics in the same registry.
+  pub fn metrics(&self) -> Arc<MetricsRegistry> {
+    self.shared.system.metrics.clone()
+  }
+
+  /// The reporters of the memory held by the core. The modules that are not plugins register
+  /// theirs here.
+  pub fn memory(&self) -> Arc<MemoryReporters> {
+    self.shared.system.memory.clone()
+  }
+
+  /// What the core is holding, by subsystem.
+  pub fn memory_report(&self) -> MemoryReport {
+    self.shared.system.memory.report()
+  }
+
+  /// The last requests handled by the dispatcher. Useful to find out what happened right before
+  /// the app froze or crashed.
+  pub fn recorder(&self) -> Arc<EventRecorder> {
+    self.shared.system.recorder.clone()
+  }
+
+  /// Records the timeline of the requests in the Chrome trace event format, also started and
+  /// stopped with [SysEvent::Trace](crate::system::SysEvent::Trace).
+  pub fn tracer(&self) -> Arc<ChromeTracer> {
+    self.shared.system.tracer.clone()
+  }
+
+  /// The resources used by each plugin, and their quotas.
+  pub fn accounting(&self) -> Arc<ResourceAccounting> {
+    self.shared.system.accounting.clone()
+  }
+
+  /// The requests that are currently being handled.
+  pub fn in_flight(&self) -> Arc<InFlightRequests> {
+    self.shared.system.in_flight.clone()
+  }
+
+  /// The states the app is in, checked against the preconditions of the events, see
+  /// [AFPlugin::requires].
+  pub fn app_states(&self) -> Arc<AppStates> {
+    self.shared.system.app_states.clone()
+  }
+
+  /// The flags the events are gated behind, see [AFPlugin::feature_flag].
+  pub fn feature_flags(&self) -> Arc<FeatureFlags> {
+    self.shared.system.feature_flags.clone()
+  }
+
+  /// Whether the app is in the background, and the observers of its lifecycle.
+  pub fn lifecycle(&self) -> Arc<Lifecycle> {
+    self.shared.system.lifecycle.clone()
+  }
+
+  /// The settings the dispatcher runs with. Observe it to apply the changes made with
+  /// [SysEvent::Reconfigure](crate::system::SysEvent::Reconfigure) outside of the dispatcher,
+  /// like the log level.
+  pub fn live_config(&self) -> Arc<LiveConfig> {
+    self.shared.system.config.clone()
+  }
+
+  /// The faults injected in the requests, see the [fault](crate::fault) module.
+  #[cfg(feature = "fault_injection")]
+  pub fn faults(&self) -> Arc<FaultInjector> {
+    self.shared.system.faults.clone()
+  }
+
+  /// Called when the app is sent to the background, where iOS and Android may suspend it at any
+  /// time. The [Low](RequestPriority::Low) priority requests wait in the queue until the app
+  /// enters the foreground again, the app enters the [BACKGROUND_STATE] and the modules are told
+  /// to flush their durable state, see [LifecycleObserver](crate::lifecycle::LifecycleObserver).
+  ///
+  /// Returns the first error of the observers that failed to flush, once all of them ran.
+  pub fn enter_background(&self) -> Result<(), DispatchError> {
+    let system = &self.shared.system;
+    if system.lifecycle.set_background(true) {
+      tracing::info!("[lifecycle]: enter the background");
+      system.app_states.enter(BACKGROUND_STATE);
+    }
+    system.lifecycle.notify(LifecycleEvent::EnterBackground)
+  }
+
+  /// Resumes the requests paused by [AFPluginDispatcher::enter_background].
+  pub fn enter_foreground(&self) {
+    let system = &self.shared.system;
+    if system.lifecycle.set_background(false) {
+      tracing::info!("[lifecycle]: enter the foreground");
+      system.app_states.leave(BACKGROUND_STATE);
+    }
+    let _ = system.lifecycle.notify(LifecycleEvent::EnterForeground);
+  }
+
+  /// Called when the OS warns that the app is using too much memory. Frees the idle request
+  /// services and tells the modules to trim their caches.
+  pub fn low_memory(&self) {
+    let system = &self.shared.system;
+    tracing::warn!(
+      "[lifecycle]: low memory, {}",
+      system.memory.report().to_json()
+    );
+    self.service_pool.clear();
+    let _ = system.lifecycle.notify(LifecycleEvent::LowMemory);
+  }
+
+  /// The events hosted by other processes, forwarded to them while they're up.
+  #[cfg(not(target_arch = "wasm32"))]
+  pub fn routes(&self) -> Arc<RoutingTable> {
+    self.shared.system.routes.clone()
+  }
+
+  /// Cancels the in-flight requests whose id is `request_id`, see [InFlightRequests::cancel].
+  pub fn cancel(&self, request_id: &str) -> bool {
+    self.shared.system.in_flight.cancel(request_id)
+  }
+
+  /// The shared state, for the builder methods. The requests in flight keep the state they
+  /// started with.
+  fn shared_mut(&mut self) -> &mut DispatchShared {
+    Arc::make_mut(&mut self.shared)
+  }
+
+  /// Handles `request` on the calling thread, without queuing it, if its handler was registered
+  /// with [AFPlugin::inline] and nothing else has to run around it: no middleware, mock,
+  /// coverage, deadline or low priority. Gives the request back otherwise, to be sent through
+  /// the queue as usual. The metrics and the recorder still see the request.
+  pub fn try_call_inline<Req>(&self, request: Req) -> Result<AFPluginEventResponse, AFPluginRequest>
+  where
+    Req: Into<AFPluginRequest>,
+  {
+    let mut request: AFPluginRequest = request.into();
+    let shared = &*self.shared;
+    if !shared.middlewares.is_empty()
+      || !shared.response_mappers.is_empty()
+      || request.deadline.is_some()
+      || request.priority == RequestPriority::Low
+    {
+      return Err(request);
+    }
+    #[cfg(feature = "testing")]
+    if shared.coverage.is_some() {
+      return Err(request);
+    }
+    #[cfg(any(test, feature = "mock"))]
+    if shared.system.mocks.get(&request.event).is_some() {
+      return Err(request);
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    if shared.system.routes.is_routed(&request.event) {
+      return Err(request);
+    }
+    let event = request.event.clone();
+    let id = request.id.clone();
+    let payload_size = request.payload.as_ref().len();
+    let started_at = shared.clock.now();
+    let response = shared.plugins.with(&event, |plugin| {
+      let plugin = match plugin {
+        Some(plugin) => plugin,
+        None => return Err(request),
+      };
+      // The queue reports the incompatible versions, the denied permissions, the disabled
+      // features, the invalid payloads, the failed preconditions and the throttled requests.
+      let account = shared.system.accounting.account(&plugin.name);
+      if plugin.check_version(&request).is_err()
+        || plugin.check_capabilities(&request).is_err()
+        || plugin
+          .check_feature_flag(&request, &shared.system.feature_flags)
+          .is_err()
+        || plugin.check_payload(&request).is_err()
+        || plugin
+          .check_preconditions(&request, &shared.system.app_states)
+          .is_err()
+        || account.exceeded(&request.event).is_some()
+      {
+        return Err(request);
+      }
+      #[cfg(feature = "use_protobuf")]
+      if plugin.convert_json_payload(&mut request).is_err() {
+        return Err(request);
+      }
+      request.probes = shared.probes.clone();
+      request.app_data = shared.app_data.clone();
+      let started_at = Instant::now();
+      let response = plugin.call_inline(request)?;
+      account.record_sync_call(started_at.elapsed());
+      Ok(response)
+    })?;
+    let elapsed = shared.clock.now().saturating_duration_since(started_at);
+    record_response_metrics(&shared.system.metrics, event.as_str(), &response, elapsed);
+    shared.system.recorder.record(
+      event.as_str(),
+      &id,
+      payload_size,
+      response.payload.as_ref().len(),
+      elapsed,
+      response.status_code == StatusCode::Ok,
+    );
+    Ok(response)
+  }
+
+  /// Called right before the request is queued. The request leaves the queue once the returned
+  /// service starts handling it.
+  fn service(&self, request: &AFPluginRequest) -> Pooled<DispatchService> {
+    self.shared.system.metrics.gauge(DISPATCH_QUEUED, &[]).inc();
+    let account = self.shared.plugins.with(&request.event, |plugin| {
+      plugin.map(|plugin| self.shared.system.accounting.account(&plugin.name))
+    });
+    if let Some(account) = &account {
+      account.enqueue();
+    }
+    self
+      .shared
+      .probes
+      .enter(&request.id, &request.event, DispatchPhase::QueueWait);
+    self.service_pool.take(DispatchService {
+      shared: self.shared.clone(),
+      queued_at: self.shared.clock.now(),
+      account,
+      // Captured here, the request is handled by another task.
+      parent_cancellation: request
+        .parent_cancellation
+        .clone()
+        .or_else(current_cancellation),
+    })
   }
 
   #[cfg(feature = "local_set")]
@@ -115,21 +799,24 @@ impl AFPluginDispatcher {
     Callback: FnOnce(AFPluginEventResponse) -> AFBoxFuture<'static, ()> + AFConcurrent + 'static,
   {
     let request: AFPluginRequest = request.into();
-    let plugins = dispatch.plugins.clone();
-    let service = Box::new(DispatchService { plugins });
+    let priority = request.priority;
+    let service = dispatch.service(&request);
     tracing::trace!("[dispatch]: Async event: {:?}", &request.event);
     let service_ctx = DispatchContext {
       request,
       callback: Some(Box::new(callback)),
     };
 
-    let result = tokio::task::spawn_local(async move {
+    let fut = async move {
       service.call(service_ctx).await.unwrap_or_else(|e| {
         tracing::error!("Dispatch runtime error: {:?}", e);
         InternalError::Other(format!("{:?}", e)).as_response()
       })
-    })
-    .await;
+    };
+    if priority == RequestPriority::High {
+      return fut.await;
+    }
+    let result = dispatch.executor.spawn_local_with_handle(fut).await;
 
     result.unwrap_or_else(|e| {
       let msg = format!("EVENT_DISPATCH join error: {:?}", e);
@@ -150,29 +837,31 @@ impl AFPluginDispatcher {
     Callback: FnOnce(AFPluginEventResponse) -> AFBoxFuture<'static, ()> + AFConcurrent + 'static,
   {
     let request: AFPluginRequest = request.into();
-    let plugins = dispatch.plugins.clone();
-    let service = Box::new(DispatchService { plugins });
+    let priority = request.priority;
+    let service = dispatch.service(&request);
     tracing::trace!("Async event: {:?}", &request.event);
     let service_ctx = DispatchContext {
       request,
       callback: Some(Box::new(callback)),
     };
 
-    dispatch
-      .runtime
-      .spawn(async move {
-        service.call(service_ctx).await.unwrap_or_else(|e| {
-          tracing::error!("Dispatch runtime error: {:?}", e);
-          InternalError::Other(format!("{:?}", e)).as_response()
-        })
-      })
-      .await
-      .unwrap_or_else(|e| {
-        let msg = format!("EVENT_DISPATCH join error: {:?}", e);
-        tracing::error!("{}", msg);
-        let error = InternalError::JoinError(msg);
-        error.as_response()
+    let fut = async move {
+      service.call(service_ctx).await.unwrap_or_else(|e| {
+        tracing::error!("Dispatch runtime error: {:?}", e);
+        InternalError::Other(format!("{:?}", e)).as_response()
       })
+    };
+    if priority == RequestPriority::High {
+      return fut.await;
+    }
+    let result = dispatch.executor.spawn_with_handle(fut).await;
+
+    result.unwrap_or_else(|e| {
+      let msg = format!("EVENT_DISPATCH join error: {:?}", e);
+      tracing::error!("{}", msg);
+      let error = InternalError::JoinError(msg);
+      error.as_response()
+    })
   }
 
   #[cfg(not(feature = "local_set"))]
@@ -186,26 +875,27 @@ impl AFPluginDispatcher {
     Callback: FnOnce(AFPluginEventResponse) -> AFBoxFuture<'static, ()> + AFConcurrent + 'static,
   {
     let request: AFPluginRequest = request.into();
-    let plugins = dispatch.plugins.clone();
-    let service = Box::new(DispatchService { plugins });
+    let priority = request.priority;
+    let service = dispatch.service(&request);
     tracing::trace!("[dispatch]: Async event: {:?}", &request.event);
     let service_ctx = DispatchContext {
       request,
       callback: Some(Box::new(callback)),
     };
 
-    let handle = dispatch.runtime.spawn(async move {
+    let fut = async move {
       service.call(service_ctx).await.unwrap_or_else(|e| {
         tracing::error!("[dispatch]: runtime error: {:?}", e);
         InternalError::Other(format!("{:?}", e)).as_response()
       })
-    });
-
-    let runtime = dispatch.runtime.clone();
+    };
+    if priority == RequestPriority::High {
+      return DispatchFuture { fut: Box::pin(fut) };
+    }
+    let handle = dispatch.executor.spawn_with_handle(fut);
     DispatchFuture {
       fut: Box::pin(async move {
-        let result = runtime.spawn(handle).await.unwrap();
-        result.unwrap_or_else(|e| {
+        handle.await.unwrap_or_else(|e| {
           let msg = format!("EVENT_DISPATCH join error: {:?}", e);
           tracing::error!("{}", msg);
           let error = InternalError::JoinError(msg);
@@ -215,11 +905,76 @@ impl AFPluginDispatcher {
     }
   }
 
+  /// Sends `payload` to the handler of `event` and decodes its response as a `R`. Use
+  /// [Empty](crate::prelude::Empty) for the events without input or output.
+  ///
+  /// The errors returned by the handler are decoded with [DispatchError::handler_error], and
+  /// [DispatchError::origin] tells them apart from the ones raised by the dispatcher.
+  pub async fn request<R, P, E>(&self, event: E, payload: P) -> Result<R, DispatchError>
+  where
+    R: AFPluginFromBytes,
+    P: ToBytes,
+    E: Into<AFPluginEvent>,
+  {
+    let request = AFPluginRequest::new(event).payload(payload.into_bytes()?);
+    AFPluginDispatcher::async_send_with_callback(self, request, |_| Box::pin(async {}))
+      .await
+      .into_data()
+  }
+
+  /// Sends the `requests` one after the other as a single [Transaction]. The handlers of the
+  /// requests get the same transaction with the [Transaction] extractor, and it's committed,
+  /// after being prepared, once all the requests succeeded.
+  ///
+  /// The first failed request rolls back the updates of all the requests, the ones after it
+  /// aren't sent. Its error is returned, or the error of the failed prepare. The requests sent
+  /// by others in the meantime see the updates before they're committed.
+  pub async fn dispatch_transaction<Req>(
+    &self,
+    requests: Vec<Req>,
+  ) -> Result<Vec<AFPluginEventResponse>, DispatchError>
+  where
+    Req: Into<AFPluginRequest> + 'static,
+  {
+    let transaction = Transaction::coordinated();
+    let mut responses = Vec::with_capacity(requests.len());
+    for request in requests {
+      let request: AFPluginRequest = request.into();
+      request.extensions.insert(transaction.clone());
+      let response =
+        AFPluginDispatcher::async_send_with_callback(self, request, |_| Box::pin(async {})).await;
+      if response.status_code != StatusCode::Ok {
+        transaction.rollback();
+        return Err(ResponseError(response).into());
+      }
+      responses.push(response);
+    }
+
+    if let Err(err) = transaction.prepare() {
+      transaction.rollback();
+      return Err(err);
+    }
+    transaction.commit();
+    Ok(responses)
+  }
+
+  /// Runs the steps of `saga` one after the other, and unwinds the ones that succeeded if a
+  /// step fails, see [Saga].
+  pub async fn run_saga(&self, saga: Saga) -> Result<Vec<AFPluginEventResponse>, SagaError> {
+    saga.run(self).await
+  }
+
+  /// Runs the events of `pipeline` one after the other, feeding the response of each event to
+  /// the next one, and returns the last response, see [Pipeline].
+  pub async fn run_pipeline(&self, pipeline: Pipeline) -> AFPluginEventResponse {
+    pipeline.run(self).await
+  }
+
   #[cfg(feature = "local_set")]
-  pub fn sync_send(
-    dispatch: Arc<AFPluginDispatcher>,
-    request: AFPluginRequest,
-  ) -> AFPluginEventResponse {
+  pub fn sync_send<Req>(dispatch: Arc<AFPluginDispatcher>, request: Req) -> AFPluginEventResponse
+  where
+    Req: Into<AFPluginRequest> + 'static,
+  {
     futures::executor::block_on(AFPluginDispatcher::async_send_with_callback(
       dispatch.as_ref(),
       request,
@@ -243,8 +998,20 @@ impl DispatchContext {
   }
 }
 
+/// What the plugin did with a request: answered it right away, or built the future of its
+/// handler.
+enum Handled {
+  Done(AFPluginEventResponse),
+  Pending(AFBoxFuture<'static, Result<AFPluginEventResponse, DispatchError>>),
+}
+
 pub(crate) struct DispatchService {
-  pub(crate) plugins: AFPluginMap,
+  pub(crate) shared: Arc<DispatchShared>,
+  pub(crate) queued_at: Instant,
+  /// The account of the plugin handling the request, if there's one.
+  pub(crate) account: Option<Arc<ModuleAccount>>,
+  /// The token of the operation that sent the request, see [AFPluginRequest::parent_cancellation].
+  pub(crate) parent_cancellation: Option<CancellationToken>,
 }
 
 impl Service<DispatchContext> for DispatchService {
@@ -252,53 +1019,335 @@ impl Service<DispatchContext> for DispatchService {
   type Error = DispatchError;
   type Future = AFBoxFuture<'static, Result<Self::Response, Self::Error>>;
 
-  #[tracing::instrument(name = "DispatchService", level = "debug", skip(self, ctx))]
   fn call(&self, ctx: DispatchContext) -> Self::Future {
-    let module_map = self.plugins.clone();
-    let (request, callback) = ctx.into_parts();
-
-    Box::pin(async move {
-      let result = {
-        match module_map.get(&request.event) {
-          Some(module) => {
-            let event = format!("{:?}", request.event);
-            event!(
-              tracing::Level::TRACE,
-              "[dispatch]: {:?} exec event:{}",
-              &module.name,
-              &event,
-            );
-            let fut = module.new_service(());
-            let service_fut = fut.await?.call(request);
-            let result = service_fut.await;
+    let shared = self.shared.clone();
+    let queued_at = self.queued_at;
+    let account = self.account.clone();
+    let parent_cancellation = self.parent_cancellation.clone();
+    let (mut request, callback) = ctx.into_parts();
+    // Every request gets its own span so the extractor/handler spans and all the events emitted
+    // while handling the request can be filtered by event, request id or plugin name.
+    let span = tracing::debug_span!(
+      "dispatch",
+      event = request.event.as_str(),
+      id = %request.id,
+      module = tracing::field::Empty,
+    );
+
+    Box::pin(
+      async move {
+        let DispatchShared {
+          plugins,
+          system,
+          middlewares,
+          response_mappers,
+          probes,
+          clock,
+          executor,
+          #[cfg(feature = "testing")]
+          coverage,
+          app_data,
+        } = &*shared;
+        if request.priority == RequestPriority::Low {
+          system.lifecycle.wait_foreground().await;
+          executor.yield_now().await;
+        }
+        // The requests sent to the former name of an event are handled as the ones of the event.
+        let alias = plugins.with(&request.event, |plugin| {
+          plugin.and_then(|plugin| plugin.event_alias(&request.event).cloned())
+        });
+        let (deprecation, removed) = match alias.map(|alias| alias.resolve(&mut request)) {
+          Some(Ok(notice)) => (Some(notice), None),
+          Some(Err(err)) => (None, Some(err)),
+          None => (None, None),
+        };
+        let event = request.event.clone();
+        let id = request.id.clone();
+        let deadline = request.deadline.or_else(|| {
+          system
+            .config
+            .request_timeout()
+            .map(|timeout| queued_at + timeout)
+        });
+        let payload_size = request.payload.as_ref().len();
+        let metrics = &system.metrics;
+        metrics.gauge(DISPATCH_QUEUED, &[]).dec();
+        if let Some(account) = &account {
+          account.dequeue();
+        }
+        #[cfg(feature = "testing")]
+        if let Some(coverage) = coverage {
+          coverage.dispatched(&event);
+        }
+        let in_flight_guard = system.in_flight.start(
+          &id,
+          event.as_str(),
+          metrics.gauge(DISPATCH_IN_FLIGHT, &[]),
+          parent_cancellation.as_ref(),
+        );
+        let started_at = clock.now();
+        let queue_wait = started_at.saturating_duration_since(queued_at);
+        probes.exit(&id, &event, DispatchPhase::QueueWait, request.created_at);
+        request.probes = probes.clone();
+        request.app_data = app_data.clone();
+        request.extensions.insert(in_flight_guard.cancellation());
+        request.mutating = plugins.with(&event, |plugin| {
+          plugin.map_or(false, |plugin| plugin.is_mutating(&event))
+        });
+        let rejected = match deadline {
+          Some(deadline) if deadline <= started_at => Some(deadline_exceeded(&event, true)),
+          _ => removed.or_else(|| run_request_middlewares(middlewares, &mut request).err()),
+        };
+        let middleware_response = match rejected {
+          None => middleware_response(middlewares, &request),
+          Some(_) => None,
+        };
+        let extensions = request.extensions.clone();
+        // The middlewares and the mappers get the request back once the response is ready.
+        let origin_request =
+          (!middlewares.is_empty() || !response_mappers.is_empty()).then(|| request.clone());
+        let handling = async {
+          if let Some(err) = rejected {
+            return Err(err);
+          }
+
+          #[cfg(feature = "fault_injection")]
+          if let Some(fault) = request.extensions.get::<InjectedFault>() {
+            let injected = fault.inject(clock.as_ref(), &event);
+            match deadline {
+              Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(clock.now());
+                timeout(clock.as_ref(), remaining, injected)
+                  .await
+                  .unwrap_or_else(|_| Err(deadline_exceeded(&event, false)))?
+              },
+              None => injected.await?,
+            }
+          }
+
+          if let Some(response) = middleware_response {
             event!(
               tracing::Level::TRACE,
-              "[dispatch]: {:?} exec event:{} with result: {}",
-              &module.name,
-              &event,
-              result.is_ok()
+              "[dispatch]: answered by a middleware"
             );
-            result
-          },
-          None => {
-            let msg = format!("[dispatch]: can not find the event handler. {:?}", request);
-            event!(tracing::Level::ERROR, "{}", msg);
-            Err(InternalError::HandleNotFound(msg).into())
-          },
+            return Ok(response);
+          }
+
+          #[cfg(any(test, feature = "mock"))]
+          if let Some(mock) = system.mocks.get(&request.event) {
+            event!(tracing::Level::TRACE, "[dispatch]: exec mocked event");
+            return Ok(mock.call(&request));
+          }
+
+          // The routed events fail over to their local stub when the endpoint can't be reached.
+          #[cfg(not(target_arch = "wasm32"))]
+          if let Some(endpoint) = system.routes.available(&request.event) {
+            // The local stub declares the capabilities and the feature flag of the event.
+            plugins.with(&request.event, |module| match module {
+              Some(module) => {
+                module.check_capabilities(&request)?;
+                module.check_feature_flag(&request, &system.feature_flags)
+              },
+              None => Ok(()),
+            })?;
+            match endpoint.forward(&request).await {
+              Ok(response) => return Ok(response),
+              Err(err) if !plugins.contains(&request.event) => return Err(err),
+              Err(_) => event!(
+                tracing::Level::TRACE,
+                "[dispatch]: fail over to the local handler"
+              ),
+            }
+          }
+
+          // The plugin is only borrowed while the handler future is built, the future owns
+          // what it needs to run.
+          let handled = plugins.with(&event, |module| match module {
+            Some(module) => {
+              tracing::Span::current().record("module", module.name.as_str());
+              event!(tracing::Level::TRACE, "[dispatch]: exec event");
+              module.check_version(&request)?;
+              module.check_capabilities(&request)?;
+              module.check_feature_flag(&request, &system.feature_flags)?;
+              module.check_payload(&request)?;
+              module.check_preconditions(&request, &system.app_states)?;
+              #[cfg(feature = "use_protobuf")]
+              module.convert_json_payload(&mut request)?;
+              if let Some(account) = &account {
+                account.check(&request.event)?;
+              }
+              #[cfg(feature = "testing")]
+              if let Some(coverage) = coverage {
+                coverage.handled(&request.event);
+              }
+              let started_at = Instant::now();
+              match module.call_fast(request) {
+                Ok(response) => {
+                  if let Some(account) = &account {
+                    account.record_sync_call(started_at.elapsed());
+                  }
+                  event!(tracing::Level::TRACE, "[dispatch]: exec fast event done");
+                  Ok(Handled::Done(response))
+                },
+                Err(request) => {
+                  let fut = module.call_service(request);
+                  Ok(Handled::Pending(match &account {
+                    Some(account) => Box::pin(Metered::new(fut, account.clone())),
+                    None => fut,
+                  }))
+                },
+              }
+            },
+            None => {
+              let msg = format!("[dispatch]: can not find the event handler. {:?}", request);
+              event!(tracing::Level::ERROR, "{}", msg);
+              Err(DispatchError::from(InternalError::HandleNotFound(msg)))
+            },
+          })?;
+          match handled {
+            Handled::Done(response) => Ok(response),
+            Handled::Pending(fut) => {
+              let result = match deadline {
+                Some(deadline) => {
+                  let remaining = deadline.saturating_duration_since(clock.now());
+                  timeout(clock.as_ref(), remaining, fut)
+                    .await
+                    .unwrap_or_else(|_| Err(deadline_exceeded(&event, false)))
+                },
+                None => fut.await,
+              };
+              event!(
+                tracing::Level::TRACE,
+                success = result.is_ok(),
+                "[dispatch]: exec event done"
+              );
+              result
+            },
+          }
+        };
+        // The requests sent and the tasks spawned by the handler are part of the request.
+        let handling = Budgeted::new(handling);
+        let handling = catch_handler_panic(event.as_str(), &id, system.recorder.clone(), handling);
+        let result: Result<AFPluginEventResponse, DispatchError> =
+          cancellation_scope(in_flight_guard.cancellation(), handling)
+            .await
+            .and_then(|result| result);
+
+        let mut response = result.unwrap_or_else(|e| e.into());
+        // The updates made through the transaction of the request only apply if the handler
+        // succeeded. The transactions spanning several requests are finished by
+        // [AFPluginDispatcher::dispatch_transaction].
+        if let Some(transaction) = extensions.remove::<Transaction>() {
+          if !transaction.is_coordinated() {
+            finish_transaction(&transaction, &mut response);
+          }
+        }
+        if let Some(notice) = deprecation {
+          response
+            .metadata
+            .insert(DEPRECATION_METADATA.to_owned(), notice);
+        }
+        if let Some(origin_request) = &origin_request {
+          run_response_middlewares(middlewares, origin_request, &mut response);
+          response = map_response(response_mappers, origin_request, response);
+        }
+        // The handler may have kept a clone of the extensions, e.g. in a spawned task.
+        extensions.clear();
+        drop(in_flight_guard);
+        let elapsed = clock.now().saturating_duration_since(started_at);
+        let label = event_label(plugins, &event);
+        record_response_metrics(metrics, label, &response, elapsed);
+        if elapsed >= system.config.slow_handler_threshold() {
+          report_slow_handler(metrics, label, elapsed, queue_wait);
+        }
+        system.recorder.record(
+          event.as_str(),
+          &id,
+          payload_size,
+          response.payload.as_ref().len(),
+          elapsed,
+          response.status_code == StatusCode::Ok,
+        );
+        event!(tracing::Level::TRACE, "Dispatch result: {:?}", response);
+        if let Some(callback) = callback {
+          callback(response.clone()).await;
         }
-      };
 
-      let response = result.unwrap_or_else(|e| e.into());
-      event!(tracing::Level::TRACE, "Dispatch result: {:?}", response);
-      if let Some(callback) = callback {
-        callback(response.clone()).await;
+        Ok(response)
       }
+      .instrument(span),
+    )
+  }
+}
 
-      Ok(response)
-    })
+/// Commits `transaction` if `response` is Ok and the transaction prepares, rolls it back
+/// otherwise. The response is replaced by the error of the failed prepare.
+fn finish_transaction(transaction: &Transaction, response: &mut AFPluginEventResponse) {
+  if response.status_code != StatusCode::Ok {
+    transaction.rollback();
+    return;
+  }
+  match transaction.prepare() {
+    Ok(_) => transaction.commit(),
+    Err(err) => {
+      transaction.rollback();
+      *response = err.into();
+    },
+  }
+}
+
+fn deadline_exceeded(event: &AFPluginEvent, queued: bool) -> DispatchError {
+  InternalError::DeadlineExceeded {
+    event: event.as_str().to_owned(),
+    queued,
+  }
+  .into()
+}
+
+/// The `event` label of the metrics of `event`, [UNREGISTERED_EVENT_LABEL] if no plugin handles it.
+fn event_label<'a>(plugins: &AFPluginRegistry, event: &'a AFPluginEvent) -> &'a str {
+  if plugins.contains(event) {
+    event.as_str()
+  } else {
+    UNREGISTERED_EVENT_LABEL
   }
 }
 
+fn record_response_metrics(
+  metrics: &MetricsRegistry,
+  event: &str,
+  response: &AFPluginEventResponse,
+  elapsed: Duration,
+) {
+  let labels = [("event", event)];
+  metrics.counter(DISPATCH_REQUESTS_TOTAL, &labels).inc();
+  if response.status_code == StatusCode::Err {
+    metrics.counter(DISPATCH_ERRORS_TOTAL, &labels).inc();
+  }
+  metrics
+    .histogram(DISPATCH_DURATION_SECONDS, &labels)
+    .observe(elapsed);
+}
+
+fn report_slow_handler(
+  metrics: &MetricsRegistry,
+  event: &str,
+  elapsed: Duration,
+  queue_wait: Duration,
+) {
+  tracing::warn!(
+    event = event,
+    elapsed_ms = elapsed.as_millis() as u64,
+    queue_wait_ms = queue_wait.as_millis() as u64,
+    "[dispatch]: slow handler, took {:?} after waiting {:?} in the queue",
+    elapsed,
+    queue_wait,
+  );
+  metrics
+    .counter(SLOW_HANDLER_TOTAL, &[("event", event)])
+    .inc();
+}
+
 #[allow(dead_code)]
 fn plugin_info(plugins: &[AFPlugin]) -> String {
   let mut info = format!("{} plugins loaded\n", plugins.len());
@@ -0,0 +1,156 @@
+//! The C ABI between the core and the plugins built as dynamic libraries. It only changes along
+//! with [PLUGIN_ABI_VERSION]: the fields are never reordered, the new ones are appended to the
+//! [PluginVTable] and a plugin built for another version is rejected by the loader.
+
+use std::ffi::c_char;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// The version of the ABI described in this module.
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// The symbol of the function returning the [PluginDescriptor] of a library:
+///
+/// ```ignore
+/// #[no_mangle]
+/// pub extern "C" fn appflowy_plugin_descriptor() -> *const PluginDescriptor
+/// ```
+///
+/// The descriptor, and everything it points to, must live as long as the library is loaded.
+pub const PLUGIN_DESCRIPTOR_SYMBOL: &[u8] = b"appflowy_plugin_descriptor\0";
+
+pub type PluginDescriptorFn = unsafe extern "C" fn() -> *const PluginDescriptor;
+
+/// The handler succeeded, the output holds its response.
+pub const PLUGIN_STATUS_OK: i32 = 0;
+/// The handler failed, the output holds its error.
+pub const PLUGIN_STATUS_ERR: i32 = 1;
+/// The plugin itself failed, e.g. its handler panicked. Any other status is a fault as well.
+pub const PLUGIN_STATUS_FAULT: i32 = 2;
+
+/// Describes a plugin and the events it handles.
+#[repr(C)]
+pub struct PluginDescriptor {
+  /// The [PLUGIN_ABI_VERSION] the plugin was built with.
+  pub abi_version: u32,
+  /// The name of the plugin, a nul-terminated UTF-8 string.
+  pub name: *const c_char,
+  /// The version of the plugin, a nul-terminated UTF-8 string, for the logs.
+  pub version: *const c_char,
+  /// The `num_events` events handled by the plugin, nul-terminated UTF-8 strings.
+  pub events: *const *const c_char,
+  pub num_events: usize,
+  pub vtable: *const PluginVTable,
+}
+
+/// The functions of a plugin. They may be called from several threads at once.
+#[repr(C)]
+pub struct PluginVTable {
+  /// Handles `event` with its payload and writes the response, or the error, to `out`. Returns
+  /// one of the `PLUGIN_STATUS_*`. The handlers must not unwind, see [handle_guarded].
+  pub handle: unsafe extern "C" fn(
+    event: *const u8,
+    event_len: usize,
+    payload: *const u8,
+    payload_len: usize,
+    out: *mut PluginBuffer,
+  ) -> i32,
+  /// Frees a buffer written by `handle`, with the allocator of the plugin.
+  pub free_buffer: unsafe extern "C" fn(buffer: PluginBuffer),
+}
+
+/// The bytes written by a plugin. They're owned by the plugin and given back to its
+/// `free_buffer` once read.
+#[repr(C)]
+pub struct PluginBuffer {
+  pub ptr: *mut u8,
+  pub len: usize,
+  pub capacity: usize,
+}
+
+impl PluginBuffer {
+  pub fn empty() -> Self {
+    Self {
+      ptr: std::ptr::null_mut(),
+      len: 0,
+      capacity: 0,
+    }
+  }
+
+  /// For the plugins: hands `data` over to the core.
+  pub fn from_vec(data: Vec<u8>) -> Self {
+    let mut data = std::mem::ManuallyDrop::new(data);
+    Self {
+      ptr: data.as_mut_ptr(),
+      len: data.len(),
+      capacity: data.capacity(),
+    }
+  }
+
+  /// # Safety
+  /// The buffer must have been written by [PluginBuffer::from_vec] of the same library.
+  pub unsafe fn into_vec(self) -> Vec<u8> {
+    if self.ptr.is_null() {
+      return vec![];
+    }
+    Vec::from_raw_parts(self.ptr, self.len, self.capacity)
+  }
+
+  pub(crate) fn as_slice(&self) -> &[u8] {
+    if self.ptr.is_null() {
+      return &[];
+    }
+    // Safety: the plugin wrote `len` bytes at `ptr`, kept until the buffer is freed.
+    unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+  }
+}
+
+/// For the plugins: the `free_buffer` of their [PluginVTable].
+///
+/// # Safety
+/// `buffer` must have been written by [PluginBuffer::from_vec] of the same library.
+pub unsafe extern "C" fn free_plugin_buffer(buffer: PluginBuffer) {
+  drop(buffer.into_vec());
+}
+
+/// For the plugins: implements the `handle` of their [PluginVTable] with `handler`, which gets
+/// the event and the payload and returns the response, or the error. A panic of the handler is
+/// reported as a fault instead of unwinding into the core.
+///
+/// # Safety
+/// The arguments must be the ones `handle` was called with.
+pub unsafe fn handle_guarded<F>(
+  event: *const u8,
+  event_len: usize,
+  payload: *const u8,
+  payload_len: usize,
+  out: *mut PluginBuffer,
+  handler: F,
+) -> i32
+where
+  F: FnOnce(&str, &[u8]) -> Result<Vec<u8>, Vec<u8>>,
+{
+  let event = match std::str::from_utf8(raw_slice(event, event_len)) {
+    Ok(event) => event,
+    Err(_) => return PLUGIN_STATUS_FAULT,
+  };
+  let payload = raw_slice(payload, payload_len);
+  match catch_unwind(AssertUnwindSafe(|| handler(event, payload))) {
+    Ok(Ok(data)) => {
+      *out = PluginBuffer::from_vec(data);
+      PLUGIN_STATUS_OK
+    },
+    Ok(Err(data)) => {
+      *out = PluginBuffer::from_vec(data);
+      PLUGIN_STATUS_ERR
+    },
+    Err(_) => PLUGIN_STATUS_FAULT,
+  }
+}
+
+unsafe fn raw_slice<'a>(ptr: *const u8, len: usize) -> &'a [u8] {
+  if ptr.is_null() || len == 0 {
+    &[]
+  } else {
+    std::slice::from_raw_parts(ptr, len)
+  }
+}
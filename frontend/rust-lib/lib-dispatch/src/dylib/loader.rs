@@ -0,0 +1,367 @@
+use std::collections::HashSet;
+use std::ffi::{c_char, CStr};
+use std::fmt::{Debug, Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use libloading::Library;
+
+use super::abi::*;
+use super::sandbox::{Sandbox, SandboxLimits};
+use crate::errors::{DispatchError, InternalError};
+use crate::module::AFPlugin;
+use crate::prelude::AFPluginDispatcher;
+use crate::proxy::{ForwardedRequest, RemoteRequest};
+use crate::response::{AFPluginEventResponse, ResponseBuilder, StatusCode};
+use crate::system::SYSTEM_EVENT_PREFIX;
+
+/// A plugin loaded from a dynamic library, see [DylibPluginLoader]. Its handlers run on the
+/// blocking threads, and once it faulted `max_faults` times it's disabled: its events are
+/// answered with an error instead of calling it again.
+pub struct DylibPlugin {
+  name: String,
+  version: String,
+  events: Vec<String>,
+  vtable: *const PluginVTable,
+  faults: AtomicU32,
+  max_faults: u32,
+  sandbox: Option<Sandbox>,
+  // Dropped last, the vtable points into the library.
+  _library: Option<Library>,
+}
+
+// Safety: the descriptor and the vtable are immutable and live as long as the library, and the
+// plugins must accept calls from several threads, see [PluginVTable].
+unsafe impl Send for DylibPlugin {}
+unsafe impl Sync for DylibPlugin {}
+
+impl DylibPlugin {
+  /// Reads the plugin described by `descriptor`, e.g. one linked into the app instead of loaded
+  /// from a library.
+  ///
+  /// # Safety
+  /// `descriptor` must follow the [PluginDescriptor] contract and outlive the plugin.
+  pub unsafe fn from_descriptor(
+    descriptor: *const PluginDescriptor,
+  ) -> Result<Self, DylibPluginError> {
+    let descriptor = descriptor
+      .as_ref()
+      .ok_or_else(|| invalid_descriptor("null descriptor"))?;
+    if descriptor.abi_version != PLUGIN_ABI_VERSION {
+      return Err(DylibPluginError::IncompatibleAbi {
+        version: descriptor.abi_version,
+      });
+    }
+    if descriptor.vtable.is_null() {
+      return Err(invalid_descriptor("null vtable"));
+    }
+    let name = read_str(descriptor.name, "name")?;
+    let version = read_str(descriptor.version, "version")?;
+    if descriptor.events.is_null() && descriptor.num_events > 0 {
+      return Err(invalid_descriptor("null events"));
+    }
+    let mut events = Vec::with_capacity(descriptor.num_events);
+    let mut seen = HashSet::new();
+    for i in 0..descriptor.num_events {
+      let event = read_str(*descriptor.events.add(i), "event")?;
+      if !seen.insert(event.clone()) {
+        return Err(invalid_descriptor(&format!("duplicate event {}", event)));
+      }
+      // The events of the dispatcher itself can't be taken over.
+      if event.starts_with(SYSTEM_EVENT_PREFIX) {
+        return Err(invalid_descriptor(&format!("reserved event {}", event)));
+      }
+      events.push(event);
+    }
+    Ok(Self {
+      name,
+      version,
+      events,
+      vtable: descriptor.vtable,
+      faults: AtomicU32::new(0),
+      max_faults: DEFAULT_MAX_FAULTS,
+      sandbox: None,
+      _library: None,
+    })
+  }
+
+  /// Loads the plugin of the library at `path`.
+  ///
+  /// # Safety
+  /// Loading a library runs its initialization code, it must be a plugin built for this ABI.
+  pub unsafe fn load<P: AsRef<Path>>(path: P) -> Result<Self, DylibPluginError> {
+    let path = path.as_ref();
+    let library = Library::new(path).map_err(|err| DylibPluginError::Load(err.to_string()))?;
+    let descriptor = {
+      let descriptor_fn = library
+        .get::<PluginDescriptorFn>(PLUGIN_DESCRIPTOR_SYMBOL)
+        .map_err(|err| DylibPluginError::Load(err.to_string()))?;
+      descriptor_fn()
+    };
+    let mut plugin = Self::from_descriptor(descriptor)?;
+    plugin._library = Some(library);
+    tracing::info!(
+      "[dylib]: loaded {} {} from {}",
+      plugin.name,
+      plugin.version,
+      path.display()
+    );
+    Ok(plugin)
+  }
+
+  pub fn max_faults(mut self, max_faults: u32) -> Self {
+    self.max_faults = max_faults.max(1);
+    self
+  }
+
+  /// Runs the handlers of the plugin on threads of their own, within `limits`, instead of the
+  /// blocking threads shared with the rest of the app.
+  pub fn sandbox(mut self, limits: SandboxLimits) -> Result<Self, DylibPluginError> {
+    let sandbox =
+      Sandbox::new(&self.name, limits).map_err(|err| DylibPluginError::Sandbox(err.to_string()))?;
+    self.sandbox = Some(sandbox);
+    Ok(self)
+  }
+
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  pub fn version(&self) -> &str {
+    &self.version
+  }
+
+  pub fn events(&self) -> &[String] {
+    &self.events
+  }
+
+  pub fn is_disabled(&self) -> bool {
+    self.faults.load(Ordering::SeqCst) >= self.max_faults
+  }
+
+  /// The plugin registering the events of the library.
+  pub fn into_plugin(self) -> AFPlugin {
+    let plugin = Arc::new(self);
+    plugin
+      .events
+      .iter()
+      .fold(AFPlugin::new().name(&plugin.name), |af_plugin, event| {
+        let plugin = plugin.clone();
+        af_plugin.event(event.as_str(), move |request: ForwardedRequest| {
+          handle(plugin.clone(), request)
+        })
+      })
+  }
+
+  /// Calls the handler of the library. Returns the reason of the fault if the plugin failed.
+  fn call(&self, event: &str, payload: &[u8]) -> Result<AFPluginEventResponse, String> {
+    // Safety: the vtable was checked when the plugin was read, and the library is still loaded.
+    let vtable = unsafe { &*self.vtable };
+    let mut out = PluginBuffer::empty();
+    let status = unsafe {
+      (vtable.handle)(
+        event.as_ptr(),
+        event.len(),
+        payload.as_ptr(),
+        payload.len(),
+        &mut out,
+      )
+    };
+    let data = out.as_slice().to_vec();
+    unsafe { (vtable.free_buffer)(out) };
+    match status {
+      PLUGIN_STATUS_OK => Ok(ResponseBuilder::new(StatusCode::Ok).data(data).build()),
+      PLUGIN_STATUS_ERR => Ok(ResponseBuilder::new(StatusCode::Err).data(data).build()),
+      status => Err(format!("{} returned the status {}", event, status)),
+    }
+  }
+
+  fn record_fault(&self, event: &str, reason: &str) -> DispatchError {
+    self.count_fault(reason);
+    plugin_fault(&self.name, &format!("{}: {}", event, reason))
+  }
+
+  fn count_fault(&self, reason: &str) {
+    let faults = self.faults.fetch_add(1, Ordering::SeqCst) + 1;
+    tracing::error!("[dylib]: {} faulted: {}", self.name, reason);
+    if faults == self.max_faults {
+      tracing::error!("[dylib]: {} is disabled after {} faults", self.name, faults);
+    }
+  }
+}
+
+const DEFAULT_MAX_FAULTS: u32 = 3;
+
+async fn handle(plugin: Arc<DylibPlugin>, request: ForwardedRequest) -> AFPluginEventResponse {
+  let RemoteRequest { event, payload, .. } = request.0;
+  if plugin.is_disabled() {
+    return plugin_fault(&plugin.name, "disabled after too many faults").into();
+  }
+  // A slow or stuck plugin only holds a blocking thread, or a worker of its sandbox, not the
+  // dispatcher.
+  let cloned_plugin = plugin.clone();
+  let cloned_event = event.clone();
+  let payload_len = payload.len();
+  let call = move || cloned_plugin.call(&cloned_event, &payload);
+  let result = match &plugin.sandbox {
+    Some(sandbox) => match sandbox.run(&event, payload_len, call).await {
+      Ok(result) => result,
+      Err(violation) => {
+        if violation.is_plugin_fault() {
+          plugin.count_fault(&violation.to_string());
+        } else {
+          tracing::warn!("[dylib]: {}", violation);
+        }
+        return DispatchError::from(violation).into();
+      },
+    },
+    None => tokio::task::spawn_blocking(call)
+      .await
+      .unwrap_or_else(|err| Err(err.to_string())),
+  };
+  match result {
+    Ok(response) => response,
+    Err(reason) => plugin.record_fault(&event, &reason).into(),
+  }
+}
+
+fn plugin_fault(plugin: &str, reason: &str) -> DispatchError {
+  InternalError::PluginFault {
+    plugin: plugin.to_owned(),
+    reason: reason.to_owned(),
+  }
+  .into()
+}
+
+unsafe fn read_str(ptr: *const c_char, field: &str) -> Result<String, DylibPluginError> {
+  if ptr.is_null() {
+    return Err(invalid_descriptor(&format!("null {}", field)));
+  }
+  CStr::from_ptr(ptr)
+    .to_str()
+    .map(|s| s.to_owned())
+    .map_err(|_| invalid_descriptor(&format!("{} is not UTF-8", field)))
+}
+
+fn invalid_descriptor(reason: &str) -> DylibPluginError {
+  DylibPluginError::InvalidDescriptor(reason.to_owned())
+}
+
+/// Discovers the plugins in a directory: the libraries with the extension of the platform, like
+/// `.so`, `.dylib` or `.dll`.
+pub struct DylibPluginLoader {
+  dir: PathBuf,
+  max_faults: u32,
+  sandbox: Option<SandboxLimits>,
+}
+
+impl DylibPluginLoader {
+  pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+    Self {
+      dir: dir.into(),
+      max_faults: DEFAULT_MAX_FAULTS,
+      sandbox: None,
+    }
+  }
+
+  /// The faults after which the loaded plugins are disabled.
+  pub fn max_faults(mut self, max_faults: u32) -> Self {
+    self.max_faults = max_faults;
+    self
+  }
+
+  /// Sandboxes the loaded plugins within `limits`, see [DylibPlugin::sandbox].
+  pub fn sandbox(mut self, limits: SandboxLimits) -> Self {
+    self.sandbox = Some(limits);
+    self
+  }
+
+  /// The libraries in the directory, sorted. Nothing is found if the directory doesn't exist.
+  pub fn discover(&self) -> std::io::Result<Vec<PathBuf>> {
+    let entries = match std::fs::read_dir(&self.dir) {
+      Ok(entries) => entries,
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+      Err(err) => return Err(err),
+    };
+    let mut paths = vec![];
+    for entry in entries {
+      let path = entry?.path();
+      if path.is_file()
+        && path.extension().and_then(|ext| ext.to_str()) == Some(std::env::consts::DLL_EXTENSION)
+      {
+        paths.push(path);
+      }
+    }
+    paths.sort();
+    Ok(paths)
+  }
+
+  /// Loads the discovered plugins and registers them in `dispatcher`. A library that can't be
+  /// loaded, or whose events are already handled, is skipped: the failures are returned along
+  /// with the path of the library.
+  ///
+  /// # Safety
+  /// See [DylibPlugin::load], the directory must only hold trusted plugins.
+  pub unsafe fn register_all(
+    &self,
+    dispatcher: &AFPluginDispatcher,
+  ) -> std::io::Result<Vec<(PathBuf, DylibPluginError)>> {
+    let mut failures = vec![];
+    for path in self.discover()? {
+      let result = DylibPlugin::load(&path)
+        .and_then(|plugin| match self.sandbox {
+          Some(limits) => plugin.sandbox(limits),
+          None => Ok(plugin),
+        })
+        .and_then(|plugin| {
+          dispatcher
+            .register_plugin(plugin.max_faults(self.max_faults).into_plugin())
+            .map_err(|err| DylibPluginError::Register(err.to_string()))
+        });
+      if let Err(err) = result {
+        tracing::error!("[dylib]: skip {}: {}", path.display(), err);
+        failures.push((path, err));
+      }
+    }
+    Ok(failures)
+  }
+}
+
+pub enum DylibPluginError {
+  /// The library, or its descriptor symbol, couldn't be loaded.
+  Load(String),
+  /// The plugin was built for another [PLUGIN_ABI_VERSION].
+  IncompatibleAbi {
+    version: u32,
+  },
+  InvalidDescriptor(String),
+  /// The dispatcher refused the plugin, e.g. one of its events is already handled.
+  Register(String),
+  /// The threads of the sandbox couldn't be started.
+  Sandbox(String),
+}
+
+impl Display for DylibPluginError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      DylibPluginError::Load(reason) => write!(f, "Load failed: {}", reason),
+      DylibPluginError::IncompatibleAbi { version } => write!(
+        f,
+        "Incompatible ABI version {}, expected {}",
+        version, PLUGIN_ABI_VERSION
+      ),
+      DylibPluginError::InvalidDescriptor(reason) => write!(f, "Invalid descriptor: {}", reason),
+      DylibPluginError::Register(reason) => write!(f, "Register failed: {}", reason),
+      DylibPluginError::Sandbox(reason) => write!(f, "Sandbox failed: {}", reason),
+    }
+  }
+}
+
+impl Debug for DylibPluginError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+impl std::error::Error for DylibPluginError {}
@@ -0,0 +1,16 @@
+//! The plugins added by third parties as dynamic libraries, without forking the app. A library
+//! exports a [PluginDescriptor] listing its events, and the [DylibPluginLoader] registers them in
+//! the dispatcher. The faults of a plugin are reported as errors of its events and never reach
+//! the core.
+//!
+//! A plugin can also be sandboxed, see [DylibPlugin::sandbox]: its handlers run on threads of
+//! their own, within the [SandboxLimits], and the requests exceeding them fail with a
+//! [SandboxViolation].
+
+pub use abi::*;
+pub use loader::*;
+pub use sandbox::*;
+
+mod abi;
+mod loader;
+mod sandbox;
@@ -0,0 +1,247 @@
+use std::fmt::{Display, Formatter};
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+use futures_channel::oneshot;
+use serde::{Deserialize, Serialize};
+
+use crate::encoding::ContentType;
+use crate::errors::Error;
+use crate::response::{AFPluginEventResponse, ErrorOrigin, ResponseBuilder};
+
+/// The number of threads of a sandbox, see [SandboxLimits::workers].
+pub const DEFAULT_SANDBOX_WORKERS: usize = 2;
+
+/// The resources a sandboxed plugin may use, see [DylibPlugin::sandbox]. No limit but the
+/// number of workers by default.
+///
+/// [DylibPlugin::sandbox]: crate::dylib::DylibPlugin::sandbox
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SandboxLimits {
+  pub workers: usize,
+  pub cpu_time: Option<Duration>,
+  pub max_payload_bytes: Option<usize>,
+  pub max_response_bytes: Option<usize>,
+}
+
+impl Default for SandboxLimits {
+  fn default() -> Self {
+    Self {
+      workers: DEFAULT_SANDBOX_WORKERS,
+      cpu_time: None,
+      max_payload_bytes: None,
+      max_response_bytes: None,
+    }
+  }
+}
+
+impl SandboxLimits {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// The threads the handlers of the plugin run on, shared with no other plugin. A request
+  /// arriving while they are all busy is rejected instead of queued.
+  pub fn workers(mut self, workers: usize) -> Self {
+    self.workers = workers.max(1);
+    self
+  }
+
+  /// The time a handler may run. The request fails once it's spent and the plugin is counted as
+  /// faulted, while its worker stays busy until the handler returns.
+  pub fn cpu_time(mut self, cpu_time: Duration) -> Self {
+    self.cpu_time = Some(cpu_time);
+    self
+  }
+
+  /// The larger payloads are rejected before the plugin is called.
+  pub fn max_payload_bytes(mut self, max_payload_bytes: usize) -> Self {
+    self.max_payload_bytes = Some(max_payload_bytes);
+    self
+  }
+
+  /// The larger responses are dropped and the plugin is counted as faulted.
+  pub fn max_response_bytes(mut self, max_response_bytes: usize) -> Self {
+    self.max_response_bytes = Some(max_response_bytes);
+    self
+  }
+}
+
+/// The limits of a sandbox that can be exceeded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SandboxResource {
+  Workers,
+  CpuTime,
+  Payload,
+  Response,
+}
+
+/// The error of the requests a sandboxed plugin was stopped from handling. The response carries
+/// it as JSON, read it back with [SandboxViolation::from_response].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SandboxViolation {
+  pub plugin: String,
+  pub event: String,
+  pub resource: SandboxResource,
+  /// The limit that was exceeded: a number of workers, of milliseconds or of bytes.
+  pub limit: u64,
+}
+
+impl SandboxViolation {
+  pub fn from_response(response: &AFPluginEventResponse) -> Option<Self> {
+    if response.error_origin != Some(ErrorOrigin::Dispatcher)
+      || response.content_type != Some(ContentType::Json)
+    {
+      return None;
+    }
+    serde_json::from_slice(response.payload.as_ref()).ok()
+  }
+
+  /// Whether the plugin is to blame, rather than the caller or the load.
+  pub fn is_plugin_fault(&self) -> bool {
+    matches!(
+      self.resource,
+      SandboxResource::CpuTime | SandboxResource::Response
+    )
+  }
+}
+
+impl Display for SandboxViolation {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "SandboxViolation: {} of {} exceeded its {:?} limit of {}",
+      self.event, self.plugin, self.resource, self.limit
+    )
+  }
+}
+
+impl Error for SandboxViolation {
+  fn as_response(&self) -> AFPluginEventResponse {
+    let data = serde_json::to_vec(self).unwrap_or_else(|_| self.to_string().into_bytes());
+    ResponseBuilder::Err()
+      .data(data)
+      .content_type(ContentType::Json)
+      .error_origin(ErrorOrigin::Dispatcher)
+      .build()
+  }
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Frees its worker once the job ran, or was dropped without running.
+struct BusyGuard(Arc<AtomicUsize>);
+
+impl Drop for BusyGuard {
+  fn drop(&mut self) {
+    self.0.fetch_sub(1, Ordering::SeqCst);
+  }
+}
+
+/// The worker threads of a sandboxed plugin. They exit once the plugin is dropped.
+pub(crate) struct Sandbox {
+  plugin: String,
+  limits: SandboxLimits,
+  jobs: mpsc::Sender<Job>,
+  busy: Arc<AtomicUsize>,
+}
+
+impl Sandbox {
+  pub(crate) fn new(plugin: &str, limits: SandboxLimits) -> std::io::Result<Self> {
+    let (tx, rx) = mpsc::channel::<Job>();
+    let rx = Arc::new(Mutex::new(rx));
+    for i in 0..limits.workers {
+      let rx = rx.clone();
+      std::thread::Builder::new()
+        .name(format!("{}-sandbox-{}", plugin, i))
+        .spawn(move || loop {
+          let job = match rx.lock().unwrap().recv() {
+            Ok(job) => job,
+            Err(_) => break,
+          };
+          // A panic drops the sender of the result, which reports it, and keeps the worker.
+          let _ = std::panic::catch_unwind(AssertUnwindSafe(job));
+        })?;
+    }
+    Ok(Self {
+      plugin: plugin.to_owned(),
+      limits,
+      jobs: tx,
+      busy: Arc::new(AtomicUsize::new(0)),
+    })
+  }
+
+  /// Runs `call`, the handler of `event`, on a worker within the limits. Returns the reason of
+  /// the fault if the plugin failed, like the call itself.
+  pub(crate) async fn run<F>(
+    &self,
+    event: &str,
+    payload_len: usize,
+    call: F,
+  ) -> Result<Result<AFPluginEventResponse, String>, SandboxViolation>
+  where
+    F: FnOnce() -> Result<AFPluginEventResponse, String> + Send + 'static,
+  {
+    let violation = |resource: SandboxResource, limit: u64| SandboxViolation {
+      plugin: self.plugin.clone(),
+      event: event.to_owned(),
+      resource,
+      limit,
+    };
+    if let Some(max_payload_bytes) = self.limits.max_payload_bytes {
+      if payload_len > max_payload_bytes {
+        return Err(violation(
+          SandboxResource::Payload,
+          max_payload_bytes as u64,
+        ));
+      }
+    }
+    if self.busy.fetch_add(1, Ordering::SeqCst) >= self.limits.workers {
+      self.busy.fetch_sub(1, Ordering::SeqCst);
+      return Err(violation(
+        SandboxResource::Workers,
+        self.limits.workers as u64,
+      ));
+    }
+    let guard = BusyGuard(self.busy.clone());
+    let (tx, rx) = oneshot::channel();
+    let job: Job = Box::new(move || {
+      let result = call();
+      // The worker is free before the caller gets the response and sends the next request.
+      drop(guard);
+      let _ = tx.send(result);
+    });
+    if self.jobs.send(job).is_err() {
+      return Ok(Err("the sandbox is closed".to_owned()));
+    }
+    let result = match self.limits.cpu_time {
+      Some(cpu_time) => match tokio::time::timeout(cpu_time, rx).await {
+        Ok(result) => result,
+        Err(_) => {
+          return Err(violation(
+            SandboxResource::CpuTime,
+            cpu_time.as_millis() as u64,
+          ))
+        },
+      },
+      None => rx.await,
+    };
+    let response = match result {
+      Ok(Ok(response)) => response,
+      Ok(Err(reason)) => return Ok(Err(reason)),
+      Err(_) => return Ok(Err(format!("{} panicked", event))),
+    };
+    if let Some(max_response_bytes) = self.limits.max_response_bytes {
+      if response.payload.as_ref().len() > max_response_bytes {
+        return Err(violation(
+          SandboxResource::Response,
+          max_response_bytes as u64,
+        ));
+      }
+    }
+    Ok(Ok(response))
+  }
+}
@@ -0,0 +1,231 @@
+use std::ops;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::{
+  encoding::ContentType,
+  errors::{DispatchError, InternalError},
+  request::{unexpected_none_payload, AFPluginEventRequest, FromAFPluginRequest, Payload},
+  response::{AFPluginEventResponse, AFPluginResponder, ResponseBuilder},
+  util::ready::{ready, Ready},
+};
+
+/// A serde based payload encoding. The types deriving `ToBytes` and `FromBytes` are encoded
+/// with one of them, see [encode_bytes](crate::prelude::encode_bytes).
+pub trait SerdeCodec {
+  const CONTENT_TYPE: ContentType;
+
+  fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, String>;
+
+  fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, String>;
+}
+
+fn decode_payload<C, T>(req: &AFPluginEventRequest, payload: &Payload) -> Result<T, DispatchError>
+where
+  C: SerdeCodec,
+  T: DeserializeOwned,
+{
+  if let Some(content_type) = req.content_type {
+    if content_type != C::CONTENT_TYPE {
+      let msg = format!(
+        "{:?} expects a {} payload, got {}",
+        req.event,
+        C::CONTENT_TYPE,
+        content_type
+      );
+      return Err(InternalError::DeserializeFromBytes(msg).into());
+    }
+  }
+
+  match payload {
+    Payload::None => Err(unexpected_none_payload(req)),
+    Payload::Bytes(bytes) => C::decode(bytes).map_err(|e| {
+      let msg = format!(
+        "Parse {} payload to {} failed: {}",
+        C::CONTENT_TYPE,
+        std::any::type_name::<T>(),
+        e
+      );
+      InternalError::DeserializeFromBytes(msg).into()
+    }),
+  }
+}
+
+fn encode_response<C, T>(value: &T) -> AFPluginEventResponse
+where
+  C: SerdeCodec,
+  T: Serialize,
+{
+  match C::encode(value) {
+    Ok(bytes) => ResponseBuilder::Ok()
+      .data(bytes)
+      .content_type(C::CONTENT_TYPE)
+      .build(),
+    Err(e) => {
+      let msg = format!(
+        "Serialize {} to {} failed: {}",
+        std::any::type_name::<T>(),
+        C::CONTENT_TYPE,
+        e
+      );
+      let err: DispatchError = InternalError::Other(msg).into();
+      err.into()
+    },
+  }
+}
+
+macro_rules! serde_payload {
+  ($(#[$meta:meta])* $name:ident, $codec:ident) => {
+    $(#[$meta])*
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct $name<T>(pub T);
+
+    impl<T> $name<T> {
+      pub fn into_inner(self) -> T {
+        self.0
+      }
+    }
+
+    impl<T> ops::Deref for $name<T> {
+      type Target = T;
+
+      fn deref(&self) -> &T {
+        &self.0
+      }
+    }
+
+    impl<T> ops::DerefMut for $name<T> {
+      fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+      }
+    }
+
+    impl<T> $name<T>
+    where
+      T: Serialize,
+    {
+      /// Encodes the value, e.g. to build the payload of a request.
+      pub fn to_vec(&self) -> Result<Vec<u8>, DispatchError> {
+        $codec::encode(&self.0).map_err(|e| InternalError::Other(e).into())
+      }
+    }
+
+    impl<T> FromAFPluginRequest for $name<T>
+    where
+      T: DeserializeOwned + 'static,
+    {
+      type Error = DispatchError;
+      type Future = Ready<Result<Self, DispatchError>>;
+
+      #[inline]
+      fn from_request(req: &AFPluginEventRequest, payload: &mut Payload) -> Self::Future {
+        ready(decode_payload::<$codec, T>(req, payload).map($name))
+      }
+    }
+
+    impl<T> AFPluginResponder for $name<T>
+    where
+      T: Serialize,
+    {
+      fn respond_to(self, _request: &AFPluginEventRequest) -> AFPluginEventResponse {
+        encode_response::<$codec, T>(&self.0)
+      }
+    }
+  };
+}
+
+pub struct JsonCodec;
+
+impl SerdeCodec for JsonCodec {
+  const CONTENT_TYPE: ContentType = ContentType::Json;
+
+  fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, String> {
+    serde_json::from_slice(bytes).map_err(|e| e.to_string())
+  }
+
+  fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+    serde_json::to_vec(value).map_err(|e| e.to_string())
+  }
+}
+
+#[cfg(feature = "use_msgpack")]
+pub struct MessagePackCodec;
+
+#[cfg(feature = "use_msgpack")]
+impl SerdeCodec for MessagePackCodec {
+  const CONTENT_TYPE: ContentType = ContentType::MessagePack;
+
+  fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, String> {
+    rmp_serde::from_slice(bytes).map_err(|e| e.to_string())
+  }
+
+  fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+    // Structs are encoded as maps so the payload can be decoded without the field order.
+    rmp_serde::to_vec_named(value).map_err(|e| e.to_string())
+  }
+}
+
+serde_payload!(
+  /// A JSON payload.
+  Json,
+  JsonCodec
+);
+
+#[cfg(feature = "use_msgpack")]
+serde_payload!(
+  /// A MessagePack payload. It's much more compact than JSON for numeric data, like the cells
+  /// of a grid.
+  MsgPack,
+  MessagePackCodec
+);
+
+#[cfg(feature = "use_cbor")]
+pub struct CborCodec;
+
+#[cfg(feature = "use_cbor")]
+impl SerdeCodec for CborCodec {
+  const CONTENT_TYPE: ContentType = ContentType::Cbor;
+
+  fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, String> {
+    ciborium::from_reader(bytes).map_err(|e| e.to_string())
+  }
+
+  fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![];
+    ciborium::into_writer(value, &mut bytes).map_err(|e| e.to_string())?;
+    Ok(bytes)
+  }
+}
+
+#[cfg(feature = "use_cbor")]
+serde_payload!(
+  /// A CBOR payload.
+  Cbor,
+  CborCodec
+);
+
+#[cfg(feature = "use_bincode")]
+pub struct BincodeCodec;
+
+#[cfg(feature = "use_bincode")]
+impl SerdeCodec for BincodeCodec {
+  const CONTENT_TYPE: ContentType = ContentType::Bincode;
+
+  fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, String> {
+    bincode::deserialize(bytes).map_err(|e| e.to_string())
+  }
+
+  fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+    bincode::serialize(value).map_err(|e| e.to_string())
+  }
+}
+
+#[cfg(feature = "use_bincode")]
+serde_payload!(
+  /// A bincode payload. It's the cheapest to encode and decode, but it's not self-describing:
+  /// both sides must use the same Rust type, so it's meant for the modules of the core and the
+  /// helper processes, not for the clients.
+  Bincode,
+  BincodeCodec
+);
@@ -0,0 +1,79 @@
+pub use codec::*;
+#[cfg(feature = "use_protobuf")]
+pub(crate) use proto_json::ProtoJson;
+#[cfg(feature = "protobuf_lite")]
+pub use proto_lite::*;
+
+mod codec;
+#[cfg(feature = "use_protobuf")]
+mod proto_json;
+#[cfg(feature = "protobuf_lite")]
+mod proto_lite;
+
+use std::fmt::{Display, Formatter};
+
+/// The encoding of a payload. Requests and responses can be tagged with it so the other side
+/// knows how to decode the payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContentType {
+  Protobuf,
+  Json,
+  #[cfg(feature = "use_msgpack")]
+  MessagePack,
+  #[cfg(feature = "use_cbor")]
+  Cbor,
+  /// For the traffic between Rust modules or processes, which share the types of the payloads.
+  #[cfg(feature = "use_bincode")]
+  Bincode,
+}
+
+impl ContentType {
+  /// The content types this build can encode and decode.
+  pub fn supported() -> Vec<ContentType> {
+    vec![
+      ContentType::Protobuf,
+      ContentType::Json,
+      #[cfg(feature = "use_msgpack")]
+      ContentType::MessagePack,
+      #[cfg(feature = "use_cbor")]
+      ContentType::Cbor,
+      #[cfg(feature = "use_bincode")]
+      ContentType::Bincode,
+    ]
+  }
+
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      ContentType::Protobuf => "application/x-protobuf",
+      ContentType::Json => "application/json",
+      #[cfg(feature = "use_msgpack")]
+      ContentType::MessagePack => "application/msgpack",
+      #[cfg(feature = "use_cbor")]
+      ContentType::Cbor => "application/cbor",
+      #[cfg(feature = "use_bincode")]
+      ContentType::Bincode => "application/x-bincode",
+    }
+  }
+
+  /// Parses a mime type, ignoring its parameters. Returns `None` for the unknown types.
+  pub fn from_mime(mime: &str) -> Option<Self> {
+    let essence = mime.split(';').next().unwrap_or_default().trim();
+    match essence.to_ascii_lowercase().as_str() {
+      "application/x-protobuf" | "application/protobuf" => Some(ContentType::Protobuf),
+      "application/json" => Some(ContentType::Json),
+      #[cfg(feature = "use_msgpack")]
+      "application/msgpack" | "application/x-msgpack" => Some(ContentType::MessagePack),
+      #[cfg(feature = "use_cbor")]
+      "application/cbor" => Some(ContentType::Cbor),
+      #[cfg(feature = "use_bincode")]
+      "application/x-bincode" => Some(ContentType::Bincode),
+      _ => None,
+    }
+  }
+}
+
+impl Display for ContentType {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.write_str(self.as_str())
+  }
+}
@@ -0,0 +1,259 @@
+//! Encodes the JSON payloads as the protobuf messages the handlers decode, with the descriptors
+//! the plugins register with [AFPlugin::proto_descriptors](crate::prelude::AFPlugin). The JSON
+//! follows the proto3 mapping:
+//!
+//! - the fields are named as in the `.proto` file or in lowerCamelCase
+//! - the 64 bits integers can be quoted, the enums are named or numbered
+//! - the bytes are in base64 and the maps are objects
+//! - the `null` fields are left out, the unknown ones are rejected
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use protobuf::descriptor::{
+  DescriptorProto, EnumDescriptorProto, FieldDescriptorProto, FieldDescriptorProto_Label as Label,
+  FieldDescriptorProto_Type as Type, FileDescriptorProto,
+};
+use protobuf::CodedOutputStream;
+use serde_json::Value;
+
+#[derive(Clone, Default)]
+pub(crate) struct ProtoJson {
+  /// By full name, like `.folder.ViewPB.Inner`.
+  messages: HashMap<String, &'static DescriptorProto>,
+  /// The full names of the top level messages, by name.
+  names: HashMap<String, String>,
+  enums: HashMap<String, &'static EnumDescriptorProto>,
+}
+
+impl ProtoJson {
+  pub(crate) fn add_file(&mut self, file: &'static FileDescriptorProto) {
+    let scope = match file.get_package() {
+      "" => String::new(),
+      package => format!(".{}", package),
+    };
+    for message in file.get_message_type() {
+      self.names.insert(
+        message.get_name().to_owned(),
+        format!("{}.{}", scope, message.get_name()),
+      );
+      self.add_message(&scope, message);
+    }
+    for enum_type in file.get_enum_type() {
+      self.add_enum(&scope, enum_type);
+    }
+  }
+
+  fn add_message(&mut self, scope: &str, message: &'static DescriptorProto) {
+    let full_name = format!("{}.{}", scope, message.get_name());
+    for nested in message.get_nested_type() {
+      self.add_message(&full_name, nested);
+    }
+    for enum_type in message.get_enum_type() {
+      self.add_enum(&full_name, enum_type);
+    }
+    self.messages.insert(full_name, message);
+  }
+
+  fn add_enum(&mut self, scope: &str, enum_type: &'static EnumDescriptorProto) {
+    self
+      .enums
+      .insert(format!("{}.{}", scope, enum_type.get_name()), enum_type);
+  }
+
+  /// Whether the top level message `name` is known, named without its package.
+  pub(crate) fn contains(&self, name: &str) -> bool {
+    self.names.contains_key(name)
+  }
+
+  /// Encodes `json` as the top level message `name`.
+  pub(crate) fn encode(&self, name: &str, json: &[u8]) -> Result<Vec<u8>, String> {
+    let full_name = self
+      .names
+      .get(name)
+      .ok_or_else(|| format!("unknown message {}", name))?;
+    let value = serde_json::from_slice::<Value>(json).map_err(|e| e.to_string())?;
+    self.encode_message(full_name, &value)
+  }
+
+  fn encode_message(&self, full_name: &str, value: &Value) -> Result<Vec<u8>, String> {
+    let message = self
+      .messages
+      .get(full_name)
+      .ok_or_else(|| format!("unknown message {}", full_name))?;
+    let object = value
+      .as_object()
+      .ok_or_else(|| format!("expected a {} object, got {}", message.get_name(), value))?;
+    let mut bytes = vec![];
+    {
+      let mut os = CodedOutputStream::vec(&mut bytes);
+      for (key, value) in object {
+        let field = message
+          .get_field()
+          .iter()
+          .find(|field| field.get_name() == key || field.get_json_name() == key)
+          .ok_or_else(|| format!("unknown field {}.{}", message.get_name(), key))?;
+        if value.is_null() {
+          continue;
+        }
+        self
+          .encode_field(field, value, &mut os)
+          .map_err(|e| format!("{}.{}: {}", message.get_name(), key, e))?;
+      }
+      os.flush().map_err(|e| e.to_string())?;
+    }
+    Ok(bytes)
+  }
+
+  fn encode_field(
+    &self,
+    field: &FieldDescriptorProto,
+    value: &Value,
+    os: &mut CodedOutputStream,
+  ) -> Result<(), String> {
+    if field.get_label() != Label::LABEL_REPEATED {
+      return self.encode_value(field, value, os);
+    }
+    if let Some(entry) = self.map_entry(field) {
+      let object = value
+        .as_object()
+        .ok_or_else(|| format!("expected an object, got {}", value))?;
+      let entry_field = |number: i32| {
+        entry
+          .get_field()
+          .iter()
+          .find(|field| field.get_number() == number)
+          .ok_or_else(|| format!("invalid map entry {}", entry.get_name()))
+      };
+      let (key_field, value_field) = (entry_field(1)?, entry_field(2)?);
+      for (key, value) in object {
+        let mut bytes = vec![];
+        {
+          let mut entry_os = CodedOutputStream::vec(&mut bytes);
+          self.encode_value(key_field, &Value::String(key.clone()), &mut entry_os)?;
+          self.encode_value(value_field, value, &mut entry_os)?;
+          entry_os.flush().map_err(|e| e.to_string())?;
+        }
+        os.write_bytes(field.get_number() as u32, &bytes)
+          .map_err(|e| e.to_string())?;
+      }
+      return Ok(());
+    }
+    let values = value
+      .as_array()
+      .ok_or_else(|| format!("expected an array, got {}", value))?;
+    for value in values {
+      self.encode_value(field, value, os)?;
+    }
+    Ok(())
+  }
+
+  fn map_entry(&self, field: &FieldDescriptorProto) -> Option<&'static DescriptorProto> {
+    if field.get_field_type() != Type::TYPE_MESSAGE {
+      return None;
+    }
+    self
+      .messages
+      .get(field.get_type_name())
+      .copied()
+      .filter(|message| message.get_options().get_map_entry())
+  }
+
+  fn encode_value(
+    &self,
+    field: &FieldDescriptorProto,
+    value: &Value,
+    os: &mut CodedOutputStream,
+  ) -> Result<(), String> {
+    let number = field.get_number() as u32;
+    let written = match field.get_field_type() {
+      Type::TYPE_DOUBLE => os.write_double(number, float(value)?),
+      Type::TYPE_FLOAT => os.write_float(number, float(value)? as f32),
+      Type::TYPE_INT64 => os.write_int64(number, integer(value)?),
+      Type::TYPE_UINT64 => os.write_uint64(number, integer(value)?),
+      Type::TYPE_INT32 => os.write_int32(number, integer(value)?),
+      Type::TYPE_FIXED64 => os.write_fixed64(number, integer(value)?),
+      Type::TYPE_FIXED32 => os.write_fixed32(number, integer(value)?),
+      Type::TYPE_BOOL => os.write_bool(number, boolean(value)?),
+      Type::TYPE_STRING => os.write_string(number, string(value)?),
+      Type::TYPE_BYTES => {
+        let bytes = STANDARD
+          .decode(string(value)?)
+          .map_err(|e| format!("invalid base64: {}", e))?;
+        os.write_bytes(number, &bytes)
+      },
+      Type::TYPE_UINT32 => os.write_uint32(number, integer(value)?),
+      Type::TYPE_ENUM => os.write_enum(number, self.enum_number(field, value)?),
+      Type::TYPE_SFIXED32 => os.write_sfixed32(number, integer(value)?),
+      Type::TYPE_SFIXED64 => os.write_sfixed64(number, integer(value)?),
+      Type::TYPE_SINT32 => os.write_sint32(number, integer(value)?),
+      Type::TYPE_SINT64 => os.write_sint64(number, integer(value)?),
+      Type::TYPE_MESSAGE => {
+        let bytes = self.encode_message(field.get_type_name(), value)?;
+        os.write_bytes(number, &bytes)
+      },
+      Type::TYPE_GROUP => return Err("the groups are not supported".to_owned()),
+    };
+    written.map_err(|e| e.to_string())
+  }
+
+  fn enum_number(&self, field: &FieldDescriptorProto, value: &Value) -> Result<i32, String> {
+    let name = match value {
+      Value::String(name) => name,
+      _ => return integer(value),
+    };
+    self
+      .enums
+      .get(field.get_type_name())
+      .and_then(|enum_type| {
+        enum_type
+          .get_value()
+          .iter()
+          .find(|variant| variant.get_name() == name)
+      })
+      .map(|variant| variant.get_number())
+      .ok_or_else(|| format!("unknown {} variant {}", field.get_type_name(), name))
+  }
+}
+
+/// An integer, quoted or not.
+fn integer<T: TryFrom<i128>>(value: &Value) -> Result<T, String> {
+  let integer = match value {
+    Value::Number(number) => number
+      .as_i64()
+      .map(i128::from)
+      .or_else(|| number.as_u64().map(i128::from)),
+    Value::String(text) => text.parse::<i128>().ok(),
+    _ => None,
+  };
+  integer
+    .and_then(|integer| T::try_from(integer).ok())
+    .ok_or_else(|| format!("expected an integer in range, got {}", value))
+}
+
+/// A number, or a quoted one like `"NaN"` or `"Infinity"`.
+fn float(value: &Value) -> Result<f64, String> {
+  let float = match value {
+    Value::Number(number) => number.as_f64(),
+    Value::String(text) => text.parse::<f64>().ok(),
+    _ => None,
+  };
+  float.ok_or_else(|| format!("expected a number, got {}", value))
+}
+
+/// A boolean, quoted when it's the key of a map.
+fn boolean(value: &Value) -> Result<bool, String> {
+  match value {
+    Value::Bool(value) => Ok(*value),
+    Value::String(text) if text == "true" => Ok(true),
+    Value::String(text) if text == "false" => Ok(false),
+    _ => Err(format!("expected a boolean, got {}", value)),
+  }
+}
+
+fn string(value: &Value) -> Result<&str, String> {
+  value
+    .as_str()
+    .ok_or_else(|| format!("expected a string, got {}", value))
+}
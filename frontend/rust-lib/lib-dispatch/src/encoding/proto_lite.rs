@@ -0,0 +1,465 @@
+//! A minimal proto3 encoder and decoder, for the mobile builds that can't afford the size of the
+//! protobuf runtime. It speaks the same wire format, so a [ProtoLite] payload is read by the
+//! protobuf runtime of the other side and the other way around, but the messages are written by
+//! hand instead of generated:
+//!
+//! ```ignore
+//! #[derive(Default)]
+//! pub struct ViewIdPB {
+//!   pub value: String,
+//! }
+//!
+//! impl LiteMessage for ViewIdPB {
+//!   fn write_to(&self, writer: &mut LiteWriter) {
+//!     writer.write_string(1, &self.value);
+//!   }
+//!
+//!   fn merge_field(&mut self, field: u32, reader: &mut LiteReader) -> Result<(), LiteError> {
+//!     match field {
+//!       1 => self.value = reader.read_string()?,
+//!       _ => reader.skip()?,
+//!     }
+//!     Ok(())
+//!   }
+//! }
+//! ```
+//!
+//! Like proto3, the scalar fields with their default value are not written.
+use std::convert::TryInto;
+use std::fmt::{Display, Formatter};
+use std::ops;
+
+use crate::encoding::ContentType;
+use crate::errors::{DispatchError, InternalError};
+use crate::request::{unexpected_none_payload, AFPluginEventRequest, FromAFPluginRequest, Payload};
+use crate::response::{AFPluginEventResponse, AFPluginResponder, ResponseBuilder};
+use crate::util::ready::{ready, Ready};
+
+/// The nesting depth after which a payload is rejected, like the protobuf runtime does.
+const MAX_DEPTH: u32 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireType {
+  Varint = 0,
+  Fixed64 = 1,
+  LengthDelimited = 2,
+  Fixed32 = 5,
+}
+
+impl WireType {
+  fn from_tag(tag: u64) -> Result<Self, LiteError> {
+    match tag & 0x7 {
+      0 => Ok(WireType::Varint),
+      1 => Ok(WireType::Fixed64),
+      2 => Ok(WireType::LengthDelimited),
+      5 => Ok(WireType::Fixed32),
+      wire_type => Err(LiteError(format!("unsupported wire type {}", wire_type))),
+    }
+  }
+}
+
+/// The payload is not a valid encoding of the message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiteError(pub String);
+
+impl Display for LiteError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.write_str(&self.0)
+  }
+}
+
+impl std::error::Error for LiteError {}
+
+/// A message encoded with the [LiteWriter] and decoded with the [LiteReader]. The fields must be
+/// written in the order of their numbers, like the protobuf runtime does, for the encodings to
+/// be the same bytes.
+pub trait LiteMessage: Default {
+  fn write_to(&self, writer: &mut LiteWriter);
+
+  /// Reads the value of `field` from `reader`. The unknown fields must be skipped with
+  /// [LiteReader::skip].
+  fn merge_field(&mut self, field: u32, reader: &mut LiteReader) -> Result<(), LiteError>;
+
+  fn encode(&self) -> Vec<u8> {
+    let mut writer = LiteWriter::new();
+    self.write_to(&mut writer);
+    writer.into_bytes()
+  }
+
+  fn decode(bytes: &[u8]) -> Result<Self, LiteError> {
+    let mut reader = LiteReader::new(bytes);
+    reader.read_fields()
+  }
+}
+
+#[derive(Debug, Default)]
+pub struct LiteWriter {
+  buf: Vec<u8>,
+}
+
+impl LiteWriter {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn into_bytes(self) -> Vec<u8> {
+    self.buf
+  }
+
+  pub fn write_uint64(&mut self, field: u32, value: u64) {
+    if value != 0 {
+      self.write_tag(field, WireType::Varint);
+      self.write_varint(value);
+    }
+  }
+
+  pub fn write_uint32(&mut self, field: u32, value: u32) {
+    self.write_uint64(field, value as u64);
+  }
+
+  pub fn write_int64(&mut self, field: u32, value: i64) {
+    self.write_uint64(field, value as u64);
+  }
+
+  /// The negative values take ten bytes, as they are sign extended to 64 bits.
+  pub fn write_int32(&mut self, field: u32, value: i32) {
+    self.write_uint64(field, value as i64 as u64);
+  }
+
+  pub fn write_sint64(&mut self, field: u32, value: i64) {
+    self.write_uint64(field, ((value << 1) ^ (value >> 63)) as u64);
+  }
+
+  pub fn write_sint32(&mut self, field: u32, value: i32) {
+    self.write_uint64(field, ((value << 1) ^ (value >> 31)) as u32 as u64);
+  }
+
+  pub fn write_bool(&mut self, field: u32, value: bool) {
+    self.write_uint64(field, value as u64);
+  }
+
+  pub fn write_enum<E: Into<i32>>(&mut self, field: u32, value: E) {
+    self.write_int32(field, value.into());
+  }
+
+  pub fn write_double(&mut self, field: u32, value: f64) {
+    if value != 0.0 {
+      self.write_tag(field, WireType::Fixed64);
+      self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+  }
+
+  pub fn write_float(&mut self, field: u32, value: f32) {
+    if value != 0.0 {
+      self.write_tag(field, WireType::Fixed32);
+      self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+  }
+
+  pub fn write_string(&mut self, field: u32, value: &str) {
+    self.write_bytes(field, value.as_bytes());
+  }
+
+  pub fn write_bytes(&mut self, field: u32, value: &[u8]) {
+    if !value.is_empty() {
+      self.write_length_delimited(field, value);
+    }
+  }
+
+  /// Unlike the scalars, a message is written even if it's empty: the field is set.
+  pub fn write_message<M: LiteMessage>(&mut self, field: u32, message: &M) {
+    let bytes = message.encode();
+    self.write_length_delimited(field, &bytes);
+  }
+
+  pub fn write_repeated_strings<S: AsRef<str>>(&mut self, field: u32, values: &[S]) {
+    for value in values {
+      self.write_length_delimited(field, value.as_ref().as_bytes());
+    }
+  }
+
+  pub fn write_repeated_messages<M: LiteMessage>(&mut self, field: u32, messages: &[M]) {
+    for message in messages {
+      self.write_message(field, message);
+    }
+  }
+
+  /// Writes the integers packed, as proto3 does for the repeated scalars.
+  pub fn write_packed_int64(&mut self, field: u32, values: &[i64]) {
+    if values.is_empty() {
+      return;
+    }
+    let mut packed = LiteWriter::new();
+    for value in values {
+      packed.write_varint(*value as u64);
+    }
+    self.write_length_delimited(field, &packed.buf);
+  }
+
+  fn write_length_delimited(&mut self, field: u32, value: &[u8]) {
+    self.write_tag(field, WireType::LengthDelimited);
+    self.write_varint(value.len() as u64);
+    self.buf.extend_from_slice(value);
+  }
+
+  fn write_tag(&mut self, field: u32, wire_type: WireType) {
+    self.write_varint(((field as u64) << 3) | wire_type as u64);
+  }
+
+  fn write_varint(&mut self, mut value: u64) {
+    while value >= 0x80 {
+      self.buf.push((value as u8) | 0x80);
+      value >>= 7;
+    }
+    self.buf.push(value as u8);
+  }
+}
+
+/// Reads the fields of a message, see [LiteMessage::merge_field].
+pub struct LiteReader<'a> {
+  buf: &'a [u8],
+  pos: usize,
+  wire_type: WireType,
+  depth: u32,
+}
+
+impl<'a> LiteReader<'a> {
+  pub fn new(buf: &'a [u8]) -> Self {
+    Self {
+      buf,
+      pos: 0,
+      wire_type: WireType::Varint,
+      depth: 0,
+    }
+  }
+
+  /// The wire type of the field being read.
+  pub fn wire_type(&self) -> WireType {
+    self.wire_type
+  }
+
+  pub fn read_uint64(&mut self) -> Result<u64, LiteError> {
+    self.expect(WireType::Varint)?;
+    self.read_varint()
+  }
+
+  pub fn read_uint32(&mut self) -> Result<u32, LiteError> {
+    self.read_uint64().map(|value| value as u32)
+  }
+
+  pub fn read_int64(&mut self) -> Result<i64, LiteError> {
+    self.read_uint64().map(|value| value as i64)
+  }
+
+  pub fn read_int32(&mut self) -> Result<i32, LiteError> {
+    self.read_uint64().map(|value| value as i32)
+  }
+
+  pub fn read_sint64(&mut self) -> Result<i64, LiteError> {
+    self
+      .read_uint64()
+      .map(|value| ((value >> 1) as i64) ^ -((value & 1) as i64))
+  }
+
+  pub fn read_sint32(&mut self) -> Result<i32, LiteError> {
+    self
+      .read_uint64()
+      .map(|value| (((value as u32) >> 1) as i32) ^ -((value & 1) as i32))
+  }
+
+  pub fn read_bool(&mut self) -> Result<bool, LiteError> {
+    self.read_uint64().map(|value| value != 0)
+  }
+
+  /// The unknown values are kept, like proto3 does for the open enums.
+  pub fn read_enum<E: From<i32>>(&mut self) -> Result<E, LiteError> {
+    self.read_int32().map(E::from)
+  }
+
+  pub fn read_double(&mut self) -> Result<f64, LiteError> {
+    self.expect(WireType::Fixed64)?;
+    let bytes = self.take(8)?;
+    Ok(f64::from_le_bytes(bytes.try_into().unwrap()))
+  }
+
+  pub fn read_float(&mut self) -> Result<f32, LiteError> {
+    self.expect(WireType::Fixed32)?;
+    let bytes = self.take(4)?;
+    Ok(f32::from_le_bytes(bytes.try_into().unwrap()))
+  }
+
+  pub fn read_string(&mut self) -> Result<String, LiteError> {
+    let bytes = self.read_length_delimited()?;
+    String::from_utf8(bytes.to_vec()).map_err(|e| LiteError(e.to_string()))
+  }
+
+  pub fn read_bytes(&mut self) -> Result<Vec<u8>, LiteError> {
+    self.read_length_delimited().map(|bytes| bytes.to_vec())
+  }
+
+  pub fn read_message<M: LiteMessage>(&mut self) -> Result<M, LiteError> {
+    if self.depth >= MAX_DEPTH {
+      return Err(LiteError("the message is nested too deep".to_owned()));
+    }
+    let bytes = self.read_length_delimited()?;
+    let mut reader = LiteReader::new(bytes);
+    reader.depth = self.depth + 1;
+    reader.read_fields()
+  }
+
+  /// Appends the values of a repeated integer field, packed or not, to `values`.
+  pub fn read_packed_int64(&mut self, values: &mut Vec<i64>) -> Result<(), LiteError> {
+    if self.wire_type == WireType::Varint {
+      values.push(self.read_varint()? as i64);
+      return Ok(());
+    }
+    let bytes = self.read_length_delimited()?;
+    let mut reader = LiteReader::new(bytes);
+    while !reader.is_empty() {
+      values.push(reader.read_varint()? as i64);
+    }
+    Ok(())
+  }
+
+  /// Skips the value of the field being read.
+  pub fn skip(&mut self) -> Result<(), LiteError> {
+    match self.wire_type {
+      WireType::Varint => self.read_varint().map(|_| ()),
+      WireType::Fixed64 => self.take(8).map(|_| ()),
+      WireType::LengthDelimited => self.read_length_delimited().map(|_| ()),
+      WireType::Fixed32 => self.take(4).map(|_| ()),
+    }
+  }
+
+  fn read_fields<M: LiteMessage>(&mut self) -> Result<M, LiteError> {
+    let mut message = M::default();
+    while !self.is_empty() {
+      let tag = self.read_varint()?;
+      self.wire_type = WireType::from_tag(tag)?;
+      let field = (tag >> 3) as u32;
+      if field == 0 {
+        return Err(LiteError("invalid field number 0".to_owned()));
+      }
+      message.merge_field(field, self)?;
+    }
+    Ok(message)
+  }
+
+  fn is_empty(&self) -> bool {
+    self.pos >= self.buf.len()
+  }
+
+  fn expect(&self, wire_type: WireType) -> Result<(), LiteError> {
+    if self.wire_type == wire_type {
+      Ok(())
+    } else {
+      Err(LiteError(format!(
+        "expected the wire type {:?}, got {:?}",
+        wire_type, self.wire_type
+      )))
+    }
+  }
+
+  fn take(&mut self, len: usize) -> Result<&'a [u8], LiteError> {
+    let end = self
+      .pos
+      .checked_add(len)
+      .filter(|end| *end <= self.buf.len())
+      .ok_or_else(|| LiteError("unexpected end of the payload".to_owned()))?;
+    let bytes = &self.buf[self.pos..end];
+    self.pos = end;
+    Ok(bytes)
+  }
+
+  fn read_length_delimited(&mut self) -> Result<&'a [u8], LiteError> {
+    self.expect(WireType::LengthDelimited)?;
+    let len = self.read_varint()?;
+    self.take(len as usize)
+  }
+
+  fn read_varint(&mut self) -> Result<u64, LiteError> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+      let byte = self.take(1)?[0];
+      value |= ((byte & 0x7f) as u64) << shift;
+      if byte < 0x80 {
+        return Ok(value);
+      }
+    }
+    Err(LiteError("varint longer than 10 bytes".to_owned()))
+  }
+}
+
+/// A protobuf payload encoded with the [LiteMessage] codec instead of the protobuf runtime.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProtoLite<T>(pub T);
+
+impl<T> ProtoLite<T> {
+  pub fn into_inner(self) -> T {
+    self.0
+  }
+}
+
+impl<T> ops::Deref for ProtoLite<T> {
+  type Target = T;
+
+  fn deref(&self) -> &T {
+    &self.0
+  }
+}
+
+impl<T> ops::DerefMut for ProtoLite<T> {
+  fn deref_mut(&mut self) -> &mut T {
+    &mut self.0
+  }
+}
+
+impl<T: LiteMessage> ProtoLite<T> {
+  /// Encodes the message, e.g. to build the payload of a request.
+  pub fn to_vec(&self) -> Vec<u8> {
+    self.0.encode()
+  }
+}
+
+impl<T> FromAFPluginRequest for ProtoLite<T>
+where
+  T: LiteMessage + 'static,
+{
+  type Error = DispatchError;
+  type Future = Ready<Result<Self, DispatchError>>;
+
+  fn from_request(req: &AFPluginEventRequest, payload: &mut Payload) -> Self::Future {
+    if let Some(content_type) = req.content_type {
+      if content_type != ContentType::Protobuf {
+        let msg = format!(
+          "{:?} expects a {} payload, got {}",
+          req.event,
+          ContentType::Protobuf,
+          content_type
+        );
+        return ready(Err(InternalError::DeserializeFromBytes(msg).into()));
+      }
+    }
+    let bytes = match payload {
+      Payload::None => return ready(Err(unexpected_none_payload(req))),
+      Payload::Bytes(bytes) => bytes,
+    };
+    ready(T::decode(bytes).map(ProtoLite).map_err(|e| {
+      let msg = format!(
+        "Parse protobuf payload to {} failed: {}",
+        std::any::type_name::<T>(),
+        e
+      );
+      InternalError::DeserializeFromBytes(msg).into()
+    }))
+  }
+}
+
+impl<T: LiteMessage> AFPluginResponder for ProtoLite<T> {
+  fn respond_to(self, _request: &AFPluginEventRequest) -> AFPluginEventResponse {
+    ResponseBuilder::Ok()
+      .data(self.0.encode())
+      .content_type(ContentType::Protobuf)
+      .build()
+  }
+}
@@ -1,4 +1,5 @@
 use std::fmt;
+use std::ops::RangeInclusive;
 
 use bytes::Bytes;
 use dyn_clone::DynClone;
@@ -7,8 +8,8 @@ use tokio::{sync::mpsc::error::SendError, task::JoinError};
 use crate::prelude::AFConcurrent;
 use crate::{
   byte_trait::AFPluginFromBytes,
-  request::AFPluginEventRequest,
-  response::{AFPluginEventResponse, ResponseBuilder},
+  request::{AFPluginEventRequest, Payload},
+  response::{AFPluginEventResponse, ErrorOrigin, ResponseBuilder},
 };
 
 pub trait Error: fmt::Debug + DynClone + AFConcurrent {
@@ -34,6 +35,60 @@ impl DispatchError {
   pub fn inner_error(&self) -> &dyn Error {
     self.inner.as_ref()
   }
+
+  /// Where the error was raised, `None` if it was returned by the handler.
+  pub fn origin(&self) -> Option<ErrorOrigin> {
+    self.inner.as_response().error_origin
+  }
+
+  /// Whether sending the request again may succeed, see [ErrorOrigin::is_retryable].
+  pub fn is_retryable(&self) -> bool {
+    self
+      .origin()
+      .map(|origin| origin.is_retryable())
+      .unwrap_or(false)
+  }
+
+  /// Decodes the error returned by the handler. `None` if the error was raised by the
+  /// dispatcher, see [DispatchError::origin], or isn't a `E`.
+  pub fn handler_error<E: AFPluginFromBytes>(&self) -> Option<E> {
+    let response = self.inner.as_response();
+    if response.error_origin.is_some() {
+      return None;
+    }
+    match response.payload {
+      Payload::Bytes(bytes) => E::parse_from_bytes(bytes).ok(),
+      Payload::None => None,
+    }
+  }
+}
+
+/// The error response of a request sent with
+/// [AFPluginDispatcher::request](crate::prelude::AFPluginDispatcher::request) or as part of a
+/// transaction, kept as is so the caller decodes the error of the handler with
+/// [DispatchError::handler_error].
+#[derive(Clone)]
+pub(crate) struct ResponseError(pub(crate) AFPluginEventResponse);
+
+impl Error for ResponseError {
+  fn as_response(&self) -> AFPluginEventResponse {
+    self.0.clone()
+  }
+}
+
+impl fmt::Debug for ResponseError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self.0.error_origin {
+      // The dispatcher errors are plain messages
+      Some(origin) => write!(
+        f,
+        "{:?}: {}",
+        origin,
+        String::from_utf8_lossy(self.0.payload.as_ref())
+      ),
+      None => write!(f, "Handler error: {}", self.0.payload),
+    }
+  }
 }
 
 impl fmt::Display for DispatchError {
@@ -110,6 +165,28 @@ pub(crate) enum InternalError {
   JoinError(String),
   ServiceNotFound(String),
   HandleNotFound(String),
+  IncompatibleVersion {
+    event: String,
+    version: u32,
+    supported: RangeInclusive<u32>,
+  },
+  StateInit {
+    state: String,
+    reason: String,
+  },
+  DeadlineExceeded {
+    event: String,
+    queued: bool,
+  },
+  Transport(String),
+  PluginFault {
+    plugin: String,
+    reason: String,
+  },
+  HandlerPanicked {
+    event: String,
+    message: String,
+  },
   Other(String),
 }
 
@@ -122,14 +199,61 @@ impl fmt::Display for InternalError {
       InternalError::JoinError(s) => fmt::Display::fmt(&s, f),
       InternalError::ServiceNotFound(s) => fmt::Display::fmt(&s, f),
       InternalError::HandleNotFound(s) => fmt::Display::fmt(&s, f),
+      InternalError::IncompatibleVersion {
+        event,
+        version,
+        supported,
+      } => write!(
+        f,
+        "IncompatibleVersion: {} does not support version {}, the supported versions are {}..={}",
+        event,
+        version,
+        supported.start(),
+        supported.end()
+      ),
+      InternalError::StateInit { state, reason } => {
+        write!(f, "StateInit: initialize {} failed: {}", state, reason)
+      },
+      InternalError::DeadlineExceeded { event, queued } => {
+        let stage = if *queued { "queued" } else { "handled" };
+        write!(
+          f,
+          "DeadlineExceeded: {} passed its deadline while {}",
+          event, stage
+        )
+      },
+      InternalError::Transport(s) => write!(f, "Transport: {}", s),
+      InternalError::PluginFault { plugin, reason } => {
+        write!(f, "PluginFault: {} failed: {}", plugin, reason)
+      },
+      InternalError::HandlerPanicked { event, message } => {
+        write!(f, "HandlerPanicked: {} panicked: {}", event, message)
+      },
       InternalError::Other(s) => fmt::Display::fmt(&s, f),
     }
   }
 }
 
+impl InternalError {
+  fn origin(&self) -> ErrorOrigin {
+    match self {
+      InternalError::JoinError(_)
+      | InternalError::StateInit { .. }
+      | InternalError::PluginFault { .. }
+      | InternalError::HandlerPanicked { .. }
+      | InternalError::Other(_) => ErrorOrigin::Internal,
+      InternalError::Transport(_) => ErrorOrigin::Transport,
+      _ => ErrorOrigin::Dispatcher,
+    }
+  }
+}
+
 impl Error for InternalError {
   fn as_response(&self) -> AFPluginEventResponse {
-    ResponseBuilder::Err().data(self.to_string()).build()
+    ResponseBuilder::Err()
+      .data(self.to_string())
+      .error_origin(self.origin())
+      .build()
   }
 }
 
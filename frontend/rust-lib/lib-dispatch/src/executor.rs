@@ -0,0 +1,308 @@
+//! The async runtime the dispatcher runs the requests on, so the embedders that don't use tokio
+//! (the tests, the browser, the async-std apps) can plug theirs in with
+//! [AFPluginDispatcher::with_executor](crate::prelude::AFPluginDispatcher::with_executor) instead
+//! of forking the crate.
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures_channel::oneshot;
+use futures_core::future::{BoxFuture, LocalBoxFuture};
+use tokio::runtime::Handle;
+
+use crate::clock::Clock;
+use crate::runtime::AFPluginRuntime;
+use crate::util::budget;
+
+/// Spawns the tasks of the dispatcher and runs its timers.
+pub trait Executor: Send + Sync + 'static {
+  /// Runs `future` in the background, on any thread of the executor.
+  fn spawn(&self, future: BoxFuture<'static, ()>);
+
+  /// Runs `future` in the background, on the thread that calls it. Used when the `local_set`
+  /// feature is enabled, since the handlers are then not `Send`.
+  fn spawn_local(&self, future: LocalBoxFuture<'static, ()>);
+
+  /// Runs `f` where it can block without holding back the other tasks.
+  fn spawn_blocking(&self, f: Box<dyn FnOnce() + Send + 'static>);
+
+  /// Resolves once `duration` has passed.
+  fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+
+  /// Lets the other tasks run before the caller goes on. The low priority requests yield once
+  /// before they are handled.
+  fn yield_now(&self) -> BoxFuture<'static, ()> {
+    Box::pin(budget::yield_now())
+  }
+
+  /// The number of threads running the tasks, the dispatcher sizes its queues from it.
+  fn num_workers(&self) -> usize {
+    1
+  }
+}
+
+/// The output of a task spawned with [ExecutorExt], `Err(Canceled)` if the task was dropped
+/// before it finished, e.g. because it panicked or the executor shut down. Dropping the handle
+/// doesn't stop the task.
+pub type TaskHandle<T> = oneshot::Receiver<T>;
+
+/// Spawns the tasks whose output is awaited, on any [Executor].
+pub trait ExecutorExt: Executor {
+  fn spawn_with_handle<F>(&self, future: F) -> TaskHandle<F::Output>
+  where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+  {
+    let (tx, rx) = oneshot::channel();
+    self.spawn(Box::pin(async move {
+      let _ = tx.send(future.await);
+    }));
+    rx
+  }
+
+  fn spawn_local_with_handle<F>(&self, future: F) -> TaskHandle<F::Output>
+  where
+    F: Future + 'static,
+    F::Output: 'static,
+  {
+    let (tx, rx) = oneshot::channel();
+    self.spawn_local(Box::pin(async move {
+      let _ = tx.send(future.await);
+    }));
+    rx
+  }
+
+  fn spawn_blocking_with_handle<F, T>(&self, f: F) -> TaskHandle<T>
+  where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+  {
+    let (tx, rx) = oneshot::channel();
+    self.spawn_blocking(Box::new(move || {
+      let _ = tx.send(f());
+    }));
+    rx
+  }
+}
+
+impl<E: Executor + ?Sized> ExecutorExt for E {}
+
+/// Runs the tasks on the tokio runtime of `handle`. The local tasks are spawned on the current
+/// `LocalSet`.
+#[derive(Debug, Clone)]
+pub struct TokioExecutor {
+  handle: Handle,
+}
+
+impl TokioExecutor {
+  pub fn new(handle: Handle) -> Self {
+    Self { handle }
+  }
+
+  /// The executor of the runtime the caller runs on. Panics outside of a tokio runtime.
+  #[track_caller]
+  pub fn current() -> Self {
+    Self::new(Handle::current())
+  }
+}
+
+impl Executor for TokioExecutor {
+  fn spawn(&self, future: BoxFuture<'static, ()>) {
+    self.handle.spawn(future);
+  }
+
+  fn spawn_local(&self, future: LocalBoxFuture<'static, ()>) {
+    tokio::task::spawn_local(future);
+  }
+
+  fn spawn_blocking(&self, f: Box<dyn FnOnce() + Send + 'static>) {
+    self.handle.spawn_blocking(f);
+  }
+
+  fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+    // The timer registers with the runtime it's created in.
+    let _guard = self.handle.enter();
+    Box::pin(tokio::time::sleep(duration))
+  }
+
+  fn yield_now(&self) -> BoxFuture<'static, ()> {
+    Box::pin(tokio::task::yield_now())
+  }
+
+  fn num_workers(&self) -> usize {
+    self.handle.metrics().num_workers()
+  }
+}
+
+/// Goes through [AFPluginRuntime::spawn] and [AFPluginRuntime::spawn_local], so the tasks of a
+/// [AFPluginRuntime::deterministic] runtime keep their scheduling.
+impl Executor for AFPluginRuntime {
+  fn spawn(&self, future: BoxFuture<'static, ()>) {
+    AFPluginRuntime::spawn(self, future);
+  }
+
+  fn spawn_local(&self, future: LocalBoxFuture<'static, ()>) {
+    AFPluginRuntime::spawn_local(self, future);
+  }
+
+  fn spawn_blocking(&self, f: Box<dyn FnOnce() + Send + 'static>) {
+    self.inner.spawn_blocking(f);
+  }
+
+  fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+    let _guard = self.inner.enter();
+    Box::pin(tokio::time::sleep(duration))
+  }
+
+  fn yield_now(&self) -> BoxFuture<'static, ()> {
+    Box::pin(tokio::task::yield_now())
+  }
+
+  fn num_workers(&self) -> usize {
+    AFPluginRuntime::num_workers(self)
+  }
+}
+
+/// The wall clock with the timers of an [Executor]. It's the clock of the dispatchers created
+/// with [AFPluginDispatcher::with_executor](crate::prelude::AFPluginDispatcher::with_executor).
+#[derive(Clone)]
+pub struct ExecutorClock {
+  executor: Arc<dyn Executor>,
+}
+
+impl ExecutorClock {
+  pub fn new(executor: Arc<dyn Executor>) -> Self {
+    Self { executor }
+  }
+}
+
+impl Clock for ExecutorClock {
+  fn now(&self) -> Instant {
+    Instant::now()
+  }
+
+  fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+    self.executor.sleep(duration)
+  }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use local::LocalExecutor;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod local {
+  use std::cell::RefCell;
+  use std::future::Future;
+  use std::sync::Mutex;
+  use std::time::Duration;
+
+  use futures::executor::{LocalPool, LocalSpawner};
+  use futures::future::{select, Either};
+  use futures::task::LocalSpawnExt;
+  use futures::StreamExt;
+  use futures_channel::{mpsc, oneshot};
+  use futures_core::future::{BoxFuture, LocalBoxFuture};
+
+  use super::Executor;
+
+  thread_local! {
+    /// The pool of the [LocalExecutor::block_on] running on this thread.
+    static SPAWNER: RefCell<Option<LocalSpawner>> = RefCell::new(None);
+  }
+
+  /// A single threaded executor without any runtime behind it: the tasks only run while
+  /// [LocalExecutor::block_on] drives them, on the thread that calls it. The blocking tasks and
+  /// the timers get a thread each, it's meant for the tests and the small tools, not for the app.
+  ///
+  /// ```ignore
+  /// let executor = Arc::new(LocalExecutor::new());
+  /// let dispatcher = AFPluginDispatcher::with_executor(executor.clone(), plugins);
+  /// let response = executor.block_on(AFPluginDispatcher::async_send(&dispatcher, request));
+  /// ```
+  pub struct LocalExecutor {
+    sender: mpsc::UnboundedSender<BoxFuture<'static, ()>>,
+    /// Taken by the running [LocalExecutor::block_on].
+    receiver: Mutex<Option<mpsc::UnboundedReceiver<BoxFuture<'static, ()>>>>,
+  }
+
+  impl Default for LocalExecutor {
+    fn default() -> Self {
+      let (sender, receiver) = mpsc::unbounded();
+      Self {
+        sender,
+        receiver: Mutex::new(Some(receiver)),
+      }
+    }
+  }
+
+  impl LocalExecutor {
+    pub fn new() -> Self {
+      Self::default()
+    }
+
+    /// Runs the spawned tasks until `future` finishes. The tasks still pending then are dropped.
+    /// Panics if called again while running.
+    #[track_caller]
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+      let mut receiver = self
+        .receiver
+        .lock()
+        .unwrap()
+        .take()
+        .expect("LocalExecutor::block_on is already running");
+      let mut pool = LocalPool::new();
+      let spawner = pool.spawner();
+      let previous = SPAWNER.with(|current| current.replace(Some(spawner.clone())));
+
+      let output = pool.run_until(async {
+        // Moves the tasks spawned from the other threads into the pool.
+        let feed = async {
+          while let Some(task) = receiver.next().await {
+            let _ = spawner.spawn_local(task);
+          }
+        };
+        let future = std::pin::pin!(future);
+        let feed = std::pin::pin!(feed);
+        match select(future, feed).await {
+          Either::Left((output, _)) => output,
+          Either::Right(_) => unreachable!("The executor holds the sender"),
+        }
+      });
+
+      SPAWNER.with(|current| current.replace(previous));
+      *self.receiver.lock().unwrap() = Some(receiver);
+      output
+    }
+  }
+
+  impl Executor for LocalExecutor {
+    fn spawn(&self, future: BoxFuture<'static, ()>) {
+      let _ = self.sender.unbounded_send(future);
+    }
+
+    #[track_caller]
+    fn spawn_local(&self, future: LocalBoxFuture<'static, ()>) {
+      SPAWNER.with(|spawner| match spawner.borrow().as_ref() {
+        Some(spawner) => {
+          let _ = spawner.spawn_local(future);
+        },
+        None => panic!("LocalExecutor::spawn_local called outside of LocalExecutor::block_on"),
+      })
+    }
+
+    fn spawn_blocking(&self, f: Box<dyn FnOnce() + Send + 'static>) {
+      std::thread::spawn(f);
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+      let (tx, rx) = oneshot::channel();
+      std::thread::spawn(move || {
+        std::thread::sleep(duration);
+        let _ = tx.send(());
+      });
+      Box::pin(async move {
+        let _ = rx.await;
+      })
+    }
+  }
+}
@@ -0,0 +1,172 @@
+//! Makes selected events slow or failing, to check how the app behaves when the core misbehaves:
+//! the loading states, the retries, the error messages.
+//!
+//! The [FaultInjector] is registered as the first middleware of the dispatchers built with the
+//! `fault_injection` feature, which is meant for the test and debug builds only. The faults are
+//! set with [AFPluginDispatcher::faults](crate::prelude::AFPluginDispatcher::faults) or, from
+//! the app, with the [SysEvent::InjectFault](crate::system::SysEvent::InjectFault) event:
+//!
+//! ```json
+//! {"event": "GetView", "fault": {"kind": "delay", "delay_ms": 2000}}
+//! {"event": "CreateView", "fault": {"kind": "error", "message": "disk is full"}, "times": 1}
+//! {"event": "GetView"}
+//! ```
+//!
+//! The last one removes the fault of the event.
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::clock::Clock;
+use crate::errors::{DispatchError, InternalError};
+use crate::middleware::AFPluginMiddleware;
+use crate::module::{AFPluginEvent, AFPluginRequest};
+
+/// What happens to the requests of an event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Fault {
+  /// The request is handled after `delay_ms`. Its deadline still applies.
+  Delay { delay_ms: u64 },
+  /// The request fails after `after_ms`, as if its deadline was exceeded.
+  Timeout { after_ms: u64 },
+  /// The request is never answered, until it's cancelled or its deadline is exceeded.
+  Drop,
+  /// The request fails with `message` instead of being handled.
+  Error { message: String },
+}
+
+/// A [Fault] and the number of requests it still applies to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FaultRule {
+  pub fault: Fault,
+  /// Applies to the next `times` requests only, to all of them when not set.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub times: Option<usize>,
+}
+
+/// The payload of [SysEvent::InjectFault](crate::system::SysEvent::InjectFault). Without a
+/// fault, the fault of the event is removed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FaultCommand {
+  pub event: String,
+  #[serde(default)]
+  pub fault: Option<Fault>,
+  #[serde(default)]
+  pub times: Option<usize>,
+}
+
+/// The fault of the request being handled, awaited by the dispatcher before the handler.
+#[derive(Debug, Clone)]
+pub(crate) struct InjectedFault(Fault);
+
+impl InjectedFault {
+  /// Waits as long as the fault says, then lets the request through or fails it.
+  pub(crate) async fn inject(
+    &self,
+    clock: &dyn Clock,
+    event: &AFPluginEvent,
+  ) -> Result<(), DispatchError> {
+    match &self.0 {
+      Fault::Delay { delay_ms } => {
+        clock.sleep(Duration::from_millis(*delay_ms)).await;
+        Ok(())
+      },
+      Fault::Timeout { after_ms } => {
+        clock.sleep(Duration::from_millis(*after_ms)).await;
+        Err(
+          InternalError::DeadlineExceeded {
+            event: event.as_str().to_owned(),
+            queued: false,
+          }
+          .into(),
+        )
+      },
+      Fault::Drop => std::future::pending().await,
+      Fault::Error { .. } => Ok(()),
+    }
+  }
+}
+
+/// The faults injected in the requests, by event. See the [module](self) docs.
+#[derive(Default)]
+pub struct FaultInjector {
+  rules: RwLock<BTreeMap<String, FaultRule>>,
+}
+
+impl FaultInjector {
+  /// Applies `fault` to all the requests of `event` from now on.
+  pub fn inject<E: ToString>(&self, event: E, fault: Fault) {
+    self.inject_times(event, fault, None);
+  }
+
+  /// Applies `fault` to the next `times` requests of `event`, or to all of them.
+  pub fn inject_times<E: ToString>(&self, event: E, fault: Fault, times: Option<usize>) {
+    self
+      .rules
+      .write()
+      .unwrap()
+      .insert(event.to_string(), FaultRule { fault, times });
+  }
+
+  /// Removes the fault of `event`. Returns false if it had none.
+  pub fn remove(&self, event: &str) -> bool {
+    self.rules.write().unwrap().remove(event).is_some()
+  }
+
+  pub fn clear(&self) {
+    self.rules.write().unwrap().clear();
+  }
+
+  /// The faults that still apply, by event.
+  pub fn rules(&self) -> BTreeMap<String, FaultRule> {
+    self.rules.read().unwrap().clone()
+  }
+
+  pub fn apply(&self, command: FaultCommand) {
+    match command.fault {
+      Some(fault) => self.inject_times(command.event, fault, command.times),
+      None => {
+        self.remove(&command.event);
+      },
+    }
+  }
+
+  /// The fault of the next request of `event`, counted against its rule.
+  fn take(&self, event: &str) -> Option<Fault> {
+    let mut rules = self.rules.write().unwrap();
+    let rule = rules.get_mut(event)?;
+    let fault = rule.fault.clone();
+    match &mut rule.times {
+      Some(0) => {
+        rules.remove(event);
+        return None;
+      },
+      Some(1) => {
+        rules.remove(event);
+      },
+      Some(times) => *times -= 1,
+      None => {},
+    }
+    Some(fault)
+  }
+}
+
+impl AFPluginMiddleware for FaultInjector {
+  fn on_request(&self, request: &mut AFPluginRequest) -> Result<(), DispatchError> {
+    match self.take(request.event.as_str()) {
+      Some(Fault::Error { message }) => {
+        tracing::debug!("[fault]: fail {}", request.event);
+        Err(InternalError::Other(message).into())
+      },
+      Some(fault) => {
+        tracing::debug!("[fault]: inject {:?} in {}", fault, request.event);
+        request.extensions.insert(InjectedFault(fault));
+        Ok(())
+      },
+      None => Ok(()),
+    }
+  }
+}
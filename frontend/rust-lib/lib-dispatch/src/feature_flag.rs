@@ -0,0 +1,189 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::encoding::ContentType;
+use crate::errors::{DispatchError, Error, InternalError};
+use crate::module::AFPluginRequest;
+use crate::response::{AFPluginEventResponse, ErrorOrigin, ResponseBuilder};
+
+/// Keeps the overrides of the [FeatureFlags], so they survive a restart of the app.
+pub trait FlagStore: Send + Sync + 'static {
+  fn load(&self) -> Result<BTreeMap<String, bool>, DispatchError>;
+
+  /// Replaces all the overrides saved before.
+  fn save(&self, overrides: &BTreeMap<String, bool>) -> Result<(), DispatchError>;
+}
+
+/// Stores the overrides as a JSON object in a file. They are written to a temporary file first
+/// and renamed over the previous ones.
+pub struct FileFlagStore {
+  path: PathBuf,
+}
+
+impl FileFlagStore {
+  pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+    Self { path: path.into() }
+  }
+}
+
+impl FlagStore for FileFlagStore {
+  fn load(&self) -> Result<BTreeMap<String, bool>, DispatchError> {
+    let content = match std::fs::read(&self.path) {
+      Ok(content) => content,
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+      Err(err) => return Err(InternalError::Other(err.to_string()).into()),
+    };
+    serde_json::from_slice(&content)
+      .map_err(|e| InternalError::DeserializeFromBytes(e.to_string()).into())
+  }
+
+  fn save(&self, overrides: &BTreeMap<String, bool>) -> Result<(), DispatchError> {
+    let content =
+      serde_json::to_vec_pretty(overrides).map_err(|e| InternalError::Other(e.to_string()))?;
+    let tmp_path = self.path.with_extension("tmp");
+    std::fs::write(&tmp_path, content).map_err(|e| InternalError::Other(e.to_string()))?;
+    std::fs::rename(&tmp_path, &self.path).map_err(|e| InternalError::Other(e.to_string()).into())
+  }
+}
+
+type FlagRule = Arc<dyn Fn(&AFPluginRequest) -> bool + Send + Sync>;
+
+/// The named flags turning the experimental events on or off, evaluated for each request. The
+/// events declare the flag they are gated behind with
+/// [AFPlugin::feature_flag](crate::prelude::AFPlugin::feature_flag), so an experiment ships
+/// disabled with the rest of the code instead of on its own branch.
+///
+/// A flag is on or off by default, or decided for each request by a rule, e.g. on for the
+/// requests of the beta testers. An override forces it on or off for all the requests; the
+/// overrides are kept in the [FlagStore] of the dispatcher, see
+/// [AFPluginDispatcher::with_flag_store](crate::prelude::AFPluginDispatcher::with_flag_store).
+#[derive(Default)]
+pub struct FeatureFlags {
+  rules: RwLock<BTreeMap<String, FlagRule>>,
+  overrides: RwLock<BTreeMap<String, bool>>,
+  store: RwLock<Option<Arc<dyn FlagStore>>>,
+}
+
+impl FeatureFlags {
+  /// Declares `flag`, on or off for all the requests. It replaces the previous rule of the flag.
+  pub fn define<T: ToString>(&self, flag: T, enabled: bool) {
+    self.define_rule(flag, move |_: &AFPluginRequest| enabled);
+  }
+
+  /// Declares `flag`, on for the requests `rule` returns `true` for.
+  pub fn define_rule<T, F>(&self, flag: T, rule: F)
+  where
+    T: ToString,
+    F: Fn(&AFPluginRequest) -> bool + Send + Sync + 'static,
+  {
+    self
+      .rules
+      .write()
+      .unwrap()
+      .insert(flag.to_string(), Arc::new(rule));
+  }
+
+  /// Whether `flag` is on for `request`. The flags that were never declared nor overridden are
+  /// off.
+  pub fn is_enabled(&self, flag: &str, request: &AFPluginRequest) -> bool {
+    if let Some(enabled) = self.overrides.read().unwrap().get(flag) {
+      return *enabled;
+    }
+    let rule = self.rules.read().unwrap().get(flag).cloned();
+    rule.map_or(false, |rule| rule(request))
+  }
+
+  /// The declared flags, sorted.
+  pub fn flags(&self) -> Vec<String> {
+    self.rules.read().unwrap().keys().cloned().collect()
+  }
+
+  pub fn overrides(&self) -> BTreeMap<String, bool> {
+    self.overrides.read().unwrap().clone()
+  }
+
+  /// Forces `flag` on or off for all the requests, or back to its rule when `None`. The
+  /// overrides are saved in the store, if any; the change is kept in memory when it fails.
+  pub fn set_override(&self, flag: &str, enabled: Option<bool>) -> Result<(), DispatchError> {
+    let overrides = {
+      let mut overrides = self.overrides.write().unwrap();
+      match enabled {
+        Some(enabled) => overrides.insert(flag.to_owned(), enabled),
+        None => overrides.remove(flag),
+      };
+      overrides.clone()
+    };
+    tracing::info!("[feature_flag]: override {} with {:?}", flag, enabled);
+    match self.store.read().unwrap().as_ref() {
+      Some(store) => store.save(&overrides),
+      None => Ok(()),
+    }
+  }
+
+  /// Keeps the overrides in `store`, and restores the ones saved there. They replace the
+  /// overrides made before.
+  pub(crate) fn set_store(&self, store: Arc<dyn FlagStore>) -> Result<(), DispatchError> {
+    let overrides = store.load();
+    *self.store.write().unwrap() = Some(store);
+    *self.overrides.write().unwrap() = overrides?;
+    Ok(())
+  }
+}
+
+/// The payload of [SysEvent::SetFeatureFlag](crate::system::SysEvent::SetFeatureFlag).
+///
+/// ```json
+/// {"flag": "ai_summary", "enabled": true}
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FlagOverride {
+  pub flag: String,
+  /// Removes the override of the flag when not set.
+  #[serde(default)]
+  pub enabled: Option<bool>,
+}
+
+/// The error of the requests rejected because the flag their event is gated behind is off. The
+/// response carries it as JSON, read it back with [FeatureDisabled::from_response].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeatureDisabled {
+  pub event: String,
+  pub flag: String,
+}
+
+impl FeatureDisabled {
+  pub fn from_response(response: &AFPluginEventResponse) -> Option<Self> {
+    if response.error_origin != Some(ErrorOrigin::Dispatcher)
+      || response.content_type != Some(ContentType::Json)
+    {
+      return None;
+    }
+    serde_json::from_slice(response.payload.as_ref()).ok()
+  }
+}
+
+impl Display for FeatureDisabled {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "FeatureDisabled: {} requires the {} flag",
+      self.event, self.flag
+    )
+  }
+}
+
+impl Error for FeatureDisabled {
+  fn as_response(&self) -> AFPluginEventResponse {
+    let data = serde_json::to_vec(self).unwrap_or_else(|_| self.to_string().into_bytes());
+    ResponseBuilder::Err()
+      .data(data)
+      .content_type(ContentType::Json)
+      .error_origin(ErrorOrigin::Dispatcher)
+      .build()
+  }
+}
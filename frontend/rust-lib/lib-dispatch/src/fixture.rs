@@ -0,0 +1,229 @@
+//! Record-and-replay fixtures for the regression tests.
+//!
+//! A [FixtureRecorder] registered as a middleware captures the requests handled by the
+//! dispatcher while it's recording, along with their responses. The resulting [Fixture] can be
+//! saved as JSON and [replayed](replay_fixture) later against the current handlers, which
+//! reports the responses that changed.
+//!
+//! The fixture can also hold the responses of some queries sent at the end of the recording,
+//! see [FixtureRecorder::record_state]. They are sent again at the end of the replay, so the
+//! final state of the plugins is compared too.
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use crate::dispatcher::AFPluginDispatcher;
+use crate::errors::{DispatchError, InternalError};
+use crate::middleware::AFPluginMiddleware;
+use crate::module::AFPluginRequest;
+use crate::request::Payload;
+use crate::response::{AFPluginEventResponse, StatusCode};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixtureResponse {
+  pub success: bool,
+  pub payload: Bytes,
+}
+
+impl From<&AFPluginEventResponse> for FixtureResponse {
+  fn from(response: &AFPluginEventResponse) -> Self {
+    Self {
+      success: response.status_code == StatusCode::Ok,
+      payload: payload_bytes(&response.payload),
+    }
+  }
+}
+
+/// A request and the response it got when it was recorded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixtureEvent {
+  pub event: String,
+  pub payload: Bytes,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub version: Option<u32>,
+  pub response: FixtureResponse,
+}
+
+impl FixtureEvent {
+  fn new(request: &AFPluginRequest, response: &AFPluginEventResponse) -> Self {
+    Self {
+      event: request.event.as_str().to_owned(),
+      payload: payload_bytes(&request.payload),
+      version: request.version,
+      response: response.into(),
+    }
+  }
+
+  pub(crate) fn request(&self) -> AFPluginRequest {
+    let mut request = AFPluginRequest::new(self.event.as_str());
+    if !self.payload.is_empty() {
+      request = request.payload(self.payload.clone());
+    }
+    if let Some(version) = self.version {
+      request = request.version(version);
+    }
+    request
+  }
+}
+
+/// The requests recorded by a [FixtureRecorder], in the order their responses were ready.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fixture {
+  pub events: Vec<FixtureEvent>,
+  /// The queries sent at the end of the recording, see [FixtureRecorder::record_state].
+  #[serde(default)]
+  pub state: Vec<FixtureEvent>,
+}
+
+impl Fixture {
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, DispatchError> {
+    let content = std::fs::read(path).map_err(|e| InternalError::Other(e.to_string()))?;
+    serde_json::from_slice(&content)
+      .map_err(|e| InternalError::DeserializeFromBytes(e.to_string()).into())
+  }
+
+  pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), DispatchError> {
+    let content =
+      serde_json::to_vec_pretty(self).map_err(|e| InternalError::Other(e.to_string()))?;
+    std::fs::write(path, content).map_err(|e| InternalError::Other(e.to_string()).into())
+  }
+}
+
+/// A middleware that records the requests and their responses into a [Fixture] while it's
+/// recording. The clones share the same fixture, so one can be registered in the dispatcher
+/// and the other kept to control the recording.
+#[derive(Clone, Default)]
+pub struct FixtureRecorder {
+  recording: Arc<AtomicBool>,
+  fixture: Arc<Mutex<Fixture>>,
+}
+
+impl FixtureRecorder {
+  /// Creates a recorder that does not record until [FixtureRecorder::start] is called.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn start(&self) {
+    self.recording.store(true, Ordering::SeqCst);
+  }
+
+  pub fn stop(&self) {
+    self.recording.store(false, Ordering::SeqCst);
+  }
+
+  pub fn is_recording(&self) -> bool {
+    self.recording.load(Ordering::SeqCst)
+  }
+
+  /// Sends `request` and records its response as part of the final state, to be compared at the
+  /// end of the replay. The request is not recorded as an event.
+  pub async fn record_state<Req>(&self, dispatcher: &AFPluginDispatcher, request: Req)
+  where
+    Req: Into<AFPluginRequest> + 'static,
+  {
+    let request: AFPluginRequest = request.into();
+    let query = request.clone();
+    let was_recording = self.recording.swap(false, Ordering::SeqCst);
+    let response = AFPluginDispatcher::async_send(dispatcher, request).await;
+    self.recording.store(was_recording, Ordering::SeqCst);
+    self
+      .fixture
+      .lock()
+      .unwrap()
+      .state
+      .push(FixtureEvent::new(&query, &response));
+  }
+
+  /// A copy of what was recorded so far.
+  pub fn fixture(&self) -> Fixture {
+    self.fixture.lock().unwrap().clone()
+  }
+
+  /// Returns what was recorded so far and starts a new fixture.
+  pub fn take_fixture(&self) -> Fixture {
+    std::mem::take(&mut *self.fixture.lock().unwrap())
+  }
+}
+
+impl AFPluginMiddleware for FixtureRecorder {
+  fn on_response(&self, request: &AFPluginRequest, response: &mut AFPluginEventResponse) {
+    if self.is_recording() {
+      self
+        .fixture
+        .lock()
+        .unwrap()
+        .events
+        .push(FixtureEvent::new(request, response));
+    }
+  }
+}
+
+/// A response that differs from the recorded one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixtureMismatch {
+  /// The index of the event in [Fixture::events], or in [Fixture::state] for the state queries.
+  pub index: usize,
+  pub is_state: bool,
+  pub event: String,
+  pub expected: FixtureResponse,
+  pub actual: FixtureResponse,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReplayReport {
+  pub replayed: usize,
+  pub mismatches: Vec<FixtureMismatch>,
+}
+
+impl ReplayReport {
+  pub fn is_success(&self) -> bool {
+    self.mismatches.is_empty()
+  }
+}
+
+/// Sends the events of `fixture` one after the other, then its state queries, and compares the
+/// responses with the recorded ones.
+pub async fn replay_fixture(dispatcher: &AFPluginDispatcher, fixture: &Fixture) -> ReplayReport {
+  let mut report = ReplayReport::default();
+  let events = fixture
+    .events
+    .iter()
+    .enumerate()
+    .map(|(index, event)| (index, false, event));
+  let state = fixture
+    .state
+    .iter()
+    .enumerate()
+    .map(|(index, event)| (index, true, event));
+  for (index, is_state, event) in events.chain(state) {
+    let response = AFPluginDispatcher::async_send(dispatcher, event.request()).await;
+    let actual = FixtureResponse::from(&response);
+    report.replayed += 1;
+    if actual != event.response {
+      tracing::warn!(
+        event = event.event.as_str(),
+        "[fixture]: the response of #{} changed",
+        index
+      );
+      report.mismatches.push(FixtureMismatch {
+        index,
+        is_state,
+        event: event.event.clone(),
+        expected: event.response.clone(),
+        actual,
+      });
+    }
+  }
+  report
+}
+
+fn payload_bytes(payload: &Payload) -> Bytes {
+  match payload {
+    Payload::Bytes(bytes) => bytes.clone(),
+    Payload::None => Bytes::new(),
+  }
+}
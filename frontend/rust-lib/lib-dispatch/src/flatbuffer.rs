@@ -0,0 +1,99 @@
+use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
+
+use bytes::Bytes;
+use flatbuffers::{Follow, Verifiable};
+
+use crate::{
+  errors::{DispatchError, InternalError},
+  request::{unexpected_none_payload, AFPluginEventRequest, FromAFPluginRequest, Payload},
+  response::{AFPluginEventResponse, AFPluginResponder, ResponseBuilder},
+  util::ready::{ready, Ready},
+};
+
+/// Binds a marker type to the root table of a FlatBuffers schema, so it can be used as the
+/// parameter of [Flatbuffer].
+///
+/// ```ignore
+/// struct DocumentSnapshotRoot;
+///
+/// impl FlatbufferRoot for DocumentSnapshotRoot {
+///   type Root<'a> = DocumentSnapshot<'a>;
+/// }
+/// ```
+pub trait FlatbufferRoot: 'static {
+  type Root<'a>: Follow<'a, Inner = Self::Root<'a>> + Verifiable;
+}
+
+/// A FlatBuffers payload. The buffer is verified once when the request is extracted, then the
+/// handler reads the fields of the [root](Flatbuffer::root) table in place, without
+/// deserializing the whole payload.
+pub struct Flatbuffer<T: FlatbufferRoot> {
+  bytes: Bytes,
+  _phantom: PhantomData<T>,
+}
+
+impl<T: FlatbufferRoot> Flatbuffer<T> {
+  /// Verifies that `bytes` contains a valid `T::Root` table.
+  pub fn from_bytes(bytes: Bytes) -> Result<Self, DispatchError> {
+    flatbuffers::root::<T::Root<'_>>(&bytes).map_err(|e| {
+      InternalError::DeserializeFromBytes(format!(
+        "Invalid {} flatbuffer: {}",
+        std::any::type_name::<T>(),
+        e
+      ))
+    })?;
+    Ok(Self {
+      bytes,
+      _phantom: PhantomData,
+    })
+  }
+
+  /// Takes the data of a finished builder.
+  pub fn from_builder(builder: &flatbuffers::FlatBufferBuilder) -> Result<Self, DispatchError> {
+    Self::from_bytes(Bytes::copy_from_slice(builder.finished_data()))
+  }
+
+  pub fn root(&self) -> T::Root<'_> {
+    // Safety: the buffer was verified when the Flatbuffer was created.
+    unsafe { flatbuffers::root_unchecked::<T::Root<'_>>(&self.bytes) }
+  }
+
+  pub fn as_bytes(&self) -> &Bytes {
+    &self.bytes
+  }
+
+  pub fn into_bytes(self) -> Bytes {
+    self.bytes
+  }
+}
+
+impl<T: FlatbufferRoot> Debug for Flatbuffer<T> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "Flatbuffer<{}>({} bytes)",
+      std::any::type_name::<T>(),
+      self.bytes.len()
+    )
+  }
+}
+
+impl<T: FlatbufferRoot> FromAFPluginRequest for Flatbuffer<T> {
+  type Error = DispatchError;
+  type Future = Ready<Result<Self, DispatchError>>;
+
+  #[inline]
+  fn from_request(req: &AFPluginEventRequest, payload: &mut Payload) -> Self::Future {
+    match payload {
+      Payload::None => ready(Err(unexpected_none_payload(req))),
+      Payload::Bytes(bytes) => ready(Self::from_bytes(bytes.clone())),
+    }
+  }
+}
+
+impl<T: FlatbufferRoot> AFPluginResponder for Flatbuffer<T> {
+  fn respond_to(self, _request: &AFPluginEventRequest) -> AFPluginEventResponse {
+    ResponseBuilder::Ok().data(self.bytes).build()
+  }
+}
@@ -0,0 +1,114 @@
+//! Helpers to check that malformed payloads never crash the core.
+//!
+//! [fuzz_event] sends a payload to an event and tells whether its extractors and handler
+//! rejected it, accepted it or crashed. The [payload_strategy] and [mutated_payload_strategy]
+//! generate the payloads for proptest, and [FuzzInput] is the input of the cargo-fuzz targets,
+//! see `lib-dispatch/fuzz`.
+use arbitrary::Arbitrary;
+use proptest::prelude::*;
+
+use crate::dispatcher::AFPluginDispatcher;
+use crate::module::{AFPluginEvent, AFPluginRequest};
+use crate::response::{AFPluginEventResponse, ErrorOrigin, StatusCode};
+
+/// The payloads generated by [payload_strategy] are at most this long.
+pub const MAX_FUZZ_PAYLOAD_SIZE: usize = 4096;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FuzzOutcome {
+  /// The payload was decoded and the handler succeeded.
+  Handled,
+  /// The extractors or the handler returned an error.
+  Rejected,
+  /// The handler panicked or the dispatcher failed, described by the message.
+  Crashed(String),
+}
+
+impl From<&AFPluginEventResponse> for FuzzOutcome {
+  fn from(response: &AFPluginEventResponse) -> Self {
+    match (&response.status_code, response.error_origin) {
+      (StatusCode::Ok, _) => FuzzOutcome::Handled,
+      (StatusCode::Err, Some(ErrorOrigin::Internal)) => {
+        FuzzOutcome::Crashed(String::from_utf8_lossy(response.payload.as_ref()).into_owned())
+      },
+      (StatusCode::Err, _) => FuzzOutcome::Rejected,
+    }
+  }
+}
+
+/// Sends `payload` to `event` and reports how it was handled.
+pub async fn fuzz_event(
+  dispatcher: &AFPluginDispatcher,
+  event: &AFPluginEvent,
+  payload: Vec<u8>,
+) -> FuzzOutcome {
+  let request = AFPluginRequest::new(event.clone()).payload(payload);
+  let response = AFPluginDispatcher::async_send(dispatcher, request).await;
+  FuzzOutcome::from(&response)
+}
+
+/// Sends `payload` to every event of the dispatcher and returns the events that crashed.
+pub async fn fuzz_events(
+  dispatcher: &AFPluginDispatcher,
+  payload: &[u8],
+) -> Vec<(AFPluginEvent, String)> {
+  let mut crashes = vec![];
+  for event in dispatcher.events() {
+    if let FuzzOutcome::Crashed(msg) = fuzz_event(dispatcher, &event, payload.to_vec()).await {
+      tracing::error!("[fuzz]: {} crashed: {}", event.as_str(), msg);
+      crashes.push((event, msg));
+    }
+  }
+  crashes
+}
+
+/// Random bytes, up to [MAX_FUZZ_PAYLOAD_SIZE] long.
+pub fn payload_strategy() -> impl Strategy<Value = Vec<u8>> {
+  proptest::collection::vec(any::<u8>(), 0..MAX_FUZZ_PAYLOAD_SIZE)
+}
+
+/// Variations of a valid payload: truncated, with flipped bytes or with trailing garbage. They
+/// get past the first checks of the decoders more often than random bytes.
+pub fn mutated_payload_strategy(valid: Vec<u8>) -> impl Strategy<Value = Vec<u8>> {
+  let len = valid.len();
+  let truncated = {
+    let valid = valid.clone();
+    (0..=len).prop_map(move |end| valid[..end].to_vec())
+  };
+  let flipped = {
+    let valid = valid.clone();
+    proptest::collection::vec((0..len.max(1), any::<u8>()), 1..8).prop_map(move |flips| {
+      let mut payload = valid.clone();
+      for (index, mask) in flips {
+        if let Some(byte) = payload.get_mut(index) {
+          *byte ^= mask;
+        }
+      }
+      payload
+    })
+  };
+  let extended = proptest::collection::vec(any::<u8>(), 1..64).prop_map(move |garbage| {
+    let mut payload = valid.clone();
+    payload.extend(garbage);
+    payload
+  });
+  prop_oneof![truncated, flipped, extended]
+}
+
+/// The input of a fuzz target: a payload and the event it's sent to.
+#[derive(Debug, Clone, Arbitrary)]
+pub struct FuzzInput {
+  event: u16,
+  pub payload: Vec<u8>,
+}
+
+impl FuzzInput {
+  /// Picks one of `events`, or none if there is no event.
+  pub fn event<'a>(&self, events: &'a [AFPluginEvent]) -> Option<&'a AFPluginEvent> {
+    if events.is_empty() {
+      None
+    } else {
+      events.get(self.event as usize % events.len())
+    }
+  }
+}
@@ -0,0 +1,129 @@
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::encoding::ContentType;
+use crate::errors::Error;
+use crate::response::{AFPluginEventResponse, ErrorOrigin, ResponseBuilder};
+
+/// The states the application is in, e.g. `session` once the user signed in or `read_only`
+/// while the workspace can't be edited. The app enters and leaves them as it goes, and the
+/// events declare the states they require with
+/// [AFPlugin::requires](crate::prelude::AFPlugin::requires).
+#[derive(Debug, Default)]
+pub struct AppStates {
+  states: RwLock<BTreeSet<String>>,
+}
+
+impl AppStates {
+  /// Returns whether the app wasn't in `state` already.
+  pub fn enter<T: ToString>(&self, state: T) -> bool {
+    let state = state.to_string();
+    tracing::trace!("[gate]: enter {}", state);
+    self.states.write().unwrap().insert(state)
+  }
+
+  /// Returns whether the app was in `state`.
+  pub fn leave(&self, state: &str) -> bool {
+    tracing::trace!("[gate]: leave {}", state);
+    self.states.write().unwrap().remove(state)
+  }
+
+  pub fn is_in(&self, state: &str) -> bool {
+    self.states.read().unwrap().contains(state)
+  }
+
+  /// The states the app is in, sorted.
+  pub fn current(&self) -> Vec<String> {
+    self.states.read().unwrap().iter().cloned().collect()
+  }
+
+  /// The `preconditions` that don't hold in the current states.
+  pub(crate) fn violations(&self, preconditions: &[Precondition]) -> Vec<Precondition> {
+    let states = self.states.read().unwrap();
+    preconditions
+      .iter()
+      .filter(|precondition| match precondition {
+        Precondition::In(state) => !states.contains(state),
+        Precondition::NotIn(state) => states.contains(state),
+      })
+      .cloned()
+      .collect()
+  }
+}
+
+/// A state an event requires the app to be in, or not to be in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Precondition {
+  In(String),
+  NotIn(String),
+}
+
+impl Precondition {
+  pub fn state<T: ToString>(state: T) -> Self {
+    Precondition::In(state.to_string())
+  }
+
+  pub fn not<T: ToString>(state: T) -> Self {
+    Precondition::NotIn(state.to_string())
+  }
+}
+
+impl Display for Precondition {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Precondition::In(state) => write!(f, "{}", state),
+      Precondition::NotIn(state) => write!(f, "not {}", state),
+    }
+  }
+}
+
+/// The error of the requests rejected because the app isn't in the states their event requires.
+/// The response carries it as JSON, read it back with [PreconditionFailed::from_response].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreconditionFailed {
+  pub event: String,
+  /// The preconditions of the event that don't hold.
+  pub violations: Vec<Precondition>,
+}
+
+impl PreconditionFailed {
+  pub fn from_response(response: &AFPluginEventResponse) -> Option<Self> {
+    if response.error_origin != Some(ErrorOrigin::Dispatcher)
+      || response.content_type != Some(ContentType::Json)
+    {
+      return None;
+    }
+    serde_json::from_slice(response.payload.as_ref()).ok()
+  }
+}
+
+impl Display for PreconditionFailed {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    let violations = self
+      .violations
+      .iter()
+      .map(|precondition| precondition.to_string())
+      .collect::<Vec<_>>();
+    write!(
+      f,
+      "PreconditionFailed: {} requires {}",
+      self.event,
+      violations.join(", ")
+    )
+  }
+}
+
+impl Error for PreconditionFailed {
+  fn as_response(&self) -> AFPluginEventResponse {
+    let data = serde_json::to_vec(self).unwrap_or_else(|_| self.to_string().into_bytes());
+    ResponseBuilder::Err()
+      .data(data)
+      .content_type(ContentType::Json)
+      .error_origin(ErrorOrigin::Dispatcher)
+      .build()
+  }
+}
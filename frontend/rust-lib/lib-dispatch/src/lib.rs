@@ -1,22 +1,86 @@
+mod encoding;
 mod errors;
+mod middleware;
 mod module;
+mod probe;
 mod request;
 mod response;
 mod service;
 pub mod util;
 
 mod byte_trait;
+#[cfg(feature = "use_capnp")]
+mod capnproto;
 mod data;
 mod dispatcher;
+#[cfg(feature = "use_flatbuffers")]
+mod flatbuffer;
 
+pub mod audit;
+pub mod bridge;
+pub mod capability;
+pub mod checkpoint;
+#[cfg(all(unix, feature = "cli"))]
+pub mod cli;
+pub mod clock;
+#[cfg(feature = "codegen")]
+pub mod codegen;
+pub mod config;
+#[cfg(feature = "testing")]
+pub mod coverage;
+pub mod crash;
+#[cfg(feature = "dashboard")]
+pub mod dashboard;
+pub mod diff;
+#[cfg(all(feature = "dylib_plugins", not(target_arch = "wasm32")))]
+pub mod dylib;
+pub mod executor;
+#[cfg(feature = "fault_injection")]
+pub mod fault;
+pub mod feature_flag;
+#[cfg(feature = "testing")]
+pub mod fixture;
+pub mod gate;
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
+pub mod lifecycle;
+#[cfg(feature = "load_generator")]
+pub mod load;
 #[macro_use]
 pub mod macros;
+pub mod memory;
+pub mod metrics;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+pub mod pagination;
+pub mod payload_schema;
+pub mod pipeline;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod proxy;
+pub mod quota;
+pub mod recorder;
 pub mod runtime;
+pub mod saga;
+#[cfg(feature = "request_signing")]
+pub mod security;
+#[cfg(feature = "testing")]
+pub mod snapshot;
+pub mod system;
+#[cfg(feature = "testing")]
+pub mod time_travel;
+pub mod transaction;
+pub mod upload;
 
 pub use errors::Error;
 
 pub mod prelude {
   pub use crate::{
-    byte_trait::*, data::*, dispatcher::*, errors::*, module::*, request::*, response::*,
+    byte_trait::*, data::*, dispatcher::*, encoding::*, errors::*, middleware::*, module::*,
+    probe::*, request::*, response::*,
   };
+
+  #[cfg(feature = "use_capnp")]
+  pub use crate::capnproto::*;
+  #[cfg(feature = "use_flatbuffers")]
+  pub use crate::flatbuffer::*;
 }
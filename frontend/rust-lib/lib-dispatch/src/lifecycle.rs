@@ -0,0 +1,135 @@
+//! Lets the core get ready for iOS and Android suspending the process with little warning, see
+//! [AFPluginDispatcher::enter_background](crate::prelude::AFPluginDispatcher::enter_background).
+use std::sync::{Arc, RwLock};
+
+use serde::Serialize;
+use tokio::sync::watch;
+
+use crate::errors::DispatchError;
+use crate::middleware::CacheInvalidator;
+
+/// The app state entered while the app is in the background, see
+/// [AppStates](crate::gate::AppStates). The events that must not run in the background require
+/// `Precondition::not(BACKGROUND_STATE)`.
+pub const BACKGROUND_STATE: &str = "background";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LifecycleEvent {
+  /// The app is no longer visible, the OS may suspend or kill it at any time.
+  EnterBackground,
+  EnterForeground,
+  /// The OS is about to kill the apps that don't release memory.
+  LowMemory,
+}
+
+/// A module told about the lifecycle of the app, registered with
+/// [AFPlugin::lifecycle_observer](crate::prelude::AFPlugin::lifecycle_observer) or
+/// [Lifecycle::observe]. A closure taking the [LifecycleEvent] is an observer.
+pub trait LifecycleObserver: Send + Sync + 'static {
+  /// Called with every lifecycle event, before [LifecycleObserver::flush] and after
+  /// [LifecycleObserver::trim].
+  fn on_lifecycle(&self, _event: LifecycleEvent) {}
+
+  /// Persists what must survive the process being killed, e.g. the pending writes. Called when
+  /// the app enters the background.
+  fn flush(&self) -> Result<(), DispatchError> {
+    Ok(())
+  }
+
+  /// Drops what can be rebuilt, e.g. the caches. Called on low memory.
+  fn trim(&self) {}
+}
+
+impl<F> LifecycleObserver for F
+where
+  F: Fn(LifecycleEvent) + Send + Sync + 'static,
+{
+  fn on_lifecycle(&self, event: LifecycleEvent) {
+    self(event)
+  }
+}
+
+/// Drops the cached responses on low memory.
+impl LifecycleObserver for CacheInvalidator {
+  fn trim(&self) {
+    self.invalidate_all();
+  }
+}
+
+/// Whether the app is in the background, and the observers of its lifecycle.
+pub struct Lifecycle {
+  background: watch::Sender<bool>,
+  observers: RwLock<Vec<(String, Arc<dyn LifecycleObserver>)>>,
+}
+
+impl Default for Lifecycle {
+  fn default() -> Self {
+    Self {
+      background: watch::channel(false).0,
+      observers: RwLock::new(vec![]),
+    }
+  }
+}
+
+impl Lifecycle {
+  pub fn is_background(&self) -> bool {
+    *self.background.borrow()
+  }
+
+  /// Registers the observer of a module that is not a plugin under `name`.
+  pub fn observe<O: LifecycleObserver>(&self, name: &str, observer: O) {
+    self.observe_arc(name, Arc::new(observer));
+  }
+
+  pub(crate) fn observe_arc(&self, name: &str, observer: Arc<dyn LifecycleObserver>) {
+    self
+      .observers
+      .write()
+      .unwrap()
+      .push((name.to_owned(), observer));
+  }
+
+  /// Removes the observers registered under `name`. Returns whether there was one.
+  pub fn unobserve(&self, name: &str) -> bool {
+    let mut observers = self.observers.write().unwrap();
+    let len = observers.len();
+    observers.retain(|(observer, _)| observer != name);
+    observers.len() != len
+  }
+
+  /// Returns whether the app wasn't in the background already.
+  pub(crate) fn set_background(&self, background: bool) -> bool {
+    self.background.send_replace(background) != background
+  }
+
+  /// Resolves once the app is in the foreground.
+  pub(crate) async fn wait_foreground(&self) {
+    let mut background = self.background.subscribe();
+    // The sender lives as long as the dispatcher, which outlives the requests.
+    let _ = background.wait_for(|background| !*background).await;
+  }
+
+  /// Tells every observer about `event`. Returns the first error of the observers that failed
+  /// to flush, once all of them ran.
+  pub(crate) fn notify(&self, event: LifecycleEvent) -> Result<(), DispatchError> {
+    // The observers may register other ones.
+    let observers = self.observers.read().unwrap().clone();
+    let mut result = Ok(());
+    for (name, observer) in observers {
+      if event == LifecycleEvent::LowMemory {
+        observer.trim();
+      }
+      observer.on_lifecycle(event);
+      if event == LifecycleEvent::EnterBackground {
+        if let Err(err) = observer.flush() {
+          tracing::error!("[lifecycle]: {} failed to flush: {}", name, err);
+          if result.is_ok() {
+            result = Err(err);
+          }
+        }
+      }
+    }
+    result
+  }
+}
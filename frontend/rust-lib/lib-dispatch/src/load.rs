@@ -0,0 +1,300 @@
+//! Fires a configurable mix of events at the dispatcher and reports the latency percentiles and
+//! the errors of each event, to compare the performance of the core before and after a change.
+//!
+//! The same seed always picks the same sequence of events, so two runs only differ by how fast
+//! the dispatcher handles them. See the `load_generator` binary for a command line front end.
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, Instant};
+
+use futures::stream::{FuturesUnordered, StreamExt};
+
+use crate::dispatcher::AFPluginDispatcher;
+use crate::module::{AFPluginEvent, AFPluginRequest};
+use crate::response::StatusCode;
+
+type BuildRequest = Box<dyn Fn() -> AFPluginRequest>;
+
+struct WeightedRequest {
+  weight: u32,
+  build: BuildRequest,
+}
+
+/// Sends the requests of the mix until the duration is over, either as fast as the concurrency
+/// allows or at a target rate.
+///
+/// ```ignore
+/// let report = LoadGenerator::new()
+///   .event("empty", 3)
+///   .request(1, || AFPluginRequest::new("echo").payload("hello"))
+///   .rate(2000.0)
+///   .duration(Duration::from_secs(10))
+///   .run(&dispatch)
+///   .await;
+/// println!("{}", report);
+/// ```
+///
+/// It must run on the task that drives the dispatcher, e.g. in a `LocalSet` with the `local_set`
+/// feature.
+pub struct LoadGenerator {
+  mix: Vec<WeightedRequest>,
+  concurrency: usize,
+  rate: Option<f64>,
+  duration: Duration,
+  seed: u64,
+}
+
+impl Default for LoadGenerator {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl LoadGenerator {
+  pub fn new() -> Self {
+    Self {
+      mix: vec![],
+      concurrency: 32,
+      rate: None,
+      duration: Duration::from_secs(5),
+      seed: 0,
+    }
+  }
+
+  /// Adds `event`, without payload, to the mix. It's picked `weight` times out of the sum of the
+  /// weights of the mix.
+  pub fn event<E: Into<AFPluginEvent>>(self, event: E, weight: u32) -> Self {
+    let event = event.into();
+    self.request(weight, move || AFPluginRequest::new(event.clone()))
+  }
+
+  /// Adds the requests built by `build` to the mix, like [LoadGenerator::event].
+  pub fn request<F>(mut self, weight: u32, build: F) -> Self
+  where
+    F: Fn() -> AFPluginRequest + 'static,
+  {
+    self.mix.push(WeightedRequest {
+      weight,
+      build: Box::new(build),
+    });
+    self
+  }
+
+  /// The number of requests in flight at most. 32 by default.
+  pub fn concurrency(mut self, concurrency: usize) -> Self {
+    self.concurrency = concurrency.max(1);
+    self
+  }
+
+  /// Sends `per_second` requests per second instead of as many as the concurrency allows. The
+  /// requests that can't be sent on time, because the concurrency is reached, are sent late and
+  /// the report shows the lower rate.
+  pub fn rate(mut self, per_second: f64) -> Self {
+    self.rate = (per_second > 0.0).then_some(per_second);
+    self
+  }
+
+  /// How long the requests are sent for. 5 seconds by default.
+  pub fn duration(mut self, duration: Duration) -> Self {
+    self.duration = duration;
+    self
+  }
+
+  /// The seed of the picks of the mix. Zero by default.
+  pub fn seed(mut self, seed: u64) -> Self {
+    self.seed = seed;
+    self
+  }
+
+  pub async fn run(self, dispatch: &AFPluginDispatcher) -> LoadReport {
+    assert!(
+      !self.mix.is_empty(),
+      "the load generator has no event to send"
+    );
+    let total_weight = self
+      .mix
+      .iter()
+      .map(|request| request.weight as u64)
+      .sum::<u64>()
+      .max(1);
+    let mut rng = SplitMix64(self.seed);
+    let mut samples = Samples::default();
+    let mut in_flight = FuturesUnordered::new();
+    let mut sent = 0_u64;
+    let started_at = Instant::now();
+
+    while started_at.elapsed() < self.duration {
+      if let Some(rate) = self.rate {
+        let due = started_at + Duration::from_secs_f64(sent as f64 / rate);
+        while Instant::now() < due {
+          tokio::select! {
+            Some(sample) = in_flight.next(), if !in_flight.is_empty() => samples.push(sample),
+            _ = tokio::time::sleep_until(due.into()) => {},
+          }
+        }
+      }
+      while in_flight.len() >= self.concurrency {
+        if let Some(sample) = in_flight.next().await {
+          samples.push(sample);
+        }
+      }
+
+      let request = (self.pick(&mut rng, total_weight).build)();
+      in_flight.push(async move {
+        let event = request.event.clone();
+        let sent_at = Instant::now();
+        let response =
+          AFPluginDispatcher::async_send_with_callback(dispatch, request, |_| Box::pin(async {}))
+            .await;
+        Sample {
+          event,
+          latency: sent_at.elapsed(),
+          success: response.status_code == StatusCode::Ok,
+        }
+      });
+      sent += 1;
+    }
+    while let Some(sample) = in_flight.next().await {
+      samples.push(sample);
+    }
+
+    samples.into_report(started_at.elapsed(), self.rate)
+  }
+
+  fn pick(&self, rng: &mut SplitMix64, total_weight: u64) -> &WeightedRequest {
+    let mut pick = rng.next() % total_weight;
+    for request in &self.mix {
+      if pick < request.weight as u64 {
+        return request;
+      }
+      pick -= request.weight as u64;
+    }
+    &self.mix[self.mix.len() - 1]
+  }
+}
+
+/// splitmix64, like the deterministic runtime, to keep the crate free of a rand dependency.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+  fn next(&mut self) -> u64 {
+    self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = self.0;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+  }
+}
+
+struct Sample {
+  event: AFPluginEvent,
+  latency: Duration,
+  success: bool,
+}
+
+#[derive(Default)]
+struct Samples {
+  by_event: HashMap<AFPluginEvent, (Vec<Duration>, usize)>,
+}
+
+impl Samples {
+  fn push(&mut self, sample: Sample) {
+    let (latencies, errors) = self.by_event.entry(sample.event).or_default();
+    latencies.push(sample.latency);
+    if !sample.success {
+      *errors += 1;
+    }
+  }
+
+  fn into_report(self, elapsed: Duration, target_rate: Option<f64>) -> LoadReport {
+    let mut all = vec![];
+    let mut all_errors = 0;
+    let mut events = BTreeMap::new();
+    for (event, (latencies, errors)) in self.by_event {
+      all.extend_from_slice(&latencies);
+      all_errors += errors;
+      events.insert(
+        event.as_str().to_owned(),
+        LatencySummary::new(latencies, errors),
+      );
+    }
+    LoadReport {
+      elapsed,
+      target_rate,
+      overall: LatencySummary::new(all, all_errors),
+      events,
+    }
+  }
+}
+
+/// The latencies of a set of requests, measured from the time they were sent.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LatencySummary {
+  pub requests: usize,
+  pub errors: usize,
+  pub p50: Duration,
+  pub p90: Duration,
+  pub p99: Duration,
+  pub max: Duration,
+}
+
+impl LatencySummary {
+  fn new(mut latencies: Vec<Duration>, errors: usize) -> Self {
+    if latencies.is_empty() {
+      return Self::default();
+    }
+    latencies.sort();
+    let percentile = |p: f64| latencies[((latencies.len() - 1) as f64 * p) as usize];
+    Self {
+      requests: latencies.len(),
+      errors,
+      p50: percentile(0.5),
+      p90: percentile(0.9),
+      p99: percentile(0.99),
+      max: latencies[latencies.len() - 1],
+    }
+  }
+}
+
+impl Display for LatencySummary {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "{} requests ({} errors), p50: {:?}, p90: {:?}, p99: {:?}, max: {:?}",
+      self.requests, self.errors, self.p50, self.p90, self.p99, self.max
+    )
+  }
+}
+
+/// The outcome of a [LoadGenerator] run.
+#[derive(Debug, Clone)]
+pub struct LoadReport {
+  pub elapsed: Duration,
+  /// The rate the requests were meant to be sent at, if any.
+  pub target_rate: Option<f64>,
+  pub overall: LatencySummary,
+  /// The summary of each event, by name.
+  pub events: BTreeMap<String, LatencySummary>,
+}
+
+impl LoadReport {
+  /// The number of requests handled per second.
+  pub fn throughput(&self) -> f64 {
+    self.overall.requests as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+  }
+}
+
+impl Display for LoadReport {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{:.0} req/s in {:?}", self.throughput(), self.elapsed)?;
+    if let Some(rate) = self.target_rate {
+      write!(f, " (target: {:.0} req/s)", rate)?;
+    }
+    writeln!(f)?;
+    writeln!(f, "all: {}", self.overall)?;
+    for (event, summary) in &self.events {
+      writeln!(f, "  {}: {}", event, summary)?;
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,119 @@
+use std::mem::size_of;
+use std::sync::{Arc, RwLock};
+
+use serde::Serialize;
+
+/// An estimate of the memory held by a subsystem. The reporters count what they own directly,
+/// e.g. the payloads kept in a cache, not the allocator overhead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct MemoryUsage {
+  pub bytes: usize,
+  /// The number of entries, requests or values held by the subsystem.
+  pub items: usize,
+}
+
+impl MemoryUsage {
+  pub fn new(bytes: usize, items: usize) -> Self {
+    Self { bytes, items }
+  }
+
+  /// The usage of `items` values of type `T`, not counting what they own on the heap.
+  pub fn of<T>(items: usize) -> Self {
+    Self::new(items * size_of::<T>(), items)
+  }
+}
+
+/// Estimates the memory held by a subsystem, see [MemoryReporters].
+pub trait MemoryReporter: Send + Sync + 'static {
+  fn memory_usage(&self) -> MemoryUsage;
+}
+
+impl<F> MemoryReporter for F
+where
+  F: Fn() -> MemoryUsage + Send + Sync + 'static,
+{
+  fn memory_usage(&self) -> MemoryUsage {
+    self()
+  }
+}
+
+/// The usage of a subsystem in a [MemoryReport].
+#[derive(Debug, Clone, Serialize)]
+pub struct SubsystemMemory {
+  pub name: String,
+  pub bytes: usize,
+  pub items: usize,
+}
+
+/// What the core is holding, by subsystem, sorted by name.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MemoryReport {
+  pub subsystems: Vec<SubsystemMemory>,
+  pub total_bytes: usize,
+}
+
+impl MemoryReport {
+  pub fn get(&self, name: &str) -> Option<&SubsystemMemory> {
+    self
+      .subsystems
+      .iter()
+      .find(|subsystem| subsystem.name == name)
+  }
+
+  pub fn to_json(&self) -> String {
+    serde_json::to_string(self).unwrap_or_default()
+  }
+}
+
+/// The reporters of the subsystems, by name. The dispatcher registers the ones of its queues and
+/// of the plugins, see [AFPlugin::memory_reporter](crate::prelude::AFPlugin::memory_reporter),
+/// and the other modules can register theirs through
+/// [AFPluginDispatcher::memory](crate::prelude::AFPluginDispatcher::memory).
+#[derive(Default)]
+pub struct MemoryReporters {
+  reporters: RwLock<Vec<(String, Arc<dyn MemoryReporter>)>>,
+}
+
+impl MemoryReporters {
+  /// Registers `reporter` under `name`, replacing the reporter registered under the same name.
+  pub fn register<R: MemoryReporter>(&self, name: &str, reporter: R) {
+    self.register_arc(name, Arc::new(reporter));
+  }
+
+  pub(crate) fn register_arc(&self, name: &str, reporter: Arc<dyn MemoryReporter>) {
+    let mut reporters = self.reporters.write().unwrap();
+    reporters.retain(|(existing, _)| existing != name);
+    reporters.push((name.to_owned(), reporter));
+  }
+
+  /// Returns whether a reporter was registered under `name`.
+  pub fn unregister(&self, name: &str) -> bool {
+    let mut reporters = self.reporters.write().unwrap();
+    let len = reporters.len();
+    reporters.retain(|(existing, _)| existing != name);
+    reporters.len() != len
+  }
+
+  /// Asks every reporter for its usage. The reporters are called outside of the lock, so they can
+  /// take their time.
+  pub fn report(&self) -> MemoryReport {
+    let reporters = self.reporters.read().unwrap().clone();
+    let mut subsystems = reporters
+      .into_iter()
+      .map(|(name, reporter)| {
+        let usage = reporter.memory_usage();
+        SubsystemMemory {
+          name,
+          bytes: usage.bytes,
+          items: usage.items,
+        }
+      })
+      .collect::<Vec<_>>();
+    subsystems.sort_by(|a, b| a.name.cmp(&b.name));
+    let total_bytes = subsystems.iter().map(|subsystem| subsystem.bytes).sum();
+    MemoryReport {
+      subsystems,
+      total_bytes,
+    }
+  }
+}
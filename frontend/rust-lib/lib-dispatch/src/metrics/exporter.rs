@@ -0,0 +1,41 @@
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+use crate::metrics::MetricsRegistry;
+
+/// Serves the metrics of the registry in the Prometheus text format. Every connection accepted
+/// on `addr` gets a snapshot of the metrics regardless of the requested path, which is all a
+/// scraper needs.
+///
+/// It's meant to be used in development builds only. Bind it to a loopback address.
+pub async fn serve_prometheus_metrics(
+  registry: Arc<MetricsRegistry>,
+  addr: SocketAddr,
+) -> io::Result<()> {
+  let listener = TcpListener::bind(addr).await?;
+  tracing::info!("[dispatch]: metrics exporter listening on {}", addr);
+  loop {
+    let (mut stream, _) = listener.accept().await?;
+    let registry = registry.clone();
+    tokio::spawn(async move {
+      // Drain the request line and headers. The content of the request doesn't matter.
+      let mut buf = [0; 1024];
+      let _ = stream.read(&mut buf).await;
+
+      let body = registry.render_prometheus();
+      let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+      );
+      if let Err(err) = stream.write_all(response.as_bytes()).await {
+        tracing::warn!("[dispatch]: write metrics failed: {}", err);
+      }
+      let _ = stream.shutdown().await;
+    });
+  }
+}
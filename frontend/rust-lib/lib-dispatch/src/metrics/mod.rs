@@ -0,0 +1,8 @@
+pub use registry::*;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use exporter::*;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod exporter;
+mod registry;
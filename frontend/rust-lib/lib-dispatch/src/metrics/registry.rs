@@ -0,0 +1,299 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// The number of requests dispatched, labeled by event.
+pub const DISPATCH_REQUESTS_TOTAL: &str = "dispatch_requests_total";
+/// The number of requests that ended with an error response, labeled by event.
+pub const DISPATCH_ERRORS_TOTAL: &str = "dispatch_errors_total";
+/// The number of requests that were sent but haven't started to be handled yet.
+pub const DISPATCH_QUEUED: &str = "dispatch_queued";
+/// The number of requests that are currently being handled.
+pub const DISPATCH_IN_FLIGHT: &str = "dispatch_in_flight";
+/// The time spent handling a request, labeled by event.
+pub const DISPATCH_DURATION_SECONDS: &str = "dispatch_duration_seconds";
+
+/// The number of requests that took longer than the slow handler threshold, labeled by event.
+pub const SLOW_HANDLER_TOTAL: &str = "slow_handler_total";
+/// The `event` label of the requests sent to an event no plugin handles. They share it, so a
+/// caller sending random events can't grow the metrics without bound.
+pub const UNREGISTERED_EVENT_LABEL: &str = "<unregistered>";
+
+/// The time the handlers spent running on the runtime, labeled by module.
+pub const MODULE_CPU_SECONDS: &str = "module_cpu_seconds";
+/// The number of requests queued, labeled by module.
+pub const MODULE_QUEUED: &str = "module_queued";
+/// The number of requests being handled, labeled by module.
+pub const MODULE_IN_FLIGHT: &str = "module_in_flight";
+/// The memory reported by the module, as of its last estimate, labeled by module.
+pub const MODULE_MEMORY_BYTES: &str = "module_memory_bytes";
+/// The number of requests throttled because their module exceeded its quota, labeled by module.
+pub const MODULE_THROTTLED_TOTAL: &str = "module_throttled_total";
+
+/// The upper bounds, in seconds, of the buckets used by [Histogram].
+const DEFAULT_BUCKETS: [f64; 10] = [0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 5.0];
+
+#[derive(Default, Debug)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+  pub fn inc(&self) {
+    self.inc_by(1);
+  }
+
+  pub fn inc_by(&self, value: u64) {
+    self.0.fetch_add(value, Ordering::Relaxed);
+  }
+
+  pub fn get(&self) -> u64 {
+    self.0.load(Ordering::Relaxed)
+  }
+}
+
+#[derive(Default, Debug)]
+pub struct Gauge(AtomicI64);
+
+impl Gauge {
+  pub fn inc(&self) {
+    self.0.fetch_add(1, Ordering::Relaxed);
+  }
+
+  pub fn dec(&self) {
+    self.0.fetch_sub(1, Ordering::Relaxed);
+  }
+
+  pub fn set(&self, value: i64) {
+    self.0.store(value, Ordering::Relaxed);
+  }
+
+  pub fn get(&self) -> i64 {
+    self.0.load(Ordering::Relaxed)
+  }
+}
+
+#[derive(Debug)]
+pub struct Histogram {
+  buckets: Vec<AtomicU64>,
+  count: AtomicU64,
+  sum_micros: AtomicU64,
+}
+
+impl Default for Histogram {
+  fn default() -> Self {
+    Self {
+      buckets: DEFAULT_BUCKETS.iter().map(|_| AtomicU64::new(0)).collect(),
+      count: AtomicU64::new(0),
+      sum_micros: AtomicU64::new(0),
+    }
+  }
+}
+
+impl Histogram {
+  pub fn observe(&self, duration: Duration) {
+    let secs = duration.as_secs_f64();
+    for (bound, bucket) in DEFAULT_BUCKETS.iter().zip(self.buckets.iter()) {
+      if secs <= *bound {
+        bucket.fetch_add(1, Ordering::Relaxed);
+      }
+    }
+    self.count.fetch_add(1, Ordering::Relaxed);
+    self
+      .sum_micros
+      .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+  }
+
+  pub fn count(&self) -> u64 {
+    self.count.load(Ordering::Relaxed)
+  }
+
+  pub fn sum(&self) -> Duration {
+    Duration::from_micros(self.sum_micros.load(Ordering::Relaxed))
+  }
+}
+
+/// Identifies a metric by its name and its labels. The labels are kept sorted so the same set of
+/// labels always maps to the same metric.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct MetricKey {
+  name: String,
+  labels: Vec<(String, String)>,
+}
+
+impl MetricKey {
+  fn new(name: &str, labels: &[(&str, &str)]) -> Self {
+    let mut labels = labels
+      .iter()
+      .map(|(k, v)| (k.to_string(), v.to_string()))
+      .collect::<Vec<_>>();
+    labels.sort();
+    Self {
+      name: name.to_owned(),
+      labels,
+    }
+  }
+
+  fn labels_str(&self, extra: Option<(&str, String)>) -> String {
+    let mut labels = self
+      .labels
+      .iter()
+      .map(|(k, v)| format!("{}=\"{}\"", k, escape_label_value(v)))
+      .collect::<Vec<_>>();
+    if let Some((k, v)) = extra {
+      labels.push(format!("{}=\"{}\"", k, v));
+    }
+    if labels.is_empty() {
+      String::new()
+    } else {
+      format!("{{{}}}", labels.join(","))
+    }
+  }
+}
+
+fn escape_label_value(value: &str) -> String {
+  value
+    .replace('\\', "\\\\")
+    .replace('"', "\\\"")
+    .replace('\n', "\\n")
+}
+
+/// Holds the metrics recorded by the dispatcher and the plugins.
+///
+/// The metrics are created on first use and live as long as the registry. Use
+/// [MetricsRegistry::render_prometheus] to export them in the Prometheus text format.
+#[derive(Default, Debug)]
+pub struct MetricsRegistry {
+  counters: RwLock<BTreeMap<MetricKey, Arc<Counter>>>,
+  gauges: RwLock<BTreeMap<MetricKey, Arc<Gauge>>>,
+  histograms: RwLock<BTreeMap<MetricKey, Arc<Histogram>>>,
+}
+
+impl MetricsRegistry {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn counter(&self, name: &str, labels: &[(&str, &str)]) -> Arc<Counter> {
+    get_or_create(&self.counters, MetricKey::new(name, labels))
+  }
+
+  pub fn gauge(&self, name: &str, labels: &[(&str, &str)]) -> Arc<Gauge> {
+    get_or_create(&self.gauges, MetricKey::new(name, labels))
+  }
+
+  pub fn histogram(&self, name: &str, labels: &[(&str, &str)]) -> Arc<Histogram> {
+    get_or_create(&self.histograms, MetricKey::new(name, labels))
+  }
+
+  /// The sum of the counters named `name`, whatever their labels.
+  pub fn counter_total(&self, name: &str) -> u64 {
+    match self.counters.read() {
+      Ok(counters) => counters
+        .iter()
+        .filter(|(key, _)| key.name == name)
+        .map(|(_, counter)| counter.get())
+        .sum(),
+      Err(_) => 0,
+    }
+  }
+
+  /// The histograms named `name`, by the value of their `label`. The histograms without the
+  /// label are left out.
+  pub fn histograms_by(&self, name: &str, label: &str) -> Vec<(String, Arc<Histogram>)> {
+    match self.histograms.read() {
+      Ok(histograms) => histograms
+        .iter()
+        .filter(|(key, _)| key.name == name)
+        .filter_map(|(key, histogram)| {
+          let (_, value) = key.labels.iter().find(|(k, _)| k == label)?;
+          Some((value.clone(), histogram.clone()))
+        })
+        .collect(),
+      Err(_) => vec![],
+    }
+  }
+
+  pub fn render_prometheus(&self) -> String {
+    let mut output = String::new();
+    let mut last_name = None;
+    if let Ok(counters) = self.counters.read() {
+      for (key, counter) in counters.iter() {
+        write_type(&mut output, &mut last_name, &key.name, "counter");
+        let _ = writeln!(
+          output,
+          "{}{} {}",
+          key.name,
+          key.labels_str(None),
+          counter.get()
+        );
+      }
+    }
+    if let Ok(gauges) = self.gauges.read() {
+      for (key, gauge) in gauges.iter() {
+        write_type(&mut output, &mut last_name, &key.name, "gauge");
+        let _ = writeln!(
+          output,
+          "{}{} {}",
+          key.name,
+          key.labels_str(None),
+          gauge.get()
+        );
+      }
+    }
+    if let Ok(histograms) = self.histograms.read() {
+      for (key, histogram) in histograms.iter() {
+        write_type(&mut output, &mut last_name, &key.name, "histogram");
+        for (bound, bucket) in DEFAULT_BUCKETS.iter().zip(histogram.buckets.iter()) {
+          let _ = writeln!(
+            output,
+            "{}_bucket{} {}",
+            key.name,
+            key.labels_str(Some(("le", bound.to_string()))),
+            bucket.load(Ordering::Relaxed)
+          );
+        }
+        let _ = writeln!(
+          output,
+          "{}_bucket{} {}",
+          key.name,
+          key.labels_str(Some(("le", "+Inf".to_string()))),
+          histogram.count()
+        );
+        let _ = writeln!(
+          output,
+          "{}_sum{} {}",
+          key.name,
+          key.labels_str(None),
+          histogram.sum().as_secs_f64()
+        );
+        let _ = writeln!(
+          output,
+          "{}_count{} {}",
+          key.name,
+          key.labels_str(None),
+          histogram.count()
+        );
+      }
+    }
+    output
+  }
+}
+
+fn get_or_create<T: Default>(map: &RwLock<BTreeMap<MetricKey, Arc<T>>>, key: MetricKey) -> Arc<T> {
+  if let Some(metric) = map.read().ok().and_then(|map| map.get(&key).cloned()) {
+    return metric;
+  }
+
+  match map.write() {
+    Ok(mut map) => map.entry(key).or_default().clone(),
+    Err(_) => Arc::new(T::default()),
+  }
+}
+
+fn write_type(output: &mut String, last_name: &mut Option<String>, name: &str, ty: &str) {
+  if last_name.as_deref() != Some(name) {
+    let _ = writeln!(output, "# TYPE {} {}", name, ty);
+    *last_name = Some(name.to_owned());
+  }
+}
@@ -0,0 +1,130 @@
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::capability::Capabilities;
+use crate::encoding::ContentType;
+use crate::errors::{DispatchError, Error};
+use crate::middleware::AFPluginMiddleware;
+use crate::module::AFPluginRequest;
+use crate::response::{AFPluginEventResponse, ErrorOrigin, ResponseBuilder};
+
+/// The metadata naming the network transport a request arrived over, e.g. `http`. The bridges
+/// set it, the requests sent by the app itself don't carry it. The bridges also grant their
+/// requests no [Capabilities], until the [AuthMiddleware] grants them the ones of their caller.
+pub const TRANSPORT_METADATA: &str = "transport";
+
+/// The metadata carrying the session token of a request, with or without the `Bearer ` prefix.
+pub const AUTHORIZATION_METADATA: &str = "authorization";
+
+/// The metadata carrying the signature of a request, see the `security` module of the
+/// `request_signing` feature.
+pub const SIGNATURE_METADATA: &str = "signature";
+
+/// Who sent a request, attached to the authenticated requests. The handlers read it with the
+/// `Extension<Identity>` extractor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+  pub user_id: String,
+  /// Granted to the requests of the identity, see [Capabilities]. `None` grants them all.
+  pub capabilities: Option<Capabilities>,
+}
+
+/// Resolves the session tokens into the identity of their user, implemented by the user module.
+pub trait SessionStore: Send + Sync + 'static {
+  /// `None` if the token is unknown, expired or revoked.
+  fn verify(&self, token: &str) -> Option<Identity>;
+}
+
+/// Authenticates the requests arriving over a network transport, the ones carrying the
+/// [TRANSPORT_METADATA]: their token is verified against the [SessionStore] and the resolved
+/// [Identity] is attached to the request, whose capabilities become the ones of the identity.
+/// The requests without a valid token are rejected with an [Unauthorized] error, unless their
+/// event allows anonymous callers, like signing in.
+pub struct AuthMiddleware {
+  sessions: Arc<dyn SessionStore>,
+  anonymous_events: HashSet<String>,
+}
+
+impl AuthMiddleware {
+  pub fn new(sessions: Arc<dyn SessionStore>) -> Self {
+    Self {
+      sessions,
+      anonymous_events: HashSet::new(),
+    }
+  }
+
+  /// Lets the requests of `event` through without a token.
+  pub fn allow_anonymous<E: ToString>(mut self, event: E) -> Self {
+    self.anonymous_events.insert(event.to_string());
+    self
+  }
+
+  fn authenticate(&self, request: &AFPluginRequest) -> Result<Option<Identity>, Unauthorized> {
+    let unauthorized = |reason: &str| Unauthorized {
+      event: request.event.as_str().to_owned(),
+      reason: reason.to_owned(),
+    };
+    let token = match request.metadata.get(AUTHORIZATION_METADATA) {
+      Some(token) => token.strip_prefix("Bearer ").unwrap_or(token).trim(),
+      None if self.anonymous_events.contains(request.event.as_str()) => return Ok(None),
+      None => return Err(unauthorized("missing token")),
+    };
+    match self.sessions.verify(token) {
+      Some(identity) => Ok(Some(identity)),
+      None if self.anonymous_events.contains(request.event.as_str()) => Ok(None),
+      None => Err(unauthorized("invalid token")),
+    }
+  }
+}
+
+impl AFPluginMiddleware for AuthMiddleware {
+  fn on_request(&self, request: &mut AFPluginRequest) -> Result<(), DispatchError> {
+    if !request.metadata.contains_key(TRANSPORT_METADATA) {
+      return Ok(());
+    }
+    if let Some(identity) = self.authenticate(request)? {
+      request.capabilities = identity.capabilities.clone();
+      request.extensions.insert(identity);
+    }
+    Ok(())
+  }
+}
+
+/// The error of the network requests rejected by the [AuthMiddleware]. The response carries it
+/// as JSON, read it back with [Unauthorized::from_response].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Unauthorized {
+  pub event: String,
+  pub reason: String,
+}
+
+impl Unauthorized {
+  pub fn from_response(response: &AFPluginEventResponse) -> Option<Self> {
+    if response.error_origin != Some(ErrorOrigin::Dispatcher)
+      || response.content_type != Some(ContentType::Json)
+    {
+      return None;
+    }
+    serde_json::from_slice(response.payload.as_ref()).ok()
+  }
+}
+
+impl Display for Unauthorized {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "Unauthorized: {}, {}", self.event, self.reason)
+  }
+}
+
+impl Error for Unauthorized {
+  fn as_response(&self) -> AFPluginEventResponse {
+    let data = serde_json::to_vec(self).unwrap_or_else(|_| self.to_string().into_bytes());
+    ResponseBuilder::Err()
+      .data(data)
+      .content_type(ContentType::Json)
+      .error_origin(ErrorOrigin::Dispatcher)
+      .build()
+  }
+}
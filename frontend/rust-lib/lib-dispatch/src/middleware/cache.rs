@@ -0,0 +1,209 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::clock::{Clock, SystemClock};
+use crate::errors::DispatchError;
+use crate::memory::{MemoryReporter, MemoryUsage};
+use crate::middleware::AFPluginMiddleware;
+use crate::module::AFPluginRequest;
+use crate::response::{AFPluginEventResponse, StatusCode};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+  event: String,
+  payload_hash: u64,
+  version: Option<u32>,
+}
+
+impl CacheKey {
+  fn new(request: &AFPluginRequest) -> Self {
+    let mut hasher = DefaultHasher::new();
+    request.payload.as_ref().hash(&mut hasher);
+    Self {
+      event: request.event.as_str().to_owned(),
+      payload_hash: hasher.finish(),
+      version: request.version,
+    }
+  }
+}
+
+struct CacheEntry {
+  response: AFPluginEventResponse,
+  expires_at: Instant,
+}
+
+/// Attached to the requests answered from the cache, so their responses are not stored again.
+#[derive(Clone)]
+struct CacheHit;
+
+/// The invalidation generation the request started in, see [ResponseCache::on_response].
+#[derive(Clone)]
+struct CacheGeneration(u64);
+
+#[derive(Default)]
+struct CacheState {
+  entries: Mutex<HashMap<CacheKey, CacheEntry>>,
+  /// Bumped on every invalidation.
+  generation: AtomicU64,
+}
+
+/// Drops the responses cached by a [ResponseCache]. The modules call it when the data read by a
+/// cached event changes, usually with the handle registered with
+/// [AFPluginDispatcher::data](crate::prelude::AFPluginDispatcher::data) and read back with the
+/// `AppData<CacheInvalidator>` extractor.
+#[derive(Clone)]
+pub struct CacheInvalidator {
+  state: Arc<CacheState>,
+}
+
+impl CacheInvalidator {
+  /// Drops the cached responses of `event`, as displayed by the
+  /// [AFPluginEvent](crate::prelude::AFPluginEvent).
+  pub fn invalidate<E: ToString>(&self, event: E) {
+    let event = event.to_string();
+    self.state.generation.fetch_add(1, Ordering::AcqRel);
+    self
+      .state
+      .entries
+      .lock()
+      .unwrap()
+      .retain(|key, _| key.event != event);
+  }
+
+  pub fn invalidate_all(&self) {
+    self.state.generation.fetch_add(1, Ordering::AcqRel);
+    self.state.entries.lock().unwrap().clear();
+  }
+}
+
+/// Answers the requests of the pure read events, like reading the workspaces or the settings,
+/// with the response of an identical request handled less than the TTL of the event ago. The
+/// requests are identical when they have the same event, payload and version.
+///
+/// Only the events registered with [ResponseCache::cache] are cached, and only their successful
+/// responses. The responses are dropped when their TTL expires or when the [CacheInvalidator]
+/// is called. A response produced while an invalidation happened is not cached, it may have read
+/// the data from before the change.
+pub struct ResponseCache {
+  ttls: HashMap<String, Duration>,
+  state: Arc<CacheState>,
+  clock: Arc<dyn Clock>,
+}
+
+impl Default for ResponseCache {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl ResponseCache {
+  pub fn new() -> Self {
+    Self {
+      ttls: HashMap::new(),
+      state: Arc::new(CacheState::default()),
+      clock: Arc::new(SystemClock),
+    }
+  }
+
+  /// Caches the responses of `event` for `ttl`.
+  pub fn cache<E: ToString>(mut self, event: E, ttl: Duration) -> Self {
+    self.ttls.insert(event.to_string(), ttl);
+    self
+  }
+
+  /// Expires the responses according to `clock` instead of the [SystemClock].
+  pub fn with_clock<C: Clock>(mut self, clock: C) -> Self {
+    self.clock = Arc::new(clock);
+    self
+  }
+
+  pub fn invalidator(&self) -> CacheInvalidator {
+    CacheInvalidator {
+      state: self.state.clone(),
+    }
+  }
+
+  /// The number of cached responses, including the expired ones that were not looked up since.
+  pub fn len(&self) -> usize {
+    self.state.entries.lock().unwrap().len()
+  }
+
+  /// Reports the memory held by the cached responses, see
+  /// [MemoryReporters](crate::memory::MemoryReporters).
+  pub fn memory_reporter(&self) -> impl MemoryReporter {
+    let state = self.state.clone();
+    move || {
+      let entries = state.entries.lock().unwrap();
+      let bytes = entries
+        .iter()
+        .map(|(key, entry)| key.event.len() + entry.response.payload.as_ref().len())
+        .sum::<usize>()
+        + entries.capacity() * std::mem::size_of::<(CacheKey, CacheEntry)>();
+      MemoryUsage::new(bytes, entries.len())
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+}
+
+impl AFPluginMiddleware for ResponseCache {
+  fn on_request(&self, request: &mut AFPluginRequest) -> Result<(), DispatchError> {
+    if self.ttls.contains_key(request.event.as_str()) {
+      let generation = self.state.generation.load(Ordering::Acquire);
+      request.extensions.insert(CacheGeneration(generation));
+    }
+    Ok(())
+  }
+
+  fn respond(&self, request: &AFPluginRequest) -> Option<AFPluginEventResponse> {
+    if !self.ttls.contains_key(request.event.as_str()) {
+      return None;
+    }
+
+    let key = CacheKey::new(request);
+    let mut entries = self.state.entries.lock().unwrap();
+    match entries.get(&key) {
+      Some(entry) if entry.expires_at > self.clock.now() => {
+        request.extensions.insert(CacheHit);
+        Some(entry.response.clone())
+      },
+      Some(_) => {
+        entries.remove(&key);
+        None
+      },
+      None => None,
+    }
+  }
+
+  fn on_response(&self, request: &AFPluginRequest, response: &mut AFPluginEventResponse) {
+    let ttl = match self.ttls.get(request.event.as_str()) {
+      Some(ttl) => *ttl,
+      None => return,
+    };
+    if response.status_code != StatusCode::Ok || request.extensions.contains::<CacheHit>() {
+      return;
+    }
+    let generation = self.state.generation.load(Ordering::Acquire);
+    match request.extensions.get::<CacheGeneration>() {
+      Some(CacheGeneration(started_in)) if started_in == generation => {},
+      _ => return,
+    }
+
+    let entry = CacheEntry {
+      response: response.clone(),
+      expires_at: self.clock.now() + ttl,
+    };
+    self
+      .state
+      .entries
+      .lock()
+      .unwrap()
+      .insert(CacheKey::new(request), entry);
+  }
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::middleware::AFPluginMiddleware;
+use crate::module::AFPluginRequest;
+use crate::response::{AFPluginEventResponse, StatusCode};
+
+/// How often the [LogMiddleware] logs the requests of an event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogSampling {
+  /// Log every request.
+  All,
+  /// Log the first request and then one request out of `n`. The failures are always logged.
+  OneIn(u64),
+  /// Only log the requests that ended with an error response.
+  FailuresOnly,
+  /// Never log the requests.
+  Off,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum LogFormat {
+  /// A human readable line.
+  Text = 0,
+  /// One JSON object per request, with the `event`, `id`, `duration_ms`, `status` and
+  /// `error_code` fields. Suitable for piping into analysis tools.
+  Json = 1,
+}
+
+/// Switches the [LogFormat] of a [LogMiddleware] while the dispatcher is running.
+#[derive(Debug, Clone)]
+pub struct LogFormatHandle(Arc<AtomicU8>);
+
+impl LogFormatHandle {
+  pub fn set(&self, format: LogFormat) {
+    self.0.store(format as u8, Ordering::Relaxed);
+  }
+
+  pub fn get(&self) -> LogFormat {
+    match self.0.load(Ordering::Relaxed) {
+      1 => LogFormat::Json,
+      _ => LogFormat::Text,
+    }
+  }
+}
+
+type ErrorCodeReader = Box<dyn Fn(&AFPluginEventResponse) -> Option<i64> + Send + Sync>;
+
+/// Logs the outcome of every request, sampled according to the [LogSampling] configured for its
+/// event. Use it to keep high-frequency events, like the ones sent on each keystroke, from
+/// flooding the logs.
+pub struct LogMiddleware {
+  default_sampling: LogSampling,
+  samplings: HashMap<String, LogSampling>,
+  counts: Mutex<HashMap<String, u64>>,
+  format: LogFormatHandle,
+  error_code: Option<ErrorCodeReader>,
+}
+
+impl Default for LogMiddleware {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl LogMiddleware {
+  pub fn new() -> Self {
+    Self {
+      default_sampling: LogSampling::All,
+      samplings: HashMap::new(),
+      counts: Mutex::new(HashMap::new()),
+      format: LogFormatHandle(Arc::new(AtomicU8::new(LogFormat::Text as u8))),
+      error_code: None,
+    }
+  }
+
+  pub fn with_format(self, format: LogFormat) -> Self {
+    self.format.set(format);
+    self
+  }
+
+  /// Returns the handle used to switch the format after the middleware was registered.
+  pub fn format_handle(&self) -> LogFormatHandle {
+    self.format.clone()
+  }
+
+  /// Reads the error code of the failed responses. The dispatcher doesn't know how the plugins
+  /// encode their errors, so the `error_code` field is empty without it.
+  pub fn with_error_code<F>(mut self, f: F) -> Self
+  where
+    F: Fn(&AFPluginEventResponse) -> Option<i64> + Send + Sync + 'static,
+  {
+    self.error_code = Some(Box::new(f));
+    self
+  }
+
+  /// The sampling of the events that have no sampling of their own. Defaults to
+  /// [LogSampling::All].
+  pub fn with_default_sampling(mut self, sampling: LogSampling) -> Self {
+    self.default_sampling = sampling;
+    self
+  }
+
+  /// Sets the sampling of `event`, as displayed by the [AFPluginEvent](crate::prelude::AFPluginEvent).
+  pub fn with_sampling<E: ToString>(mut self, event: E, sampling: LogSampling) -> Self {
+    self.samplings.insert(event.to_string(), sampling);
+    self
+  }
+
+  pub fn sampling(&self, event: &str) -> LogSampling {
+    self
+      .samplings
+      .get(event)
+      .copied()
+      .unwrap_or(self.default_sampling)
+  }
+
+  fn should_log(&self, event: &str, success: bool) -> bool {
+    match self.sampling(event) {
+      LogSampling::All => true,
+      LogSampling::FailuresOnly => !success,
+      LogSampling::Off => false,
+      LogSampling::OneIn(n) => {
+        let mut counts = match self.counts.lock() {
+          Ok(counts) => counts,
+          Err(_) => return !success,
+        };
+        let count = counts.entry(event.to_owned()).or_default();
+        let sampled = *count % n.max(1) == 0;
+        *count += 1;
+        sampled || !success
+      },
+    }
+  }
+}
+
+impl AFPluginMiddleware for LogMiddleware {
+  fn on_response(&self, request: &AFPluginRequest, response: &mut AFPluginEventResponse) {
+    let event = request.event.as_str();
+    let success = response.status_code == StatusCode::Ok;
+    if !self.should_log(event, success) {
+      return;
+    }
+
+    let elapsed = request.created_at.elapsed();
+    if self.format.get() == LogFormat::Json {
+      let error_code = if success {
+        None
+      } else {
+        self.error_code.as_ref().and_then(|f| f(response))
+      };
+      let line = serde_json::json!({
+        "event": event,
+        "id": request.id,
+        "duration_ms": elapsed.as_secs_f64() * 1000.0,
+        "status": if success { "ok" } else { "error" },
+        "error_code": error_code,
+      });
+      tracing::info!("{}", line);
+      return;
+    }
+
+    if success {
+      tracing::info!(
+        event,
+        id = %request.id,
+        elapsed_ms = elapsed.as_millis() as u64,
+        "[dispatch]: {} done",
+        event
+      );
+    } else {
+      tracing::warn!(
+        event,
+        id = %request.id,
+        elapsed_ms = elapsed.as_millis() as u64,
+        "[dispatch]: {} failed",
+        event
+      );
+    }
+  }
+}
@@ -0,0 +1,247 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::encoding::ContentType;
+use crate::errors::Error;
+use crate::module::AFPluginRequest;
+use crate::response::{AFPluginEventResponse, ErrorOrigin, ResponseBuilder, StatusCode};
+
+/// The metadata of the responses of the deprecated events, holding their deprecation notice. The
+/// dispatcher sets it on the responses of the requests sent to an alias, see `AFPlugin::alias`.
+pub const DEPRECATION_METADATA: &str = "deprecation";
+
+/// Rewrites the responses once the middlewares are done with them, right before they're handed
+/// to the sender, whether they come from a handler, a middleware or the dispatcher itself. The
+/// response recorded by the middlewares, e.g. a cached one, is the one before the mapping.
+///
+/// The mappers are registered with `AFPluginDispatcher::with_response_mapper` and run in the
+/// order they were registered. A closure taking the request and the response is a mapper.
+pub trait ResponseMapper: Send + Sync + 'static {
+  /// Called with the request, as seen by the plugin, and its response.
+  fn map(
+    &self,
+    request: &AFPluginRequest,
+    response: AFPluginEventResponse,
+  ) -> AFPluginEventResponse;
+}
+
+impl<F> ResponseMapper for F
+where
+  F: Fn(&AFPluginRequest, AFPluginEventResponse) -> AFPluginEventResponse + Send + Sync + 'static,
+{
+  fn map(
+    &self,
+    request: &AFPluginRequest,
+    response: AFPluginEventResponse,
+  ) -> AFPluginEventResponse {
+    self(request, response)
+  }
+}
+
+pub(crate) type ResponseMappers = Arc<Vec<Arc<dyn ResponseMapper>>>;
+
+pub(crate) fn map_response(
+  mappers: &[Arc<dyn ResponseMapper>],
+  request: &AFPluginRequest,
+  response: AFPluginEventResponse,
+) -> AFPluginEventResponse {
+  mappers
+    .iter()
+    .fold(response, |response, mapper| mapper.map(request, response))
+}
+
+/// Removes the internal fields from the JSON objects returned by the events, e.g. the ids only
+/// the core uses. Only the top level fields of the object, or of the objects of an array, are
+/// removed. The other encodings are left as they are.
+#[derive(Default)]
+pub struct StripFields {
+  fields: HashMap<String, HashSet<String>>,
+}
+
+impl StripFields {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn strip<E: ToString, F: ToString>(mut self, event: E, field: F) -> Self {
+    self
+      .fields
+      .entry(event.to_string())
+      .or_default()
+      .insert(field.to_string());
+    self
+  }
+}
+
+impl ResponseMapper for StripFields {
+  fn map(
+    &self,
+    request: &AFPluginRequest,
+    mut response: AFPluginEventResponse,
+  ) -> AFPluginEventResponse {
+    let fields = match self.fields.get(request.event.as_str()) {
+      Some(fields) if response.content_type == Some(ContentType::Json) => fields,
+      _ => return response,
+    };
+    let mut value = match serde_json::from_slice::<serde_json::Value>(response.payload.as_ref()) {
+      Ok(value) => value,
+      Err(_) => return response,
+    };
+    let strip = |value: &mut serde_json::Value| {
+      if let Some(object) = value.as_object_mut() {
+        object.retain(|key, _| !fields.contains(key));
+      }
+    };
+    match &mut value {
+      serde_json::Value::Array(values) => values.iter_mut().for_each(strip),
+      value => strip(value),
+    }
+    if let Ok(payload) = serde_json::to_vec(&value) {
+      response.payload = payload.into();
+    }
+    response
+  }
+}
+
+/// Marks the responses of the deprecated events with their deprecation notice, in the
+/// [DEPRECATION_METADATA], so the clients learn about it before the event is removed.
+#[derive(Default)]
+pub struct Deprecations {
+  notices: HashMap<String, String>,
+}
+
+impl Deprecations {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// `notice` tells the callers what to use instead, e.g. `use GetViewV2`.
+  pub fn deprecate<E: ToString, N: ToString>(mut self, event: E, notice: N) -> Self {
+    self.notices.insert(event.to_string(), notice.to_string());
+    self
+  }
+}
+
+impl ResponseMapper for Deprecations {
+  fn map(
+    &self,
+    request: &AFPluginRequest,
+    mut response: AFPluginEventResponse,
+  ) -> AFPluginEventResponse {
+    if let Some(notice) = self.notices.get(request.event.as_str()) {
+      response
+        .metadata
+        .insert(DEPRECATION_METADATA.to_owned(), notice.clone());
+    }
+    response
+  }
+}
+
+/// Replaces the successful responses whose payload exceeds the limit of their event with a
+/// [ResponseTooLarge] error, e.g. to keep a runaway query from flooding the Dart side.
+pub struct SizeLimit {
+  default_limit: Option<usize>,
+  limits: HashMap<String, usize>,
+}
+
+impl SizeLimit {
+  /// Limits the responses of all the events to `max_bytes`.
+  pub fn new(max_bytes: usize) -> Self {
+    Self {
+      default_limit: Some(max_bytes),
+      limits: HashMap::new(),
+    }
+  }
+
+  /// Only limits the events given to [SizeLimit::limit].
+  pub fn per_event() -> Self {
+    Self {
+      default_limit: None,
+      limits: HashMap::new(),
+    }
+  }
+
+  /// Overrides the limit of `event`.
+  pub fn limit<E: ToString>(mut self, event: E, max_bytes: usize) -> Self {
+    self.limits.insert(event.to_string(), max_bytes);
+    self
+  }
+}
+
+impl ResponseMapper for SizeLimit {
+  fn map(
+    &self,
+    request: &AFPluginRequest,
+    response: AFPluginEventResponse,
+  ) -> AFPluginEventResponse {
+    let limit = match self.limits.get(request.event.as_str()) {
+      Some(limit) => *limit,
+      None => match self.default_limit {
+        Some(limit) => limit,
+        None => return response,
+      },
+    };
+    let size = response.payload.as_ref().len();
+    if response.status_code != StatusCode::Ok || size <= limit {
+      return response;
+    }
+    tracing::warn!(
+      "[dispatch]: drop the response of {}, {} bytes over the limit of {}",
+      request.event.as_str(),
+      size,
+      limit
+    );
+    let mut rejected = ResponseTooLarge {
+      event: request.event.as_str().to_owned(),
+      size,
+      limit,
+    }
+    .as_response();
+    rejected.metadata = response.metadata;
+    rejected
+  }
+}
+
+/// The error replacing the responses exceeding their [SizeLimit]. The response carries it as
+/// JSON, read it back with [ResponseTooLarge::from_response].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResponseTooLarge {
+  pub event: String,
+  pub size: usize,
+  pub limit: usize,
+}
+
+impl ResponseTooLarge {
+  pub fn from_response(response: &AFPluginEventResponse) -> Option<Self> {
+    if response.error_origin != Some(ErrorOrigin::Dispatcher)
+      || response.content_type != Some(ContentType::Json)
+    {
+      return None;
+    }
+    serde_json::from_slice(response.payload.as_ref()).ok()
+  }
+}
+
+impl Display for ResponseTooLarge {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "ResponseTooLarge: {}, {} bytes over the limit of {}",
+      self.event, self.size, self.limit
+    )
+  }
+}
+
+impl Error for ResponseTooLarge {
+  fn as_response(&self) -> AFPluginEventResponse {
+    let data = serde_json::to_vec(self).unwrap_or_else(|_| self.to_string().into_bytes());
+    ResponseBuilder::Err()
+      .data(data)
+      .content_type(ContentType::Json)
+      .error_origin(ErrorOrigin::Dispatcher)
+      .build()
+  }
+}
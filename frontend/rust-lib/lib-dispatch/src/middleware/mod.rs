@@ -0,0 +1,75 @@
+pub use auth::*;
+pub use cache::{CacheInvalidator, ResponseCache};
+pub use log::*;
+pub use mapping::*;
+#[cfg(feature = "request_signing")]
+pub use signature::*;
+
+mod auth;
+mod cache;
+mod log;
+mod mapping;
+#[cfg(feature = "request_signing")]
+mod signature;
+
+use std::sync::Arc;
+
+use crate::errors::DispatchError;
+use crate::module::AFPluginRequest;
+use crate::response::AFPluginEventResponse;
+
+/// A middleware runs around every request handled by the dispatcher, including the requests
+/// whose event has no handler.
+///
+/// The middlewares are called in the order they were registered before the request is handled,
+/// and in the reverse order once the response is ready. The [ResponseMapper]s run after them.
+pub trait AFPluginMiddleware: Send + Sync + 'static {
+  /// Called before the request is passed to its plugin. Returning an error rejects the request:
+  /// the plugin is not called and the error is converted into the response.
+  fn on_request(&self, _request: &mut AFPluginRequest) -> Result<(), DispatchError> {
+    Ok(())
+  }
+
+  /// Called once all the middlewares accepted the request. Returning a response answers the
+  /// request without calling its plugin, e.g. with a cached response. The response middlewares
+  /// still run.
+  fn respond(&self, _request: &AFPluginRequest) -> Option<AFPluginEventResponse> {
+    None
+  }
+
+  /// Called with the request, as seen by the plugin, and its response.
+  fn on_response(&self, _request: &AFPluginRequest, _response: &mut AFPluginEventResponse) {}
+}
+
+pub(crate) type AFPluginMiddlewares = Arc<Vec<Arc<dyn AFPluginMiddleware>>>;
+
+pub(crate) fn run_request_middlewares(
+  middlewares: &[Arc<dyn AFPluginMiddleware>],
+  request: &mut AFPluginRequest,
+) -> Result<(), DispatchError> {
+  for middleware in middlewares {
+    middleware.on_request(request)?;
+  }
+  Ok(())
+}
+
+/// The response of the first middleware that answers the request, see
+/// [AFPluginMiddleware::respond].
+pub(crate) fn middleware_response(
+  middlewares: &[Arc<dyn AFPluginMiddleware>],
+  request: &AFPluginRequest,
+) -> Option<AFPluginEventResponse> {
+  middlewares
+    .iter()
+    .find_map(|middleware| middleware.respond(request))
+}
+
+pub(crate) fn run_response_middlewares(
+  middlewares: &[Arc<dyn AFPluginMiddleware>],
+  request: &AFPluginRequest,
+  response: &mut AFPluginEventResponse,
+) {
+  for middleware in middlewares.iter().rev() {
+    middleware.on_response(request, response);
+  }
+}
@@ -0,0 +1,69 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::errors::DispatchError;
+use crate::middleware::{AFPluginMiddleware, Unauthorized, SIGNATURE_METADATA, TRANSPORT_METADATA};
+use crate::module::AFPluginRequest;
+use crate::security::{KeyRing, SignatureError};
+
+/// The id of the key a request was signed with, attached to the verified requests. The handlers
+/// read it with the `Extension<SignedBy>` extractor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedBy(pub String);
+
+/// Checks the signature of the requests against a [KeyRing], see the
+/// [security](crate::security) module. The requests carrying a signature are rejected with an
+/// [Unauthorized] error if it's not valid, and so are the requests arriving over a bridge, the
+/// ones carrying the [TRANSPORT_METADATA], without one, unless their event allows it.
+///
+/// That includes the WebSocket and local socket requests, which carry their signature in their
+/// frame, see [serve_framed](crate::bridge::serve_framed): the token of a WebSocket connection
+/// doesn't replace the signatures of its requests.
+pub struct SignatureMiddleware {
+  keys: Arc<KeyRing>,
+  unsigned_events: HashSet<String>,
+}
+
+impl SignatureMiddleware {
+  pub fn new(keys: Arc<KeyRing>) -> Self {
+    Self {
+      keys,
+      unsigned_events: HashSet::new(),
+    }
+  }
+
+  /// Lets the network requests of `event` through without a signature.
+  pub fn allow_unsigned<E: ToString>(mut self, event: E) -> Self {
+    self.unsigned_events.insert(event.to_string());
+    self
+  }
+}
+
+impl AFPluginMiddleware for SignatureMiddleware {
+  fn on_request(&self, request: &mut AFPluginRequest) -> Result<(), DispatchError> {
+    if !request.metadata.contains_key(SIGNATURE_METADATA)
+      && (!request.metadata.contains_key(TRANSPORT_METADATA)
+        || self.unsigned_events.contains(request.event.as_str()))
+    {
+      return Ok(());
+    }
+    match self.keys.verify(request) {
+      Ok(key_id) => {
+        request.extensions.insert(SignedBy(key_id));
+        Ok(())
+      },
+      Err(err) => {
+        if !matches!(err, SignatureError::Missing) {
+          tracing::warn!("[signature]: rejected {}: {}", request.event.as_str(), err);
+        }
+        Err(
+          Unauthorized {
+            event: request.event.as_str().to_owned(),
+            reason: err.to_string(),
+          }
+          .into(),
+        )
+      },
+    }
+  }
+}
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use std::sync::{Arc, Mutex, RwLock};
+
+use bytes::Bytes;
+
+use crate::module::{AFPluginEvent, AFPluginRequest};
+use crate::request::Payload;
+use crate::response::AFPluginEventResponse;
+
+type MockResponder = Box<dyn Fn(&AFPluginRequest) -> AFPluginEventResponse + Send + Sync>;
+
+/// Stands in for the handler of an event and records the requests it receives.
+pub struct EventMock {
+  responder: MockResponder,
+  calls: Mutex<Vec<AFPluginRequest>>,
+}
+
+impl EventMock {
+  fn new(responder: MockResponder) -> Self {
+    Self {
+      responder,
+      calls: Mutex::new(vec![]),
+    }
+  }
+
+  pub(crate) fn call(&self, request: &AFPluginRequest) -> AFPluginEventResponse {
+    self.calls.lock().unwrap().push(request.clone());
+    (self.responder)(request)
+  }
+
+  /// The requests received so far, oldest first.
+  pub fn calls(&self) -> Vec<AFPluginRequest> {
+    self.calls.lock().unwrap().clone()
+  }
+
+  /// The payloads of the requests received so far, oldest first.
+  pub fn payloads(&self) -> Vec<Bytes> {
+    self
+      .calls
+      .lock()
+      .unwrap()
+      .iter()
+      .map(|request| match &request.payload {
+        Payload::Bytes(bytes) => bytes.clone(),
+        Payload::None => Bytes::new(),
+      })
+      .collect()
+  }
+
+  pub fn call_count(&self) -> usize {
+    self.calls.lock().unwrap().len()
+  }
+}
+
+/// The mocks of a dispatcher. A mocked event is answered by its [EventMock], whether a plugin
+/// handles it or not, so a test can stub out the plugins the code under test depends on. The
+/// middlewares still run around the mocked requests.
+#[derive(Default)]
+pub struct EventMocks {
+  mocks: RwLock<HashMap<AFPluginEvent, Arc<EventMock>>>,
+}
+
+impl EventMocks {
+  /// Answers `event` with a copy of `response`.
+  pub fn mock<E>(&self, event: E, response: AFPluginEventResponse) -> Arc<EventMock>
+  where
+    E: Eq + Hash + Debug + Clone + Display,
+  {
+    self.mock_with(event, move |_| response.clone())
+  }
+
+  /// Answers `event` with the response built by `responder` for each request.
+  pub fn mock_with<E, F>(&self, event: E, responder: F) -> Arc<EventMock>
+  where
+    E: Eq + Hash + Debug + Clone + Display,
+    F: Fn(&AFPluginRequest) -> AFPluginEventResponse + Send + Sync + 'static,
+  {
+    let mock = Arc::new(EventMock::new(Box::new(responder)));
+    self
+      .mocks
+      .write()
+      .unwrap()
+      .insert(event.into(), mock.clone());
+    mock
+  }
+
+  /// Hands `event` back to its handler.
+  pub fn remove<E>(&self, event: E) -> Option<Arc<EventMock>>
+  where
+    E: Eq + Hash + Debug + Clone + Display,
+  {
+    self.mocks.write().unwrap().remove(&event.into())
+  }
+
+  pub fn clear(&self) {
+    self.mocks.write().unwrap().clear();
+  }
+
+  pub fn get(&self, event: &AFPluginEvent) -> Option<Arc<EventMock>> {
+    self.mocks.read().unwrap().get(event).cloned()
+  }
+}
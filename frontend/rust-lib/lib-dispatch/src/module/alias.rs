@@ -0,0 +1,121 @@
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::encoding::ContentType;
+use crate::errors::{DispatchError, Error};
+use crate::module::{AFPluginEvent, AFPluginRequest};
+use crate::response::{AFPluginEventResponse, ErrorOrigin, ResponseBuilder};
+
+/// The former name of an event, kept so the clients built before the event was renamed still
+/// reach its handler, see [AFPlugin::alias](crate::prelude::AFPlugin::alias).
+#[derive(Debug, Clone)]
+pub struct EventAlias {
+  pub alias: AFPluginEvent,
+  pub event: AFPluginEvent,
+  /// The contract version the alias is removed in, see
+  /// [AFPlugin::alias_until](crate::prelude::AFPlugin::alias_until).
+  pub removed_in: Option<u32>,
+  /// Whether the use of the alias was logged already.
+  warned: Arc<AtomicBool>,
+}
+
+impl EventAlias {
+  pub(crate) fn new(alias: AFPluginEvent, event: AFPluginEvent, removed_in: Option<u32>) -> Self {
+    Self {
+      alias,
+      event,
+      removed_in,
+      warned: Arc::new(AtomicBool::new(false)),
+    }
+  }
+
+  /// The deprecation notice of the responses of the requests sent to the alias.
+  pub fn notice(&self) -> String {
+    match self.removed_in {
+      Some(version) => format!(
+        "{} is deprecated and removed in version {}, use {}",
+        self.alias.as_str(),
+        version,
+        self.event.as_str()
+      ),
+      None => format!(
+        "{} is deprecated, use {}",
+        self.alias.as_str(),
+        self.event.as_str()
+      ),
+    }
+  }
+
+  /// Sends `request` to the event the alias stands for and returns the deprecation notice, or
+  /// rejects it with an [EventRemoved] error if it expects a version the alias is removed in.
+  pub(crate) fn resolve(&self, request: &mut AFPluginRequest) -> Result<String, DispatchError> {
+    if let (Some(removed_in), Some(version)) = (self.removed_in, request.version) {
+      if version >= removed_in {
+        return Err(
+          EventRemoved {
+            event: self.alias.as_str().to_owned(),
+            replacement: self.event.as_str().to_owned(),
+            removed_in,
+            version,
+          }
+          .into(),
+        );
+      }
+    }
+    let notice = self.notice();
+    // Once per alias, the clients calling it usually call it again and again.
+    if !self.warned.swap(true, Ordering::Relaxed) {
+      tracing::warn!("[dispatch]: {}", notice);
+    }
+    request.event = self.event.clone();
+    Ok(notice)
+  }
+}
+
+/// The error of the requests sent to an alias by a client expecting a contract version the alias
+/// is removed in. The response carries it as JSON, read it back with
+/// [EventRemoved::from_response].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventRemoved {
+  pub event: String,
+  /// The event to send instead.
+  pub replacement: String,
+  pub removed_in: u32,
+  /// The version of the request.
+  pub version: u32,
+}
+
+impl EventRemoved {
+  pub fn from_response(response: &AFPluginEventResponse) -> Option<Self> {
+    if response.error_origin != Some(ErrorOrigin::Dispatcher)
+      || response.content_type != Some(ContentType::Json)
+    {
+      return None;
+    }
+    serde_json::from_slice(response.payload.as_ref()).ok()
+  }
+}
+
+impl Display for EventRemoved {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "EventRemoved: {} was removed in version {}, use {}",
+      self.event, self.removed_in, self.replacement
+    )
+  }
+}
+
+impl Error for EventRemoved {
+  fn as_response(&self) -> AFPluginEventResponse {
+    let data = serde_json::to_vec(self).unwrap_or_else(|_| self.to_string().into_bytes());
+    ResponseBuilder::Err()
+      .data(data)
+      .content_type(ContentType::Json)
+      .error_origin(ErrorOrigin::Dispatcher)
+      .build()
+  }
+}
@@ -55,6 +55,10 @@ impl AFPluginStateMap {
     self.0.contains_key(&TypeId::of::<T>())
   }
 
+  pub(crate) fn type_ids(&self) -> impl Iterator<Item = &TypeId> {
+    self.0.keys()
+  }
+
   pub fn extend(&mut self, other: AFPluginStateMap) {
     self.0.extend(other.0);
   }
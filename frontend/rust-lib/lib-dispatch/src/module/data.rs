@@ -1,5 +1,8 @@
 use std::{any::type_name, ops::Deref, sync::Arc};
 
+use futures_core::future::BoxFuture;
+
+use crate::module::LazyState;
 use crate::prelude::AFConcurrent;
 use crate::{
   errors::{DispatchError, InternalError},
@@ -7,6 +10,9 @@ use crate::{
   util::ready::{ready, Ready},
 };
 
+/// A state registered with [AFPlugin::state](crate::module::AFPlugin::state). It only resolves
+/// in the handlers of the plugin that registered it, see [Shared] for the states that are meant
+/// to be read by the other plugins.
 pub struct AFPluginState<T: ?Sized + AFConcurrent>(Arc<T>);
 
 impl<T> AFPluginState<T>
@@ -56,18 +62,143 @@ where
   T: ?Sized + Send + Sync + 'static,
 {
   type Error = DispatchError;
-  type Future = Ready<Result<Self, DispatchError>>;
+  type Future = BoxFuture<'static, Result<Self, DispatchError>>;
 
   #[inline]
   fn from_request(req: &AFPluginEventRequest, _: &mut Payload) -> Self::Future {
     if let Some(state) = req.get_state::<AFPluginState<T>>() {
-      ready(Ok(state))
+      Box::pin(ready(Ok(state)))
+    } else if let Some(lazy) = req.get_state::<LazyState<T>>() {
+      Box::pin(async move { lazy.get().await })
     } else {
       let msg = format!(
         "Failed to get the plugin state of type: {}",
         type_name::<T>()
       );
       tracing::error!("{}", msg,);
+      Box::pin(ready(Err(InternalError::Other(msg).into())))
+    }
+  }
+}
+
+/// The state shared by the handlers of all the plugins, like the device info or the app config.
+/// It's registered with [AFPluginDispatcher::data](crate::prelude::AFPluginDispatcher::data),
+/// unlike the [AFPluginState] that is only visible to the handlers of the plugin that registered
+/// it.
+pub struct AppData<T: ?Sized + AFConcurrent>(Arc<T>);
+
+impl<T> AppData<T>
+where
+  T: AFConcurrent,
+{
+  pub fn new(data: T) -> Self {
+    AppData(Arc::new(data))
+  }
+
+  pub fn get_ref(&self) -> &T {
+    self.0.as_ref()
+  }
+}
+
+impl<T> Deref for AppData<T>
+where
+  T: ?Sized + AFConcurrent,
+{
+  type Target = Arc<T>;
+
+  fn deref(&self) -> &Arc<T> {
+    &self.0
+  }
+}
+
+impl<T> Clone for AppData<T>
+where
+  T: ?Sized + AFConcurrent,
+{
+  fn clone(&self) -> AppData<T> {
+    AppData(self.0.clone())
+  }
+}
+
+impl<T> From<Arc<T>> for AppData<T>
+where
+  T: ?Sized + AFConcurrent,
+{
+  fn from(arc: Arc<T>) -> Self {
+    AppData(arc)
+  }
+}
+
+impl<T> FromAFPluginRequest for AppData<T>
+where
+  T: ?Sized + Send + Sync + 'static,
+{
+  type Error = DispatchError;
+  type Future = Ready<Result<Self, DispatchError>>;
+
+  #[inline]
+  fn from_request(req: &AFPluginEventRequest, _: &mut Payload) -> Self::Future {
+    if let Some(data) = req.get_app_data::<AppData<T>>() {
+      ready(Ok(data))
+    } else {
+      let msg = format!("Failed to get the app data of type: {}", type_name::<T>());
+      tracing::error!("{}", msg,);
+      ready(Err(InternalError::Other(msg).into()))
+    }
+  }
+}
+
+/// A state that a plugin shares with the handlers of all the plugins, registered with
+/// [AFPlugin::shared_state](crate::module::AFPlugin::shared_state).
+pub struct Shared<T: ?Sized + AFConcurrent>(Arc<T>);
+
+impl<T> Shared<T>
+where
+  T: AFConcurrent,
+{
+  pub fn new(data: T) -> Self {
+    Shared(Arc::new(data))
+  }
+
+  pub fn get_ref(&self) -> &T {
+    self.0.as_ref()
+  }
+}
+
+impl<T> Deref for Shared<T>
+where
+  T: ?Sized + AFConcurrent,
+{
+  type Target = Arc<T>;
+
+  fn deref(&self) -> &Arc<T> {
+    &self.0
+  }
+}
+
+impl<T> Clone for Shared<T>
+where
+  T: ?Sized + AFConcurrent,
+{
+  fn clone(&self) -> Shared<T> {
+    Shared(self.0.clone())
+  }
+}
+
+impl<T> FromAFPluginRequest for Shared<T>
+where
+  T: ?Sized + Send + Sync + 'static,
+{
+  type Error = DispatchError;
+  type Future = Ready<Result<Self, DispatchError>>;
+
+  #[inline]
+  fn from_request(req: &AFPluginEventRequest, _: &mut Payload) -> Self::Future {
+    if let Some(state) = req.get_app_data::<Shared<T>>() {
+      ready(Ok(state))
+    } else {
+      let msg = format!("No plugin shares a state of type: {}", type_name::<T>());
+      tracing::error!("{}", msg,);
       ready(Err(InternalError::Other(msg).into()))
     }
   }
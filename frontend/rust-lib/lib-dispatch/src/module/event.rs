@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::sync::{OnceLock, RwLock};
+
+/// An event handled by a plugin.
+///
+/// The name of the event is interned the first time it's seen: the events are compared and
+/// hashed by their id, so looking up their handler doesn't touch the string, which is kept for
+/// the logs and the FFI. Interned names are never freed, there is one per event name.
+#[derive(Clone)]
+pub struct AFPluginEvent {
+  id: u32,
+  name: &'static str,
+}
+
+impl AFPluginEvent {
+  pub fn as_str(&self) -> &str {
+    self.name
+  }
+
+  /// The id the name of the event was interned with. It's only stable within the process.
+  pub fn id(&self) -> u32 {
+    self.id
+  }
+
+  fn intern(name: &str) -> Self {
+    let interner = interner();
+    if let Some(event) = interner.read().unwrap().get(name) {
+      return event.clone();
+    }
+
+    let mut interner = interner.write().unwrap();
+    // Another thread may have interned it in the meantime.
+    if let Some(event) = interner.get(name) {
+      return event.clone();
+    }
+    let event = AFPluginEvent {
+      id: interner.len() as u32,
+      name: Box::leak(name.to_owned().into_boxed_str()),
+    };
+    interner.insert(event.name, event.clone());
+    event
+  }
+}
+
+/// The interned events by name.
+fn interner() -> &'static RwLock<HashMap<&'static str, AFPluginEvent>> {
+  static INTERNER: OnceLock<RwLock<HashMap<&'static str, AFPluginEvent>>> = OnceLock::new();
+  INTERNER.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+impl PartialEq for AFPluginEvent {
+  fn eq(&self, other: &Self) -> bool {
+    self.id == other.id
+  }
+}
+
+impl Eq for AFPluginEvent {}
+
+impl Hash for AFPluginEvent {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    self.id.hash(state);
+  }
+}
+
+impl Debug for AFPluginEvent {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_tuple("AFPluginEvent").field(&self.name).finish()
+  }
+}
+
+impl<T: Display + Eq + Hash + Debug + Clone> std::convert::From<T> for AFPluginEvent {
+  fn from(t: T) -> Self {
+    AFPluginEvent::intern(&t.to_string())
+  }
+}
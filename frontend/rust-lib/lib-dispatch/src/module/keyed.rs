@@ -0,0 +1,174 @@
+use std::any::type_name;
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::hash::Hash;
+use std::ops::Deref;
+use std::sync::{Arc, RwLock};
+
+use crate::errors::{DispatchError, InternalError};
+use crate::module::AppData;
+use crate::request::{AFPluginEventRequest, FromAFPluginRequest, Payload};
+use crate::util::ready::{ready, Ready};
+
+type KeyedInit<K, T> = Arc<dyn Fn(&K) -> T + Send + Sync>;
+
+/// The states partitioned by a key, like the user id or the workspace id, so each account keeps
+/// its own entry. The entry of a key is built the first time a handler asks for it, and dropped
+/// with [KeyedStates::evict], usually when the user signs out.
+///
+/// It's registered with [AFPluginDispatcher::data](crate::prelude::AFPluginDispatcher::data) and
+/// the handlers read the entry of the current session with the [KeyedState] extractor. The clones
+/// share the same entries, so the caller can keep one to evict the entries later.
+pub struct KeyedStates<K, T> {
+  entries: Arc<RwLock<HashMap<K, Arc<T>>>>,
+  init: KeyedInit<K, T>,
+}
+
+impl<K, T> Clone for KeyedStates<K, T> {
+  fn clone(&self) -> Self {
+    Self {
+      entries: self.entries.clone(),
+      init: self.init.clone(),
+    }
+  }
+}
+
+impl<K, T> Debug for KeyedStates<K, T>
+where
+  K: Debug,
+{
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("KeyedStates")
+      .field(
+        "keys",
+        &self.entries.read().unwrap().keys().collect::<Vec<_>>(),
+      )
+      .finish_non_exhaustive()
+  }
+}
+
+impl<K, T> KeyedStates<K, T>
+where
+  K: Eq + Hash + Clone,
+{
+  /// `init` builds the entry of a key the first time it's requested.
+  pub fn new<F>(init: F) -> Self
+  where
+    F: Fn(&K) -> T + Send + Sync + 'static,
+  {
+    Self {
+      entries: Arc::new(RwLock::new(HashMap::new())),
+      init: Arc::new(init),
+    }
+  }
+
+  /// Returns the entry of `key`, building it if needed.
+  pub fn get_or_init(&self, key: &K) -> Arc<T> {
+    if let Some(entry) = self.get(key) {
+      return entry;
+    }
+    let mut entries = self.entries.write().unwrap();
+    // Another caller may have built the entry while the lock was released.
+    entries
+      .entry(key.clone())
+      .or_insert_with(|| Arc::new((self.init)(key)))
+      .clone()
+  }
+
+  /// Returns the entry of `key` without building it.
+  pub fn get(&self, key: &K) -> Option<Arc<T>> {
+    self.entries.read().unwrap().get(key).cloned()
+  }
+
+  /// Drops the entry of `key`. The handlers that already extracted it keep their copy until they
+  /// return, the next request builds a new one.
+  pub fn evict(&self, key: &K) -> Option<Arc<T>> {
+    self.entries.write().unwrap().remove(key)
+  }
+
+  pub fn clear(&self) {
+    self.entries.write().unwrap().clear();
+  }
+
+  pub fn contains(&self, key: &K) -> bool {
+    self.entries.read().unwrap().contains_key(key)
+  }
+
+  pub fn keys(&self) -> Vec<K> {
+    self.entries.read().unwrap().keys().cloned().collect()
+  }
+
+  pub fn len(&self) -> usize {
+    self.entries.read().unwrap().len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+}
+
+/// Extracts the entry of the [KeyedStates] registered for `K` and `T`. The key of the current
+/// session is read from the [Extensions](crate::request::Extensions) of the request, where a
+/// middleware stores it, e.g. the user id resolved from the token. The request is rejected if
+/// there is no key or no [KeyedStates] registered.
+pub struct KeyedState<K, T> {
+  key: K,
+  state: Arc<T>,
+}
+
+impl<K, T> KeyedState<K, T> {
+  pub fn key(&self) -> &K {
+    &self.key
+  }
+
+  pub fn into_inner(self) -> Arc<T> {
+    self.state
+  }
+}
+
+impl<K, T> Deref for KeyedState<K, T> {
+  type Target = Arc<T>;
+
+  fn deref(&self) -> &Arc<T> {
+    &self.state
+  }
+}
+
+impl<K, T> FromAFPluginRequest for KeyedState<K, T>
+where
+  K: Eq + Hash + Clone + Send + Sync + 'static,
+  T: Send + Sync + 'static,
+{
+  type Error = DispatchError;
+  type Future = Ready<Result<Self, DispatchError>>;
+
+  #[inline]
+  fn from_request(req: &AFPluginEventRequest, _: &mut Payload) -> Self::Future {
+    let states = match req.get_app_data::<AppData<KeyedStates<K, T>>>() {
+      Some(states) => states,
+      None => {
+        let msg = format!(
+          "Failed to get the keyed states of type: {}",
+          type_name::<T>()
+        );
+        tracing::error!("{}", msg);
+        return ready(Err(InternalError::Other(msg).into()));
+      },
+    };
+    match req.extensions().get::<K>() {
+      Some(key) => {
+        let state = states.get_or_init(&key);
+        ready(Ok(KeyedState { key, state }))
+      },
+      None => {
+        let msg = format!(
+          "No session key of type {} for the keyed state {}",
+          type_name::<K>(),
+          type_name::<T>()
+        );
+        tracing::error!("{}", msg);
+        ready(Err(InternalError::Other(msg).into()))
+      },
+    }
+  }
+}
@@ -0,0 +1,94 @@
+use std::any::type_name;
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use futures_core::future::BoxFuture;
+use tokio::sync::OnceCell;
+
+use crate::errors::{DispatchError, InternalError};
+use crate::module::AFPluginState;
+use crate::system::InitStatus;
+
+type StateInit<T> = Arc<dyn Fn() -> BoxFuture<'static, Result<Arc<T>, String>> + Send + Sync>;
+
+/// A plugin state built the first time a handler extracts it, see [AFPlugin::state_lazy].
+///
+/// [AFPlugin::state_lazy]: crate::module::AFPlugin::state_lazy
+pub(crate) struct LazyState<T: ?Sized> {
+  cell: Arc<OnceCell<Arc<T>>>,
+  init: StateInit<T>,
+  status: Arc<Mutex<InitStatus>>,
+}
+
+impl<T: ?Sized> Clone for LazyState<T> {
+  fn clone(&self) -> Self {
+    Self {
+      cell: self.cell.clone(),
+      init: self.init.clone(),
+      status: self.status.clone(),
+    }
+  }
+}
+
+impl<T> LazyState<T>
+where
+  T: Send + Sync + 'static,
+{
+  pub(crate) fn new<F, Fut, E>(init: F) -> Self
+  where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<T, E>> + Send + 'static,
+    E: Display,
+  {
+    let init: StateInit<T> = Arc::new(move || {
+      let fut = init();
+      Box::pin(async move { fut.await.map(Arc::new).map_err(|err| err.to_string()) })
+    });
+    Self {
+      cell: Arc::new(OnceCell::new()),
+      init,
+      status: Arc::new(Mutex::new(InitStatus::Pending)),
+    }
+  }
+}
+
+impl<T> LazyState<T>
+where
+  T: ?Sized + Send + Sync + 'static,
+{
+  /// Whether the state is built, reported by the health check.
+  pub(crate) fn status(&self) -> Arc<Mutex<InitStatus>> {
+    self.status.clone()
+  }
+
+  /// Returns the state, building it if needed. The concurrent callers wait for the same
+  /// initialization. A failed initialization is not cached, the next caller tries again.
+  pub(crate) async fn get(&self) -> Result<AFPluginState<T>, DispatchError> {
+    let state = self
+      .cell
+      .get_or_try_init(|| async {
+        (self.init)().await.map_err(|reason| {
+          tracing::error!(
+            "Initialize the plugin state {} failed: {}",
+            type_name::<T>(),
+            reason
+          );
+          self.set_status(InitStatus::Failed(reason.clone()));
+          InternalError::StateInit {
+            state: type_name::<T>().to_owned(),
+            reason,
+          }
+        })
+      })
+      .await?;
+    self.set_status(InitStatus::Ready);
+    Ok(AFPluginState::from(state.clone()))
+  }
+
+  fn set_status(&self, status: InitStatus) {
+    if let Ok(mut current) = self.status.lock() {
+      *current = status;
+    }
+  }
+}
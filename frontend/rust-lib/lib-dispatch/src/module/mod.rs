@@ -1,9 +1,26 @@
 #![allow(clippy::module_inception)]
 
+pub use alias::{EventAlias, EventRemoved};
 pub use container::*;
 pub use data::*;
+pub use event::AFPluginEvent;
+pub use keyed::{KeyedState, KeyedStates};
+pub(crate) use lazy::LazyState;
 pub use module::*;
+pub use observable::{ObservableState, StateBus, StateChange, STATE_BUS_CAPACITY};
+pub use registry::AFPluginRegistry;
+pub use schema::EventSchema;
+pub(crate) use state_snapshot::{ErasedStateSnapshot, StateSnapshotter};
+pub use state_snapshot::{StateSnapshot, StatesSnapshot};
 
+mod alias;
 mod container;
 mod data;
+mod event;
+mod keyed;
+mod lazy;
 mod module;
+mod observable;
+mod registry;
+mod schema;
+mod state_snapshot;
@@ -1,21 +1,46 @@
+use crate::capability::{Capabilities, PermissionDenied};
+use crate::diff::DIFF_BASE_METADATA;
 use crate::dispatcher::AFConcurrent;
+use crate::encoding::ContentType;
+#[cfg(feature = "use_protobuf")]
+use crate::encoding::ProtoJson;
+use crate::feature_flag::{FeatureDisabled, FeatureFlags};
+use crate::gate::{AppStates, Precondition, PreconditionFailed};
+use crate::lifecycle::LifecycleObserver;
+use crate::memory::MemoryReporter;
+use crate::module::schema::{EventSchema, HandlerSchema};
+use crate::module::{
+  AFPluginEvent, AFPluginStateMap, ErasedStateSnapshot, EventAlias, StateSnapshot, StateSnapshotter,
+};
+use crate::pagination::PageRequest;
+use crate::payload_schema::{InvalidPayload, PayloadSchema};
 use crate::prelude::{AFBoxFuture, AFStateMap};
+use crate::probe::{DispatchPhase, DispatchProbes};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::proxy::{ForwardedRequest, RemoteProxy};
 use crate::service::AFPluginHandler;
+use crate::system::InitStatus;
 use crate::{
   errors::{DispatchError, InternalError},
-  request::{payload::Payload, AFPluginEventRequest, FromAFPluginRequest},
+  request::{
+    payload::Payload, AFPluginEventRequest, CancellationToken, Extensions, FromAFPluginRequest,
+  },
   response::{AFPluginEventResponse, AFPluginResponder},
   service::{
     factory, AFPluginHandlerService, AFPluginServiceFactory, BoxService, BoxServiceFactory,
     Service, ServiceRequest, ServiceResponse,
   },
 };
+use derivative::*;
 use futures_core::ready;
 use nanoid::nanoid;
 use pin_project::pin_project;
-use std::sync::Arc;
+use std::any::TypeId;
+use std::ops::RangeInclusive;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use std::{
-  collections::HashMap,
+  collections::{HashMap, HashSet},
   fmt,
   fmt::{Debug, Display},
   future::Future,
@@ -25,10 +50,13 @@ use std::{
 };
 
 pub type AFPluginMap = Arc<HashMap<AFPluginEvent, Arc<AFPlugin>>>;
+
+/// A handler registered with [AFPlugin::event_fast].
+type FastHandler = Arc<dyn Fn(&AFPluginEventRequest) -> AFPluginEventResponse + Send + Sync>;
 pub(crate) fn plugin_map_or_crash(plugins: Vec<AFPlugin>) -> AFPluginMap {
   let mut plugin_map: HashMap<AFPluginEvent, Arc<AFPlugin>> = HashMap::new();
   plugins.into_iter().for_each(|m| {
-    let events = m.events();
+    let events = m.routed_events();
     #[allow(clippy::arc_with_non_send_sync)]
     let plugins = Arc::new(m);
     events.into_iter().for_each(|e| {
@@ -43,13 +71,24 @@ pub(crate) fn plugin_map_or_crash(plugins: Vec<AFPlugin>) -> AFPluginMap {
   Arc::new(plugin_map)
 }
 
-#[derive(PartialEq, Eq, Hash, Debug, Clone)]
-pub struct AFPluginEvent(String);
-
-impl<T: Display + Eq + Hash + Debug + Clone> std::convert::From<T> for AFPluginEvent {
-  fn from(t: T) -> Self {
-    AFPluginEvent(format!("{}", t))
+/// Collects the states shared by the plugins, see [AFPlugin::shared_state]. Two plugins can't
+/// share a state of the same type.
+pub(crate) fn shared_states_or_crash(plugins: &mut [AFPlugin]) -> AFPluginStateMap {
+  let mut shared_states = AFPluginStateMap::new();
+  let mut owners: HashMap<TypeId, String> = HashMap::new();
+  for plugin in plugins.iter_mut() {
+    let states = std::mem::take(&mut plugin.shared_states);
+    for type_id in states.type_ids() {
+      if let Some(owner) = owners.insert(*type_id, plugin.name.clone()) {
+        panic!(
+          "⚠️⚠️⚠️Error: {:?} and {:?} share a state of the same type",
+          owner, plugin.name
+        );
+      }
+    }
+    shared_states.extend(states);
   }
+  shared_states
 }
 
 /// A plugin is used to handle the events that the plugin can handle.
@@ -64,12 +103,63 @@ pub struct AFPlugin {
   /// a list of `AFPluginState` that the plugin registers. The state can be read by the plugin's handler.
   states: AFStateMap,
 
+  /// The states the plugin shares with the handlers of the other plugins, see
+  /// [AFPlugin::shared_state]. They are moved to the dispatcher when it's created.
+  shared_states: AFPluginStateMap,
+
+  /// The states registered with [AFPlugin::snapshot_state].
+  snapshotters: Vec<Arc<dyn ErasedStateSnapshot>>,
+
   /// Contains a list of factories that are used to generate the services used to handle the passed-in
   /// `ServiceRequest`.
   ///
   event_service_factory: Arc<
     HashMap<AFPluginEvent, BoxServiceFactory<(), ServiceRequest, ServiceResponse, DispatchError>>,
   >,
+
+  /// The payload and response types of the handlers, see [EventSchema].
+  schemas: HashMap<AFPluginEvent, HandlerSchema>,
+
+  /// The contract versions supported by the events, see [AFPlugin::versions].
+  versions: HashMap<AFPluginEvent, RangeInclusive<u32>>,
+
+  /// The states the events require, see [AFPlugin::requires].
+  preconditions: HashMap<AFPluginEvent, Vec<Precondition>>,
+
+  /// The capabilities the callers of the events need, see [AFPlugin::requires_capability].
+  capabilities: HashMap<AFPluginEvent, Vec<String>>,
+
+  /// The flags the events are gated behind, see [AFPlugin::feature_flag].
+  feature_flags: HashMap<AFPluginEvent, String>,
+
+  /// The former names of the events, see [AFPlugin::alias].
+  aliases: HashMap<AFPluginEvent, EventAlias>,
+
+  /// The schemas the payloads of the events must match, see [AFPlugin::payload_schema].
+  payload_schemas: HashMap<AFPluginEvent, PayloadSchema>,
+
+  /// The messages the JSON payloads are converted to, see [AFPlugin::proto_descriptors].
+  #[cfg(feature = "use_protobuf")]
+  proto_json: ProtoJson,
+
+  /// The handlers called inline, see [AFPlugin::event_fast].
+  fast_handlers: HashMap<AFPluginEvent, FastHandler>,
+
+  /// The fast events that can be handled on the thread sending them, see [AFPlugin::inline].
+  inline_events: HashSet<AFPluginEvent>,
+
+  /// The events that change the data, see [AFPlugin::mutating].
+  mutating_events: HashSet<AFPluginEvent>,
+
+  /// The reporters registered with [AFPlugin::memory_reporter].
+  memory_reporters: Vec<(String, Arc<dyn MemoryReporter>)>,
+
+  /// The observers registered with [AFPlugin::lifecycle_observer].
+  lifecycle_observers: Vec<Arc<dyn LifecycleObserver>>,
+
+  /// The type names of the states registered with [AFPlugin::state_lazy], and whether they are
+  /// built.
+  lazy_states: Vec<(String, Arc<Mutex<InitStatus>>)>,
 }
 
 impl std::default::Default for AFPlugin {
@@ -77,8 +167,25 @@ impl std::default::Default for AFPlugin {
     Self {
       name: "".to_owned(),
       states: Default::default(),
+      shared_states: AFPluginStateMap::new(),
+      snapshotters: vec![],
       #[allow(clippy::arc_with_non_send_sync)]
       event_service_factory: Arc::new(HashMap::new()),
+      schemas: HashMap::new(),
+      versions: HashMap::new(),
+      preconditions: HashMap::new(),
+      capabilities: HashMap::new(),
+      feature_flags: HashMap::new(),
+      aliases: HashMap::new(),
+      payload_schemas: HashMap::new(),
+      #[cfg(feature = "use_protobuf")]
+      proto_json: ProtoJson::default(),
+      fast_handlers: HashMap::new(),
+      inline_events: HashSet::new(),
+      mutating_events: HashSet::new(),
+      memory_reporters: vec![],
+      lifecycle_observers: vec![],
+      lazy_states: vec![],
     }
   }
 }
@@ -100,6 +207,84 @@ impl AFPlugin {
     self
   }
 
+  /// Registers a state like [AFPlugin::state] that is also saved and restored by
+  /// [AFPluginDispatcher::snapshot_states](crate::prelude::AFPluginDispatcher::snapshot_states)
+  /// and [AFPluginDispatcher::restore_states](crate::prelude::AFPluginDispatcher::restore_states).
+  pub fn snapshot_state<D: StateSnapshot>(mut self, data: D) -> Self {
+    let state = crate::module::AFPluginState::new(data);
+    self
+      .snapshotters
+      .push(Arc::new(StateSnapshotter(state.clone())));
+    Arc::get_mut(&mut self.states).unwrap().insert(state);
+    self
+  }
+
+  /// Reports the memory held by a subsystem of the plugin, like a cache, under `name`. See
+  /// [MemoryReporters](crate::memory::MemoryReporters).
+  pub fn memory_reporter<R: MemoryReporter>(mut self, name: &str, reporter: R) -> Self {
+    self
+      .memory_reporters
+      .push((name.to_owned(), Arc::new(reporter)));
+    self
+  }
+
+  pub(crate) fn memory_reporters(&self) -> &[(String, Arc<dyn MemoryReporter>)] {
+    &self.memory_reporters
+  }
+
+  /// Tells `observer` about the app entering the background or the foreground and running low
+  /// on memory, see [LifecycleObserver].
+  pub fn lifecycle_observer<O: LifecycleObserver>(mut self, observer: O) -> Self {
+    self.lifecycle_observers.push(Arc::new(observer));
+    self
+  }
+
+  pub(crate) fn lifecycle_observers(&self) -> &[Arc<dyn LifecycleObserver>] {
+    &self.lifecycle_observers
+  }
+
+  pub(crate) fn lazy_states(&self) -> &[(String, Arc<Mutex<InitStatus>>)] {
+    &self.lazy_states
+  }
+
+  pub(crate) fn snapshotters(&self) -> &[Arc<dyn ErasedStateSnapshot>] {
+    &self.snapshotters
+  }
+
+  pub(crate) fn has_shared_states(&self) -> bool {
+    self.shared_states.type_ids().next().is_some()
+  }
+
+  /// Registers a state that the handlers of all the plugins can read with the [Shared]
+  /// extractor. The states registered with [AFPlugin::state] are only visible to the handlers of
+  /// this plugin, use this one for the states that are meant to be used by the other plugins.
+  ///
+  /// [Shared]: crate::module::Shared
+  pub fn shared_state<D: Send + Sync + 'static>(mut self, data: D) -> Self {
+    self.shared_states.insert(crate::module::Shared::new(data));
+    self
+  }
+
+  /// Registers a state that is built by `init` the first time a handler extracts it, instead of
+  /// when the plugin is created. Use it for the expensive states that are not always needed, like
+  /// a connection pool. The handlers extract it with [AFPluginState] like the other states. If
+  /// `init` fails, the request is rejected with a `StateInit` error and the next request tries
+  /// again.
+  pub fn state_lazy<D, F, Fut, E>(mut self, init: F) -> Self
+  where
+    D: Send + Sync + 'static,
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<D, E>> + Send + 'static,
+    E: Display,
+  {
+    let state = crate::module::LazyState::<D>::new(init);
+    self
+      .lazy_states
+      .push((std::any::type_name::<D>().to_owned(), state.status()));
+    Arc::get_mut(&mut self.states).unwrap().insert(state);
+    self
+  }
+
   #[track_caller]
   pub fn event<E, H, T, R>(mut self, event: E, handler: H) -> Self
   where
@@ -114,6 +299,9 @@ impl AFPlugin {
     if self.event_service_factory.contains_key(&event) {
       panic!("Register duplicate Event: {:?}", &event);
     } else {
+      self
+        .schemas
+        .insert(event.clone(), HandlerSchema::new::<T, R::Output>());
       Arc::get_mut(&mut self.event_service_factory)
         .unwrap()
         .insert(event, factory(AFPluginHandlerService::new(handler)));
@@ -121,6 +309,416 @@ impl AFPlugin {
     self
   }
 
+  /// Forwards the requests of `event` to the remote core behind `proxy` instead of handling them
+  /// locally. The remote response is returned as is.
+  #[cfg(not(target_arch = "wasm32"))]
+  #[track_caller]
+  pub fn proxy<E>(self, event: E, proxy: RemoteProxy) -> Self
+  where
+    E: Eq + Hash + Debug + Clone + Display,
+  {
+    self.event(event, move |request: ForwardedRequest| {
+      proxy.clone().forward(request)
+    })
+  }
+
+  /// Registers a handler that takes no parameters and returns its response right away, like the
+  /// handlers reading a flag. Its requests skip the service chain: the handler is called inline
+  /// by the dispatcher, without building the services of the plugin or boxing any future. The
+  /// payload of the requests is ignored, like it is by the other handlers without parameters.
+  #[track_caller]
+  pub fn event_fast<E, F, R>(self, event: E, handler: F) -> Self
+  where
+    F: Fn() -> R + Clone + Send + Sync + 'static,
+    R: AFPluginResponder + AFConcurrent + 'static,
+    E: Eq + Hash + Debug + Clone + Display,
+  {
+    let key: AFPluginEvent = event.clone().into();
+    // Also registered as a regular event, so it has a schema and can be replaced or mocked.
+    let fallback = handler.clone();
+    let mut plugin = self.event(event, move || {
+      let response = fallback();
+      async move { response }
+    });
+    plugin
+      .fast_handlers
+      .insert(key, Arc::new(move |request| handler().respond_to(request)));
+    plugin
+  }
+
+  /// Lets the dispatcher call the handler of `event`, registered with [AFPlugin::event_fast], on
+  /// the thread that sends the request instead of queuing it. Only set it on the handlers that
+  /// are cheap and safe to run on any thread, see
+  /// [AFPluginDispatcher::try_call_inline](crate::prelude::AFPluginDispatcher::try_call_inline).
+  #[track_caller]
+  pub fn inline<E>(mut self, event: E) -> Self
+  where
+    E: Eq + Hash + Debug + Clone + Display,
+  {
+    let event: AFPluginEvent = event.into();
+    if !self.fast_handlers.contains_key(&event) {
+      panic!("Only the fast events can be handled inline: {:?}", &event);
+    }
+    self.inline_events.insert(event);
+    self
+  }
+
+  /// Calls the handler of `request` if it can be called on the current thread, see
+  /// [AFPlugin::inline]. Gives the request back otherwise.
+  pub(crate) fn call_inline(
+    &self,
+    request: AFPluginRequest,
+  ) -> Result<AFPluginEventResponse, AFPluginRequest> {
+    if !self.inline_events.contains(&request.event) {
+      return Err(request);
+    }
+    self.call_fast(request)
+  }
+
+  /// Builds the future that runs the handler of `request`. Unlike going through
+  /// [AFPlugin::new_service], nothing but the states is cloned from the plugin, which is only
+  /// borrowed while the future is built.
+  pub(crate) fn call_service(
+    &self,
+    request: AFPluginRequest,
+  ) -> AFBoxFuture<'static, Result<AFPluginEventResponse, DispatchError>> {
+    call_event_service(&self.event_service_factory, &self.states, request)
+  }
+
+  /// Calls the handler of `request` inline if it was registered with [AFPlugin::event_fast].
+  /// Gives the request back otherwise.
+  pub(crate) fn call_fast(
+    &self,
+    request: AFPluginRequest,
+  ) -> Result<AFPluginEventResponse, AFPluginRequest> {
+    let handler = match self.fast_handlers.get(&request.event) {
+      Some(handler) => handler,
+      None => return Err(request),
+    };
+    let AFPluginRequest {
+      id,
+      event,
+      content_type,
+      metadata,
+      probes,
+      app_data,
+      extensions,
+      ..
+    } = request;
+    let mut request = AFPluginEventRequest::new(id, event, self.states.clone());
+    request.content_type = content_type;
+    request.metadata = metadata;
+    request.app_data = app_data;
+    request.extensions = extensions;
+    let response = probes.scope(&request.id, &request.event, DispatchPhase::Handler, || {
+      handler(&request)
+    });
+    Ok(response)
+  }
+
+  /// The versions of the payload and response of `event` that its handler supports. The requests
+  /// that carry a version out of this range are rejected with an `IncompatibleVersion` error
+  /// before reaching the handler. The requests without a version are always accepted.
+  #[track_caller]
+  pub fn versions<E>(mut self, event: E, versions: RangeInclusive<u32>) -> Self
+  where
+    E: Eq + Hash + Debug + Clone + Display,
+  {
+    let event: AFPluginEvent = event.into();
+    if !self.event_service_factory.contains_key(&event) {
+      panic!("Set the versions of an unregistered Event: {:?}", &event);
+    }
+    self.versions.insert(event, versions);
+    self
+  }
+
+  /// Sends the requests of `alias`, the former name of `event`, to the handler of `event`, so
+  /// renaming an event doesn't break the clients built before. Their responses carry a
+  /// deprecation notice in the [DEPRECATION_METADATA](crate::prelude::DEPRECATION_METADATA), and
+  /// the first use of the alias is logged with a warning.
+  #[track_caller]
+  pub fn alias<A, E>(self, alias: A, event: E) -> Self
+  where
+    A: Eq + Hash + Debug + Clone + Display,
+    E: Eq + Hash + Debug + Clone + Display,
+  {
+    self.insert_alias(alias.into(), event.into(), None)
+  }
+
+  /// Like [AFPlugin::alias], but the requests of `alias` carrying the contract version
+  /// `removed_in`, or a later one, are rejected with an
+  /// [EventRemoved](crate::prelude::EventRemoved) error. The requests without a version are
+  /// still accepted.
+  #[track_caller]
+  pub fn alias_until<A, E>(self, alias: A, event: E, removed_in: u32) -> Self
+  where
+    A: Eq + Hash + Debug + Clone + Display,
+    E: Eq + Hash + Debug + Clone + Display,
+  {
+    self.insert_alias(alias.into(), event.into(), Some(removed_in))
+  }
+
+  #[track_caller]
+  fn insert_alias(
+    mut self,
+    alias: AFPluginEvent,
+    event: AFPluginEvent,
+    removed_in: Option<u32>,
+  ) -> Self {
+    if !self.event_service_factory.contains_key(&event) {
+      panic!("Set an alias of an unregistered Event: {:?}", &event);
+    }
+    if self.event_service_factory.contains_key(&alias) {
+      panic!("The alias {:?} is a registered Event", &alias);
+    }
+    self
+      .aliases
+      .insert(alias.clone(), EventAlias::new(alias, event, removed_in));
+    self
+  }
+
+  /// The alias named `event`, if it's one.
+  pub(crate) fn event_alias(&self, event: &AFPluginEvent) -> Option<&EventAlias> {
+    self.aliases.get(event)
+  }
+
+  /// The former names of the events, see [AFPlugin::alias].
+  pub fn aliases(&self) -> Vec<EventAlias> {
+    self.aliases.values().cloned().collect()
+  }
+
+  pub(crate) fn check_version(&self, request: &AFPluginRequest) -> Result<(), DispatchError> {
+    match (request.version, self.versions.get(&request.event)) {
+      (Some(version), Some(supported)) if !supported.contains(&version) => Err(
+        InternalError::IncompatibleVersion {
+          event: request.event.as_str().to_owned(),
+          version,
+          supported: supported.clone(),
+        }
+        .into(),
+      ),
+      _ => Ok(()),
+    }
+  }
+
+  /// Rejects the requests of `event` with a [PreconditionFailed] error, before they reach the
+  /// handler, unless `precondition` holds in the [AppStates] of the dispatcher. An event may
+  /// require several states.
+  #[track_caller]
+  pub fn requires<E>(mut self, event: E, precondition: Precondition) -> Self
+  where
+    E: Eq + Hash + Debug + Clone + Display,
+  {
+    let event: AFPluginEvent = event.into();
+    if !self.event_service_factory.contains_key(&event) {
+      panic!(
+        "Set the preconditions of an unregistered Event: {:?}",
+        &event
+      );
+    }
+    self
+      .preconditions
+      .entry(event)
+      .or_default()
+      .push(precondition);
+    self
+  }
+
+  pub(crate) fn check_preconditions(
+    &self,
+    request: &AFPluginRequest,
+    states: &AppStates,
+  ) -> Result<(), DispatchError> {
+    let violations = match self.preconditions.get(&request.event) {
+      None => return Ok(()),
+      Some(preconditions) => states.violations(preconditions),
+    };
+    if violations.is_empty() {
+      return Ok(());
+    }
+    Err(
+      PreconditionFailed {
+        event: request.event.as_str().to_owned(),
+        violations,
+      }
+      .into(),
+    )
+  }
+
+  /// Rejects the requests of `event` with a [PermissionDenied] error, before their payload is
+  /// extracted, unless their caller was granted `capability`. The requests without
+  /// [Capabilities] come from the app itself and are always accepted.
+  #[track_caller]
+  pub fn requires_capability<E, C>(mut self, event: E, capability: C) -> Self
+  where
+    E: Eq + Hash + Debug + Clone + Display,
+    C: ToString,
+  {
+    let event: AFPluginEvent = event.into();
+    if !self.event_service_factory.contains_key(&event) {
+      panic!(
+        "Set the capabilities of an unregistered Event: {:?}",
+        &event
+      );
+    }
+    self
+      .capabilities
+      .entry(event)
+      .or_default()
+      .push(capability.to_string());
+    self
+  }
+
+  pub(crate) fn check_capabilities(&self, request: &AFPluginRequest) -> Result<(), DispatchError> {
+    let missing = match (&request.capabilities, self.capabilities.get(&request.event)) {
+      (Some(granted), Some(required)) => granted.missing(required),
+      _ => return Ok(()),
+    };
+    if missing.is_empty() {
+      return Ok(());
+    }
+    Err(
+      PermissionDenied {
+        event: request.event.as_str().to_owned(),
+        missing,
+      }
+      .into(),
+    )
+  }
+
+  /// Declares that `event` changes the data, e.g. to record its requests in the audit log, see
+  /// [AFPluginRequest::is_mutating].
+  #[track_caller]
+  pub fn mutating<E>(mut self, event: E) -> Self
+  where
+    E: Eq + Hash + Debug + Clone + Display,
+  {
+    let event: AFPluginEvent = event.into();
+    if !self.event_service_factory.contains_key(&event) {
+      panic!("Declare an unregistered Event as mutating: {:?}", &event);
+    }
+    self.mutating_events.insert(event);
+    self
+  }
+
+  pub fn is_mutating(&self, event: &AFPluginEvent) -> bool {
+    self.mutating_events.contains(event)
+  }
+
+  /// Rejects the requests of `event` with a [FeatureDisabled] error, before their payload is
+  /// extracted, unless `flag` is on for them in the [FeatureFlags] of the dispatcher. It
+  /// replaces the previous flag of the event.
+  #[track_caller]
+  pub fn feature_flag<E, T>(mut self, event: E, flag: T) -> Self
+  where
+    E: Eq + Hash + Debug + Clone + Display,
+    T: ToString,
+  {
+    let event: AFPluginEvent = event.into();
+    if !self.event_service_factory.contains_key(&event) {
+      panic!(
+        "Set the feature flag of an unregistered Event: {:?}",
+        &event
+      );
+    }
+    self.feature_flags.insert(event, flag.to_string());
+    self
+  }
+
+  pub(crate) fn check_feature_flag(
+    &self,
+    request: &AFPluginRequest,
+    flags: &FeatureFlags,
+  ) -> Result<(), DispatchError> {
+    match self.feature_flags.get(&request.event) {
+      Some(flag) if !flags.is_enabled(flag, request) => Err(
+        FeatureDisabled {
+          event: request.event.as_str().to_owned(),
+          flag: flag.clone(),
+        }
+        .into(),
+      ),
+      _ => Ok(()),
+    }
+  }
+
+  /// Rejects the requests of `event` whose payload doesn't match `schema` with an
+  /// [InvalidPayload] error listing the invalid fields, before the payload is extracted. It
+  /// replaces the previous schema of the event.
+  #[track_caller]
+  pub fn payload_schema<E>(mut self, event: E, schema: PayloadSchema) -> Self
+  where
+    E: Eq + Hash + Debug + Clone + Display,
+  {
+    let event: AFPluginEvent = event.into();
+    if !self.event_service_factory.contains_key(&event) {
+      panic!(
+        "Set the payload schema of an unregistered Event: {:?}",
+        &event
+      );
+    }
+    self.payload_schemas.insert(event, schema);
+    self
+  }
+
+  pub(crate) fn check_payload(&self, request: &AFPluginRequest) -> Result<(), DispatchError> {
+    let schema = match self.payload_schemas.get(&request.event) {
+      Some(schema) => schema,
+      None => return Ok(()),
+    };
+    schema.validate(request).map_err(|errors| {
+      InvalidPayload {
+        event: request.event.as_str().to_owned(),
+        errors,
+      }
+      .into()
+    })
+  }
+
+  /// Converts the JSON payloads, the ones of the requests tagged with [ContentType::Json], into
+  /// the protobuf messages of `files` their handlers decode with `AFPluginData`. The messages
+  /// are matched by name: pass the descriptors of the `.proto` files generated from the
+  /// entities of the plugin, e.g. `crate::protobuf::file_descriptors()`.
+  #[cfg(feature = "use_protobuf")]
+  pub fn proto_descriptors(
+    mut self,
+    files: Vec<&'static protobuf::descriptor::FileDescriptorProto>,
+  ) -> Self {
+    for file in files {
+      self.proto_json.add_file(file);
+    }
+    self
+  }
+
+  /// Converts the JSON payload of `request`, see [AFPlugin::proto_descriptors]. The request is
+  /// left as it is if it fails.
+  #[cfg(feature = "use_protobuf")]
+  pub(crate) fn convert_json_payload(
+    &self,
+    request: &mut AFPluginRequest,
+  ) -> Result<(), DispatchError> {
+    if request.content_type != Some(ContentType::Json) || request.payload_bytes().is_empty() {
+      return Ok(());
+    }
+    let message = match self
+      .schemas
+      .get(&request.event)
+      .and_then(|schema| schema.data_type())
+    {
+      Some(message) if self.proto_json.contains(message) => message,
+      _ => return Ok(()),
+    };
+    let bytes = self
+      .proto_json
+      .encode(message, request.payload_bytes())
+      .map_err(|err| {
+        InternalError::DeserializeFromBytes(format!("Convert the JSON to {}: {}", message, err))
+      })?;
+    request.payload = Payload::from(bytes);
+    request.content_type = Some(ContentType::Protobuf);
+    Ok(())
+  }
+
   pub fn events(&self) -> Vec<AFPluginEvent> {
     self
       .event_service_factory
@@ -128,17 +726,88 @@ impl AFPlugin {
       .cloned()
       .collect::<Vec<_>>()
   }
+
+  /// The events and the aliases the plugin handles the requests of.
+  pub(crate) fn routed_events(&self) -> Vec<AFPluginEvent> {
+    let mut events = self.events();
+    events.extend(self.aliases.keys().cloned());
+    events
+  }
+
+  pub fn schemas(&self) -> Vec<EventSchema> {
+    self
+      .schemas
+      .iter()
+      .map(|(event, schema)| EventSchema {
+        event: event.as_str().to_owned(),
+        plugin: self.name.clone(),
+        payload: schema.payload.to_owned(),
+        response: schema.response.to_owned(),
+        versions: self.versions.get(event).cloned(),
+      })
+      .collect()
+  }
+}
+
+/// The order in which the requests sent at the same time are handled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RequestPriority {
+  /// Steps back once before being handled, so the requests sent along with it start first.
+  Low,
+  #[default]
+  Normal,
+  /// Handled by the task that sends it instead of being spawned behind the requests already
+  /// queued. The handler is dropped along with the send future.
+  High,
 }
 
+/// The metadata carrying the idempotency key of a mutation, see [AFPluginRequest::idempotency_key].
+pub const IDEMPOTENCY_KEY_METADATA: &str = "idempotency-key";
+
+/// The metadata carrying the locale of the caller, e.g. `fr-FR`, see [AFPluginRequest::locale].
+pub const LOCALE_METADATA: &str = "locale";
+
 /// A request that will be passed to the corresponding plugin.
 ///
 /// Each request can carry the payload that will be deserialized into the corresponding data struct.
 ///
-#[derive(Debug, Clone)]
+#[derive(Clone, Derivative)]
+#[derivative(Debug)]
 pub struct AFPluginRequest {
   pub id: String,
   pub event: AFPluginEvent,
   pub(crate) payload: Payload,
+  /// The encoding of the payload, if the sender specified it.
+  pub content_type: Option<ContentType>,
+  /// The contract version of the payload the sender expects, see [AFPlugin::versions].
+  pub version: Option<u32>,
+  /// The time, according to the clock of the dispatcher, after which the request fails with a
+  /// `DeadlineExceeded` error, whether it's still queued or being handled.
+  pub deadline: Option<Instant>,
+  pub priority: RequestPriority,
+  /// The capabilities granted to the caller, see [AFPlugin::requires_capability]. `None` for the
+  /// app itself, which may call every event.
+  pub capabilities: Option<Capabilities>,
+  /// What the transport knows about the request, e.g. the token sent along with it, see
+  /// [AuthMiddleware](crate::prelude::AuthMiddleware).
+  pub metadata: HashMap<String, String>,
+  /// The token of the operation the request is part of: cancelling it cancels the request. Taken
+  /// from the sending task, see [current_cancellation](crate::prelude::current_cancellation),
+  /// when not set.
+  pub parent_cancellation: Option<CancellationToken>,
+  /// The time the request was created. Used to measure how long the request waited before
+  /// being handled.
+  pub(crate) created_at: Instant,
+  /// Set by the dispatcher once it starts handling the request.
+  pub(crate) probes: DispatchProbes,
+  /// The state shared by all the plugins. Set by the dispatcher along with the probes.
+  #[derivative(Debug = "ignore")]
+  pub(crate) app_data: AFStateMap,
+  /// The values attached to the request, see [Extensions].
+  pub(crate) extensions: Extensions,
+  /// Whether the plugin declared the event as [mutating](AFPlugin::mutating). Set by the
+  /// dispatcher along with the probes.
+  pub(crate) mutating: bool,
 }
 
 impl AFPluginRequest {
@@ -150,6 +819,27 @@ impl AFPluginRequest {
       id: nanoid!(6),
       event: event.into(),
       payload: Payload::None,
+      content_type: None,
+      version: None,
+      deadline: None,
+      priority: RequestPriority::default(),
+      capabilities: None,
+      metadata: HashMap::new(),
+      parent_cancellation: None,
+      created_at: Instant::now(),
+      probes: DispatchProbes::default(),
+      app_data: AFStateMap::default(),
+      extensions: Extensions::default(),
+      mutating: false,
+    }
+  }
+
+  pub fn builder<E>(event: E) -> AFPluginRequestBuilder
+  where
+    E: Into<AFPluginEvent>,
+  {
+    AFPluginRequestBuilder {
+      request: Self::new(event),
     }
   }
 
@@ -160,6 +850,185 @@ impl AFPluginRequest {
     self.payload = payload.into();
     self
   }
+
+  pub fn content_type(mut self, content_type: ContentType) -> Self {
+    self.content_type = Some(content_type);
+    self
+  }
+
+  /// The bytes of the payload, empty if the request has none.
+  pub fn payload_bytes(&self) -> &[u8] {
+    self.payload.as_ref()
+  }
+
+  pub fn version(mut self, version: u32) -> Self {
+    self.version = Some(version);
+    self
+  }
+
+  pub fn deadline(mut self, deadline: Instant) -> Self {
+    self.deadline = Some(deadline);
+    self
+  }
+
+  pub fn priority(mut self, priority: RequestPriority) -> Self {
+    self.priority = priority;
+    self
+  }
+
+  pub fn capabilities(mut self, capabilities: Capabilities) -> Self {
+    self.capabilities = Some(capabilities);
+    self
+  }
+
+  /// Whether the plugin handling the request declared its event as [mutating](AFPlugin::mutating).
+  /// Always `false` before the dispatcher starts handling the request.
+  pub fn is_mutating(&self) -> bool {
+    self.mutating
+  }
+
+  pub fn metadata<K: ToString, V: ToString>(mut self, key: K, value: V) -> Self {
+    self.metadata.insert(key.to_string(), value.to_string());
+    self
+  }
+
+  /// Identifies the mutation across its retries: the sender reuses the key when it sends the
+  /// request again, e.g. after a crash, and the store recording the keys answers the replays with
+  /// the response of the first attempt instead of applying the mutation twice.
+  pub fn idempotency_key<K: ToString>(self, key: K) -> Self {
+    self.metadata(IDEMPOTENCY_KEY_METADATA, key)
+  }
+
+  /// The language the caller shows the errors in, e.g. `fr-FR`.
+  pub fn locale<L: ToString>(self, locale: L) -> Self {
+    self.metadata(LOCALE_METADATA, locale)
+  }
+
+  pub fn parent_cancellation(mut self, parent: CancellationToken) -> Self {
+    self.parent_cancellation = Some(parent);
+    self
+  }
+
+  /// Asks for the response as a patch against the payload whose hash is `hash`, see
+  /// [DiffResponses](crate::diff::DiffResponses).
+  pub fn diff_base<H: ToString>(self, hash: H) -> Self {
+    self.metadata(DIFF_BASE_METADATA, hash)
+  }
+
+  /// Asks a list event for one page of it, see [PageRequest].
+  pub fn page(mut self, page: PageRequest) -> Self {
+    page.to_metadata(&mut self.metadata);
+    self
+  }
+
+  /// The values attached to the request. The middlewares insert them in
+  /// [AFPluginMiddleware::on_request](crate::prelude::AFPluginMiddleware::on_request) and the
+  /// handlers read them with the [Extension](crate::prelude::Extension) extractor.
+  pub fn extensions(&self) -> &Extensions {
+    &self.extensions
+  }
+}
+
+/// Builds an [AFPluginRequest] step by step. The send methods of the dispatcher accept the
+/// builder as well as the built request.
+///
+/// ```ignore
+/// let request = AFPluginRequest::builder(UserEvent::GetUserProfile)
+///   .payload(bytes)
+///   .timeout(Duration::from_secs(5))
+///   .priority(RequestPriority::High)
+///   .build();
+/// ```
+#[derive(Debug)]
+pub struct AFPluginRequestBuilder {
+  request: AFPluginRequest,
+}
+
+impl AFPluginRequestBuilder {
+  /// Replaces the random id of the request, e.g. to cancel it later with
+  /// [AFPluginDispatcher::cancel](crate::prelude::AFPluginDispatcher::cancel).
+  pub fn id<T: Into<String>>(mut self, id: T) -> Self {
+    self.request.id = id.into();
+    self
+  }
+
+  pub fn payload<P>(mut self, payload: P) -> Self
+  where
+    P: Into<Payload>,
+  {
+    self.request = self.request.payload(payload);
+    self
+  }
+
+  pub fn content_type(mut self, content_type: ContentType) -> Self {
+    self.request = self.request.content_type(content_type);
+    self
+  }
+
+  pub fn version(mut self, version: u32) -> Self {
+    self.request = self.request.version(version);
+    self
+  }
+
+  pub fn deadline(mut self, deadline: Instant) -> Self {
+    self.request = self.request.deadline(deadline);
+    self
+  }
+
+  /// Sets the deadline `timeout` from now.
+  pub fn timeout(self, timeout: Duration) -> Self {
+    self.deadline(Instant::now() + timeout)
+  }
+
+  pub fn priority(mut self, priority: RequestPriority) -> Self {
+    self.request = self.request.priority(priority);
+    self
+  }
+
+  pub fn capabilities(mut self, capabilities: Capabilities) -> Self {
+    self.request = self.request.capabilities(capabilities);
+    self
+  }
+
+  pub fn metadata<K: ToString, V: ToString>(mut self, key: K, value: V) -> Self {
+    self.request = self.request.metadata(key, value);
+    self
+  }
+
+  pub fn idempotency_key<K: ToString>(mut self, key: K) -> Self {
+    self.request = self.request.idempotency_key(key);
+    self
+  }
+
+  pub fn locale<L: ToString>(mut self, locale: L) -> Self {
+    self.request = self.request.locale(locale);
+    self
+  }
+
+  pub fn parent_cancellation(mut self, parent: CancellationToken) -> Self {
+    self.request = self.request.parent_cancellation(parent);
+    self
+  }
+
+  pub fn diff_base<H: ToString>(mut self, hash: H) -> Self {
+    self.request = self.request.diff_base(hash);
+    self
+  }
+
+  pub fn page(mut self, page: PageRequest) -> Self {
+    self.request = self.request.page(page);
+    self
+  }
+
+  pub fn build(self) -> AFPluginRequest {
+    self.request
+  }
+}
+
+impl From<AFPluginRequestBuilder> for AFPluginRequest {
+  fn from(builder: AFPluginRequestBuilder) -> Self {
+    builder.build()
+  }
 }
 
 impl std::fmt::Display for AFPluginRequest {
@@ -199,30 +1068,57 @@ impl Service<AFPluginRequest> for AFPluginService {
   type Future = AFBoxFuture<'static, Result<Self::Response, Self::Error>>;
 
   fn call(&self, request: AFPluginRequest) -> Self::Future {
-    let AFPluginRequest { id, event, payload } = request;
-    let states = self.states.clone();
-    let request = AFPluginEventRequest::new(id, event, states);
-
-    match self.services.get(&request.event) {
-      Some(factory) => {
-        let service_fut = factory.new_service(());
-        let fut = AFPluginServiceFuture {
-          fut: Box::pin(async {
-            let service = service_fut.await?;
-            let service_req = ServiceRequest::new(request, payload);
-            service.call(service_req).await
-          }),
-        };
-        Box::pin(async move { Ok(fut.await.unwrap_or_else(|e| e.into())) })
-      },
-      None => {
-        let msg = format!(
-          "Can not find service factory for event: {:?}",
-          request.event
-        );
-        Box::pin(async { Err(InternalError::ServiceNotFound(msg).into()) })
-      },
-    }
+    call_event_service(&self.services, &self.states, request)
+  }
+}
+
+/// Runs the handler of `request` with the services of a plugin. Only borrows them: the returned
+/// future owns what it needs.
+fn call_event_service(
+  services: &HashMap<
+    AFPluginEvent,
+    BoxServiceFactory<(), ServiceRequest, ServiceResponse, DispatchError>,
+  >,
+  states: &AFStateMap,
+  request: AFPluginRequest,
+) -> AFBoxFuture<'static, Result<AFPluginEventResponse, DispatchError>> {
+  let AFPluginRequest {
+    id,
+    event,
+    payload,
+    content_type,
+    metadata,
+    probes,
+    app_data,
+    extensions,
+    ..
+  } = request;
+  let mut request = AFPluginEventRequest::new(id, event, states.clone());
+  request.content_type = content_type;
+  request.metadata = metadata;
+  request.probes = probes;
+  request.app_data = app_data;
+  request.extensions = extensions;
+
+  match services.get(&request.event) {
+    Some(factory) => {
+      let service_fut = factory.new_service(());
+      let fut = AFPluginServiceFuture {
+        fut: Box::pin(async {
+          let service = service_fut.await?;
+          let service_req = ServiceRequest::new(request, payload);
+          service.call(service_req).await
+        }),
+      };
+      Box::pin(async move { Ok(fut.await.unwrap_or_else(|e| e.into())) })
+    },
+    None => {
+      let msg = format!(
+        "Can not find service factory for event: {:?}",
+        request.event
+      );
+      Box::pin(async { Err(InternalError::ServiceNotFound(msg).into()) })
+    },
   }
 }
 
@@ -0,0 +1,164 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, RwLock};
+
+use tokio::sync::broadcast;
+
+use crate::module::AFPluginStateMap;
+
+/// How many changes of a state type a slow subscriber can fall behind before it misses some.
+pub const STATE_BUS_CAPACITY: usize = 64;
+
+/// A change of an [ObservableState], published on the [StateBus].
+pub struct StateChange<T> {
+  pub old: Arc<T>,
+  pub new: Arc<T>,
+}
+
+impl<T> Clone for StateChange<T> {
+  fn clone(&self) -> Self {
+    Self {
+      old: self.old.clone(),
+      new: self.new.clone(),
+    }
+  }
+}
+
+impl<T: Debug> Debug for StateChange<T> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("StateChange")
+      .field("old", &self.old)
+      .field("new", &self.new)
+      .finish()
+  }
+}
+
+/// The internal bus of the dispatcher. Each state type gets its own channel, so the modules
+/// subscribe to the changes of the states they care about, e.g. the current workspace, instead
+/// of polling them.
+///
+/// The clones share the same channels. The bus of a dispatcher is returned by
+/// [AFPluginDispatcher::state_bus](crate::prelude::AFPluginDispatcher::state_bus) and the
+/// handlers read it with the `AppData<StateBus>` extractor.
+#[derive(Clone, Default)]
+pub struct StateBus {
+  channels: Arc<RwLock<AFPluginStateMap>>,
+}
+
+impl StateBus {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Creates a cell holding `value` whose changes are published on this bus.
+  pub fn observable<T: Send + Sync + 'static>(&self, value: T) -> ObservableState<T> {
+    ObservableState {
+      value: Arc::new(RwLock::new(Arc::new(value))),
+      bus: self.clone(),
+    }
+  }
+
+  /// Receives the changes of the states of type `T` published from now on.
+  pub fn subscribe<T: Send + Sync + 'static>(&self) -> broadcast::Receiver<StateChange<T>> {
+    self.sender::<T>().subscribe()
+  }
+
+  /// Publishes `change` to the subscribers of `T` and returns how many received it.
+  pub fn publish<T: Send + Sync + 'static>(&self, change: StateChange<T>) -> usize {
+    self.sender::<T>().send(change).unwrap_or(0)
+  }
+
+  fn sender<T: Send + Sync + 'static>(&self) -> broadcast::Sender<StateChange<T>> {
+    if let Some(sender) = self
+      .channels
+      .read()
+      .unwrap()
+      .get::<broadcast::Sender<StateChange<T>>>()
+    {
+      return sender.clone();
+    }
+
+    let mut channels = self.channels.write().unwrap();
+    if let Some(sender) = channels.get::<broadcast::Sender<StateChange<T>>>() {
+      return sender.clone();
+    }
+    let (sender, _) = broadcast::channel(STATE_BUS_CAPACITY);
+    channels.insert(sender.clone());
+    sender
+  }
+}
+
+impl Debug for StateBus {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("StateBus").finish_non_exhaustive()
+  }
+}
+
+/// A state cell whose mutations publish a [StateChange] on the [StateBus] it was created from.
+/// Register it with
+/// [AFPluginDispatcher::observable_state](crate::prelude::AFPluginDispatcher::observable_state)
+/// and read it in the handlers with the `AppData<ObservableState<T>>` extractor.
+///
+/// The clones share the same value.
+pub struct ObservableState<T> {
+  value: Arc<RwLock<Arc<T>>>,
+  bus: StateBus,
+}
+
+impl<T> Clone for ObservableState<T> {
+  fn clone(&self) -> Self {
+    Self {
+      value: self.value.clone(),
+      bus: self.bus.clone(),
+    }
+  }
+}
+
+impl<T: Debug> Debug for ObservableState<T> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_tuple("ObservableState").field(&self.get()).finish()
+  }
+}
+
+impl<T> ObservableState<T>
+where
+  T: Send + Sync + 'static,
+{
+  pub fn get(&self) -> Arc<T> {
+    self.value.read().unwrap().clone()
+  }
+
+  /// Replaces the value and publishes the change.
+  pub fn set(&self, value: T) {
+    self.replace(|_| value);
+  }
+
+  /// Updates a copy of the value with `f` and publishes the change.
+  pub fn update<F>(&self, f: F)
+  where
+    T: Clone,
+    F: FnOnce(&mut T),
+  {
+    self.replace(|old| {
+      let mut new = old.clone();
+      f(&mut new);
+      new
+    });
+  }
+
+  /// Receives the changes of the states of type `T` published from now on.
+  pub fn subscribe(&self) -> broadcast::Receiver<StateChange<T>> {
+    self.bus.subscribe::<T>()
+  }
+
+  fn replace<F>(&self, f: F)
+  where
+    F: FnOnce(&T) -> T,
+  {
+    // The change is published while the lock is held, so the subscribers see the changes in the
+    // order they were made.
+    let mut value = self.value.write().unwrap();
+    let new = Arc::new(f(value.as_ref()));
+    let old = std::mem::replace(&mut *value, new.clone());
+    self.bus.publish(StateChange { old, new });
+  }
+}
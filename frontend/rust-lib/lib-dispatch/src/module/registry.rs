@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+
+use crate::module::{AFPlugin, AFPluginEvent, AFPluginMap};
+
+/// The plugins of the dispatcher by event.
+///
+/// The map is never mutated in place: registering a plugin builds a new map and swaps it in
+/// atomically, so looking up the plugin of an event in the dispatch hot path never waits for a
+/// lock, even while plugins are being registered. The requests that already looked up their
+/// plugin keep running on it.
+#[derive(Clone)]
+pub struct AFPluginRegistry {
+  map: Arc<ArcSwap<HashMap<AFPluginEvent, Arc<AFPlugin>>>>,
+}
+
+impl AFPluginRegistry {
+  pub(crate) fn new(map: AFPluginMap) -> Self {
+    Self {
+      map: Arc::new(ArcSwap::new(map)),
+    }
+  }
+
+  /// The plugin that handles `event`.
+  pub fn get(&self, event: &AFPluginEvent) -> Option<Arc<AFPlugin>> {
+    self.map.load().get(event).cloned()
+  }
+
+  /// Calls `f` with the plugin that handles `event`, without taking a reference on it like
+  /// [AFPluginRegistry::get] does. The map stays pinned until `f` returns, so `f` must not
+  /// block.
+  pub(crate) fn with<R>(&self, event: &AFPluginEvent, f: impl FnOnce(Option<&AFPlugin>) -> R) -> R {
+    f(self.map.load().get(event).map(|plugin| plugin.as_ref()))
+  }
+
+  pub fn contains(&self, event: &AFPluginEvent) -> bool {
+    self.map.load().contains_key(event)
+  }
+
+  /// The current plugins. Later registrations are not visible in the returned map.
+  pub fn snapshot(&self) -> AFPluginMap {
+    self.map.load_full()
+  }
+
+  /// Adds `plugin`. Fails without changing the registry if one of its events is already
+  /// handled by another plugin.
+  pub fn register(&self, plugin: AFPlugin) -> Result<(), String> {
+    let events = plugin.routed_events();
+    #[allow(clippy::arc_with_non_send_sync)]
+    let plugin = Arc::new(plugin);
+    self.update(|current| {
+      if let Some((event, owner)) = events
+        .iter()
+        .find_map(|event| current.get(event).map(|owner| (event, owner)))
+      {
+        return Err(format!(
+          "{:?} is already defined in {:?}",
+          event, owner.name
+        ));
+      }
+      let mut map = current.clone();
+      for event in &events {
+        map.insert(event.clone(), plugin.clone());
+      }
+      Ok(map)
+    })
+  }
+
+  /// Routes the events of `plugin` to it, and drops the events of the plugin named like it that
+  /// `plugin` does not handle.
+  pub fn replace(&self, plugin: AFPlugin) {
+    let events = plugin.routed_events();
+    #[allow(clippy::arc_with_non_send_sync)]
+    let plugin = Arc::new(plugin);
+    let _ = self.update::<()>(|current| {
+      let mut map = current.clone();
+      map.retain(|_, current| current.name != plugin.name);
+      for event in &events {
+        map.insert(event.clone(), plugin.clone());
+      }
+      Ok(map)
+    });
+  }
+
+  /// Removes the plugin named `name`. Returns whether there was one.
+  pub fn unregister(&self, name: &str) -> bool {
+    let mut removed = false;
+    let _ = self.update::<()>(|current| {
+      let mut map = current.clone();
+      map.retain(|_, plugin| plugin.name != name);
+      removed = map.len() != current.len();
+      Ok(map)
+    });
+    removed
+  }
+
+  /// Builds the next map from the current one and swaps it in, starting over if another
+  /// registration swapped the map in the meantime.
+  fn update<E>(
+    &self,
+    mut f: impl FnMut(
+      &HashMap<AFPluginEvent, Arc<AFPlugin>>,
+    ) -> Result<HashMap<AFPluginEvent, Arc<AFPlugin>>, E>,
+  ) -> Result<(), E> {
+    let mut current = self.map.load_full();
+    loop {
+      #[allow(clippy::arc_with_non_send_sync)]
+      let next = Arc::new(f(&current)?);
+      let previous = self.map.compare_and_swap(&current, next);
+      if Arc::ptr_eq(&previous, &current) {
+        return Ok(());
+      }
+      current = arc_swap::Guard::into_inner(previous);
+    }
+  }
+}
@@ -0,0 +1,67 @@
+use std::ops::RangeInclusive;
+
+use serde::{Deserialize, Serialize};
+
+/// Describes what an event expects and what it returns.
+///
+/// The types are the ones of the handler, as named by [std::any::type_name]: the payload is the
+/// type built from the request, a tuple when the handler takes several arguments, and the
+/// response is the type returned by the handler. The names are meant for tooling and may change
+/// between compiler versions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventSchema {
+  pub event: String,
+  pub plugin: String,
+  pub payload: String,
+  pub response: String,
+  /// The contract versions the handler supports, if they were declared.
+  pub versions: Option<RangeInclusive<u32>>,
+}
+
+/// The types of a handler, recorded when it's registered.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct HandlerSchema {
+  pub(crate) payload: &'static str,
+  pub(crate) response: &'static str,
+}
+
+impl HandlerSchema {
+  pub(crate) fn new<T, R>() -> Self {
+    // The arguments of a handler that takes only one are still wrapped in a tuple.
+    let payload = std::any::type_name::<T>();
+    let payload = payload
+      .strip_prefix('(')
+      .and_then(|p| p.strip_suffix(",)"))
+      .unwrap_or(payload);
+    Self {
+      payload,
+      response: std::any::type_name::<R>(),
+    }
+  }
+
+  /// The type the handler decodes the payload into with `AFPluginData`, named without its path.
+  /// `None` if it doesn't take one, or if it's generic.
+  #[cfg(feature = "use_protobuf")]
+  pub(crate) fn data_type(&self) -> Option<&'static str> {
+    const DATA: &str = "AFPluginData<";
+    let start = self.payload.find(DATA)? + DATA.len();
+    let mut depth = 0;
+    let len = self.payload[start..].find(|c| match c {
+      '<' => {
+        depth += 1;
+        false
+      },
+      '>' if depth == 0 => true,
+      '>' => {
+        depth -= 1;
+        false
+      },
+      _ => false,
+    })?;
+    let data_type = &self.payload[start..start + len];
+    if data_type.contains('<') {
+      return None;
+    }
+    data_type.rsplit("::").next()
+  }
+}
@@ -0,0 +1,92 @@
+use std::any::Any;
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use crate::module::AFPluginState;
+
+/// A plugin state that can be saved and put back, so the tests can reset it between cases
+/// without rebuilding the dispatcher. Register it with [AFPlugin::snapshot_state].
+///
+/// The states are shared by the handlers, so [StateSnapshot::restore] takes `&self` and relies on
+/// the interior mutability of the state.
+///
+/// [AFPlugin::snapshot_state]: crate::module::AFPlugin::snapshot_state
+pub trait StateSnapshot: Send + Sync + 'static {
+  type Snapshot: Clone + Send + Sync + 'static;
+
+  fn snapshot(&self) -> Self::Snapshot;
+
+  fn restore(&self, snapshot: Self::Snapshot);
+
+  /// The state as JSON, shown by the debugging tools, see the `TimeTravel` of the `testing`
+  /// feature. `null` when not implemented.
+  fn inspect(&self) -> Value {
+    Value::Null
+  }
+}
+
+pub(crate) trait ErasedStateSnapshot: Send + Sync {
+  /// The type name of the state.
+  fn name(&self) -> &'static str;
+
+  fn snapshot(&self) -> Box<dyn Any + Send + Sync>;
+
+  fn restore(&self, snapshot: &(dyn Any + Send + Sync));
+
+  fn inspect(&self) -> Value;
+}
+
+pub(crate) struct StateSnapshotter<T: StateSnapshot>(pub(crate) AFPluginState<T>);
+
+impl<T: StateSnapshot> ErasedStateSnapshot for StateSnapshotter<T> {
+  fn name(&self) -> &'static str {
+    std::any::type_name::<T>()
+  }
+
+  fn snapshot(&self) -> Box<dyn Any + Send + Sync> {
+    Box::new(self.0.snapshot())
+  }
+
+  fn restore(&self, snapshot: &(dyn Any + Send + Sync)) {
+    if let Some(snapshot) = snapshot.downcast_ref::<T::Snapshot>() {
+      self.0.restore(snapshot.clone());
+    }
+  }
+
+  fn inspect(&self) -> Value {
+    self.0.inspect()
+  }
+}
+
+/// The saved states of a dispatcher, see
+/// [AFPluginDispatcher::snapshot_states](crate::prelude::AFPluginDispatcher::snapshot_states).
+/// It can be restored as many times as needed.
+pub struct StatesSnapshot {
+  entries: Vec<(Arc<dyn ErasedStateSnapshot>, Box<dyn Any + Send + Sync>)>,
+}
+
+impl StatesSnapshot {
+  pub(crate) fn capture(snapshotters: &[Arc<dyn ErasedStateSnapshot>]) -> Self {
+    let entries = snapshotters
+      .iter()
+      .map(|snapshotter| (snapshotter.clone(), snapshotter.snapshot()))
+      .collect();
+    Self { entries }
+  }
+
+  pub(crate) fn restore(&self) {
+    for (snapshotter, snapshot) in &self.entries {
+      snapshotter.restore(snapshot.as_ref());
+    }
+  }
+
+  /// The number of states saved in the snapshot.
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+}
@@ -0,0 +1,231 @@
+//! The offset/limit plumbing of the events returning a list, e.g. the rows of a grid.
+//!
+//! The sender asks for a page with [AFPluginRequest::page], which puts the [PageRequest] in the
+//! metadata of the request, and the handler reads it with the [PageRequest] extractor. The
+//! handler returns a [PageResponse], which adds the total count and the cursor of the next page
+//! to the metadata of the response, read back with [PageInfo::from_response].
+//!
+//! [AFPluginRequest::page]: crate::prelude::AFPluginRequest::page
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{DispatchError, InternalError};
+use crate::request::{AFPluginEventRequest, FromAFPluginRequest, Payload};
+use crate::response::{AFPluginEventResponse, AFPluginResponder, StatusCode};
+use crate::util::ready::{ready, Ready};
+
+/// The metadata of the requests holding the index of the first item of the page.
+pub const PAGE_OFFSET_METADATA: &str = "page-offset";
+/// The metadata of the requests holding the maximum number of items of the page.
+pub const PAGE_LIMIT_METADATA: &str = "page-limit";
+/// The metadata of the requests holding the cursor of the page, taken from the previous one.
+pub const PAGE_CURSOR_METADATA: &str = "page-cursor";
+/// The metadata of the responses holding the number of items of the whole list.
+pub const PAGE_TOTAL_METADATA: &str = "page-total";
+/// The metadata of the responses holding the cursor of the next page. Missing on the last page.
+pub const NEXT_PAGE_CURSOR_METADATA: &str = "page-next-cursor";
+
+/// The limit of the requests that don't set one.
+pub const DEFAULT_PAGE_LIMIT: usize = 50;
+/// The larger limits are lowered to it.
+pub const MAX_PAGE_LIMIT: usize = 1000;
+
+/// The page of a list a request asks for. The handlers paginating by key use the cursor, the
+/// other ones the offset.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageRequest {
+  pub offset: usize,
+  pub limit: usize,
+  pub cursor: Option<String>,
+}
+
+impl Default for PageRequest {
+  fn default() -> Self {
+    Self {
+      offset: 0,
+      limit: DEFAULT_PAGE_LIMIT,
+      cursor: None,
+    }
+  }
+}
+
+impl PageRequest {
+  pub fn new(offset: usize, limit: usize) -> Self {
+    Self {
+      offset,
+      limit,
+      cursor: None,
+    }
+  }
+
+  /// The page following the one whose response carried `cursor`.
+  pub fn after<C: ToString>(cursor: C, limit: usize) -> Self {
+    Self {
+      offset: 0,
+      limit,
+      cursor: Some(cursor.to_string()),
+    }
+  }
+
+  /// Slices the page out of the whole list, for the handlers that load it all anyway. The
+  /// cursors are the offsets of the pages, the cursor of the request wins over its offset.
+  pub fn paginate<T>(&self, items: Vec<T>) -> PageResponse<Vec<T>> {
+    let total = items.len();
+    let start = self
+      .cursor
+      .as_ref()
+      .and_then(|cursor| cursor.parse().ok())
+      .unwrap_or(self.offset)
+      .min(total);
+    let end = start.saturating_add(self.limit).min(total);
+    let page = PageResponse::new(items.into_iter().skip(start).take(end - start).collect())
+      .total(total as u64);
+    if end < total {
+      page.next_cursor(end)
+    } else {
+      page
+    }
+  }
+
+  pub(crate) fn to_metadata(&self, metadata: &mut HashMap<String, String>) {
+    metadata.insert(PAGE_OFFSET_METADATA.to_owned(), self.offset.to_string());
+    metadata.insert(PAGE_LIMIT_METADATA.to_owned(), self.limit.to_string());
+    if let Some(cursor) = &self.cursor {
+      metadata.insert(PAGE_CURSOR_METADATA.to_owned(), cursor.clone());
+    }
+  }
+
+  /// Reads the page from the metadata of a request. The missing values take their default, a
+  /// zero limit is rejected and the limits above [MAX_PAGE_LIMIT] are lowered to it.
+  pub fn from_metadata(metadata: &HashMap<String, String>) -> Result<Self, DispatchError> {
+    let offset = parse_metadata(metadata, PAGE_OFFSET_METADATA)?.unwrap_or(0);
+    let limit = match parse_metadata(metadata, PAGE_LIMIT_METADATA)? {
+      Some(0) => {
+        let msg = format!("The {} must be greater than 0", PAGE_LIMIT_METADATA);
+        return Err(InternalError::DeserializeFromBytes(msg).into());
+      },
+      Some(limit) => limit.min(MAX_PAGE_LIMIT),
+      None => DEFAULT_PAGE_LIMIT,
+    };
+    Ok(Self {
+      offset,
+      limit,
+      cursor: metadata.get(PAGE_CURSOR_METADATA).cloned(),
+    })
+  }
+}
+
+fn parse_metadata<T: FromStr>(
+  metadata: &HashMap<String, String>,
+  key: &str,
+) -> Result<Option<T>, DispatchError> {
+  match metadata.get(key) {
+    Some(value) => value.parse().map(Some).map_err(|_| {
+      let msg = format!("Invalid {}: {}", key, value);
+      InternalError::DeserializeFromBytes(msg).into()
+    }),
+    None => Ok(None),
+  }
+}
+
+impl FromAFPluginRequest for PageRequest {
+  type Error = DispatchError;
+  type Future = Ready<Result<Self, DispatchError>>;
+
+  #[inline]
+  fn from_request(req: &AFPluginEventRequest, _: &mut Payload) -> Self::Future {
+    ready(PageRequest::from_metadata(req.metadata()))
+  }
+}
+
+/// A page of a list, returned by a handler. `T` is the responder of the items, e.g.
+/// `Json<Vec<Row>>` or `AFPluginData<RepeatedRowPB>`. The total count and the next cursor are
+/// added to the metadata of the successful responses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageResponse<T> {
+  pub items: T,
+  pub total: Option<u64>,
+  pub next_cursor: Option<String>,
+}
+
+impl<T> PageResponse<T> {
+  pub fn new(items: T) -> Self {
+    Self {
+      items,
+      total: None,
+      next_cursor: None,
+    }
+  }
+
+  pub fn total(mut self, total: u64) -> Self {
+    self.total = Some(total);
+    self
+  }
+
+  /// The cursor of the next page. The last page has none.
+  pub fn next_cursor<C: ToString>(mut self, cursor: C) -> Self {
+    self.next_cursor = Some(cursor.to_string());
+    self
+  }
+
+  /// Converts the items, e.g. to wrap the `Vec` returned by [PageRequest::paginate] in the
+  /// responder of their encoding.
+  pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> PageResponse<U> {
+    PageResponse {
+      items: f(self.items),
+      total: self.total,
+      next_cursor: self.next_cursor,
+    }
+  }
+}
+
+impl<T> AFPluginResponder for PageResponse<T>
+where
+  T: AFPluginResponder,
+{
+  fn respond_to(self, req: &AFPluginEventRequest) -> AFPluginEventResponse {
+    let mut response = self.items.respond_to(req);
+    if response.status_code == StatusCode::Ok {
+      if let Some(total) = self.total {
+        response
+          .metadata
+          .insert(PAGE_TOTAL_METADATA.to_owned(), total.to_string());
+      }
+      if let Some(cursor) = self.next_cursor {
+        response
+          .metadata
+          .insert(NEXT_PAGE_CURSOR_METADATA.to_owned(), cursor);
+      }
+    }
+    response
+  }
+}
+
+/// What the response of a [PageResponse] tells about the list.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PageInfo {
+  pub total: Option<u64>,
+  pub next_cursor: Option<String>,
+}
+
+impl PageInfo {
+  pub fn from_response(response: &AFPluginEventResponse) -> Self {
+    Self {
+      total: response
+        .metadata
+        .get(PAGE_TOTAL_METADATA)
+        .and_then(|total| total.parse().ok()),
+      next_cursor: response.metadata.get(NEXT_PAGE_CURSOR_METADATA).cloned(),
+    }
+  }
+
+  /// The request of the next page, `None` on the last page.
+  pub fn next_page(&self, limit: usize) -> Option<PageRequest> {
+    self
+      .next_cursor
+      .as_ref()
+      .map(|cursor| PageRequest::after(cursor, limit))
+  }
+}
@@ -0,0 +1,309 @@
+//! Validates the payloads of the requests before they reach the handler, so a payload missing a
+//! field is rejected with the path of the field instead of failing deep in the handler.
+//!
+//! The schemas are registered with
+//! [AFPlugin::payload_schema](crate::prelude::AFPlugin::payload_schema).
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::encoding::ContentType;
+use crate::errors::Error;
+use crate::module::AFPluginRequest;
+use crate::response::{AFPluginEventResponse, ErrorOrigin, ResponseBuilder};
+
+/// What the payload of an event must look like.
+pub enum PayloadSchema {
+  /// A JSON schema, checked against the JSON, MessagePack and CBOR payloads. The keywords
+  /// supported are `type`, `enum`, `required`, `properties`, `additionalProperties`, `items`,
+  /// `minLength`, `maxLength`, `minimum`, `maximum`, `minItems` and `maxItems`, the other ones
+  /// are ignored.
+  Json(Value),
+  /// A protobuf message, checked against the protobuf payloads. The payload must decode as the
+  /// message and carry the `required` fields, since proto3 has no required fields of its own.
+  #[cfg(feature = "use_protobuf")]
+  Protobuf {
+    descriptor: fn() -> &'static protobuf::reflect::MessageDescriptor,
+    required: Vec<String>,
+  },
+}
+
+impl PayloadSchema {
+  pub fn json(schema: Value) -> Self {
+    PayloadSchema::Json(schema)
+  }
+
+  /// The schema of the payloads encoding `M`. Only the top level `required` fields are checked,
+  /// a scalar field holding its default value counts as missing.
+  #[cfg(feature = "use_protobuf")]
+  #[track_caller]
+  pub fn protobuf<M: protobuf::Message>(required: &[&str]) -> Self {
+    let descriptor = M::descriptor_static();
+    for field in required {
+      if !descriptor.fields().iter().any(|f| f.name() == *field) {
+        panic!("{} has no field {}", descriptor.name(), field);
+      }
+    }
+    PayloadSchema::Protobuf {
+      descriptor: M::descriptor_static,
+      required: required.iter().map(|field| field.to_string()).collect(),
+    }
+  }
+
+  /// Returns the errors of every invalid field. The payloads encoded differently than the
+  /// schema expects are left to the extractor of the handler, which rejects them.
+  pub fn validate(&self, request: &AFPluginRequest) -> Result<(), Vec<FieldError>> {
+    let errors = match self {
+      PayloadSchema::Json(schema) => match decode_value(request) {
+        Some(Ok(value)) => {
+          let mut errors = vec![];
+          validate_value(schema, &value, "", &mut errors);
+          errors
+        },
+        Some(Err(err)) => vec![FieldError::new("", err)],
+        None => vec![],
+      },
+      #[cfg(feature = "use_protobuf")]
+      PayloadSchema::Protobuf {
+        descriptor,
+        required,
+      } => match request.content_type {
+        None | Some(ContentType::Protobuf) => {
+          validate_message(descriptor(), required, request.payload_bytes())
+        },
+        _ => vec![],
+      },
+    };
+    if errors.is_empty() {
+      Ok(())
+    } else {
+      Err(errors)
+    }
+  }
+}
+
+/// Decodes the payload as a JSON value, `None` if it's encoded with protobuf or bincode, which
+/// can't be decoded without their type. An empty payload is `null`.
+fn decode_value(request: &AFPluginRequest) -> Option<Result<Value, String>> {
+  let bytes = request.payload_bytes();
+  if bytes.is_empty() {
+    return Some(Ok(Value::Null));
+  }
+  let value = match request.content_type {
+    None | Some(ContentType::Json) => serde_json::from_slice(bytes).map_err(|e| e.to_string()),
+    #[cfg(feature = "use_msgpack")]
+    Some(ContentType::MessagePack) => rmp_serde::from_slice(bytes).map_err(|e| e.to_string()),
+    #[cfg(feature = "use_cbor")]
+    Some(ContentType::Cbor) => ciborium::from_reader(bytes).map_err(|e| e.to_string()),
+    #[cfg(feature = "use_bincode")]
+    Some(ContentType::Bincode) => return None,
+    Some(ContentType::Protobuf) => return None,
+  };
+  Some(value)
+}
+
+fn validate_value(schema: &Value, value: &Value, path: &str, errors: &mut Vec<FieldError>) {
+  let schema = match schema.as_object() {
+    Some(schema) => schema,
+    None => return,
+  };
+  if let Some(expected) = schema.get("type") {
+    let types = match expected {
+      Value::String(ty) => vec![ty.as_str()],
+      Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+      _ => vec![],
+    };
+    if !types.is_empty() && !types.iter().any(|ty| has_type(value, ty)) {
+      let msg = format!("expected {}, got {}", types.join(" or "), type_name(value));
+      errors.push(FieldError::new(path, msg));
+      return;
+    }
+  }
+  if let Some(Value::Array(allowed)) = schema.get("enum") {
+    if !allowed.contains(value) {
+      errors.push(FieldError::new(
+        path,
+        format!("expected one of {:?}", allowed),
+      ));
+    }
+  }
+
+  let bound = |keyword: &str| schema.get(keyword).and_then(Value::as_f64);
+  match value {
+    Value::String(s) => {
+      let len = s.chars().count() as f64;
+      if let Some(min) = bound("minLength").filter(|min| len < *min) {
+        errors.push(FieldError::new(path, format!("shorter than {}", min)));
+      }
+      if let Some(max) = bound("maxLength").filter(|max| len > *max) {
+        errors.push(FieldError::new(path, format!("longer than {}", max)));
+      }
+    },
+    Value::Number(n) => {
+      let n = n.as_f64().unwrap_or_default();
+      if let Some(min) = bound("minimum").filter(|min| n < *min) {
+        errors.push(FieldError::new(path, format!("less than {}", min)));
+      }
+      if let Some(max) = bound("maximum").filter(|max| n > *max) {
+        errors.push(FieldError::new(path, format!("greater than {}", max)));
+      }
+    },
+    Value::Array(items) => {
+      let len = items.len() as f64;
+      if let Some(min) = bound("minItems").filter(|min| len < *min) {
+        errors.push(FieldError::new(path, format!("fewer than {} items", min)));
+      }
+      if let Some(max) = bound("maxItems").filter(|max| len > *max) {
+        errors.push(FieldError::new(path, format!("more than {} items", max)));
+      }
+      if let Some(item_schema) = schema.get("items") {
+        for (i, item) in items.iter().enumerate() {
+          validate_value(item_schema, item, &format!("{}/{}", path, i), errors);
+        }
+      }
+    },
+    Value::Object(fields) => {
+      if let Some(Value::Array(required)) = schema.get("required") {
+        for field in required.iter().filter_map(Value::as_str) {
+          if !fields.contains_key(field) {
+            errors.push(FieldError::new(field_path(path, field), "missing"));
+          }
+        }
+      }
+      let properties = schema.get("properties").and_then(Value::as_object);
+      for (field, value) in fields {
+        match properties.and_then(|properties| properties.get(field)) {
+          Some(field_schema) => {
+            validate_value(field_schema, value, &field_path(path, field), errors)
+          },
+          None => match schema.get("additionalProperties") {
+            Some(Value::Bool(false)) => {
+              errors.push(FieldError::new(field_path(path, field), "unexpected field"))
+            },
+            Some(field_schema) => {
+              validate_value(field_schema, value, &field_path(path, field), errors)
+            },
+            None => {},
+          },
+        }
+      }
+    },
+    _ => {},
+  }
+}
+
+fn has_type(value: &Value, ty: &str) -> bool {
+  match ty {
+    "integer" => value.as_f64().map_or(false, |n| n.fract() == 0.0),
+    ty => type_name(value) == ty,
+  }
+}
+
+fn type_name(value: &Value) -> &'static str {
+  match value {
+    Value::Null => "null",
+    Value::Bool(_) => "boolean",
+    Value::Number(_) => "number",
+    Value::String(_) => "string",
+    Value::Array(_) => "array",
+    Value::Object(_) => "object",
+  }
+}
+
+/// The JSON pointer of `field` in the object at `path`.
+fn field_path(path: &str, field: &str) -> String {
+  format!("{}/{}", path, field.replace('~', "~0").replace('/', "~1"))
+}
+
+#[cfg(feature = "use_protobuf")]
+fn validate_message(
+  descriptor: &protobuf::reflect::MessageDescriptor,
+  required: &[String],
+  bytes: &[u8],
+) -> Vec<FieldError> {
+  let mut message = descriptor.new_instance();
+  if let Err(err) = message.merge_from_bytes(bytes) {
+    return vec![FieldError::new("", err.to_string())];
+  }
+  descriptor
+    .fields()
+    .iter()
+    .filter(|field| required.iter().any(|name| name == field.name()))
+    .filter(|field| {
+      if field.is_repeated() {
+        field.len_field(message.as_ref()) == 0
+      } else {
+        !field.has_field(message.as_ref())
+      }
+    })
+    .map(|field| FieldError::new(field_path("", field.name()), "missing"))
+    .collect()
+}
+
+/// An invalid field of a payload.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldError {
+  /// The JSON pointer of the field, e.g. `/rows/2/id`. Empty for the payload itself.
+  pub path: String,
+  pub message: String,
+}
+
+impl FieldError {
+  fn new<P: Into<String>, M: Into<String>>(path: P, message: M) -> Self {
+    Self {
+      path: path.into(),
+      message: message.into(),
+    }
+  }
+}
+
+impl Display for FieldError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self.path.as_str() {
+      "" => write!(f, "payload: {}", self.message),
+      path => write!(f, "{}: {}", path, self.message),
+    }
+  }
+}
+
+/// The error of the requests whose payload doesn't match the schema of their event. The
+/// response carries it as JSON, read it back with [InvalidPayload::from_response].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InvalidPayload {
+  pub event: String,
+  pub errors: Vec<FieldError>,
+}
+
+impl InvalidPayload {
+  pub fn from_response(response: &AFPluginEventResponse) -> Option<Self> {
+    if response.error_origin != Some(ErrorOrigin::Dispatcher)
+      || response.content_type != Some(ContentType::Json)
+    {
+      return None;
+    }
+    serde_json::from_slice(response.payload.as_ref()).ok()
+  }
+}
+
+impl Display for InvalidPayload {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    let errors = self
+      .errors
+      .iter()
+      .map(|error| error.to_string())
+      .collect::<Vec<_>>();
+    write!(f, "InvalidPayload: {}, {}", self.event, errors.join(", "))
+  }
+}
+
+impl Error for InvalidPayload {
+  fn as_response(&self) -> AFPluginEventResponse {
+    let data = serde_json::to_vec(self).unwrap_or_else(|_| self.to_string().into_bytes());
+    ResponseBuilder::Err()
+      .data(data)
+      .content_type(ContentType::Json)
+      .error_origin(ErrorOrigin::Dispatcher)
+      .build()
+  }
+}
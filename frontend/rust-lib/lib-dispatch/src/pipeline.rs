@@ -0,0 +1,111 @@
+use std::convert::TryFrom;
+
+use crate::byte_trait::{AFPluginFromBytes, ToBytes};
+use crate::data::AFPluginData;
+use crate::errors::DispatchError;
+use crate::module::{AFPluginEvent, AFPluginRequest};
+use crate::prelude::AFPluginDispatcher;
+use crate::request::Payload;
+use crate::response::{AFPluginEventResponse, ResponseBuilder, StatusCode};
+
+type MapStage = Box<dyn FnOnce(Payload) -> Result<Payload, DispatchError> + Send>;
+
+enum Stage {
+  Event(AFPluginEvent),
+  Map(MapStage),
+}
+
+/// Starts a [Pipeline] without input.
+pub fn pipeline() -> Pipeline {
+  Pipeline {
+    input: Payload::None,
+    stages: vec![],
+  }
+}
+
+/// Chains events, the payload of each response being the payload of the next request, with
+/// conversions in between. Run with [AFPluginDispatcher::run_pipeline].
+///
+/// ```ignore
+/// let response = dispatcher
+///   .run_pipeline(
+///     pipeline()
+///       .then(FolderEvent::GetCurrentWorkspace)
+///       .map_data(|workspace: WorkspacePB| CreateViewPayloadPB::starter_doc(workspace.id))
+///       .then(FolderEvent::CreateView),
+///   )
+///   .await;
+/// ```
+pub struct Pipeline {
+  input: Payload,
+  stages: Vec<Stage>,
+}
+
+impl Pipeline {
+  /// The payload of the first request.
+  pub fn input<P>(mut self, payload: P) -> Self
+  where
+    P: Into<Payload>,
+  {
+    self.input = payload.into();
+    self
+  }
+
+  /// Sends the output of the previous stage to the handler of `event`.
+  pub fn then<E>(mut self, event: E) -> Self
+  where
+    E: Into<AFPluginEvent>,
+  {
+    self.stages.push(Stage::Event(event.into()));
+    self
+  }
+
+  /// Converts the output of the previous stage, an error stops the pipeline.
+  pub fn map<F>(mut self, f: F) -> Self
+  where
+    F: FnOnce(Payload) -> Result<Payload, DispatchError> + Send + 'static,
+  {
+    self.stages.push(Stage::Map(Box::new(f)));
+    self
+  }
+
+  /// Decodes the output of the previous stage as a `T` and encodes the converted `U`.
+  pub fn map_data<T, U, F>(self, f: F) -> Self
+  where
+    T: AFPluginFromBytes,
+    U: ToBytes,
+    F: FnOnce(T) -> U + Send + 'static,
+  {
+    self.map(|payload| {
+      let data = AFPluginData::<T>::try_from(payload)?.into_inner();
+      Ok(Payload::Bytes(f(data).into_bytes()?))
+    })
+  }
+
+  /// Returns the response of the last event, or the first error response. A pipeline ending
+  /// with a conversion returns its output as an Ok response.
+  pub(crate) async fn run(self, dispatch: &AFPluginDispatcher) -> AFPluginEventResponse {
+    let mut response = ResponseBuilder::Ok().data(self.input).build();
+    for stage in self.stages {
+      match stage {
+        Stage::Event(event) => {
+          tracing::trace!("[pipeline]: {:?}", event);
+          let request = AFPluginRequest::new(event).payload(response.payload);
+          response =
+            AFPluginDispatcher::async_send_with_callback(dispatch, request, |_| Box::pin(async {}))
+              .await;
+        },
+        Stage::Map(f) => {
+          response = match f(response.payload) {
+            Ok(payload) => ResponseBuilder::Ok().data(payload).build(),
+            Err(err) => err.into(),
+          };
+        },
+      }
+      if response.status_code != StatusCode::Ok {
+        break;
+      }
+    }
+    response
+  }
+}
@@ -0,0 +1,163 @@
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use super::{DispatchPhase, DispatchProbe};
+use crate::module::AFPluginEvent;
+
+/// The number of phases a [ChromeTracer] keeps, the later ones are dropped.
+pub const DEFAULT_TRACE_CAPACITY: usize = 100_000;
+
+/// Records the [DispatchPhase]s of the requests as a timeline in the Chrome trace event format,
+/// which `chrome://tracing` and Perfetto open. Every dispatcher has one, started and stopped with
+/// [SysEvent::Trace](crate::system::SysEvent::Trace), it records nothing until then.
+pub struct ChromeTracer {
+  enabled: AtomicBool,
+  origin: Instant,
+  capacity: usize,
+  dropped: AtomicU64,
+  events: Mutex<Vec<TraceEvent>>,
+}
+
+/// A phase of a request, a complete event of the trace event format.
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceEvent {
+  /// The event of the request.
+  pub name: String,
+  /// The phase.
+  pub cat: &'static str,
+  pub ph: &'static str,
+  /// The start of the phase, in microseconds since the tracer was created.
+  pub ts: u64,
+  /// The duration of the phase, in microseconds.
+  pub dur: u64,
+  pub pid: u32,
+  /// The thread the phase ended on.
+  pub tid: u64,
+  pub args: TraceArgs,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceArgs {
+  pub request_id: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Trace<'a> {
+  trace_events: &'a [TraceEvent],
+  display_time_unit: &'static str,
+}
+
+impl Default for ChromeTracer {
+  fn default() -> Self {
+    Self::new(DEFAULT_TRACE_CAPACITY)
+  }
+}
+
+impl ChromeTracer {
+  pub fn new(capacity: usize) -> Self {
+    Self {
+      enabled: AtomicBool::new(false),
+      origin: Instant::now(),
+      capacity,
+      dropped: AtomicU64::new(0),
+      events: Mutex::new(vec![]),
+    }
+  }
+
+  pub fn is_enabled(&self) -> bool {
+    self.enabled.load(Ordering::Relaxed)
+  }
+
+  /// Starts recording, the phases recorded before are cleared.
+  pub fn start(&self) {
+    self.clear();
+    self.enabled.store(true, Ordering::Relaxed);
+  }
+
+  /// Stops recording and returns the trace, see [ChromeTracer::to_json].
+  pub fn stop(&self) -> String {
+    self.enabled.store(false, Ordering::Relaxed);
+    self.to_json()
+  }
+
+  pub fn clear(&self) {
+    if let Ok(mut events) = self.events.lock() {
+      events.clear();
+    }
+    self.dropped.store(0, Ordering::Relaxed);
+  }
+
+  /// The recorded phases, in the order they ended.
+  pub fn events(&self) -> Vec<TraceEvent> {
+    match self.events.lock() {
+      Ok(events) => events.clone(),
+      Err(poisoned) => poisoned.into_inner().clone(),
+    }
+  }
+
+  /// The number of phases dropped because the tracer was full.
+  pub fn dropped(&self) -> u64 {
+    self.dropped.load(Ordering::Relaxed)
+  }
+
+  /// The recorded phases as a trace event JSON object, to save in a file and open in
+  /// `chrome://tracing`.
+  pub fn to_json(&self) -> String {
+    let events = self.events();
+    let trace = Trace {
+      trace_events: &events,
+      display_time_unit: "ms",
+    };
+    serde_json::to_string(&trace).unwrap_or_default()
+  }
+}
+
+impl DispatchProbe for ChromeTracer {
+  fn exit(&self, request_id: &str, event: &AFPluginEvent, phase: DispatchPhase, elapsed: Duration) {
+    if !self.is_enabled() {
+      return;
+    }
+    let started_at = Instant::now()
+      .checked_sub(elapsed)
+      .unwrap_or(self.origin)
+      .max(self.origin);
+    let trace_event = TraceEvent {
+      name: event.as_str().to_owned(),
+      cat: phase.as_str(),
+      ph: "X",
+      ts: started_at.duration_since(self.origin).as_micros() as u64,
+      dur: elapsed.as_micros() as u64,
+      pid: 1,
+      tid: thread_index(),
+      args: TraceArgs {
+        request_id: request_id.to_owned(),
+      },
+    };
+    if let Ok(mut events) = self.events.lock() {
+      if events.len() < self.capacity {
+        events.push(trace_event);
+        return;
+      }
+    }
+    self.dropped.fetch_add(1, Ordering::Relaxed);
+  }
+}
+
+/// A small number identifying the current thread, the trace viewers expect integers.
+fn thread_index() -> u64 {
+  static NEXT_THREAD: AtomicU64 = AtomicU64::new(1);
+  thread_local! {
+    static THREAD: Cell<u64> = Cell::new(0);
+  }
+  THREAD.with(|thread| {
+    if thread.get() == 0 {
+      thread.set(NEXT_THREAD.fetch_add(1, Ordering::Relaxed));
+    }
+    thread.get()
+  })
+}
@@ -0,0 +1,109 @@
+pub use chrome::*;
+
+mod chrome;
+
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::module::AFPluginEvent;
+
+/// The phases a request goes through while being dispatched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DispatchPhase {
+  /// From the moment the request is sent until the dispatcher starts handling it.
+  QueueWait,
+  /// Building the handler's parameters from the request.
+  Extraction,
+  /// Running the handler.
+  Handler,
+  /// Converting the value returned by the handler into the response.
+  Serialization,
+}
+
+impl DispatchPhase {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      DispatchPhase::QueueWait => "queue_wait",
+      DispatchPhase::Extraction => "extraction",
+      DispatchPhase::Handler => "handler",
+      DispatchPhase::Serialization => "serialization",
+    }
+  }
+}
+
+impl Display for DispatchPhase {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.write_str(self.as_str())
+  }
+}
+
+/// A probe is notified when a request enters and exits each [DispatchPhase]. Implement it to
+/// feed a profiler or to write Chrome tracing files.
+///
+/// The probes are called synchronously on the dispatch path, so they should return quickly.
+pub trait DispatchProbe: Send + Sync + 'static {
+  fn enter(&self, _request_id: &str, _event: &AFPluginEvent, _phase: DispatchPhase) {}
+
+  fn exit(
+    &self,
+    _request_id: &str,
+    _event: &AFPluginEvent,
+    _phase: DispatchPhase,
+    _elapsed: Duration,
+  ) {
+  }
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct DispatchProbes(Arc<Vec<Arc<dyn DispatchProbe>>>);
+
+impl Debug for DispatchProbes {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "DispatchProbes({})", self.0.len())
+  }
+}
+
+impl DispatchProbes {
+  pub(crate) fn push(&mut self, probe: Arc<dyn DispatchProbe>) {
+    Arc::make_mut(&mut self.0).push(probe);
+  }
+
+  pub(crate) fn enter(&self, request_id: &str, event: &AFPluginEvent, phase: DispatchPhase) {
+    for probe in self.0.iter() {
+      probe.enter(request_id, event, phase);
+    }
+  }
+
+  /// Notifies the probes that the phase that started at `started_at` is done.
+  pub(crate) fn exit(
+    &self,
+    request_id: &str,
+    event: &AFPluginEvent,
+    phase: DispatchPhase,
+    started_at: Instant,
+  ) {
+    if self.0.is_empty() {
+      return;
+    }
+    let elapsed = started_at.elapsed();
+    for probe in self.0.iter() {
+      probe.exit(request_id, event, phase, elapsed);
+    }
+  }
+
+  /// Runs `f` between the enter and exit notifications of `phase`.
+  pub(crate) fn scope<T>(
+    &self,
+    request_id: &str,
+    event: &AFPluginEvent,
+    phase: DispatchPhase,
+    f: impl FnOnce() -> T,
+  ) -> T {
+    let started_at = Instant::now();
+    self.enter(request_id, event, phase);
+    let value = f();
+    self.exit(request_id, event, phase, started_at);
+    value
+  }
+}
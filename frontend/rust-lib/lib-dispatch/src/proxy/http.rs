@@ -0,0 +1,79 @@
+use futures_core::future::BoxFuture;
+use hyper::client::HttpConnector;
+use hyper::header::CONTENT_TYPE;
+use hyper::{Body, Client, Method, Request};
+
+use super::{RemoteRequest, RemoteResponse, RemoteTransport, TransportError};
+use crate::encoding::ContentType;
+use crate::response::StatusCode;
+
+/// Sends the requests to the [HttpGateway](crate::bridge::HttpGateway) of the remote core at
+/// `base_url`, e.g. `http://10.0.0.2:8080`.
+#[derive(Clone)]
+pub struct HttpTransport {
+  base_url: String,
+  client: Client<HttpConnector>,
+}
+
+impl HttpTransport {
+  pub fn new<T: ToString>(base_url: T) -> Self {
+    Self {
+      base_url: base_url.to_string().trim_end_matches('/').to_owned(),
+      client: Client::new(),
+    }
+  }
+}
+
+impl RemoteTransport for HttpTransport {
+  fn send(
+    &self,
+    request: RemoteRequest,
+  ) -> BoxFuture<'static, Result<RemoteResponse, TransportError>> {
+    let client = self.client.clone();
+    let uri = format!("{}/api/{}", self.base_url, request.event);
+    Box::pin(async move {
+      let mut http_request = Request::builder().method(Method::POST).uri(uri);
+      if let Some(content_type) = request.content_type {
+        http_request = http_request.header(CONTENT_TYPE, content_type.as_str());
+      }
+      let http_request = http_request
+        .body(Body::from(request.payload))
+        .map_err(|err| TransportError(err.to_string()))?;
+      let response = client
+        .request(http_request)
+        .await
+        .map_err(|err| TransportError(err.to_string()))?;
+      let status = response.status().as_u16();
+      let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(ContentType::from_mime);
+      let payload = hyper::body::to_bytes(response.into_body())
+        .await
+        .map_err(|err| TransportError(err.to_string()))?;
+      // The gateway answers 400 when the handler failed, the other codes come from the gateway
+      // itself, e.g. when the remote core doesn't know the event, or from the servers in between.
+      let status_code = match status {
+        200 => StatusCode::Ok,
+        400 => StatusCode::Err,
+        status if status >= 500 => {
+          return Err(TransportError(format!("remote core answered {}", status)));
+        },
+        _ => {
+          let message = format!("remote core answered {}", status);
+          return Ok(RemoteResponse {
+            status_code: StatusCode::Err,
+            payload: message.into(),
+            content_type: None,
+          });
+        },
+      };
+      Ok(RemoteResponse {
+        status_code,
+        payload,
+        content_type,
+      })
+    })
+  }
+}
@@ -0,0 +1,97 @@
+use std::path::PathBuf;
+
+use futures_core::future::BoxFuture;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+
+use super::{RemoteRequest, RemoteResponse, RemoteTransport, TransportError};
+use crate::bridge::frame::{decode_response, encode_request};
+use crate::bridge::MAX_FRAME_SIZE;
+
+/// Sends the requests to the helper process serving its dispatcher on the Unix domain socket at
+/// `path`, see [serve_unix_socket](crate::bridge::serve_unix_socket). Each request opens its own
+/// connection, so a restarted helper is picked up by the next request.
+#[derive(Clone, Debug)]
+pub struct UnixSocketTransport {
+  path: PathBuf,
+  token: Option<String>,
+}
+
+impl UnixSocketTransport {
+  pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+    Self {
+      path: path.into(),
+      token: None,
+    }
+  }
+
+  /// Sends the session `token` along with the requests, for the helpers authenticating them
+  /// with the [AuthMiddleware](crate::prelude::AuthMiddleware).
+  pub fn with_token<T: ToString>(mut self, token: T) -> Self {
+    self.token = Some(token.to_string());
+    self
+  }
+}
+
+impl RemoteTransport for UnixSocketTransport {
+  fn send(
+    &self,
+    request: RemoteRequest,
+  ) -> BoxFuture<'static, Result<RemoteResponse, TransportError>> {
+    let path = self.path.clone();
+    let token = self.token.clone();
+    Box::pin(async move {
+      exchange(path, token, request)
+        .await
+        .map_err(|err| TransportError(err.to_string()))
+    })
+  }
+}
+
+async fn exchange(
+  path: PathBuf,
+  token: Option<String>,
+  request: RemoteRequest,
+) -> std::io::Result<RemoteResponse> {
+  let frame = encode_request(
+    0,
+    &request.event,
+    request.content_type,
+    token.as_deref(),
+    &request.payload,
+  )
+  .map_err(invalid_data)?;
+  let mut stream = UnixStream::connect(&path).await?;
+  stream
+    .write_all(&(frame.len() as u32).to_be_bytes())
+    .await?;
+  stream.write_all(&frame).await?;
+  stream.flush().await?;
+
+  loop {
+    let mut len = [0u8; 4];
+    stream.read_exact(&mut len).await?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME_SIZE {
+      return Err(invalid_data(format!(
+        "frame of {} bytes exceeds {} bytes",
+        len, MAX_FRAME_SIZE
+      )));
+    }
+    let mut frame = vec![0u8; len];
+    stream.read_exact(&mut frame).await?;
+    if let Some((_, status_code, content_type, payload)) =
+      decode_response(&frame).map_err(invalid_data)?
+    {
+      return Ok(RemoteResponse {
+        status_code,
+        payload,
+        content_type,
+      });
+    }
+  }
+}
+
+fn invalid_data(msg: String) -> std::io::Error {
+  std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+}
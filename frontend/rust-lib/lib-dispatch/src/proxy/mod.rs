@@ -0,0 +1,150 @@
+//! Forwards events to the dispatcher of a remote core: a server for the thin clients, or a
+//! helper process hosting some of the modules, see [RoutingTable].
+
+#[cfg(feature = "http_bridge")]
+pub use http::*;
+#[cfg(unix)]
+pub use local_socket::*;
+pub use routing::*;
+
+#[cfg(feature = "http_bridge")]
+mod http;
+#[cfg(unix)]
+mod local_socket;
+mod routing;
+
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures_core::future::BoxFuture;
+
+use crate::encoding::ContentType;
+use crate::errors::{DispatchError, InternalError};
+use crate::request::{AFPluginEventRequest, FromAFPluginRequest, Payload};
+use crate::response::{AFPluginEventResponse, ResponseBuilder, StatusCode};
+use crate::util::ready::{ready, Ready};
+
+/// The request sent to the remote core.
+#[derive(Debug, Clone)]
+pub struct RemoteRequest {
+  pub event: String,
+  pub payload: Bytes,
+  /// The encoding of the payload, if the sender specified it.
+  pub content_type: Option<ContentType>,
+}
+
+/// The response of the remote core, `Err` if the handler, or the remote dispatcher, failed.
+#[derive(Debug, Clone)]
+pub struct RemoteResponse {
+  pub status_code: StatusCode,
+  pub payload: Bytes,
+  pub content_type: Option<ContentType>,
+}
+
+/// The request or its response was lost on the way, e.g. the connection dropped or timed out.
+/// The remote core may or may not have handled the request.
+#[derive(Debug, Clone)]
+pub struct TransportError(pub String);
+
+impl Display for TransportError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.write_str(&self.0)
+  }
+}
+
+impl std::error::Error for TransportError {}
+
+/// Carries the requests to the remote core and brings their responses back, e.g. over HTTP
+/// with [HttpTransport] or over a WebSocket.
+pub trait RemoteTransport: Send + Sync + 'static {
+  fn send(
+    &self,
+    request: RemoteRequest,
+  ) -> BoxFuture<'static, Result<RemoteResponse, TransportError>>;
+}
+
+/// The handler of the events forwarded to a remote core, registered with
+/// [AFPlugin::proxy](crate::prelude::AFPlugin::proxy). The transport failures are answered with
+/// an error whose [ErrorOrigin](crate::prelude::ErrorOrigin) is `Transport`, the requests may be
+/// retried.
+#[derive(Clone)]
+pub struct RemoteProxy {
+  transport: Arc<dyn RemoteTransport>,
+  timeout: Option<Duration>,
+}
+
+impl RemoteProxy {
+  pub fn new<T: RemoteTransport>(transport: T) -> Self {
+    Self {
+      transport: Arc::new(transport),
+      timeout: None,
+    }
+  }
+
+  /// Fails the requests whose response didn't come back within `timeout`.
+  pub fn timeout(mut self, timeout: Duration) -> Self {
+    self.timeout = Some(timeout);
+    self
+  }
+
+  /// Sends `request` to the remote core, failing if the response didn't come back in time.
+  pub(crate) async fn send(
+    &self,
+    request: RemoteRequest,
+  ) -> Result<RemoteResponse, TransportError> {
+    let sent = self.transport.send(request);
+    match self.timeout {
+      None => sent.await,
+      Some(timeout) => tokio::time::timeout(timeout, sent)
+        .await
+        .unwrap_or_else(|_| Err(TransportError(format!("timed out after {:?}", timeout)))),
+    }
+  }
+
+  pub(crate) async fn forward(self, request: ForwardedRequest) -> AFPluginEventResponse {
+    let event = request.0.event.clone();
+    match self.send(request.0).await {
+      Ok(response) => response.into(),
+      Err(err) => {
+        tracing::warn!("[proxy]: forward {} failed: {}", event, err);
+        transport_error(&event, &err).into()
+      },
+    }
+  }
+}
+
+impl From<RemoteResponse> for AFPluginEventResponse {
+  fn from(response: RemoteResponse) -> Self {
+    let builder = ResponseBuilder::new(response.status_code).data(response.payload);
+    match response.content_type {
+      Some(content_type) => builder.content_type(content_type).build(),
+      None => builder.build(),
+    }
+  }
+}
+
+pub(crate) fn transport_error(event: &str, err: &TransportError) -> DispatchError {
+  InternalError::Transport(format!("{}: {}", event, err)).into()
+}
+
+/// The event and the raw payload of the request handled by a [RemoteProxy].
+pub(crate) struct ForwardedRequest(pub(crate) RemoteRequest);
+
+impl FromAFPluginRequest for ForwardedRequest {
+  type Error = DispatchError;
+  type Future = Ready<Result<Self, DispatchError>>;
+
+  fn from_request(req: &AFPluginEventRequest, payload: &mut Payload) -> Self::Future {
+    let payload = match payload {
+      Payload::None => Bytes::new(),
+      Payload::Bytes(bytes) => bytes.clone(),
+    };
+    ready(Ok(ForwardedRequest(RemoteRequest {
+      event: req.event.as_str().to_owned(),
+      payload,
+      content_type: req.content_type,
+    })))
+  }
+}
@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+
+use super::{transport_error, RemoteProxy, RemoteRequest};
+use crate::errors::DispatchError;
+use crate::module::{AFPluginEvent, AFPluginRequest};
+use crate::request::Payload;
+use crate::response::AFPluginEventResponse;
+
+/// The process hosting some of the modules out of the core, e.g. the indexer, reached through
+/// its [RemoteProxy]. After `max_failures` transport failures in a row the endpoint is down: its
+/// events are handled by the local stubs until `retry_after` elapsed, then the next request
+/// tries the endpoint again.
+pub struct RemoteEndpoint {
+  name: String,
+  proxy: RemoteProxy,
+  max_failures: u32,
+  retry_after: Duration,
+  health: Mutex<EndpointHealth>,
+}
+
+#[derive(Default)]
+struct EndpointHealth {
+  failures: u32,
+  down_since: Option<Instant>,
+}
+
+impl RemoteEndpoint {
+  pub fn new<T: ToString>(name: T, proxy: RemoteProxy) -> Self {
+    Self {
+      name: name.to_string(),
+      proxy,
+      max_failures: 3,
+      retry_after: Duration::from_secs(10),
+      health: Mutex::new(EndpointHealth::default()),
+    }
+  }
+
+  pub fn max_failures(mut self, max_failures: u32) -> Self {
+    self.max_failures = max_failures.max(1);
+    self
+  }
+
+  pub fn retry_after(mut self, retry_after: Duration) -> Self {
+    self.retry_after = retry_after;
+    self
+  }
+
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  /// Whether the last requests reached the endpoint.
+  pub fn is_healthy(&self) -> bool {
+    self.health.lock().unwrap().down_since.is_none()
+  }
+
+  fn is_available(&self, now: Instant) -> bool {
+    match self.health.lock().unwrap().down_since {
+      None => true,
+      Some(down_since) => now.saturating_duration_since(down_since) >= self.retry_after,
+    }
+  }
+
+  fn record_success(&self) {
+    let mut health = self.health.lock().unwrap();
+    if health.down_since.is_some() {
+      tracing::info!("[routing]: {} is back up", self.name);
+    }
+    *health = EndpointHealth::default();
+  }
+
+  fn record_failure(&self) {
+    let mut health = self.health.lock().unwrap();
+    health.failures += 1;
+    // A failed retry puts the endpoint down for another `retry_after`.
+    if health.failures >= self.max_failures || health.down_since.is_some() {
+      if health.down_since.is_none() {
+        tracing::warn!("[routing]: {} is down", self.name);
+      }
+      health.down_since = Some(Instant::now());
+    }
+  }
+
+  /// Forwards `request` to the endpoint. The transport failures are returned as errors, for the
+  /// dispatcher to fail over to the local stub.
+  pub(crate) async fn forward(
+    &self,
+    request: &AFPluginRequest,
+  ) -> Result<AFPluginEventResponse, DispatchError> {
+    let payload = match &request.payload {
+      Payload::None => Bytes::new(),
+      Payload::Bytes(bytes) => bytes.clone(),
+    };
+    let remote_request = RemoteRequest {
+      event: request.event.as_str().to_owned(),
+      payload,
+      content_type: request.content_type,
+    };
+    match self.proxy.send(remote_request).await {
+      Ok(response) => {
+        self.record_success();
+        Ok(response.into())
+      },
+      Err(err) => {
+        tracing::warn!(
+          "[routing]: forward {} to {} failed: {}",
+          request.event.as_str(),
+          self.name,
+          err
+        );
+        self.record_failure();
+        Err(transport_error(request.event.as_str(), &err))
+      },
+    }
+  }
+}
+
+/// Maps the events to the endpoints hosting them out of process. The events that aren't routed
+/// are handled locally, and so are the routed ones while their endpoint is down: the plugins
+/// register local stubs for them, answering with a degraded result or an error.
+#[derive(Default)]
+pub struct RoutingTable {
+  routes: RwLock<HashMap<AFPluginEvent, Arc<RemoteEndpoint>>>,
+}
+
+impl RoutingTable {
+  /// Sends the requests of `event` to `endpoint`, replacing its previous route.
+  pub fn route<E: Into<AFPluginEvent>>(&self, event: E, endpoint: Arc<RemoteEndpoint>) {
+    let event = event.into();
+    tracing::trace!("[routing]: {} -> {}", event.as_str(), endpoint.name);
+    self.routes.write().unwrap().insert(event, endpoint);
+  }
+
+  /// Handles the requests of `event` locally again.
+  pub fn unroute<E: Into<AFPluginEvent>>(&self, event: E) -> Option<Arc<RemoteEndpoint>> {
+    self.routes.write().unwrap().remove(&event.into())
+  }
+
+  pub fn endpoint<E: Into<AFPluginEvent>>(&self, event: E) -> Option<Arc<RemoteEndpoint>> {
+    self.routes.read().unwrap().get(&event.into()).cloned()
+  }
+
+  /// The endpoint the request of `event` must be forwarded to, `None` if it's handled locally.
+  pub(crate) fn available(&self, event: &AFPluginEvent) -> Option<Arc<RemoteEndpoint>> {
+    self
+      .routes
+      .read()
+      .unwrap()
+      .get(event)
+      .filter(|endpoint| endpoint.is_available(Instant::now()))
+      .cloned()
+  }
+
+  pub(crate) fn is_routed(&self, event: &AFPluginEvent) -> bool {
+    self.routes.read().unwrap().contains_key(event)
+  }
+}
@@ -0,0 +1,414 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use pin_project::pin_project;
+use serde::{Deserialize, Serialize};
+
+use crate::encoding::ContentType;
+use crate::errors::Error;
+use crate::memory::MemoryReporter;
+use crate::metrics::{
+  Counter, Gauge, Histogram, MetricsRegistry, MODULE_CPU_SECONDS, MODULE_IN_FLIGHT,
+  MODULE_MEMORY_BYTES, MODULE_QUEUED, MODULE_THROTTLED_TOTAL,
+};
+use crate::module::AFPluginEvent;
+use crate::response::{AFPluginEventResponse, ErrorOrigin, ResponseBuilder};
+
+/// The memory of a module is estimated at most this often when it has a memory quota, the
+/// reporters may take their time.
+const MEMORY_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The resources a module may use before its events are throttled, see
+/// [AFPluginDispatcher::with_quota](crate::prelude::AFPluginDispatcher::with_quota). No limit by
+/// default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ModuleQuota {
+  pub cpu_time: Option<CpuQuota>,
+  pub max_pending: Option<usize>,
+  pub max_memory_bytes: Option<usize>,
+}
+
+/// At most `budget` of handler time every `window`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuQuota {
+  pub budget: Duration,
+  pub window: Duration,
+}
+
+impl ModuleQuota {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// The time the handlers of the module may spend running on the runtime, polling their
+  /// futures, every `window`. Once it's spent, the events are throttled until the window ends.
+  pub fn cpu_time(mut self, budget: Duration, window: Duration) -> Self {
+    self.cpu_time = Some(CpuQuota { budget, window });
+    self
+  }
+
+  /// The requests of the module queued or being handled. A request starting while that many
+  /// others are pending is throttled.
+  pub fn max_pending(mut self, max_pending: usize) -> Self {
+    self.max_pending = Some(max_pending);
+    self
+  }
+
+  /// The memory reported by the module, see
+  /// [AFPlugin::memory_reporter](crate::prelude::AFPlugin::memory_reporter).
+  pub fn max_memory_bytes(mut self, max_memory_bytes: usize) -> Self {
+    self.max_memory_bytes = Some(max_memory_bytes);
+    self
+  }
+}
+
+/// What a module used, see [ResourceAccounting::usage].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModuleUsage {
+  /// The handler time since the dispatcher started.
+  pub cpu_time: Duration,
+  /// The handler time of the current window of the [CpuQuota].
+  pub window_cpu_time: Duration,
+  pub queued: usize,
+  pub in_flight: usize,
+  pub memory_bytes: usize,
+  /// The number of requests throttled since the dispatcher started.
+  pub throttled: u64,
+}
+
+/// The resources of a module that can be exceeded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaResource {
+  CpuTime,
+  Pending,
+  Memory,
+}
+
+/// Accounts the resources used by each module, by plugin name, and throttles the events of the
+/// modules that exceed their [ModuleQuota]. The usage is exported through the metrics, labeled
+/// by module.
+pub struct ResourceAccounting {
+  metrics: Arc<MetricsRegistry>,
+  accounts: RwLock<HashMap<String, Arc<ModuleAccount>>>,
+}
+
+impl ResourceAccounting {
+  pub(crate) fn new(metrics: Arc<MetricsRegistry>) -> Self {
+    Self {
+      metrics,
+      accounts: RwLock::new(HashMap::new()),
+    }
+  }
+
+  /// Replaces the quota of `module`, the requests already being handled keep running.
+  pub fn set_quota(&self, module: &str, quota: ModuleQuota) {
+    *self.account(module).quota.write().unwrap() = quota;
+  }
+
+  pub fn quota(&self, module: &str) -> ModuleQuota {
+    *self.account(module).quota.read().unwrap()
+  }
+
+  /// What `module` used, with its memory estimated now.
+  pub fn usage(&self, module: &str) -> ModuleUsage {
+    let account = self.account(module);
+    let memory_bytes = account.sample_memory(Instant::now());
+    let (window_cpu_time, _) = account.window_cpu_time(Instant::now());
+    ModuleUsage {
+      cpu_time: Duration::from_nanos(account.cpu_nanos.load(Ordering::Relaxed)),
+      window_cpu_time,
+      queued: account.queued.load(Ordering::Relaxed),
+      in_flight: account.in_flight.load(Ordering::Relaxed),
+      memory_bytes,
+      throttled: account.throttled.get(),
+    }
+  }
+
+  /// The modules that were accounted, sorted.
+  pub fn modules(&self) -> Vec<String> {
+    let mut modules = self
+      .accounts
+      .read()
+      .unwrap()
+      .keys()
+      .cloned()
+      .collect::<Vec<_>>();
+    modules.sort();
+    modules
+  }
+
+  pub(crate) fn register_memory(&self, module: &str, reporter: Arc<dyn MemoryReporter>) {
+    self.account(module).memory.write().unwrap().push(reporter);
+  }
+
+  pub(crate) fn account(&self, module: &str) -> Arc<ModuleAccount> {
+    if let Some(account) = self.accounts.read().unwrap().get(module) {
+      return account.clone();
+    }
+    self
+      .accounts
+      .write()
+      .unwrap()
+      .entry(module.to_owned())
+      .or_insert_with(|| Arc::new(ModuleAccount::new(module, &self.metrics)))
+      .clone()
+  }
+}
+
+/// The usage of a module, updated by the dispatcher.
+pub(crate) struct ModuleAccount {
+  module: String,
+  quota: RwLock<ModuleQuota>,
+  cpu_nanos: AtomicU64,
+  /// The start of the current window of the [CpuQuota] and the handler time spent in it.
+  window: Mutex<(Instant, Duration)>,
+  queued: AtomicUsize,
+  in_flight: AtomicUsize,
+  memory: RwLock<Vec<Arc<dyn MemoryReporter>>>,
+  /// The last memory estimate and when it was taken.
+  memory_sample: Mutex<Option<(Instant, usize)>>,
+  cpu_histogram: Arc<Histogram>,
+  queued_gauge: Arc<Gauge>,
+  in_flight_gauge: Arc<Gauge>,
+  memory_gauge: Arc<Gauge>,
+  throttled: Arc<Counter>,
+}
+
+impl ModuleAccount {
+  fn new(module: &str, metrics: &MetricsRegistry) -> Self {
+    let labels = [("module", module)];
+    Self {
+      module: module.to_owned(),
+      quota: RwLock::new(ModuleQuota::default()),
+      cpu_nanos: AtomicU64::new(0),
+      window: Mutex::new((Instant::now(), Duration::ZERO)),
+      queued: AtomicUsize::new(0),
+      in_flight: AtomicUsize::new(0),
+      memory: RwLock::new(vec![]),
+      memory_sample: Mutex::new(None),
+      cpu_histogram: metrics.histogram(MODULE_CPU_SECONDS, &labels),
+      queued_gauge: metrics.gauge(MODULE_QUEUED, &labels),
+      in_flight_gauge: metrics.gauge(MODULE_IN_FLIGHT, &labels),
+      memory_gauge: metrics.gauge(MODULE_MEMORY_BYTES, &labels),
+      throttled: metrics.counter(MODULE_THROTTLED_TOTAL, &labels),
+    }
+  }
+
+  pub(crate) fn enqueue(&self) {
+    self.queued.fetch_add(1, Ordering::Relaxed);
+    self.queued_gauge.inc();
+  }
+
+  pub(crate) fn dequeue(&self) {
+    self.queued.fetch_sub(1, Ordering::Relaxed);
+    self.queued_gauge.dec();
+  }
+
+  /// Counts the request as being handled until the guard is dropped.
+  pub(crate) fn start(self: &Arc<Self>) -> ModuleLoadGuard {
+    self.in_flight.fetch_add(1, Ordering::Relaxed);
+    self.in_flight_gauge.inc();
+    ModuleLoadGuard {
+      account: self.clone(),
+    }
+  }
+
+  /// Called once the handler of a request is done, with the time it spent running.
+  pub(crate) fn record_request(&self, cpu_time: Duration) {
+    self.cpu_histogram.observe(cpu_time);
+  }
+
+  /// Accounts a handler that responded right away, without a future to meter.
+  pub(crate) fn record_sync_call(&self, elapsed: Duration) {
+    self.record_cpu_time(elapsed);
+    self.record_request(elapsed);
+  }
+
+  pub(crate) fn record_cpu_time(&self, elapsed: Duration) {
+    self
+      .cpu_nanos
+      .fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    let now = Instant::now();
+    let window = self.quota.read().unwrap().cpu_time.map(|cpu| cpu.window);
+    let mut current = self.window.lock().unwrap();
+    if let Some(window) = window {
+      if now.duration_since(current.0) >= window {
+        *current = (now, Duration::ZERO);
+      }
+    }
+    current.1 += elapsed;
+  }
+
+  /// The handler time spent in the current window, and when the window ends.
+  fn window_cpu_time(&self, now: Instant) -> (Duration, Option<Instant>) {
+    let window = self.quota.read().unwrap().cpu_time.map(|cpu| cpu.window);
+    let current = self.window.lock().unwrap();
+    match window {
+      Some(window) if now.duration_since(current.0) >= window => (Duration::ZERO, None),
+      Some(window) => (current.1, Some(current.0 + window)),
+      None => (current.1, None),
+    }
+  }
+
+  fn sample_memory(&self, now: Instant) -> usize {
+    let reporters = self.memory.read().unwrap().clone();
+    let bytes = reporters
+      .iter()
+      .map(|reporter| reporter.memory_usage().bytes)
+      .sum::<usize>();
+    *self.memory_sample.lock().unwrap() = Some((now, bytes));
+    self.memory_gauge.set(bytes as i64);
+    bytes
+  }
+
+  fn memory_bytes(&self, now: Instant) -> usize {
+    let sample = *self.memory_sample.lock().unwrap();
+    match sample {
+      Some((sampled_at, bytes)) if now.duration_since(sampled_at) < MEMORY_SAMPLE_INTERVAL => bytes,
+      _ => self.sample_memory(now),
+    }
+  }
+
+  /// Fails with [QuotaExceeded], counted as throttled, if the module used up one of its quotas.
+  pub(crate) fn check(&self, event: &AFPluginEvent) -> Result<(), QuotaExceeded> {
+    match self.exceeded(event) {
+      Some(err) => {
+        self.throttled.inc();
+        tracing::warn!("[dispatch]: throttle {}", err);
+        Err(err)
+      },
+      None => Ok(()),
+    }
+  }
+
+  /// The quota used up by the module, if any. The request itself is not counted as pending yet.
+  pub(crate) fn exceeded(&self, event: &AFPluginEvent) -> Option<QuotaExceeded> {
+    let quota = *self.quota.read().unwrap();
+    let now = Instant::now();
+    let exceeded = |resource: QuotaResource, retry_after: Option<Duration>| QuotaExceeded {
+      module: self.module.clone(),
+      event: event.as_str().to_owned(),
+      resource,
+      retry_after_ms: retry_after.map(|retry_after| retry_after.as_millis() as u64),
+    };
+    if let Some(cpu) = quota.cpu_time {
+      let (spent, window_end) = self.window_cpu_time(now);
+      if spent >= cpu.budget {
+        let retry_after = window_end.map(|end| end.saturating_duration_since(now));
+        return Some(exceeded(QuotaResource::CpuTime, retry_after));
+      }
+    }
+    if let Some(max_pending) = quota.max_pending {
+      let pending = self.queued.load(Ordering::Relaxed) + self.in_flight.load(Ordering::Relaxed);
+      if pending >= max_pending {
+        return Some(exceeded(QuotaResource::Pending, None));
+      }
+    }
+    if let Some(max_memory_bytes) = quota.max_memory_bytes {
+      if self.memory_bytes(now) > max_memory_bytes {
+        return Some(exceeded(QuotaResource::Memory, None));
+      }
+    }
+    None
+  }
+}
+
+pub(crate) struct ModuleLoadGuard {
+  account: Arc<ModuleAccount>,
+}
+
+impl Drop for ModuleLoadGuard {
+  fn drop(&mut self) {
+    self.account.in_flight.fetch_sub(1, Ordering::Relaxed);
+    self.account.in_flight_gauge.dec();
+  }
+}
+
+/// Accounts the time spent polling the handler future to its module.
+#[pin_project]
+pub(crate) struct Metered<F> {
+  #[pin]
+  fut: F,
+  account: Arc<ModuleAccount>,
+  cpu_time: Duration,
+  _guard: ModuleLoadGuard,
+}
+
+impl<F> Metered<F> {
+  pub(crate) fn new(fut: F, account: Arc<ModuleAccount>) -> Self {
+    let _guard = account.start();
+    Self {
+      fut,
+      account,
+      cpu_time: Duration::ZERO,
+      _guard,
+    }
+  }
+}
+
+impl<F: Future> Future for Metered<F> {
+  type Output = F::Output;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    let this = self.project();
+    let started_at = Instant::now();
+    let poll = this.fut.poll(cx);
+    let elapsed = started_at.elapsed();
+    this.account.record_cpu_time(elapsed);
+    *this.cpu_time += elapsed;
+    if poll.is_ready() {
+      this.account.record_request(*this.cpu_time);
+    }
+    poll
+  }
+}
+
+/// The error of the requests throttled because their module exceeded its [ModuleQuota]. The
+/// response carries it as JSON, read it back with [QuotaExceeded::from_response].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuotaExceeded {
+  pub module: String,
+  pub event: String,
+  pub resource: QuotaResource,
+  /// When the quota frees up, if it's known.
+  pub retry_after_ms: Option<u64>,
+}
+
+impl QuotaExceeded {
+  pub fn from_response(response: &AFPluginEventResponse) -> Option<Self> {
+    if response.error_origin != Some(ErrorOrigin::Dispatcher)
+      || response.content_type != Some(ContentType::Json)
+    {
+      return None;
+    }
+    serde_json::from_slice(response.payload.as_ref()).ok()
+  }
+}
+
+impl Display for QuotaExceeded {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "QuotaExceeded: {} of {} exceeded its {:?} quota",
+      self.event, self.module, self.resource
+    )
+  }
+}
+
+impl Error for QuotaExceeded {
+  fn as_response(&self) -> AFPluginEventResponse {
+    let data = serde_json::to_vec(self).unwrap_or_else(|_| self.to_string().into_bytes());
+    ResponseBuilder::Err()
+      .data(data)
+      .content_type(ContentType::Json)
+      .error_origin(ErrorOrigin::Dispatcher)
+      .build()
+  }
+}
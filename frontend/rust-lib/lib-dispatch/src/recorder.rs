@@ -0,0 +1,210 @@
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::memory::MemoryUsage;
+
+/// The number of records kept by the [EventRecorder] created by the dispatcher.
+pub const DEFAULT_RECORDER_CAPACITY: usize = 64;
+
+/// A summary of a dispatched request. The payloads are never recorded, only their sizes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventRecord {
+  pub event: String,
+  pub id: String,
+  pub payload_size: usize,
+  pub response_size: usize,
+  pub duration_ms: f64,
+  pub success: bool,
+  /// The time the request finished, in milliseconds since the unix epoch.
+  pub timestamp: u64,
+}
+
+impl Display for EventRecord {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "[{}] {}:{} payload: {} bytes, response: {} bytes, {:.3}ms, {}",
+      self.timestamp,
+      self.id,
+      self.event,
+      self.payload_size,
+      self.response_size,
+      self.duration_ms,
+      if self.success { "ok" } else { "err" }
+    )
+  }
+}
+
+/// A handler that panicked, see [install_panic_hook](crate::crash::install_panic_hook).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PanicRecord {
+  pub event: String,
+  pub id: String,
+  /// The name of the thread the handler ran on, e.g. `flowy-dispatch-3`.
+  pub thread: String,
+  pub message: String,
+  pub backtrace: String,
+  /// The time of the panic, in milliseconds since the unix epoch.
+  pub timestamp: u64,
+}
+
+impl Display for PanicRecord {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "[{}] {}:{} panicked on {}: {}\n{}",
+      self.timestamp, self.id, self.event, self.thread, self.message, self.backtrace
+    )
+  }
+}
+
+/// Keeps the last `capacity` dispatched requests, and as many panics. The oldest record is
+/// dropped when the recorder is full, so it's cheap enough to be always on.
+#[derive(Debug)]
+pub struct EventRecorder {
+  capacity: usize,
+  records: Mutex<VecDeque<EventRecord>>,
+  panics: Mutex<VecDeque<PanicRecord>>,
+}
+
+impl Default for EventRecorder {
+  fn default() -> Self {
+    Self::new(DEFAULT_RECORDER_CAPACITY)
+  }
+}
+
+impl EventRecorder {
+  pub fn new(capacity: usize) -> Self {
+    Self {
+      capacity,
+      records: Mutex::new(VecDeque::with_capacity(capacity)),
+      panics: Mutex::new(VecDeque::new()),
+    }
+  }
+
+  pub fn record(
+    &self,
+    event: &str,
+    id: &str,
+    payload_size: usize,
+    response_size: usize,
+    duration: Duration,
+    success: bool,
+  ) {
+    if self.capacity == 0 {
+      return;
+    }
+
+    let timestamp = now_millis();
+    let record = EventRecord {
+      event: event.to_owned(),
+      id: id.to_owned(),
+      payload_size,
+      response_size,
+      duration_ms: duration.as_secs_f64() * 1000.0,
+      success,
+      timestamp,
+    };
+
+    if let Ok(mut records) = self.records.lock() {
+      if records.len() == self.capacity {
+        records.pop_front();
+      }
+      records.push_back(record);
+    }
+  }
+
+  pub(crate) fn record_panic(
+    &self,
+    event: &str,
+    id: &str,
+    thread: &str,
+    message: &str,
+    backtrace: String,
+  ) {
+    if self.capacity == 0 {
+      return;
+    }
+
+    let record = PanicRecord {
+      event: event.to_owned(),
+      id: id.to_owned(),
+      thread: thread.to_owned(),
+      message: message.to_owned(),
+      backtrace,
+      timestamp: now_millis(),
+    };
+    // Called from the panic hook, it must not panic itself.
+    if let Ok(mut panics) = self.panics.try_lock() {
+      if panics.len() == self.capacity {
+        panics.pop_front();
+      }
+      panics.push_back(record);
+    }
+  }
+
+  /// Returns the recorded requests, from the oldest to the newest.
+  pub fn dump(&self) -> Vec<EventRecord> {
+    match self.records.lock() {
+      Ok(records) => records.iter().cloned().collect(),
+      Err(poisoned) => poisoned.into_inner().iter().cloned().collect(),
+    }
+  }
+
+  /// Returns the recorded panics, from the oldest to the newest.
+  pub fn panics(&self) -> Vec<PanicRecord> {
+    match self.panics.lock() {
+      Ok(panics) => panics.iter().cloned().collect(),
+      Err(poisoned) => poisoned.into_inner().iter().cloned().collect(),
+    }
+  }
+
+  /// The memory held by the records.
+  pub fn memory_usage(&self) -> MemoryUsage {
+    let records = match self.records.lock() {
+      Ok(records) => records,
+      Err(poisoned) => poisoned.into_inner(),
+    };
+    let bytes = records
+      .iter()
+      .map(|record| record.event.len() + record.id.len())
+      .sum::<usize>()
+      + records.capacity() * std::mem::size_of::<EventRecord>();
+    let panics = match self.panics.lock() {
+      Ok(panics) => panics,
+      Err(poisoned) => poisoned.into_inner(),
+    };
+    let panic_bytes = panics
+      .iter()
+      .map(|record| {
+        record.event.len()
+          + record.id.len()
+          + record.thread.len()
+          + record.message.len()
+          + record.backtrace.len()
+      })
+      .sum::<usize>()
+      + panics.capacity() * std::mem::size_of::<PanicRecord>();
+    MemoryUsage::new(bytes + panic_bytes, records.len() + panics.len())
+  }
+
+  pub fn clear(&self) {
+    if let Ok(mut records) = self.records.lock() {
+      records.clear();
+    }
+    if let Ok(mut panics) = self.panics.lock() {
+      panics.clear();
+    }
+  }
+}
+
+pub(crate) fn now_millis() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_millis() as u64)
+    .unwrap_or_default()
+}
@@ -0,0 +1,101 @@
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+
+use futures::future::{select, Either};
+pub use tokio_util::sync::CancellationToken;
+
+use crate::errors::DispatchError;
+use crate::request::{AFPluginEventRequest, FromAFPluginRequest, Payload};
+use crate::util::ready::{ready, Ready};
+
+tokio::task_local! {
+  static CURRENT_CANCELLATION: CancellationToken;
+}
+
+/// The token of the operation the current task runs for: the request being handled, or the
+/// token passed to [cancellation_scope]. `None` outside of both.
+///
+/// The requests sent and the tasks spawned with [af_spawn](crate::prelude::af_spawn) from within
+/// the operation get a child of this token, so cancelling the operation cancels all the work it
+/// started, while cancelling a child leaves its parent running.
+pub fn current_cancellation() -> Option<CancellationToken> {
+  CURRENT_CANCELLATION.try_with(|token| token.clone()).ok()
+}
+
+/// Runs `future` as part of the operation cancelled by `token`, see [current_cancellation]. The
+/// future itself isn't stopped when the token is cancelled, wrap it in [cancellable] for that.
+pub async fn cancellation_scope<F: Future>(token: CancellationToken, future: F) -> F::Output {
+  CURRENT_CANCELLATION.scope(token, future).await
+}
+
+/// The error of the futures stopped by the cancellation of their operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl Display for Cancelled {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "Cancelled")
+  }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Runs `future` until it completes or the current operation is cancelled, whichever comes
+/// first, e.g. to stop a timer along with the request that armed it:
+///
+/// ```ignore
+/// cancellable(tokio::time::sleep(RETRY_INTERVAL)).await?;
+/// ```
+///
+/// Outside of an operation, the future runs to completion.
+pub async fn cancellable<F: Future>(future: F) -> Result<F::Output, Cancelled> {
+  let token = match current_cancellation() {
+    Some(token) => token,
+    None => return Ok(future.await),
+  };
+  let cancelled = std::pin::pin!(token.cancelled());
+  let future = std::pin::pin!(future);
+  match select(future, cancelled).await {
+    Either::Left((output, _)) => Ok(output),
+    Either::Right(_) => Err(Cancelled),
+  }
+}
+
+/// The token of the request being handled, cancelled when the client sends the
+/// [SysEvent::Cancel](crate::system::SysEvent::Cancel) event with the id of the request, or when
+/// [AFPluginDispatcher::cancel](crate::prelude::AFPluginDispatcher::cancel) is called, or when
+/// the token of the operation that sent the request is, see [current_cancellation]. The long
+/// running handlers check it to stop early:
+///
+/// ```ignore
+/// pub(crate) async fn export_handler(
+///   data: AFPluginData<ExportPB>,
+///   cancellation: CancellationToken,
+/// ) -> Result<(), FlowyError> {
+///   for view in views {
+///     if cancellation.is_cancelled() {
+///       return Err(FlowyError::cancelled());
+///     }
+///     ..
+///   }
+/// }
+/// ```
+impl FromAFPluginRequest for CancellationToken {
+  type Error = DispatchError;
+  type Future = Ready<Result<Self, DispatchError>>;
+
+  #[inline]
+  fn from_request(req: &AFPluginEventRequest, _: &mut Payload) -> Self::Future {
+    // The requests handled outside of the dispatcher, e.g. inline, can't be cancelled.
+    let extensions = req.extensions();
+    let token = match extensions.get::<CancellationToken>() {
+      Some(token) => token,
+      None => {
+        let token = CancellationToken::new();
+        extensions.insert(token.clone());
+        token
+      },
+    };
+    ready(Ok(token))
+  }
+}
@@ -0,0 +1,87 @@
+use std::any::type_name;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, RwLock};
+
+use crate::errors::{DispatchError, InternalError};
+use crate::module::AFPluginStateMap;
+use crate::request::{AFPluginEventRequest, FromAFPluginRequest, Payload};
+use crate::util::ready::{ready, Ready};
+
+/// The values attached to a single request, usually by the middlewares. An auth middleware can
+/// store the user it resolved from the token and the handler reads it back with the [Extension]
+/// extractor.
+///
+/// The clones of a request share the same extensions. They are cleared once the response
+/// middlewares ran, so nothing stored in them outlives the request.
+#[derive(Clone, Default)]
+pub struct Extensions(Arc<RwLock<AFPluginStateMap>>);
+
+impl Extensions {
+  /// Stores `value`, replacing and returning the value of the same type, if any.
+  pub fn insert<T: Send + Sync + 'static>(&self, value: T) -> Option<T> {
+    self.0.write().unwrap().insert(value)
+  }
+
+  pub fn remove<T: Send + Sync + 'static>(&self) -> Option<T> {
+    self.0.write().unwrap().remove::<T>()
+  }
+
+  pub fn get<T: Clone + 'static>(&self) -> Option<T> {
+    self.0.read().unwrap().get::<T>().cloned()
+  }
+
+  pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+    self.0.read().unwrap().contains::<T>()
+  }
+
+  pub(crate) fn clear(&self) {
+    *self.0.write().unwrap() = AFPluginStateMap::new();
+  }
+}
+
+impl Debug for Extensions {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("Extensions").finish_non_exhaustive()
+  }
+}
+
+/// Extracts a copy of the value of type `T` stored in the [Extensions] of the request. The
+/// request is rejected if there is none.
+pub struct Extension<T>(pub T);
+
+impl<T> Extension<T> {
+  pub fn into_inner(self) -> T {
+    self.0
+  }
+}
+
+impl<T> std::ops::Deref for Extension<T> {
+  type Target = T;
+
+  fn deref(&self) -> &T {
+    &self.0
+  }
+}
+
+impl<T> FromAFPluginRequest for Extension<T>
+where
+  T: Clone + Send + Sync + 'static,
+{
+  type Error = DispatchError;
+  type Future = Ready<Result<Self, DispatchError>>;
+
+  #[inline]
+  fn from_request(req: &AFPluginEventRequest, _: &mut Payload) -> Self::Future {
+    match req.extensions.get::<T>() {
+      Some(value) => ready(Ok(Extension(value))),
+      None => {
+        let msg = format!(
+          "Failed to get the request extension of type: {}",
+          type_name::<T>()
+        );
+        tracing::error!("{}", msg);
+        ready(Err(InternalError::Other(msg).into()))
+      },
+    }
+  }
+}
@@ -1,6 +1,10 @@
 #![allow(clippy::module_inception)]
+mod cancellation;
+mod extensions;
 pub mod payload;
 mod request;
 
+pub use cancellation::*;
+pub use extensions::*;
 pub use payload::*;
 pub use request::*;
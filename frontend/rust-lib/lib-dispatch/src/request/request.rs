@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::future::Future;
 use std::{
   fmt::Debug,
@@ -8,7 +9,10 @@ use std::{
 use derivative::*;
 use futures_core::ready;
 
+use crate::encoding::ContentType;
 use crate::prelude::AFStateMap;
+use crate::probe::DispatchProbes;
+use crate::request::Extensions;
 use crate::{
   errors::{DispatchError, InternalError},
   module::AFPluginEvent,
@@ -18,11 +22,18 @@ use crate::{
 
 #[derive(Clone, Debug, Derivative)]
 pub struct AFPluginEventRequest {
-  #[allow(dead_code)]
   pub(crate) id: String,
   pub(crate) event: AFPluginEvent,
   #[derivative(Debug = "ignore")]
   pub(crate) states: AFStateMap,
+  /// The state shared by all the plugins, see
+  /// [AFPluginDispatcher::data](crate::prelude::AFPluginDispatcher::data).
+  #[derivative(Debug = "ignore")]
+  pub(crate) app_data: AFStateMap,
+  pub(crate) content_type: Option<ContentType>,
+  pub(crate) metadata: HashMap<String, String>,
+  pub(crate) probes: DispatchProbes,
+  pub(crate) extensions: Extensions,
 }
 
 impl AFPluginEventRequest {
@@ -34,9 +45,24 @@ impl AFPluginEventRequest {
       id,
       event: event.into(),
       states,
+      app_data: AFStateMap::default(),
+      content_type: None,
+      metadata: HashMap::new(),
+      probes: DispatchProbes::default(),
+      extensions: Extensions::default(),
     }
   }
 
+  /// The id of the [AFPluginRequest](crate::prelude::AFPluginRequest) being handled.
+  pub fn id(&self) -> &str {
+    &self.id
+  }
+
+  /// The metadata of the [AFPluginRequest](crate::prelude::AFPluginRequest) being handled.
+  pub fn metadata(&self) -> &HashMap<String, String> {
+    &self.metadata
+  }
+
   pub fn get_state<T>(&self) -> Option<T>
   where
     T: Send + Sync + 'static + Clone,
@@ -47,6 +73,18 @@ impl AFPluginEventRequest {
 
     None
   }
+
+  pub fn get_app_data<T>(&self) -> Option<T>
+  where
+    T: Send + Sync + 'static + Clone,
+  {
+    self.app_data.get::<T>().cloned()
+  }
+
+  /// The values attached to the request by the middlewares.
+  pub fn extensions(&self) -> &Extensions {
+    &self.extensions
+  }
 }
 
 pub trait FromAFPluginRequest: Sized {
@@ -1,6 +1,9 @@
+use std::collections::HashMap;
+
 use crate::{
+  encoding::ContentType,
   request::Payload,
-  response::{AFPluginEventResponse, StatusCode},
+  response::{AFPluginEventResponse, ErrorOrigin, StatusCode},
 };
 
 macro_rules! static_response {
@@ -15,6 +18,9 @@ macro_rules! static_response {
 pub struct ResponseBuilder<T = Payload> {
   pub payload: T,
   pub status: StatusCode,
+  pub content_type: Option<ContentType>,
+  pub error_origin: Option<ErrorOrigin>,
+  pub metadata: HashMap<String, String>,
 }
 
 impl ResponseBuilder {
@@ -22,6 +28,9 @@ impl ResponseBuilder {
     ResponseBuilder {
       payload: Payload::None,
       status,
+      content_type: None,
+      error_origin: None,
+      metadata: HashMap::new(),
     }
   }
 
@@ -30,10 +39,28 @@ impl ResponseBuilder {
     self
   }
 
+  pub fn content_type(mut self, content_type: ContentType) -> Self {
+    self.content_type = Some(content_type);
+    self
+  }
+
+  pub fn error_origin(mut self, origin: ErrorOrigin) -> Self {
+    self.error_origin = Some(origin);
+    self
+  }
+
+  pub fn metadata<K: ToString, V: ToString>(mut self, key: K, value: V) -> Self {
+    self.metadata.insert(key.to_string(), value.to_string());
+    self
+  }
+
   pub fn build(self) -> AFPluginEventResponse {
     AFPluginEventResponse {
       payload: self.payload,
       status_code: self.status,
+      content_type: self.content_type,
+      error_origin: self.error_origin,
+      metadata: self.metadata,
     }
   }
 
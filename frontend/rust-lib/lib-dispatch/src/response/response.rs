@@ -1,12 +1,13 @@
 use crate::{
   byte_trait::AFPluginFromBytes,
   data::AFPluginData,
-  errors::DispatchError,
+  encoding::ContentType,
+  errors::{DispatchError, ResponseError},
   request::{AFPluginEventRequest, Payload},
   response::AFPluginResponder,
 };
 use derivative::*;
-use std::{convert::TryFrom, fmt, fmt::Formatter};
+use std::{collections::HashMap, convert::TryFrom, fmt, fmt::Formatter};
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "use_serde", derive(serde_repr::Serialize_repr))]
@@ -16,6 +17,26 @@ pub enum StatusCode {
   Err = 1,
 }
 
+/// Tells the errors raised by the dispatcher apart from the ones returned by the handlers.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(serde::Serialize))]
+pub enum ErrorOrigin {
+  /// The request could not be delivered to a handler: the event is unknown, its version is not
+  /// supported or its payload could not be decoded.
+  Dispatcher,
+  /// The dispatcher failed while handling the request, for example because the handler panicked.
+  Internal,
+  /// The request was forwarded to a remote core, see [RemoteProxy](crate::proxy::RemoteProxy),
+  /// but the request or its response was lost on the way. It may be retried.
+  Transport,
+}
+
+impl ErrorOrigin {
+  pub fn is_retryable(&self) -> bool {
+    matches!(self, ErrorOrigin::Transport)
+  }
+}
+
 // serde user guide: https://serde.rs/field-attrs.html
 #[derive(Debug, Clone, Derivative)]
 #[cfg_attr(feature = "use_serde", derive(serde::Serialize))]
@@ -23,6 +44,14 @@ pub struct AFPluginEventResponse {
   #[derivative(Debug = "ignore")]
   pub payload: Payload,
   pub status_code: StatusCode,
+  /// The encoding of the payload, if the responder specified it.
+  #[cfg_attr(feature = "use_serde", serde(skip))]
+  pub content_type: Option<ContentType>,
+  /// Set when the error was not returned by the handler, see [ErrorOrigin].
+  pub error_origin: Option<ErrorOrigin>,
+  /// What the dispatcher attaches to the response, e.g. the deprecation notice of its event,
+  /// see [ResponseMapper](crate::prelude::ResponseMapper). The HTTP bridge sends it as headers.
+  pub metadata: HashMap<String, String>,
 }
 
 impl AFPluginEventResponse {
@@ -30,6 +59,9 @@ impl AFPluginEventResponse {
     AFPluginEventResponse {
       payload: Payload::None,
       status_code,
+      content_type: None,
+      error_origin: None,
+      metadata: HashMap::new(),
     }
   }
 
@@ -49,6 +81,18 @@ impl AFPluginEventResponse {
       },
     }
   }
+
+  /// Decodes the data of a successful response. The error responses are returned as is, see
+  /// [DispatchError::handler_error] to decode the error of the handler.
+  pub fn into_data<T>(self) -> Result<T, DispatchError>
+  where
+    T: AFPluginFromBytes,
+  {
+    match self.status_code {
+      StatusCode::Ok => Ok(<AFPluginData<T>>::try_from(self.payload)?.into_inner()),
+      StatusCode::Err => Err(ResponseError(self).into()),
+    }
+  }
 }
 
 impl std::fmt::Display for AFPluginEventResponse {
@@ -1,18 +1,30 @@
 use std::fmt::{Display, Formatter};
 use std::future::Future;
 use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
 
+use pin_project::pin_project;
 use tokio::runtime;
 use tokio::runtime::Runtime;
 use tokio::task::JoinHandle;
 
+use crate::config::SystemConfig;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::crash::WORKER_THREAD_PREFIX;
+
 pub struct AFPluginRuntime {
   pub(crate) inner: Runtime,
+  scheduler: Option<Arc<DeterministicScheduler>>,
 }
 
 impl Display for AFPluginRuntime {
   fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-    if cfg!(any(target_arch = "wasm32", feature = "local_set")) {
+    if let Some(scheduler) = &self.scheduler {
+      write!(f, "Runtime(deterministic, seed: {})", scheduler.seed)
+    } else if cfg!(any(target_arch = "wasm32", feature = "local_set")) {
       write!(f, "Runtime(local_set)")
     } else {
       write!(f, "Runtime")
@@ -22,8 +34,48 @@ impl Display for AFPluginRuntime {
 
 impl AFPluginRuntime {
   pub fn new() -> io::Result<Self> {
-    let inner = default_tokio_runtime()?;
-    Ok(Self { inner })
+    let inner = tokio_runtime(None)?;
+    Ok(Self {
+      inner,
+      scheduler: None,
+    })
+  }
+
+  /// A runtime with the worker threads of `config`. The browser runtime has none, it ignores
+  /// them.
+  pub fn with_config(config: &SystemConfig) -> io::Result<Self> {
+    let inner = tokio_runtime(config.worker_threads)?;
+    Ok(Self {
+      inner,
+      scheduler: None,
+    })
+  }
+
+  /// A runtime for the tests that runs every task on the thread that drives it. The tasks spawned
+  /// through the runtime are delayed by a number of scheduler rounds picked from `seed`, so the
+  /// same seed always produces the same interleaving and different seeds explore other ones.
+  ///
+  /// Use [AFPluginRuntime::run_until_idle] or [AFPluginRuntime::idle] to let the spawned tasks
+  /// run instead of sleeping.
+  pub fn deterministic(seed: u64) -> io::Result<Self> {
+    let mut builder = runtime::Builder::new_current_thread();
+    #[cfg(not(target_arch = "wasm32"))]
+    builder.enable_io().enable_time();
+    let inner = builder.build()?;
+    Ok(Self {
+      inner,
+      scheduler: Some(Arc::new(DeterministicScheduler::new(seed))),
+    })
+  }
+
+  /// The seed of the runtime if it was created by [AFPluginRuntime::deterministic].
+  pub fn seed(&self) -> Option<u64> {
+    self.scheduler.as_ref().map(|scheduler| scheduler.seed)
+  }
+
+  /// The number of worker threads used by the runtime.
+  pub fn num_workers(&self) -> usize {
+    self.inner.metrics().num_workers()
   }
 
   #[track_caller]
@@ -32,7 +84,23 @@ impl AFPluginRuntime {
     F: Future + Send + 'static,
     <F as Future>::Output: Send + 'static,
   {
-    self.inner.spawn(future)
+    match &self.scheduler {
+      Some(scheduler) => self.inner.spawn(scheduler.schedule(future)),
+      None => self.inner.spawn(future),
+    }
+  }
+
+  /// Spawn `future` on the current `LocalSet`.
+  #[track_caller]
+  pub fn spawn_local<F>(&self, future: F) -> JoinHandle<F::Output>
+  where
+    F: Future + 'static,
+    <F as Future>::Output: 'static,
+  {
+    match &self.scheduler {
+      Some(scheduler) => tokio::task::spawn_local(scheduler.schedule(future)),
+      None => tokio::task::spawn_local(future),
+    }
   }
 
   #[track_caller]
@@ -42,21 +110,147 @@ impl AFPluginRuntime {
   {
     self.inner.block_on(f)
   }
+
+  /// Drive the runtime until none of the tasks spawned through it can make progress. The tasks
+  /// spawned on a `LocalSet` only run if the set is driven too, await [AFPluginRuntime::idle]
+  /// inside of it instead.
+  #[track_caller]
+  pub fn run_until_idle(&self) {
+    self.inner.block_on(self.idle())
+  }
+
+  /// Resolve once none of the tasks spawned through the runtime can make progress. Only the
+  /// [AFPluginRuntime::deterministic] runtimes track their tasks, the other ones just yield once.
+  pub async fn idle(&self) {
+    let Some(scheduler) = &self.scheduler else {
+      tokio::task::yield_now().await;
+      return;
+    };
+
+    let mut quiet_rounds = 0;
+    while quiet_rounds < IDLE_QUIET_ROUNDS {
+      let polls = scheduler.polls.load(Ordering::Relaxed);
+      tokio::task::yield_now().await;
+      if scheduler.polls.load(Ordering::Relaxed) == polls {
+        quiet_rounds += 1;
+      } else {
+        quiet_rounds = 0;
+      }
+    }
+  }
+}
+
+/// The number of scheduler rounds without any task being polled after which a deterministic
+/// runtime is considered idle.
+const IDLE_QUIET_ROUNDS: usize = 2;
+
+/// The maximum number of scheduler rounds a deterministic runtime delays a spawned task by.
+const MAX_SCHEDULING_DELAY: u64 = 4;
+
+struct DeterministicScheduler {
+  seed: u64,
+  rng: AtomicU64,
+  /// The number of times the scheduled tasks were polled, used to detect when the runtime is idle.
+  polls: AtomicU64,
+}
+
+impl DeterministicScheduler {
+  fn new(seed: u64) -> Self {
+    Self {
+      seed,
+      rng: AtomicU64::new(seed),
+      polls: AtomicU64::new(0),
+    }
+  }
+
+  fn schedule<F: Future>(self: &Arc<Self>, future: F) -> Scheduled<F> {
+    Scheduled {
+      delay: self.next_delay(),
+      scheduler: self.clone(),
+      future,
+    }
+  }
+
+  /// splitmix64, good enough to spread the delays and keeps the crate free of a rand dependency.
+  fn next_delay(&self) -> u64 {
+    const GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut z = self
+      .rng
+      .fetch_add(GAMMA, Ordering::Relaxed)
+      .wrapping_add(GAMMA);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (z ^ (z >> 31)) % (MAX_SCHEDULING_DELAY + 1)
+  }
+}
+
+/// A task of a deterministic runtime, it yields `delay` times before running `future`.
+#[pin_project]
+struct Scheduled<F> {
+  delay: u64,
+  scheduler: Arc<DeterministicScheduler>,
+  #[pin]
+  future: F,
+}
+
+impl<F: Future> Future for Scheduled<F> {
+  type Output = F::Output;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    let this = self.project();
+    this.scheduler.polls.fetch_add(1, Ordering::Relaxed);
+    if *this.delay > 0 {
+      *this.delay -= 1;
+      cx.waker().wake_by_ref();
+      return Poll::Pending;
+    }
+    this.future.poll(cx)
+  }
 }
 
-#[cfg(feature = "local_set")]
 pub fn default_tokio_runtime() -> io::Result<Runtime> {
-  runtime::Builder::new_multi_thread()
+  tokio_runtime(None)
+}
+
+/// `flowy-dispatch-{n}`, so the crash reports tell the threads of the dispatcher apart.
+#[cfg(not(target_arch = "wasm32"))]
+fn worker_thread_name() -> String {
+  use std::sync::atomic::AtomicUsize;
+
+  static NEXT_WORKER: AtomicUsize = AtomicUsize::new(0);
+  let n = NEXT_WORKER.fetch_add(1, Ordering::Relaxed);
+  format!("{}-{}", WORKER_THREAD_PREFIX, n)
+}
+
+/// There are no threads in the browser, so the runtime only drives the tasks of the thread that
+/// calls it.
+#[cfg(target_arch = "wasm32")]
+fn tokio_runtime(_worker_threads: Option<usize>) -> io::Result<Runtime> {
+  runtime::Builder::new_current_thread().build()
+}
+
+/// A runtime with one worker thread per core, unless `worker_threads` is set.
+#[cfg(all(feature = "local_set", not(target_arch = "wasm32")))]
+fn tokio_runtime(worker_threads: Option<usize>) -> io::Result<Runtime> {
+  let mut builder = runtime::Builder::new_multi_thread();
+  if let Some(worker_threads) = worker_threads {
+    builder.worker_threads(worker_threads);
+  }
+  builder
     .enable_io()
     .enable_time()
-    .thread_name("dispatch-rt-st")
+    .thread_name_fn(worker_thread_name)
     .build()
 }
 
-#[cfg(not(feature = "local_set"))]
-pub fn default_tokio_runtime() -> io::Result<Runtime> {
-  runtime::Builder::new_multi_thread()
-    .thread_name("dispatch-rt-mt")
+#[cfg(all(not(feature = "local_set"), not(target_arch = "wasm32")))]
+fn tokio_runtime(worker_threads: Option<usize>) -> io::Result<Runtime> {
+  let mut builder = runtime::Builder::new_multi_thread();
+  if let Some(worker_threads) = worker_threads {
+    builder.worker_threads(worker_threads);
+  }
+  builder
+    .thread_name_fn(worker_thread_name)
     .enable_io()
     .enable_time()
     .on_thread_start(move || {
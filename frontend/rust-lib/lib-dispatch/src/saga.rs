@@ -0,0 +1,186 @@
+use std::fmt::{Debug, Display, Formatter};
+
+use crate::errors::{DispatchError, ResponseError};
+use crate::module::AFPluginRequest;
+use crate::prelude::AFPluginDispatcher;
+use crate::response::{AFPluginEventResponse, StatusCode};
+
+type StepAction = Box<dyn FnOnce(&[AFPluginEventResponse]) -> AFPluginRequest + Send>;
+
+type StepCompensation = Box<dyn FnOnce(&AFPluginEventResponse) -> AFPluginRequest + Send>;
+
+/// A step of a [Saga]: the request doing the work and the one undoing it.
+pub struct SagaStep {
+  action: StepAction,
+  compensation: Option<StepCompensation>,
+}
+
+impl SagaStep {
+  pub fn new<Req>(request: Req) -> Self
+  where
+    Req: Into<AFPluginRequest> + Send + 'static,
+  {
+    Self::deferred(move |_| request.into())
+  }
+
+  /// The request is built once the previous steps succeeded, from their responses, e.g. to create
+  /// a document in the workspace created by the previous step.
+  pub fn deferred<F>(f: F) -> Self
+  where
+    F: FnOnce(&[AFPluginEventResponse]) -> AFPluginRequest + Send + 'static,
+  {
+    Self {
+      action: Box::new(f),
+      compensation: None,
+    }
+  }
+
+  /// The request undoing the step. Without it, the step is left as is when the saga unwinds.
+  pub fn compensate<Req>(self, request: Req) -> Self
+  where
+    Req: Into<AFPluginRequest> + Send + 'static,
+  {
+    self.compensate_with(move |_| request.into())
+  }
+
+  /// Builds the request undoing the step from the response of the step, e.g. to delete the
+  /// workspace it created.
+  pub fn compensate_with<F>(mut self, f: F) -> Self
+  where
+    F: FnOnce(&AFPluginEventResponse) -> AFPluginRequest + Send + 'static,
+  {
+    self.compensation = Some(Box::new(f));
+    self
+  }
+}
+
+/// A flow of requests that can't be made part of one [Transaction](crate::transaction::Transaction)
+/// because it's too long or its steps update different stores, e.g. signing up, then creating the
+/// default workspace, then creating the starter document.
+///
+/// The steps are sent one after the other by [AFPluginDispatcher::run_saga]. When a step fails,
+/// the steps that succeeded before it are unwound: their compensations are sent in the reverse
+/// order.
+pub struct Saga {
+  name: String,
+  steps: Vec<SagaStep>,
+}
+
+impl Saga {
+  pub fn new<T: ToString>(name: T) -> Self {
+    Self {
+      name: name.to_string(),
+      steps: vec![],
+    }
+  }
+
+  pub fn step(mut self, step: SagaStep) -> Self {
+    self.steps.push(step);
+    self
+  }
+
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  /// Returns the responses of all the steps if they all succeeded.
+  pub(crate) async fn run(
+    self,
+    dispatch: &AFPluginDispatcher,
+  ) -> Result<Vec<AFPluginEventResponse>, SagaError> {
+    let Saga { name, steps } = self;
+    let mut responses = Vec::with_capacity(steps.len());
+    let mut compensations = Vec::with_capacity(steps.len());
+    for (index, step) in steps.into_iter().enumerate() {
+      let request = (step.action)(&responses);
+      tracing::trace!("[saga]: {} step {}: {:?}", name, index, request.event);
+      let response = send(dispatch, request).await;
+      if response.status_code != StatusCode::Ok {
+        tracing::warn!("[saga]: {} failed at step {}, unwinding", name, index);
+        let failed_compensations = unwind(dispatch, &name, compensations).await;
+        return Err(SagaError {
+          saga: name,
+          step: index,
+          error: ResponseError(response).into(),
+          failed_compensations,
+        });
+      }
+      compensations.push((index, step.compensation, response.clone()));
+      responses.push(response);
+    }
+    Ok(responses)
+  }
+}
+
+/// Sends the compensations of the steps that succeeded, the last step first. A failed
+/// compensation doesn't stop the others.
+async fn unwind(
+  dispatch: &AFPluginDispatcher,
+  name: &str,
+  compensations: Vec<(usize, Option<StepCompensation>, AFPluginEventResponse)>,
+) -> Vec<(usize, DispatchError)> {
+  let mut failed = vec![];
+  for (index, compensation, response) in compensations.into_iter().rev() {
+    let compensation = match compensation {
+      None => continue,
+      Some(compensation) => compensation,
+    };
+    let response = send(dispatch, compensation(&response)).await;
+    if response.status_code != StatusCode::Ok {
+      tracing::error!("[saga]: {} failed to compensate step {}", name, index);
+      failed.push((index, ResponseError(response).into()));
+    }
+  }
+  failed
+}
+
+async fn send(dispatch: &AFPluginDispatcher, request: AFPluginRequest) -> AFPluginEventResponse {
+  AFPluginDispatcher::async_send_with_callback(dispatch, request, |_| Box::pin(async {})).await
+}
+
+/// Returned by [AFPluginDispatcher::run_saga] when a step failed. The steps before it are
+/// compensated, except the ones listed in [SagaError::failed_compensations].
+pub struct SagaError {
+  pub saga: String,
+  /// The index of the failed step.
+  pub step: usize,
+  /// The error of the failed step.
+  pub error: DispatchError,
+  /// The steps whose compensation failed as well, with the error of the compensation. Their
+  /// updates are left as is.
+  pub failed_compensations: Vec<(usize, DispatchError)>,
+}
+
+impl SagaError {
+  /// Whether all the steps before the failed one were compensated.
+  pub fn is_unwound(&self) -> bool {
+    self.failed_compensations.is_empty()
+  }
+}
+
+impl Display for SagaError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "Saga {} failed at step {}: {}",
+      self.saga, self.step, self.error
+    )?;
+    if !self.is_unwound() {
+      let steps = self
+        .failed_compensations
+        .iter()
+        .map(|(step, _)| step.to_string())
+        .collect::<Vec<_>>();
+      write!(f, ", the steps {} weren't compensated", steps.join(", "))?;
+    }
+    Ok(())
+  }
+}
+
+impl Debug for SagaError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+impl std::error::Error for SagaError {}
@@ -0,0 +1,240 @@
+//! The signatures that make the requests arriving over the network tamper-evident.
+//!
+//! A client signs the event, the content type and the payload of a request with one of its
+//! keys, along with the id of the key and the time of the signature. The signature travels in
+//! the [SIGNATURE_METADATA] of the request: the `X-Signature` header over HTTP and gRPC, the
+//! signed request frames over WebSocket, see [serve_framed](crate::bridge::serve_framed). Its
+//! format is `{key id}:{unix time in ms}:{signature in hex}`.
+//!
+//! The server keeps the keys of its clients in a [KeyRing], checked by the
+//! [SignatureMiddleware](crate::middleware::SignatureMiddleware). Two schemes are supported:
+//! HMAC-SHA256, with a secret shared by the client and the server, and Ed25519, where the server
+//! only knows the public key of the client. A key is rotated by adding the new one under a new
+//! id, then removing the old one once the clients moved to it.
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ring::hmac;
+use ring::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
+
+pub use crate::middleware::SIGNATURE_METADATA;
+use crate::module::AFPluginRequest;
+
+/// How far the time of a signature may be from the time of the server, in both directions.
+/// The older signatures are rejected, so a captured request can't be replayed later on.
+pub const DEFAULT_MAX_SKEW: Duration = Duration::from_secs(300);
+
+/// Why the signature of a request was rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureError {
+  Missing,
+  Malformed,
+  UnknownKey(String),
+  /// The signature is older, or newer, than the [KeyRing] allows.
+  Expired,
+  Invalid,
+}
+
+impl Display for SignatureError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      SignatureError::Missing => f.write_str("missing signature"),
+      SignatureError::Malformed => f.write_str("malformed signature"),
+      SignatureError::UnknownKey(key_id) => write!(f, "unknown signing key {}", key_id),
+      SignatureError::Expired => f.write_str("expired signature"),
+      SignatureError::Invalid => f.write_str("invalid signature"),
+    }
+  }
+}
+
+impl std::error::Error for SignatureError {}
+
+/// The bytes a signature covers. The key id and the time are part of them, so they can't be
+/// swapped for other ones.
+pub fn signing_input(key_id: &str, timestamp_ms: u64, request: &AFPluginRequest) -> Vec<u8> {
+  let content_type = request
+    .content_type
+    .map(|content_type| content_type.as_str())
+    .unwrap_or_default();
+  let payload = request.payload.as_ref();
+  let mut input = format!(
+    "{}\n{}\n{}\n{}\n",
+    key_id,
+    timestamp_ms,
+    request.event.as_str(),
+    content_type
+  )
+  .into_bytes();
+  input.reserve(payload.len());
+  input.extend_from_slice(payload);
+  input
+}
+
+fn now_ms() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|elapsed| elapsed.as_millis() as u64)
+    .unwrap_or_default()
+}
+
+enum SigningKey {
+  Hmac(hmac::Key),
+  Ed25519(Ed25519KeyPair),
+}
+
+/// Signs the requests with a key, on the client side. The key id can't contain `:`.
+pub struct RequestSigner {
+  key_id: String,
+  key: SigningKey,
+}
+
+impl RequestSigner {
+  pub fn hmac(key_id: &str, secret: &[u8]) -> Self {
+    Self {
+      key_id: key_id.to_owned(),
+      key: SigningKey::Hmac(hmac::Key::new(hmac::HMAC_SHA256, secret)),
+    }
+  }
+
+  /// The Ed25519 key derived from the 32 bytes `seed`. Register its
+  /// [public key](RequestSigner::public_key) in the [KeyRing] of the server.
+  pub fn ed25519(key_id: &str, seed: &[u8]) -> Result<Self, SignatureError> {
+    let key_pair =
+      Ed25519KeyPair::from_seed_unchecked(seed).map_err(|_| SignatureError::Malformed)?;
+    Ok(Self {
+      key_id: key_id.to_owned(),
+      key: SigningKey::Ed25519(key_pair),
+    })
+  }
+
+  pub fn key_id(&self) -> &str {
+    &self.key_id
+  }
+
+  /// `None` for the HMAC keys, whose secret is shared.
+  pub fn public_key(&self) -> Option<Vec<u8>> {
+    match &self.key {
+      SigningKey::Hmac(_) => None,
+      SigningKey::Ed25519(key_pair) => Some(key_pair.public_key().as_ref().to_vec()),
+    }
+  }
+
+  /// The value of the [SIGNATURE_METADATA] of `request`, signed at `timestamp_ms`.
+  pub fn signature(&self, request: &AFPluginRequest, timestamp_ms: u64) -> String {
+    let input = signing_input(&self.key_id, timestamp_ms, request);
+    let signature = match &self.key {
+      SigningKey::Hmac(key) => hex::encode(hmac::sign(key, &input)),
+      SigningKey::Ed25519(key_pair) => hex::encode(key_pair.sign(&input)),
+    };
+    format!("{}:{}:{}", self.key_id, timestamp_ms, signature)
+  }
+
+  /// Signs `request` now. It must not be changed afterwards.
+  pub fn sign(&self, request: AFPluginRequest) -> AFPluginRequest {
+    let signature = self.signature(&request, now_ms());
+    request.metadata(SIGNATURE_METADATA, signature)
+  }
+}
+
+#[derive(Clone)]
+enum VerifyingKey {
+  Hmac(hmac::Key),
+  Ed25519(Vec<u8>),
+}
+
+/// The keys the signatures of the requests are checked against, by key id.
+pub struct KeyRing {
+  keys: RwLock<HashMap<String, VerifyingKey>>,
+  max_skew: Duration,
+}
+
+impl KeyRing {
+  pub fn new() -> Self {
+    Self {
+      keys: RwLock::new(HashMap::new()),
+      max_skew: DEFAULT_MAX_SKEW,
+    }
+  }
+
+  /// Accepts the signatures up to `max_skew` away from now instead of [DEFAULT_MAX_SKEW].
+  pub fn with_max_skew(mut self, max_skew: Duration) -> Self {
+    self.max_skew = max_skew;
+    self
+  }
+
+  /// Adds the HMAC-SHA256 `secret` shared with a client, replacing the key of the same id.
+  pub fn add_hmac_key(&self, key_id: &str, secret: &[u8]) {
+    let key = VerifyingKey::Hmac(hmac::Key::new(hmac::HMAC_SHA256, secret));
+    self.keys.write().unwrap().insert(key_id.to_owned(), key);
+  }
+
+  /// Adds the Ed25519 `public_key` of a client, replacing the key of the same id.
+  pub fn add_ed25519_key(&self, key_id: &str, public_key: &[u8]) {
+    let key = VerifyingKey::Ed25519(public_key.to_vec());
+    self.keys.write().unwrap().insert(key_id.to_owned(), key);
+  }
+
+  /// Returns false if there was no such key. The requests signed with it are rejected from now
+  /// on.
+  pub fn remove_key(&self, key_id: &str) -> bool {
+    self.keys.write().unwrap().remove(key_id).is_some()
+  }
+
+  pub fn key_ids(&self) -> Vec<String> {
+    let mut key_ids = self
+      .keys
+      .read()
+      .unwrap()
+      .keys()
+      .cloned()
+      .collect::<Vec<_>>();
+    key_ids.sort();
+    key_ids
+  }
+
+  /// Checks the [SIGNATURE_METADATA] of `request`. Returns the id of the key it was signed
+  /// with.
+  pub fn verify(&self, request: &AFPluginRequest) -> Result<String, SignatureError> {
+    let signature = request
+      .metadata
+      .get(SIGNATURE_METADATA)
+      .ok_or(SignatureError::Missing)?;
+    let mut parts = signature.splitn(3, ':');
+    let (key_id, timestamp_ms, signature) = match (parts.next(), parts.next(), parts.next()) {
+      (Some(key_id), Some(timestamp_ms), Some(signature)) => (key_id, timestamp_ms, signature),
+      _ => return Err(SignatureError::Malformed),
+    };
+    let timestamp_ms = timestamp_ms
+      .parse::<u64>()
+      .map_err(|_| SignatureError::Malformed)?;
+    let signature = hex::decode(signature).map_err(|_| SignatureError::Malformed)?;
+    let key = self
+      .keys
+      .read()
+      .unwrap()
+      .get(key_id)
+      .cloned()
+      .ok_or_else(|| SignatureError::UnknownKey(key_id.to_owned()))?;
+    if now_ms().abs_diff(timestamp_ms) > self.max_skew.as_millis() as u64 {
+      return Err(SignatureError::Expired);
+    }
+    let input = signing_input(key_id, timestamp_ms, request);
+    let verified = match &key {
+      VerifyingKey::Hmac(key) => hmac::verify(key, &input, &signature),
+      VerifyingKey::Ed25519(public_key) => {
+        UnparsedPublicKey::new(&ED25519, public_key).verify(&input, &signature)
+      },
+    };
+    verified
+      .map(|_| key_id.to_owned())
+      .map_err(|_| SignatureError::Invalid)
+  }
+}
+
+impl Default for KeyRing {
+  fn default() -> Self {
+    Self::new()
+  }
+}
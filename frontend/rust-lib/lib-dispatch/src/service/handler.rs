@@ -7,10 +7,13 @@ use std::{
 
 use futures_core::ready;
 use pin_project::pin_project;
+use std::time::Instant;
+use tracing::instrument::{Instrument, Instrumented};
 
 use crate::dispatcher::AFConcurrent;
 use crate::{
   errors::DispatchError,
+  probe::DispatchPhase,
   request::{AFPluginEventRequest, FromAFPluginRequest},
   response::{AFPluginEventResponse, AFPluginResponder},
   service::{AFPluginServiceFactory, Service, ServiceRequest, ServiceResponse},
@@ -98,8 +101,18 @@ where
 
   fn call(&self, req: ServiceRequest) -> Self::Future {
     let (req, mut payload) = req.into_parts();
-    let fut = T::from_request(&req, &mut payload);
-    HandlerServiceFuture::Extract(fut, Some(req), self.handler.clone())
+    let started_at = Instant::now();
+    req
+      .probes
+      .enter(&req.id, &req.event, DispatchPhase::Extraction);
+    let span = tracing::trace_span!("extract");
+    let fut = span.in_scope(|| T::from_request(&req, &mut payload));
+    HandlerServiceFuture::Extract(
+      fut.instrument(span),
+      Some(req),
+      self.handler.clone(),
+      started_at,
+    )
   }
 }
 
@@ -111,8 +124,17 @@ where
   R: Future + AFConcurrent,
   R::Output: AFPluginResponder,
 {
-  Extract(#[pin] T::Future, Option<AFPluginEventRequest>, H),
-  Handle(#[pin] R, Option<AFPluginEventRequest>),
+  Extract(
+    #[pin] Instrumented<T::Future>,
+    Option<AFPluginEventRequest>,
+    H,
+    Instant,
+  ),
+  Handle(
+    #[pin] Instrumented<R>,
+    Option<AFPluginEventRequest>,
+    Instant,
+  ),
 }
 
 impl<F, T, R> Future for HandlerServiceFuture<F, T, R>
@@ -127,11 +149,24 @@ where
   fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
     loop {
       match self.as_mut().project() {
-        HandlerServiceProj::Extract(fut, req, handle) => {
-          match ready!(fut.poll(cx)) {
+        HandlerServiceProj::Extract(fut, req, handle, started_at) => {
+          let result = ready!(fut.poll(cx));
+          if let Some(req) = req.as_ref() {
+            let phase = DispatchPhase::Extraction;
+            req.probes.exit(&req.id, &req.event, phase, *started_at);
+          }
+          match result {
             Ok(params) => {
-              let fut = handle.call(params);
-              let state = HandlerServiceFuture::Handle(fut, req.take());
+              let req = req.take();
+              if let Some(req) = req.as_ref() {
+                req
+                  .probes
+                  .enter(&req.id, &req.event, DispatchPhase::Handler);
+              }
+              let started_at = Instant::now();
+              let span = tracing::trace_span!("handle");
+              let fut = span.in_scope(|| handle.call(params));
+              let state = HandlerServiceFuture::Handle(fut.instrument(span), req, started_at);
               self.as_mut().set(state);
             },
             Err(err) => {
@@ -142,10 +177,14 @@ where
             },
           };
         },
-        HandlerServiceProj::Handle(fut, req) => {
+        HandlerServiceProj::Handle(fut, req, started_at) => {
           let result = ready!(fut.poll(cx));
           let req = req.take().unwrap();
-          let resp = result.respond_to(&req);
+          let probes = &req.probes;
+          probes.exit(&req.id, &req.event, DispatchPhase::Handler, *started_at);
+          let resp = probes.scope(&req.id, &req.event, DispatchPhase::Serialization, || {
+            result.respond_to(&req)
+          });
           return Poll::Ready(Ok(ServiceResponse::new(req, resp)));
         },
       }
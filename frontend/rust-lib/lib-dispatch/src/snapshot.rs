@@ -0,0 +1,217 @@
+//! Golden-file assertions on the responses of the events.
+//!
+//! A [Snapshot] turns a response into a JSON document: its status and its payload, parsed as JSON
+//! when possible, kept as a string when it's UTF-8 and as hex otherwise. The fields that change
+//! from one run to the other, like the ids and the timestamps, are replaced by [REDACTED] before
+//! the document is compared with the one committed at the snapshot path.
+//!
+//! A missing snapshot is written instead of being compared, and all of them are rewritten when
+//! the [UPDATE_SNAPSHOTS_ENV] environment variable is set, so accepting a contract change is a
+//! matter of running the tests once with it and committing the result.
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+use serde_json::{Map, Value};
+
+use crate::response::{AFPluginEventResponse, StatusCode};
+
+/// Rewrite the snapshots instead of comparing them when this environment variable is set.
+pub const UPDATE_SNAPSHOTS_ENV: &str = "UPDATE_SNAPSHOTS";
+
+/// The value the redacted fields are replaced with.
+pub const REDACTED: &str = "[redacted]";
+
+/// The fields that are redacted by default.
+pub const DEFAULT_REDACTED_FIELDS: &[&str] = &["id", "uid", "timestamp", "request_id"];
+
+/// The fields ending with one of these are redacted by default, like `view_id` or `created_at`.
+pub const DEFAULT_REDACTED_SUFFIXES: &[&str] = &["_id", "_at", "_timestamp"];
+
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+  fields: Vec<String>,
+  suffixes: Vec<String>,
+  kept: Vec<String>,
+}
+
+impl std::default::Default for Snapshot {
+  fn default() -> Self {
+    Self {
+      fields: DEFAULT_REDACTED_FIELDS
+        .iter()
+        .map(|f| f.to_string())
+        .collect(),
+      suffixes: DEFAULT_REDACTED_SUFFIXES
+        .iter()
+        .map(|s| s.to_string())
+        .collect(),
+      kept: vec![],
+    }
+  }
+}
+
+impl Snapshot {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Redact the fields named `field` too.
+  pub fn redact(mut self, field: &str) -> Self {
+    self.fields.push(field.to_owned());
+    self
+  }
+
+  /// Keep the fields named `field` even if they match the redacted names.
+  pub fn keep(mut self, field: &str) -> Self {
+    self.kept.push(field.to_owned());
+    self
+  }
+
+  fn is_redacted(&self, field: &str) -> bool {
+    if self.kept.iter().any(|kept| kept == field) {
+      return false;
+    }
+    self.fields.iter().any(|redacted| redacted == field)
+      || self
+        .suffixes
+        .iter()
+        .any(|suffix| field.ends_with(suffix.as_str()))
+  }
+
+  /// The document of `response` that is compared with the snapshot.
+  pub fn normalize(&self, response: &AFPluginEventResponse) -> Value {
+    let status = match response.status_code {
+      StatusCode::Ok => "Ok",
+      StatusCode::Err => "Err",
+    };
+    let bytes = response.payload.as_ref();
+    let payload = match serde_json::from_slice::<Value>(bytes) {
+      Ok(value) => self.normalize_value(value),
+      Err(_) => match std::str::from_utf8(bytes) {
+        Ok(s) => Value::String(s.to_owned()),
+        Err(_) => {
+          let mut map = Map::new();
+          map.insert("hex".to_owned(), Value::String(hex(bytes)));
+          Value::Object(map)
+        },
+      },
+    };
+    let mut document = Map::new();
+    document.insert("status".to_owned(), Value::String(status.to_owned()));
+    document.insert("payload".to_owned(), payload);
+    Value::Object(document)
+  }
+
+  /// Replace the redacted fields of `value`, at any depth.
+  pub fn normalize_value(&self, value: Value) -> Value {
+    match value {
+      Value::Object(map) => Value::Object(
+        map
+          .into_iter()
+          .map(|(key, value)| {
+            let value = if self.is_redacted(&key) && !value.is_object() && !value.is_array() {
+              Value::String(REDACTED.to_owned())
+            } else {
+              self.normalize_value(value)
+            };
+            (key, value)
+          })
+          .collect(),
+      ),
+      Value::Array(values) => Value::Array(
+        values
+          .into_iter()
+          .map(|value| self.normalize_value(value))
+          .collect(),
+      ),
+      value => value,
+    }
+  }
+
+  /// Compare `response` with the snapshot at `path`, see the module documentation.
+  pub fn check<P: AsRef<Path>>(
+    &self,
+    path: P,
+    response: &AFPluginEventResponse,
+  ) -> Result<(), SnapshotMismatch> {
+    self.check_value(path, self.normalize(response))
+  }
+
+  /// Compare a document that was already normalized with the snapshot at `path`. Useful for the
+  /// responses that need to be decoded first, like the protobuf ones.
+  pub fn check_value<P: AsRef<Path>>(
+    &self,
+    path: P,
+    actual: Value,
+  ) -> Result<(), SnapshotMismatch> {
+    let path = path.as_ref();
+    let actual = format!("{}\n", serde_json::to_string_pretty(&actual).unwrap());
+    let update = std::env::var_os(UPDATE_SNAPSHOTS_ENV).is_some();
+    match std::fs::read_to_string(path) {
+      Ok(expected) if !update => {
+        if expected == actual {
+          Ok(())
+        } else {
+          Err(SnapshotMismatch {
+            path: path.to_path_buf(),
+            expected,
+            actual,
+          })
+        }
+      },
+      _ => {
+        tracing::info!("[snapshot]: writing {}", path.display());
+        if let Some(parent) = path.parent() {
+          let _ = std::fs::create_dir_all(parent);
+        }
+        std::fs::write(path, actual).map_err(|e| SnapshotMismatch {
+          path: path.to_path_buf(),
+          expected: String::new(),
+          actual: e.to_string(),
+        })
+      },
+    }
+  }
+
+  /// Like [Snapshot::check] but panics with both documents when they differ.
+  #[track_caller]
+  pub fn assert_matches<P: AsRef<Path>>(&self, path: P, response: &AFPluginEventResponse) {
+    if let Err(mismatch) = self.check(path, response) {
+      panic!("{}", mismatch);
+    }
+  }
+}
+
+/// Compare `response` with the snapshot at `path` using the default redactions.
+#[track_caller]
+pub fn assert_snapshot<P: AsRef<Path>>(path: P, response: &AFPluginEventResponse) {
+  Snapshot::new().assert_matches(path, response)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotMismatch {
+  pub path: PathBuf,
+  pub expected: String,
+  pub actual: String,
+}
+
+impl Display for SnapshotMismatch {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "the response does not match the snapshot {}, set {} to update it\nexpected:\n{}\nactual:\n{}",
+      self.path.display(),
+      UPDATE_SNAPSHOTS_ENV,
+      self.expected,
+      self.actual
+    )
+  }
+}
+
+fn hex(bytes: &[u8]) -> String {
+  use std::fmt::Write;
+  bytes.iter().fold(String::new(), |mut s, b| {
+    let _ = write!(s, "{:02x}", b);
+    s
+  })
+}
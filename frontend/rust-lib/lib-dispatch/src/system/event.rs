@@ -0,0 +1,84 @@
+use std::fmt::{Display, Formatter};
+
+/// The events handled by the dispatcher itself. They are prefixed with `Sys` to avoid colliding
+/// with the events of the plugins.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SysEvent {
+  /// Returns the requests kept by the event recorder as a JSON array.
+  DumpRecorder,
+  /// Returns a [DispatcherSnapshot](crate::system::DispatcherSnapshot) as JSON.
+  Inspect,
+  /// Returns the [EventSchema](crate::prelude::EventSchema)s of all the events as a JSON array,
+  /// or only the one of the event whose name is the payload.
+  Schema,
+  /// Returns a [MemoryReport](crate::memory::MemoryReport) as JSON.
+  MemoryReport,
+  /// Cancels the in-flight requests whose id is the payload. Returns `true`, or `false` if none
+  /// of them is in flight.
+  Cancel,
+  /// Starts recording the timeline of the requests when the payload is `on`. Stops it when the
+  /// payload is `off` and returns the timeline in the Chrome trace event format, see
+  /// [ChromeTracer](crate::prelude::ChromeTracer).
+  Trace,
+  /// Returns a [HealthReport](crate::system::HealthReport) as JSON. Cheap enough to be polled
+  /// by a supervisor.
+  HealthCheck,
+  /// Returns a [SystemInfo](crate::system::SystemInfo) as JSON: the versions, the features and
+  /// the encodings supported by the core.
+  Info,
+  /// Opens an upload session for the request whose id is the payload, see the
+  /// [upload](crate::upload) module.
+  UploadOpen,
+  /// Sends a chunk of an upload, the payload is built with
+  /// [encode_chunk](crate::upload::encode_chunk). Responds once the handler made room for it.
+  UploadChunk,
+  /// Closes the upload of the request whose id is the payload, which ends its stream. Returns
+  /// `true`, or `false` if it's not open.
+  UploadClose,
+  /// Applies the JSON merge patch of the [SystemConfig](crate::config::SystemConfig) in the
+  /// payload, see [LiveConfig::apply](crate::system::LiveConfig::apply). Returns the
+  /// [ConfigChange](crate::system::ConfigChange) as JSON, an empty patch returns the current
+  /// config unchanged.
+  Reconfigure,
+  /// Returns the [CrashReport](crate::crash::CrashReport) the previous run of the app wrote to
+  /// the crash file as JSON, or `null` if it didn't crash, and removes it. See
+  /// [AFPluginDispatcher::with_crash_file](crate::prelude::AFPluginDispatcher::with_crash_file).
+  CrashReport,
+  /// Changes the level of the logs of a module, or of all of them, with the
+  /// [SetLogLevel](crate::system::SetLogLevel) in the payload. It's a [SysEvent::Reconfigure] of
+  /// the `log_level` or the `log_levels`, applied by the observers of the config. Returns the
+  /// [LogLevels](crate::system::LogLevels) as JSON.
+  SetLogLevel,
+  /// Overrides a feature flag with the [FlagOverride](crate::feature_flag::FlagOverride) in the
+  /// payload, see [FeatureFlags](crate::feature_flag::FeatureFlags). Returns the overrides as a
+  /// JSON object, by flag.
+  SetFeatureFlag,
+  /// Injects the fault of the [FaultCommand](crate::fault::FaultCommand) in the payload in the
+  /// requests of its event, or removes it. Returns the active faults as JSON, by event.
+  #[cfg(feature = "fault_injection")]
+  InjectFault,
+}
+
+impl Display for SysEvent {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      SysEvent::DumpRecorder => f.write_str("SysDumpRecorder"),
+      SysEvent::Inspect => f.write_str("SysInspect"),
+      SysEvent::Schema => f.write_str("SysSchema"),
+      SysEvent::MemoryReport => f.write_str("SysMemoryReport"),
+      SysEvent::Cancel => f.write_str("SysCancel"),
+      SysEvent::Trace => f.write_str("SysTrace"),
+      SysEvent::HealthCheck => f.write_str("SysHealthCheck"),
+      SysEvent::Info => f.write_str("SysInfo"),
+      SysEvent::UploadOpen => f.write_str("SysUploadOpen"),
+      SysEvent::UploadChunk => f.write_str("SysUploadChunk"),
+      SysEvent::UploadClose => f.write_str("SysUploadClose"),
+      SysEvent::Reconfigure => f.write_str("SysReconfigure"),
+      SysEvent::CrashReport => f.write_str("SysCrashReport"),
+      SysEvent::SetLogLevel => f.write_str("SysSetLogLevel"),
+      SysEvent::SetFeatureFlag => f.write_str("SysSetFeatureFlag"),
+      #[cfg(feature = "fault_injection")]
+      SysEvent::InjectFault => f.write_str("SysInjectFault"),
+    }
+  }
+}
@@ -0,0 +1,221 @@
+use serde::{Deserialize, Serialize};
+
+use crate::crash::take_crash_report;
+use crate::errors::{DispatchError, InternalError};
+#[cfg(feature = "fault_injection")]
+use crate::fault::FaultCommand;
+use crate::feature_flag::FlagOverride;
+use crate::metrics::DISPATCH_QUEUED;
+use crate::module::AFPluginState;
+use crate::recorder::EventRecord;
+use crate::system::{HealthReport, LogLevels, PluginInfo, SetLogLevel, SystemInfo, SystemState};
+use crate::upload::UploadChunk;
+
+/// The number of failed requests included in the [DispatcherSnapshot].
+const RECENT_ERRORS_LIMIT: usize = 10;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InFlightRequestSnapshot {
+  pub id: String,
+  pub event: String,
+  pub age_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DispatcherSnapshot {
+  pub plugins: Vec<PluginInfo>,
+  /// The requests that were sent but haven't started yet.
+  pub queue_depth: i64,
+  pub in_flight: Vec<InFlightRequestSnapshot>,
+  pub num_workers: usize,
+  pub recent_errors: Vec<EventRecord>,
+}
+
+impl DispatcherSnapshot {
+  pub fn new(state: &SystemState) -> Self {
+    let in_flight = state
+      .in_flight
+      .snapshot()
+      .into_iter()
+      .map(|request| InFlightRequestSnapshot {
+        age_ms: request.age().as_millis() as u64,
+        id: request.id,
+        event: request.event,
+      })
+      .collect();
+    let mut recent_errors = state
+      .recorder
+      .dump()
+      .into_iter()
+      .filter(|record| !record.success)
+      .collect::<Vec<_>>();
+    let skip = recent_errors.len().saturating_sub(RECENT_ERRORS_LIMIT);
+    recent_errors.drain(..skip);
+
+    Self {
+      plugins: state.plugins.get().cloned().unwrap_or_default(),
+      queue_depth: state.metrics.gauge(DISPATCH_QUEUED, &[]).get(),
+      in_flight,
+      num_workers: state.num_workers,
+      recent_errors,
+    }
+  }
+}
+
+pub(crate) async fn dump_recorder_handler(
+  state: AFPluginState<SystemState>,
+) -> Result<String, DispatchError> {
+  let records = state.recorder.dump();
+  serde_json::to_string(&records).map_err(|e| InternalError::Other(e.to_string()).into())
+}
+
+pub(crate) async fn inspect_handler(
+  state: AFPluginState<SystemState>,
+) -> Result<String, DispatchError> {
+  let snapshot = DispatcherSnapshot::new(state.get_ref());
+  serde_json::to_string(&snapshot).map_err(|e| InternalError::Other(e.to_string()).into())
+}
+
+pub(crate) async fn schema_handler(
+  event: Result<String, DispatchError>,
+  state: AFPluginState<SystemState>,
+) -> Result<String, DispatchError> {
+  let schemas = state.schemas.get().map(Vec::as_slice).unwrap_or_default();
+  let json = match event {
+    Err(_) => serde_json::to_string(schemas),
+    Ok(event) => match schemas.iter().find(|schema| schema.event == event) {
+      None => {
+        let msg = format!("Can not find the schema of event: {}", event);
+        return Err(InternalError::ServiceNotFound(msg).into());
+      },
+      Some(schema) => serde_json::to_string(schema),
+    },
+  };
+  json.map_err(|e| InternalError::Other(e.to_string()).into())
+}
+
+pub(crate) async fn memory_report_handler(
+  state: AFPluginState<SystemState>,
+) -> Result<String, DispatchError> {
+  let report = state.memory.report();
+  serde_json::to_string(&report).map_err(|e| InternalError::Other(e.to_string()).into())
+}
+
+pub(crate) async fn cancel_handler(
+  request_id: String,
+  state: AFPluginState<SystemState>,
+) -> String {
+  state.in_flight.cancel(&request_id).to_string()
+}
+
+pub(crate) async fn trace_handler(
+  command: String,
+  state: AFPluginState<SystemState>,
+) -> Result<String, DispatchError> {
+  match command.as_str() {
+    "on" => {
+      state.tracer.start();
+      Ok(String::new())
+    },
+    "off" => Ok(state.tracer.stop()),
+    _ => {
+      let msg = format!("Expected on or off, got: {}", command);
+      Err(InternalError::DeserializeFromBytes(msg).into())
+    },
+  }
+}
+
+pub(crate) async fn health_check_handler(
+  state: AFPluginState<SystemState>,
+) -> Result<String, DispatchError> {
+  let report = HealthReport::new(state.get_ref());
+  serde_json::to_string(&report).map_err(|e| InternalError::Other(e.to_string()).into())
+}
+
+pub(crate) async fn info_handler(
+  state: AFPluginState<SystemState>,
+) -> Result<String, DispatchError> {
+  let info = SystemInfo::new(state.get_ref());
+  serde_json::to_string(&info).map_err(|e| InternalError::Other(e.to_string()).into())
+}
+
+pub(crate) async fn upload_open_handler(
+  request_id: String,
+  state: AFPluginState<SystemState>,
+) -> Result<(), DispatchError> {
+  state.uploads.open(&request_id)
+}
+
+pub(crate) async fn upload_chunk_handler(
+  chunk: UploadChunk,
+  state: AFPluginState<SystemState>,
+) -> Result<(), DispatchError> {
+  state
+    .uploads
+    .send_chunk(&chunk.request_id, chunk.chunk)
+    .await
+}
+
+pub(crate) async fn upload_close_handler(
+  request_id: String,
+  state: AFPluginState<SystemState>,
+) -> String {
+  state.uploads.close(&request_id).to_string()
+}
+
+pub(crate) async fn reconfigure_handler(
+  delta: String,
+  state: AFPluginState<SystemState>,
+) -> Result<String, DispatchError> {
+  let change = state
+    .config
+    .apply(&delta)
+    .map_err(|e| InternalError::DeserializeFromBytes(e.to_string()))?;
+  serde_json::to_string(&change).map_err(|e| InternalError::Other(e.to_string()).into())
+}
+
+pub(crate) async fn set_log_level_handler(
+  change: String,
+  state: AFPluginState<SystemState>,
+) -> Result<String, DispatchError> {
+  let change: SetLogLevel = serde_json::from_str(&change)
+    .map_err(|e| InternalError::DeserializeFromBytes(e.to_string()))?;
+  let change = state
+    .config
+    .set_log_level(&change)
+    .map_err(|e| InternalError::DeserializeFromBytes(e.to_string()))?;
+  serde_json::to_string(&LogLevels::from(&change.current))
+    .map_err(|e| InternalError::Other(e.to_string()).into())
+}
+
+pub(crate) async fn set_feature_flag_handler(
+  change: String,
+  state: AFPluginState<SystemState>,
+) -> Result<String, DispatchError> {
+  let change: FlagOverride = serde_json::from_str(&change)
+    .map_err(|e| InternalError::DeserializeFromBytes(e.to_string()))?;
+  state
+    .feature_flags
+    .set_override(&change.flag, change.enabled)?;
+  serde_json::to_string(&state.feature_flags.overrides())
+    .map_err(|e| InternalError::Other(e.to_string()).into())
+}
+
+pub(crate) async fn crash_report_handler(
+  state: AFPluginState<SystemState>,
+) -> Result<String, DispatchError> {
+  let report = state.crash_file.get().and_then(take_crash_report);
+  serde_json::to_string(&report).map_err(|e| InternalError::Other(e.to_string()).into())
+}
+
+#[cfg(feature = "fault_injection")]
+pub(crate) async fn inject_fault_handler(
+  command: String,
+  state: AFPluginState<SystemState>,
+) -> Result<String, DispatchError> {
+  let command: FaultCommand = serde_json::from_str(&command)
+    .map_err(|e| InternalError::DeserializeFromBytes(e.to_string()))?;
+  state.faults.apply(command);
+  serde_json::to_string(&state.faults.rules())
+    .map_err(|e| InternalError::Other(e.to_string()).into())
+}
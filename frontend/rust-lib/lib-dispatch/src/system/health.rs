@@ -0,0 +1,121 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::metrics::DISPATCH_QUEUED;
+use crate::system::{InFlightRequestSnapshot, SystemState};
+
+/// The requests running for longer than this are reported as stalled by the health check. They
+/// most likely block the worker that runs them.
+pub const STALLED_REQUEST_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// Whether a state registered with [AFPlugin::state_lazy](crate::prelude::AFPlugin::state_lazy)
+/// is built.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", content = "reason", rename_all = "snake_case")]
+pub enum InitStatus {
+  /// No handler extracted it yet.
+  Pending,
+  Ready,
+  /// The last attempt failed, the next handler extracting it tries again.
+  Failed(String),
+}
+
+/// The initialization of a lazy state of a plugin.
+#[derive(Debug, Clone)]
+pub struct ModuleInit {
+  pub plugin: String,
+  pub state: String,
+  status: Arc<Mutex<InitStatus>>,
+}
+
+impl ModuleInit {
+  pub(crate) fn new(plugin: &str, state: &str, status: Arc<Mutex<InitStatus>>) -> Self {
+    Self {
+      plugin: plugin.to_owned(),
+      state: state.to_owned(),
+      status,
+    }
+  }
+
+  pub fn status(&self) -> InitStatus {
+    match self.status.lock() {
+      Ok(status) => status.clone(),
+      Err(poisoned) => poisoned.into_inner().clone(),
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ModuleHealth {
+  pub plugin: String,
+  pub state: String,
+  #[serde(flatten)]
+  pub status: InitStatus,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WorkerHealth {
+  pub num_workers: usize,
+  /// The requests running for longer than [STALLED_REQUEST_THRESHOLD].
+  pub stalled: Vec<InFlightRequestSnapshot>,
+}
+
+/// The liveness of the dispatcher, returned by the
+/// [SysEvent::HealthCheck](crate::system::SysEvent::HealthCheck) event.
+#[derive(Debug, Serialize)]
+pub struct HealthReport {
+  /// False if a lazy state failed to initialize, or if every worker runs a stalled request.
+  pub healthy: bool,
+  pub uptime_ms: u64,
+  /// The requests that were sent but haven't started yet.
+  pub queue_depth: i64,
+  pub in_flight: usize,
+  pub workers: WorkerHealth,
+  pub modules: Vec<ModuleHealth>,
+}
+
+impl HealthReport {
+  pub fn new(state: &SystemState) -> Self {
+    let stalled = state
+      .in_flight
+      .snapshot()
+      .into_iter()
+      .filter(|request| request.age() >= STALLED_REQUEST_THRESHOLD)
+      .map(|request| InFlightRequestSnapshot {
+        age_ms: request.age().as_millis() as u64,
+        id: request.id,
+        event: request.event,
+      })
+      .collect::<Vec<_>>();
+    let modules = state
+      .modules
+      .get()
+      .map(Vec::as_slice)
+      .unwrap_or_default()
+      .iter()
+      .map(|module| ModuleHealth {
+        plugin: module.plugin.clone(),
+        state: module.state.clone(),
+        status: module.status(),
+      })
+      .collect::<Vec<_>>();
+    let healthy = stalled.len() < state.num_workers.max(1)
+      && !modules
+        .iter()
+        .any(|module| matches!(module.status, InitStatus::Failed(_)));
+
+    Self {
+      healthy,
+      uptime_ms: state.started_at.elapsed().as_millis() as u64,
+      queue_depth: state.metrics.gauge(DISPATCH_QUEUED, &[]).get(),
+      in_flight: state.in_flight.len(),
+      workers: WorkerHealth {
+        num_workers: state.num_workers,
+        stalled,
+      },
+      modules,
+    }
+  }
+}
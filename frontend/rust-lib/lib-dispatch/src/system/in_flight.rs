@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio_util::sync::CancellationToken;
+
+use crate::memory::MemoryUsage;
+use crate::metrics::Gauge;
+
+#[derive(Debug, Clone)]
+pub struct InFlightRequest {
+  pub id: String,
+  pub event: String,
+  pub started_at: Instant,
+  /// Cancelled by [InFlightRequests::cancel], or along with the token of the operation that
+  /// sent the request. The handler gets it with the [CancellationToken] extractor.
+  pub cancellation: CancellationToken,
+}
+
+impl InFlightRequest {
+  pub fn age(&self) -> Duration {
+    self.started_at.elapsed()
+  }
+}
+
+/// The requests that are currently being handled by the dispatcher.
+#[derive(Debug, Default)]
+pub struct InFlightRequests {
+  next_key: AtomicU64,
+  requests: Mutex<HashMap<u64, InFlightRequest>>,
+}
+
+impl InFlightRequests {
+  /// Tracks the request until the returned guard is dropped, which also covers the requests
+  /// whose future is dropped before completion. The token of the request is a child of `parent`
+  /// if given.
+  pub(crate) fn start(
+    self: &Arc<Self>,
+    id: &str,
+    event: &str,
+    gauge: Arc<Gauge>,
+    parent: Option<&CancellationToken>,
+  ) -> InFlightGuard {
+    let key = self.next_key.fetch_add(1, Ordering::Relaxed);
+    let request = InFlightRequest {
+      id: id.to_owned(),
+      event: event.to_owned(),
+      started_at: Instant::now(),
+      cancellation: parent.map_or_else(CancellationToken::new, CancellationToken::child_token),
+    };
+    let cancellation = request.cancellation.clone();
+    if let Ok(mut requests) = self.requests.lock() {
+      requests.insert(key, request);
+    }
+    gauge.inc();
+    InFlightGuard {
+      key,
+      requests: self.clone(),
+      gauge,
+      cancellation,
+    }
+  }
+
+  /// Cancels the requests whose id is `id`. Returns false if none of them is in flight.
+  ///
+  /// The cancellation is cooperative: the handler keeps running until it checks its
+  /// [CancellationToken].
+  pub fn cancel(&self, id: &str) -> bool {
+    let requests = match self.requests.lock() {
+      Ok(requests) => requests,
+      Err(_) => return false,
+    };
+    let mut cancelled = false;
+    for request in requests.values().filter(|request| request.id == id) {
+      request.cancellation.cancel();
+      cancelled = true;
+    }
+    cancelled
+  }
+
+  /// Returns the in-flight requests, from the oldest to the newest.
+  pub fn snapshot(&self) -> Vec<InFlightRequest> {
+    let mut requests = match self.requests.lock() {
+      Ok(requests) => requests.values().cloned().collect::<Vec<_>>(),
+      Err(_) => vec![],
+    };
+    requests.sort_by_key(|request| request.started_at);
+    requests
+  }
+
+  pub fn len(&self) -> usize {
+    self.requests.lock().map(|r| r.len()).unwrap_or(0)
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// The memory held to track the requests, not the requests themselves.
+  pub fn memory_usage(&self) -> MemoryUsage {
+    let requests = match self.requests.lock() {
+      Ok(requests) => requests,
+      Err(_) => return MemoryUsage::default(),
+    };
+    let bytes = requests
+      .values()
+      .map(|request| request.id.len() + request.event.len())
+      .sum::<usize>()
+      + requests.capacity() * std::mem::size_of::<(u64, InFlightRequest)>();
+    MemoryUsage::new(bytes, requests.len())
+  }
+}
+
+pub(crate) struct InFlightGuard {
+  key: u64,
+  requests: Arc<InFlightRequests>,
+  gauge: Arc<Gauge>,
+  cancellation: CancellationToken,
+}
+
+impl InFlightGuard {
+  pub(crate) fn cancellation(&self) -> CancellationToken {
+    self.cancellation.clone()
+  }
+}
+
+impl Drop for InFlightGuard {
+  fn drop(&mut self) {
+    if let Ok(mut requests) = self.requests.requests.lock() {
+      requests.remove(&self.key);
+    }
+    self.gauge.dec();
+  }
+}
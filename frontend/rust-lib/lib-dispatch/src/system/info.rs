@@ -0,0 +1,81 @@
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+
+use serde::Serialize;
+
+use crate::encoding::ContentType;
+use crate::system::SystemState;
+
+/// The cargo features of lib-dispatch, and whether this build enables them.
+const FEATURES: &[(&str, bool)] = &[
+  ("local_set", cfg!(feature = "local_set")),
+  ("use_serde", cfg!(feature = "use_serde")),
+  ("use_protobuf", cfg!(feature = "use_protobuf")),
+  ("protobuf_lite", cfg!(feature = "protobuf_lite")),
+  ("use_flatbuffers", cfg!(feature = "use_flatbuffers")),
+  ("use_capnp", cfg!(feature = "use_capnp")),
+  ("use_msgpack", cfg!(feature = "use_msgpack")),
+  ("use_cbor", cfg!(feature = "use_cbor")),
+  ("use_bincode", cfg!(feature = "use_bincode")),
+  ("ws_bridge", cfg!(feature = "ws_bridge")),
+  ("http_bridge", cfg!(feature = "http_bridge")),
+  ("grpc_bridge", cfg!(feature = "grpc_bridge")),
+  ("dylib_plugins", cfg!(feature = "dylib_plugins")),
+  ("fuzz", cfg!(feature = "fuzz")),
+  ("load_generator", cfg!(feature = "load_generator")),
+  ("dashboard", cfg!(feature = "dashboard")),
+  ("fault_injection", cfg!(feature = "fault_injection")),
+  ("mock", cfg!(feature = "mock")),
+  ("testing", cfg!(feature = "testing")),
+  ("request_signing", cfg!(feature = "request_signing")),
+  ("toml_config", cfg!(feature = "toml_config")),
+  ("codegen", cfg!(feature = "codegen")),
+  ("cli", cfg!(feature = "cli")),
+];
+
+/// What the core supports, returned by the [SysEvent::Info](crate::system::SysEvent::Info)
+/// event so the clients can adapt to it.
+#[derive(Debug, Serialize)]
+pub struct SystemInfo {
+  /// The version of lib-dispatch, and the ones registered with
+  /// `AFPluginDispatcher::with_crate_version`.
+  pub crate_versions: BTreeMap<String, String>,
+  /// The cargo features of lib-dispatch enabled in this build.
+  pub features: Vec<&'static str>,
+  /// The mime types of the payload encodings.
+  pub encodings: Vec<&'static str>,
+  /// The contract versions of the events that declared them, see
+  /// [AFPlugin::versions](crate::prelude::AFPlugin::versions).
+  pub event_versions: BTreeMap<String, RangeInclusive<u32>>,
+}
+
+impl SystemInfo {
+  pub fn new(state: &SystemState) -> Self {
+    let crate_versions = match state.crate_versions.read() {
+      Ok(versions) => versions.clone(),
+      Err(poisoned) => poisoned.into_inner().clone(),
+    };
+    let event_versions = state
+      .schemas
+      .get()
+      .map(Vec::as_slice)
+      .unwrap_or_default()
+      .iter()
+      .filter_map(|schema| Some((schema.event.clone(), schema.versions.clone()?)))
+      .collect();
+
+    Self {
+      crate_versions,
+      features: FEATURES
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(feature, _)| *feature)
+        .collect(),
+      encodings: ContentType::supported()
+        .iter()
+        .map(ContentType::as_str)
+        .collect(),
+      event_versions,
+    }
+  }
+}
@@ -0,0 +1,231 @@
+pub use event::*;
+pub use handler::{DispatcherSnapshot, InFlightRequestSnapshot};
+pub use health::*;
+pub use in_flight::*;
+pub use info::*;
+pub use reconfigure::*;
+
+mod event;
+mod handler;
+mod health;
+mod in_flight;
+mod info;
+mod reconfigure;
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Instant;
+
+use serde::Serialize;
+
+#[cfg(feature = "fault_injection")]
+use crate::fault::FaultInjector;
+use crate::feature_flag::FeatureFlags;
+use crate::gate::AppStates;
+use crate::lifecycle::Lifecycle;
+use crate::memory::{MemoryReporters, MemoryUsage};
+use crate::metrics::{MetricsRegistry, DISPATCH_QUEUED};
+#[cfg(any(test, feature = "mock"))]
+use crate::mock::EventMocks;
+use crate::module::{AFPlugin, AFPluginRequest, EventSchema};
+use crate::probe::ChromeTracer;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::proxy::RoutingTable;
+use crate::quota::ResourceAccounting;
+use crate::recorder::EventRecorder;
+use crate::upload::UploadSessions;
+
+/// The name of the plugin that handles the [SysEvent]s.
+pub const SYSTEM_PLUGIN_NAME: &str = "lib-dispatch";
+
+/// The prefix of the [SysEvent]s, which the other plugins can't register.
+pub const SYSTEM_EVENT_PREFIX: &str = "Sys";
+
+/// Required to change the configuration of the dispatcher, its log levels and its feature flags.
+pub const CONFIGURE_CAPABILITY: &str = "system.configure";
+
+/// Required to inspect the requests in flight, to read the recorder, the traces and the crash
+/// report, and to inject faults.
+pub const DEBUG_CAPABILITY: &str = "system.debug";
+
+/// Required to cancel the requests, which may be the ones of other callers.
+pub const CANCEL_CAPABILITY: &str = "system.cancel";
+
+/// Required to upload the payloads too big for a single request.
+pub const UPLOAD_CAPABILITY: &str = "system.upload";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginInfo {
+  pub name: String,
+  pub events: Vec<String>,
+}
+
+/// The dispatcher internals exposed to the [SysEvent] handlers.
+#[derive(Clone)]
+pub struct SystemState {
+  pub metrics: Arc<MetricsRegistry>,
+  pub recorder: Arc<EventRecorder>,
+  pub in_flight: Arc<InFlightRequests>,
+  #[cfg(any(test, feature = "mock"))]
+  pub mocks: Arc<EventMocks>,
+  pub memory: Arc<MemoryReporters>,
+  pub app_states: Arc<AppStates>,
+  /// The flags the events are gated behind, overridden with [SysEvent::SetFeatureFlag].
+  pub feature_flags: Arc<FeatureFlags>,
+  pub lifecycle: Arc<Lifecycle>,
+  pub tracer: Arc<ChromeTracer>,
+  pub uploads: Arc<UploadSessions>,
+  pub accounting: Arc<ResourceAccounting>,
+  /// The settings of the dispatcher, changed with [SysEvent::Reconfigure].
+  pub config: Arc<LiveConfig>,
+  /// The faults injected in the requests, changed with [SysEvent::InjectFault].
+  #[cfg(feature = "fault_injection")]
+  pub faults: Arc<FaultInjector>,
+  #[cfg(not(target_arch = "wasm32"))]
+  pub routes: Arc<RoutingTable>,
+  /// The plugins registered in the dispatcher, including the system plugin. It's set once all
+  /// the plugins are known.
+  pub plugins: Arc<OnceLock<Vec<PluginInfo>>>,
+  /// The schemas of all the events, sorted by event. Set along with the plugins.
+  pub schemas: Arc<OnceLock<Vec<EventSchema>>>,
+  /// The lazy states of the plugins. Set along with the plugins.
+  pub modules: Arc<OnceLock<Vec<ModuleInit>>>,
+  /// The file the crash reports are written to, read by [SysEvent::CrashReport]. Set by
+  /// [AFPluginDispatcher::with_crash_file](crate::prelude::AFPluginDispatcher::with_crash_file).
+  pub crash_file: Arc<OnceLock<PathBuf>>,
+  /// The versions of the crates reported by [SysEvent::Info], by crate name.
+  pub crate_versions: Arc<RwLock<BTreeMap<String, String>>>,
+  pub num_workers: usize,
+  pub started_at: Instant,
+}
+
+impl SystemState {
+  pub(crate) fn new(num_workers: usize) -> Self {
+    let metrics = Arc::new(MetricsRegistry::new());
+    let recorder = Arc::new(EventRecorder::default());
+    let in_flight = Arc::new(InFlightRequests::default());
+    let memory = Arc::new(MemoryReporters::default());
+    let queued = metrics.gauge(DISPATCH_QUEUED, &[]);
+    memory.register("dispatch.queue", move || {
+      MemoryUsage::of::<AFPluginRequest>(queued.get().max(0) as usize)
+    });
+    let cloned_recorder = recorder.clone();
+    memory.register("dispatch.recorder", move || cloned_recorder.memory_usage());
+    let cloned_in_flight = in_flight.clone();
+    memory.register("dispatch.in_flight", move || {
+      cloned_in_flight.memory_usage()
+    });
+    let accounting = Arc::new(ResourceAccounting::new(metrics.clone()));
+    let config = Arc::new(LiveConfig::default());
+    let cloned_accounting = accounting.clone();
+    config.observe(move |change: &ConfigChange| update_max_pending(&cloned_accounting, change));
+    Self {
+      accounting,
+      config,
+      metrics,
+      recorder,
+      in_flight,
+      #[cfg(any(test, feature = "mock"))]
+      mocks: Arc::new(EventMocks::default()),
+      memory,
+      app_states: Arc::new(AppStates::default()),
+      feature_flags: Arc::new(FeatureFlags::default()),
+      lifecycle: Arc::new(Lifecycle::default()),
+      tracer: Arc::new(ChromeTracer::default()),
+      uploads: Arc::new(UploadSessions::default()),
+      #[cfg(feature = "fault_injection")]
+      faults: Arc::new(FaultInjector::default()),
+      #[cfg(not(target_arch = "wasm32"))]
+      routes: Arc::new(RoutingTable::default()),
+      plugins: Arc::new(OnceLock::new()),
+      schemas: Arc::new(OnceLock::new()),
+      modules: Arc::new(OnceLock::new()),
+      crash_file: Arc::new(OnceLock::new()),
+      crate_versions: Arc::new(RwLock::new(BTreeMap::from([(
+        env!("CARGO_PKG_NAME").to_owned(),
+        env!("CARGO_PKG_VERSION").to_owned(),
+      )]))),
+      num_workers,
+      started_at: Instant::now(),
+    }
+  }
+
+  pub(crate) fn set_plugins(&self, plugins: &[AFPlugin]) {
+    let infos = plugins
+      .iter()
+      .map(|plugin| {
+        let mut events = plugin
+          .events()
+          .into_iter()
+          .map(|event| event.as_str().to_owned())
+          .collect::<Vec<_>>();
+        events.sort();
+        PluginInfo {
+          name: plugin.name.clone(),
+          events,
+        }
+      })
+      .collect();
+    let _ = self.plugins.set(infos);
+
+    let mut schemas = plugins
+      .iter()
+      .flat_map(|plugin| plugin.schemas())
+      .collect::<Vec<_>>();
+    schemas.sort_by(|a, b| a.event.cmp(&b.event));
+    let _ = self.schemas.set(schemas);
+
+    let modules = plugins
+      .iter()
+      .flat_map(|plugin| {
+        plugin
+          .lazy_states()
+          .iter()
+          .map(move |(state, status)| ModuleInit::new(&plugin.name, state, status.clone()))
+      })
+      .collect();
+    let _ = self.modules.set(modules);
+  }
+}
+
+/// The built-in plugin registered by every dispatcher. Its events are handled by the dispatcher
+/// itself and never touch the user plugins. The ones that change the dispatcher or expose what
+/// other callers sent require a capability, which the requests of the bridges aren't granted
+/// unless their caller is.
+pub(crate) fn system_plugin(state: SystemState) -> AFPlugin {
+  let plugin = AFPlugin::new()
+    .name(SYSTEM_PLUGIN_NAME)
+    .state(state)
+    .event(SysEvent::DumpRecorder, handler::dump_recorder_handler)
+    .event(SysEvent::Inspect, handler::inspect_handler)
+    .event(SysEvent::Schema, handler::schema_handler)
+    .event(SysEvent::MemoryReport, handler::memory_report_handler)
+    .event(SysEvent::Cancel, handler::cancel_handler)
+    .event(SysEvent::Trace, handler::trace_handler)
+    .event(SysEvent::HealthCheck, handler::health_check_handler)
+    .event(SysEvent::Info, handler::info_handler)
+    .event(SysEvent::UploadOpen, handler::upload_open_handler)
+    .event(SysEvent::UploadChunk, handler::upload_chunk_handler)
+    .event(SysEvent::UploadClose, handler::upload_close_handler)
+    .event(SysEvent::Reconfigure, handler::reconfigure_handler)
+    .event(SysEvent::CrashReport, handler::crash_report_handler)
+    .event(SysEvent::SetLogLevel, handler::set_log_level_handler)
+    .event(SysEvent::SetFeatureFlag, handler::set_feature_flag_handler)
+    .requires_capability(SysEvent::Reconfigure, CONFIGURE_CAPABILITY)
+    .requires_capability(SysEvent::SetLogLevel, CONFIGURE_CAPABILITY)
+    .requires_capability(SysEvent::SetFeatureFlag, CONFIGURE_CAPABILITY)
+    .requires_capability(SysEvent::DumpRecorder, DEBUG_CAPABILITY)
+    .requires_capability(SysEvent::Inspect, DEBUG_CAPABILITY)
+    .requires_capability(SysEvent::Trace, DEBUG_CAPABILITY)
+    .requires_capability(SysEvent::CrashReport, DEBUG_CAPABILITY)
+    .requires_capability(SysEvent::Cancel, CANCEL_CAPABILITY)
+    .requires_capability(SysEvent::UploadOpen, UPLOAD_CAPABILITY)
+    .requires_capability(SysEvent::UploadChunk, UPLOAD_CAPABILITY)
+    .requires_capability(SysEvent::UploadClose, UPLOAD_CAPABILITY);
+  #[cfg(feature = "fault_injection")]
+  let plugin = plugin
+    .event(SysEvent::InjectFault, handler::inject_fault_handler)
+    .requires_capability(SysEvent::InjectFault, DEBUG_CAPABILITY);
+  plugin
+}
@@ -0,0 +1,260 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use arc_swap::ArcSwap;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::config::{SystemConfig, SystemConfigError};
+use crate::quota::ResourceAccounting;
+
+/// The settings that only take effect when the dispatcher is created.
+const RESTART_SETTINGS: [&str; 2] = ["worker_threads", "request_pool_capacity"];
+
+/// Told about the changes of the [SystemConfig] made with
+/// [SysEvent::Reconfigure](crate::system::SysEvent::Reconfigure), e.g. to apply the new log
+/// level. A closure taking the [ConfigChange] is an observer.
+pub trait ConfigObserver: Send + Sync + 'static {
+  fn on_config_changed(&self, change: &ConfigChange);
+}
+
+impl<F> ConfigObserver for F
+where
+  F: Fn(&ConfigChange) + Send + Sync + 'static,
+{
+  fn on_config_changed(&self, change: &ConfigChange) {
+    self(change)
+  }
+}
+
+/// The result of a reconfiguration, returned as JSON by
+/// [SysEvent::Reconfigure](crate::system::SysEvent::Reconfigure).
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigChange {
+  /// The settings whose value changed, sorted by name, e.g. `log_level`.
+  pub changed: Vec<String>,
+  pub previous: SystemConfig,
+  pub current: SystemConfig,
+}
+
+/// The payload of [SysEvent::SetLogLevel](crate::system::SysEvent::SetLogLevel).
+///
+/// ```json
+/// {"target": "flowy_folder", "level": "debug"}
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SetLogLevel {
+  /// The module, e.g. `flowy_folder` or `lib_dispatch::dispatcher`. When not set, the level of
+  /// the modules without their own is changed.
+  #[serde(default)]
+  pub target: Option<String>,
+  /// One of `trace`, `debug`, `info`, `warn`, `error` or `off`. When not set, the level of the
+  /// target is removed and it goes back to the one of the other modules.
+  #[serde(default)]
+  pub level: Option<String>,
+}
+
+impl SetLogLevel {
+  /// The JSON merge patch of the [SystemConfig] making the change.
+  fn to_delta(&self) -> Value {
+    let level = self.level.clone().map_or(Value::Null, Value::String);
+    let mut delta = Map::new();
+    match &self.target {
+      Some(target) => {
+        let mut levels = Map::new();
+        levels.insert(target.clone(), level);
+        delta.insert("log_levels".to_owned(), Value::Object(levels));
+      },
+      None => {
+        delta.insert("log_level".to_owned(), level);
+      },
+    }
+    Value::Object(delta)
+  }
+}
+
+/// The log levels of the core, returned as JSON by
+/// [SysEvent::SetLogLevel](crate::system::SysEvent::SetLogLevel).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogLevels {
+  /// The level of the modules without their own, the one of the log filter when not set.
+  pub default: Option<String>,
+  pub targets: BTreeMap<String, String>,
+}
+
+impl From<&SystemConfig> for LogLevels {
+  fn from(config: &SystemConfig) -> Self {
+    Self {
+      default: config.log_level.clone(),
+      targets: config.log_levels.clone(),
+    }
+  }
+}
+
+/// The [SystemConfig] of a running dispatcher. The requests read the current one when they
+/// start, so a reconfiguration applies to the next requests.
+pub struct LiveConfig {
+  current: ArcSwap<SystemConfig>,
+  /// Held while a delta is applied, so each one applies to the result of the previous one.
+  update: Mutex<()>,
+  observers: RwLock<Vec<Arc<dyn ConfigObserver>>>,
+}
+
+impl Default for LiveConfig {
+  fn default() -> Self {
+    Self {
+      current: ArcSwap::from_pointee(SystemConfig::default()),
+      update: Mutex::new(()),
+      observers: RwLock::new(vec![]),
+    }
+  }
+}
+
+impl LiveConfig {
+  pub fn get(&self) -> Arc<SystemConfig> {
+    self.current.load_full()
+  }
+
+  pub fn slow_handler_threshold(&self) -> Duration {
+    self.current.load().slow_handler_threshold()
+  }
+
+  pub fn request_timeout(&self) -> Option<Duration> {
+    self.current.load().request_timeout()
+  }
+
+  pub fn observe<O: ConfigObserver>(&self, observer: O) {
+    self.observers.write().unwrap().push(Arc::new(observer));
+  }
+
+  /// Updates the config while the dispatcher is being built, the observers are not told.
+  pub(crate) fn modify<F: FnOnce(&mut SystemConfig)>(&self, f: F) {
+    let _update = self.update.lock().unwrap();
+    let mut config = SystemConfig::clone(&self.current.load());
+    f(&mut config);
+    self.current.store(Arc::new(config));
+  }
+
+  /// Applies `delta`, a JSON merge patch (RFC 7386) of the config: the settings it sets are
+  /// replaced, the ones it sets to `null` go back to their default, the others are kept. The
+  /// result is validated as a whole and applied at once, or not at all. The settings that need
+  /// a restart, like `worker_threads`, can't be changed.
+  ///
+  /// ```json
+  /// {"log_level": "trace", "max_pending": {"database": 16, "search": null}}
+  /// ```
+  pub fn apply(&self, delta: &str) -> Result<ConfigChange, SystemConfigError> {
+    match serde_json::from_str::<Value>(delta) {
+      Ok(delta @ Value::Object(_)) => self.apply_value(delta),
+      Ok(_) => Err(SystemConfigError::new("expected a JSON object".to_owned())),
+      Err(err) => Err(SystemConfigError::new(err.to_string())),
+    }
+  }
+
+  /// Changes the `log_level`, or the level of one target in the `log_levels`, like
+  /// [LiveConfig::apply].
+  pub fn set_log_level(&self, change: &SetLogLevel) -> Result<ConfigChange, SystemConfigError> {
+    self.apply_value(change.to_delta())
+  }
+
+  fn apply_value(&self, delta: Value) -> Result<ConfigChange, SystemConfigError> {
+    let _update = self.update.lock().unwrap();
+    let previous = SystemConfig::clone(&self.current.load());
+    let previous_value = to_value(&previous)?;
+    let mut value = previous_value.clone();
+    merge_patch(&mut value, delta);
+    let current: SystemConfig = serde_json::from_value(value.clone())
+      .map_err(|err| SystemConfigError::new(err.to_string()))?;
+    current.validate()?;
+
+    let changed = changed_settings(&previous_value, &value);
+    let errors = changed
+      .iter()
+      .filter(|setting| RESTART_SETTINGS.contains(&setting.as_str()))
+      .map(|setting| format!("{}: can't be changed without a restart", setting))
+      .collect::<Vec<_>>();
+    if !errors.is_empty() {
+      return Err(SystemConfigError { errors });
+    }
+
+    self.current.store(Arc::new(current.clone()));
+    let change = ConfigChange {
+      changed,
+      previous,
+      current,
+    };
+    if !change.changed.is_empty() {
+      tracing::info!("[Dispatch]: reconfigured {}", change.changed.join(", "));
+      let observers = self.observers.read().unwrap().clone();
+      for observer in observers {
+        observer.on_config_changed(&change);
+      }
+    }
+    Ok(change)
+  }
+}
+
+/// Keeps the `max_pending` quotas of the plugins in line with the config. The other parts of
+/// their quotas are kept.
+pub(crate) fn update_max_pending(accounting: &ResourceAccounting, change: &ConfigChange) {
+  let (previous, current) = (&change.previous.max_pending, &change.current.max_pending);
+  for plugin in previous.keys().chain(current.keys()) {
+    if previous.get(plugin) != current.get(plugin) {
+      let mut quota = accounting.quota(plugin);
+      quota.max_pending = current.get(plugin).copied();
+      accounting.set_quota(plugin, quota);
+    }
+  }
+}
+
+fn to_value(config: &SystemConfig) -> Result<Value, SystemConfigError> {
+  serde_json::to_value(config).map_err(|err| SystemConfigError::new(err.to_string()))
+}
+
+fn merge_patch(target: &mut Value, patch: Value) {
+  let patch = match patch {
+    Value::Object(patch) => patch,
+    patch => {
+      *target = patch;
+      return;
+    },
+  };
+  if !target.is_object() {
+    *target = Value::Object(Default::default());
+  }
+  let target = target.as_object_mut().unwrap();
+  for (key, value) in patch {
+    if value.is_null() {
+      target.remove(&key);
+    } else {
+      merge_patch(target.entry(key).or_insert(Value::Null), value);
+    }
+  }
+}
+
+/// The top level settings that differ, sorted by name. The removed settings are compared with
+/// their default, which they went back to.
+fn changed_settings(previous: &Value, current: &Value) -> Vec<String> {
+  let defaults = to_value(&SystemConfig::default()).unwrap_or_default();
+  let setting = |value: &Value, key: &str| {
+    value
+      .get(key)
+      .or_else(|| defaults.get(key))
+      .cloned()
+      .unwrap_or(Value::Null)
+  };
+  let mut changed = defaults
+    .as_object()
+    .map(|defaults| {
+      defaults
+        .keys()
+        .filter(|key| setting(previous, key) != setting(current, key))
+        .cloned()
+        .collect::<Vec<_>>()
+    })
+    .unwrap_or_default();
+  changed.sort();
+  changed
+}
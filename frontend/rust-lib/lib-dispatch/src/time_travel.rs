@@ -0,0 +1,197 @@
+//! Steps through the history of the app, for the debugging tools.
+//!
+//! The history is a journal of the requests, recorded with a [FixtureRecorder] while the app
+//! runs, and the snapshot of the states taken when the recording started, see
+//! [AFPluginDispatcher::snapshot_states]. A [TimeTravel] puts the states back to the snapshot and
+//! replays the requests one by one, so the states can be inspected after each of them, see
+//! [StateSnapshot::inspect].
+//!
+//! Going back in time restores the closest checkpoint before the target and replays the requests
+//! from there. Only the states registered with [AFPlugin::snapshot_state] are rewound: the
+//! handlers writing to a database or to the disk should be pointed at a scratch copy first.
+//!
+//! ```ignore
+//! let origin = dispatcher.snapshot_states();
+//! recorder.start();
+//! // ... the app runs ...
+//! let mut time_travel = TimeTravel::new(&dispatcher, origin, recorder.take_fixture());
+//! time_travel.run_to_event("CreateView").await?;
+//! let states = time_travel.inspect();
+//! time_travel.step_back().await?;
+//! ```
+//!
+//! [FixtureRecorder]: crate::fixture::FixtureRecorder
+//! [StateSnapshot::inspect]: crate::module::StateSnapshot::inspect
+//! [AFPlugin::snapshot_state]: crate::module::AFPlugin::snapshot_state
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::dispatcher::AFPluginDispatcher;
+use crate::errors::{DispatchError, InternalError};
+use crate::fixture::{Fixture, FixtureEvent, FixtureResponse};
+use crate::module::StatesSnapshot;
+
+/// The number of requests between two checkpoints of the states.
+pub const DEFAULT_CHECKPOINT_INTERVAL: usize = 16;
+
+/// A request of the journal replayed by a [TimeTravel].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TimeTravelStep {
+  /// The index of the request in the journal.
+  pub index: usize,
+  pub event: String,
+  /// The response recorded in the journal.
+  pub recorded: FixtureResponse,
+  /// The response of the replay.
+  pub replayed: FixtureResponse,
+}
+
+impl TimeTravelStep {
+  /// Whether the replay took another path than the recording, e.g. because a handler depends
+  /// on a state that is not rewound.
+  pub fn is_divergent(&self) -> bool {
+    self.recorded != self.replayed
+  }
+}
+
+/// A debugger over a journal of requests, see the [module](self) docs. The position is the
+/// number of requests of the journal applied to the states, from 0 to [TimeTravel::len].
+pub struct TimeTravel<'a> {
+  dispatcher: &'a AFPluginDispatcher,
+  journal: Vec<FixtureEvent>,
+  position: usize,
+  checkpoint_interval: usize,
+  /// The states after the number of requests of the key. The one at 0 is the origin.
+  checkpoints: BTreeMap<usize, StatesSnapshot>,
+}
+
+impl<'a> TimeTravel<'a> {
+  /// Puts the states of `dispatcher` back to `origin`, before the first request of `journal`.
+  pub fn new(dispatcher: &'a AFPluginDispatcher, origin: StatesSnapshot, journal: Fixture) -> Self {
+    dispatcher.restore_states(&origin);
+    Self {
+      dispatcher,
+      journal: journal.events,
+      position: 0,
+      checkpoint_interval: DEFAULT_CHECKPOINT_INTERVAL,
+      checkpoints: BTreeMap::from([(0, origin)]),
+    }
+  }
+
+  /// Saves the states every `interval` requests instead of [DEFAULT_CHECKPOINT_INTERVAL]. The
+  /// shorter it is, the faster going back is and the more memory the checkpoints take.
+  pub fn with_checkpoint_interval(mut self, interval: usize) -> Self {
+    self.checkpoint_interval = interval.max(1);
+    self
+  }
+
+  pub fn position(&self) -> usize {
+    self.position
+  }
+
+  /// The number of requests in the journal.
+  pub fn len(&self) -> usize {
+    self.journal.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.journal.is_empty()
+  }
+
+  pub fn is_at_end(&self) -> bool {
+    self.position == self.journal.len()
+  }
+
+  pub fn journal(&self) -> &[FixtureEvent] {
+    &self.journal
+  }
+
+  /// The request applied by the next [TimeTravel::step], `None` at the end of the journal.
+  pub fn next_event(&self) -> Option<&FixtureEvent> {
+    self.journal.get(self.position)
+  }
+
+  /// The states as they are at the current position, see [AFPluginDispatcher::inspect_states].
+  pub fn inspect(&self) -> BTreeMap<String, Value> {
+    self.dispatcher.inspect_states()
+  }
+
+  /// Replays the next request. Returns `None` at the end of the journal.
+  pub async fn step(&mut self) -> Option<TimeTravelStep> {
+    let event = self.journal.get(self.position)?;
+    let response = AFPluginDispatcher::async_send(self.dispatcher, event.request()).await;
+    let step = TimeTravelStep {
+      index: self.position,
+      event: event.event.clone(),
+      recorded: event.response.clone(),
+      replayed: FixtureResponse::from(&response),
+    };
+    if step.is_divergent() {
+      tracing::warn!(
+        event = step.event.as_str(),
+        "[time travel]: the response of #{} changed",
+        step.index
+      );
+    }
+    self.position += 1;
+    if self.position % self.checkpoint_interval == 0 {
+      self
+        .checkpoints
+        .insert(self.position, self.dispatcher.snapshot_states());
+    }
+    Some(step)
+  }
+
+  /// Goes back one request, by replaying the journal from the closest checkpoint.
+  pub async fn step_back(&mut self) -> Result<Vec<TimeTravelStep>, DispatchError> {
+    match self.position.checked_sub(1) {
+      Some(position) => self.run_to(position).await,
+      None => Err(InternalError::Other("Already at the start of the journal".to_owned()).into()),
+    }
+  }
+
+  /// Moves to `position`, backward or forward. Returns the requests replayed on the way.
+  pub async fn run_to(&mut self, position: usize) -> Result<Vec<TimeTravelStep>, DispatchError> {
+    if position > self.journal.len() {
+      let msg = format!(
+        "The journal has {} requests, can't go to {}",
+        self.journal.len(),
+        position
+      );
+      return Err(InternalError::Other(msg).into());
+    }
+    if position < self.position {
+      let (checkpoint, states) = self
+        .checkpoints
+        .range(..=position)
+        .next_back()
+        .expect("The origin is always checkpointed");
+      self.dispatcher.restore_states(states);
+      self.position = *checkpoint;
+    }
+    let mut steps = vec![];
+    while self.position < position {
+      steps.extend(self.step().await);
+    }
+    Ok(steps)
+  }
+
+  /// Replays the requests up to and including the next one whose event is `event`. Returns the
+  /// requests replayed on the way, and stays where it was if there is no such request.
+  pub async fn run_to_event(&mut self, event: &str) -> Result<Vec<TimeTravelStep>, DispatchError> {
+    let index = self.journal[self.position..]
+      .iter()
+      .position(|recorded| recorded.event == event)
+      .ok_or_else(|| {
+        InternalError::Other(format!("No {} request after #{}", event, self.position))
+      })?;
+    self.run_to(self.position + index + 1).await
+  }
+
+  /// Goes back to the start of the journal.
+  pub async fn rewind(&mut self) -> Result<(), DispatchError> {
+    self.run_to(0).await.map(|_| ())
+  }
+}
@@ -0,0 +1,245 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::errors::DispatchError;
+use crate::module::ObservableState;
+use crate::request::{AFPluginEventRequest, FromAFPluginRequest, Payload};
+use crate::util::ready::{ready, Ready};
+
+type TransactionCallback = Box<dyn FnOnce() + Send>;
+
+type PrepareCallback = Box<dyn FnOnce() -> Result<(), DispatchError> + Send>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionStatus {
+  Active,
+  /// All the prepare callbacks succeeded, the transaction is about to commit.
+  Prepared,
+  Committed,
+  RolledBack,
+}
+
+/// A resource whose updates can be made part of a [Transaction], like a cache or a state cell.
+/// [TransactionParticipant::begin] is called when the resource joins the transaction, it
+/// registers the callbacks that apply or revert the updates made from then on.
+pub trait TransactionParticipant {
+  fn begin(&self, transaction: &Transaction);
+}
+
+/// Groups the updates a handler makes to several resources, e.g. a sqlite write and the cache
+/// that mirrors it, so they either all apply or all revert.
+///
+/// The resources register their callbacks with [Transaction::on_commit] and
+/// [Transaction::on_rollback], or join with [Transaction::enlist]. The commit callbacks run in
+/// the order they were registered, the rollback callbacks in the reverse order. A transaction
+/// that is dropped while still active is rolled back.
+///
+/// The commit is done in two phases: the callbacks registered with [Transaction::on_prepare]
+/// check first that the updates can apply, any failure rolls the transaction back instead.
+///
+/// A handler gets the transaction of its request with the [Transaction] extractor. The dispatcher
+/// commits it when the handler returns an Ok response and rolls it back otherwise. The requests
+/// sent with [dispatch_transaction] share one transaction, committed once all of them succeeded.
+///
+/// [dispatch_transaction]: crate::prelude::AFPluginDispatcher::dispatch_transaction
+#[derive(Clone)]
+pub struct Transaction {
+  inner: Arc<TransactionInner>,
+}
+
+struct TransactionInner {
+  id: u64,
+  /// Finished by the dispatcher once all the requests of the transaction are handled, instead
+  /// of after each request.
+  coordinated: bool,
+  state: Mutex<TransactionState>,
+}
+
+struct TransactionState {
+  status: TransactionStatus,
+  prepares: Vec<PrepareCallback>,
+  commits: Vec<TransactionCallback>,
+  rollbacks: Vec<TransactionCallback>,
+}
+
+impl Transaction {
+  pub fn begin() -> Self {
+    Self::new(false)
+  }
+
+  /// A transaction spanning several requests, see [Transaction::is_coordinated].
+  pub(crate) fn coordinated() -> Self {
+    Self::new(true)
+  }
+
+  fn new(coordinated: bool) -> Self {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+    Self {
+      inner: Arc::new(TransactionInner {
+        id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        coordinated,
+        state: Mutex::new(TransactionState {
+          status: TransactionStatus::Active,
+          prepares: vec![],
+          commits: vec![],
+          rollbacks: vec![],
+        }),
+      }),
+    }
+  }
+
+  pub fn id(&self) -> u64 {
+    self.inner.id
+  }
+
+  pub fn status(&self) -> TransactionStatus {
+    self.inner.state.lock().unwrap().status
+  }
+
+  /// Whether the transaction spans several requests. The dispatcher then finishes it once all
+  /// of them are handled.
+  pub(crate) fn is_coordinated(&self) -> bool {
+    self.inner.coordinated
+  }
+
+  /// Registers a check run by [Transaction::prepare], before any commit callback. Returning an
+  /// error vetoes the commit.
+  pub fn on_prepare<F>(&self, f: F)
+  where
+    F: FnOnce() -> Result<(), DispatchError> + Send + 'static,
+  {
+    self.push(|state| state.prepares.push(Box::new(f)));
+  }
+
+  pub fn on_commit<F: FnOnce() + Send + 'static>(&self, f: F) {
+    self.push(|state| state.commits.push(Box::new(f)));
+  }
+
+  pub fn on_rollback<F: FnOnce() + Send + 'static>(&self, f: F) {
+    self.push(|state| state.rollbacks.push(Box::new(f)));
+  }
+
+  /// Makes `participant` part of the transaction.
+  pub fn enlist<P: TransactionParticipant + ?Sized>(&self, participant: &P) {
+    participant.begin(self);
+  }
+
+  /// The first phase of the commit: runs the prepare callbacks in the order they were registered
+  /// and stops at the first error, after which the transaction must be rolled back.
+  pub fn prepare(&self) -> Result<(), DispatchError> {
+    let prepares = {
+      let mut state = self.inner.state.lock().unwrap();
+      if state.status != TransactionStatus::Active {
+        return Ok(());
+      }
+      std::mem::take(&mut state.prepares)
+    };
+    // Like the other callbacks, they run outside of the lock.
+    for prepare in prepares {
+      prepare()?;
+    }
+    let mut state = self.inner.state.lock().unwrap();
+    if state.status == TransactionStatus::Active {
+      state.status = TransactionStatus::Prepared;
+    }
+    Ok(())
+  }
+
+  /// Runs the commit callbacks. Does nothing if the transaction is already finished.
+  pub fn commit(&self) {
+    self.inner.finish(TransactionStatus::Committed);
+  }
+
+  /// Runs the rollback callbacks. Does nothing if the transaction is already finished.
+  pub fn rollback(&self) {
+    self.inner.finish(TransactionStatus::RolledBack);
+  }
+
+  fn push<F: FnOnce(&mut TransactionState)>(&self, f: F) {
+    let mut state = self.inner.state.lock().unwrap();
+    if state.status != TransactionStatus::Active {
+      tracing::warn!(
+        "Register a callback on the finished transaction {}: {:?}",
+        self.inner.id,
+        state.status
+      );
+      return;
+    }
+    f(&mut state);
+  }
+}
+
+impl TransactionInner {
+  fn finish(&self, status: TransactionStatus) {
+    let callbacks = {
+      let mut state = self.state.lock().unwrap();
+      if matches!(
+        state.status,
+        TransactionStatus::Committed | TransactionStatus::RolledBack
+      ) {
+        return;
+      }
+      state.status = status;
+      state.prepares.clear();
+      let commits = std::mem::take(&mut state.commits);
+      let rollbacks = std::mem::take(&mut state.rollbacks);
+      match status {
+        TransactionStatus::Committed => commits,
+        _ => rollbacks.into_iter().rev().collect(),
+      }
+    };
+    tracing::trace!("[transaction]: {} {:?}", self.id, status);
+    // The callbacks run outside of the lock, they may read the transaction.
+    for callback in callbacks {
+      callback();
+    }
+  }
+}
+
+impl Drop for TransactionInner {
+  fn drop(&mut self) {
+    self.finish(TransactionStatus::RolledBack);
+  }
+}
+
+impl Debug for Transaction {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("Transaction")
+      .field("id", &self.id())
+      .field("status", &self.status())
+      .finish()
+  }
+}
+
+/// Restores the previous value when the transaction is rolled back.
+impl<T> TransactionParticipant for ObservableState<T>
+where
+  T: Clone + Send + Sync + 'static,
+{
+  fn begin(&self, transaction: &Transaction) {
+    let state = self.clone();
+    let saved = self.get();
+    transaction.on_rollback(move || state.set(saved.as_ref().clone()));
+  }
+}
+
+impl FromAFPluginRequest for Transaction {
+  type Error = DispatchError;
+  type Future = Ready<Result<Self, DispatchError>>;
+
+  #[inline]
+  fn from_request(req: &AFPluginEventRequest, _: &mut Payload) -> Self::Future {
+    // The extractors of the same request share the same transaction.
+    let extensions = req.extensions();
+    let transaction = match extensions.get::<Transaction>() {
+      Some(transaction) => transaction,
+      None => {
+        let transaction = Transaction::begin();
+        extensions.insert(transaction.clone());
+        transaction
+      },
+    };
+    ready(Ok(transaction))
+  }
+}
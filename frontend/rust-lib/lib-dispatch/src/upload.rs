@@ -0,0 +1,260 @@
+//! Lets a client send a large payload, like a file to import, in chunks instead of one buffer.
+//!
+//! 1. The client opens an upload session with the [SysEvent::UploadOpen] event, whose payload is
+//!    the id of the request that will consume the upload.
+//! 2. It sends that request, the handler extracts a [PayloadStream] instead of its payload.
+//! 3. It sends the chunks with the [SysEvent::UploadChunk] event, see [encode_chunk]. The session
+//!    buffers a few chunks only, the response to a chunk is delayed until the handler made room
+//!    for it, so the client waits for it before sending the next one.
+//! 4. It closes the session with the [SysEvent::UploadClose] event, which ends the stream. The
+//!    handler returns the response of the request sent in step 2.
+//!
+//! [SysEvent::UploadOpen]: crate::system::SysEvent::UploadOpen
+//! [SysEvent::UploadChunk]: crate::system::SysEvent::UploadChunk
+//! [SysEvent::UploadClose]: crate::system::SysEvent::UploadClose
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use bytes::{Bytes, BytesMut};
+use futures_core::Stream;
+use tokio::sync::mpsc;
+
+use crate::errors::{DispatchError, InternalError};
+use crate::module::AppData;
+use crate::request::{unexpected_none_payload, AFPluginEventRequest, FromAFPluginRequest, Payload};
+use crate::util::ready::{ready, Ready};
+
+/// The number of chunks a session buffers before the next chunk waits for the handler.
+pub const DEFAULT_UPLOAD_WINDOW: usize = 4;
+
+/// Separates the id of the request from the bytes in the payload of a chunk.
+const CHUNK_SEPARATOR: u8 = b'\n';
+
+/// The upload sessions that are open, by the id of the request that consumes them.
+pub struct UploadSessions {
+  window: usize,
+  sessions: Mutex<HashMap<String, UploadSession>>,
+}
+
+struct UploadSession {
+  /// Dropped when the session is closed, which ends the stream.
+  sender: Option<mpsc::Sender<Bytes>>,
+  /// Taken by the [PayloadStream] of the request.
+  receiver: Option<mpsc::Receiver<Bytes>>,
+}
+
+impl Default for UploadSessions {
+  fn default() -> Self {
+    Self::new(DEFAULT_UPLOAD_WINDOW)
+  }
+}
+
+impl UploadSessions {
+  pub fn new(window: usize) -> Self {
+    Self {
+      window: window.max(1),
+      sessions: Mutex::new(HashMap::new()),
+    }
+  }
+
+  /// Opens the session consumed by the request `request_id`.
+  pub fn open(&self, request_id: &str) -> Result<(), DispatchError> {
+    let mut sessions = self.sessions.lock().unwrap();
+    if sessions.contains_key(request_id) {
+      let msg = format!("The upload {} is already open", request_id);
+      return Err(InternalError::Other(msg).into());
+    }
+    let (sender, receiver) = mpsc::channel(self.window);
+    sessions.insert(
+      request_id.to_owned(),
+      UploadSession {
+        sender: Some(sender),
+        receiver: Some(receiver),
+      },
+    );
+    Ok(())
+  }
+
+  /// Hands `chunk` to the handler. Waits while the session buffers as many chunks as its window.
+  /// Fails if the session is closed or the handler dropped its stream.
+  pub async fn send_chunk(&self, request_id: &str, chunk: Bytes) -> Result<(), DispatchError> {
+    let sender = self
+      .sessions
+      .lock()
+      .unwrap()
+      .get(request_id)
+      .and_then(|session| session.sender.clone());
+    let sender = sender.ok_or_else(|| upload_closed(request_id))?;
+    sender
+      .send(chunk)
+      .await
+      .map_err(|_| upload_closed(request_id))
+  }
+
+  /// Ends the stream of the session once the chunks it buffers are read. Returns false if the
+  /// session is not open.
+  pub fn close(&self, request_id: &str) -> bool {
+    let mut sessions = self.sessions.lock().unwrap();
+    match sessions.get_mut(request_id) {
+      Some(session) if session.sender.is_some() => {
+        session.sender = None;
+        if session.receiver.is_none() {
+          sessions.remove(request_id);
+        }
+        true
+      },
+      _ => false,
+    }
+  }
+
+  /// The number of open sessions.
+  pub fn len(&self) -> usize {
+    self.sessions.lock().unwrap().len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  fn take_receiver(&self, request_id: &str) -> Option<mpsc::Receiver<Bytes>> {
+    let mut sessions = self.sessions.lock().unwrap();
+    let session = sessions.get_mut(request_id)?;
+    let receiver = session.receiver.take()?;
+    if session.sender.is_none() {
+      sessions.remove(request_id);
+    }
+    Some(receiver)
+  }
+
+  fn remove(&self, request_id: &str) {
+    self.sessions.lock().unwrap().remove(request_id);
+  }
+}
+
+fn upload_closed(request_id: &str) -> DispatchError {
+  InternalError::Other(format!("The upload {} is closed", request_id)).into()
+}
+
+/// The payload of a [SysEvent::UploadChunk](crate::system::SysEvent::UploadChunk) event.
+pub fn encode_chunk(request_id: &str, chunk: &[u8]) -> Vec<u8> {
+  let mut payload = Vec::with_capacity(request_id.len() + chunk.len() + 1);
+  payload.extend_from_slice(request_id.as_bytes());
+  payload.push(CHUNK_SEPARATOR);
+  payload.extend_from_slice(chunk);
+  payload
+}
+
+/// The payload built by [encode_chunk], split into the id of the request and the chunk.
+#[derive(Debug, Clone)]
+pub struct UploadChunk {
+  pub request_id: String,
+  pub chunk: Bytes,
+}
+
+impl UploadChunk {
+  pub fn decode(payload: &Bytes) -> Result<Self, DispatchError> {
+    let separator = payload
+      .iter()
+      .position(|byte| *byte == CHUNK_SEPARATOR)
+      .ok_or_else(|| {
+        InternalError::DeserializeFromBytes("The chunk has no request id".to_owned())
+      })?;
+    let request_id = std::str::from_utf8(&payload[..separator])
+      .map_err(|e| InternalError::DeserializeFromBytes(e.to_string()))?;
+    Ok(Self {
+      request_id: request_id.to_owned(),
+      chunk: payload.slice(separator + 1..),
+    })
+  }
+}
+
+impl FromAFPluginRequest for UploadChunk {
+  type Error = DispatchError;
+  type Future = Ready<Result<Self, DispatchError>>;
+
+  fn from_request(req: &AFPluginEventRequest, payload: &mut Payload) -> Self::Future {
+    match payload {
+      Payload::None => ready(Err(unexpected_none_payload(req))),
+      Payload::Bytes(bytes) => ready(UploadChunk::decode(bytes)),
+    }
+  }
+}
+
+/// The chunks of the upload of the request being handled, see the [module](self) docs. Dropping
+/// it closes the session, the chunks sent after that fail.
+///
+/// ```ignore
+/// pub(crate) async fn import_csv_handler(
+///   mut upload: PayloadStream,
+///   manager: AFPluginState<Weak<DatabaseManager>>,
+/// ) -> Result<(), FlowyError> {
+///   let mut importer = manager.upgrade()?.csv_importer();
+///   while let Some(chunk) = upload.next_chunk().await {
+///     importer.feed(&chunk)?;
+///   }
+///   importer.finish().await
+/// }
+/// ```
+pub struct PayloadStream {
+  request_id: String,
+  receiver: mpsc::Receiver<Bytes>,
+  sessions: Arc<UploadSessions>,
+}
+
+impl PayloadStream {
+  /// The next chunk, or `None` once the session is closed and its chunks are read.
+  pub async fn next_chunk(&mut self) -> Option<Bytes> {
+    self.receiver.recv().await
+  }
+
+  /// Reads all the chunks into one buffer, for the handlers that need the whole payload.
+  pub async fn read_to_end(mut self) -> Bytes {
+    let mut payload = BytesMut::new();
+    while let Some(chunk) = self.next_chunk().await {
+      payload.extend_from_slice(&chunk);
+    }
+    payload.freeze()
+  }
+}
+
+impl Stream for PayloadStream {
+  type Item = Bytes;
+
+  fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    self.receiver.poll_recv(cx)
+  }
+}
+
+impl Drop for PayloadStream {
+  fn drop(&mut self) {
+    self.sessions.remove(&self.request_id);
+  }
+}
+
+impl FromAFPluginRequest for PayloadStream {
+  type Error = DispatchError;
+  type Future = Ready<Result<Self, DispatchError>>;
+
+  fn from_request(req: &AFPluginEventRequest, _: &mut Payload) -> Self::Future {
+    let sessions = match req.get_app_data::<AppData<UploadSessions>>() {
+      Some(sessions) => Arc::clone(&sessions),
+      None => {
+        let msg = "The dispatcher has no upload sessions".to_owned();
+        return ready(Err(InternalError::Other(msg).into()));
+      },
+    };
+    match sessions.take_receiver(req.id()) {
+      Some(receiver) => ready(Ok(PayloadStream {
+        request_id: req.id().to_owned(),
+        receiver,
+        sessions,
+      })),
+      None => {
+        let msg = format!("No upload is open for the request {}", req.id());
+        ready(Err(InternalError::Other(msg).into()))
+      },
+    }
+  }
+}
@@ -0,0 +1,153 @@
+//! Lets the long handlers, like the ones looping over the rows of a large grid, share their
+//! worker with the other events. A handler spends a unit of budget per step with
+//! [consume_budget], or iterates with [for_each], [map] or [stream] which do it for every item,
+//! and gives the worker back to the other tasks once the budget is spent.
+//!
+//! ```ignore
+//! async fn count_cells(rows: AFPluginData<RepeatedRowPB>) -> String {
+//!   let mut cells = 0;
+//!   budget::for_each(rows.into_inner().items, |row| cells += row.cells.len()).await;
+//!   cells.to_string()
+//! }
+//! ```
+use std::cell::Cell;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use pin_project::pin_project;
+
+/// The number of units a task spends before yielding. The dispatcher refills it every time it
+/// polls a handler.
+pub const DEFAULT_YIELD_BUDGET: u32 = 128;
+
+thread_local! {
+  static BUDGET: Cell<u32> = Cell::new(DEFAULT_YIELD_BUDGET);
+}
+
+/// Spends one unit of the budget of the current task, or returns false if it's spent.
+fn spend() -> bool {
+  BUDGET.with(|budget| match budget.get() {
+    0 => false,
+    left => {
+      budget.set(left - 1);
+      true
+    },
+  })
+}
+
+fn refill() {
+  BUDGET.with(|budget| budget.set(DEFAULT_YIELD_BUDGET));
+}
+
+/// Yields once to the executor, which runs the other ready tasks before resuming the caller.
+/// Unlike `tokio::task::yield_now`, it works on any [Executor](crate::executor::Executor).
+pub fn yield_now() -> YieldNow {
+  YieldNow { yielded: false }
+}
+
+pub struct YieldNow {
+  yielded: bool,
+}
+
+impl Future for YieldNow {
+  type Output = ();
+
+  fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+    if self.yielded {
+      return Poll::Ready(());
+    }
+    self.yielded = true;
+    cx.waker().wake_by_ref();
+    Poll::Pending
+  }
+}
+
+/// Spends one unit of the budget, and yields if it's spent.
+pub async fn consume_budget() {
+  if !spend() {
+    yield_now().await;
+    refill();
+    spend();
+  }
+}
+
+/// Runs `f` on every item, yielding whenever the budget is spent.
+pub async fn for_each<I, F>(iter: I, mut f: F)
+where
+  I: IntoIterator,
+  F: FnMut(I::Item),
+{
+  for item in iter {
+    consume_budget().await;
+    f(item);
+  }
+}
+
+/// Collects `f` of every item, yielding whenever the budget is spent.
+pub async fn map<I, F, T>(iter: I, mut f: F) -> Vec<T>
+where
+  I: IntoIterator,
+  F: FnMut(I::Item) -> T,
+{
+  let iter = iter.into_iter();
+  let mut items = Vec::with_capacity(iter.size_hint().0);
+  for item in iter {
+    consume_budget().await;
+    items.push(f(item));
+  }
+  items
+}
+
+/// The items of `iter` as a [Stream] that yields whenever the budget is spent.
+pub fn stream<I: IntoIterator>(iter: I) -> BudgetedStream<I::IntoIter> {
+  BudgetedStream {
+    iter: iter.into_iter(),
+  }
+}
+
+pub struct BudgetedStream<I> {
+  iter: I,
+}
+
+impl<I: Iterator + Unpin> Stream for BudgetedStream<I> {
+  type Item = I::Item;
+
+  fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<I::Item>> {
+    if !spend() {
+      refill();
+      cx.waker().wake_by_ref();
+      return Poll::Pending;
+    }
+    Poll::Ready(self.iter.next())
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    self.iter.size_hint()
+  }
+}
+
+/// Polls `future` with a full budget every time, the budget of the caller is kept aside.
+#[pin_project]
+pub(crate) struct Budgeted<F> {
+  #[pin]
+  future: F,
+}
+
+impl<F> Budgeted<F> {
+  pub(crate) fn new(future: F) -> Self {
+    Self { future }
+  }
+}
+
+impl<F: Future> Future for Budgeted<F> {
+  type Output = F::Output;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+    let caller = BUDGET.with(|budget| budget.replace(DEFAULT_YIELD_BUDGET));
+    let poll = self.project().future.poll(cx);
+    BUDGET.with(|budget| budget.set(caller));
+    poll
+  }
+}
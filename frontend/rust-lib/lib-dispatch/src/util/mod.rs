@@ -1 +1,3 @@
+pub mod budget;
+pub mod pool;
 pub mod ready;
@@ -0,0 +1,130 @@
+use std::fmt::{Debug, Formatter};
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+
+/// How many idle objects a pool keeps by default. The objects returned while the pool is full
+/// are freed.
+pub const DEFAULT_POOL_CAPACITY: usize = 64;
+
+/// A slab of boxed objects recycled once they are dropped. The objects allocated for every
+/// request reuse the allocations of the requests that completed before them instead of going
+/// through the allocator, which matters when the events are dispatched on every keystroke.
+///
+/// The clones share the same slab.
+pub struct ObjectPool<T> {
+  slots: Arc<Mutex<Vec<Box<Option<T>>>>>,
+  capacity: usize,
+}
+
+impl<T> Clone for ObjectPool<T> {
+  fn clone(&self) -> Self {
+    Self {
+      slots: self.slots.clone(),
+      capacity: self.capacity,
+    }
+  }
+}
+
+impl<T> Default for ObjectPool<T> {
+  fn default() -> Self {
+    Self::new(DEFAULT_POOL_CAPACITY)
+  }
+}
+
+impl<T> Debug for ObjectPool<T> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("ObjectPool")
+      .field("idle", &self.idle())
+      .field("capacity", &self.capacity)
+      .finish()
+  }
+}
+
+impl<T> ObjectPool<T> {
+  pub fn new(capacity: usize) -> Self {
+    Self {
+      slots: Arc::new(Mutex::new(Vec::with_capacity(capacity))),
+      capacity,
+    }
+  }
+
+  /// Moves `value` into an idle allocation of the pool, or into a new one if there is none.
+  pub fn take(&self, value: T) -> Pooled<T> {
+    let slot = match self.slots.lock().unwrap().pop() {
+      Some(mut slot) => {
+        *slot = Some(value);
+        slot
+      },
+      None => Box::new(Some(value)),
+    };
+    Pooled {
+      slot: Some(slot),
+      pool: self.clone(),
+    }
+  }
+
+  /// The number of allocations waiting to be reused.
+  pub fn idle(&self) -> usize {
+    self.slots.lock().unwrap().len()
+  }
+
+  pub fn capacity(&self) -> usize {
+    self.capacity
+  }
+
+  /// Frees the idle allocations, e.g. when the app runs low on memory.
+  pub fn clear(&self) {
+    self.slots.lock().unwrap().clear();
+  }
+
+  fn recycle(&self, mut slot: Box<Option<T>>) {
+    // The value is dropped right away, only its allocation is kept.
+    *slot = None;
+    let mut slots = self.slots.lock().unwrap();
+    if slots.len() < self.capacity {
+      slots.push(slot);
+    }
+  }
+}
+
+/// An object taken from an [ObjectPool]. Its allocation goes back to the pool when it's dropped.
+pub struct Pooled<T> {
+  slot: Option<Box<Option<T>>>,
+  pool: ObjectPool<T>,
+}
+
+impl<T> Deref for Pooled<T> {
+  type Target = T;
+
+  fn deref(&self) -> &T {
+    self
+      .slot
+      .as_ref()
+      .and_then(|slot| slot.as_ref().as_ref())
+      .expect("the pooled value is only taken on drop")
+  }
+}
+
+impl<T> DerefMut for Pooled<T> {
+  fn deref_mut(&mut self) -> &mut T {
+    self
+      .slot
+      .as_mut()
+      .and_then(|slot| slot.as_mut().as_mut())
+      .expect("the pooled value is only taken on drop")
+  }
+}
+
+impl<T: Debug> Debug for Pooled<T> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    self.deref().fmt(f)
+  }
+}
+
+impl<T> Drop for Pooled<T> {
+  fn drop(&mut self) {
+    if let Some(slot) = self.slot.take() {
+      self.pool.recycle(slot);
+    }
+  }
+}
@@ -0,0 +1,70 @@
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+async fn get_view(view_id: String) -> String {
+  format!("view {}", view_id)
+}
+
+fn deprecation(resp: &AFPluginEventResponse) -> Option<&str> {
+  resp.metadata.get(DEPRECATION_METADATA).map(String::as_str)
+}
+
+#[tokio::test]
+async fn event_alias_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new()
+      .name("folder")
+      .event("get_view_v2", get_view)
+      .alias("get_view", "get_view_v2")
+      .alias_until("read_view", "get_view_v2", 3)],
+  ));
+  let local_set = LocalSet::new();
+  let send = |request: AFPluginRequest| {
+    local_set.run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+  };
+
+  let resp = send(AFPluginRequest::new("get_view_v2").payload("1")).await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  assert_eq!(deprecation(&resp), None);
+
+  // The former name reaches the same handler, with a deprecation notice.
+  let resp = send(AFPluginRequest::new("get_view").payload("1")).await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  assert_eq!(String::from_utf8_lossy(resp.payload.as_ref()), "view 1");
+  assert_eq!(
+    deprecation(&resp),
+    Some("get_view is deprecated, use get_view_v2")
+  );
+
+  // The aliases scheduled for removal only serve the older clients.
+  let resp = send(AFPluginRequest::new("read_view").payload("1").version(2)).await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  assert_eq!(
+    deprecation(&resp),
+    Some("read_view is deprecated and removed in version 3, use get_view_v2")
+  );
+  let resp = send(AFPluginRequest::new("read_view").payload("1")).await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  let resp = send(AFPluginRequest::new("read_view").payload("1").version(3)).await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+  assert_eq!(
+    EventRemoved::from_response(&resp).unwrap(),
+    EventRemoved {
+      event: "read_view".to_string(),
+      replacement: "get_view_v2".to_string(),
+      removed_in: 3,
+      version: 3,
+    }
+  );
+
+  // An alias can't be registered as an event by another plugin.
+  let result = dispatch.register_plugin(AFPlugin::new().name("legacy").event("get_view", get_view));
+  assert!(result.is_err());
+
+  std::mem::forget(dispatch);
+}
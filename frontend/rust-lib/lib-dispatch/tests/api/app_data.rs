@@ -0,0 +1,81 @@
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+struct DeviceInfo {
+  name: String,
+}
+
+struct UserState {
+  name: String,
+}
+
+async fn device_name(device: AppData<DeviceInfo>) -> String {
+  device.name.clone()
+}
+
+async fn greeting(device: AppData<DeviceInfo>, user: AFPluginState<UserState>) -> String {
+  format!("{} on {}", user.name, device.name)
+}
+
+async fn missing_data(_: AppData<String>) -> String {
+  "unreachable".to_string()
+}
+
+fn payload_str(response: &AFPluginEventResponse) -> String {
+  String::from_utf8_lossy(response.payload.as_ref()).into_owned()
+}
+
+#[tokio::test]
+async fn app_data_is_shared_by_all_plugins_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(
+      runtime,
+      vec![
+        AFPlugin::new()
+          .name("device")
+          .event("device_name", device_name)
+          .event("missing_data", missing_data),
+        AFPlugin::new()
+          .name("user")
+          .state(UserState {
+            name: "nathan".to_string(),
+          })
+          .event("greeting", greeting),
+      ],
+    )
+    .data(DeviceInfo {
+      name: "macbook".to_string(),
+    }),
+  );
+  let local_set = LocalSet::new();
+
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new("device_name"),
+    ))
+    .await;
+  assert_eq!(payload_str(&resp), "macbook");
+
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new("greeting"),
+    ))
+    .await;
+  assert_eq!(payload_str(&resp), "nathan on macbook");
+
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new("missing_data"),
+    ))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+
+  std::mem::forget(dispatch);
+}
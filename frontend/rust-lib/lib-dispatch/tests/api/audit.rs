@@ -0,0 +1,127 @@
+use lib_dispatch::audit::{
+  audit_plugin, AuditEvent, AuditMiddleware, AuditQuery, AuditRecord, AuditStore,
+  BufferedAuditStore, FileAuditStore, AUDIT_CAPABILITY,
+};
+use lib_dispatch::capability::{Capabilities, PermissionDenied};
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+async fn create_doc(name: String) -> String {
+  name
+}
+
+async fn get_doc() -> String {
+  "doc".to_string()
+}
+
+#[tokio::test]
+async fn audit_mutating_events_test() {
+  let dir = std::env::temp_dir().join(nanoid::nanoid!(6));
+  let store: Arc<dyn AuditStore> = Arc::new(FileAuditStore::new(&dir).unwrap());
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  let plugin = AFPlugin::new()
+    .event("CreateDocument", create_doc)
+    .event("ApplyAction", create_doc)
+    .event("CreateDraft", create_doc)
+    .event("GetDocument", get_doc)
+    .mutating("CreateDocument")
+    .mutating("ApplyAction");
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(runtime, vec![plugin, audit_plugin(store.clone())])
+      .with_middleware(AuditMiddleware::new(store.clone()).with_identity(|| Some("1".to_string()))),
+  );
+  let local_set = LocalSet::new();
+  for request in [
+    AFPluginRequest::new("CreateDocument").payload("my doc"),
+    AFPluginRequest::new("ApplyAction").payload("insert"),
+    AFPluginRequest::new("CreateDraft").payload("draft"),
+    AFPluginRequest::new("GetDocument"),
+  ] {
+    local_set
+      .run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+      .await;
+  }
+
+  // Only the events declared as mutating are recorded, whatever their name
+  let records = store.query(&AuditQuery::default()).unwrap();
+  assert_eq!(records.len(), 2);
+  assert_eq!(records[0].event, "CreateDocument");
+  assert_eq!(records[0].user.as_deref(), Some("1"));
+  assert_eq!(records[0].payload_size, 6);
+  assert_eq!(records[1].event, "ApplyAction");
+
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new(AuditEvent::Query).payload(r#"{"event":"CreateDocument"}"#),
+    ))
+    .await;
+  let json: serde_json::Value = serde_json::from_slice(resp.payload.as_ref()).unwrap();
+  assert_eq!(json.as_array().unwrap().len(), 1);
+
+  // The callers outside of the app need the capability to read the log
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new(AuditEvent::Query)
+        .payload("{}")
+        .capabilities(Capabilities::new()),
+    ))
+    .await;
+  assert_eq!(
+    PermissionDenied::from_response(&resp).unwrap().missing,
+    vec![AUDIT_CAPABILITY]
+  );
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new(AuditEvent::Query)
+        .payload("{}")
+        .capabilities(Capabilities::new().grant(AUDIT_CAPABILITY)),
+    ))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+
+  std::mem::forget(dispatch);
+  let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn buffered_audit_store_test() {
+  let dir = std::env::temp_dir().join(nanoid::nanoid!(6));
+  let store = BufferedAuditStore::new(FileAuditStore::new(&dir).unwrap()).unwrap();
+  let record = |event: &str| AuditRecord {
+    timestamp: 0,
+    user: None,
+    event: event.to_string(),
+    request_id: nanoid::nanoid!(6),
+    payload_size: 0,
+    success: true,
+  };
+  for event in ["CreateView", "DeleteView", "MoveView"] {
+    store.append(record(event)).unwrap();
+  }
+
+  // The query waits for the pending records, which are written in order
+  let events = store
+    .query(&AuditQuery::default())
+    .unwrap()
+    .into_iter()
+    .map(|record| record.event)
+    .collect::<Vec<_>>();
+  assert_eq!(events, vec!["CreateView", "DeleteView", "MoveView"]);
+
+  // Dropping the store writes the pending records
+  store.append(record("UpdateView")).unwrap();
+  drop(store);
+  let records = FileAuditStore::new(&dir)
+    .unwrap()
+    .query(&AuditQuery::default())
+    .unwrap();
+  assert_eq!(records.len(), 4);
+
+  let _ = std::fs::remove_dir_all(dir);
+}
@@ -0,0 +1,91 @@
+use lib_dispatch::capability::{Capabilities, PermissionDenied};
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+struct TestSessions;
+
+impl SessionStore for TestSessions {
+  fn verify(&self, token: &str) -> Option<Identity> {
+    match token {
+      "nathan-token" => Some(Identity {
+        user_id: "nathan".to_string(),
+        capabilities: None,
+      }),
+      "guest-token" => Some(Identity {
+        user_id: "guest".to_string(),
+        capabilities: Some(Capabilities::new()),
+      }),
+      _ => None,
+    }
+  }
+}
+
+async fn whoami(identity: Extension<Identity>) -> String {
+  identity.user_id.clone()
+}
+
+async fn version() -> String {
+  "1.0".to_string()
+}
+
+async fn sign_in() -> String {
+  "signed in".to_string()
+}
+
+fn remote(event: &str) -> AFPluginRequest {
+  AFPluginRequest::new(event).metadata(TRANSPORT_METADATA, "http")
+}
+
+#[tokio::test]
+async fn auth_middleware_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(
+      runtime,
+      vec![AFPlugin::new()
+        .event("whoami", whoami)
+        .event("sign_in", sign_in)
+        .event("version", version)
+        .requires_capability("whoami", "user.read")],
+    )
+    .with_middleware(AuthMiddleware::new(Arc::new(TestSessions)).allow_anonymous("sign_in")),
+  );
+  let local_set = LocalSet::new();
+  let send = |request: AFPluginRequest| {
+    local_set.run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+  };
+
+  // The requests of the app itself aren't authenticated
+  let resp = send(AFPluginRequest::new("version")).await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+
+  let resp = send(remote("version")).await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+  assert_eq!(
+    Unauthorized::from_response(&resp),
+    Some(Unauthorized {
+      event: "version".to_string(),
+      reason: "missing token".to_string(),
+    })
+  );
+  let resp = send(remote("whoami").metadata(AUTHORIZATION_METADATA, "Bearer unknown")).await;
+  assert_eq!(
+    Unauthorized::from_response(&resp).unwrap().reason,
+    "invalid token"
+  );
+
+  let resp = send(remote("whoami").metadata(AUTHORIZATION_METADATA, "Bearer nathan-token")).await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  assert_eq!(String::from_utf8_lossy(resp.payload.as_ref()), "nathan");
+
+  // The capabilities of the identity apply to its requests
+  let resp = send(remote("whoami").metadata(AUTHORIZATION_METADATA, "guest-token")).await;
+  assert!(PermissionDenied::from_response(&resp).is_some());
+
+  let resp = send(remote("sign_in")).await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  std::mem::forget(dispatch);
+}
@@ -0,0 +1,111 @@
+use lib_dispatch::bridge::serve_json_rpc;
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+async fn hello(name: String) -> String {
+  format!("hello {}", name)
+}
+
+async fn add(numbers: Json<Vec<i64>>) -> Json<i64> {
+  Json(numbers.iter().sum())
+}
+
+#[tokio::test]
+async fn json_rpc_bridge_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event("hello", hello).event("add", add)],
+  ));
+  let input = [
+    r#"{"jsonrpc":"2.0","id":1,"method":"hello","params":"world"}"#,
+    r#"{"jsonrpc":"2.0","id":2,"method":"add","params":[1,2,3]}"#,
+    r#"{"jsonrpc":"2.0","method":"hello","params":"nobody"}"#,
+    r#"{"jsonrpc":"2.0","id":3,"method":"missing"}"#,
+    r#"not json"#,
+  ]
+  .join("\n");
+  let mut output = vec![];
+  LocalSet::new()
+    .run_until(serve_json_rpc(
+      dispatch.as_ref(),
+      input.as_bytes(),
+      &mut output,
+    ))
+    .await
+    .unwrap();
+
+  let responses = String::from_utf8(output)
+    .unwrap()
+    .lines()
+    .map(|line| serde_json::from_str::<Value>(line).unwrap())
+    .collect::<Vec<_>>();
+  // The notification gets no response.
+  assert_eq!(responses.len(), 4);
+  assert_eq!(responses[0]["id"], 1);
+  assert_eq!(responses[0]["result"], "hello world");
+  assert_eq!(responses[1]["result"], 6);
+  assert_eq!(responses[2]["id"], 3);
+  assert_eq!(responses[2]["error"]["code"], -32601);
+  assert_eq!(responses[3]["error"]["code"], -32700);
+
+  std::mem::forget(dispatch);
+}
+
+struct TestSessions;
+
+impl SessionStore for TestSessions {
+  fn verify(&self, token: &str) -> Option<Identity> {
+    (token == "nathan-token").then(|| Identity {
+      user_id: "nathan".to_string(),
+      capabilities: None,
+    })
+  }
+}
+
+#[tokio::test]
+async fn json_rpc_bridge_auth_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(runtime, vec![AFPlugin::new().event("hello", hello)])
+      .with_middleware(AuthMiddleware::new(Arc::new(TestSessions))),
+  );
+  let input = [
+    r#"{"jsonrpc":"2.0","id":1,"method":"hello","params":"world"}"#,
+    r#"{"jsonrpc":"2.0","id":2,"method":"hello","params":"world","authorization":"unknown"}"#,
+    r#"{"jsonrpc":"2.0","id":3,"method":"hello","params":"world","authorization":"nathan-token"}"#,
+  ]
+  .join("\n");
+  let mut output = vec![];
+  LocalSet::new()
+    .run_until(serve_json_rpc(
+      dispatch.as_ref(),
+      input.as_bytes(),
+      &mut output,
+    ))
+    .await
+    .unwrap();
+
+  let responses = String::from_utf8(output)
+    .unwrap()
+    .lines()
+    .map(|line| serde_json::from_str::<Value>(line).unwrap())
+    .collect::<Vec<_>>();
+  assert_eq!(responses[0]["error"]["code"], -32000);
+  let reason = |response: &Value| {
+    let message = response["error"]["message"].as_str().unwrap();
+    serde_json::from_str::<Unauthorized>(message)
+      .unwrap()
+      .reason
+  };
+  assert_eq!(reason(&responses[0]), "missing token");
+  assert_eq!(reason(&responses[1]), "invalid token");
+  assert_eq!(responses[2]["result"], "hello world");
+
+  std::mem::forget(dispatch);
+}
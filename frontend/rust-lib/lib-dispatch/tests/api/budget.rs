@@ -0,0 +1,86 @@
+use futures::task::noop_waker;
+use futures_util::StreamExt;
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use lib_dispatch::util::budget::{self, DEFAULT_YIELD_BUDGET};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::task::Context;
+use tokio::task::LocalSet;
+
+const ROWS: usize = 10_000;
+
+/// The number of times `future` returns `Pending` before it's done.
+fn count_yields<F: Future>(future: F) -> usize {
+  let waker = noop_waker();
+  let mut cx = Context::from_waker(&waker);
+  let mut future = Box::pin(future);
+  let mut yields = 0;
+  while future.as_mut().poll(&mut cx).is_pending() {
+    yields += 1;
+  }
+  yields
+}
+
+#[test]
+fn budget_yield_test() {
+  let expected = (ROWS - 1) / DEFAULT_YIELD_BUDGET as usize;
+  assert_eq!(count_yields(budget::for_each(0..ROWS, |_| {})), expected);
+
+  let mut doubled = vec![];
+  let yields = count_yields(async {
+    doubled = budget::map(0..ROWS, |row| row * 2).await;
+  });
+  assert_eq!(yields, expected);
+  assert_eq!(doubled[ROWS - 1], (ROWS - 1) * 2);
+
+  let yields = count_yields(async {
+    let rows = budget::stream(0..ROWS).collect::<Vec<_>>().await;
+    assert_eq!(rows.len(), ROWS);
+  });
+  assert_eq!(yields, expected);
+}
+
+#[derive(Clone, Default)]
+struct Finished(Arc<Mutex<Vec<&'static str>>>);
+
+async fn sum_rows(finished: AFPluginState<Finished>) -> String {
+  let mut sum = 0;
+  budget::for_each(0..ROWS, |row| sum += row).await;
+  finished.0.lock().unwrap().push("sum_rows");
+  sum.to_string()
+}
+
+async fn hello(finished: AFPluginState<Finished>) -> String {
+  finished.0.lock().unwrap().push("hello");
+  "hello".to_string()
+}
+
+#[tokio::test]
+async fn long_handler_interleaves_test() {
+  let finished = Finished::default();
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new()
+      .state(finished.clone())
+      .event("sum_rows", sum_rows)
+      .event("hello", hello)],
+  ));
+  let local_set = LocalSet::new();
+
+  let (sum, _) = local_set
+    .run_until(async {
+      futures::join!(
+        AFPluginDispatcher::async_send(dispatch.as_ref(), AFPluginRequest::new("sum_rows")),
+        AFPluginDispatcher::async_send(dispatch.as_ref(), AFPluginRequest::new("hello")),
+      )
+    })
+    .await;
+  let expected = (0..ROWS).sum::<usize>().to_string();
+  assert_eq!(String::from_utf8_lossy(sum.payload.as_ref()), expected);
+  // The long handler gave the worker to the short one.
+  assert_eq!(*finished.0.lock().unwrap(), vec!["hello", "sum_rows"]);
+  std::mem::forget(dispatch);
+}
@@ -0,0 +1,66 @@
+use lib_dispatch::clock::MockClock;
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::LocalSet;
+
+async fn count(counter: AFPluginState<Arc<AtomicUsize>>) -> String {
+  (counter.fetch_add(1, Ordering::SeqCst) + 1).to_string()
+}
+
+async fn send(
+  local_set: &LocalSet,
+  dispatch: &AFPluginDispatcher,
+  event: &str,
+  payload: &str,
+) -> String {
+  let request = AFPluginRequest::new(event).payload(payload);
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(dispatch, request))
+    .await;
+  String::from_utf8_lossy(resp.payload.as_ref()).into_owned()
+}
+
+#[tokio::test]
+async fn response_cache_test() {
+  let clock = MockClock::new();
+  let cache = ResponseCache::new()
+    .cache("cached", Duration::from_secs(10))
+    .with_clock(clock.clone());
+  let invalidator = cache.invalidator();
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(
+      runtime,
+      vec![AFPlugin::new()
+        .state(Arc::new(AtomicUsize::new(0)))
+        .event("cached", count)
+        .event("uncached", count)],
+    )
+    .with_middleware(cache),
+  );
+  let local_set = LocalSet::new();
+
+  assert_eq!(send(&local_set, &dispatch, "cached", "a").await, "1");
+  assert_eq!(send(&local_set, &dispatch, "cached", "a").await, "1");
+  // The requests with another payload are cached separately.
+  assert_eq!(send(&local_set, &dispatch, "cached", "b").await, "2");
+  assert_eq!(send(&local_set, &dispatch, "uncached", "a").await, "3");
+  assert_eq!(send(&local_set, &dispatch, "uncached", "a").await, "4");
+
+  clock.advance(Duration::from_secs(10));
+  assert_eq!(send(&local_set, &dispatch, "cached", "a").await, "5");
+  assert_eq!(send(&local_set, &dispatch, "cached", "a").await, "5");
+
+  invalidator.invalidate("cached");
+  assert_eq!(send(&local_set, &dispatch, "cached", "a").await, "6");
+  assert_eq!(send(&local_set, &dispatch, "cached", "b").await, "7");
+
+  invalidator.invalidate_all();
+  assert_eq!(send(&local_set, &dispatch, "cached", "b").await, "8");
+
+  std::mem::forget(dispatch);
+}
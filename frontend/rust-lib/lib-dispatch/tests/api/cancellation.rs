@@ -0,0 +1,175 @@
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use lib_dispatch::system::SysEvent;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::LocalSet;
+
+/// Runs until the request is cancelled.
+async fn export(cancellation: CancellationToken) -> Result<String, DispatchError> {
+  cancellation.cancelled().await;
+  Err(DispatchError::from("cancelled".to_string()))
+}
+
+fn request_with_id(event: &str, id: &str) -> AFPluginRequest {
+  let mut request = AFPluginRequest::new(event);
+  request.id = id.to_owned();
+  request
+}
+
+async fn wait_in_flight(dispatch: &AFPluginDispatcher) {
+  while dispatch.in_flight().is_empty() {
+    tokio::time::sleep(Duration::from_millis(10)).await;
+  }
+}
+
+#[tokio::test]
+async fn cancel_request_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event("export", export)],
+  ));
+  let local_set = LocalSet::new();
+
+  let (resp, cancelled) = local_set
+    .run_until(async {
+      tokio::join!(
+        AFPluginDispatcher::async_send(dispatch.as_ref(), request_with_id("export", "export_1")),
+        async {
+          wait_in_flight(dispatch.as_ref()).await;
+          // Only the requests with the same id are cancelled.
+          assert!(!dispatch.cancel("export_2"));
+          dispatch.cancel("export_1")
+        }
+      )
+    })
+    .await;
+  assert!(cancelled);
+  assert_eq!(resp.status_code, StatusCode::Err);
+  assert!(dispatch.in_flight().is_empty());
+  assert!(!dispatch.cancel("export_1"));
+
+  std::mem::forget(dispatch);
+}
+
+#[tokio::test]
+async fn cancel_event_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event("export", export)],
+  ));
+  let local_set = LocalSet::new();
+
+  let (resp, cancel_resp) = local_set
+    .run_until(async {
+      tokio::join!(
+        AFPluginDispatcher::async_send(dispatch.as_ref(), request_with_id("export", "export_1")),
+        async {
+          wait_in_flight(dispatch.as_ref()).await;
+          AFPluginDispatcher::async_send(
+            dispatch.as_ref(),
+            AFPluginRequest::new(SysEvent::Cancel).payload("export_1"),
+          )
+          .await
+        }
+      )
+    })
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+  assert_eq!(cancel_resp.status_code, StatusCode::Ok);
+  assert_eq!(cancel_resp.payload.as_ref(), b"true");
+
+  std::mem::forget(dispatch);
+}
+
+/// Runs until the request is cancelled, along with the tasks it spawned.
+async fn watch() -> Result<String, DispatchError> {
+  let spawned = af_spawn_cancellable(futures::future::pending::<()>());
+  let blocking = af_spawn_blocking(|cancellation| {
+    while !cancellation.is_cancelled() {
+      std::thread::sleep(Duration::from_millis(10));
+    }
+    "stopped"
+  });
+  assert_eq!(spawned.await.unwrap(), Err(Cancelled));
+  Ok(blocking.await.unwrap().to_string())
+}
+
+#[tokio::test]
+async fn cancel_parent_operation_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new()
+      .event("export", export)
+      .event("watch", watch)],
+  ));
+  let local_set = LocalSet::new();
+  let operation = CancellationToken::new();
+
+  let (export_resp, watch_resp, _) = local_set
+    .run_until(async {
+      tokio::join!(
+        // Sent from within the operation.
+        cancellation_scope(
+          operation.clone(),
+          AFPluginDispatcher::async_send(dispatch.as_ref(), AFPluginRequest::new("export")),
+        ),
+        AFPluginDispatcher::async_send(
+          dispatch.as_ref(),
+          AFPluginRequest::new("watch").parent_cancellation(operation.clone()),
+        ),
+        async {
+          while dispatch.in_flight().len() < 2 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+          }
+          operation.cancel();
+        }
+      )
+    })
+    .await;
+  assert_eq!(export_resp.status_code, StatusCode::Err);
+  assert_eq!(watch_resp.status_code, StatusCode::Ok);
+  assert_eq!(watch_resp.payload.as_ref(), b"stopped");
+  assert!(dispatch.in_flight().is_empty());
+
+  std::mem::forget(dispatch);
+}
+
+#[tokio::test]
+async fn cancel_child_request_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event("export", export)],
+  ));
+  let local_set = LocalSet::new();
+  let operation = CancellationToken::new();
+
+  let (resp, _) = local_set
+    .run_until(async {
+      tokio::join!(
+        AFPluginDispatcher::async_send(
+          dispatch.as_ref(),
+          request_with_id("export", "export_1").parent_cancellation(operation.clone()),
+        ),
+        async {
+          wait_in_flight(dispatch.as_ref()).await;
+          dispatch.cancel("export_1");
+        }
+      )
+    })
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+  // Cancelling a request leaves the operation it's part of running.
+  assert!(!operation.is_cancelled());
+  assert!(current_cancellation().is_none());
+
+  std::mem::forget(dispatch);
+}
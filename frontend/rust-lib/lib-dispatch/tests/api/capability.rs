@@ -0,0 +1,137 @@
+use lib_dispatch::bridge::serve_json_rpc;
+use lib_dispatch::capability::{Capabilities, PermissionDenied};
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use lib_dispatch::system::{SysEvent, CONFIGURE_CAPABILITY, DEBUG_CAPABILITY};
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+async fn delete_view(view_id: String) -> String {
+  format!("deleted {}", view_id)
+}
+
+async fn read_view() -> String {
+  "view".to_string()
+}
+
+#[tokio::test]
+async fn capability_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new()
+      .event("delete_view", delete_view)
+      .event("read_view", read_view)
+      .requires_capability("delete_view", "folder.read")
+      .requires_capability("delete_view", "folder.write")
+      .requires_capability("read_view", "folder.read")],
+  ));
+  let local_set = LocalSet::new();
+  let send = |request: AFPluginRequest| {
+    local_set.run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+  };
+
+  // The app itself may call every event
+  let resp = send(AFPluginRequest::new("delete_view").payload("1")).await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+
+  let reader = Capabilities::new().grant("folder.read");
+  let resp = send(AFPluginRequest::new("read_view").capabilities(reader.clone())).await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+
+  // The permission is checked before the payload is extracted
+  let resp = send(AFPluginRequest::new("delete_view").capabilities(reader)).await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+  assert_eq!(
+    PermissionDenied::from_response(&resp),
+    Some(PermissionDenied {
+      event: "delete_view".to_string(),
+      missing: vec!["folder.write".to_string()],
+    })
+  );
+
+  let resp = send(AFPluginRequest::new("read_view").capabilities(Capabilities::new())).await;
+  assert_eq!(
+    PermissionDenied::from_response(&resp).unwrap().missing,
+    vec!["folder.read"]
+  );
+
+  let writer = ["folder.read", "folder.write"]
+    .iter()
+    .collect::<Capabilities>();
+  let request = AFPluginRequest::builder("delete_view")
+    .payload("1")
+    .capabilities(writer)
+    .build();
+  let resp = send(request).await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+}
+
+#[tokio::test]
+async fn bridge_capability_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new()
+      .event("read_view", read_view)
+      .requires_capability("read_view", "folder.read")],
+  ));
+  let input = [
+    r#"{"jsonrpc":"2.0","id":1,"method":"read_view"}"#,
+    r#"{"jsonrpc":"2.0","id":2,"method":"SysDumpRecorder"}"#,
+    r#"{"jsonrpc":"2.0","id":3,"method":"SysInspect"}"#,
+    r#"{"jsonrpc":"2.0","id":4,"method":"SysTrace","params":"on"}"#,
+  ]
+  .join("\n");
+  let mut output = vec![];
+  let local_set = LocalSet::new();
+  local_set
+    .run_until(serve_json_rpc(
+      dispatch.as_ref(),
+      input.as_bytes(),
+      &mut output,
+    ))
+    .await
+    .unwrap();
+
+  // The requests of the bridges are granted no capability
+  let missing = String::from_utf8(output)
+    .unwrap()
+    .lines()
+    .map(|line| {
+      let response = serde_json::from_str::<serde_json::Value>(line).unwrap();
+      let message = response["error"]["message"].as_str().unwrap().to_owned();
+      serde_json::from_str::<PermissionDenied>(&message)
+        .unwrap()
+        .missing
+    })
+    .collect::<Vec<_>>();
+  assert_eq!(
+    missing,
+    vec![
+      vec!["folder.read"],
+      vec![DEBUG_CAPABILITY],
+      vec![DEBUG_CAPABILITY],
+      vec![DEBUG_CAPABILITY]
+    ]
+  );
+
+  let send = |request: AFPluginRequest| {
+    local_set.run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+  };
+  let resp =
+    send(AFPluginRequest::new(SysEvent::Reconfigure).capabilities(Capabilities::new())).await;
+  assert_eq!(
+    PermissionDenied::from_response(&resp).unwrap().missing,
+    vec![CONFIGURE_CAPABILITY]
+  );
+  let debugger = Capabilities::new().grant(DEBUG_CAPABILITY);
+  let resp =
+    send(AFPluginRequest::new(SysEvent::DumpRecorder).capabilities(debugger.clone())).await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  let resp = send(AFPluginRequest::new(SysEvent::Inspect).capabilities(debugger)).await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  std::mem::forget(dispatch);
+}
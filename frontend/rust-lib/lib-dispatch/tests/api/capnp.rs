@@ -0,0 +1,52 @@
+use capnp::message::Builder;
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+async fn greet(data: Capnp<capnp::text::Owned>) -> String {
+  let name = data.root().ok().and_then(|name| name.to_str().ok());
+  format!("hello {}", name.unwrap_or_default())
+}
+
+async fn echo(data: Capnp<capnp::text::Owned>) -> Capnp<capnp::text::Owned> {
+  data
+}
+
+fn text_message(text: &str) -> Vec<u8> {
+  let mut builder = Builder::new_default();
+  builder.set_root(text).unwrap();
+  capnp::serialize::write_message_to_words(&builder)
+}
+
+#[tokio::test]
+async fn capnp_payload_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event("greet", greet).event("echo", echo)],
+  ));
+  let local_set = LocalSet::new();
+  let request = AFPluginRequest::new("greet").payload(text_message("world"));
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  assert_eq!(resp.payload.as_ref(), b"hello world");
+
+  let request = AFPluginRequest::new("echo").payload(text_message("world"));
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+    .await;
+  let echoed = Capnp::<capnp::text::Owned>::from_bytes(resp.payload.to_vec().into()).unwrap();
+  assert_eq!(echoed.root().unwrap().to_str().unwrap(), "world");
+
+  let request = AFPluginRequest::new("greet").payload(vec![1u8, 2, 3]);
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+
+  std::mem::forget(dispatch);
+}
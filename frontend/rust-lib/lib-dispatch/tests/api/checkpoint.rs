@@ -0,0 +1,84 @@
+use lib_dispatch::checkpoint::{
+  Checkpoint, CheckpointMiddleware, CheckpointStore, FileCheckpointStore, CHECKPOINT_KEY_METADATA,
+};
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::{Arc, Mutex};
+use tokio::task::LocalSet;
+
+#[derive(Clone, Default)]
+struct Imported(Arc<Mutex<Vec<String>>>);
+
+/// Imports the rows of the payload one by one, the `crash` row kills the app the first time.
+async fn import_rows(
+  rows: String,
+  checkpoint: Extension<Checkpoint>,
+  imported: AppData<Imported>,
+) -> String {
+  let done = checkpoint
+    .progress()
+    .map(|progress| String::from_utf8_lossy(&progress).parse::<usize>().unwrap())
+    .unwrap_or_default();
+  for (index, row) in rows.split(',').enumerate().skip(done) {
+    if row == "crash" && !checkpoint.is_resumed() {
+      panic!("the app died halfway");
+    }
+    imported.0.lock().unwrap().push(row.to_string());
+    checkpoint.save((index + 1).to_string()).unwrap();
+  }
+  "imported".to_string()
+}
+
+fn make_dispatcher(checkpoints: &CheckpointMiddleware, imported: &Imported) -> AFPluginDispatcher {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  let plugin = AFPlugin::new().event("import_rows", import_rows);
+  checkpoints.register(AFPluginDispatcher::new(runtime, vec![plugin]).data(imported.clone()))
+}
+
+#[tokio::test]
+async fn checkpoint_resume_test() {
+  let dir = std::env::temp_dir().join(nanoid::nanoid!(6));
+  let store: Arc<dyn CheckpointStore> = Arc::new(FileCheckpointStore::new(&dir).unwrap());
+  let checkpoints = CheckpointMiddleware::new(store.clone()).checkpointed("import_rows");
+  let imported = Imported::default();
+  let dispatch = make_dispatcher(&checkpoints, &imported);
+  let local_set = LocalSet::new();
+
+  let request = AFPluginRequest::new("import_rows")
+    .payload("a,b")
+    .metadata(CHECKPOINT_KEY_METADATA, "import-1");
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(&dispatch, request))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  // The checkpoint of a completed import is removed
+  assert!(store.list().unwrap().is_empty());
+
+  let request = AFPluginRequest::new("import_rows")
+    .payload("c,d,crash,e")
+    .metadata(CHECKPOINT_KEY_METADATA, "import-2");
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(&dispatch, request))
+    .await;
+  assert_eq!(resp.error_origin, Some(ErrorOrigin::Internal));
+  let records = store.list().unwrap();
+  assert_eq!(records.len(), 1);
+  assert_eq!(records[0].key, "import-2");
+  assert_eq!(records[0].progress.as_ref(), b"2");
+
+  // After a restart, the import goes on from the last checkpoint
+  let dispatch = make_dispatcher(&checkpoints, &imported);
+  let responses = local_set
+    .run_until(checkpoints.resume(&dispatch))
+    .await
+    .unwrap();
+  assert_eq!(responses.len(), 1);
+  assert_eq!(responses[0].0, "import-2");
+  assert_eq!(responses[0].1.payload.as_ref(), b"imported");
+  assert_eq!(
+    *imported.0.lock().unwrap(),
+    vec!["a", "b", "c", "d", "crash", "e"]
+  );
+  assert!(store.list().unwrap().is_empty());
+  let _ = std::fs::remove_dir_all(dir);
+}
@@ -0,0 +1,74 @@
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use lib_dispatch::system::SysEvent;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::LocalSet;
+
+async fn hello() -> String {
+  "say hello".to_string()
+}
+
+#[tokio::test]
+async fn chrome_trace_event_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event("hello", hello)],
+  ));
+  let local_set = LocalSet::new();
+  let send = |request: AFPluginRequest| {
+    local_set.run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+  };
+
+  // Nothing is recorded until the trace is started.
+  send(AFPluginRequest::new("hello")).await;
+  assert!(dispatch.tracer().events().is_empty());
+
+  let resp = send(AFPluginRequest::new(SysEvent::Trace).payload("on")).await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  send(AFPluginRequest::new("hello")).await;
+  let resp = send(AFPluginRequest::new(SysEvent::Trace).payload("off")).await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+
+  let json: serde_json::Value = serde_json::from_slice(resp.payload.as_ref()).unwrap();
+  assert_eq!(json["displayTimeUnit"], "ms");
+  let hello_phases = json["traceEvents"]
+    .as_array()
+    .unwrap()
+    .iter()
+    .filter(|event| event["name"] == "hello")
+    .collect::<Vec<_>>();
+  for phase in ["queue_wait", "handler"] {
+    let event = hello_phases
+      .iter()
+      .find(|event| event["cat"] == phase)
+      .unwrap();
+    assert_eq!(event["ph"], "X");
+    assert!(event["args"]["request_id"].is_string());
+  }
+
+  // Once stopped, the requests are no longer recorded.
+  let recorded = dispatch.tracer().events().len();
+  send(AFPluginRequest::new("hello")).await;
+  assert_eq!(dispatch.tracer().events().len(), recorded);
+
+  let resp = send(AFPluginRequest::new(SysEvent::Trace).payload("maybe")).await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+  std::mem::forget(dispatch);
+}
+
+#[test]
+fn chrome_tracer_capacity_test() {
+  let tracer = ChromeTracer::new(1);
+  let event = AFPluginEvent::from("hello");
+  tracer.exit("1", &event, DispatchPhase::Handler, Duration::ZERO);
+  assert!(tracer.events().is_empty());
+
+  tracer.start();
+  tracer.exit("1", &event, DispatchPhase::Handler, Duration::ZERO);
+  tracer.exit("2", &event, DispatchPhase::Handler, Duration::ZERO);
+  assert_eq!(tracer.events().len(), 1);
+  assert_eq!(tracer.dropped(), 1);
+}
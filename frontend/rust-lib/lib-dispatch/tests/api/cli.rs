@@ -0,0 +1,150 @@
+#[cfg(feature = "use_protobuf")]
+use bytes::Bytes;
+use lib_dispatch::bridge::serve_unix_socket;
+use lib_dispatch::cli::{format_payload, CliError, EventClient};
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+#[cfg(feature = "use_protobuf")]
+use protobuf::ProtobufError;
+#[cfg(feature = "use_protobuf")]
+use std::convert::TryFrom;
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+async fn rename_view(name: String) -> String {
+  name
+}
+
+/// Named like the message it wraps, which is how the plugin finds the message of the handler.
+#[cfg(feature = "use_protobuf")]
+struct FieldDescriptorProto(protobuf::descriptor::FieldDescriptorProto);
+
+#[cfg(feature = "use_protobuf")]
+impl TryFrom<Bytes> for FieldDescriptorProto {
+  type Error = ProtobufError;
+
+  fn try_from(bytes: Bytes) -> Result<Self, Self::Error> {
+    protobuf::Message::parse_from_bytes(&bytes).map(FieldDescriptorProto)
+  }
+}
+
+#[cfg(feature = "use_protobuf")]
+async fn describe_field(data: AFPluginData<FieldDescriptorProto>) -> String {
+  let field = data.into_inner().0;
+  format!(
+    "{} {} {:?} {:?} {}",
+    field.get_name(),
+    field.get_number(),
+    field.get_label(),
+    field.get_field_type(),
+    field.get_options().get_deprecated()
+  )
+}
+
+#[tokio::test]
+async fn event_client_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new()
+      .name("folder")
+      .event("rename_view", rename_view)],
+  ));
+  let path = std::env::temp_dir().join(format!("flowy-cli-{}.sock", nanoid::nanoid!(6)));
+  let client = EventClient::new(&path);
+
+  let local_set = LocalSet::new();
+  local_set.spawn_local(serve_unix_socket(path.clone(), dispatch.clone()));
+  local_set
+    .run_until(async {
+      tokio::task::yield_now().await;
+      let events = client.events().await.unwrap();
+      let rename_view = events
+        .iter()
+        .find(|schema| schema.event == "rename_view")
+        .unwrap();
+      assert_eq!(rename_view.plugin, "folder");
+
+      let response = client
+        .send("rename_view", Some(r#"{ "name": "Untitled" }"#))
+        .await
+        .unwrap();
+      assert_eq!(response.status_code, StatusCode::Ok);
+      assert_eq!(
+        format_payload(&response.payload),
+        "{\n  \"name\": \"Untitled\"\n}"
+      );
+
+      let err = client.send("rename_view", Some("{ name")).await;
+      assert!(matches!(err, Err(CliError::InvalidPayload(_))));
+      let response = client.send("unknown", None).await.unwrap();
+      assert_eq!(response.status_code, StatusCode::Err);
+    })
+    .await;
+
+  let _ = std::fs::remove_file(&path);
+  assert!(matches!(client.events().await, Err(CliError::Transport(_))));
+  std::mem::forget(dispatch);
+}
+
+#[cfg(feature = "use_protobuf")]
+#[tokio::test]
+async fn event_client_protobuf_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new()
+      .name("descriptor")
+      .event("describe_field", describe_field)
+      .proto_descriptors(vec![protobuf::descriptor::file_descriptor_proto()])],
+  ));
+  let path = std::env::temp_dir().join(format!("flowy-cli-{}.sock", nanoid::nanoid!(6)));
+  let client = EventClient::new(&path);
+
+  let local_set = LocalSet::new();
+  local_set.spawn_local(serve_unix_socket(path.clone(), dispatch.clone()));
+  local_set
+    .run_until(async {
+      tokio::task::yield_now().await;
+      // A quoted integer, the enums by name and by number, and a nested message.
+      let response = client
+        .send(
+          "describe_field",
+          Some(
+            r#"{
+              "name": "title",
+              "number": "3",
+              "label": "LABEL_REPEATED",
+              "type": 9,
+              "options": { "deprecated": true }
+            }"#,
+          ),
+        )
+        .await
+        .unwrap();
+      assert_eq!(response.status_code, StatusCode::Ok);
+      assert_eq!(
+        format_payload(&response.payload),
+        "title 3 LABEL_REPEATED TYPE_STRING true"
+      );
+
+      let response = client
+        .send("describe_field", Some(r#"{ "title": "Untitled" }"#))
+        .await
+        .unwrap();
+      assert_eq!(response.status_code, StatusCode::Err);
+    })
+    .await;
+
+  let _ = std::fs::remove_file(&path);
+  std::mem::forget(dispatch);
+}
+
+#[test]
+fn format_payload_test() {
+  assert_eq!(format_payload(b"hello"), "hello");
+  assert_eq!(format_payload(b"[1,2]"), "[\n  1,\n  2\n]");
+  assert_eq!(format_payload(&[0xff, 0x01]), "ff 01");
+}
@@ -0,0 +1,95 @@
+use lib_dispatch::clock::{timeout, Clock, Elapsed, MockClock};
+use lib_dispatch::metrics::SLOW_HANDLER_TOTAL;
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::LocalSet;
+
+async fn slow_handler(clock: AFPluginState<MockClock>) -> String {
+  clock.advance(Duration::from_secs(2));
+  "done".to_string()
+}
+
+async fn fast_handler() -> String {
+  "done".to_string()
+}
+
+#[tokio::test]
+async fn mock_clock_slow_handler_test() {
+  let clock = MockClock::new();
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(
+      runtime,
+      vec![AFPlugin::new()
+        .state(clock.clone())
+        .event("slow", slow_handler)
+        .event("fast", fast_handler)],
+    )
+    .with_clock(clock.clone())
+    .with_slow_handler_threshold(Duration::from_secs(1)),
+  );
+  let local_set = LocalSet::new();
+  for event in ["slow", "fast"] {
+    local_set
+      .run_until(AFPluginDispatcher::async_send(
+        dispatch.as_ref(),
+        AFPluginRequest::new(event),
+      ))
+      .await;
+  }
+
+  let metrics = dispatch.metrics();
+  assert_eq!(
+    metrics
+      .counter(SLOW_HANDLER_TOTAL, &[("event", "slow")])
+      .get(),
+    1
+  );
+  assert_eq!(
+    metrics
+      .counter(SLOW_HANDLER_TOTAL, &[("event", "fast")])
+      .get(),
+    0
+  );
+  assert_eq!(clock.elapsed(), Duration::from_secs(2));
+  std::mem::forget(dispatch);
+}
+
+#[test]
+fn mock_clock_sleep_test() {
+  let clock = MockClock::new();
+  let start = clock.now();
+  let mut sleep = clock.sleep(Duration::from_millis(100));
+  assert_eq!(clock.pending_sleeps(), 1);
+  assert!(futures::executor::block_on(futures::future::poll_immediate(&mut sleep)).is_none());
+
+  clock.advance(Duration::from_millis(99));
+  assert!(futures::executor::block_on(futures::future::poll_immediate(&mut sleep)).is_none());
+
+  clock.advance(Duration::from_millis(1));
+  assert!(futures::executor::block_on(futures::future::poll_immediate(&mut sleep)).is_some());
+  assert_eq!(clock.pending_sleeps(), 0);
+  assert_eq!(clock.now() - start, Duration::from_millis(100));
+}
+
+#[test]
+fn mock_clock_timeout_test() {
+  let clock = MockClock::new();
+  let mut never = Box::pin(timeout(
+    &clock,
+    Duration::from_secs(5),
+    futures::future::pending::<()>(),
+  ));
+  assert!(futures::executor::block_on(futures::future::poll_immediate(&mut never)).is_none());
+  clock.advance(Duration::from_secs(5));
+  assert_eq!(
+    futures::executor::block_on(never),
+    Err(Elapsed(Duration::from_secs(5)))
+  );
+
+  let ready = futures::executor::block_on(timeout(&clock, Duration::from_secs(5), async { 1 }));
+  assert_eq!(ready, Ok(1));
+}
@@ -0,0 +1,73 @@
+use lib_dispatch::codegen::DartCodegen;
+use lib_dispatch::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+struct CreateViewPayload {
+  name: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct View {
+  id: String,
+}
+
+struct Workspace;
+
+async fn create_view(
+  payload: Json<CreateViewPayload>,
+  _workspace: AFPluginState<Workspace>,
+) -> Result<Json<View>, DispatchError> {
+  let _ = payload.into_inner().name;
+  Ok(Json(View {
+    id: "v1".to_string(),
+  }))
+}
+
+async fn list_views(_ids: Json<Vec<String>>) -> Json<Vec<Option<View>>> {
+  Json(vec![])
+}
+
+async fn close_views() {}
+
+#[test]
+fn dart_codegen_test() {
+  let plugins = vec![AFPlugin::new()
+    .name("flowy-folder")
+    .state(Workspace)
+    .event("CreateView", create_view)
+    .event("list_views", list_views)
+    .event("default", close_views)];
+  let dart = DartCodegen::from_plugins(&plugins)
+    .import("package:appflowy_backend/protobuf/view.pb.dart")
+    .external_model("View")
+    .generate();
+
+  assert!(dart.contains("import 'package:appflowy_backend/protobuf/view.pb.dart';"));
+  assert!(dart.contains("enum FlowyFolderEvent {"));
+  // The state of the handler is not part of the request, the error is not the response.
+  assert!(dart.contains("  /// `CreateViewPayload` -> `View`.\n  createView('CreateView'),"));
+  assert!(dart.contains("  /// `void` -> `void`.\n  default_('default'),"));
+  assert!(dart.contains("  /// `List<String>` -> `List<View?>`.\n  listViews('list_views');"));
+  assert!(dart.contains("  const FlowyFolderEvent(this.event);"));
+  assert!(dart.contains("class CreateViewPayload {"));
+  assert!(dart.contains("/// A stub of the Rust type `api::codegen::CreateViewPayload`."));
+  assert!(!dart.contains("class View {"));
+  assert!(!dart.contains("Workspace"));
+  assert_eq!(
+    dart,
+    DartCodegen::from_plugins(&plugins)
+      .import("package:appflowy_backend/protobuf/view.pb.dart")
+      .external_model("View")
+      .generate()
+  );
+
+  let path = std::env::temp_dir()
+    .join(format!("flowy-codegen-{}", nanoid::nanoid!(6)))
+    .join("events.dart");
+  let codegen = DartCodegen::from_plugins(&plugins);
+  assert!(codegen.write_to(&path).unwrap());
+  assert!(!codegen.write_to(&path).unwrap());
+  assert_eq!(std::fs::read_to_string(&path).unwrap(), codegen.generate());
+  let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
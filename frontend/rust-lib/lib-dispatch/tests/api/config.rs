@@ -0,0 +1,90 @@
+use lib_dispatch::config::{Config, ConfigSection, ConfigStore};
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+#[derive(Deserialize)]
+struct ServerConfig {
+  base_url: String,
+  #[serde(default)]
+  timeout_secs: Option<u64>,
+}
+
+impl ConfigSection for ServerConfig {
+  const SECTION: &'static str = "server";
+}
+
+#[derive(Deserialize)]
+struct StorageConfig {
+  #[allow(dead_code)]
+  document_dir: String,
+}
+
+impl ConfigSection for StorageConfig {
+  const SECTION: &'static str = "storage";
+}
+
+async fn server_url(config: Config<ServerConfig>) -> String {
+  format!("{} {:?}", config.base_url, config.timeout_secs)
+}
+
+async fn document_dir(_config: Config<StorageConfig>) -> String {
+  "unreachable".to_string()
+}
+
+#[tokio::test]
+async fn config_extractor_test() {
+  let store =
+    ConfigStore::from_json(r#"{"server": {"base_url": "https://beta.appflowy.cloud"}}"#).unwrap();
+  assert!(store.contains("server"));
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(
+      runtime,
+      vec![AFPlugin::new()
+        .event("server_url", server_url)
+        .event("document_dir", document_dir)],
+    )
+    .config(store),
+  );
+  let local_set = LocalSet::new();
+
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new("server_url"),
+    ))
+    .await;
+  assert_eq!(
+    String::from_utf8_lossy(resp.payload.as_ref()),
+    "https://beta.appflowy.cloud None"
+  );
+
+  // The storage section is missing and its field is required.
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new("document_dir"),
+    ))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+
+  std::mem::forget(dispatch);
+}
+
+#[test]
+fn config_store_section_test() {
+  let store = ConfigStore::new()
+    .with_section(
+      "server",
+      serde_json::json!({ "base_url": "http://localhost", "timeout_secs": 5 }),
+    )
+    .unwrap();
+  let server = store.section::<ServerConfig>().unwrap();
+  assert_eq!(server.base_url, "http://localhost");
+  assert_eq!(server.timeout_secs, Some(5));
+  assert!(store.section::<StorageConfig>().is_err());
+}
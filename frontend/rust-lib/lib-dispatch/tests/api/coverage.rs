@@ -0,0 +1,55 @@
+use lib_dispatch::coverage::EventCoverage;
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+async fn hello() -> String {
+  "hello".to_string()
+}
+
+#[tokio::test]
+async fn event_coverage_report_test() {
+  let coverage = Arc::new(EventCoverage::new());
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(
+      runtime,
+      vec![AFPlugin::new()
+        .name("greeting")
+        .event("hello", hello)
+        .event("mocked", hello)
+        .event("untested", hello)],
+    )
+    .with_mock("mocked", ResponseBuilder::Ok().data("canned").build())
+    .with_coverage(coverage.clone()),
+  );
+  let local_set = LocalSet::new();
+  for event in ["hello", "hello", "mocked"] {
+    local_set
+      .run_until(AFPluginDispatcher::async_send(
+        dispatch.as_ref(),
+        AFPluginRequest::new(event),
+      ))
+      .await;
+  }
+
+  let hello = coverage.get("hello").unwrap();
+  assert_eq!((hello.dispatched, hello.handled), (2, 2));
+  let mocked = coverage.get("mocked").unwrap();
+  assert_eq!((mocked.dispatched, mocked.handled), (1, 0));
+
+  let report = coverage.report();
+  assert_eq!(report.entries.len(), 3);
+  assert_eq!(report.never_dispatched, vec!["untested".to_string()]);
+  assert_eq!(
+    report.never_handled,
+    vec!["mocked".to_string(), "untested".to_string()]
+  );
+  let text = report.to_string();
+  assert!(text.contains("1/3 handled"));
+  assert!(text.contains("untested: dispatched 0, handled 0, never dispatched"));
+
+  std::mem::forget(dispatch);
+}
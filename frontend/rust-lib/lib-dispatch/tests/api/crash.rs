@@ -0,0 +1,114 @@
+use lib_dispatch::crash::{CrashReport, WORKER_THREAD_PREFIX};
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use lib_dispatch::system::{InitStatus, SysEvent};
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+async fn import_csv() -> String {
+  panic!("malformed row")
+}
+
+async fn hello() -> String {
+  "hello".to_string()
+}
+
+#[tokio::test]
+async fn handler_panic_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new()
+      .event("import_csv", import_csv)
+      .event("hello", hello)],
+  ));
+  let local_set = LocalSet::new();
+
+  for priority in [RequestPriority::Normal, RequestPriority::High] {
+    let request = AFPluginRequest::new("import_csv").priority(priority);
+    let resp = local_set
+      .run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+      .await;
+    assert_eq!(resp.status_code, StatusCode::Err);
+    assert_eq!(resp.error_origin, Some(ErrorOrigin::Internal));
+    assert!(String::from_utf8_lossy(resp.payload.as_ref()).starts_with("HandlerPanicked"));
+  }
+
+  let panics = dispatch.recorder().panics();
+  assert_eq!(panics.len(), 2);
+  assert_eq!(panics[0].event, "import_csv");
+  assert_eq!(panics[0].message, "malformed row");
+  assert!(!panics[0].backtrace.is_empty());
+  assert!(dispatch.in_flight().is_empty());
+
+  // The worker goes on with the next requests.
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new("hello"),
+    ))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  std::mem::forget(dispatch);
+}
+
+#[test]
+fn worker_thread_name_test() {
+  let runtime = AFPluginRuntime::new().unwrap();
+  let name = runtime
+    .block_on(runtime.spawn(async { std::thread::current().name().map(str::to_owned) }))
+    .unwrap()
+    .unwrap();
+  assert!(name.starts_with(&format!("{}-", WORKER_THREAD_PREFIX)));
+}
+
+struct Pool;
+
+#[tokio::test]
+async fn crash_report_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  let path = std::env::temp_dir().join(format!("flowy-crash-{}.json", nanoid::nanoid!(6)));
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(
+      runtime,
+      vec![AFPlugin::new()
+        .name("grid")
+        .state_lazy(|| async { Ok::<_, String>(Pool) })
+        .event("import_csv", import_csv)
+        .event("hello", hello)],
+    )
+    .with_crash_file(&path),
+  );
+  let local_set = LocalSet::new();
+  let send = |request: AFPluginRequest| {
+    local_set.run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+  };
+  send(AFPluginRequest::new("hello")).await;
+  // The dispatcher recovers from the panics of the handlers.
+  send(AFPluginRequest::new("import_csv")).await;
+  assert!(!path.exists());
+
+  std::thread::spawn(|| panic!("core aborted"))
+    .join()
+    .unwrap_err();
+  let resp = send(AFPluginRequest::new(SysEvent::CrashReport)).await;
+  assert!(!path.exists());
+  let report: CrashReport = serde_json::from_slice(resp.payload.as_ref()).unwrap();
+  assert_eq!(report.message, "core aborted");
+  assert!(report.location.unwrap().contains("crash.rs"));
+  assert_eq!(report.event, None);
+  assert!(report.records.iter().any(|record| record.event == "hello"));
+  assert_eq!(report.panics.len(), 1);
+  assert_eq!(report.panics[0].event, "import_csv");
+  assert!(report.in_flight.is_empty());
+  assert_eq!(report.modules.len(), 1);
+  assert_eq!(report.modules[0].plugin, "grid");
+  assert_eq!(report.modules[0].status, InitStatus::Pending);
+
+  // Taken once.
+  let resp = send(AFPluginRequest::new(SysEvent::CrashReport)).await;
+  assert_eq!(String::from_utf8_lossy(resp.payload.as_ref()), "null");
+  std::mem::forget(dispatch);
+}
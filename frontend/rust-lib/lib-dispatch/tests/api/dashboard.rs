@@ -0,0 +1,67 @@
+use lib_dispatch::clock::MockClock;
+use lib_dispatch::dashboard::Dashboard;
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::LocalSet;
+
+async fn open_view() {}
+
+async fn import_csv(clock: AFPluginState<MockClock>) {
+  clock.advance(Duration::from_secs(1));
+}
+
+async fn delete_view() -> Result<(), DispatchError> {
+  Err(DispatchError::from("view not found".to_string()))
+}
+
+#[tokio::test]
+async fn dashboard_sample_test() {
+  let clock = MockClock::new();
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(
+      runtime,
+      vec![AFPlugin::new()
+        .state(clock.clone())
+        .event("open_view", open_view)
+        .event("import_csv", import_csv)
+        .event("delete_view", delete_view)],
+    )
+    .with_clock(clock.clone()),
+  );
+  let mut dashboard = Dashboard::new(&dispatch).rows(2);
+
+  let local_set = LocalSet::new();
+  for event in ["open_view", "open_view", "import_csv", "delete_view"] {
+    local_set
+      .run_until(AFPluginDispatcher::async_send(
+        dispatch.as_ref(),
+        AFPluginRequest::new(event),
+      ))
+      .await;
+  }
+  clock.advance(Duration::from_secs(1));
+
+  let frame = dashboard.sample();
+  assert_eq!(frame.requests_total, 4);
+  assert_eq!(frame.errors_total, 1);
+  assert_eq!(frame.requests_per_second, 2.0);
+  assert_eq!(frame.in_flight_total, 0);
+  assert_eq!(frame.slowest.len(), 2);
+  assert_eq!(frame.slowest[0].event, "import_csv");
+  assert_eq!(frame.slowest[0].mean_ms, 1000.0);
+  assert_eq!(frame.recent_errors.len(), 1);
+  assert_eq!(frame.recent_errors[0].event, "delete_view");
+
+  let text = frame.to_string();
+  assert!(text.starts_with("2.0 req/s  total: 4  errors: 1  in flight: 0"));
+  assert!(text.contains("Recent errors"));
+
+  // The rate only counts the requests since the previous sample.
+  clock.advance(Duration::from_secs(1));
+  assert_eq!(dashboard.sample().requests_per_second, 0.0);
+  std::mem::forget(dispatch);
+}
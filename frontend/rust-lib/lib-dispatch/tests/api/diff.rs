@@ -0,0 +1,95 @@
+use lib_dispatch::diff::{payload_hash, BinaryPatch, DiffResponses, HeldPayload, PatchError};
+use lib_dispatch::diff::{DIFF_BASE_METADATA, PAYLOAD_HASH_METADATA};
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::{Arc, Mutex};
+use tokio::task::LocalSet;
+
+struct Snapshot(Mutex<Vec<u8>>);
+
+async fn get_snapshot(snapshot: AFPluginState<Arc<Snapshot>>) -> Vec<u8> {
+  snapshot.0.lock().unwrap().clone()
+}
+
+fn document(len: usize) -> Vec<u8> {
+  (0..len).map(|i| (i * 31 % 251) as u8).collect()
+}
+
+#[test]
+fn patch_round_trip_test() {
+  let base = document(64 * 1024);
+  let mut target = base.clone();
+  target[1000..1010].copy_from_slice(b"0123456789");
+  target.splice(20_000..20_000, b"inserted".iter().cloned());
+  target.drain(40_000..40_100);
+
+  let patch = BinaryPatch::diff(&base, &target);
+  let bytes = patch.to_bytes();
+  assert!(bytes.len() < 200, "patch of {} bytes", bytes.len());
+
+  let decoded = BinaryPatch::from_bytes(&bytes).unwrap();
+  assert_eq!(decoded, patch);
+  assert_eq!(decoded.apply(&base).unwrap(), target);
+  assert_eq!(decoded.apply(&target), Err(PatchError::BaseMismatch));
+  assert_eq!(
+    BinaryPatch::from_bytes(&bytes[..bytes.len() - 1]),
+    Err(PatchError::Malformed)
+  );
+}
+
+#[tokio::test]
+async fn diff_response_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  let snapshot = Arc::new(Snapshot(Mutex::new(document(16 * 1024))));
+  let dispatcher = AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new()
+      .state(snapshot.clone())
+      .event("get_snapshot", get_snapshot)],
+  )
+  .with_response_mapper(DiffResponses::new().event("get_snapshot"));
+  let local_set = LocalSet::new();
+  let mut held = HeldPayload::default();
+
+  // The first fetch carries the full payload.
+  let response = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      &dispatcher,
+      held.request("get_snapshot"),
+    ))
+    .await;
+  assert!(!response.metadata.contains_key(DIFF_BASE_METADATA));
+  let payload = held.update(&response).unwrap();
+  assert_eq!(payload.as_ref(), document(16 * 1024).as_slice());
+
+  // The next one is a patch against it.
+  snapshot.0.lock().unwrap()[500..505].copy_from_slice(b"edits");
+  let expected = snapshot.0.lock().unwrap().clone();
+  let response = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      &dispatcher,
+      held.request("get_snapshot"),
+    ))
+    .await;
+  assert!(response.metadata.contains_key(DIFF_BASE_METADATA));
+  assert!(response.payload.as_ref().len() < 1024);
+  assert_eq!(
+    response.metadata.get(PAYLOAD_HASH_METADATA),
+    Some(&payload_hash(&expected))
+  );
+  assert_eq!(
+    held.update(&response).unwrap().as_ref(),
+    expected.as_slice()
+  );
+  assert_eq!(held.payload().as_ref(), expected.as_slice());
+
+  // A base the dispatcher doesn't know falls back to the full payload.
+  let response = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      &dispatcher,
+      AFPluginRequest::new("get_snapshot").diff_base("0123456789abcdef"),
+    ))
+    .await;
+  assert!(!response.metadata.contains_key(DIFF_BASE_METADATA));
+  assert_eq!(response.payload.as_ref(), expected.as_slice());
+}
@@ -0,0 +1,180 @@
+use std::ffi::{c_char, CString};
+use std::sync::Arc;
+use std::time::Duration;
+
+use lib_dispatch::dylib::*;
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use tokio::task::LocalSet;
+
+unsafe extern "C" fn handle(
+  event: *const u8,
+  event_len: usize,
+  payload: *const u8,
+  payload_len: usize,
+  out: *mut PluginBuffer,
+) -> i32 {
+  handle_guarded(
+    event,
+    event_len,
+    payload,
+    payload_len,
+    out,
+    |event, payload| match event {
+      "word_count" => {
+        let count = String::from_utf8_lossy(payload).split_whitespace().count();
+        Ok(count.to_string().into_bytes())
+      },
+      "reject" => Err(b"rejected".to_vec()),
+      "slow" => {
+        std::thread::sleep(Duration::from_millis(300));
+        Ok(b"done".to_vec())
+      },
+      "repeat" => Ok(payload.repeat(10)),
+      _ => panic!("{} crashed", event),
+    },
+  )
+}
+
+fn leak_str(s: &str) -> *const c_char {
+  CString::new(s).unwrap().into_raw()
+}
+
+/// The descriptor a library would export, built in the process.
+fn descriptor(abi_version: u32, events: &[&str]) -> *const PluginDescriptor {
+  let events = events
+    .iter()
+    .map(|event| leak_str(event))
+    .collect::<Vec<_>>();
+  let vtable = Box::leak(Box::new(PluginVTable {
+    handle,
+    free_buffer: free_plugin_buffer,
+  }));
+  Box::leak(Box::new(PluginDescriptor {
+    abi_version,
+    name: leak_str("word-count"),
+    version: leak_str("1.0.0"),
+    num_events: events.len(),
+    events: Box::leak(events.into_boxed_slice()).as_ptr(),
+    vtable,
+  }))
+}
+
+#[tokio::test]
+async fn dylib_plugin_test() {
+  let err = unsafe { DylibPlugin::from_descriptor(descriptor(99, &["word_count"])) }.err();
+  assert!(matches!(
+    err,
+    Some(DylibPluginError::IncompatibleAbi { version: 99 })
+  ));
+  let err = unsafe { DylibPlugin::from_descriptor(descriptor(PLUGIN_ABI_VERSION, &["a", "a"])) };
+  assert!(matches!(err, Err(DylibPluginError::InvalidDescriptor(_))));
+  let err = unsafe { DylibPlugin::from_descriptor(descriptor(PLUGIN_ABI_VERSION, &["SysInfo"])) };
+  assert!(matches!(err, Err(DylibPluginError::InvalidDescriptor(_))));
+
+  let events = ["word_count", "reject", "crash"];
+  let plugin = unsafe { DylibPlugin::from_descriptor(descriptor(PLUGIN_ABI_VERSION, &events)) }
+    .unwrap()
+    .max_faults(2);
+  assert_eq!(plugin.name(), "word-count");
+  assert_eq!(plugin.events(), &events);
+
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(runtime, vec![]));
+  dispatch.register_plugin(plugin.into_plugin()).unwrap();
+  let local_set = LocalSet::new();
+  let send = |event: &'static str| {
+    local_set.run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new(event).payload("hello dynamic world"),
+    ))
+  };
+
+  let resp = send("word_count").await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  assert_eq!(resp.payload.as_ref(), b"3");
+  let resp = send("reject").await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+  assert_eq!(resp.error_origin, None);
+  assert_eq!(resp.payload.as_ref(), b"rejected");
+
+  // The panics stay in the plugin, which is disabled after `max_faults`
+  for _ in 0..2 {
+    let resp = send("crash").await;
+    assert_eq!(resp.error_origin, Some(ErrorOrigin::Internal));
+  }
+  let resp = send("word_count").await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+  assert_eq!(resp.error_origin, Some(ErrorOrigin::Internal));
+}
+
+#[tokio::test]
+async fn dylib_sandbox_test() {
+  let events = ["word_count", "slow", "repeat"];
+  let plugin = unsafe { DylibPlugin::from_descriptor(descriptor(PLUGIN_ABI_VERSION, &events)) }
+    .unwrap()
+    .max_faults(2)
+    .sandbox(
+      SandboxLimits::new()
+        .workers(1)
+        .cpu_time(Duration::from_millis(100))
+        .max_payload_bytes(64)
+        .max_response_bytes(100),
+    )
+    .unwrap();
+
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(runtime, vec![]));
+  dispatch.register_plugin(plugin.into_plugin()).unwrap();
+  let local_set = LocalSet::new();
+  let send = |event: &'static str, payload: &str| {
+    local_set.run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new(event).payload(payload.to_owned()),
+    ))
+  };
+  let violation =
+    |response: &AFPluginEventResponse| SandboxViolation::from_response(response).unwrap().resource;
+
+  let resp = send("word_count", "hello sandboxed world").await;
+  assert_eq!(resp.payload.as_ref(), b"3");
+  let resp = send("word_count", &"word ".repeat(13)).await;
+  assert_eq!(violation(&resp), SandboxResource::Payload);
+  let resp = send("repeat", "0123456789").await;
+  assert_eq!(violation(&resp), SandboxResource::Response);
+
+  // The slow handler keeps the only worker busy after its request failed.
+  let resp = send("slow", "").await;
+  assert_eq!(violation(&resp), SandboxResource::CpuTime);
+  let resp = send("word_count", "busy").await;
+  assert_eq!(violation(&resp), SandboxResource::Workers);
+  tokio::time::sleep(Duration::from_millis(300)).await;
+
+  // The plugin faulted twice, the large response and the slow handler.
+  let resp = send("word_count", "disabled").await;
+  assert_eq!(resp.error_origin, Some(ErrorOrigin::Internal));
+}
+
+#[test]
+fn dylib_loader_test() {
+  let dir = std::env::temp_dir().join(format!("lib-dispatch-plugins-{}", std::process::id()));
+  let _ = std::fs::remove_dir_all(&dir);
+  let loader = DylibPluginLoader::new(&dir);
+  assert!(loader.discover().unwrap().is_empty());
+
+  std::fs::create_dir_all(&dir).unwrap();
+  let library = dir.join(format!("broken.{}", std::env::consts::DLL_EXTENSION));
+  std::fs::write(&library, b"not a library").unwrap();
+  std::fs::write(dir.join("notes.txt"), b"").unwrap();
+  assert_eq!(loader.discover().unwrap(), vec![library.clone()]);
+
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  let dispatch = AFPluginDispatcher::new(runtime, vec![]);
+  let failures = unsafe { loader.register_all(&dispatch) }.unwrap();
+  assert_eq!(failures.len(), 1);
+  assert_eq!(failures[0].0, library);
+  assert!(matches!(failures[0].1, DylibPluginError::Load(_)));
+  let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,189 @@
+use flowy_derive::{FromBytes, ToBytes};
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct Cells {
+  row: u32,
+  values: Vec<f64>,
+}
+
+#[cfg(feature = "use_msgpack")]
+async fn sum_msgpack(cells: MsgPack<Cells>) -> MsgPack<f64> {
+  MsgPack(cells.values.iter().sum())
+}
+
+async fn sum_json(cells: Json<Cells>) -> Json<f64> {
+  Json(cells.values.iter().sum())
+}
+
+#[cfg(feature = "use_cbor")]
+async fn sum_cbor(cells: Cbor<Cells>) -> Cbor<f64> {
+  Cbor(cells.values.iter().sum())
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, ToBytes, FromBytes)]
+#[codec(json)]
+struct RenameParams {
+  id: String,
+  name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, ToBytes, FromBytes)]
+struct Renamed {
+  id: String,
+  name: String,
+  version: u32,
+}
+
+async fn rename(params: AFPluginData<RenameParams>) -> DataResult<Renamed, DispatchError> {
+  let params = params.into_inner();
+  data_result_ok(Renamed {
+    id: params.id,
+    name: params.name,
+    version: 2,
+  })
+}
+
+#[tokio::test]
+async fn serde_payload_encoding_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event("sum_json", sum_json)],
+  ));
+  let cells = Cells {
+    row: 1,
+    values: vec![1.5, 2.5],
+  };
+
+  let request = AFPluginRequest::new("sum_json").payload(Json(&cells).to_vec().unwrap());
+  let resp = LocalSet::new()
+    .run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+    .await;
+  assert_eq!(resp.content_type, Some(ContentType::Json));
+  assert_eq!(resp.payload.as_ref(), b"4.0");
+
+  std::mem::forget(dispatch);
+}
+
+#[cfg(feature = "use_msgpack")]
+#[tokio::test]
+async fn msgpack_payload_encoding_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event("sum_msgpack", sum_msgpack)],
+  ));
+  let local_set = LocalSet::new();
+  let cells = Cells {
+    row: 1,
+    values: vec![1.5, 2.5],
+  };
+
+  let request = AFPluginRequest::new("sum_msgpack")
+    .payload(MsgPack(&cells).to_vec().unwrap())
+    .content_type(ContentType::MessagePack);
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  assert_eq!(resp.content_type, Some(ContentType::MessagePack));
+  let sum: f64 = rmp_serde::from_slice(resp.payload.as_ref()).unwrap();
+  assert_eq!(sum, 4.0);
+
+  // The payload is rejected when it's tagged with another encoding.
+  let request = AFPluginRequest::new("sum_msgpack")
+    .payload(Json(&cells).to_vec().unwrap())
+    .content_type(ContentType::Json);
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+
+  std::mem::forget(dispatch);
+}
+
+#[cfg(feature = "use_cbor")]
+#[tokio::test]
+async fn cbor_payload_encoding_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event("sum_cbor", sum_cbor)],
+  ));
+  let local_set = LocalSet::new();
+  let cells = Cells {
+    row: 1,
+    values: vec![1.5, 2.5],
+  };
+
+  let request = AFPluginRequest::new("sum_cbor")
+    .payload(Cbor(&cells).to_vec().unwrap())
+    .content_type(ContentType::Cbor);
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  assert_eq!(resp.content_type, Some(ContentType::Cbor));
+  let sum: f64 = ciborium::from_reader(resp.payload.as_ref()).unwrap();
+  assert_eq!(sum, 4.0);
+
+  // Same error mapping as the JSON payloads.
+  let request = AFPluginRequest::new("sum_cbor").payload(vec![0xff_u8]);
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+
+  std::mem::forget(dispatch);
+}
+
+#[tokio::test]
+async fn derived_bytes_encoding_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event("rename", rename)],
+  ));
+  let local_set = LocalSet::new();
+  let params = RenameParams {
+    id: "v1".to_string(),
+    name: "Roadmap".to_string(),
+  };
+
+  let renamed: Renamed = local_set
+    .run_until(dispatch.request("rename", params))
+    .await
+    .unwrap();
+  assert_eq!(
+    renamed,
+    Renamed {
+      id: "v1".to_string(),
+      name: "Roadmap".to_string(),
+      version: 2,
+    }
+  );
+
+  // Encoded with the codec of the type.
+  let bytes = RenameParams {
+    id: "v1".to_string(),
+    name: "Roadmap".to_string(),
+  }
+  .into_bytes()
+  .unwrap();
+  assert_eq!(bytes.as_ref(), br#"{"id":"v1","name":"Roadmap"}"#);
+  assert_eq!(
+    RenameParams::parse_from_bytes(bytes).unwrap().name,
+    "Roadmap"
+  );
+
+  std::mem::forget(dispatch);
+}
@@ -0,0 +1,92 @@
+use lib_dispatch::executor::{Executor, ExecutorExt, LocalExecutor, TokioExecutor};
+use lib_dispatch::prelude::*;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+async fn hello() -> String {
+  "hello".to_string()
+}
+
+async fn import_csv(executor: AFPluginState<Arc<LocalExecutor>>) -> String {
+  executor.sleep(Duration::from_secs(5)).await;
+  "imported".to_string()
+}
+
+async fn broken_task() -> u32 {
+  panic!("boom")
+}
+
+#[test]
+fn local_executor_dispatch_test() {
+  let executor = Arc::new(LocalExecutor::new());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::with_executor(
+      executor.clone(),
+      vec![AFPlugin::new()
+        .state(executor.clone())
+        .event("hello", hello)
+        .event("import_csv", import_csv)],
+    )
+    .with_default_timeout(Duration::from_millis(50)),
+  );
+
+  let resp = executor.block_on(AFPluginDispatcher::async_send(
+    dispatch.as_ref(),
+    AFPluginRequest::new("hello"),
+  ));
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  assert_eq!(String::from_utf8_lossy(resp.payload.as_ref()), "hello");
+
+  // The low priority requests yield to the executor before they are handled.
+  let resp = executor.block_on(AFPluginDispatcher::async_send(
+    dispatch.as_ref(),
+    AFPluginRequest::new("hello").priority(RequestPriority::Low),
+  ));
+  assert_eq!(resp.status_code, StatusCode::Ok);
+
+  // The timeout runs on the timers of the executor, there is no tokio runtime.
+  let started_at = Instant::now();
+  let resp = executor.block_on(AFPluginDispatcher::async_send(
+    dispatch.as_ref(),
+    AFPluginRequest::new("import_csv"),
+  ));
+  assert_eq!(resp.status_code, StatusCode::Err);
+  assert!(started_at.elapsed() < Duration::from_secs(5));
+  std::mem::forget(dispatch);
+}
+
+#[test]
+fn local_executor_tasks_test() {
+  let executor = Arc::new(LocalExecutor::new());
+  let output = executor.block_on(async {
+    let sent = executor.spawn_with_handle(async { 1 });
+    let local = executor.spawn_local_with_handle(async { 2 });
+    let blocking = executor.spawn_blocking_with_handle(|| {
+      std::thread::sleep(Duration::from_millis(10));
+      3
+    });
+    executor.sleep(Duration::from_millis(10)).await;
+    sent.await.unwrap() + local.await.unwrap() + blocking.await.unwrap()
+  });
+  assert_eq!(output, 6);
+
+  // The executor can drive another future once the first one is done.
+  let output = executor.block_on(executor.spawn_with_handle(async { "again" }));
+  assert_eq!(output.unwrap(), "again");
+}
+
+#[tokio::test]
+async fn tokio_executor_test() {
+  let executor = TokioExecutor::current();
+  let blocking = executor.spawn_blocking_with_handle(|| "blocking");
+  assert_eq!(blocking.await.unwrap(), "blocking");
+
+  // A task that panics reports that it was canceled.
+  let panicked = executor.spawn_with_handle(broken_task());
+  assert!(panicked.await.is_err());
+
+  let started_at = Instant::now();
+  executor.sleep(Duration::from_millis(10)).await;
+  assert!(started_at.elapsed() >= Duration::from_millis(10));
+}
@@ -0,0 +1,74 @@
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::{Arc, Mutex};
+use tokio::task::LocalSet;
+
+#[derive(Clone)]
+struct User {
+  name: String,
+}
+
+/// Resolves the user from the payload, like an auth middleware would do with a token.
+#[derive(Default, Clone)]
+struct AuthMiddleware {
+  seen: Arc<Mutex<Vec<Extensions>>>,
+  responded_to: Arc<Mutex<Vec<String>>>,
+}
+
+impl AFPluginMiddleware for AuthMiddleware {
+  fn on_request(&self, request: &mut AFPluginRequest) -> Result<(), DispatchError> {
+    let token = String::from_utf8_lossy(request.payload_bytes()).into_owned();
+    if token.is_empty() {
+      return Err("unauthorized".to_string().into());
+    }
+    request.extensions().insert(User { name: token });
+    self.seen.lock().unwrap().push(request.extensions().clone());
+    Ok(())
+  }
+
+  fn on_response(&self, request: &AFPluginRequest, _response: &mut AFPluginEventResponse) {
+    if let Some(user) = request.extensions().get::<User>() {
+      self.responded_to.lock().unwrap().push(user.name);
+    }
+  }
+}
+
+async fn whoami(user: Extension<User>) -> String {
+  user.name.clone()
+}
+
+#[tokio::test]
+async fn middleware_extensions_test() {
+  let middleware = AuthMiddleware::default();
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(runtime, vec![AFPlugin::new().event("whoami", whoami)])
+      .with_middleware(middleware.clone()),
+  );
+  let local_set = LocalSet::new();
+
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new("whoami").payload("nathan"),
+    ))
+    .await;
+  assert_eq!(String::from_utf8_lossy(resp.payload.as_ref()), "nathan");
+  assert_eq!(*middleware.responded_to.lock().unwrap(), vec!["nathan"]);
+
+  // The extensions are cleared once the request is done.
+  let seen = middleware.seen.lock().unwrap().clone();
+  assert_eq!(seen.len(), 1);
+  assert!(!seen[0].contains::<User>());
+
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new("whoami"),
+    ))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+
+  std::mem::forget(dispatch);
+}
@@ -0,0 +1,97 @@
+use lib_dispatch::clock::{Clock, MockClock};
+use lib_dispatch::fault::Fault;
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use lib_dispatch::system::SysEvent;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::LocalSet;
+
+async fn hello() -> String {
+  "hello".to_string()
+}
+
+fn payload_str(response: &AFPluginEventResponse) -> String {
+  String::from_utf8_lossy(response.payload.as_ref()).into_owned()
+}
+
+#[tokio::test]
+async fn fault_injection_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  let clock = MockClock::new();
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(runtime, vec![AFPlugin::new().event("hello", hello)])
+      .with_clock(clock.clone()),
+  );
+  let local_set = LocalSet::new();
+  let send = |request: AFPluginRequestBuilder| {
+    local_set.run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+  };
+  let inject_fault =
+    |command: &'static str| send(AFPluginRequest::builder(SysEvent::InjectFault).payload(command));
+  // Sends the request and moves the clock once the fault is waiting.
+  let send_and_advance = |request: AFPluginRequestBuilder, duration: Duration| {
+    local_set.run_until(async {
+      let (resp, _) = tokio::join!(
+        AFPluginDispatcher::async_send(dispatch.as_ref(), request),
+        async {
+          while clock.pending_sleeps() == 0 {
+            tokio::task::yield_now().await;
+          }
+          clock.advance(duration);
+        }
+      );
+      resp
+    })
+  };
+
+  // The delay applies to the next request only.
+  let resp =
+    inject_fault(r#"{"event": "hello", "fault": {"kind": "delay", "delay_ms": 1000}, "times": 1}"#)
+      .await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  assert!(payload_str(&resp).contains(r#""times":1"#));
+  let resp = send_and_advance(AFPluginRequest::builder("hello"), Duration::from_secs(1)).await;
+  assert_eq!(payload_str(&resp), "hello");
+  assert!(dispatch.faults().rules().is_empty());
+
+  dispatch.faults().inject(
+    "hello",
+    Fault::Error {
+      message: "disk is full".to_string(),
+    },
+  );
+  let resp = send(AFPluginRequest::builder("hello")).await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+  assert!(payload_str(&resp).contains("disk is full"));
+
+  dispatch
+    .faults()
+    .inject("hello", Fault::Timeout { after_ms: 500 });
+  let resp = send_and_advance(
+    AFPluginRequest::builder("hello"),
+    Duration::from_millis(500),
+  )
+  .await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+  assert!(payload_str(&resp).contains("DeadlineExceeded"));
+
+  // The dropped requests end with their deadline.
+  dispatch.faults().inject("hello", Fault::Drop);
+  let resp = send_and_advance(
+    AFPluginRequest::builder("hello").deadline(clock.now() + Duration::from_secs(1)),
+    Duration::from_secs(2),
+  )
+  .await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+  assert!(payload_str(&resp).contains("while handled"));
+  assert!(dispatch.in_flight().is_empty());
+
+  let resp = inject_fault(r#"{"event": "hello"}"#).await;
+  assert_eq!(payload_str(&resp), "{}");
+  let resp = send(AFPluginRequest::builder("hello")).await;
+  assert_eq!(payload_str(&resp), "hello");
+
+  std::mem::forget(dispatch);
+}
@@ -0,0 +1,95 @@
+use lib_dispatch::feature_flag::{FeatureDisabled, FileFlagStore};
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use lib_dispatch::system::SysEvent;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+async fn summarize(text: String) -> String {
+  format!("summary of {}", text)
+}
+
+async fn search() -> String {
+  "results".to_string()
+}
+
+fn make_dispatcher(store_path: &Path) -> AFPluginDispatcher {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new()
+      .name("ai")
+      .event("summarize", summarize)
+      .event("search", search)
+      .feature_flag("summarize", "ai_summary")],
+  )
+  .with_flag_store(FileFlagStore::new(store_path))
+}
+
+#[tokio::test]
+async fn feature_flag_test() {
+  let path = std::env::temp_dir().join(format!("flags-{}.json", nanoid::nanoid!(6)));
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(make_dispatcher(&path));
+  let flags = dispatch.feature_flags();
+  let local_set = LocalSet::new();
+  let send = |request: AFPluginRequest| {
+    local_set.run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+  };
+  let summarize_notes = || AFPluginRequest::new("summarize").payload("notes");
+  let set_flag =
+    |change: &str| send(AFPluginRequest::new(SysEvent::SetFeatureFlag).payload(change));
+
+  // The flags that were never declared are off.
+  let resp = send(summarize_notes()).await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+  assert_eq!(
+    FeatureDisabled::from_response(&resp).unwrap(),
+    FeatureDisabled {
+      event: "summarize".to_string(),
+      flag: "ai_summary".to_string(),
+    }
+  );
+  let resp = send(AFPluginRequest::new("search")).await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+
+  // Evaluated for each request.
+  flags.define_rule("ai_summary", |request: &AFPluginRequest| {
+    request.metadata.get("channel").map(String::as_str) == Some("beta")
+  });
+  let resp = send(summarize_notes()).await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+  let resp = send(summarize_notes().metadata("channel", "beta")).await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  assert_eq!(resp.payload.as_ref(), b"summary of notes");
+  assert_eq!(flags.flags(), vec!["ai_summary"]);
+
+  // The overrides apply to all the requests and are persisted.
+  let resp = set_flag(r#"{"flag": "ai_summary", "enabled": true}"#).await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  let overrides: BTreeMap<String, bool> = serde_json::from_slice(resp.payload.as_ref()).unwrap();
+  assert_eq!(
+    overrides,
+    BTreeMap::from([("ai_summary".to_string(), true)])
+  );
+  let resp = send(summarize_notes()).await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+
+  let restored = make_dispatcher(&path);
+  assert_eq!(restored.feature_flags().overrides(), overrides);
+  std::mem::forget(restored);
+
+  // Without the override, the rule decides again.
+  let resp = set_flag(r#"{"flag": "ai_summary"}"#).await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  assert_eq!(String::from_utf8_lossy(resp.payload.as_ref()), "{}");
+  let resp = send(summarize_notes()).await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+  let resp = set_flag(r#"{"name": "ai_summary"}"#).await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+
+  std::fs::remove_file(&path).unwrap();
+  std::mem::forget(dispatch);
+}
@@ -0,0 +1,85 @@
+use lib_dispatch::fixture::{replay_fixture, Fixture, FixtureRecorder};
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+struct Counter {
+  step: usize,
+  count: AtomicUsize,
+}
+
+async fn increment(counter: AFPluginState<Counter>) -> String {
+  let count = counter.count.fetch_add(counter.step, Ordering::SeqCst) + counter.step;
+  count.to_string()
+}
+
+async fn count(counter: AFPluginState<Counter>) -> String {
+  counter.count.load(Ordering::SeqCst).to_string()
+}
+
+fn counter_dispatcher(step: usize, recorder: FixtureRecorder) -> Arc<AFPluginDispatcher> {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  let plugin = AFPlugin::new()
+    .name("counter")
+    .state(Counter {
+      step,
+      count: AtomicUsize::new(0),
+    })
+    .event("increment", increment)
+    .event("count", count);
+  #[allow(clippy::arc_with_non_send_sync)]
+  Arc::new(AFPluginDispatcher::new(runtime, vec![plugin]).with_middleware(recorder))
+}
+
+#[tokio::test]
+async fn record_and_replay_fixture_test() {
+  let recorder = FixtureRecorder::new();
+  let dispatch = counter_dispatcher(1, recorder.clone());
+  let local_set = LocalSet::new();
+  local_set
+    .run_until(async {
+      // Not recorded.
+      AFPluginDispatcher::async_send(dispatch.as_ref(), AFPluginRequest::new("count")).await;
+      recorder.start();
+      for _ in 0..2 {
+        AFPluginDispatcher::async_send(dispatch.as_ref(), AFPluginRequest::new("increment")).await;
+      }
+      recorder
+        .record_state(dispatch.as_ref(), AFPluginRequest::new("count"))
+        .await;
+      recorder.stop();
+    })
+    .await;
+
+  let fixture = recorder.take_fixture();
+  assert_eq!(fixture.events.len(), 2);
+  assert_eq!(fixture.state.len(), 1);
+  assert_eq!(fixture.state[0].response.payload.as_ref(), b"2");
+
+  let path = std::env::temp_dir().join(format!("fixture-{}.json", std::process::id()));
+  fixture.save(&path).unwrap();
+  let fixture = Fixture::load(&path).unwrap();
+  std::fs::remove_file(&path).unwrap();
+
+  let same = counter_dispatcher(1, FixtureRecorder::new());
+  let report = local_set
+    .run_until(replay_fixture(same.as_ref(), &fixture))
+    .await;
+  assert_eq!(report.replayed, 3);
+  assert!(report.is_success(), "{:?}", report);
+
+  let changed = counter_dispatcher(2, FixtureRecorder::new());
+  let report = local_set
+    .run_until(replay_fixture(changed.as_ref(), &fixture))
+    .await;
+  assert_eq!(report.mismatches.len(), 3);
+  let state = report.mismatches.last().unwrap();
+  assert!(state.is_state);
+  assert_eq!(state.actual.payload.as_ref(), b"4");
+
+  std::mem::forget(dispatch);
+  std::mem::forget(same);
+  std::mem::forget(changed);
+}
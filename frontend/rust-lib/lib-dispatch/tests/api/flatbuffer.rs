@@ -0,0 +1,87 @@
+use flatbuffers::{
+  FlatBufferBuilder, Follow, ForwardsUOffset, Table, VOffsetT, Verifiable, WIPOffset,
+};
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+// What flatc generates for `table Greeting { name: string; }`.
+struct Greeting<'a> {
+  _tab: Table<'a>,
+}
+
+impl<'a> Greeting<'a> {
+  const VT_NAME: VOffsetT = 4;
+
+  fn name(&self) -> Option<&'a str> {
+    unsafe { self._tab.get::<ForwardsUOffset<&str>>(Self::VT_NAME, None) }
+  }
+}
+
+impl<'a> Follow<'a> for Greeting<'a> {
+  type Inner = Greeting<'a>;
+
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Greeting {
+      _tab: Table::new(buf, loc),
+    }
+  }
+}
+
+impl Verifiable for Greeting<'_> {
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier,
+    pos: usize,
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    v.visit_table(pos)?
+      .visit_field::<ForwardsUOffset<&str>>("name", Self::VT_NAME, false)?
+      .finish();
+    Ok(())
+  }
+}
+
+struct GreetingRoot;
+
+impl FlatbufferRoot for GreetingRoot {
+  type Root<'a> = Greeting<'a>;
+}
+
+async fn greet(data: Flatbuffer<GreetingRoot>) -> String {
+  format!("hello {}", data.root().name().unwrap_or_default())
+}
+
+fn greeting(name: &str) -> Vec<u8> {
+  let mut builder = FlatBufferBuilder::new();
+  let name = builder.create_string(name);
+  let start = builder.start_table();
+  builder.push_slot_always::<WIPOffset<_>>(Greeting::VT_NAME, name);
+  let root = builder.end_table(start);
+  builder.finish(root, None);
+  builder.finished_data().to_vec()
+}
+
+#[tokio::test]
+async fn flatbuffer_payload_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event("greet", greet)],
+  ));
+  let local_set = LocalSet::new();
+  let request = AFPluginRequest::new("greet").payload(greeting("world"));
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  assert_eq!(resp.payload.as_ref(), b"hello world");
+
+  let request = AFPluginRequest::new("greet").payload(vec![1u8, 2, 3]);
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+
+  std::mem::forget(dispatch);
+}
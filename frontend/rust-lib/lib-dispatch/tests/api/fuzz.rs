@@ -0,0 +1,80 @@
+use lib_dispatch::fuzz::{fuzz_event, fuzz_events, mutated_payload_strategy, FuzzOutcome};
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use proptest::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Cells {
+  row: u32,
+  values: Vec<f64>,
+}
+
+async fn sum_json(cells: Json<Cells>) -> Json<f64> {
+  Json(cells.values.iter().sum())
+}
+
+async fn sum_msgpack(cells: MsgPack<Cells>) -> MsgPack<f64> {
+  MsgPack(cells.values.iter().sum())
+}
+
+async fn first_value(cells: Json<Cells>) -> Json<f64> {
+  Json(cells.values[0])
+}
+
+fn cells_dispatcher() -> Arc<AFPluginDispatcher> {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new()
+      .name("cells")
+      .event("sum_json", sum_json)
+      .event("sum_msgpack", sum_msgpack)],
+  ))
+}
+
+#[tokio::test]
+async fn fuzz_crash_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event("first_value", first_value)],
+  ));
+  let local_set = LocalSet::new();
+  let event = AFPluginEvent::from("first_value");
+  let outcome = local_set
+    .run_until(fuzz_event(
+      dispatch.as_ref(),
+      &event,
+      b"{\"row\":1}".to_vec(),
+    ))
+    .await;
+  assert_eq!(outcome, FuzzOutcome::Rejected);
+
+  let empty = br#"{"row":1,"values":[]}"#.to_vec();
+  let outcome = local_set
+    .run_until(fuzz_event(dispatch.as_ref(), &event, empty))
+    .await;
+  assert!(matches!(outcome, FuzzOutcome::Crashed(_)), "{:?}", outcome);
+
+  std::mem::forget(dispatch);
+}
+
+proptest! {
+  #![proptest_config(ProptestConfig::with_cases(64))]
+
+  #[test]
+  fn fuzz_payload_decoding_test(
+    payload in mutated_payload_strategy(br#"{"row":1,"values":[1.0,2.0]}"#.to_vec())
+  ) {
+    let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    let dispatch = cells_dispatcher();
+    let crashes = runtime.block_on(LocalSet::new().run_until(fuzz_events(dispatch.as_ref(), &payload)));
+    std::mem::forget(dispatch);
+    prop_assert!(crashes.is_empty(), "{:?}", crashes);
+  }
+}
@@ -0,0 +1,63 @@
+use lib_dispatch::gate::{Precondition, PreconditionFailed};
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+async fn create_view() -> String {
+  "view".to_string()
+}
+
+async fn hello() -> String {
+  "hello".to_string()
+}
+
+#[tokio::test]
+async fn precondition_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new()
+      .event("create_view", create_view)
+      .event("hello", hello)
+      .requires("create_view", Precondition::state("session"))
+      .requires("create_view", Precondition::not("read_only"))],
+  ));
+  let local_set = LocalSet::new();
+  let send = |event: &'static str| {
+    local_set.run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new(event),
+    ))
+  };
+
+  let resp = send("create_view").await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+  assert_eq!(resp.error_origin, Some(ErrorOrigin::Dispatcher));
+  assert_eq!(
+    PreconditionFailed::from_response(&resp),
+    Some(PreconditionFailed {
+      event: "create_view".to_string(),
+      violations: vec![Precondition::state("session")],
+    })
+  );
+  assert_eq!(send("hello").await.status_code, StatusCode::Ok);
+
+  let states = dispatch.app_states();
+  assert!(states.enter("session"));
+  assert_eq!(send("create_view").await.status_code, StatusCode::Ok);
+
+  states.enter("read_only");
+  let resp = send("create_view").await;
+  assert_eq!(
+    PreconditionFailed::from_response(&resp).unwrap().violations,
+    vec![Precondition::not("read_only")]
+  );
+
+  assert!(states.leave("read_only"));
+  assert_eq!(states.current(), vec!["session"]);
+  assert_eq!(send("create_view").await.status_code, StatusCode::Ok);
+
+  std::mem::forget(dispatch);
+}
@@ -0,0 +1,71 @@
+use hyper::client::conn;
+use hyper::{Body, Request};
+use lib_dispatch::bridge::{grpc_methods, GrpcGateway, DEFAULT_GRPC_SERVICE};
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::LocalSet;
+
+async fn hello(name: String) -> String {
+  format!("hello {}", name)
+}
+
+async fn call(addr: SocketAddr, path: &str, message: &[u8]) -> (Vec<u8>, String) {
+  let stream = TcpStream::connect(addr).await.unwrap();
+  let (mut sender, connection) = conn::Builder::new()
+    .http2_only(true)
+    .handshake::<_, Body>(stream)
+    .await
+    .unwrap();
+  tokio::spawn(connection);
+
+  let mut body = vec![0];
+  body.extend_from_slice(&(message.len() as u32).to_be_bytes());
+  body.extend_from_slice(message);
+  let request = Request::post(format!("http://{}{}", addr, path))
+    .header("content-type", "application/grpc")
+    .body(Body::from(body))
+    .unwrap();
+  let mut response = sender.send_request(request).await.unwrap();
+  let data = hyper::body::HttpBody::data(response.body_mut())
+    .await
+    .map(|data| data.unwrap().to_vec())
+    .unwrap_or_default();
+  let trailers = hyper::body::HttpBody::trailers(response.body_mut())
+    .await
+    .unwrap()
+    .unwrap();
+  let status = trailers["grpc-status"].to_str().unwrap().to_owned();
+  (data, status)
+}
+
+#[tokio::test]
+async fn grpc_gateway_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event("hello", hello)],
+  ));
+  assert!(grpc_methods(&dispatch, DEFAULT_GRPC_SERVICE)
+    .contains(&"/appflowy.Dispatcher/hello".to_owned()));
+  let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+  let addr = listener.local_addr().unwrap();
+
+  let local_set = LocalSet::new();
+  local_set.spawn_local(GrpcGateway::new(addr).serve_listener(listener, dispatch.clone()));
+  local_set
+    .run_until(async move {
+      let (data, status) = call(addr, "/appflowy.Dispatcher/hello", b"world").await;
+      assert_eq!(status, "0");
+      assert_eq!(&data[5..], b"hello world");
+
+      let (_, status) = call(addr, "/appflowy.Dispatcher/missing", b"").await;
+      assert_eq!(status, "12");
+    })
+    .await;
+
+  std::mem::forget(dispatch);
+}
@@ -0,0 +1,66 @@
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use lib_dispatch::system::SysEvent;
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+struct Pool;
+
+struct BrokenPool;
+
+async fn open(_pool: AFPluginState<Pool>) {}
+
+async fn broken(_pool: AFPluginState<BrokenPool>) {}
+
+fn health_check(resp: AFPluginEventResponse) -> serde_json::Value {
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  serde_json::from_slice(resp.payload.as_ref()).unwrap()
+}
+
+#[tokio::test]
+async fn health_check_event_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new()
+      .name("storage")
+      .state_lazy(|| async { Ok::<_, String>(Pool) })
+      .state_lazy(|| async { Err::<BrokenPool, _>("disk is full") })
+      .event("open", open)
+      .event("broken", broken)],
+  ));
+  let local_set = LocalSet::new();
+  let send = |request: AFPluginRequest| {
+    local_set.run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+  };
+
+  let report = health_check(send(AFPluginRequest::new(SysEvent::HealthCheck)).await);
+  assert_eq!(report["healthy"], true);
+  assert_eq!(report["queue_depth"], 0);
+  assert!(report["uptime_ms"].is_u64());
+  assert!(report["workers"]["num_workers"].as_u64().unwrap() > 0);
+  assert!(report["workers"]["stalled"].as_array().unwrap().is_empty());
+  let modules = report["modules"].as_array().unwrap();
+  assert_eq!(modules.len(), 2);
+  assert!(modules.iter().all(|module| module["plugin"] == "storage"));
+  assert!(modules.iter().all(|module| module["status"] == "pending"));
+
+  send(AFPluginRequest::new("open")).await;
+  send(AFPluginRequest::new("broken")).await;
+  let report = health_check(send(AFPluginRequest::new(SysEvent::HealthCheck)).await);
+  assert_eq!(report["healthy"], false);
+  let module = |state: &str| {
+    report["modules"]
+      .as_array()
+      .unwrap()
+      .iter()
+      .find(|module| module["state"].as_str().unwrap().ends_with(state))
+      .unwrap()
+      .clone()
+  };
+  assert_eq!(module("::Pool")["status"], "ready");
+  assert_eq!(module("::BrokenPool")["status"], "failed");
+  assert_eq!(module("::BrokenPool")["reason"], "disk is full");
+  std::mem::forget(dispatch);
+}
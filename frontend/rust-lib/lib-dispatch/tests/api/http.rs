@@ -0,0 +1,96 @@
+use lib_dispatch::bridge::HttpGateway;
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::LocalSet;
+
+async fn hello(name: String) -> String {
+  format!("hello {}", name)
+}
+
+async fn post(addr: SocketAddr, method: &str, path: &str, body: &str) -> String {
+  let mut stream = TcpStream::connect(addr).await.unwrap();
+  let request = format!(
+    "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{}",
+    method,
+    path,
+    body.len(),
+    body
+  );
+  stream.write_all(request.as_bytes()).await.unwrap();
+  let mut response = String::new();
+  stream.read_to_string(&mut response).await.unwrap();
+  response
+}
+
+#[tokio::test]
+async fn http_gateway_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event("hello", hello)],
+  ));
+  let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+  let addr = listener.local_addr().unwrap();
+
+  let local_set = LocalSet::new();
+  local_set.spawn_local(HttpGateway::new(addr).serve_listener(listener, dispatch.clone()));
+  local_set
+    .run_until(async move {
+      let response = post(addr, "POST", "/api/hello", "world").await;
+      assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+      assert!(response.ends_with("hello world"), "{}", response);
+
+      let response = post(addr, "GET", "/api", "").await;
+      assert!(response.contains("\"/api/hello\""), "{}", response);
+
+      let response = post(addr, "POST", "/api/missing", "").await;
+      assert!(response.starts_with("HTTP/1.1 404"), "{}", response);
+
+      let response = post(addr, "GET", "/api/hello", "").await;
+      assert!(response.starts_with("HTTP/1.1 405"), "{}", response);
+    })
+    .await;
+
+  std::mem::forget(dispatch);
+}
+
+#[tokio::test]
+async fn http_gateway_body_limit_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event("hello", hello)],
+  ));
+  let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+  let addr = listener.local_addr().unwrap();
+  let gateway = HttpGateway::new(addr).with_max_body_size(8);
+
+  let local_set = LocalSet::new();
+  local_set.spawn_local(gateway.serve_listener(listener, dispatch.clone()));
+  local_set
+    .run_until(async move {
+      let response = post(addr, "POST", "/api/hello", "world").await;
+      assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+
+      let response = post(addr, "POST", "/api/hello", "a long name").await;
+      assert!(response.starts_with("HTTP/1.1 413"), "{}", response);
+
+      // Without a Content-Length, the body is only rejected once it's read.
+      let mut stream = TcpStream::connect(addr).await.unwrap();
+      let request = "POST /api/hello HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+                     Transfer-Encoding: chunked\r\n\r\nb\r\na long name\r\n0\r\n\r\n";
+      stream.write_all(request.as_bytes()).await.unwrap();
+      let mut response = String::new();
+      stream.read_to_string(&mut response).await.unwrap();
+      assert!(response.starts_with("HTTP/1.1 413"), "{}", response);
+    })
+    .await;
+
+  std::mem::forget(dispatch);
+}
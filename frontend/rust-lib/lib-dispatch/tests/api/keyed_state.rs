@@ -0,0 +1,79 @@
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+struct UserId(i64);
+
+struct UserCache {
+  uid: i64,
+  opened: AtomicUsize,
+}
+
+/// Reads the user id from the payload, like a session middleware would do with a token.
+struct SessionMiddleware;
+
+impl AFPluginMiddleware for SessionMiddleware {
+  fn on_request(&self, request: &mut AFPluginRequest) -> Result<(), DispatchError> {
+    let uid = String::from_utf8_lossy(request.payload_bytes()).into_owned();
+    if let Ok(uid) = uid.parse() {
+      request.extensions().insert(UserId(uid));
+    }
+    Ok(())
+  }
+}
+
+async fn open(cache: KeyedState<UserId, UserCache>) -> String {
+  let opened = cache.opened.fetch_add(1, Ordering::SeqCst) + 1;
+  format!("{}:{}", cache.uid, opened)
+}
+
+#[tokio::test]
+async fn keyed_state_test() {
+  let inits = Arc::new(AtomicUsize::new(0));
+  let counter = inits.clone();
+  let caches = KeyedStates::new(move |uid: &UserId| {
+    counter.fetch_add(1, Ordering::SeqCst);
+    UserCache {
+      uid: uid.0,
+      opened: AtomicUsize::new(0),
+    }
+  });
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(runtime, vec![AFPlugin::new().event("open", open)])
+      .data(caches.clone())
+      .with_middleware(SessionMiddleware),
+  );
+  let local_set = LocalSet::new();
+  let send = |uid: &str| {
+    local_set.run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new("open").payload(uid.to_string()),
+    ))
+  };
+
+  for (uid, expected) in [("1", "1:1"), ("2", "2:1"), ("1", "1:2")] {
+    let resp = send(uid).await;
+    assert_eq!(String::from_utf8_lossy(resp.payload.as_ref()), expected);
+  }
+  assert_eq!(inits.load(Ordering::SeqCst), 2);
+  assert_eq!(caches.len(), 2);
+
+  // Signing out drops the entry, the next session starts from scratch.
+  assert!(caches.evict(&UserId(1)).is_some());
+  assert!(!caches.contains(&UserId(1)));
+  let resp = send("1").await;
+  assert_eq!(String::from_utf8_lossy(resp.payload.as_ref()), "1:1");
+  assert_eq!(inits.load(Ordering::SeqCst), 3);
+
+  // Without a session key the request is rejected.
+  let resp = send("").await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+  assert_eq!(resp.error_origin, Some(ErrorOrigin::Internal));
+
+  std::mem::forget(dispatch);
+}
@@ -0,0 +1,78 @@
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+struct Pool {
+  connections: usize,
+}
+
+struct BrokenPool;
+
+async fn connections(pool: AFPluginState<Pool>) -> String {
+  pool.connections.to_string()
+}
+
+async fn broken(_pool: AFPluginState<BrokenPool>) -> String {
+  "unreachable".to_string()
+}
+
+#[tokio::test]
+async fn lazy_state_test() {
+  let inits = Arc::new(AtomicUsize::new(0));
+  let pool_inits = inits.clone();
+  let broken_inits = Arc::new(AtomicUsize::new(0));
+  let failed_inits = broken_inits.clone();
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new()
+      .state_lazy(move || {
+        let inits = pool_inits.clone();
+        async move {
+          inits.fetch_add(1, Ordering::SeqCst);
+          Ok::<_, String>(Pool { connections: 4 })
+        }
+      })
+      .state_lazy(move || {
+        let inits = failed_inits.clone();
+        async move {
+          inits.fetch_add(1, Ordering::SeqCst);
+          Err::<BrokenPool, _>("disk is full")
+        }
+      })
+      .event("connections", connections)
+      .event("broken", broken)],
+  ));
+  let local_set = LocalSet::new();
+  assert_eq!(inits.load(Ordering::SeqCst), 0);
+
+  for _ in 0..3 {
+    let resp = local_set
+      .run_until(AFPluginDispatcher::async_send(
+        dispatch.as_ref(),
+        AFPluginRequest::new("connections"),
+      ))
+      .await;
+    assert_eq!(String::from_utf8_lossy(resp.payload.as_ref()), "4");
+  }
+  assert_eq!(inits.load(Ordering::SeqCst), 1);
+
+  for _ in 0..2 {
+    let resp = local_set
+      .run_until(AFPluginDispatcher::async_send(
+        dispatch.as_ref(),
+        AFPluginRequest::new("broken"),
+      ))
+      .await;
+    assert_eq!(resp.status_code, StatusCode::Err);
+    assert_eq!(resp.error_origin, Some(ErrorOrigin::Internal));
+    assert!(String::from_utf8_lossy(resp.payload.as_ref()).contains("disk is full"));
+  }
+  // The failures are not cached.
+  assert_eq!(broken_inits.load(Ordering::SeqCst), 2);
+
+  std::mem::forget(dispatch);
+}
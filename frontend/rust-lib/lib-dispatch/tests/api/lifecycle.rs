@@ -0,0 +1,123 @@
+use lib_dispatch::lifecycle::{LifecycleEvent, LifecycleObserver, BACKGROUND_STATE};
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::LocalSet;
+
+/// Records what it's told, and fails to flush if `broken`.
+#[derive(Clone, Default)]
+struct Journal {
+  calls: Arc<Mutex<Vec<String>>>,
+  broken: bool,
+}
+
+impl Journal {
+  fn calls(&self) -> Vec<String> {
+    self.calls.lock().unwrap().clone()
+  }
+}
+
+impl LifecycleObserver for Journal {
+  fn on_lifecycle(&self, event: LifecycleEvent) {
+    self.calls.lock().unwrap().push(format!("{:?}", event));
+  }
+
+  fn flush(&self) -> Result<(), DispatchError> {
+    self.calls.lock().unwrap().push("flush".to_string());
+    if self.broken {
+      return Err(DispatchError::from("disk full".to_string()));
+    }
+    Ok(())
+  }
+
+  fn trim(&self) {
+    self.calls.lock().unwrap().push("trim".to_string());
+  }
+}
+
+async fn sync_rows() -> String {
+  "synced".to_string()
+}
+
+#[tokio::test]
+async fn lifecycle_events_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  let journal = Journal::default();
+  let broken = Journal {
+    broken: true,
+    ..Default::default()
+  };
+  let dispatch = AFPluginDispatcher::new(
+    runtime,
+    vec![
+      AFPlugin::new()
+        .name("database")
+        .lifecycle_observer(journal.clone()),
+      AFPlugin::new()
+        .name("document")
+        .lifecycle_observer(broken.clone()),
+    ],
+  );
+
+  // The failure of an observer doesn't keep the other ones from flushing.
+  assert!(dispatch.enter_background().is_err());
+  assert!(dispatch.lifecycle().is_background());
+  assert!(dispatch.app_states().is_in(BACKGROUND_STATE));
+  assert_eq!(journal.calls(), vec!["EnterBackground", "flush"]);
+  assert_eq!(broken.calls(), vec!["EnterBackground", "flush"]);
+
+  dispatch.low_memory();
+  dispatch.enter_foreground();
+  assert!(!dispatch.lifecycle().is_background());
+  assert!(!dispatch.app_states().is_in(BACKGROUND_STATE));
+  assert_eq!(
+    journal.calls(),
+    vec![
+      "EnterBackground",
+      "flush",
+      "trim",
+      "LowMemory",
+      "EnterForeground"
+    ]
+  );
+
+  assert!(dispatch.unregister_plugin("database"));
+  dispatch.low_memory();
+  assert_eq!(journal.calls().len(), 5);
+}
+
+#[tokio::test]
+async fn low_priority_requests_wait_for_foreground_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event("sync_rows", sync_rows)],
+  ));
+  let local_set = LocalSet::new();
+
+  local_set
+    .run_until(async {
+      dispatch.enter_background().unwrap();
+      let cloned_dispatch = dispatch.clone();
+      let low = tokio::task::spawn_local(async move {
+        let request = AFPluginRequest::new("sync_rows").priority(RequestPriority::Low);
+        AFPluginDispatcher::async_send(cloned_dispatch.as_ref(), request).await
+      });
+
+      // The other requests still go through.
+      let resp =
+        AFPluginDispatcher::async_send(dispatch.as_ref(), AFPluginRequest::new("sync_rows")).await;
+      assert_eq!(resp.status_code, StatusCode::Ok);
+      tokio::time::sleep(Duration::from_millis(50)).await;
+      assert!(!low.is_finished());
+
+      dispatch.enter_foreground();
+      let resp = low.await.unwrap();
+      assert_eq!(resp.status_code, StatusCode::Ok);
+    })
+    .await;
+
+  std::mem::forget(dispatch);
+}
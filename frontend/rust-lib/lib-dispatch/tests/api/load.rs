@@ -0,0 +1,46 @@
+use lib_dispatch::load::LoadGenerator;
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::LocalSet;
+
+async fn hello() -> String {
+  "say hello".to_string()
+}
+
+#[tokio::test]
+async fn load_generator_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event("hello", hello)],
+  ));
+  let local_set = LocalSet::new();
+  let report = local_set
+    .run_until(
+      LoadGenerator::new()
+        .event("hello", 3)
+        // No plugin handles this event, every request fails.
+        .event("missing", 1)
+        .concurrency(4)
+        .rate(200.0)
+        .duration(Duration::from_millis(200))
+        .seed(7)
+        .run(dispatch.as_ref()),
+    )
+    .await;
+
+  let hello = &report.events["hello"];
+  let missing = &report.events["missing"];
+  assert_eq!(hello.errors, 0);
+  assert_eq!(missing.errors, missing.requests);
+  assert_eq!(report.overall.requests, hello.requests + missing.requests);
+  assert!(hello.requests > missing.requests);
+  // The rate caps the number of requests sent during the run.
+  assert!(report.overall.requests <= 41);
+  assert!(hello.p50 <= hello.max);
+
+  std::mem::forget(dispatch);
+}
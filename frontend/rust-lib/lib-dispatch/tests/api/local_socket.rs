@@ -0,0 +1,228 @@
+use lib_dispatch::bridge::{serve_unix_socket, MAX_IN_FLIGHT_REQUESTS};
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+use tokio::task::LocalSet;
+
+async fn hello(name: String) -> String {
+  format!("hello {}", name)
+}
+
+#[derive(Default)]
+struct Concurrency {
+  current: AtomicUsize,
+  max: AtomicUsize,
+}
+
+async fn slow(concurrency: AFPluginState<Arc<Concurrency>>) -> String {
+  let current = concurrency.current.fetch_add(1, Ordering::SeqCst) + 1;
+  concurrency.max.fetch_max(current, Ordering::SeqCst);
+  tokio::time::sleep(Duration::from_millis(10)).await;
+  concurrency.current.fetch_sub(1, Ordering::SeqCst);
+  "done".to_string()
+}
+
+struct TestSessions;
+
+impl SessionStore for TestSessions {
+  fn verify(&self, token: &str) -> Option<Identity> {
+    (token == "nathan-token").then(|| Identity {
+      user_id: "nathan".to_string(),
+      capabilities: None,
+    })
+  }
+}
+
+fn request_frame(id: u32, event: &str, payload: &[u8]) -> Vec<u8> {
+  let mut frame = id.to_be_bytes().to_vec();
+  frame.extend_from_slice(&(event.len() as u16).to_be_bytes());
+  frame.extend_from_slice(event.as_bytes());
+  frame.extend_from_slice(payload);
+  length_prefixed(frame)
+}
+
+fn tagged_request_frame(id: u32, event: &str, content_type: u8, payload: &[u8]) -> Vec<u8> {
+  let mut frame = id.to_be_bytes().to_vec();
+  frame.extend_from_slice(&(event.len() as u16 | 0x8000).to_be_bytes());
+  frame.extend_from_slice(event.as_bytes());
+  frame.push(content_type);
+  frame.extend_from_slice(payload);
+  length_prefixed(frame)
+}
+
+fn authorized_request_frame(id: u32, event: &str, token: &str, payload: &[u8]) -> Vec<u8> {
+  let mut frame = id.to_be_bytes().to_vec();
+  frame.extend_from_slice(&(event.len() as u16 | 0x2000).to_be_bytes());
+  frame.extend_from_slice(event.as_bytes());
+  frame.extend_from_slice(&(token.len() as u16).to_be_bytes());
+  frame.extend_from_slice(token.as_bytes());
+  frame.extend_from_slice(payload);
+  length_prefixed(frame)
+}
+
+fn length_prefixed(frame: Vec<u8>) -> Vec<u8> {
+  let mut framed = (frame.len() as u32).to_be_bytes().to_vec();
+  framed.extend_from_slice(&frame);
+  framed
+}
+
+#[tokio::test]
+async fn unix_socket_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event("hello", hello)],
+  ));
+  let path = std::env::temp_dir().join(format!("lib-dispatch-{}.sock", std::process::id()));
+  let _ = std::fs::remove_file(&path);
+
+  let local_set = LocalSet::new();
+  local_set.spawn_local(serve_unix_socket(path.clone(), dispatch.clone()));
+  local_set
+    .run_until(async {
+      tokio::task::yield_now().await;
+      let mut stream = UnixStream::connect(&path).await.unwrap();
+      // Two requests in flight on the same connection.
+      stream
+        .write_all(&request_frame(1, "hello", b"a"))
+        .await
+        .unwrap();
+      stream
+        .write_all(&request_frame(2, "hello", b"b"))
+        .await
+        .unwrap();
+
+      let mut responses = vec![];
+      for _ in 0..2 {
+        let mut len = [0u8; 4];
+        stream.read_exact(&mut len).await.unwrap();
+        let mut frame = vec![0; u32::from_be_bytes(len) as usize];
+        stream.read_exact(&mut frame).await.unwrap();
+        let id = u32::from_be_bytes([frame[1], frame[2], frame[3], frame[4]]);
+        responses.push((id, frame[5], frame[6..].to_vec()));
+      }
+      responses.sort();
+      assert_eq!(responses[0], (1, 0, b"hello a".to_vec()));
+      assert_eq!(responses[1], (2, 0, b"hello b".to_vec()));
+
+      // Tagged with the unknown content type, the response is still tagged.
+      stream
+        .write_all(&tagged_request_frame(3, "hello", 0, b"c"))
+        .await
+        .unwrap();
+      let mut len = [0u8; 4];
+      stream.read_exact(&mut len).await.unwrap();
+      let mut frame = vec![0; u32::from_be_bytes(len) as usize];
+      stream.read_exact(&mut frame).await.unwrap();
+      assert_eq!(frame[5], 0x80);
+      assert_eq!(&frame[7..], b"hello c");
+    })
+    .await;
+
+  let _ = std::fs::remove_file(&path);
+  std::mem::forget(dispatch);
+}
+
+#[tokio::test]
+async fn unix_socket_auth_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(runtime, vec![AFPlugin::new().event("hello", hello)])
+      .with_middleware(AuthMiddleware::new(Arc::new(TestSessions))),
+  );
+  let path = std::env::temp_dir().join(format!("lib-dispatch-auth-{}.sock", std::process::id()));
+  let _ = std::fs::remove_file(&path);
+
+  let local_set = LocalSet::new();
+  local_set.spawn_local(serve_unix_socket(path.clone(), dispatch.clone()));
+  local_set
+    .run_until(async {
+      tokio::task::yield_now().await;
+      let mut stream = UnixStream::connect(&path).await.unwrap();
+      // Answered one after the other, to tell the responses apart without their id.
+      let frames = [
+        request_frame(1, "hello", b"a"),
+        authorized_request_frame(2, "hello", "unknown", b"b"),
+        authorized_request_frame(3, "hello", "nathan-token", b"c"),
+      ];
+      let mut responses = vec![];
+      for frame in frames {
+        stream.write_all(&frame).await.unwrap();
+        let mut len = [0u8; 4];
+        stream.read_exact(&mut len).await.unwrap();
+        let mut frame = vec![0; u32::from_be_bytes(len) as usize];
+        stream.read_exact(&mut frame).await.unwrap();
+        responses.push((frame[5], frame[6..].to_vec()));
+      }
+      let reason = |payload: &[u8]| {
+        serde_json::from_slice::<Unauthorized>(payload)
+          .unwrap()
+          .reason
+      };
+      assert_eq!(responses[0].0, 1);
+      assert_eq!(reason(&responses[0].1), "missing token");
+      assert_eq!(responses[1].0, 1);
+      assert_eq!(reason(&responses[1].1), "invalid token");
+      assert_eq!(responses[2], (0, b"hello c".to_vec()));
+    })
+    .await;
+
+  let _ = std::fs::remove_file(&path);
+  std::mem::forget(dispatch);
+}
+
+#[tokio::test]
+async fn unix_socket_in_flight_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  let concurrency = Arc::new(Concurrency::default());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new()
+      .state(concurrency.clone())
+      .event("slow", slow)],
+  ));
+  let path = std::env::temp_dir().join(format!(
+    "lib-dispatch-in-flight-{}.sock",
+    std::process::id()
+  ));
+  let _ = std::fs::remove_file(&path);
+
+  let local_set = LocalSet::new();
+  local_set.spawn_local(serve_unix_socket(path.clone(), dispatch.clone()));
+  local_set
+    .run_until(async {
+      tokio::task::yield_now().await;
+      let stream = UnixStream::connect(&path).await.unwrap();
+      let (mut reader, mut writer) = stream.into_split();
+      let count = MAX_IN_FLIGHT_REQUESTS * 2;
+      let write = async {
+        for id in 0..count {
+          let frame = request_frame(id as u32, "slow", b"");
+          writer.write_all(&frame).await.unwrap();
+        }
+      };
+      let read = async {
+        for _ in 0..count {
+          let mut len = [0u8; 4];
+          reader.read_exact(&mut len).await.unwrap();
+          let mut frame = vec![0; u32::from_be_bytes(len) as usize];
+          reader.read_exact(&mut frame).await.unwrap();
+          assert_eq!(&frame[6..], b"done");
+        }
+      };
+      tokio::join!(write, read);
+    })
+    .await;
+
+  // The frames past the limit waited for a request to be answered.
+  assert!(concurrency.max.load(Ordering::SeqCst) <= MAX_IN_FLIGHT_REQUESTS);
+  let _ = std::fs::remove_file(&path);
+  std::mem::forget(dispatch);
+}
@@ -1 +1,93 @@
+mod alias;
+mod app_data;
+mod audit;
+mod auth;
+mod bridge;
+mod budget;
+mod cache;
+mod cancellation;
+mod capability;
+#[cfg(feature = "use_msgpack")]
+mod checkpoint;
+mod chrome_trace;
+#[cfg(feature = "use_capnp")]
+mod capnp;
+#[cfg(all(unix, feature = "cli"))]
+mod cli;
+mod clock;
+#[cfg(feature = "codegen")]
+mod codegen;
+mod config;
+#[cfg(feature = "testing")]
+mod coverage;
+mod crash;
+#[cfg(feature = "dashboard")]
+mod dashboard;
+mod diff;
+#[cfg(feature = "dylib_plugins")]
+mod dylib;
+mod encoding;
+mod executor;
+mod extensions;
+#[cfg(feature = "fault_injection")]
+mod fault;
+mod feature_flag;
+#[cfg(feature = "testing")]
+mod fixture;
+mod gate;
+mod health;
+#[cfg(feature = "use_flatbuffers")]
+mod flatbuffer;
+#[cfg(feature = "fuzz")]
+mod fuzz;
+#[cfg(feature = "grpc_bridge")]
+mod grpc;
+#[cfg(feature = "http_bridge")]
+mod http;
+mod keyed_state;
+mod lazy_state;
+mod lifecycle;
+#[cfg(feature = "load_generator")]
+mod load;
+#[cfg(unix)]
+mod local_socket;
+mod memory;
+mod metrics;
+#[cfg(feature = "mock")]
+mod mock;
 mod module;
+mod observable_state;
+mod pagination;
+mod payload_schema;
+mod pipeline;
+mod pool;
+mod probe;
+#[cfg(all(feature = "protobuf_lite", feature = "use_protobuf"))]
+mod proto_lite;
+#[cfg(not(target_arch = "wasm32"))]
+mod proxy;
+mod quota;
+mod reconfigure;
+mod request_builder;
+mod response_mapping;
+#[cfg(unix)]
+mod routing;
+mod runtime;
+mod saga;
+mod shared_state;
+#[cfg(feature = "request_signing")]
+mod signature;
+#[cfg(feature = "testing")]
+mod snapshot;
+mod state_snapshot;
+mod system;
+#[cfg(feature = "toml_config")]
+mod system_config;
+#[cfg(feature = "testing")]
+mod time_travel;
+mod transaction;
+#[cfg(feature = "use_protobuf")]
+mod typed_request;
+mod upload;
+#[cfg(feature = "ws_bridge")]
+mod websocket;
@@ -0,0 +1,62 @@
+use lib_dispatch::memory::MemoryUsage;
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use lib_dispatch::system::SysEvent;
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+async fn hello() -> String {
+  "say hello".to_string()
+}
+
+#[tokio::test]
+async fn memory_report_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new()
+      .event("hello", hello)
+      .memory_reporter("test.cache", || MemoryUsage::new(1024, 4))],
+  ));
+  dispatch
+    .memory()
+    .register("test.buffer", || MemoryUsage::of::<u64>(8));
+  let local_set = LocalSet::new();
+  local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new("hello"),
+    ))
+    .await;
+
+  let report = dispatch.memory_report();
+  assert_eq!(report.get("test.cache").unwrap().bytes, 1024);
+  assert_eq!(report.get("test.buffer").unwrap().items, 8);
+  assert_eq!(report.get("dispatch.recorder").unwrap().items, 1);
+  assert_eq!(report.get("dispatch.in_flight").unwrap().items, 0);
+  assert_eq!(
+    report.total_bytes,
+    report.subsystems.iter().map(|s| s.bytes).sum::<usize>()
+  );
+
+  assert!(dispatch.memory().unregister("test.buffer"));
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new(SysEvent::MemoryReport),
+    ))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  let json: serde_json::Value = serde_json::from_slice(resp.payload.as_ref()).unwrap();
+  let names = json["subsystems"]
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|s| s["name"].as_str().unwrap().to_owned())
+    .collect::<Vec<_>>();
+  assert!(names.contains(&"test.cache".to_owned()));
+  assert!(!names.contains(&"test.buffer".to_owned()));
+
+  std::mem::forget(dispatch);
+}
@@ -0,0 +1,50 @@
+use lib_dispatch::metrics::{
+  DISPATCH_ERRORS_TOTAL, DISPATCH_REQUESTS_TOTAL, UNREGISTERED_EVENT_LABEL,
+};
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+async fn hello() -> String {
+  "say hello".to_string()
+}
+
+#[tokio::test]
+async fn metrics_record_dispatched_events_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event("hello", hello)],
+  ));
+  let local_set = LocalSet::new();
+  local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new("hello"),
+    ))
+    .await;
+  local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new("unknown"),
+    ))
+    .await;
+
+  let metrics = dispatch.metrics();
+  let labels = [("event", "hello")];
+  assert_eq!(metrics.counter(DISPATCH_REQUESTS_TOTAL, &labels).get(), 1);
+  assert_eq!(metrics.counter(DISPATCH_ERRORS_TOTAL, &labels).get(), 0);
+
+  // The events no plugin handles share a label.
+  let labels = [("event", UNREGISTERED_EVENT_LABEL)];
+  assert_eq!(metrics.counter(DISPATCH_ERRORS_TOTAL, &labels).get(), 1);
+
+  let text = metrics.render_prometheus();
+  assert!(text.contains("dispatch_requests_total{event=\"hello\"} 1"));
+  assert!(text.contains("dispatch_duration_seconds_count{event=\"hello\"} 1"));
+  assert!(!text.contains("unknown"));
+
+  std::mem::forget(dispatch);
+}
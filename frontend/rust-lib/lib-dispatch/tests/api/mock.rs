@@ -0,0 +1,123 @@
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+async fn user_name() -> String {
+  "real user".to_string()
+}
+
+async fn workspace_name() -> String {
+  "real workspace".to_string()
+}
+
+async fn stub_user_name() -> String {
+  "stub user".to_string()
+}
+
+async fn send(
+  local_set: &LocalSet,
+  dispatch: &AFPluginDispatcher,
+  request: AFPluginRequest,
+) -> AFPluginEventResponse {
+  local_set
+    .run_until(AFPluginDispatcher::async_send(dispatch, request))
+    .await
+}
+
+fn payload_str(response: &AFPluginEventResponse) -> String {
+  String::from_utf8_lossy(response.payload.as_ref()).into_owned()
+}
+
+#[tokio::test]
+async fn mock_event_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(
+      runtime,
+      vec![AFPlugin::new()
+        .name("user")
+        .event("user_name", user_name)
+        .event("workspace_name", workspace_name)],
+    )
+    .with_mock(
+      "workspace_name",
+      ResponseBuilder::Ok().data("canned").build(),
+    ),
+  );
+  let local_set = LocalSet::new();
+
+  let resp = send(
+    &local_set,
+    &dispatch,
+    AFPluginRequest::new("workspace_name"),
+  )
+  .await;
+  assert_eq!(payload_str(&resp), "canned");
+
+  let mock = dispatch.mocks().mock_with("user_name", |request| {
+    let name = String::from_utf8_lossy(request.payload_bytes()).into_owned();
+    ResponseBuilder::Ok()
+      .data(format!("mocked {}", name))
+      .build()
+  });
+  let resp = send(
+    &local_set,
+    &dispatch,
+    AFPluginRequest::new("user_name").payload("nathan"),
+  )
+  .await;
+  assert_eq!(payload_str(&resp), "mocked nathan");
+  assert_eq!(mock.call_count(), 1);
+  assert_eq!(mock.payloads()[0].as_ref(), b"nathan");
+
+  // The events without a handler can be mocked too.
+  dispatch
+    .mocks()
+    .mock("unknown", ResponseBuilder::Err().data("stub").build());
+  let resp = send(&local_set, &dispatch, AFPluginRequest::new("unknown")).await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+  assert_eq!(payload_str(&resp), "stub");
+
+  dispatch.mocks().remove("user_name");
+  let resp = send(&local_set, &dispatch, AFPluginRequest::new("user_name")).await;
+  assert_eq!(payload_str(&resp), "real user");
+  assert_eq!(mock.call_count(), 1);
+
+  std::mem::forget(dispatch);
+}
+
+#[tokio::test]
+async fn replace_plugin_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(
+      runtime,
+      vec![AFPlugin::new()
+        .name("user")
+        .event("user_name", user_name)
+        .event("workspace_name", workspace_name)],
+    )
+    .replace_plugin(
+      AFPlugin::new()
+        .name("user")
+        .event("user_name", stub_user_name),
+    ),
+  );
+  let local_set = LocalSet::new();
+
+  let resp = send(&local_set, &dispatch, AFPluginRequest::new("user_name")).await;
+  assert_eq!(payload_str(&resp), "stub user");
+
+  let resp = send(
+    &local_set,
+    &dispatch,
+    AFPluginRequest::new("workspace_name"),
+  )
+  .await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+
+  std::mem::forget(dispatch);
+}
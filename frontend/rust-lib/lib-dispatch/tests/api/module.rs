@@ -32,3 +32,208 @@ async fn test() {
 
   std::mem::forget(dispatch);
 }
+
+#[tokio::test]
+async fn incompatible_version_test() {
+  let event = "1";
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event(event, hello).versions(event, 2..=3)],
+  ));
+  assert_eq!(
+    dispatch.schema(&event.into()).unwrap().versions,
+    Some(2..=3)
+  );
+
+  let local_set = LocalSet::new();
+  for (request, status_code) in [
+    (AFPluginRequest::new(event), StatusCode::Ok),
+    (AFPluginRequest::new(event).version(3), StatusCode::Ok),
+    (AFPluginRequest::new(event).version(1), StatusCode::Err),
+  ] {
+    let resp = local_set
+      .run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+      .await;
+    assert_eq!(resp.status_code, status_code);
+  }
+
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new(event).version(4),
+    ))
+    .await;
+  let msg = String::from_utf8_lossy(resp.payload.as_ref()).into_owned();
+  assert!(msg.contains("IncompatibleVersion"), "{}", msg);
+  assert!(msg.contains("2..=3"), "{}", msg);
+
+  std::mem::forget(dispatch);
+}
+
+#[derive(Debug, Clone)]
+struct HandlerError;
+
+impl lib_dispatch::Error for HandlerError {
+  fn as_response(&self) -> AFPluginEventResponse {
+    ResponseBuilder::Err().data("failed").build()
+  }
+}
+
+async fn fail() -> Result<String, HandlerError> {
+  Err(HandlerError)
+}
+
+#[tokio::test]
+async fn error_origin_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event("hello", hello).event("fail", fail)],
+  ));
+
+  let local_set = LocalSet::new();
+  for (event, status_code, origin) in [
+    ("hello", StatusCode::Ok, None),
+    ("fail", StatusCode::Err, None),
+    ("unknown", StatusCode::Err, Some(ErrorOrigin::Dispatcher)),
+  ] {
+    let resp = local_set
+      .run_until(AFPluginDispatcher::async_send(
+        dispatch.as_ref(),
+        AFPluginRequest::new(event),
+      ))
+      .await;
+    assert_eq!(resp.status_code, status_code, "{}", event);
+    assert_eq!(resp.error_origin, origin, "{}", event);
+  }
+
+  std::mem::forget(dispatch);
+}
+
+pub async fn goodbye() -> String {
+  "say goodbye".to_string()
+}
+
+#[tokio::test]
+async fn register_plugin_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().name("hello").event("hello", hello)],
+  ));
+  let local_set = LocalSet::new();
+  assert!(!dispatch.has_event(&"goodbye".into()));
+
+  dispatch
+    .register_plugin(AFPlugin::new().name("goodbye").event("goodbye", goodbye))
+    .unwrap();
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new("goodbye"),
+    ))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+
+  // An event can only be handled by one plugin.
+  assert!(dispatch
+    .register_plugin(AFPlugin::new().name("other").event("hello", goodbye))
+    .is_err());
+  assert!(dispatch
+    .register_plugin(AFPlugin::new().name("shared").shared_state(1u32))
+    .is_err());
+
+  assert!(dispatch.unregister_plugin("goodbye"));
+  assert!(!dispatch.unregister_plugin("goodbye"));
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new("goodbye"),
+    ))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+
+  std::mem::forget(dispatch);
+}
+
+#[tokio::test]
+async fn fast_event_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new()
+      .name("fast")
+      .event_fast("is_ready", || "ready".to_string())
+      .event_fast("fail", || -> Result<(), DispatchError> {
+        Err("not ready".to_string().into())
+      })],
+  ));
+  assert!(dispatch.schema(&"is_ready".into()).is_some());
+
+  let local_set = LocalSet::new();
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new("is_ready"),
+    ))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  assert_eq!(String::from_utf8_lossy(resp.payload.as_ref()), "ready");
+
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new("fail"),
+    ))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+
+  std::mem::forget(dispatch);
+}
+
+#[tokio::test]
+async fn inline_event_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  let dispatch = AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new()
+      .event_fast("inline", || "inline".to_string())
+      .inline("inline")
+      .event_fast("queued", || "queued".to_string())],
+  );
+
+  let resp = dispatch
+    .try_call_inline(AFPluginRequest::new("inline"))
+    .unwrap();
+  assert_eq!(String::from_utf8_lossy(resp.payload.as_ref()), "inline");
+  assert!(dispatch
+    .try_call_inline(AFPluginRequest::new("queued"))
+    .is_err());
+
+  // A mock must see the request, it goes through the queue.
+  #[cfg(feature = "mock")]
+  let dispatch = dispatch.with_mock("inline", ResponseBuilder::Ok().data("mocked").build());
+  #[cfg(feature = "mock")]
+  assert!(dispatch
+    .try_call_inline(AFPluginRequest::new("inline"))
+    .is_err());
+
+  std::mem::forget(dispatch);
+}
+
+#[test]
+fn event_interning_test() {
+  let event = AFPluginEvent::from("interned");
+  let same = AFPluginEvent::from("interned".to_string());
+  let other = AFPluginEvent::from("other");
+  assert_eq!(event, same);
+  assert_eq!(event.id(), same.id());
+  assert_ne!(event, other);
+  assert_eq!(event.as_str(), "interned");
+  assert_eq!(format!("{:?}", other), "AFPluginEvent(\"other\")");
+}
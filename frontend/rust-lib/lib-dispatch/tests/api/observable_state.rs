@@ -0,0 +1,69 @@
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::TryRecvError;
+use tokio::task::LocalSet;
+
+#[derive(Clone, Debug, PartialEq)]
+struct CurrentWorkspace {
+  id: String,
+}
+
+async fn open_workspace(
+  id: String,
+  workspace: AppData<ObservableState<CurrentWorkspace>>,
+) -> String {
+  workspace.set(CurrentWorkspace { id });
+  workspace.get().id.clone()
+}
+
+#[tokio::test]
+async fn observable_state_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(
+      runtime,
+      vec![AFPlugin::new().event("open_workspace", open_workspace)],
+    )
+    .observable_state(CurrentWorkspace {
+      id: "w1".to_string(),
+    }),
+  );
+  let mut changes = dispatch.state_bus().subscribe::<CurrentWorkspace>();
+  let local_set = LocalSet::new();
+
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new("open_workspace").payload("w2"),
+    ))
+    .await;
+  assert_eq!(String::from_utf8_lossy(resp.payload.as_ref()), "w2");
+
+  let change = changes.recv().await.unwrap();
+  assert_eq!(change.old.id, "w1");
+  assert_eq!(change.new.id, "w2");
+  assert!(matches!(changes.try_recv(), Err(TryRecvError::Empty)));
+
+  std::mem::forget(dispatch);
+}
+
+#[test]
+fn observable_state_update_test() {
+  let bus = StateBus::new();
+  let counter = bus.observable(1_i64);
+  let mut changes = counter.subscribe();
+  // Another state type is published on its own channel.
+  let mut names = bus.subscribe::<String>();
+
+  counter.update(|value| *value += 1);
+  counter.set(10);
+  assert_eq!(*counter.get(), 10);
+
+  let first = changes.try_recv().unwrap();
+  assert_eq!((*first.old, *first.new), (1, 2));
+  let second = changes.try_recv().unwrap();
+  assert_eq!((*second.old, *second.new), (2, 10));
+  assert!(matches!(names.try_recv(), Err(TryRecvError::Empty)));
+}
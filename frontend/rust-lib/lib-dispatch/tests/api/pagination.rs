@@ -0,0 +1,58 @@
+use lib_dispatch::pagination::{PageInfo, PageRequest, PageResponse, PAGE_LIMIT_METADATA};
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+async fn list_rows(page: PageRequest) -> PageResponse<Json<Vec<u32>>> {
+  page.paginate((0..120).collect()).map(Json)
+}
+
+fn rows(response: &AFPluginEventResponse) -> Vec<u32> {
+  serde_json::from_slice(response.payload.as_ref()).unwrap()
+}
+
+async fn send(dispatcher: &AFPluginDispatcher, request: AFPluginRequest) -> AFPluginEventResponse {
+  LocalSet::new()
+    .run_until(AFPluginDispatcher::async_send(dispatcher, request))
+    .await
+}
+
+#[tokio::test]
+async fn page_through_list_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  let dispatcher =
+    AFPluginDispatcher::new(runtime, vec![AFPlugin::new().event("list_rows", list_rows)]);
+
+  // Without page params, the first page of the default size.
+  let response = send(&dispatcher, AFPluginRequest::new("list_rows")).await;
+  assert_eq!(rows(&response), (0..50).collect::<Vec<_>>());
+
+  let response = send(
+    &dispatcher,
+    AFPluginRequest::new("list_rows").page(PageRequest::new(100, 30)),
+  )
+  .await;
+  assert_eq!(rows(&response), (100..120).collect::<Vec<_>>());
+  let info = PageInfo::from_response(&response);
+  assert_eq!(info.total, Some(120));
+  assert_eq!(info.next_cursor, None);
+
+  // Follow the cursors to the end of the list.
+  let mut all = vec![];
+  let mut page = Some(PageRequest::new(0, 45));
+  while let Some(request) = page.take() {
+    let response = send(&dispatcher, AFPluginRequest::new("list_rows").page(request)).await;
+    all.extend(rows(&response));
+    page = PageInfo::from_response(&response).next_page(45);
+  }
+  assert_eq!(all, (0..120).collect::<Vec<_>>());
+
+  let response = send(
+    &dispatcher,
+    AFPluginRequest::new("list_rows").metadata(PAGE_LIMIT_METADATA, "0"),
+  )
+  .await;
+  assert_eq!(response.status_code, StatusCode::Err);
+  assert!(response.metadata.is_empty());
+}
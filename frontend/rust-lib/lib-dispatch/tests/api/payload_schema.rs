@@ -0,0 +1,137 @@
+use lib_dispatch::payload_schema::{FieldError, InvalidPayload, PayloadSchema};
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+#[derive(Deserialize)]
+struct CreateRow {
+  view_id: String,
+  #[allow(dead_code)]
+  cells: Vec<String>,
+}
+
+async fn create_row(params: Json<CreateRow>) -> String {
+  params.into_inner().view_id
+}
+
+fn create_row_schema() -> PayloadSchema {
+  PayloadSchema::json(json!({
+    "type": "object",
+    "required": ["view_id", "cells"],
+    "properties": {
+      "view_id": { "type": "string", "minLength": 1 },
+      "cells": { "type": "array", "items": { "type": "string" } },
+      "position": { "enum": ["start", "end"] },
+    },
+    "additionalProperties": false,
+  }))
+}
+
+async fn send(dispatcher: &AFPluginDispatcher, request: AFPluginRequest) -> AFPluginEventResponse {
+  LocalSet::new()
+    .run_until(AFPluginDispatcher::async_send(dispatcher, request))
+    .await
+}
+
+fn json_request(payload: serde_json::Value) -> AFPluginRequest {
+  AFPluginRequest::new("create_row")
+    .payload(serde_json::to_vec(&payload).unwrap())
+    .content_type(ContentType::Json)
+}
+
+#[tokio::test]
+async fn json_payload_schema_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  let dispatcher = AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new()
+      .event("create_row", create_row)
+      .payload_schema("create_row", create_row_schema())],
+  );
+
+  let resp = send(
+    &dispatcher,
+    json_request(json!({ "view_id": "v1", "cells": ["a", "b"] })),
+  )
+  .await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  assert_eq!(resp.payload.as_ref(), b"v1");
+
+  // An older client missing a field, with a typo in another one.
+  let resp = send(
+    &dispatcher,
+    json_request(json!({ "view_id": "", "cells": ["a", 2], "positon": "end" })),
+  )
+  .await;
+  let mut invalid = InvalidPayload::from_response(&resp).unwrap();
+  assert_eq!(invalid.event, "create_row");
+  invalid.errors.sort_by(|a, b| a.path.cmp(&b.path));
+  assert_eq!(
+    invalid.errors,
+    vec![
+      FieldError {
+        path: "/cells/1".to_string(),
+        message: "expected string, got number".to_string(),
+      },
+      FieldError {
+        path: "/positon".to_string(),
+        message: "unexpected field".to_string(),
+      },
+      FieldError {
+        path: "/view_id".to_string(),
+        message: "shorter than 1".to_string(),
+      },
+    ]
+  );
+
+  let resp = send(&dispatcher, json_request(json!({ "cells": [] }))).await;
+  let invalid = InvalidPayload::from_response(&resp).unwrap();
+  assert_eq!(invalid.errors[0].path, "/view_id");
+  assert_eq!(invalid.errors[0].message, "missing");
+
+  // The MessagePack payloads are checked against the same schema.
+  #[cfg(feature = "use_msgpack")]
+  {
+    let request = AFPluginRequest::new("create_row")
+      .payload(rmp_serde::to_vec_named(&json!({ "view_id": 1, "cells": [] })).unwrap())
+      .content_type(ContentType::MessagePack);
+    let invalid = InvalidPayload::from_response(&send(&dispatcher, request).await).unwrap();
+    assert_eq!(
+      invalid.errors[0].to_string(),
+      "/view_id: expected string, got number"
+    );
+  }
+}
+
+#[cfg(feature = "use_protobuf")]
+#[tokio::test]
+async fn protobuf_payload_schema_test() {
+  use protobuf::well_known_types::Duration;
+  use protobuf::Message;
+
+  async fn sleep() -> String {
+    "slept".to_string()
+  }
+
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  let dispatcher = AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new()
+      .event("sleep", sleep)
+      .payload_schema("sleep", PayloadSchema::protobuf::<Duration>(&["seconds"]))],
+  );
+
+  let mut duration = Duration::new();
+  duration.set_seconds(5);
+  let request = AFPluginRequest::new("sleep").payload(duration.write_to_bytes().unwrap());
+  assert_eq!(send(&dispatcher, request).await.status_code, StatusCode::Ok);
+
+  let mut duration = Duration::new();
+  duration.set_nanos(5);
+  let request = AFPluginRequest::new("sleep").payload(duration.write_to_bytes().unwrap());
+  let invalid = InvalidPayload::from_response(&send(&dispatcher, request).await).unwrap();
+  assert_eq!(invalid.errors[0].path, "/seconds");
+}
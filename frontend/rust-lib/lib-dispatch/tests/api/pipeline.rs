@@ -0,0 +1,74 @@
+use lib_dispatch::pipeline::pipeline;
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+async fn upper(name: String) -> String {
+  name.to_uppercase()
+}
+
+async fn greet(name: String) -> String {
+  format!("hello {}", name)
+}
+
+async fn fail(_: String) -> Result<String, DispatchError> {
+  Err(DispatchError::from("failed".to_string()))
+}
+
+fn payload_str(response: &AFPluginEventResponse) -> String {
+  String::from_utf8_lossy(response.payload.as_ref()).into_owned()
+}
+
+#[tokio::test]
+async fn pipeline_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new()
+      .event("upper", upper)
+      .event("greet", greet)
+      .event("fail", fail)],
+  ));
+  let local_set = LocalSet::new();
+
+  let resp = local_set
+    .run_until(
+      dispatch.run_pipeline(
+        pipeline()
+          .input("appflowy")
+          .then("upper")
+          .map(|payload| {
+            let name = String::from_utf8_lossy(payload.as_ref()).into_owned();
+            Ok(Payload::from(format!("{}!", name)))
+          })
+          .then("greet"),
+      ),
+    )
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  assert_eq!(payload_str(&resp), "hello APPFLOWY!");
+
+  // The first error response stops the pipeline
+  let resp = local_set
+    .run_until(dispatch.run_pipeline(pipeline().input("appflowy").then("fail").then("greet")))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+  assert_eq!(payload_str(&resp), "failed");
+
+  let resp = local_set
+    .run_until(
+      dispatch.run_pipeline(
+        pipeline()
+          .input("appflowy")
+          .map(|_| Err(DispatchError::from("invalid name".to_string())))
+          .then("greet"),
+      ),
+    )
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+  assert_eq!(payload_str(&resp), "invalid name");
+
+  std::mem::forget(dispatch);
+}
@@ -0,0 +1,50 @@
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use lib_dispatch::util::pool::ObjectPool;
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+#[test]
+fn object_pool_test() {
+  let pool = ObjectPool::<Vec<u8>>::new(1);
+  let first = pool.take(vec![1]);
+  let address = &*first as *const Vec<u8>;
+  let second = pool.take(vec![2]);
+  assert_eq!(pool.idle(), 0);
+
+  drop(first);
+  drop(second);
+  // The pool is full once it holds `capacity` idle allocations.
+  assert_eq!(pool.idle(), 1);
+
+  let mut third = pool.take(vec![3]);
+  third.push(4);
+  assert_eq!(*third, vec![3, 4]);
+  assert_eq!(pool.idle(), 0);
+  // The allocation of the first object is reused.
+  assert_eq!(address, &*third as *const Vec<u8>);
+}
+
+async fn hello() -> String {
+  "say hello".to_string()
+}
+
+#[tokio::test]
+async fn dispatch_with_request_pool_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(runtime, vec![AFPlugin::new().event("hello", hello)])
+      .with_request_pool_capacity(1),
+  );
+  let local_set = LocalSet::new();
+  for _ in 0..10 {
+    let request = AFPluginRequest::new("hello");
+    let resp = local_set
+      .run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+      .await;
+    assert_eq!(resp.status_code, StatusCode::Ok);
+    assert_eq!(String::from_utf8_lossy(resp.payload.as_ref()), "say hello");
+  }
+  std::mem::forget(dispatch);
+}
@@ -0,0 +1,57 @@
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::LocalSet;
+
+async fn hello(name: String) -> String {
+  format!("hello {}", name)
+}
+
+#[derive(Clone, Default)]
+struct PhaseProbe {
+  phases: Arc<Mutex<Vec<String>>>,
+}
+
+impl DispatchProbe for PhaseProbe {
+  fn enter(&self, _request_id: &str, _event: &AFPluginEvent, phase: DispatchPhase) {
+    self.phases.lock().unwrap().push(format!("enter {}", phase));
+  }
+
+  fn exit(&self, _request_id: &str, _event: &AFPluginEvent, phase: DispatchPhase, _: Duration) {
+    self.phases.lock().unwrap().push(format!("exit {}", phase));
+  }
+}
+
+#[tokio::test]
+async fn probe_dispatch_phases_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  let probe = PhaseProbe::default();
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(runtime, vec![AFPlugin::new().event("hello", hello)])
+      .with_probe(probe.clone()),
+  );
+  let request = AFPluginRequest::new("hello").payload("world");
+  let resp = LocalSet::new()
+    .run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+
+  let phases = probe.phases.lock().unwrap().clone();
+  assert_eq!(
+    phases,
+    vec![
+      "enter queue_wait",
+      "exit queue_wait",
+      "enter extraction",
+      "exit extraction",
+      "enter handler",
+      "exit handler",
+      "enter serialization",
+      "exit serialization",
+    ]
+  );
+
+  std::mem::forget(dispatch);
+}
@@ -0,0 +1,328 @@
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use protobuf::well_known_types::{
+  Any, DoubleValue, Duration, Field, FieldMask, Field_Cardinality, Field_Kind, Option as PbOption,
+};
+use protobuf::Message;
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+// The well known types of protobuf, written by hand for the lite codec.
+
+#[derive(Debug, Default, PartialEq)]
+struct LiteDuration {
+  seconds: i64,
+  nanos: i32,
+}
+
+impl LiteMessage for LiteDuration {
+  fn write_to(&self, writer: &mut LiteWriter) {
+    writer.write_int64(1, self.seconds);
+    writer.write_int32(2, self.nanos);
+  }
+
+  fn merge_field(&mut self, field: u32, reader: &mut LiteReader) -> Result<(), LiteError> {
+    match field {
+      1 => self.seconds = reader.read_int64()?,
+      2 => self.nanos = reader.read_int32()?,
+      _ => reader.skip()?,
+    }
+    Ok(())
+  }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct LiteAny {
+  type_url: String,
+  value: Vec<u8>,
+}
+
+impl LiteMessage for LiteAny {
+  fn write_to(&self, writer: &mut LiteWriter) {
+    writer.write_string(1, &self.type_url);
+    writer.write_bytes(2, &self.value);
+  }
+
+  fn merge_field(&mut self, field: u32, reader: &mut LiteReader) -> Result<(), LiteError> {
+    match field {
+      1 => self.type_url = reader.read_string()?,
+      2 => self.value = reader.read_bytes()?,
+      _ => reader.skip()?,
+    }
+    Ok(())
+  }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct LiteOption {
+  name: String,
+  value: Option<LiteAny>,
+}
+
+impl LiteMessage for LiteOption {
+  fn write_to(&self, writer: &mut LiteWriter) {
+    writer.write_string(1, &self.name);
+    if let Some(value) = &self.value {
+      writer.write_message(2, value);
+    }
+  }
+
+  fn merge_field(&mut self, field: u32, reader: &mut LiteReader) -> Result<(), LiteError> {
+    match field {
+      1 => self.name = reader.read_string()?,
+      2 => self.value = Some(reader.read_message()?),
+      _ => reader.skip()?,
+    }
+    Ok(())
+  }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct LiteField {
+  kind: i32,
+  cardinality: i32,
+  number: i32,
+  name: String,
+  type_url: String,
+  oneof_index: i32,
+  packed: bool,
+  options: Vec<LiteOption>,
+  json_name: String,
+  default_value: String,
+}
+
+impl LiteMessage for LiteField {
+  fn write_to(&self, writer: &mut LiteWriter) {
+    writer.write_enum(1, self.kind);
+    writer.write_enum(2, self.cardinality);
+    writer.write_int32(3, self.number);
+    writer.write_string(4, &self.name);
+    writer.write_string(6, &self.type_url);
+    writer.write_int32(7, self.oneof_index);
+    writer.write_bool(8, self.packed);
+    writer.write_repeated_messages(9, &self.options);
+    writer.write_string(10, &self.json_name);
+    writer.write_string(11, &self.default_value);
+  }
+
+  fn merge_field(&mut self, field: u32, reader: &mut LiteReader) -> Result<(), LiteError> {
+    match field {
+      1 => self.kind = reader.read_enum()?,
+      2 => self.cardinality = reader.read_enum()?,
+      3 => self.number = reader.read_int32()?,
+      4 => self.name = reader.read_string()?,
+      6 => self.type_url = reader.read_string()?,
+      7 => self.oneof_index = reader.read_int32()?,
+      8 => self.packed = reader.read_bool()?,
+      9 => self.options.push(reader.read_message()?),
+      10 => self.json_name = reader.read_string()?,
+      11 => self.default_value = reader.read_string()?,
+      _ => reader.skip()?,
+    }
+    Ok(())
+  }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct LiteFieldMask {
+  paths: Vec<String>,
+}
+
+impl LiteMessage for LiteFieldMask {
+  fn write_to(&self, writer: &mut LiteWriter) {
+    writer.write_repeated_strings(1, &self.paths);
+  }
+
+  fn merge_field(&mut self, field: u32, reader: &mut LiteReader) -> Result<(), LiteError> {
+    match field {
+      1 => self.paths.push(reader.read_string()?),
+      _ => reader.skip()?,
+    }
+    Ok(())
+  }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct LiteDouble {
+  value: f64,
+}
+
+impl LiteMessage for LiteDouble {
+  fn write_to(&self, writer: &mut LiteWriter) {
+    writer.write_double(1, self.value);
+  }
+
+  fn merge_field(&mut self, field: u32, reader: &mut LiteReader) -> Result<(), LiteError> {
+    match field {
+      1 => self.value = reader.read_double()?,
+      _ => reader.skip()?,
+    }
+    Ok(())
+  }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct LiteRows {
+  ids: Vec<i64>,
+}
+
+impl LiteMessage for LiteRows {
+  fn write_to(&self, writer: &mut LiteWriter) {
+    writer.write_packed_int64(1, &self.ids);
+  }
+
+  fn merge_field(&mut self, field: u32, reader: &mut LiteReader) -> Result<(), LiteError> {
+    match field {
+      1 => reader.read_packed_int64(&mut self.ids)?,
+      _ => reader.skip()?,
+    }
+    Ok(())
+  }
+}
+
+/// Encodes `pb` with both codecs and checks that they produce the same bytes, and that each
+/// decodes what the other encoded.
+fn assert_wire_compatible<P, L>(pb: &P, lite: &L)
+where
+  P: Message + PartialEq,
+  L: LiteMessage + PartialEq + std::fmt::Debug,
+{
+  let pb_bytes = pb.write_to_bytes().unwrap();
+  assert_eq!(lite.encode(), pb_bytes);
+  assert_eq!(&L::decode(&pb_bytes).unwrap(), lite);
+  assert!(&P::parse_from_bytes(&lite.encode()).unwrap() == pb);
+}
+
+#[test]
+fn proto_lite_cross_codec_test() {
+  let mut duration = Duration::new();
+  duration.set_seconds(90_061);
+  duration.set_nanos(-5);
+  assert_wire_compatible(
+    &duration,
+    &LiteDuration {
+      seconds: 90_061,
+      nanos: -5,
+    },
+  );
+  assert_wire_compatible(&Duration::new(), &LiteDuration::default());
+
+  let mut any = Any::new();
+  any.set_type_url("type.googleapis.com/flowy.ViewPB".to_string());
+  any.set_value(vec![0, 1, 0xff]);
+  let mut option = PbOption::new();
+  option.set_name("deprecated".to_string());
+  option.set_value(any);
+  let mut field = Field::new();
+  field.set_kind(Field_Kind::TYPE_STRING);
+  field.set_cardinality(Field_Cardinality::CARDINALITY_REPEATED);
+  field.set_number(300);
+  field.set_name("view_ids".to_string());
+  field.set_packed(true);
+  field.mut_options().push(option);
+  field.mut_options().push(PbOption::new());
+  field.set_json_name("viewIds".to_string());
+  assert_wire_compatible(
+    &field,
+    &LiteField {
+      kind: Field_Kind::TYPE_STRING as i32,
+      cardinality: Field_Cardinality::CARDINALITY_REPEATED as i32,
+      number: 300,
+      name: "view_ids".to_string(),
+      packed: true,
+      options: vec![
+        LiteOption {
+          name: "deprecated".to_string(),
+          value: Some(LiteAny {
+            type_url: "type.googleapis.com/flowy.ViewPB".to_string(),
+            value: vec![0, 1, 0xff],
+          }),
+        },
+        LiteOption::default(),
+      ],
+      json_name: "viewIds".to_string(),
+      ..Default::default()
+    },
+  );
+
+  let mut mask = FieldMask::new();
+  mask.mut_paths().push("name".to_string());
+  mask.mut_paths().push(String::new());
+  assert_wire_compatible(
+    &mask,
+    &LiteFieldMask {
+      paths: vec!["name".to_string(), String::new()],
+    },
+  );
+
+  let mut double = DoubleValue::new();
+  double.set_value(-2.5);
+  assert_wire_compatible(&double, &LiteDouble { value: -2.5 });
+}
+
+#[test]
+fn proto_lite_decode_test() {
+  // The fields the message doesn't know are skipped.
+  let mut field = Field::new();
+  field.set_number(7);
+  field.set_name("id".to_string());
+  let duration = LiteDuration::decode(&field.write_to_bytes().unwrap()).unwrap();
+  assert_eq!(duration.seconds, 0);
+
+  // The repeated integers are read whether they are packed or not.
+  let rows = LiteRows {
+    ids: vec![1, -1, 1 << 40],
+  };
+  assert_eq!(LiteRows::decode(&rows.encode()).unwrap(), rows);
+  assert_eq!(LiteRows::decode(&[8, 3, 8, 4]).unwrap().ids, vec![3, 4]);
+
+  // Truncated payloads and mismatched wire types are errors.
+  let bytes = LiteAny {
+    type_url: "type.googleapis.com/flowy.ViewPB".to_string(),
+    value: vec![],
+  }
+  .encode();
+  assert!(LiteAny::decode(&bytes[..bytes.len() - 1]).is_err());
+  assert!(LiteDuration::decode(&bytes).is_err());
+}
+
+async fn double_duration(duration: ProtoLite<LiteDuration>) -> ProtoLite<LiteDuration> {
+  ProtoLite(LiteDuration {
+    seconds: duration.seconds * 2,
+    nanos: duration.nanos * 2,
+  })
+}
+
+#[tokio::test]
+async fn proto_lite_payload_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event("double", double_duration)],
+  ));
+  let local_set = LocalSet::new();
+
+  // A payload encoded by the protobuf runtime.
+  let mut duration = Duration::new();
+  duration.set_seconds(3);
+  duration.set_nanos(4);
+  let request = AFPluginRequest::new("double").payload(duration.write_to_bytes().unwrap());
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  assert_eq!(resp.content_type, Some(ContentType::Protobuf));
+  let doubled = Duration::parse_from_bytes(resp.payload.as_ref()).unwrap();
+  assert_eq!((doubled.get_seconds(), doubled.get_nanos()), (6, 8));
+
+  let request = AFPluginRequest::new("double")
+    .payload(Json(3).to_vec().unwrap())
+    .content_type(ContentType::Json);
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+
+  std::mem::forget(dispatch);
+}
@@ -0,0 +1,93 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures_util::future::BoxFuture;
+use lib_dispatch::prelude::*;
+use lib_dispatch::proxy::{
+  RemoteProxy, RemoteRequest, RemoteResponse, RemoteTransport, TransportError,
+};
+use lib_dispatch::runtime::AFPluginRuntime;
+use tokio::task::LocalSet;
+
+/// Answers the requests like a remote core would, recording the events it received.
+#[derive(Clone, Default)]
+struct MockTransport {
+  received: Arc<Mutex<Vec<String>>>,
+}
+
+impl RemoteTransport for MockTransport {
+  fn send(
+    &self,
+    request: RemoteRequest,
+  ) -> BoxFuture<'static, Result<RemoteResponse, TransportError>> {
+    self.received.lock().unwrap().push(request.event.clone());
+    Box::pin(async move {
+      match request.event.as_str() {
+        "echo" => Ok(RemoteResponse {
+          status_code: StatusCode::Ok,
+          payload: request.payload,
+          content_type: request.content_type,
+        }),
+        "reject" => Ok(RemoteResponse {
+          status_code: StatusCode::Err,
+          payload: Bytes::from_static(b"rejected"),
+          content_type: None,
+        }),
+        "hang" => std::future::pending().await,
+        _ => Err(TransportError("connection reset".to_string())),
+      }
+    })
+  }
+}
+
+fn payload_bytes(resp: &AFPluginEventResponse) -> Bytes {
+  match &resp.payload {
+    Payload::Bytes(bytes) => bytes.clone(),
+    Payload::None => Bytes::new(),
+  }
+}
+
+#[tokio::test]
+async fn remote_proxy_test() {
+  let transport = MockTransport::default();
+  let proxy = RemoteProxy::new(transport.clone()).timeout(Duration::from_millis(50));
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new()
+      .proxy("echo", proxy.clone())
+      .proxy("reject", proxy.clone())
+      .proxy("hang", proxy.clone())
+      .proxy("unreachable", proxy)],
+  ));
+  let local_set = LocalSet::new();
+  let send = |request: AFPluginRequest| {
+    local_set.run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+  };
+
+  let resp = send(AFPluginRequest::new("echo").payload(b"hello".to_vec())).await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  assert_eq!(resp.error_origin, None);
+  assert_eq!(payload_bytes(&resp), Bytes::from_static(b"hello"));
+
+  // The errors of the remote handlers are passed through as they are
+  let resp = send(AFPluginRequest::new("reject")).await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+  assert_eq!(resp.error_origin, None);
+  assert_eq!(payload_bytes(&resp), Bytes::from_static(b"rejected"));
+
+  let resp = send(AFPluginRequest::new("unreachable")).await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+  assert_eq!(resp.error_origin, Some(ErrorOrigin::Transport));
+  assert!(resp.error_origin.unwrap().is_retryable());
+
+  let resp = send(AFPluginRequest::new("hang")).await;
+  assert_eq!(resp.error_origin, Some(ErrorOrigin::Transport));
+
+  assert_eq!(
+    *transport.received.lock().unwrap(),
+    vec!["echo", "reject", "unreachable", "hang"]
+  );
+}
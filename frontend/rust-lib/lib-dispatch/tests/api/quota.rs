@@ -0,0 +1,92 @@
+use lib_dispatch::memory::MemoryUsage;
+use lib_dispatch::metrics::{MODULE_CPU_SECONDS, MODULE_THROTTLED_TOTAL};
+use lib_dispatch::prelude::*;
+use lib_dispatch::quota::{ModuleQuota, QuotaExceeded, QuotaResource};
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::LocalSet;
+
+async fn render() -> String {
+  // Hogs the runtime thread
+  std::thread::sleep(Duration::from_millis(20));
+  "rendered".to_string()
+}
+
+async fn ping() -> String {
+  "pong".to_string()
+}
+
+#[tokio::test]
+async fn module_quota_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(
+      runtime,
+      vec![
+        AFPlugin::new()
+          .name("editor")
+          .event("render", render)
+          .event("editor_ping", ping),
+        AFPlugin::new()
+          .name("cache")
+          .event("cache_ping", ping)
+          .memory_reporter("cache.entries", || MemoryUsage::new(2048, 8)),
+      ],
+    )
+    .with_quota(
+      "editor",
+      ModuleQuota::new().cpu_time(Duration::from_millis(10), Duration::from_secs(60)),
+    ),
+  );
+  let local_set = LocalSet::new();
+  let send = |event: &'static str| {
+    local_set.run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new(event),
+    ))
+  };
+
+  let resp = send("render").await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  let usage = dispatch.accounting().usage("editor");
+  assert!(usage.cpu_time >= Duration::from_millis(20));
+  assert_eq!(usage.in_flight, 0);
+  let cpu = dispatch
+    .metrics()
+    .histogram(MODULE_CPU_SECONDS, &[("module", "editor")]);
+  assert_eq!(cpu.count(), 1);
+
+  // The budget of the window is spent
+  let resp = send("editor_ping").await;
+  let err = QuotaExceeded::from_response(&resp).unwrap();
+  assert_eq!(err.module, "editor");
+  assert_eq!(err.resource, QuotaResource::CpuTime);
+  assert!(err.retry_after_ms.unwrap() > 0);
+  assert_eq!(dispatch.accounting().usage("editor").throttled, 1);
+  let throttled = dispatch
+    .metrics()
+    .counter(MODULE_THROTTLED_TOTAL, &[("module", "editor")]);
+  assert_eq!(throttled.get(), 1);
+
+  // The other modules aren't affected
+  let resp = send("cache_ping").await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  assert_eq!(dispatch.accounting().usage("cache").memory_bytes, 2048);
+
+  let accounting = dispatch.accounting();
+  accounting.set_quota("cache", ModuleQuota::new().max_memory_bytes(1024));
+  let resp = send("cache_ping").await;
+  assert_eq!(
+    QuotaExceeded::from_response(&resp).unwrap().resource,
+    QuotaResource::Memory
+  );
+  accounting.set_quota("cache", ModuleQuota::new().max_pending(0));
+  let resp = send("cache_ping").await;
+  assert_eq!(
+    QuotaExceeded::from_response(&resp).unwrap().resource,
+    QuotaResource::Pending
+  );
+  assert_eq!(accounting.modules(), vec!["cache", "editor"]);
+}
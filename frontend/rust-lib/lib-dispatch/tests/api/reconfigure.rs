@@ -0,0 +1,154 @@
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use lib_dispatch::system::{ConfigChange, LogLevels, SysEvent};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::LocalSet;
+
+async fn export() -> String {
+  tokio::time::sleep(Duration::from_millis(200)).await;
+  "exported".to_string()
+}
+
+#[tokio::test]
+async fn reconfigure_event_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().name("storage").event("export", export)],
+  ));
+  let changes = Arc::new(Mutex::new(vec![]));
+  let cloned_changes = changes.clone();
+  dispatch
+    .live_config()
+    .observe(move |change: &ConfigChange| {
+      cloned_changes.lock().unwrap().push(change.changed.clone())
+    });
+  let local_set = LocalSet::new();
+  let send = |request: AFPluginRequest| {
+    local_set.run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+  };
+  let reconfigure = |delta: &str| send(AFPluginRequest::new(SysEvent::Reconfigure).payload(delta));
+
+  let resp = send(AFPluginRequest::new("export")).await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+
+  // The new timeout applies to the next requests.
+  let resp = reconfigure(
+    r#"{"request_timeout_ms": 50, "log_level": "debug", "max_pending": {"storage": 3}}"#,
+  )
+  .await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  let change: serde_json::Value = serde_json::from_slice(resp.payload.as_ref()).unwrap();
+  assert_eq!(
+    change["changed"],
+    serde_json::json!(["log_level", "max_pending", "request_timeout_ms"])
+  );
+  assert_eq!(change["current"]["request_timeout_ms"], 50);
+  assert_eq!(dispatch.accounting().quota("storage").max_pending, Some(3));
+  let resp = send(AFPluginRequest::new("export")).await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+
+  // The invalid deltas are rejected as a whole.
+  for delta in [
+    r#"{"request_timeout_ms": null, "log_level": "loud"}"#,
+    r#"{"request_timeout_ms": null, "worker_threads": 2}"#,
+    r#"{"request_timeout_ms": null, "request_timeout": 10}"#,
+    r#"["request_timeout_ms"]"#,
+  ] {
+    let resp = reconfigure(delta).await;
+    assert_eq!(resp.status_code, StatusCode::Err, "{}", delta);
+  }
+  assert_eq!(dispatch.live_config().get().request_timeout_ms, Some(50));
+
+  // A null goes back to the default.
+  let resp = reconfigure(r#"{"request_timeout_ms": null, "max_pending": {"storage": null}}"#).await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  assert_eq!(dispatch.accounting().quota("storage").max_pending, None);
+  let resp = send(AFPluginRequest::new("export")).await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+
+  // The observers are told about the changes only.
+  let resp = reconfigure("{}").await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  assert_eq!(
+    *changes.lock().unwrap(),
+    vec![
+      vec!["log_level", "max_pending", "request_timeout_ms"],
+      vec!["max_pending", "request_timeout_ms"],
+    ]
+  );
+
+  std::mem::forget(dispatch);
+}
+
+#[tokio::test]
+async fn set_log_level_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().name("storage").event("export", export)],
+  ));
+  let changes = Arc::new(Mutex::new(vec![]));
+  let cloned_changes = changes.clone();
+  dispatch
+    .live_config()
+    .observe(move |change: &ConfigChange| {
+      cloned_changes.lock().unwrap().push(change.changed.clone())
+    });
+  let local_set = LocalSet::new();
+  let set_log_level = |change: &str| {
+    local_set.run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new(SysEvent::SetLogLevel).payload(change),
+    ))
+  };
+  let levels = |resp: AFPluginEventResponse| {
+    assert_eq!(resp.status_code, StatusCode::Ok);
+    serde_json::from_slice::<LogLevels>(resp.payload.as_ref()).unwrap()
+  };
+
+  let resp = set_log_level(r#"{"target": "flowy_folder", "level": "trace"}"#).await;
+  let resp = levels(resp);
+  assert_eq!(resp.default, None);
+  assert_eq!(resp.targets["flowy_folder"], "trace");
+  let resp = set_log_level(r#"{"target": "lib_dispatch::dispatcher", "level": "debug"}"#).await;
+  assert_eq!(levels(resp).targets.len(), 2);
+  let resp = set_log_level(r#"{"level": "warn"}"#).await;
+  assert_eq!(levels(resp).default.as_deref(), Some("warn"));
+  assert_eq!(
+    dispatch.live_config().get().log_level.as_deref(),
+    Some("warn")
+  );
+
+  // Without a level, the target goes back to the default one.
+  let resp = set_log_level(r#"{"target": "flowy_folder"}"#).await;
+  let resp = levels(resp);
+  assert_eq!(
+    resp.targets.keys().collect::<Vec<_>>(),
+    vec!["lib_dispatch::dispatcher"]
+  );
+
+  for change in [
+    r#"{"target": "flowy_folder", "level": "loud"}"#,
+    r#"{"target": "flowy_folder=trace,lib_dispatch", "level": "info"}"#,
+    r#"{"target": "", "level": "info"}"#,
+    r#"{"module": "flowy_folder", "level": "info"}"#,
+  ] {
+    let resp = set_log_level(change).await;
+    assert_eq!(resp.status_code, StatusCode::Err, "{}", change);
+  }
+  assert_eq!(
+    *changes.lock().unwrap(),
+    vec![
+      vec!["log_levels"],
+      vec!["log_levels"],
+      vec!["log_level"],
+      vec!["log_levels"],
+    ]
+  );
+
+  std::mem::forget(dispatch);
+}
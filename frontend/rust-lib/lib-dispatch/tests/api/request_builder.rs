@@ -0,0 +1,149 @@
+use lib_dispatch::clock::{Clock, MockClock};
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::LocalSet;
+
+#[derive(Default)]
+struct HandledOrder(Mutex<Vec<&'static str>>);
+
+async fn low(order: AppData<Arc<HandledOrder>>) {
+  order.0.lock().unwrap().push("low");
+}
+
+async fn normal(order: AppData<Arc<HandledOrder>>) {
+  order.0.lock().unwrap().push("normal");
+}
+
+async fn high(order: AppData<Arc<HandledOrder>>) {
+  order.0.lock().unwrap().push("high");
+}
+
+async fn hello() -> String {
+  "hello".to_string()
+}
+
+async fn stall() -> String {
+  std::future::pending().await
+}
+
+fn payload_str(response: &AFPluginEventResponse) -> String {
+  String::from_utf8_lossy(response.payload.as_ref()).into_owned()
+}
+
+#[test]
+fn request_builder_test() {
+  let deadline = Instant::now() + Duration::from_secs(5);
+  let request = AFPluginRequest::builder("hello")
+    .id("request_1")
+    .payload("payload")
+    .version(2)
+    .deadline(deadline)
+    .priority(RequestPriority::High)
+    .build();
+  assert_eq!(request.id, "request_1");
+  assert_eq!(request.payload_bytes(), b"payload");
+  assert_eq!(request.version, Some(2));
+  assert_eq!(request.deadline, Some(deadline));
+  assert_eq!(request.priority, RequestPriority::High);
+
+  let request = AFPluginRequest::new("hello");
+  assert_eq!(request.deadline, None);
+  assert_eq!(request.priority, RequestPriority::Normal);
+}
+
+#[tokio::test]
+async fn request_priority_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  let order = Arc::new(HandledOrder::default());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(
+      runtime,
+      vec![AFPlugin::new()
+        .event("low", low)
+        .event("normal", normal)
+        .event("high", high)],
+    )
+    .data(order.clone()),
+  );
+  let local_set = LocalSet::new();
+
+  // The builders are sent without being built
+  local_set
+    .run_until(async {
+      tokio::join!(
+        AFPluginDispatcher::async_send(
+          dispatch.as_ref(),
+          AFPluginRequest::builder("low").priority(RequestPriority::Low),
+        ),
+        AFPluginDispatcher::async_send(dispatch.as_ref(), AFPluginRequest::builder("normal")),
+        AFPluginDispatcher::async_send(
+          dispatch.as_ref(),
+          AFPluginRequest::builder("high").priority(RequestPriority::High),
+        ),
+      )
+    })
+    .await;
+  assert_eq!(*order.0.lock().unwrap(), vec!["high", "normal", "low"]);
+
+  std::mem::forget(dispatch);
+}
+
+#[tokio::test]
+async fn request_deadline_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  let clock = MockClock::new();
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(
+      runtime,
+      vec![AFPlugin::new().event("hello", hello).event("stall", stall)],
+    )
+    .with_clock(clock.clone()),
+  );
+  let local_set = LocalSet::new();
+
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::builder("hello").deadline(clock.now() + Duration::from_secs(1)),
+    ))
+    .await;
+  assert_eq!(payload_str(&resp), "hello");
+
+  // The deadline passed before the request was handled
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::builder("hello").deadline(clock.now()),
+    ))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+  assert_eq!(resp.error_origin, Some(ErrorOrigin::Dispatcher));
+  assert!(payload_str(&resp).contains("DeadlineExceeded"));
+
+  // The deadline passed while the request was handled
+  let (resp, _) = local_set
+    .run_until(async {
+      tokio::join!(
+        AFPluginDispatcher::async_send(
+          dispatch.as_ref(),
+          AFPluginRequest::builder("stall").deadline(clock.now() + Duration::from_secs(1)),
+        ),
+        async {
+          while clock.pending_sleeps() == 0 {
+            tokio::task::yield_now().await;
+          }
+          clock.advance(Duration::from_secs(2));
+        }
+      )
+    })
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+  assert!(payload_str(&resp).contains("while handled"));
+  assert!(dispatch.in_flight().is_empty());
+
+  std::mem::forget(dispatch);
+}
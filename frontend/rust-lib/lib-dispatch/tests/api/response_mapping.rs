@@ -0,0 +1,107 @@
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct View {
+  id: String,
+  name: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  internal_rev: Option<u64>,
+}
+
+async fn get_view() -> Json<View> {
+  Json(View {
+    id: "v1".to_string(),
+    name: "Roadmap".to_string(),
+    internal_rev: Some(7),
+  })
+}
+
+async fn list_views() -> Json<Vec<View>> {
+  Json(vec![
+    View {
+      id: "v1".to_string(),
+      name: "Roadmap".to_string(),
+      internal_rev: Some(7),
+    },
+    View {
+      id: "v2".to_string(),
+      name: "Backlog".to_string(),
+      internal_rev: Some(3),
+    },
+  ])
+}
+
+fn make_dispatcher() -> AFPluginDispatcher {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new()
+      .event("get_view", get_view)
+      .event("list_views", list_views)],
+  )
+}
+
+async fn send(dispatcher: &AFPluginDispatcher, event: &str) -> AFPluginEventResponse {
+  LocalSet::new()
+    .run_until(AFPluginDispatcher::async_send(
+      dispatcher,
+      AFPluginRequest::new(event.to_string()),
+    ))
+    .await
+}
+
+#[tokio::test]
+async fn strip_fields_and_deprecation_test() {
+  let dispatcher = make_dispatcher()
+    .with_response_mapper(
+      StripFields::new()
+        .strip("get_view", "internal_rev")
+        .strip("list_views", "internal_rev"),
+    )
+    .with_response_mapper(Deprecations::new().deprecate("get_view", "use get_view_v2"));
+
+  let resp = send(&dispatcher, "get_view").await;
+  let view: View = serde_json::from_slice(resp.payload.as_ref()).unwrap();
+  assert_eq!(view.internal_rev, None);
+  assert_eq!(view.name, "Roadmap");
+  assert_eq!(
+    resp.metadata.get(DEPRECATION_METADATA).map(String::as_str),
+    Some("use get_view_v2")
+  );
+
+  let resp = send(&dispatcher, "list_views").await;
+  let views: Vec<View> = serde_json::from_slice(resp.payload.as_ref()).unwrap();
+  assert!(views.iter().all(|view| view.internal_rev.is_none()));
+  assert!(resp.metadata.is_empty());
+}
+
+#[tokio::test]
+async fn size_limit_test() {
+  let dispatcher = make_dispatcher()
+    .with_response_mapper(SizeLimit::per_event().limit("list_views", 64))
+    // Mappers see the response rewritten by the previous ones.
+    .with_response_mapper(|_: &AFPluginRequest, mut response: AFPluginEventResponse| {
+      response
+        .metadata
+        .insert("size".to_string(), response.payload.as_ref().len().to_string());
+      response
+    });
+
+  let resp = send(&dispatcher, "get_view").await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+
+  let resp = send(&dispatcher, "list_views").await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+  let err = ResponseTooLarge::from_response(&resp).unwrap();
+  assert_eq!(err.event, "list_views");
+  assert_eq!(err.limit, 64);
+  assert!(err.size > 64);
+  assert_eq!(
+    resp.metadata.get("size"),
+    Some(&resp.payload.as_ref().len().to_string())
+  );
+}
@@ -0,0 +1,202 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use lib_dispatch::bridge::serve_unix_socket;
+use lib_dispatch::prelude::*;
+use lib_dispatch::proxy::{
+  RemoteEndpoint, RemoteProxy, RemoteRequest, RemoteResponse, RemoteTransport, TransportError,
+  UnixSocketTransport,
+};
+use lib_dispatch::runtime::AFPluginRuntime;
+use tokio::task::LocalSet;
+
+async fn remote_search(query: String) -> String {
+  format!("remote {}", query)
+}
+
+async fn stub_search(query: String) -> String {
+  format!("stub {}", query)
+}
+
+#[cfg(feature = "use_bincode")]
+#[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq)]
+struct SearchHits {
+  query: String,
+  rows: Vec<u64>,
+}
+
+#[cfg(feature = "use_bincode")]
+async fn remote_bincode_search(query: Bincode<String>) -> Bincode<SearchHits> {
+  Bincode(SearchHits {
+    query: query.into_inner(),
+    rows: vec![1, 3],
+  })
+}
+
+/// A helper process that can't be reached.
+#[derive(Clone, Default)]
+struct DownTransport {
+  calls: Arc<AtomicUsize>,
+}
+
+impl RemoteTransport for DownTransport {
+  fn send(
+    &self,
+    _request: RemoteRequest,
+  ) -> BoxFuture<'static, Result<RemoteResponse, TransportError>> {
+    self.calls.fetch_add(1, Ordering::SeqCst);
+    Box::pin(async { Err(TransportError("connection refused".to_string())) })
+  }
+}
+
+fn payload_string(resp: &AFPluginEventResponse) -> String {
+  String::from_utf8(resp.payload.as_ref().to_vec()).unwrap()
+}
+
+#[tokio::test]
+async fn route_to_helper_process_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let helper = Arc::new(AFPluginDispatcher::new(
+    runtime.clone(),
+    vec![AFPlugin::new().event("search", remote_search)],
+  ));
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event("search", stub_search)],
+  ));
+  let path = std::env::temp_dir().join(format!("lib-dispatch-routing-{}.sock", std::process::id()));
+  let _ = std::fs::remove_file(&path);
+  let endpoint = Arc::new(RemoteEndpoint::new(
+    "indexer",
+    RemoteProxy::new(UnixSocketTransport::new(&path)),
+  ));
+  dispatch.routes().route("search", endpoint.clone());
+
+  let local_set = LocalSet::new();
+  local_set.spawn_local(serve_unix_socket(path.clone(), helper.clone()));
+  local_set
+    .run_until(async {
+      tokio::task::yield_now().await;
+      let request = AFPluginRequest::new("search").payload("notes");
+      let resp = AFPluginDispatcher::async_send(dispatch.as_ref(), request).await;
+      assert_eq!(resp.status_code, StatusCode::Ok);
+      assert_eq!(payload_string(&resp), "remote notes");
+      assert!(endpoint.is_healthy());
+
+      assert!(dispatch.routes().unroute("search").is_some());
+      let request = AFPluginRequest::new("search").payload("notes");
+      let resp = AFPluginDispatcher::async_send(dispatch.as_ref(), request).await;
+      assert_eq!(payload_string(&resp), "stub notes");
+    })
+    .await;
+
+  let _ = std::fs::remove_file(&path);
+  std::mem::forget(helper);
+  std::mem::forget(dispatch);
+}
+
+#[tokio::test]
+async fn fail_over_to_local_stub_test() {
+  let transport = DownTransport::default();
+  let endpoint = Arc::new(
+    RemoteEndpoint::new("indexer", RemoteProxy::new(transport.clone()))
+      .max_failures(2)
+      .retry_after(Duration::from_millis(50)),
+  );
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event("search", stub_search)],
+  ));
+  dispatch.routes().route("search", endpoint.clone());
+  dispatch.routes().route("export", endpoint.clone());
+  let local_set = LocalSet::new();
+  let send = |event: &'static str| {
+    local_set.run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new(event).payload("notes"),
+    ))
+  };
+
+  // Each failed request is answered by the stub, until the endpoint is down
+  for _ in 0..2 {
+    assert_eq!(payload_string(&send("search").await), "stub notes");
+  }
+  assert!(!endpoint.is_healthy());
+  assert_eq!(transport.calls.load(Ordering::SeqCst), 2);
+  assert_eq!(payload_string(&send("search").await), "stub notes");
+  assert_eq!(transport.calls.load(Ordering::SeqCst), 2);
+
+  // The endpoint is tried again once `retry_after` elapsed
+  tokio::time::sleep(Duration::from_millis(60)).await;
+  assert_eq!(payload_string(&send("search").await), "stub notes");
+  assert_eq!(transport.calls.load(Ordering::SeqCst), 3);
+
+  // Without a local stub, the transport error is returned
+  tokio::time::sleep(Duration::from_millis(60)).await;
+  let resp = send("export").await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+  assert_eq!(resp.error_origin, Some(ErrorOrigin::Transport));
+}
+
+#[cfg(feature = "use_bincode")]
+#[tokio::test]
+async fn bincode_route_to_helper_process_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let helper = Arc::new(AFPluginDispatcher::new(
+    runtime.clone(),
+    vec![AFPlugin::new().event("search", remote_bincode_search)],
+  ));
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event("search", stub_search)],
+  ));
+  let path = std::env::temp_dir().join(format!("lib-dispatch-bincode-{}.sock", std::process::id()));
+  let _ = std::fs::remove_file(&path);
+  let endpoint = Arc::new(RemoteEndpoint::new(
+    "indexer",
+    RemoteProxy::new(UnixSocketTransport::new(&path)),
+  ));
+  dispatch.routes().route("search", endpoint);
+
+  let local_set = LocalSet::new();
+  local_set.spawn_local(serve_unix_socket(path.clone(), helper.clone()));
+  local_set
+    .run_until(async {
+      tokio::task::yield_now().await;
+      // The content type travels with the frames, both ways.
+      let request = AFPluginRequest::new("search")
+        .payload(Bincode("notes".to_string()).to_vec().unwrap())
+        .content_type(ContentType::Bincode);
+      let resp = AFPluginDispatcher::async_send(dispatch.as_ref(), request).await;
+      assert_eq!(resp.status_code, StatusCode::Ok);
+      assert_eq!(resp.content_type, Some(ContentType::Bincode));
+      let hits: SearchHits = bincode::deserialize(resp.payload.as_ref()).unwrap();
+      assert_eq!(
+        hits,
+        SearchHits {
+          query: "notes".to_string(),
+          rows: vec![1, 3],
+        }
+      );
+
+      // A payload tagged with another encoding is rejected by the helper.
+      let request = AFPluginRequest::new("search")
+        .payload(Json("notes").to_vec().unwrap())
+        .content_type(ContentType::Json);
+      let resp = AFPluginDispatcher::async_send(dispatch.as_ref(), request).await;
+      assert_eq!(resp.status_code, StatusCode::Err);
+    })
+    .await;
+
+  let _ = std::fs::remove_file(&path);
+  std::mem::forget(helper);
+  std::mem::forget(dispatch);
+}
@@ -0,0 +1,62 @@
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::{Arc, Mutex};
+use tokio::task::LocalSet;
+
+fn spawn_order(seed: u64) -> Vec<usize> {
+  let runtime = AFPluginRuntime::deterministic(seed).unwrap();
+  let order = Arc::new(Mutex::new(vec![]));
+  for i in 0..16 {
+    let order = order.clone();
+    runtime.spawn(async move {
+      tokio::task::yield_now().await;
+      order.lock().unwrap().push(i);
+    });
+  }
+  runtime.run_until_idle();
+  let order = order.lock().unwrap().clone();
+  order
+}
+
+#[test]
+fn deterministic_runtime_order_test() {
+  let order = spawn_order(42);
+  assert_eq!(order.len(), 16);
+  assert_eq!(order, spawn_order(42));
+  assert!((0..8).any(|seed| spawn_order(seed) != order));
+}
+
+async fn hello() -> String {
+  "hello".to_string()
+}
+
+#[test]
+fn deterministic_runtime_dispatch_test() {
+  let runtime = Arc::new(AFPluginRuntime::deterministic(7).unwrap());
+  assert_eq!(runtime.seed(), Some(7));
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime.clone(),
+    vec![AFPlugin::new().event("hello", hello)],
+  ));
+  let responses = Arc::new(Mutex::new(vec![]));
+  let local_set = LocalSet::new();
+  runtime.block_on(local_set.run_until(async {
+    for _ in 0..3 {
+      let dispatch = dispatch.clone();
+      let responses = responses.clone();
+      runtime.spawn_local(async move {
+        let response =
+          AFPluginDispatcher::async_send(dispatch.as_ref(), AFPluginRequest::new("hello")).await;
+        responses.lock().unwrap().push(response.status_code);
+      });
+    }
+    assert!(responses.lock().unwrap().is_empty());
+    runtime.idle().await;
+  }));
+  assert_eq!(
+    *responses.lock().unwrap(),
+    vec![StatusCode::Ok, StatusCode::Ok, StatusCode::Ok]
+  );
+  std::mem::forget(dispatch);
+}
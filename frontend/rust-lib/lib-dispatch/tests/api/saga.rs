@@ -0,0 +1,127 @@
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use lib_dispatch::saga::{Saga, SagaStep};
+use std::sync::{Arc, Mutex};
+use tokio::task::LocalSet;
+
+/// Stands for the objects created by the steps, e.g. the user, the workspace and the document.
+#[derive(Clone, Default)]
+struct Objects(Arc<Mutex<Vec<String>>>);
+
+impl Objects {
+  fn names(&self) -> Vec<String> {
+    self.0.lock().unwrap().clone()
+  }
+}
+
+async fn create(name: String, objects: AppData<Objects>) -> Result<String, DispatchError> {
+  if name.ends_with("invalid") {
+    return Err(DispatchError::from(format!("can't create {}", name)));
+  }
+  objects.0.lock().unwrap().push(name.clone());
+  Ok(name)
+}
+
+async fn delete(name: String, objects: AppData<Objects>) -> Result<(), DispatchError> {
+  let mut objects = objects.0.lock().unwrap();
+  match objects.iter().position(|object| object == &name) {
+    Some(index) => {
+      objects.remove(index);
+      Ok(())
+    },
+    None => Err(DispatchError::from(format!("{} not found", name))),
+  }
+}
+
+fn setup() -> (Arc<AFPluginDispatcher>, Objects) {
+  let objects = Objects::default();
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(
+      runtime,
+      vec![AFPlugin::new()
+        .event("create", create)
+        .event("delete", delete)],
+    )
+    .data(objects.clone()),
+  );
+  (dispatch, objects)
+}
+
+/// Creates the user, then its workspace, then the `document` in the workspace.
+fn sign_up_saga(document: &'static str) -> Saga {
+  let delete = |response: &AFPluginEventResponse| {
+    AFPluginRequest::new("delete").payload(response.payload.clone())
+  };
+  Saga::new("sign_up")
+    .step(SagaStep::new(AFPluginRequest::new("create").payload("user")).compensate_with(delete))
+    .step(
+      SagaStep::new(AFPluginRequest::new("create").payload("workspace")).compensate_with(delete),
+    )
+    .step(
+      SagaStep::deferred(move |responses| {
+        let workspace = String::from_utf8_lossy(responses[1].payload.as_ref()).into_owned();
+        AFPluginRequest::new("create").payload(format!("{}/{}", workspace, document))
+      })
+      .compensate_with(delete),
+    )
+}
+
+#[tokio::test]
+async fn saga_test() {
+  let (dispatch, objects) = setup();
+  let local_set = LocalSet::new();
+
+  let responses = local_set
+    .run_until(dispatch.run_saga(sign_up_saga("getting started")))
+    .await
+    .unwrap();
+  assert_eq!(responses.len(), 3);
+  assert_eq!(
+    objects.names(),
+    vec!["user", "workspace", "workspace/getting started"]
+  );
+
+  std::mem::forget(dispatch);
+}
+
+#[tokio::test]
+async fn saga_unwind_test() {
+  let (dispatch, objects) = setup();
+  let local_set = LocalSet::new();
+
+  let err = local_set
+    .run_until(dispatch.run_saga(sign_up_saga("invalid")))
+    .await
+    .unwrap_err();
+  assert_eq!(err.step, 2);
+  assert!(err.is_unwound());
+  assert!(err.to_string().contains("can't create workspace/invalid"));
+  assert!(objects.names().is_empty());
+
+  // The failed compensation doesn't stop the unwinding.
+  let saga = Saga::new("broken")
+    .step(
+      SagaStep::new(AFPluginRequest::new("create").payload("user"))
+        .compensate(AFPluginRequest::new("delete").payload("user")),
+    )
+    .step(
+      SagaStep::new(AFPluginRequest::new("create").payload("workspace"))
+        .compensate(AFPluginRequest::new("delete").payload("unknown")),
+    )
+    .step(SagaStep::new(
+      AFPluginRequest::new("create").payload("invalid"),
+    ));
+  let err = local_set
+    .run_until(dispatch.run_saga(saga))
+    .await
+    .unwrap_err();
+  assert_eq!(err.step, 2);
+  assert!(!err.is_unwound());
+  assert_eq!(err.failed_compensations.len(), 1);
+  assert_eq!(err.failed_compensations[0].0, 1);
+  assert_eq!(objects.names(), vec!["workspace"]);
+
+  std::mem::forget(dispatch);
+}
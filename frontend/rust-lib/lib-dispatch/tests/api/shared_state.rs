@@ -0,0 +1,81 @@
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+struct UserCache {
+  name: String,
+}
+
+struct CurrentUser {
+  id: i64,
+}
+
+async fn user_name(cache: AFPluginState<UserCache>) -> String {
+  cache.name.clone()
+}
+
+async fn folder_owner(user: Shared<CurrentUser>) -> String {
+  user.id.to_string()
+}
+
+async fn folder_user_cache(cache: AFPluginState<UserCache>) -> String {
+  cache.name.clone()
+}
+
+fn payload_str(response: &AFPluginEventResponse) -> String {
+  String::from_utf8_lossy(response.payload.as_ref()).into_owned()
+}
+
+#[tokio::test]
+async fn plugin_state_isolation_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![
+      AFPlugin::new()
+        .name("user")
+        .state(UserCache {
+          name: "nathan".to_string(),
+        })
+        .shared_state(CurrentUser { id: 7 })
+        .event("user_name", user_name),
+      AFPlugin::new()
+        .name("folder")
+        .event("folder_owner", folder_owner)
+        .event("folder_user_cache", folder_user_cache),
+    ],
+  ));
+  let local_set = LocalSet::new();
+  let send = |event: &'static str| {
+    local_set.run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new(event),
+    ))
+  };
+
+  assert_eq!(payload_str(&send("user_name").await), "nathan");
+  assert_eq!(payload_str(&send("folder_owner").await), "7");
+  // The plugin states are only visible to the handlers of their plugin.
+  assert_eq!(send("folder_user_cache").await.status_code, StatusCode::Err);
+
+  std::mem::forget(dispatch);
+}
+
+#[test]
+#[should_panic]
+fn duplicate_shared_state_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  let _ = AFPluginDispatcher::new(
+    runtime,
+    vec![
+      AFPlugin::new()
+        .name("a")
+        .shared_state(CurrentUser { id: 1 }),
+      AFPlugin::new()
+        .name("b")
+        .shared_state(CurrentUser { id: 2 }),
+    ],
+  );
+}
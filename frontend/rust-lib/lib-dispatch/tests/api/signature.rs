@@ -0,0 +1,90 @@
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use lib_dispatch::security::{KeyRing, RequestSigner};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task::LocalSet;
+
+async fn create_view(name: String, signed_by: Extension<SignedBy>) -> String {
+  format!("{} by {}", name, signed_by.into_inner().0)
+}
+
+async fn version() -> String {
+  "1.0".to_string()
+}
+
+fn remote(event: &str) -> AFPluginRequest {
+  AFPluginRequest::new(event).metadata(TRANSPORT_METADATA, "http")
+}
+
+fn reason(response: &AFPluginEventResponse) -> String {
+  Unauthorized::from_response(response).unwrap().reason
+}
+
+#[tokio::test]
+async fn signature_middleware_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  let keys = Arc::new(KeyRing::new().with_max_skew(Duration::from_secs(60)));
+  let hmac_signer = RequestSigner::hmac("desktop", b"shared secret");
+  let ed25519_signer = RequestSigner::ed25519("cli", &[7; 32]).unwrap();
+  keys.add_hmac_key("desktop", b"shared secret");
+  keys.add_ed25519_key("cli", &ed25519_signer.public_key().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(
+      runtime,
+      vec![AFPlugin::new()
+        .event("create_view", create_view)
+        .event("version", version)],
+    )
+    .with_middleware(SignatureMiddleware::new(keys.clone()).allow_unsigned("version")),
+  );
+  let local_set = LocalSet::new();
+  let send = |request: AFPluginRequest| {
+    local_set.run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+  };
+
+  for signer in [&hmac_signer, &ed25519_signer] {
+    let resp = send(signer.sign(remote("create_view").payload("board"))).await;
+    assert_eq!(resp.status_code, StatusCode::Ok);
+    assert_eq!(
+      String::from_utf8_lossy(resp.payload.as_ref()),
+      format!("board by {}", signer.key_id())
+    );
+  }
+
+  // The payload was changed after the request was signed.
+  let signed = hmac_signer.sign(remote("create_view").payload("board"));
+  let signature = signed.metadata[SIGNATURE_METADATA].clone();
+  let tampered = remote("create_view")
+    .payload("grid")
+    .metadata(SIGNATURE_METADATA, signature);
+  let resp = send(tampered).await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+  assert_eq!(reason(&resp), "invalid signature");
+
+  let now_ms = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .unwrap()
+    .as_millis() as u64;
+  let request = remote("create_view").payload("board");
+  let signature = hmac_signer.signature(&request, now_ms - 120_000);
+  let resp = send(request.metadata(SIGNATURE_METADATA, signature)).await;
+  assert_eq!(reason(&resp), "expired signature");
+
+  let resp = send(remote("create_view").payload("board")).await;
+  assert_eq!(reason(&resp), "missing signature");
+  let resp = send(remote("version")).await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  // The requests of the app itself don't need a signature.
+  let resp = send(AFPluginRequest::new("version")).await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+
+  // The removed keys are rejected, e.g. once rotated.
+  assert!(keys.remove_key("desktop"));
+  assert_eq!(keys.key_ids(), vec!["cli"]);
+  let resp = send(hmac_signer.sign(remote("create_view").payload("board"))).await;
+  assert_eq!(reason(&resp), "unknown signing key desktop");
+
+  std::mem::forget(dispatch);
+}
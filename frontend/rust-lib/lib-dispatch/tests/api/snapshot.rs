@@ -0,0 +1,73 @@
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use lib_dispatch::snapshot::{assert_snapshot, Snapshot, REDACTED, UPDATE_SNAPSHOTS_ENV};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::task::LocalSet;
+
+async fn profile() -> String {
+  let now = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .unwrap()
+    .as_nanos();
+  serde_json::json!({
+    "id": now.to_string(),
+    "name": "nathan",
+    "workspaces": [{ "workspace_id": now.to_string(), "created_at": now as u64, "name": "main" }],
+  })
+  .to_string()
+}
+
+async fn send(event: &str) -> AFPluginEventResponse {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event("profile", profile)],
+  ));
+  let response = LocalSet::new()
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new(event),
+    ))
+    .await;
+  std::mem::forget(dispatch);
+  response
+}
+
+#[tokio::test]
+async fn snapshot_match_test() {
+  let response = send("profile").await;
+  assert_snapshot(
+    concat!(
+      env!("CARGO_MANIFEST_DIR"),
+      "/tests/api/snapshots/profile.json"
+    ),
+    &response,
+  );
+
+  let document = Snapshot::new().keep("id").normalize(&response);
+  assert_ne!(document["payload"]["id"], REDACTED);
+  assert_eq!(document["payload"]["workspaces"][0]["created_at"], REDACTED);
+}
+
+#[tokio::test]
+async fn snapshot_mismatch_test() {
+  if std::env::var_os(UPDATE_SNAPSHOTS_ENV).is_some() {
+    return;
+  }
+  let path = std::env::temp_dir().join(format!("af_snapshot_{}.json", std::process::id()));
+  let _ = std::fs::remove_file(&path);
+  let snapshot = Snapshot::new();
+
+  // The first run writes the snapshot.
+  let response = send("profile").await;
+  assert!(snapshot.check(&path, &response).is_ok());
+  assert!(snapshot.check(&path, &response).is_ok());
+
+  let response = send("unknown").await;
+  let mismatch = snapshot.check(&path, &response).unwrap_err();
+  assert!(mismatch.expected.contains("nathan"));
+  assert!(mismatch.actual.contains("\"Err\""));
+  let _ = std::fs::remove_file(&path);
+}
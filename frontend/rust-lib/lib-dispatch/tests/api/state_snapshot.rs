@@ -0,0 +1,60 @@
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::{Arc, Mutex};
+use tokio::task::LocalSet;
+
+#[derive(Default)]
+struct Documents(Mutex<Vec<String>>);
+
+impl StateSnapshot for Documents {
+  type Snapshot = Vec<String>;
+
+  fn snapshot(&self) -> Self::Snapshot {
+    self.0.lock().unwrap().clone()
+  }
+
+  fn restore(&self, snapshot: Self::Snapshot) {
+    *self.0.lock().unwrap() = snapshot;
+  }
+}
+
+async fn create_document(name: String, documents: AFPluginState<Documents>) -> String {
+  let mut documents = documents.0.lock().unwrap();
+  documents.push(name);
+  documents.len().to_string()
+}
+
+#[tokio::test]
+async fn state_snapshot_restore_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new()
+      .snapshot_state(Documents::default())
+      .event("create_document", create_document)],
+  ));
+  let local_set = LocalSet::new();
+  let create = |name: &'static str| {
+    local_set.run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new("create_document").payload(name),
+    ))
+  };
+
+  create("baseline").await;
+  let baseline = dispatch.snapshot_states();
+  assert_eq!(baseline.len(), 1);
+
+  for _ in 0..2 {
+    create("first").await;
+    let resp = create("second").await;
+    assert_eq!(String::from_utf8_lossy(resp.payload.as_ref()), "3");
+    dispatch.restore_states(&baseline);
+  }
+
+  let resp = create("after restore").await;
+  assert_eq!(String::from_utf8_lossy(resp.payload.as_ref()), "2");
+
+  std::mem::forget(dispatch);
+}
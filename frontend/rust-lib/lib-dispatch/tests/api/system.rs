@@ -0,0 +1,160 @@
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use lib_dispatch::system::SysEvent;
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+async fn hello() -> String {
+  "say hello".to_string()
+}
+
+#[tokio::test]
+async fn dump_recorder_event_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event("hello", hello)],
+  ));
+  let local_set = LocalSet::new();
+  let request = AFPluginRequest::new("hello").payload("world");
+  local_set
+    .run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+    .await;
+
+  let records = dispatch.recorder().dump();
+  assert_eq!(records.len(), 1);
+  assert_eq!(records[0].event, "hello");
+  assert_eq!(records[0].payload_size, 5);
+  assert!(records[0].success);
+
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new(SysEvent::DumpRecorder),
+    ))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  let json: serde_json::Value = serde_json::from_slice(resp.payload.as_ref()).unwrap();
+  assert_eq!(json[0]["event"], "hello");
+
+  std::mem::forget(dispatch);
+}
+
+#[tokio::test]
+async fn inspect_event_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().name("test").event("hello", hello)],
+  ));
+  let local_set = LocalSet::new();
+  local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      // No plugin handles this event, so it ends with an error.
+      AFPluginRequest::new("missing"),
+    ))
+    .await;
+
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new(SysEvent::Inspect),
+    ))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  let json: serde_json::Value = serde_json::from_slice(resp.payload.as_ref()).unwrap();
+  assert_eq!(json["plugins"][0]["name"], "test");
+  assert_eq!(json["plugins"][0]["events"][0], "hello");
+  assert_eq!(json["queue_depth"], 0);
+  // The inspect request itself is the only one in flight.
+  assert_eq!(json["in_flight"].as_array().unwrap().len(), 1);
+  assert_eq!(json["in_flight"][0]["event"], "SysInspect");
+  assert!(json["num_workers"].as_u64().unwrap() > 0);
+  assert_eq!(json["recent_errors"].as_array().unwrap().len(), 1);
+  assert_eq!(json["recent_errors"][0]["event"], "missing");
+  assert!(dispatch.in_flight().is_empty());
+
+  std::mem::forget(dispatch);
+}
+
+async fn greet(name: String) -> String {
+  format!("hello {}", name)
+}
+
+#[tokio::test]
+async fn schema_event_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().name("test").event("greet", greet)],
+  ));
+  let schema = dispatch.schema(&"greet".into()).unwrap();
+  assert_eq!(schema.plugin, "test");
+  assert_eq!(schema.payload, std::any::type_name::<String>());
+  assert_eq!(schema.response, std::any::type_name::<String>());
+
+  let local_set = LocalSet::new();
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new(SysEvent::Schema).payload("greet"),
+    ))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  let json: serde_json::Value = serde_json::from_slice(resp.payload.as_ref()).unwrap();
+  assert_eq!(json["event"], "greet");
+
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new(SysEvent::Schema),
+    ))
+    .await;
+  let json: serde_json::Value = serde_json::from_slice(resp.payload.as_ref()).unwrap();
+  assert_eq!(json.as_array().unwrap().len(), dispatch.schemas().len());
+
+  std::mem::forget(dispatch);
+}
+
+#[tokio::test]
+async fn info_event_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(
+      runtime,
+      vec![AFPlugin::new()
+        .event("greet", greet)
+        .event("hello", hello)
+        .versions("greet", 1..=3)],
+    )
+    .with_crate_version("flowy-core", "0.4.2"),
+  );
+
+  let resp = LocalSet::new()
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new(SysEvent::Info),
+    ))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  let json: serde_json::Value = serde_json::from_slice(resp.payload.as_ref()).unwrap();
+  assert_eq!(json["crate_versions"]["flowy-core"], "0.4.2");
+  assert!(json["crate_versions"]["lib-dispatch"].is_string());
+  let features = json["features"].as_array().unwrap();
+  assert_eq!(
+    features.contains(&"use_protobuf".into()),
+    cfg!(feature = "use_protobuf")
+  );
+  let encodings = json["encodings"].as_array().unwrap();
+  assert!(encodings.contains(&"application/json".into()));
+  assert_eq!(json["event_versions"]["greet"]["start"], 1);
+  assert_eq!(json["event_versions"]["greet"]["end"], 3);
+  assert!(json["event_versions"].get("hello").is_none());
+
+  std::mem::forget(dispatch);
+}
@@ -0,0 +1,86 @@
+use lib_dispatch::config::SystemConfig;
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::LocalSet;
+
+async fn import_csv() -> String {
+  tokio::time::sleep(Duration::from_secs(5)).await;
+  "imported".to_string()
+}
+
+fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+  vars
+    .iter()
+    .map(|(key, value)| (key.to_string(), value.to_string()))
+    .collect()
+}
+
+#[test]
+fn system_config_load_test() {
+  let path = std::env::temp_dir().join(format!("dispatch-{}.toml", nanoid::nanoid!(6)));
+
+  // Without a file, the defaults.
+  let config = SystemConfig::load(&path, env(&[("HOME", "/root")])).unwrap();
+  assert_eq!(config, SystemConfig::default());
+
+  std::fs::write(
+    &path,
+    "worker_threads = 2\nrequest_timeout_ms = 100\n\n[max_pending]\ndatabase = 8\n",
+  )
+  .unwrap();
+  let config = SystemConfig::load(
+    &path,
+    env(&[
+      ("APPFLOWY_DISPATCH_WORKER_THREADS", "4"),
+      ("APPFLOWY_DISPATCH_LOG_LEVEL", "debug"),
+    ]),
+  )
+  .unwrap();
+  assert_eq!(config.worker_threads, Some(4));
+  assert_eq!(config.request_timeout(), Some(Duration::from_millis(100)));
+  assert_eq!(config.log_level.as_deref(), Some("debug"));
+  assert_eq!(config.max_pending.get("database"), Some(&8));
+
+  // All the invalid values are reported at once.
+  let err = SystemConfig::load(
+    &path,
+    env(&[
+      ("APPFLOWY_DISPATCH_WORKER_THREADS", "four"),
+      ("APPFLOWY_DISPATCH_QUEUE_SIZE", "8"),
+    ]),
+  )
+  .unwrap_err();
+  assert_eq!(err.errors.len(), 2);
+
+  let err = SystemConfig::from_toml("worker_threads = 0\nlog_level = \"loud\"").unwrap_err();
+  assert_eq!(err.errors.len(), 2);
+  assert!(SystemConfig::from_toml("worker_thread = 2").is_err());
+  std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn system_config_timeout_test() {
+  let config = SystemConfig::from_toml("worker_threads = 1\nrequest_timeout_ms = 50").unwrap();
+  let runtime = Arc::new(AFPluginRuntime::with_config(&config).unwrap());
+  assert_eq!(runtime.num_workers(), 1);
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(
+      runtime,
+      vec![AFPlugin::new().event("import_csv", import_csv)],
+    )
+    .with_system_config(&config),
+  );
+
+  let resp = LocalSet::new()
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new("import_csv"),
+    ))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+
+  std::mem::forget(dispatch);
+}
@@ -0,0 +1,113 @@
+use lib_dispatch::fixture::FixtureRecorder;
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use lib_dispatch::time_travel::TimeTravel;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use tokio::task::LocalSet;
+
+#[derive(Default)]
+struct Documents(Mutex<Vec<String>>);
+
+impl StateSnapshot for Documents {
+  type Snapshot = Vec<String>;
+
+  fn snapshot(&self) -> Self::Snapshot {
+    self.0.lock().unwrap().clone()
+  }
+
+  fn restore(&self, snapshot: Self::Snapshot) {
+    *self.0.lock().unwrap() = snapshot;
+  }
+
+  fn inspect(&self) -> serde_json::Value {
+    json!(*self.0.lock().unwrap())
+  }
+}
+
+async fn create_document(name: String, documents: AFPluginState<Documents>) -> String {
+  let mut documents = documents.0.lock().unwrap();
+  documents.push(name);
+  documents.len().to_string()
+}
+
+async fn clear_documents(documents: AFPluginState<Documents>) {
+  documents.0.lock().unwrap().clear();
+}
+
+fn documents(time_travel: &TimeTravel) -> serde_json::Value {
+  time_travel
+    .inspect()
+    .into_iter()
+    .find(|(name, _)| name.ends_with("Documents"))
+    .unwrap()
+    .1
+}
+
+#[tokio::test]
+async fn time_travel_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  let recorder = FixtureRecorder::new();
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(
+      runtime,
+      vec![AFPlugin::new()
+        .snapshot_state(Documents::default())
+        .event("create_document", create_document)
+        .event("clear_documents", clear_documents)],
+    )
+    .with_middleware(recorder.clone()),
+  );
+  let local_set = LocalSet::new();
+  let send = |request: AFPluginRequest| {
+    local_set.run_until(AFPluginDispatcher::async_send(dispatch.as_ref(), request))
+  };
+
+  // Record the history of the app.
+  send(AFPluginRequest::new("create_document").payload("draft")).await;
+  let origin = dispatch.snapshot_states();
+  recorder.start();
+  for name in ["a", "b", "c"] {
+    send(AFPluginRequest::new("create_document").payload(name)).await;
+  }
+  send(AFPluginRequest::new("clear_documents")).await;
+  send(AFPluginRequest::new("create_document").payload("d")).await;
+  recorder.stop();
+
+  local_set
+    .run_until(async {
+      let mut time_travel = TimeTravel::new(dispatch.as_ref(), origin, recorder.take_fixture())
+        .with_checkpoint_interval(2);
+      assert_eq!(time_travel.len(), 5);
+      assert_eq!(documents(&time_travel), json!(["draft"]));
+
+      let step = time_travel.step().await.unwrap();
+      assert_eq!((step.index, step.event.as_str()), (0, "create_document"));
+      assert!(!step.is_divergent());
+      assert_eq!(documents(&time_travel), json!(["draft", "a"]));
+
+      let steps = time_travel.run_to_event("clear_documents").await.unwrap();
+      assert_eq!(steps.len(), 3);
+      assert_eq!(time_travel.position(), 4);
+      assert_eq!(documents(&time_travel), json!([]));
+
+      // Going back replays from the closest checkpoint.
+      let steps = time_travel.step_back().await.unwrap();
+      assert_eq!(steps.len(), 1);
+      assert_eq!(documents(&time_travel), json!(["draft", "a", "b", "c"]));
+      time_travel.run_to(5).await.unwrap();
+      assert!(time_travel.is_at_end());
+      assert!(time_travel.step().await.is_none());
+      assert_eq!(documents(&time_travel), json!(["d"]));
+
+      time_travel.rewind().await.unwrap();
+      assert_eq!(documents(&time_travel), json!(["draft"]));
+      assert!(time_travel.step_back().await.is_err());
+      assert!(time_travel.run_to(6).await.is_err());
+      assert!(time_travel.run_to_event("delete_document").await.is_err());
+    })
+    .await;
+
+  std::mem::forget(dispatch);
+}
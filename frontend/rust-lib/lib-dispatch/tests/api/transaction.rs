@@ -0,0 +1,165 @@
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use lib_dispatch::transaction::{Transaction, TransactionStatus};
+use std::sync::{Arc, Mutex};
+use tokio::task::LocalSet;
+
+/// Stands for the rows written to sqlite.
+#[derive(Clone, Default)]
+struct Rows(Arc<Mutex<Vec<String>>>);
+
+impl Rows {
+  fn names(&self) -> Vec<String> {
+    self.0.lock().unwrap().clone()
+  }
+}
+
+async fn add_row(
+  name: String,
+  transaction: Transaction,
+  rows: AppData<Rows>,
+  count: AppData<ObservableState<usize>>,
+) -> Result<String, DispatchError> {
+  transaction.enlist(count.as_ref());
+  count.update(|count| *count += 1);
+
+  rows.0.lock().unwrap().push(name.clone());
+  let unique_rows = rows.get_ref().clone();
+  transaction.on_prepare(move || {
+    let mut names = unique_rows.names();
+    names.sort();
+    names.dedup();
+    if names.len() != unique_rows.names().len() {
+      return Err(DispatchError::from("duplicated row".to_string()));
+    }
+    Ok(())
+  });
+  let rows = rows.get_ref().clone();
+  transaction.on_rollback(move || {
+    rows.0.lock().unwrap().pop();
+  });
+
+  if name == "invalid" {
+    return Err(DispatchError::from("invalid row".to_string()));
+  }
+  Ok(name)
+}
+
+#[tokio::test]
+async fn transaction_test() {
+  let rows = Rows::default();
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  let dispatcher =
+    AFPluginDispatcher::new(runtime, vec![AFPlugin::new().event("add_row", add_row)])
+      .data(rows.clone())
+      .observable_state(0_usize);
+  let bus = dispatcher.state_bus();
+  let mut counts = bus.subscribe::<usize>();
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(dispatcher);
+  let local_set = LocalSet::new();
+
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new("add_row").payload("a"),
+    ))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Ok);
+  assert_eq!(rows.names(), vec!["a"]);
+  assert_eq!(*counts.recv().await.unwrap().new, 1);
+
+  // The failed handler reverts both the rows and the count.
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new("add_row").payload("invalid"),
+    ))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+  assert_eq!(rows.names(), vec!["a"]);
+  assert_eq!(*counts.recv().await.unwrap().new, 2);
+  let reverted = counts.recv().await.unwrap();
+  assert_eq!((*reverted.old, *reverted.new), (2, 1));
+
+  std::mem::forget(dispatch);
+}
+
+#[tokio::test]
+async fn dispatch_transaction_test() {
+  let rows = Rows::default();
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(runtime, vec![AFPlugin::new().event("add_row", add_row)])
+      .data(rows.clone())
+      .observable_state(0_usize),
+  );
+  let local_set = LocalSet::new();
+  let add_rows = |names: &[&str]| {
+    names
+      .iter()
+      .map(|name| AFPluginRequest::new("add_row").payload(*name))
+      .collect::<Vec<_>>()
+  };
+
+  let responses = local_set
+    .run_until(dispatch.dispatch_transaction(add_rows(&["a", "b"])))
+    .await
+    .unwrap();
+  assert_eq!(responses.len(), 2);
+  assert_eq!(rows.names(), vec!["a", "b"]);
+
+  // The failed request reverts the rows added before it, the ones after it aren't sent.
+  let err = local_set
+    .run_until(dispatch.dispatch_transaction(add_rows(&["c", "invalid", "d"])))
+    .await
+    .unwrap_err();
+  assert!(err.to_string().contains("invalid row"), "{}", err);
+  assert_eq!(rows.names(), vec!["a", "b"]);
+
+  // Both requests succeed, but the prepare vetoes the commit.
+  let err = local_set
+    .run_until(dispatch.dispatch_transaction(add_rows(&["c", "c"])))
+    .await
+    .unwrap_err();
+  assert!(err.to_string().contains("duplicated row"), "{}", err);
+  assert_eq!(rows.names(), vec!["a", "b"]);
+
+  // The prepare of a single request vetoes it as well.
+  let resp = local_set
+    .run_until(AFPluginDispatcher::async_send(
+      dispatch.as_ref(),
+      AFPluginRequest::new("add_row").payload("a"),
+    ))
+    .await;
+  assert_eq!(resp.status_code, StatusCode::Err);
+  assert_eq!(rows.names(), vec!["a", "b"]);
+
+  std::mem::forget(dispatch);
+}
+
+#[test]
+fn transaction_callbacks_order_test() {
+  let calls = Arc::new(Mutex::new(vec![]));
+  let transaction = Transaction::begin();
+  for i in 0..3 {
+    let calls = calls.clone();
+    transaction.on_rollback(move || calls.lock().unwrap().push(i));
+  }
+  transaction.rollback();
+  assert_eq!(*calls.lock().unwrap(), vec![2, 1, 0]);
+  assert_eq!(transaction.status(), TransactionStatus::RolledBack);
+
+  // A finished transaction can't be committed.
+  transaction.commit();
+  assert_eq!(transaction.status(), TransactionStatus::RolledBack);
+
+  // Dropping an active transaction rolls it back.
+  let dropped = Arc::new(Mutex::new(false));
+  let transaction = Transaction::begin();
+  let cloned = dropped.clone();
+  transaction.on_rollback(move || *cloned.lock().unwrap() = true);
+  drop(transaction);
+  assert!(*dropped.lock().unwrap());
+}
@@ -0,0 +1,117 @@
+use bytes::Bytes;
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use protobuf::ProtobufError;
+use std::convert::TryFrom;
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+#[derive(Debug, PartialEq)]
+struct Name(String);
+
+impl TryFrom<Bytes> for Name {
+  type Error = ProtobufError;
+
+  fn try_from(bytes: Bytes) -> Result<Self, Self::Error> {
+    String::from_utf8(bytes.to_vec())
+      .map(Name)
+      .map_err(|e| ProtobufError::Utf8(e.utf8_error()))
+  }
+}
+
+impl TryFrom<Name> for Bytes {
+  type Error = ProtobufError;
+
+  fn try_from(name: Name) -> Result<Self, Self::Error> {
+    Ok(Bytes::from(name.0))
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct NameError(String);
+
+impl lib_dispatch::Error for NameError {
+  fn as_response(&self) -> AFPluginEventResponse {
+    ResponseBuilder::Err().data(self.0.clone()).build()
+  }
+}
+
+impl TryFrom<Bytes> for NameError {
+  type Error = ProtobufError;
+
+  fn try_from(bytes: Bytes) -> Result<Self, Self::Error> {
+    Ok(NameError(String::from_utf8_lossy(&bytes).into_owned()))
+  }
+}
+
+async fn greet(data: AFPluginData<Name>) -> DataResult<Name, NameError> {
+  let name = data.into_inner();
+  if name.0.is_empty() {
+    return Err(NameError("empty name".to_owned()));
+  }
+  data_result_ok(Name(format!("hello {}", name.0)))
+}
+
+async fn reset() {}
+
+#[tokio::test]
+async fn typed_request_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event("greet", greet).event("reset", reset)],
+  ));
+  let local_set = LocalSet::new();
+
+  let greeting = local_set
+    .run_until(dispatch.request::<Name, _, _>("greet", Name("appflowy".to_owned())))
+    .await
+    .unwrap();
+  assert_eq!(greeting, Name("hello appflowy".to_owned()));
+
+  let empty = local_set
+    .run_until(dispatch.request::<Empty, _, _>("reset", Empty))
+    .await
+    .unwrap();
+  assert_eq!(empty, Empty);
+
+  // The handler returned no data
+  let err = local_set
+    .run_until(dispatch.request::<Name, _, _>("reset", Empty))
+    .await
+    .unwrap_err();
+  assert_eq!(err.origin(), Some(ErrorOrigin::Dispatcher));
+
+  std::mem::forget(dispatch);
+}
+
+#[tokio::test]
+async fn typed_request_error_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event("greet", greet)],
+  ));
+  let local_set = LocalSet::new();
+
+  let err = local_set
+    .run_until(dispatch.request::<Name, _, _>("greet", Name(String::new())))
+    .await
+    .unwrap_err();
+  assert_eq!(err.origin(), None);
+  assert_eq!(
+    err.handler_error::<NameError>(),
+    Some(NameError("empty name".to_owned()))
+  );
+
+  let err = local_set
+    .run_until(dispatch.request::<Name, _, _>("unknown", Empty))
+    .await
+    .unwrap_err();
+  assert_eq!(err.origin(), Some(ErrorOrigin::Dispatcher));
+  assert!(err.handler_error::<NameError>().is_none());
+
+  std::mem::forget(dispatch);
+}
@@ -0,0 +1,75 @@
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use lib_dispatch::system::SysEvent;
+use lib_dispatch::upload::{encode_chunk, PayloadStream};
+use std::sync::Arc;
+use tokio::task::LocalSet;
+
+async fn import_text(upload: PayloadStream) -> String {
+  String::from_utf8(upload.read_to_end().await.to_vec()).unwrap()
+}
+
+async fn import_first_chunk(mut upload: PayloadStream) -> String {
+  let chunk = upload.next_chunk().await.unwrap_or_default();
+  String::from_utf8(chunk.to_vec()).unwrap()
+}
+
+#[tokio::test]
+async fn upload_session_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new()
+      .event("import_text", import_text)
+      .event("import_first_chunk", import_first_chunk)],
+  ));
+  let send = |request: AFPluginRequest| {
+    let dispatch = dispatch.clone();
+    async move { AFPluginDispatcher::async_send(dispatch.as_ref(), request).await }
+  };
+
+  LocalSet::new()
+    .run_until(async {
+      let mut import = AFPluginRequest::new("import_text");
+      import.id = "import_1".to_owned();
+      let resp = send(AFPluginRequest::new(SysEvent::UploadOpen).payload("import_1")).await;
+      assert_eq!(resp.status_code, StatusCode::Ok);
+      let handler = tokio::task::spawn_local(send(import));
+
+      // More chunks than the session buffers, each one waits for the handler to read.
+      for word in ["a ", "large ", "file ", "sent ", "in ", "chunks"] {
+        let chunk = encode_chunk("import_1", word.as_bytes());
+        let resp = send(AFPluginRequest::new(SysEvent::UploadChunk).payload(chunk)).await;
+        assert_eq!(resp.status_code, StatusCode::Ok);
+      }
+      let resp = send(AFPluginRequest::new(SysEvent::UploadClose).payload("import_1")).await;
+      assert_eq!(String::from_utf8_lossy(resp.payload.as_ref()), "true");
+
+      let resp = handler.await.unwrap();
+      assert_eq!(resp.status_code, StatusCode::Ok);
+      assert_eq!(
+        String::from_utf8_lossy(resp.payload.as_ref()),
+        "a large file sent in chunks"
+      );
+
+      // The chunks sent once the handler dropped its stream fail.
+      let mut import = AFPluginRequest::new("import_first_chunk");
+      import.id = "import_2".to_owned();
+      send(AFPluginRequest::new(SysEvent::UploadOpen).payload("import_2")).await;
+      let handler = tokio::task::spawn_local(send(import));
+      let chunk = encode_chunk("import_2", b"header");
+      send(AFPluginRequest::new(SysEvent::UploadChunk).payload(chunk)).await;
+      let resp = handler.await.unwrap();
+      assert_eq!(String::from_utf8_lossy(resp.payload.as_ref()), "header");
+      let chunk = encode_chunk("import_2", b"rows");
+      let resp = send(AFPluginRequest::new(SysEvent::UploadChunk).payload(chunk)).await;
+      assert_eq!(resp.status_code, StatusCode::Err);
+
+      // A request without an upload session can't extract the stream.
+      let resp = send(AFPluginRequest::new("import_text")).await;
+      assert_eq!(resp.status_code, StatusCode::Err);
+    })
+    .await;
+  std::mem::forget(dispatch);
+}
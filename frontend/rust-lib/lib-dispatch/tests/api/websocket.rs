@@ -0,0 +1,265 @@
+use bytes::Bytes;
+use futures_util::{SinkExt, StreamExt};
+use lib_dispatch::bridge::{WebSocketBridge, MAX_IN_FLIGHT_REQUESTS};
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::broadcast;
+use tokio::task::LocalSet;
+use tokio_tungstenite::tungstenite::Message;
+
+async fn hello(name: String) -> String {
+  format!("hello {}", name)
+}
+
+#[derive(Default)]
+struct Concurrency {
+  current: AtomicUsize,
+  max: AtomicUsize,
+}
+
+async fn slow(concurrency: AFPluginState<Arc<Concurrency>>) -> String {
+  let current = concurrency.current.fetch_add(1, Ordering::SeqCst) + 1;
+  concurrency.max.fetch_max(current, Ordering::SeqCst);
+  tokio::time::sleep(Duration::from_millis(10)).await;
+  concurrency.current.fetch_sub(1, Ordering::SeqCst);
+  "done".to_string()
+}
+
+struct TestSessions;
+
+impl SessionStore for TestSessions {
+  fn verify(&self, token: &str) -> Option<Identity> {
+    (token == "nathan-token").then(|| Identity {
+      user_id: "nathan".to_string(),
+      capabilities: None,
+    })
+  }
+}
+
+fn request_frame(id: u32, event: &str, payload: &[u8]) -> Vec<u8> {
+  let mut frame = id.to_be_bytes().to_vec();
+  frame.extend_from_slice(&(event.len() as u16).to_be_bytes());
+  frame.extend_from_slice(event.as_bytes());
+  frame.extend_from_slice(payload);
+  frame
+}
+
+#[tokio::test]
+async fn websocket_bridge_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new().event("hello", hello)],
+  ));
+  let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+  let addr = listener.local_addr().unwrap();
+  let (notifications, _) = broadcast::channel(8);
+  let bridge = WebSocketBridge::new(addr, "secret").with_notifications(notifications.clone());
+
+  let local_set = LocalSet::new();
+  local_set.spawn_local(bridge.serve_listener(listener, dispatch.clone()));
+  local_set
+    .run_until(async move {
+      let url = format!("ws://{}", addr);
+      // A client that doesn't authenticate is disconnected.
+      let (mut ws, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+      ws.send(Message::Text("wrong".to_owned())).await.unwrap();
+      assert!(matches!(
+        ws.next().await,
+        Some(Ok(Message::Close(_))) | None
+      ));
+
+      let (mut ws, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+      ws.send(Message::Text("secret".to_owned())).await.unwrap();
+      ws.send(Message::Binary(request_frame(7, "hello", b"world")))
+        .await
+        .unwrap();
+      let frame = match ws.next().await {
+        Some(Ok(Message::Binary(frame))) => frame,
+        other => panic!("unexpected message: {:?}", other),
+      };
+      assert_eq!(frame[0], 0);
+      assert_eq!(
+        u32::from_be_bytes([frame[1], frame[2], frame[3], frame[4]]),
+        7
+      );
+      assert_eq!(frame[5], 0);
+      assert_eq!(&frame[6..], b"hello world");
+
+      notifications.send(Bytes::from_static(b"changed")).unwrap();
+      let frame = match ws.next().await {
+        Some(Ok(Message::Binary(frame))) => frame,
+        other => panic!("unexpected message: {:?}", other),
+      };
+      assert_eq!(frame[0], 1);
+      assert_eq!(&frame[1..], b"changed");
+    })
+    .await;
+
+  std::mem::forget(dispatch);
+}
+
+#[tokio::test]
+async fn websocket_bridge_auth_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(runtime, vec![AFPlugin::new().event("hello", hello)])
+      .with_middleware(AuthMiddleware::new(Arc::new(TestSessions))),
+  );
+  let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+  let addr = listener.local_addr().unwrap();
+  let bridge = WebSocketBridge::new(addr, "secret");
+
+  let local_set = LocalSet::new();
+  local_set.spawn_local(bridge.serve_listener(listener, dispatch.clone()));
+  local_set
+    .run_until(async move {
+      let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr))
+        .await
+        .unwrap();
+      ws.send(Message::Text("secret".to_owned())).await.unwrap();
+
+      // The connection token doesn't authenticate the requests.
+      ws.send(Message::Binary(request_frame(1, "hello", b"world")))
+        .await
+        .unwrap();
+      let frame = match ws.next().await {
+        Some(Ok(Message::Binary(frame))) => frame,
+        other => panic!("unexpected message: {:?}", other),
+      };
+      assert_eq!(frame[5], 1);
+      let unauthorized = serde_json::from_slice::<Unauthorized>(&frame[6..]).unwrap();
+      assert_eq!(unauthorized.reason, "missing token");
+
+      let token = b"nathan-token";
+      let mut frame = 2u32.to_be_bytes().to_vec();
+      frame.extend_from_slice(&(5u16 | 0x2000).to_be_bytes());
+      frame.extend_from_slice(b"hello");
+      frame.extend_from_slice(&(token.len() as u16).to_be_bytes());
+      frame.extend_from_slice(token);
+      frame.extend_from_slice(b"world");
+      ws.send(Message::Binary(frame)).await.unwrap();
+      let frame = match ws.next().await {
+        Some(Ok(Message::Binary(frame))) => frame,
+        other => panic!("unexpected message: {:?}", other),
+      };
+      assert_eq!(frame[5], 0);
+      assert_eq!(&frame[6..], b"hello world");
+    })
+    .await;
+
+  std::mem::forget(dispatch);
+}
+
+#[cfg(feature = "request_signing")]
+#[tokio::test]
+async fn websocket_bridge_signature_test() {
+  use lib_dispatch::security::{KeyRing, RequestSigner};
+
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  let signer = RequestSigner::hmac("desktop", b"shared secret");
+  let keys = Arc::new(KeyRing::new());
+  keys.add_hmac_key("desktop", b"shared secret");
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(
+    AFPluginDispatcher::new(runtime, vec![AFPlugin::new().event("hello", hello)])
+      .with_middleware(SignatureMiddleware::new(keys)),
+  );
+  let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+  let addr = listener.local_addr().unwrap();
+  let bridge = WebSocketBridge::new(addr, "secret");
+
+  let local_set = LocalSet::new();
+  local_set.spawn_local(bridge.serve_listener(listener, dispatch.clone()));
+  local_set
+    .run_until(async move {
+      let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr))
+        .await
+        .unwrap();
+      ws.send(Message::Text("secret".to_owned())).await.unwrap();
+
+      // The connection token doesn't replace the signature.
+      ws.send(Message::Binary(request_frame(1, "hello", b"world")))
+        .await
+        .unwrap();
+      let frame = match ws.next().await {
+        Some(Ok(Message::Binary(frame))) => frame,
+        other => panic!("unexpected message: {:?}", other),
+      };
+      assert_eq!(frame[5], 1);
+      let unauthorized = serde_json::from_slice::<Unauthorized>(&frame[6..]).unwrap();
+      assert_eq!(unauthorized.reason, "missing signature");
+
+      let signed = signer.sign(AFPluginRequest::new("hello").payload("world"));
+      let signature = signed.metadata[SIGNATURE_METADATA].as_bytes();
+      let mut frame = 2u32.to_be_bytes().to_vec();
+      frame.extend_from_slice(&(5u16 | 0x4000).to_be_bytes());
+      frame.extend_from_slice(b"hello");
+      frame.extend_from_slice(&(signature.len() as u16).to_be_bytes());
+      frame.extend_from_slice(signature);
+      frame.extend_from_slice(b"world");
+      ws.send(Message::Binary(frame)).await.unwrap();
+      let frame = match ws.next().await {
+        Some(Ok(Message::Binary(frame))) => frame,
+        other => panic!("unexpected message: {:?}", other),
+      };
+      assert_eq!(frame[5], 0);
+      assert_eq!(&frame[6..], b"hello world");
+    })
+    .await;
+
+  std::mem::forget(dispatch);
+}
+
+#[tokio::test]
+async fn websocket_bridge_in_flight_test() {
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  let concurrency = Arc::new(Concurrency::default());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime,
+    vec![AFPlugin::new()
+      .state(concurrency.clone())
+      .event("slow", slow)],
+  ));
+  let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+  let addr = listener.local_addr().unwrap();
+  let bridge = WebSocketBridge::new(addr, "secret");
+
+  let local_set = LocalSet::new();
+  local_set.spawn_local(bridge.serve_listener(listener, dispatch.clone()));
+  local_set
+    .run_until(async move {
+      let url = format!("ws://{}", addr);
+      let (ws, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+      let (mut sink, mut stream) = ws.split();
+      sink.send(Message::Text("secret".to_owned())).await.unwrap();
+      let count = MAX_IN_FLIGHT_REQUESTS * 2;
+      let write = async {
+        for id in 0..count {
+          let frame = request_frame(id as u32, "slow", b"");
+          sink.send(Message::Binary(frame)).await.unwrap();
+        }
+      };
+      let read = async {
+        for _ in 0..count {
+          match stream.next().await {
+            Some(Ok(Message::Binary(frame))) => assert_eq!(&frame[6..], b"done"),
+            other => panic!("unexpected message: {:?}", other),
+          }
+        }
+      };
+      tokio::join!(write, read);
+    })
+    .await;
+
+  // The messages past the limit waited for a request to be answered.
+  assert!(concurrency.max.load(Ordering::SeqCst) <= MAX_IN_FLIGHT_REQUESTS);
+  std::mem::forget(dispatch);
+}
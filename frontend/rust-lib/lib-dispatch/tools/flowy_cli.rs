@@ -0,0 +1,122 @@
+//! Sends events to a running app, instead of clicking through the UI to trigger them. The app
+//! must serve its dispatcher on a Unix domain socket, see
+//! `lib_dispatch::bridge::serve_unix_socket`: the debug builds do when `APPFLOWY_CLI_SOCKET` is
+//! set.
+//!
+//! cargo run -p lib-dispatch --bin flowy-cli -- --socket /tmp/appflowy.sock events
+//! cargo run -p lib-dispatch --bin flowy-cli -- --socket /tmp/appflowy.sock send <event> [json]
+//! cargo run -p lib-dispatch --bin flowy-cli -- --socket /tmp/appflowy.sock dart <file>
+
+const USAGE: &str = "Usage:
+  flowy-cli --socket <path> [--token <token>] events
+  flowy-cli --socket <path> [--token <token>] send <event> [json]
+  flowy-cli --socket <path> [--token <token>] dart <file>
+
+The socket and the session token can also be set with the FLOWY_SOCKET and FLOWY_TOKEN
+environment variables.";
+
+#[cfg(unix)]
+use lib_dispatch::cli::{format_payload, CliError, EventClient};
+#[cfg(unix)]
+use lib_dispatch::codegen::DartCodegen;
+#[cfg(unix)]
+use lib_dispatch::prelude::StatusCode;
+#[cfg(unix)]
+use lib_dispatch::proxy::RemoteResponse;
+#[cfg(unix)]
+use lib_dispatch::system::SYSTEM_PLUGIN_NAME;
+
+#[cfg(unix)]
+fn main() {
+  let mut args = std::env::args().skip(1).collect::<Vec<_>>();
+  let socket = match take_option(&mut args, "--socket", "FLOWY_SOCKET") {
+    Some(socket) => socket,
+    None => exit_with_usage(),
+  };
+  let mut client = EventClient::new(socket);
+  if let Some(token) = take_option(&mut args, "--token", "FLOWY_TOKEN") {
+    client = client.with_token(token);
+  }
+  let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+
+  let runtime = tokio::runtime::Builder::new_current_thread()
+    .enable_all()
+    .build()
+    .unwrap();
+  let result = runtime.block_on(async {
+    match args.as_slice() {
+      ["events"] => {
+        for schema in client.events().await? {
+          println!(
+            "{:<40} {} -> {}",
+            schema.event, schema.payload, schema.response
+          );
+        }
+        Ok(true)
+      },
+      ["send", event] => print_response(client.send(event, None).await?),
+      ["send", event, payload] => print_response(client.send(event, Some(*payload)).await?),
+      ["dart", file] => {
+        let mut schemas = client.events().await?;
+        schemas.retain(|schema| schema.plugin != SYSTEM_PLUGIN_NAME);
+        match DartCodegen::new(schemas).write_to(file) {
+          Ok(true) => println!("Wrote {}", file),
+          Ok(false) => println!("{} is up to date", file),
+          Err(err) => {
+            eprintln!("Can not write {}: {}", file, err);
+            return Ok(false);
+          },
+        }
+        Ok(true)
+      },
+      _ => exit_with_usage(),
+    }
+  });
+
+  match result {
+    Ok(true) => {},
+    Ok(false) => std::process::exit(1),
+    Err(err) => {
+      eprintln!("{}", err);
+      std::process::exit(2);
+    },
+  }
+}
+
+/// Removes the option `name` and its value from `args`, falling back to the environment
+/// variable `var`.
+#[cfg(unix)]
+fn take_option(args: &mut Vec<String>, name: &str, var: &str) -> Option<String> {
+  match args.iter().position(|arg| arg == name) {
+    Some(index) if index + 1 >= args.len() => exit_with_usage(),
+    Some(index) => {
+      let value = args.remove(index + 1);
+      args.remove(index);
+      Some(value)
+    },
+    None => std::env::var(var).ok(),
+  }
+}
+
+/// Prints the status and the payload of `response`. Returns whether it's a success.
+#[cfg(unix)]
+fn print_response(response: RemoteResponse) -> Result<bool, CliError> {
+  println!("{:?}", response.status_code);
+  let payload = format_payload(&response.payload);
+  if !payload.is_empty() {
+    println!("{}", payload);
+  }
+  Ok(response.status_code == StatusCode::Ok)
+}
+
+#[cfg(not(unix))]
+fn main() {
+  eprintln!("flowy-cli needs a Unix domain socket.\n\n{}", USAGE);
+  std::process::exit(2);
+}
+
+#[cfg(unix)]
+fn exit_with_usage() -> ! {
+  eprintln!("{}", USAGE);
+  std::process::exit(2);
+}
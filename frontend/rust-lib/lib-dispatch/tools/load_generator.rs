@@ -0,0 +1,116 @@
+//! Keeps the dispatcher busy with a mix of events and prints the throughput and the latency
+//! percentiles of each event. See [LoadGenerator].
+//!
+//! cargo run --release -p lib-dispatch --features load_generator --bin load_generator -- \
+//!   --mix empty:3,echo:1 --payload 1024 --concurrency 64 --rate 5000 --seconds 10 --seed 7
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use lib_dispatch::load::LoadGenerator;
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+use tokio::task::LocalSet;
+
+async fn empty() {}
+
+async fn echo(data: String) -> String {
+  data
+}
+
+async fn fail() -> Result<(), DispatchError> {
+  Err(DispatchError::from("fail".to_string()))
+}
+
+struct Options {
+  mix: Vec<(String, u32)>,
+  payload_size: usize,
+  concurrency: usize,
+  rate: Option<f64>,
+  duration: Duration,
+  seed: u64,
+}
+
+impl Options {
+  fn from_args() -> Self {
+    let mut options = Options {
+      mix: vec![("empty".to_owned(), 1)],
+      payload_size: 0,
+      concurrency: 32,
+      rate: None,
+      duration: Duration::from_secs(5),
+      seed: 0,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+      let value = args
+        .next()
+        .unwrap_or_else(|| panic!("{} expects a value", arg));
+      let number = || {
+        value
+          .parse::<u64>()
+          .unwrap_or_else(|_| panic!("{} expects a number", arg))
+      };
+      match arg.as_str() {
+        "--mix" => options.mix = parse_mix(&value),
+        "--payload" => options.payload_size = number() as usize,
+        "--concurrency" => options.concurrency = number() as usize,
+        "--rate" => options.rate = Some(number() as f64),
+        "--seconds" => options.duration = Duration::from_secs(number()),
+        "--seed" => options.seed = number(),
+        _ => panic!("Unknown option: {}", arg),
+      }
+    }
+    options
+  }
+}
+
+/// Parses `event:weight` pairs separated by commas. The weight is 1 when omitted.
+fn parse_mix(value: &str) -> Vec<(String, u32)> {
+  value
+    .split(',')
+    .map(|entry| match entry.split_once(':') {
+      Some((event, weight)) => (
+        event.to_owned(),
+        weight
+          .parse()
+          .unwrap_or_else(|_| panic!("Invalid weight: {}", entry)),
+      ),
+      None => (entry.to_owned(), 1),
+    })
+    .collect()
+}
+
+fn main() {
+  let options = Options::from_args();
+  let runtime = Arc::new(AFPluginRuntime::new().unwrap());
+  #[allow(clippy::arc_with_non_send_sync)]
+  let dispatch = Arc::new(AFPluginDispatcher::new(
+    runtime.clone(),
+    vec![AFPlugin::new()
+      .name("load")
+      .event("empty", empty)
+      .event("echo", echo)
+      .event("fail", fail)],
+  ));
+
+  let payload = "x".repeat(options.payload_size);
+  let mut generator = LoadGenerator::new()
+    .concurrency(options.concurrency)
+    .duration(options.duration)
+    .seed(options.seed);
+  if let Some(rate) = options.rate {
+    generator = generator.rate(rate);
+  }
+  for (event, weight) in options.mix {
+    let payload = payload.clone();
+    generator = generator.request(weight, move || {
+      AFPluginRequest::new(event.as_str()).payload(payload.as_str())
+    });
+  }
+
+  let local_set = LocalSet::new();
+  let report = runtime.block_on(local_set.run_until(generator.run(dispatch.as_ref())));
+  print!("{}", report);
+  std::mem::forget(dispatch);
+}
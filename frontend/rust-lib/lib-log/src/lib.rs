@@ -11,16 +11,37 @@ use tracing_appender::{non_blocking::WorkerGuard, rolling::RollingFileAppender};
 use tracing_bunyan_formatter::JsonStorageLayer;
 use tracing_subscriber::fmt::format::Writer;
 use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::reload::Handle;
 use tracing_subscriber::{layer::SubscriberExt, EnvFilter};
 
 use crate::layer::FlowyFormattingLayer;
 use crate::stream_log::{StreamLog, StreamLogSender};
 
 mod layer;
+mod otlp;
 pub mod stream_log;
 
+pub use otlp::OtlpConfig;
+
+type ReloadFilter = Box<dyn Fn(EnvFilter) -> Result<(), String> + Send + Sync>;
+
 lazy_static! {
   static ref LOG_GUARD: RwLock<Option<WorkerGuard>> = RwLock::new(None);
+  static ref LOG_FILTER: RwLock<Option<ReloadFilter>> = RwLock::new(None);
+}
+
+/// Replaces the filter the logs were built with, see [Builder::env_filter], without a restart.
+/// Fails if the directives are invalid or the logs were not built.
+pub fn reload_env_filter(env_filter: &str) -> Result<(), String> {
+  let env_filter = EnvFilter::try_new(env_filter).map_err(|e| e.to_string())?;
+  match LOG_FILTER.read().unwrap().as_ref() {
+    Some(reload) => reload(env_filter),
+    None => Err("The logs are not initialized".to_owned()),
+  }
+}
+
+fn reload_filter<S: 'static>(handle: Handle<EnvFilter, S>) -> ReloadFilter {
+  Box::new(move |env_filter| handle.reload(env_filter).map_err(|e| e.to_string()))
 }
 
 pub struct Builder {
@@ -31,6 +52,7 @@ pub struct Builder {
   #[allow(dead_code)]
   platform: OperatingSystem,
   stream_log_sender: Option<Arc<dyn StreamLogSender>>,
+  otlp: Option<OtlpConfig>,
 }
 
 impl Builder {
@@ -53,6 +75,7 @@ impl Builder {
       file_appender,
       platform: platform.clone(),
       stream_log_sender,
+      otlp: None,
     }
   }
 
@@ -61,13 +84,21 @@ impl Builder {
     self
   }
 
-  pub fn build(self) -> Result<(), String> {
+  /// Export the spans to an OpenTelemetry collector. It's ignored unless the `otlp` feature
+  /// is enabled.
+  pub fn otlp(mut self, config: OtlpConfig) -> Self {
+    self.otlp = Some(config);
+    self
+  }
+
+  pub fn build(mut self) -> Result<(), String> {
+    let otlp = self.otlp.take();
     let env_filter = EnvFilter::new(self.env_filter);
     let (non_blocking, guard) = tracing_appender::non_blocking(self.file_appender);
     let file_layer = FlowyFormattingLayer::new(non_blocking);
 
-    if let Some(stream_log_sender) = &self.stream_log_sender {
-      let subscriber = tracing_subscriber::fmt()
+    let reload = if let Some(stream_log_sender) = &self.stream_log_sender {
+      let builder = tracing_subscriber::fmt()
         .with_timer(CustomTime)
         .with_max_level(tracing::Level::TRACE)
         .with_ansi(self.platform.is_not_ios())
@@ -77,26 +108,34 @@ impl Builder {
         .with_thread_ids(false)
         .pretty()
         .with_env_filter(env_filter)
-        .finish()
-        .with(JsonStorageLayer)
-        .with(file_layer);
+        .with_filter_reloading();
+      let reload = reload_filter(builder.reload_handle());
+      let subscriber = builder.finish().with(JsonStorageLayer).with(file_layer);
+      let subscriber = subscriber.with(otlp::otlp_layer(otlp.as_ref())?);
       set_global_default(subscriber).map_err(|e| format!("{:?}", e))?;
+      reload
     } else {
-      let subscriber = tracing_subscriber::fmt()
+      let builder = tracing_subscriber::fmt()
         .with_timer(CustomTime)
         .with_max_level(tracing::Level::TRACE)
         .with_ansi(true)
         .with_thread_ids(false)
         .pretty()
         .with_env_filter(env_filter)
+        .with_filter_reloading();
+      let reload = reload_filter(builder.reload_handle());
+      let subscriber = builder
         .finish()
         .with(FlowyFormattingLayer::new(DebugStdoutWriter))
         .with(JsonStorageLayer)
         .with(file_layer);
+      let subscriber = subscriber.with(otlp::otlp_layer(otlp.as_ref())?);
       set_global_default(subscriber).map_err(|e| format!("{:?}", e))?;
+      reload
     };
 
     *LOG_GUARD.write().unwrap() = Some(guard);
+    *LOG_FILTER.write().unwrap() = Some(reload);
     Ok(())
   }
 }
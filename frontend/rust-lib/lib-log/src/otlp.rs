@@ -0,0 +1,98 @@
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// Where and how the spans are exported with the OpenTelemetry protocol. The spans are only
+/// exported when the `otlp` feature is enabled.
+#[derive(Debug, Clone)]
+pub struct OtlpConfig {
+  /// The gRPC endpoint of the collector, e.g. `http://localhost:4317`.
+  pub endpoint: String,
+  pub service_name: String,
+  /// Extra resource attributes attached to every span, like the app version or the platform.
+  pub resource_attributes: Vec<(String, String)>,
+  /// Only the spans whose target starts with one of these prefixes are exported. All the spans
+  /// are exported when it's empty.
+  pub targets: Vec<String>,
+}
+
+impl OtlpConfig {
+  pub fn new(endpoint: &str, service_name: &str) -> Self {
+    Self {
+      endpoint: endpoint.to_owned(),
+      service_name: service_name.to_owned(),
+      resource_attributes: vec![],
+      targets: vec![],
+    }
+  }
+
+  pub fn resource_attribute(mut self, key: &str, value: &str) -> Self {
+    self
+      .resource_attributes
+      .push((key.to_owned(), value.to_owned()));
+    self
+  }
+
+  pub fn target(mut self, target: &str) -> Self {
+    self.targets.push(target.to_owned());
+    self
+  }
+
+  #[allow(dead_code)]
+  fn accept(&self, target: &str) -> bool {
+    self.targets.is_empty() || self.targets.iter().any(|t| target.starts_with(t.as_str()))
+  }
+}
+
+pub(crate) type BoxLayer<S> = Box<dyn Layer<S> + Send + Sync>;
+
+/// Builds the layer that sends the spans to the collector in batches. It must be called within
+/// a tokio runtime.
+#[cfg(feature = "otlp")]
+pub(crate) fn otlp_layer<S>(config: Option<&OtlpConfig>) -> Result<Option<BoxLayer<S>>, String>
+where
+  S: tracing::Subscriber + for<'span> LookupSpan<'span> + Send + Sync,
+{
+  use opentelemetry::KeyValue;
+  use opentelemetry_otlp::WithExportConfig;
+  use opentelemetry_sdk::trace::Config;
+  use opentelemetry_sdk::Resource;
+
+  let config = match config {
+    None => return Ok(None),
+    Some(config) => config.clone(),
+  };
+  let mut attributes = vec![KeyValue::new("service.name", config.service_name.clone())];
+  attributes.extend(
+    config
+      .resource_attributes
+      .iter()
+      .map(|(key, value)| KeyValue::new(key.clone(), value.clone())),
+  );
+
+  let exporter = opentelemetry_otlp::new_exporter()
+    .tonic()
+    .with_endpoint(config.endpoint.clone());
+  let tracer = opentelemetry_otlp::new_pipeline()
+    .tracing()
+    .with_exporter(exporter)
+    .with_trace_config(Config::default().with_resource(Resource::new(attributes)))
+    .install_batch(opentelemetry_sdk::runtime::Tokio)
+    .map_err(|e| format!("{:?}", e))?;
+  let layer = tracing_opentelemetry::layer()
+    .with_tracer(tracer)
+    .with_filter(tracing_subscriber::filter::filter_fn(move |metadata| {
+      config.accept(metadata.target())
+    }));
+  Ok(Some(Box::new(layer)))
+}
+
+#[cfg(not(feature = "otlp"))]
+pub(crate) fn otlp_layer<S>(config: Option<&OtlpConfig>) -> Result<Option<BoxLayer<S>>, String>
+where
+  S: tracing::Subscriber + for<'span> LookupSpan<'span> + Send + Sync,
+{
+  if config.is_some() {
+    eprintln!("The spans are not exported, the otlp feature is disabled");
+  }
+  Ok(None)
+}
@@ -0,0 +1,16 @@
+const { EventEmitter } = require("events");
+const native = require("./appflowy_node.node");
+
+/// Emits a "notification" event with the encoded SubscribeObject of every notification.
+class Notifications extends EventEmitter {
+  constructor() {
+    super();
+    native.onNotification((payload) => this.emit("notification", payload));
+  }
+}
+
+module.exports = {
+  init: native.init,
+  dispatch: native.dispatch,
+  Notifications,
+};
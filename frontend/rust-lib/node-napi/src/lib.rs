@@ -0,0 +1,167 @@
+use std::sync::{Arc, RwLock};
+
+use bytes::Bytes;
+use lazy_static::lazy_static;
+use napi::bindgen_prelude::Buffer;
+use napi::threadsafe_function::{
+  ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode,
+};
+use napi::JsFunction;
+use napi_derive::napi;
+use semver::Version;
+use serde::Deserialize;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::LocalSet;
+use tracing::error;
+
+use flowy_core::config::AppFlowyCoreConfig;
+use flowy_core::*;
+use flowy_notification::entities::SubscribeObject;
+use flowy_notification::{register_notification_sender, NotificationSender};
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+
+const DEFAULT_NAME: &str = "appflowy";
+
+lazy_static! {
+  static ref NODE_APPFLOWY_CORE: NodeAppFlowyCore = NodeAppFlowyCore::default();
+}
+
+/// The configuration passed to [init] as JSON.
+#[derive(Deserialize)]
+struct NodeConfiguration {
+  root: String,
+  app_version: String,
+  device_id: String,
+  platform: String,
+}
+
+/// A request waiting to be dispatched on the core's thread, like the tasks of the Dart FFI.
+struct Task {
+  dispatcher: Arc<AFPluginDispatcher>,
+  request: AFPluginRequest,
+  ret: oneshot::Sender<AFPluginEventResponse>,
+}
+
+#[derive(Default)]
+struct NodeAppFlowyCore {
+  core: RwLock<Option<AppFlowyCore>>,
+  sender: RwLock<Option<mpsc::UnboundedSender<Task>>>,
+}
+
+unsafe impl Sync for NodeAppFlowyCore {}
+unsafe impl Send for Task {}
+
+/// The response of [dispatch]. The code is `0` when the handler succeeded, in which case the
+/// payload is the encoded response, and `1` when it failed, in which case the payload is the
+/// encoded error. Same as the `FFIResponse` of the Dart FFI.
+#[napi(object)]
+pub struct DispatchResponse {
+  pub code: u32,
+  pub payload: Buffer,
+}
+
+/// Initializes the core. It must be called once, before any [dispatch].
+#[napi]
+pub fn init(configuration: String) -> napi::Result<()> {
+  let configuration: NodeConfiguration = serde_json::from_str(&configuration)
+    .map_err(|e| napi::Error::from_reason(format!("Invalid configuration: {}", e)))?;
+  let app_version =
+    Version::parse(&configuration.app_version).unwrap_or_else(|_| Version::new(0, 5, 8));
+  let config = AppFlowyCoreConfig::new(
+    app_version,
+    configuration.root.clone(),
+    configuration.root,
+    configuration.device_id,
+    configuration.platform,
+    DEFAULT_NAME.to_string(),
+  );
+
+  let (sender, mut task_rx) = mpsc::unbounded_channel::<Task>();
+  let runtime =
+    Arc::new(AFPluginRuntime::new().map_err(|e| napi::Error::from_reason(e.to_string()))?);
+  let cloned_runtime = runtime.clone();
+  std::thread::spawn(move || {
+    let local_set = LocalSet::new();
+    cloned_runtime.block_on(local_set.run_until(async move {
+      while let Some(Task {
+        dispatcher,
+        request,
+        ret,
+      }) = task_rx.recv().await
+      {
+        tokio::task::spawn_local(async move {
+          let resp = AFPluginDispatcher::async_send(dispatcher.as_ref(), request).await;
+          let _ = ret.send(resp);
+        });
+      }
+    }));
+  });
+
+  *NODE_APPFLOWY_CORE.sender.write().unwrap() = Some(sender);
+  let cloned_runtime = runtime.clone();
+  *NODE_APPFLOWY_CORE.core.write().unwrap() =
+    runtime.block_on(async move { Some(AppFlowyCore::new(config, cloned_runtime, None).await) });
+  Ok(())
+}
+
+/// Dispatches `event` with `payload`, the encoded request, and resolves with its response.
+#[napi]
+pub async fn dispatch(event: String, payload: Buffer) -> napi::Result<DispatchResponse> {
+  let dispatcher = NODE_APPFLOWY_CORE
+    .core
+    .read()
+    .unwrap()
+    .as_ref()
+    .map(|core| core.event_dispatcher.clone())
+    .ok_or_else(|| napi::Error::from_reason("The core is not initialized, call init first"))?;
+  let request = AFPluginRequest::new(event).payload(payload.to_vec());
+
+  let (ret, rx) = oneshot::channel();
+  if let Some(sender) = NODE_APPFLOWY_CORE.sender.read().unwrap().as_ref() {
+    if let Err(e) = sender.send(Task {
+      dispatcher,
+      request,
+      ret,
+    }) {
+      error!("Failed to send task: {}", e);
+    }
+  }
+  let resp = rx
+    .await
+    .map_err(|_| napi::Error::from_reason("The dispatcher dropped the request"))?;
+  let code = match resp.status_code {
+    StatusCode::Ok => 0,
+    StatusCode::Err => 1,
+  };
+  Ok(DispatchResponse {
+    code,
+    payload: resp.payload.as_ref().to_vec().into(),
+  })
+}
+
+/// Calls `callback` with every notification, encoded as a `SubscribeObject`. The `Notifications`
+/// emitter of `index.js` is built on top of it.
+#[napi]
+pub fn on_notification(callback: JsFunction) -> napi::Result<()> {
+  let callback: ThreadsafeFunction<Vec<u8>, ErrorStrategy::Fatal> = callback
+    .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<Vec<u8>>| {
+      Ok(vec![Buffer::from(ctx.value)])
+    })?;
+  register_notification_sender(NodeNotificationSender { callback });
+  Ok(())
+}
+
+struct NodeNotificationSender {
+  callback: ThreadsafeFunction<Vec<u8>, ErrorStrategy::Fatal>,
+}
+
+impl NotificationSender for NodeNotificationSender {
+  fn send_subject(&self, subject: SubscribeObject) -> Result<(), String> {
+    let bytes: Bytes = subject.try_into().map_err(|e| format!("{:?}", e))?;
+    self
+      .callback
+      .call(bytes.to_vec(), ThreadsafeFunctionCallMode::NonBlocking);
+    Ok(())
+  }
+}
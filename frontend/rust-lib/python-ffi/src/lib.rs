@@ -0,0 +1,161 @@
+use std::sync::{Arc, RwLock};
+
+use lazy_static::lazy_static;
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use semver::Version;
+use serde::Deserialize;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::LocalSet;
+use tracing::error;
+
+use flowy_core::config::AppFlowyCoreConfig;
+use flowy_core::*;
+use flowy_error::FlowyError;
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::AFPluginRuntime;
+
+const DEFAULT_NAME: &str = "appflowy";
+
+create_exception!(
+  appflowy,
+  DispatchError,
+  PyException,
+  "An event could not be dispatched."
+);
+create_exception!(
+  appflowy,
+  EventError,
+  DispatchError,
+  "The handler of an event failed. The arguments are the error code, the message and the payload."
+);
+
+lazy_static! {
+  static ref PY_APPFLOWY_CORE: PyAppFlowyCore = PyAppFlowyCore::default();
+}
+
+/// The configuration passed to [init] as JSON.
+#[derive(Deserialize)]
+struct PyConfiguration {
+  root: String,
+  app_version: String,
+  device_id: String,
+  platform: String,
+}
+
+/// A request waiting to be dispatched on the core's thread, like the tasks of the Dart FFI.
+struct Task {
+  dispatcher: Arc<AFPluginDispatcher>,
+  request: AFPluginRequest,
+  ret: oneshot::Sender<AFPluginEventResponse>,
+}
+
+#[derive(Default)]
+struct PyAppFlowyCore {
+  core: RwLock<Option<AppFlowyCore>>,
+  sender: RwLock<Option<mpsc::UnboundedSender<Task>>>,
+}
+
+unsafe impl Sync for PyAppFlowyCore {}
+unsafe impl Send for Task {}
+
+/// Initializes the core. It must be called once, before any [dispatch].
+#[pyfunction]
+fn init(configuration: &str) -> PyResult<()> {
+  let configuration: PyConfiguration = serde_json::from_str(configuration)
+    .map_err(|e| DispatchError::new_err(format!("Invalid configuration: {}", e)))?;
+  let app_version =
+    Version::parse(&configuration.app_version).unwrap_or_else(|_| Version::new(0, 5, 8));
+  let config = AppFlowyCoreConfig::new(
+    app_version,
+    configuration.root.clone(),
+    configuration.root,
+    configuration.device_id,
+    configuration.platform,
+    DEFAULT_NAME.to_string(),
+  );
+
+  let (sender, mut task_rx) = mpsc::unbounded_channel::<Task>();
+  let runtime = Arc::new(AFPluginRuntime::new()?);
+  let cloned_runtime = runtime.clone();
+  std::thread::spawn(move || {
+    let local_set = LocalSet::new();
+    cloned_runtime.block_on(local_set.run_until(async move {
+      while let Some(Task {
+        dispatcher,
+        request,
+        ret,
+      }) = task_rx.recv().await
+      {
+        tokio::task::spawn_local(async move {
+          let resp = AFPluginDispatcher::async_send(dispatcher.as_ref(), request).await;
+          let _ = ret.send(resp);
+        });
+      }
+    }));
+  });
+
+  *PY_APPFLOWY_CORE.sender.write().unwrap() = Some(sender);
+  let cloned_runtime = runtime.clone();
+  *PY_APPFLOWY_CORE.core.write().unwrap() =
+    runtime.block_on(async move { Some(AppFlowyCore::new(config, cloned_runtime, None).await) });
+  Ok(())
+}
+
+/// Dispatches `event` with `payload`, the encoded request, and returns an awaitable that resolves
+/// with the encoded response. A failed handler raises an [EventError].
+#[pyfunction]
+fn dispatch<'py>(py: Python<'py>, event: String, payload: Vec<u8>) -> PyResult<&'py PyAny> {
+  let dispatcher = PY_APPFLOWY_CORE
+    .core
+    .read()
+    .unwrap()
+    .as_ref()
+    .map(|core| core.event_dispatcher.clone())
+    .ok_or_else(|| DispatchError::new_err("The core is not initialized, call init first"))?;
+  let request = AFPluginRequest::new(event).payload(payload);
+
+  let (ret, rx) = oneshot::channel();
+  if let Some(sender) = PY_APPFLOWY_CORE.sender.read().unwrap().as_ref() {
+    if let Err(e) = sender.send(Task {
+      dispatcher,
+      request,
+      ret,
+    }) {
+      error!("Failed to send task: {}", e);
+    }
+  }
+
+  pyo3_asyncio::tokio::future_into_py(py, async move {
+    let resp = rx
+      .await
+      .map_err(|_| DispatchError::new_err("The dispatcher dropped the request"))?;
+    let payload = resp.payload.as_ref().to_vec();
+    match resp.status_code {
+      StatusCode::Ok => Ok(Python::with_gil(|py| {
+        PyBytes::new(py, &payload).to_object(py)
+      })),
+      StatusCode::Err => Err(event_error(payload)),
+    }
+  })
+}
+
+/// Translates the payload of a failed response into an [EventError]. The payloads that aren't a
+/// `FlowyError`, like the errors of the dispatcher itself, raise a [DispatchError].
+fn event_error(payload: Vec<u8>) -> PyErr {
+  match FlowyError::try_from(bytes::Bytes::from(payload.clone())) {
+    Ok(err) => EventError::new_err((err.code.value(), err.msg, err.payload)),
+    Err(_) => DispatchError::new_err(String::from_utf8_lossy(&payload).into_owned()),
+  }
+}
+
+#[pymodule]
+fn appflowy(py: Python<'_>, m: &PyModule) -> PyResult<()> {
+  m.add("DispatchError", py.get_type::<DispatchError>())?;
+  m.add("EventError", py.get_type::<EventError>())?;
+  m.add_function(wrap_pyfunction!(init, m)?)?;
+  m.add_function(wrap_pyfunction!(dispatch, m)?)?;
+  Ok(())
+}